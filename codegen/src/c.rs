@@ -0,0 +1,874 @@
+use std::{
+    collections::HashMap,
+    ffi::{OsStr, OsString},
+    mem,
+    path::Path,
+};
+
+use diagnostics::Span;
+use parser::ast;
+
+use crate::{
+    error::{BuildError, CodegenError},
+    tool,
+};
+
+/// The runtime header, which is copied to the top of every generated file.
+pub const RUNTIME: &str = include_str!("c/runtime.h");
+
+/// Flags which are passed to the C compiler by [`build`].
+pub const CFLAGS: [&str; 2] = ["-std=c99", "-O2"];
+
+const INDENT: &str = "    ";
+
+/// Parameter and return types of a function.
+struct Signature {
+    /// Types of the parameters.
+    params: Vec<ast::Type>,
+    /// Return type. `None` if the function returns nothing.
+    return_type: Option<ast::Type>,
+}
+
+/// A translated expression.
+struct Operand {
+    /// C code of the expression.
+    code: String,
+    /// Type of the expression. `None` if the expression has no value.
+    value_type: Option<ast::Type>,
+    /// Whether evaluating the code later gives the same value and has no
+    /// effects (literals and temporaries).
+    stable: bool,
+}
+
+impl Operand {
+    fn new(code: String, value_type: ast::Type, stable: bool) -> Self {
+        Self {
+            code,
+            value_type: Some(value_type),
+            stable,
+        }
+    }
+}
+
+/// The `Generator` struct translates function definitions of a Kolang
+/// program to C functions, one at a time.
+///
+/// Kolang evaluates operands from left to right, but C does not specify the
+/// order. Expressions which may have effects (calls, assignments and
+/// operations which may fail) are therefore split into statements which
+/// store the operands evaluated before them in temporaries.
+struct Generator {
+    /// Generated code of the function definitions.
+    out: String,
+    /// Defined functions, by name.
+    functions: HashMap<String, Signature>,
+    /// C names and types of the variables of each open scope, by name.
+    scopes: Vec<HashMap<String, (String, ast::Type)>>,
+    /// Number of variables defined with each name in the current function.
+    defined: HashMap<String, usize>,
+    /// Number of temporaries used by the current function.
+    temps: usize,
+    /// Indentation level of the next line.
+    indent: usize,
+}
+
+impl Generator {
+    fn new() -> Self {
+        Self {
+            out: String::new(),
+            functions: HashMap::new(),
+            scopes: Vec::new(),
+            defined: HashMap::new(),
+            temps: 0,
+            indent: 0,
+        }
+    }
+
+    /// Returns the C declaration of a function, without semicolon.
+    /// Starts a new function, whose scope contains the parameters.
+    fn prototype(&mut self, stmt: &ast::Stmt) -> Result<String, CodegenError> {
+        let ast::Stmt::FnDef {
+            id,
+            params,
+            return_type,
+            span,
+            ..
+        } = stmt
+        else {
+            unreachable!("Only functions are translated");
+        };
+
+        self.scopes = vec![HashMap::new()];
+        self.defined.clear();
+        self.temps = 0;
+
+        let return_type = match return_type {
+            Some(t) => c_type(t)?,
+            None => "void",
+        };
+        let params = if params.is_empty() {
+            "void".to_string()
+        } else {
+            let mut list = Vec::new();
+            for (param, t) in params {
+                let name = self.define(param, t.clone(), (span.line, span.column))?;
+                list.push(format!("{} {}", c_type(t)?, name));
+            }
+            list.join(", ")
+        };
+
+        Ok(format!("static {} fn_{}({})", return_type, id, params))
+    }
+
+    /// Translates a function definition.
+    fn function(&mut self, stmt: &ast::Stmt) -> Result<(), CodegenError> {
+        let ast::Stmt::FnDef {
+            id,
+            return_type,
+            body,
+            span,
+            ..
+        } = stmt
+        else {
+            unreachable!("Only functions are translated");
+        };
+
+        let prototype = self.prototype(stmt)?;
+        self.out.push_str(&prototype);
+        self.out.push_str("\n{\n");
+        self.indent = 1;
+        self.line(format!("kl_enter({}, {});", span.line, span.column));
+        self.body(body)?;
+        match return_type {
+            Some(_) => {
+                let message = format!("Function `{}` finished without returning a value", id);
+                self.line(format!(
+                    "kl_fail({}, {}, {});",
+                    span.line,
+                    span.column,
+                    c_string(&message)
+                ));
+            }
+            None => self.line("kl_leave();".into()),
+        }
+        self.out.push_str("}\n");
+        Ok(())
+    }
+
+    /// Translates a statement which is the body of a function, loop or
+    /// conditional, without the surrounding braces.
+    fn body(&mut self, stmt: &ast::Stmt) -> Result<(), CodegenError> {
+        self.scopes.push(HashMap::new());
+        match stmt {
+            ast::Stmt::Block { stmts, .. } => {
+                for s in stmts {
+                    self.stmt(s)?;
+                }
+            }
+            s => self.stmt(s)?,
+        }
+        self.scopes.pop();
+        Ok(())
+    }
+
+    /// Translates a statement which is placed in braces.
+    fn nested(&mut self, stmt: &ast::Stmt) -> Result<(), CodegenError> {
+        self.indent += 1;
+        self.body(stmt)?;
+        self.indent -= 1;
+        Ok(())
+    }
+
+    /// Translates a statement.
+    fn stmt(&mut self, stmt: &ast::Stmt) -> Result<(), CodegenError> {
+        match stmt {
+            ast::Stmt::Let {
+                id,
+                var_type,
+                expr,
+                span,
+            } => {
+                let value = match expr {
+                    Some(e) => self.value(e)?.code,
+                    None => default_value(var_type)?,
+                };
+                let name = self.define(id, var_type.clone(), (span.line, span.column))?;
+                self.line(format!("{} {} = {};", c_type(var_type)?, name, value));
+            }
+            ast::Stmt::Expr { expr, .. } => {
+                let op = self.expr(expr)?;
+                // Assignments are already translated to statements.
+                let assigned = matches!(expr, ast::Expr::Assign { .. });
+                if has_effects(expr) && !assigned && !op.code.is_empty() {
+                    self.line(format!("{};", op.code));
+                }
+            }
+            ast::Stmt::If {
+                cond,
+                then_stmt,
+                else_stmt,
+                ..
+            } => {
+                let cond = self.value(cond)?;
+                self.line(format!("if ({}) {{", cond.code));
+                self.nested(then_stmt)?;
+                if let Some(els) = else_stmt {
+                    self.line("} else {".into());
+                    self.nested(els)?;
+                }
+                self.line("}".into());
+            }
+            ast::Stmt::While { cond, body, .. } => {
+                // The statements which evaluate the condition run in each iteration.
+                self.indent += 1;
+                let (prelude, cond) = self.capture(|g| g.value(cond));
+                self.indent -= 1;
+                let cond = cond?;
+
+                if prelude.is_empty() {
+                    self.line(format!("while ({}) {{", cond.code));
+                } else {
+                    self.line("for (;;) {".into());
+                    self.out.push_str(&prelude);
+                    self.line(format!("{}if (!({})) break;", INDENT, cond.code));
+                }
+                self.nested(body)?;
+                self.line("}".into());
+            }
+            ast::Stmt::For {
+                id,
+                start,
+                end,
+                body,
+                span,
+            } => {
+                let range = self.operands(&[start, end])?;
+                let int = ast::Type::Int { span: *span };
+
+                self.line("{".into());
+                self.indent += 1;
+                self.scopes.push(HashMap::new());
+                let i = self.define(id, int, (span.line, span.column))?;
+                let end = self.temp();
+                self.line(format!("int64_t {} = {};", i, range[0].code));
+                self.line(format!("int64_t {} = {};", end, range[1].code));
+                self.line(format!("while ({} <= {}) {{", i, end));
+                self.nested(body)?;
+                self.line(format!("{}if ({} == INT64_MAX) break;", INDENT, i));
+                self.line(format!("{}{}++;", INDENT, i));
+                self.line("}".into());
+                self.scopes.pop();
+                self.indent -= 1;
+                self.line("}".into());
+            }
+            ast::Stmt::Return { expr, .. } => {
+                let mut v = self.value(expr)?;
+                // The function leaves after the value is evaluated.
+                self.stabilize(&mut v)?;
+                self.line("kl_leave();".into());
+                self.line(format!("return {};", v.code));
+            }
+            ast::Stmt::Block { .. } => {
+                self.line("{".into());
+                self.nested(stmt)?;
+                self.line("}".into());
+            }
+            ast::Stmt::FnDef { span, .. } => {
+                return Err(CodegenError::InvalidCode {
+                    line: span.line,
+                    column: span.column,
+                })
+            }
+            ast::Stmt::Empty { .. } => {}
+        }
+
+        Ok(())
+    }
+
+    /// Translates an expression. Statements which should run before the
+    /// expression is evaluated are written to the output.
+    fn expr(&mut self, expr: &ast::Expr) -> Result<Operand, CodegenError> {
+        let span = expr.span();
+        let pos = (span.line, span.column);
+
+        let op = match expr {
+            ast::Expr::LiteralInt { value, .. } => {
+                let code = if *value == i64::MIN {
+                    "INT64_MIN".into()
+                } else {
+                    format!("INT64_C({})", value)
+                };
+                Operand::new(code, ast::Type::Int { span }, true)
+            }
+            ast::Expr::LiteralStr { value, .. } => Operand::new(
+                format!("kl_str_of({}, {})", c_string(value), value.len()),
+                ast::Type::Str { span },
+                true,
+            ),
+            ast::Expr::LiteralChar { value, .. } => Operand::new(
+                format!("(kl_char){}", *value as u32),
+                ast::Type::Char { span },
+                true,
+            ),
+            ast::Expr::LiteralFloat { value, .. } => {
+                Operand::new(c_float(*value), ast::Type::Float { span }, true)
+            }
+            ast::Expr::LiteralBool { value, .. } => {
+                Operand::new(value.to_string(), ast::Type::Bool { span }, true)
+            }
+            ast::Expr::LiteralArray { elements, .. } => {
+                let elements: Vec<&ast::Expr> = elements.iter().collect();
+                let values = self.operands(&elements)?;
+                let element_type = match values.first().and_then(|v| v.value_type.clone()) {
+                    Some(ast::Type::Array { .. }) => {
+                        return Err(CodegenError::NestedArray {
+                            line: pos.0,
+                            column: pos.1,
+                        })
+                    }
+                    Some(t) => t,
+                    None => ast::Type::Error { span },
+                };
+
+                let code = if values.is_empty() {
+                    "kl_array_of(0, NULL)".to_string()
+                } else {
+                    let member = member(&element_type);
+                    let values: Vec<String> = values
+                        .iter()
+                        .map(|v| format!("{{.{} = {}}}", member, v.code))
+                        .collect();
+                    format!(
+                        "kl_array_of({}, (kl_value[]){{{}}})",
+                        values.len(),
+                        values.join(", ")
+                    )
+                };
+                let t = ast::Type::Array {
+                    element_type: Box::new(element_type),
+                    span,
+                };
+                Operand::new(code, t, false)
+            }
+            ast::Expr::BinaryOp { l, op, r, .. } => match op {
+                ast::BinOp::LogAnd { .. } | ast::BinOp::LogOr { .. } => self.logical(l, op, r)?,
+                _ => {
+                    let values = self.operands(&[l, r])?;
+                    binary(op, &values[0], &values[1], span)
+                }
+            },
+            ast::Expr::UnaryOp { op, expr, .. } => {
+                let v = self.value(expr)?;
+                let t = v.value_type.clone().unwrap_or(ast::Type::Error { span });
+                let code = match (op, &t) {
+                    (ast::UnOp::Neg { .. }, ast::Type::Int { .. }) => format!("kl_neg({})", v.code),
+                    (ast::UnOp::Neg { .. }, _) => format!("(-{})", v.code),
+                    (ast::UnOp::LogNot { .. }, _) => format!("(!{})", v.code),
+                    (ast::UnOp::BitNot { .. }, _) => format!("(~{})", v.code),
+                };
+                Operand::new(code, t, false)
+            }
+            ast::Expr::Identifier { id, .. } => {
+                let (name, t) = self.lookup(id, pos)?;
+                Operand::new(name, t, false)
+            }
+            ast::Expr::Call { id, args, .. } => {
+                let args: Vec<&ast::Expr> = args.iter().collect();
+                if id == "println" && !self.functions.contains_key(id) {
+                    self.println(&args)?;
+                    return Ok(Operand {
+                        code: String::new(),
+                        value_type: None,
+                        stable: true,
+                    });
+                }
+
+                let (params, return_type) = match self.functions.get(id) {
+                    Some(f) => (f.params.len(), f.return_type.clone()),
+                    None => {
+                        return Err(CodegenError::UndefinedFunction {
+                            id: id.clone(),
+                            line: pos.0,
+                            column: pos.1,
+                        })
+                    }
+                };
+                if params != args.len() {
+                    return Err(CodegenError::WrongArgumentCount {
+                        function: id.clone(),
+                        expected: params,
+                        found: args.len(),
+                        line: pos.0,
+                        column: pos.1,
+                    });
+                }
+
+                let values = self.operands(&args)?;
+                let values: Vec<String> = values.into_iter().map(|v| v.code).collect();
+                Operand {
+                    code: format!("fn_{}({})", id, values.join(", ")),
+                    value_type: return_type,
+                    stable: false,
+                }
+            }
+            ast::Expr::ArrayExpr { id, index, .. } => {
+                let index = self.value(index)?;
+                let (name, t) = self.lookup(id, pos)?;
+                let ast::Type::Array { element_type, .. } = t else {
+                    return Err(CodegenError::InvalidCode {
+                        line: pos.0,
+                        column: pos.1,
+                    });
+                };
+                let code = format!(
+                    "kl_index({}, {}, {}, {}).{}",
+                    name,
+                    index.code,
+                    pos.0,
+                    pos.1,
+                    member(&element_type)
+                );
+                Operand::new(code, *element_type, false)
+            }
+            ast::Expr::Assign { id, expr, .. } => {
+                let v = self.value(expr)?;
+                let (name, t) = self.lookup(id, pos)?;
+                self.line(format!("{} = {};", name, v.code));
+                Operand::new(name, t, false)
+            }
+            ast::Expr::Error { .. } => {
+                return Err(CodegenError::InvalidCode {
+                    line: pos.0,
+                    column: pos.1,
+                })
+            }
+        };
+
+        Ok(op)
+    }
+
+    /// Translates an expression which should have a value.
+    fn value(&mut self, expr: &ast::Expr) -> Result<Operand, CodegenError> {
+        let op = self.expr(expr)?;
+        match op.value_type {
+            Some(ast::Type::Error { .. }) | None => {
+                let span = expr.span();
+                Err(CodegenError::InvalidCode {
+                    line: span.line,
+                    column: span.column,
+                })
+            }
+            Some(_) => Ok(op),
+        }
+    }
+
+    /// Translates expressions which are evaluated from left to right.
+    /// Operands are stored in temporaries if a later expression may have effects.
+    fn operands(&mut self, exprs: &[&ast::Expr]) -> Result<Vec<Operand>, CodegenError> {
+        let mut values: Vec<Operand> = Vec::with_capacity(exprs.len());
+        for e in exprs {
+            if has_effects(e) {
+                for v in &mut values {
+                    self.stabilize(v)?;
+                }
+            }
+            values.push(self.value(e)?);
+        }
+        Ok(values)
+    }
+
+    /// Translates a short-circuiting logical operation.
+    fn logical(
+        &mut self,
+        l: &ast::Expr,
+        op: &ast::BinOp,
+        r: &ast::Expr,
+    ) -> Result<Operand, CodegenError> {
+        let span = op.span();
+        let and = matches!(op, ast::BinOp::LogAnd { .. });
+        let l = self.value(l)?;
+
+        if !has_effects(r) {
+            let r = self.value(r)?;
+            let c_op = if and { "&&" } else { "||" };
+            let code = format!("({} {} {})", l.code, c_op, r.code);
+            return Ok(Operand::new(code, ast::Type::Bool { span }, false));
+        }
+
+        // The statements of the right operand only run if it is evaluated.
+        let result = self.temp();
+        self.line(format!("bool {} = {};", result, l.code));
+        let not = if and { "" } else { "!" };
+        self.line(format!("if ({}{}) {{", not, result));
+        self.indent += 1;
+        let r = self.value(r)?;
+        self.line(format!("{} = {};", result, r.code));
+        self.indent -= 1;
+        self.line("}".into());
+
+        Ok(Operand::new(result, ast::Type::Bool { span }, true))
+    }
+
+    /// Translates a call of the `println` builtin function.
+    fn println(&mut self, args: &[&ast::Expr]) -> Result<(), CodegenError> {
+        let mut values = self.operands(args)?;
+        // All arguments are evaluated before printing.
+        for (v, arg) in values.iter_mut().zip(args) {
+            if has_effects(arg) {
+                self.stabilize(v)?;
+            }
+        }
+
+        for v in values {
+            let line = match v.value_type {
+                Some(ast::Type::Int { .. }) => format!("kl_print_int({});", v.code),
+                Some(ast::Type::Float { .. }) => format!("kl_print_float({});", v.code),
+                Some(ast::Type::Char { .. }) => format!("kl_print_char({});", v.code),
+                Some(ast::Type::Str { .. }) => format!("kl_print_str({});", v.code),
+                Some(ast::Type::Bool { .. }) => format!("kl_print_bool({});", v.code),
+                Some(ast::Type::Array { element_type, .. }) => {
+                    format!("kl_print_array({}, {});", v.code, kind(&element_type))
+                }
+                Some(ast::Type::Error { .. }) | None => unreachable!("Operands have values"),
+            };
+            self.line(line);
+        }
+        self.line("kl_println();".into());
+        Ok(())
+    }
+
+    /// Stores the value of an operand in a temporary, unless it is stable.
+    fn stabilize(&mut self, v: &mut Operand) -> Result<(), CodegenError> {
+        if v.stable {
+            return Ok(());
+        }
+
+        let t = match &v.value_type {
+            Some(t) => c_type(t)?,
+            None => unreachable!("Operands have values"),
+        };
+        let temp = self.temp();
+        self.line(format!("{} {} = {};", t, temp, v.code));
+        v.code = temp;
+        v.stable = true;
+        Ok(())
+    }
+
+    /// Runs `f` and returns the code it wrote, without writing it to the output.
+    fn capture<T>(&mut self, f: impl FnOnce(&mut Self) -> T) -> (String, T) {
+        let out = mem::take(&mut self.out);
+        let result = f(self);
+        (mem::replace(&mut self.out, out), result)
+    }
+
+    /// Defines a variable in the current scope and returns its C name.
+    /// Variables with the same name get different C names, because C
+    /// variables are visible in their own initializer.
+    fn define(
+        &mut self,
+        id: &str,
+        t: ast::Type,
+        pos: (usize, usize),
+    ) -> Result<String, CodegenError> {
+        c_type(&t).map_err(|_| CodegenError::InvalidCode {
+            line: pos.0,
+            column: pos.1,
+        })?;
+
+        let count = self.defined.entry(id.to_string()).or_insert(0);
+        *count += 1;
+        let name = match *count {
+            1 => format!("v_{}", id),
+            n => format!("v{}_{}", n, id),
+        };
+
+        self.scopes
+            .last_mut()
+            .expect("There is an open scope")
+            .insert(id.to_string(), (name.clone(), t));
+        Ok(name)
+    }
+
+    /// Returns the C name and type of a variable.
+    fn lookup(&self, id: &str, pos: (usize, usize)) -> Result<(String, ast::Type), CodegenError> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|s| s.get(id))
+            .cloned()
+            .ok_or_else(|| CodegenError::UndefinedVariable {
+                id: id.to_string(),
+                line: pos.0,
+                column: pos.1,
+            })
+    }
+
+    /// Returns the name of a new temporary.
+    fn temp(&mut self) -> String {
+        self.temps += 1;
+        format!("t{}", self.temps)
+    }
+
+    /// Writes a line of code with the current indentation.
+    fn line(&mut self, code: String) {
+        for _ in 0..self.indent {
+            self.out.push_str(INDENT);
+        }
+        self.out.push_str(&code);
+        self.out.push('\n');
+    }
+}
+
+/// Translates a binary operation (except logical ones, which short-circuit).
+fn binary(op: &ast::BinOp, l: &Operand, r: &Operand, span: Span) -> Operand {
+    use ast::BinOp as B;
+    use ast::Type as T;
+
+    let t = l.value_type.clone().unwrap_or(T::Error { span });
+    let (a, b) = (&l.code, &r.code);
+    let pos = (op.span().line, op.span().column);
+    let int = matches!(t, T::Int { .. });
+
+    let code = match op {
+        B::Add { .. } if int => format!("kl_add({}, {})", a, b),
+        B::Sub { .. } if int => format!("kl_sub({}, {})", a, b),
+        B::Mul { .. } if int => format!("kl_mul({}, {})", a, b),
+        B::Div { .. } if int => format!("kl_div({}, {}, {}, {})", a, b, pos.0, pos.1),
+        B::Mod { .. } if int => format!("kl_mod({}, {}, {}, {})", a, b, pos.0, pos.1),
+        B::Mod { .. } => format!("fmod({}, {})", a, b),
+        B::Eq { .. } | B::NEq { .. } => {
+            let not = if let B::NEq { .. } = op { "!" } else { "" };
+            match &t {
+                T::Str { .. } => format!("{}kl_str_eq({}, {})", not, a, b),
+                T::Array { element_type, .. } => {
+                    format!("{}kl_array_eq({}, {}, {})", not, a, b, kind(element_type))
+                }
+                _ => format!("({} {} {})", a, op, b),
+            }
+        }
+        _ => format!("({} {} {})", a, op, b),
+    };
+
+    let t = match op {
+        B::Eq { .. }
+        | B::NEq { .. }
+        | B::LT { .. }
+        | B::GT { .. }
+        | B::LEq { .. }
+        | B::GEq { .. } => T::Bool { span },
+        _ => t,
+    };
+    Operand::new(code, t, false)
+}
+
+/// Returns the C type of values of a Kolang type.
+fn c_type(t: &ast::Type) -> Result<&'static str, CodegenError> {
+    Ok(match t {
+        ast::Type::Int { .. } => "int64_t",
+        ast::Type::Float { .. } => "double",
+        ast::Type::Char { .. } => "kl_char",
+        ast::Type::Str { .. } => "kl_str",
+        ast::Type::Bool { .. } => "bool",
+        ast::Type::Array { .. } => "kl_array",
+        ast::Type::Error { span } => {
+            return Err(CodegenError::InvalidCode {
+                line: span.line,
+                column: span.column,
+            })
+        }
+    })
+}
+
+/// Returns the C code of the value of variables which are defined
+/// without initial value.
+fn default_value(t: &ast::Type) -> Result<String, CodegenError> {
+    Ok(match t {
+        ast::Type::Int { .. } => "INT64_C(0)".into(),
+        ast::Type::Float { .. } => "0.0".into(),
+        ast::Type::Char { .. } => "(kl_char)0".into(),
+        ast::Type::Str { .. } => "kl_str_of(\"\", 0)".into(),
+        ast::Type::Bool { .. } => "false".into(),
+        ast::Type::Array { .. } => "kl_array_of(0, NULL)".into(),
+        ast::Type::Error { span } => {
+            return Err(CodegenError::InvalidCode {
+                line: span.line,
+                column: span.column,
+            })
+        }
+    })
+}
+
+/// Returns the member of `kl_value` which stores array elements of a type.
+fn member(t: &ast::Type) -> &'static str {
+    match t {
+        ast::Type::Float { .. } => "f",
+        ast::Type::Char { .. } => "c",
+        ast::Type::Str { .. } => "s",
+        ast::Type::Bool { .. } => "b",
+        _ => "i",
+    }
+}
+
+/// Returns the `kl_kind` of array elements of a type.
+fn kind(t: &ast::Type) -> &'static str {
+    match t {
+        ast::Type::Float { .. } => "KL_FLOAT",
+        ast::Type::Char { .. } => "KL_CHAR",
+        ast::Type::Str { .. } => "KL_STR",
+        ast::Type::Bool { .. } => "KL_BOOL",
+        _ => "KL_INT",
+    }
+}
+
+/// Checks whether evaluating an expression may have effects: call functions,
+/// assign variables or fail.
+fn has_effects(expr: &ast::Expr) -> bool {
+    match expr {
+        ast::Expr::Call { .. }
+        | ast::Expr::Assign { .. }
+        | ast::Expr::ArrayExpr { .. }
+        | ast::Expr::Error { .. }
+        | ast::Expr::BinaryOp {
+            op: ast::BinOp::Div { .. } | ast::BinOp::Mod { .. },
+            ..
+        } => true,
+        ast::Expr::BinaryOp { l, r, .. } => has_effects(l) || has_effects(r),
+        ast::Expr::UnaryOp { expr, .. } => has_effects(expr),
+        ast::Expr::LiteralArray { elements, .. } => elements.iter().any(has_effects),
+        ast::Expr::LiteralInt { .. }
+        | ast::Expr::LiteralStr { .. }
+        | ast::Expr::LiteralChar { .. }
+        | ast::Expr::LiteralFloat { .. }
+        | ast::Expr::LiteralBool { .. }
+        | ast::Expr::Identifier { .. } => false,
+    }
+}
+
+/// Returns a C string literal of UTF-8 bytes. Bytes other than printable
+/// ASCII characters are written as octal escapes, which (unlike hexadecimal
+/// escapes) cannot be continued by the next character.
+fn c_string(s: &str) -> String {
+    let mut literal = String::with_capacity(s.len() + 2);
+    literal.push('"');
+    for b in s.bytes() {
+        match b {
+            // `?` could start a trigraph.
+            b'"' | b'\\' | b'?' => literal.push_str(&format!("\\{:03o}", b)),
+            b' '..=b'~' => literal.push(b as char),
+            _ => literal.push_str(&format!("\\{:03o}", b)),
+        }
+    }
+    literal.push('"');
+    literal
+}
+
+/// Returns a C literal of a float, which reads back as the same value.
+fn c_float(value: f64) -> String {
+    if value.is_nan() {
+        "NAN".into()
+    } else if value.is_infinite() {
+        if value > 0.0 {
+            "HUGE_VAL"
+        } else {
+            "(-HUGE_VAL)"
+        }
+        .into()
+    } else {
+        format!("{:?}", value)
+    }
+}
+
+/// Translates a type-checked Kolang program to a C99 program, which runs its
+/// `main` function. The runtime is included in the generated code, so it can
+/// be built with a C compiler alone. `source_name` is the name of the Kolang
+/// source file, which is mentioned in runtime errors.
+///
+/// The functions which are translated are selected by [`ast::definitions`].
+///
+/// # Errors
+/// Returns the first error found in the code. Code which passes the semantic
+/// checks only fails if it has no `main` function or uses nested arrays.
+///
+/// # Examples
+///
+/// ```
+/// use lexer::Lexer;
+/// use parser::Parser;
+///
+/// let source = "fn main() { println(1 + 2); }".as_bytes();
+/// let (ast, _) = Parser::new(Lexer::new(source)).parse().unwrap();
+///
+/// let c = codegen::c::generate(&ast, "main.kol").unwrap();
+/// assert!(c.contains("static void fn_main(void)"));
+/// assert!(c.contains("kl_print_int(kl_add(INT64_C(1), INT64_C(2)));"));
+/// ```
+pub fn generate(prog: &[ast::Stmt], source_name: &str) -> Result<String, CodegenError> {
+    let mut g = Generator::new();
+
+    let functions = ast::definitions(prog);
+    for stmt in &functions {
+        if let ast::Stmt::FnDef {
+            id,
+            params,
+            return_type,
+            ..
+        } = stmt
+        {
+            let signature = Signature {
+                params: params.iter().map(|(_, t)| t.clone()).collect(),
+                return_type: return_type.clone(),
+            };
+            g.functions.insert(id.clone(), signature);
+        }
+    }
+
+    let main = functions
+        .iter()
+        .find(|f| matches!(f, ast::Stmt::FnDef { id, .. } if id == "main"));
+    match main {
+        Some(ast::Stmt::FnDef { params, span, .. }) if !params.is_empty() => {
+            return Err(CodegenError::WrongArgumentCount {
+                function: "main".into(),
+                expected: params.len(),
+                found: 0,
+                line: span.line,
+                column: span.column,
+            })
+        }
+        Some(_) => {}
+        None => return Err(CodegenError::NoMain),
+    }
+
+    let mut prototypes = String::new();
+    for f in &functions {
+        prototypes.push_str(&g.prototype(f)?);
+        prototypes.push_str(";\n");
+    }
+    for f in &functions {
+        g.out.push('\n');
+        g.function(f)?;
+    }
+
+    Ok(format!(
+        "/* Generated by kolang from {name}. */\n\n#define KL_SOURCE {name}\n\n{}\n{}{}\nint main(void)\n{{\n{INDENT}fn_main();\n{INDENT}return 0;\n}}\n",
+        RUNTIME,
+        prototypes,
+        g.out,
+        name = c_string(source_name),
+    ))
+}
+
+/// Builds an executable from C code with the system C compiler, which is
+/// the `CC` environment variable or `cc`. The code is compiled with
+/// [`CFLAGS`] and linked with the math library.
+///
+/// # Errors
+/// Returns [`BuildError::ToolNotFound`] if the compiler cannot be run,
+/// and [`BuildError::ToolFailed`] if it rejects the code.
+pub fn build(code: &str, output: &Path) -> Result<(), BuildError> {
+    let compiler = std::env::var_os("CC").unwrap_or_else(|| OsString::from("cc"));
+    let mut args: Vec<&OsStr> = CFLAGS.iter().map(OsStr::new).collect();
+    args.extend([OsStr::new("-o"), output.as_os_str()]);
+    args.extend(["-x", "c", "-", "-x", "none", "-lm"].map(OsStr::new));
+    tool::run(&compiler, &args, code)
+}
@@ -0,0 +1,343 @@
+/*
+ * Kolang runtime for programs compiled to C.
+ *
+ * The code generator copies this header to the top of every generated file,
+ * so the output can be built with a C99 compiler alone: `cc -std=c99 f.c -lm`.
+ * Define KL_SOURCE to the name of the Kolang source file before this header
+ * to mention it in runtime errors.
+ *
+ * Values behave like in the interpreter: integer arithmetic wraps around,
+ * arrays are bounds-checked and runtime errors exit with status 3.
+ * Arrays are immutable and never freed.
+ */
+
+#include <inttypes.h>
+#include <math.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+#ifndef KL_SOURCE
+#define KL_SOURCE "<unknown>"
+#endif
+
+#if defined(__GNUC__)
+#define KL_NORETURN __attribute__((noreturn))
+#else
+#define KL_NORETURN
+#endif
+
+/* Maximum number of nested function calls, like in the interpreter. */
+#define KL_MAX_CALL_DEPTH 10000
+
+/* Exit status of a program which failed at runtime. */
+#define KL_EXIT_RUNTIME 3
+
+/* `char` value: a Unicode scalar value. */
+typedef uint32_t kl_char;
+
+/* `str` value: UTF-8 bytes, which are not null-terminated. */
+typedef struct {
+    const char *data;
+    size_t len;
+} kl_str;
+
+/* Element of an array. */
+typedef union {
+    int64_t i;
+    double f;
+    kl_char c;
+    kl_str s;
+    bool b;
+} kl_value;
+
+/* Type of array elements. */
+typedef enum { KL_INT, KL_FLOAT, KL_CHAR, KL_STR, KL_BOOL } kl_kind;
+
+/* Array value. */
+typedef struct {
+    const kl_value *data;
+    int64_t len;
+} kl_array;
+
+/* Number of running functions. */
+static long kl_depth;
+
+/* Reports a runtime error at a position of the Kolang code and exits. */
+static KL_NORETURN void kl_fail(long line, long column, const char *message)
+{
+    fflush(stdout);
+    fprintf(stderr, "%s:%ld:%ld: Runtime error: %s\n", KL_SOURCE, line, column, message);
+    exit(KL_EXIT_RUNTIME);
+}
+
+/* Enters a function defined at provided position. */
+static inline void kl_enter(long line, long column)
+{
+    if (kl_depth >= KL_MAX_CALL_DEPTH) {
+        kl_fail(line, column, "Stack overflow");
+    }
+    kl_depth++;
+}
+
+/* Leaves the running function. */
+static inline void kl_leave(void)
+{
+    kl_depth--;
+}
+
+static inline int64_t kl_add(int64_t a, int64_t b)
+{
+    return (int64_t)((uint64_t)a + (uint64_t)b);
+}
+
+static inline int64_t kl_sub(int64_t a, int64_t b)
+{
+    return (int64_t)((uint64_t)a - (uint64_t)b);
+}
+
+static inline int64_t kl_mul(int64_t a, int64_t b)
+{
+    return (int64_t)((uint64_t)a * (uint64_t)b);
+}
+
+static inline int64_t kl_neg(int64_t a)
+{
+    return (int64_t)(0 - (uint64_t)a);
+}
+
+static inline int64_t kl_div(int64_t a, int64_t b, long line, long column)
+{
+    if (b == 0) {
+        kl_fail(line, column, "Division by zero");
+    }
+    return b == -1 ? kl_neg(a) : a / b;
+}
+
+static inline int64_t kl_mod(int64_t a, int64_t b, long line, long column)
+{
+    if (b == 0) {
+        kl_fail(line, column, "Division by zero");
+    }
+    return b == -1 ? 0 : a % b;
+}
+
+static inline kl_str kl_str_of(const char *data, size_t len)
+{
+    kl_str s;
+    s.data = data;
+    s.len = len;
+    return s;
+}
+
+static inline bool kl_str_eq(kl_str a, kl_str b)
+{
+    return a.len == b.len && memcmp(a.data, b.data, a.len) == 0;
+}
+
+/* Creates an array with a copy of `len` elements. */
+static inline kl_array kl_array_of(int64_t len, const kl_value *elements)
+{
+    kl_array a;
+    kl_value *data = NULL;
+
+    if (len > 0) {
+        data = malloc((size_t)len * sizeof *data);
+        if (data == NULL) {
+            fflush(stdout);
+            fputs("Runtime error: Out of memory\n", stderr);
+            exit(KL_EXIT_RUNTIME);
+        }
+        memcpy(data, elements, (size_t)len * sizeof *data);
+    }
+    a.data = data;
+    a.len = len;
+    return a;
+}
+
+static inline kl_value kl_index(kl_array a, int64_t index, long line, long column)
+{
+    if (index < 0 || index >= a.len) {
+        char message[96];
+        sprintf(message, "Index out of bounds: the length is %" PRId64 " but the index is %" PRId64,
+                a.len, index);
+        kl_fail(line, column, message);
+    }
+    return a.data[index];
+}
+
+static inline bool kl_value_eq(kl_value a, kl_value b, kl_kind kind)
+{
+    switch (kind) {
+    case KL_INT:
+        return a.i == b.i;
+    case KL_FLOAT:
+        return a.f == b.f;
+    case KL_CHAR:
+        return a.c == b.c;
+    case KL_STR:
+        return kl_str_eq(a.s, b.s);
+    default:
+        return a.b == b.b;
+    }
+}
+
+static inline bool kl_array_eq(kl_array a, kl_array b, kl_kind kind)
+{
+    int64_t i;
+
+    if (a.len != b.len) {
+        return false;
+    }
+    for (i = 0; i < a.len; i++) {
+        if (!kl_value_eq(a.data[i], b.data[i], kind)) {
+            return false;
+        }
+    }
+    return true;
+}
+
+static inline void kl_print_int(int64_t n)
+{
+    printf("%" PRId64, n);
+}
+
+/* Prints the shortest decimal which reads back as the same float,
+   without an exponent (like Rust does). */
+static inline void kl_print_float(double x)
+{
+    char buf[32];
+    char digits[20];
+    int precision, exponent, point, n = 0, i;
+
+    if (x != x) {
+        fputs("NaN", stdout);
+        return;
+    }
+    if (signbit(x)) {
+        putchar('-');
+        x = -x;
+    }
+    if (isinf(x)) {
+        fputs("inf", stdout);
+        return;
+    }
+    if (x == 0) {
+        putchar('0');
+        return;
+    }
+
+    /* 17 significant digits are always enough. */
+    for (precision = 1;; precision++) {
+        sprintf(buf, "%.*e", precision - 1, x);
+        if (precision == 17 || strtod(buf, NULL) == x) {
+            break;
+        }
+    }
+
+    /* buf is d[.ddd]e[+-]xx */
+    for (i = 0; buf[i] != 'e'; i++) {
+        if (buf[i] != '.') {
+            digits[n++] = buf[i];
+        }
+    }
+    exponent = atoi(buf + i + 1);
+    point = exponent + 1;
+
+    if (point <= 0) {
+        fputs("0.", stdout);
+        for (i = point; i < 0; i++) {
+            putchar('0');
+        }
+        fwrite(digits, 1, (size_t)n, stdout);
+    } else if (point >= n) {
+        fwrite(digits, 1, (size_t)n, stdout);
+        for (i = n; i < point; i++) {
+            putchar('0');
+        }
+    } else {
+        fwrite(digits, 1, (size_t)point, stdout);
+        putchar('.');
+        fwrite(digits + point, 1, (size_t)(n - point), stdout);
+    }
+}
+
+/* Prints a character encoded in UTF-8. */
+static inline void kl_print_char(kl_char c)
+{
+    unsigned char buf[4];
+    size_t n;
+
+    if (c < 0x80) {
+        buf[0] = (unsigned char)c;
+        n = 1;
+    } else if (c < 0x800) {
+        buf[0] = (unsigned char)(0xC0 | (c >> 6));
+        buf[1] = (unsigned char)(0x80 | (c & 0x3F));
+        n = 2;
+    } else if (c < 0x10000) {
+        buf[0] = (unsigned char)(0xE0 | (c >> 12));
+        buf[1] = (unsigned char)(0x80 | ((c >> 6) & 0x3F));
+        buf[2] = (unsigned char)(0x80 | (c & 0x3F));
+        n = 3;
+    } else {
+        buf[0] = (unsigned char)(0xF0 | (c >> 18));
+        buf[1] = (unsigned char)(0x80 | ((c >> 12) & 0x3F));
+        buf[2] = (unsigned char)(0x80 | ((c >> 6) & 0x3F));
+        buf[3] = (unsigned char)(0x80 | (c & 0x3F));
+        n = 4;
+    }
+    fwrite(buf, 1, n, stdout);
+}
+
+static inline void kl_print_str(kl_str s)
+{
+    fwrite(s.data, 1, s.len, stdout);
+}
+
+static inline void kl_print_bool(bool b)
+{
+    fputs(b ? "true" : "false", stdout);
+}
+
+static inline void kl_print_value(kl_value v, kl_kind kind)
+{
+    switch (kind) {
+    case KL_INT:
+        kl_print_int(v.i);
+        break;
+    case KL_FLOAT:
+        kl_print_float(v.f);
+        break;
+    case KL_CHAR:
+        kl_print_char(v.c);
+        break;
+    case KL_STR:
+        kl_print_str(v.s);
+        break;
+    default:
+        kl_print_bool(v.b);
+        break;
+    }
+}
+
+static inline void kl_print_array(kl_array a, kl_kind kind)
+{
+    int64_t i;
+
+    putchar('[');
+    for (i = 0; i < a.len; i++) {
+        if (i != 0) {
+            fputs(", ", stdout);
+        }
+        kl_print_value(a.data[i], kind);
+    }
+    putchar(']');
+}
+
+static inline void kl_println(void)
+{
+    putchar('\n');
+}
@@ -0,0 +1,174 @@
+use std::{error, fmt, io, process::ExitStatus};
+
+/// Errors which may occur while generating code.
+/// Code which passes the semantic checks only fails if it uses
+/// a feature which the generator does not support.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CodegenError {
+    /// Variable is used before definition.
+    UndefinedVariable {
+        /// Name of the variable.
+        id: String,
+        /// Line of code where the variable is used.
+        line: usize,
+        /// Column of code where the variable is used.
+        column: usize,
+    },
+    /// Called function is not defined.
+    UndefinedFunction {
+        /// Name of the function.
+        id: String,
+        /// Line of code where the function is called.
+        line: usize,
+        /// Column of code where the function is called.
+        column: usize,
+    },
+    /// Function is called with wrong number of arguments.
+    WrongArgumentCount {
+        /// Name of the function.
+        function: String,
+        /// Number of function parameters.
+        expected: usize,
+        /// Number of provided arguments.
+        found: usize,
+        /// Line of code where the function is called.
+        line: usize,
+        /// Column of code where the function is called.
+        column: usize,
+    },
+    /// Array literal contains arrays.
+    NestedArray {
+        /// Line of code where the array literal is.
+        line: usize,
+        /// Column of code where the array literal is.
+        column: usize,
+    },
+    /// Code uses values of a type which the generator does not support.
+    UnsupportedType {
+        /// Name of the type.
+        name: String,
+        /// Line of code where the value is used.
+        line: usize,
+        /// Column of code where the value is used.
+        column: usize,
+    },
+    /// Code contains a syntax or type error, so it cannot be translated.
+    InvalidCode {
+        /// Line of code where the error is.
+        line: usize,
+        /// Column of code where the error is.
+        column: usize,
+    },
+    /// There is no `main` function to start the program.
+    NoMain,
+}
+
+impl CodegenError {
+    /// Returns line and column of code where the error happened,
+    /// if the error is related to a position in code.
+    pub fn position(&self) -> Option<(usize, usize)> {
+        match self {
+            CodegenError::UndefinedVariable { line, column, .. }
+            | CodegenError::UndefinedFunction { line, column, .. }
+            | CodegenError::WrongArgumentCount { line, column, .. }
+            | CodegenError::NestedArray { line, column }
+            | CodegenError::UnsupportedType { line, column, .. }
+            | CodegenError::InvalidCode { line, column } => Some((*line, *column)),
+            CodegenError::NoMain => None,
+        }
+    }
+}
+
+impl fmt::Display for CodegenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CodegenError::UndefinedVariable { id, .. } => {
+                write!(f, "Variable `{id}` is not defined")
+            }
+            CodegenError::UndefinedFunction { id, .. } => {
+                write!(f, "Function `{id}` is not defined")
+            }
+            CodegenError::WrongArgumentCount {
+                function,
+                expected,
+                found,
+                ..
+            } => {
+                let s = if *expected == 1 { "" } else { "s" };
+                let verb = if *found == 1 { "was" } else { "were" };
+                write!(
+                    f,
+                    "Function `{function}` takes {expected} argument{s} but {found} {verb} supplied"
+                )
+            }
+            CodegenError::NestedArray { .. } => f.write_str("Nested arrays are not supported"),
+            CodegenError::UnsupportedType { name, .. } => {
+                write!(
+                    f,
+                    "Values of type `{name}` are not supported by this code generator"
+                )
+            }
+            CodegenError::InvalidCode { .. } => f.write_str("Code contains errors"),
+            CodegenError::NoMain => f.write_str("`main` function is not defined"),
+        }
+    }
+}
+
+impl error::Error for CodegenError {}
+
+/// Errors which may occur while building an executable from generated code.
+#[derive(Debug)]
+pub enum BuildError {
+    /// A program of the toolchain (compiler, assembler or linker)
+    /// could not be found.
+    ToolNotFound {
+        /// Name of the program.
+        tool: String,
+    },
+    /// A program of the toolchain rejected its input.
+    ToolFailed {
+        /// Name of the program.
+        tool: String,
+        /// Exit status of the program.
+        status: ExitStatus,
+        /// Messages which the program printed.
+        stderr: String,
+    },
+    /// Something went wrong while running the toolchain.
+    Io(io::Error),
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BuildError::ToolNotFound { tool } => write!(f, "Program `{tool}` was not found"),
+            BuildError::ToolFailed {
+                tool,
+                status,
+                stderr,
+            } => {
+                write!(f, "Program `{tool}` failed ({status})")?;
+                if !stderr.is_empty() {
+                    write!(f, ":\n{}", stderr.trim_end())?;
+                }
+                Ok(())
+            }
+            BuildError::Io(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl error::Error for BuildError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            BuildError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for BuildError {
+    fn from(e: io::Error) -> Self {
+        BuildError::Io(e)
+    }
+}
@@ -0,0 +1,19 @@
+#![warn(missing_docs)]
+
+//! # Kolang code generators
+//! Utilities for translating type-checked Kolang code to other languages,
+//! so it can be built to native executables.
+
+/// This module includes the generator of portable C99 code and the driver
+/// of the system C compiler.
+pub mod c;
+/// This module includes the errors which may occur while generating code
+/// and building executables.
+pub mod error;
+mod tool;
+/// This module includes the generator of WebAssembly modules and their
+/// text format printer.
+pub mod wasm;
+/// This module includes the generator of x86-64 assembly for Linux and the
+/// driver of the system assembler and linker.
+pub mod x86_64;
@@ -0,0 +1,44 @@
+use std::{
+    ffi::OsStr,
+    io::{self, Write},
+    process::{Command, Stdio},
+};
+
+use crate::error::BuildError;
+
+/// Runs a program of the toolchain with arguments, writing `input` to its
+/// standard input. Output of the program is discarded, but its error messages
+/// are returned in [`BuildError::ToolFailed`] if it fails.
+pub(crate) fn run(program: &OsStr, args: &[&OsStr], input: &str) -> Result<(), BuildError> {
+    let mut child = match Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+    {
+        Ok(child) => child,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            return Err(BuildError::ToolNotFound {
+                tool: program.to_string_lossy().into_owned(),
+            })
+        }
+        Err(e) => return Err(e.into()),
+    };
+
+    let written = child
+        .stdin
+        .take()
+        .expect("Standard input is piped")
+        .write_all(input.as_bytes());
+    let result = child.wait_with_output()?;
+    if !result.status.success() {
+        return Err(BuildError::ToolFailed {
+            tool: program.to_string_lossy().into_owned(),
+            status: result.status,
+            stderr: String::from_utf8_lossy(&result.stderr).into_owned(),
+        });
+    }
+    written?;
+    Ok(())
+}
@@ -0,0 +1,1428 @@
+use std::{collections::HashMap, mem};
+
+use parser::ast;
+
+use crate::error::CodegenError;
+
+/// This module includes the representation of WebAssembly modules and
+/// their binary format.
+pub mod module;
+mod wat;
+
+use module::{
+    BlockType, Data, Export, ExportKind, FuncType, Function, Global, Import, Instr, Module, ValType,
+};
+
+/// Name of the module which the host functions are imported from.
+pub const HOST_MODULE: &str = "kolang";
+
+/// Maximum number of nested function calls, like in the interpreter.
+const MAX_CALL_DEPTH: i32 = 10_000;
+
+/// Address of the first byte of data. Address 0 is left unused.
+const DATA_START: u32 = 8;
+
+/// Offset of the elements of an array from its address, after its length.
+const ELEMENTS: u32 = 8;
+
+/// Offset of the bytes of a string from its address, after its length.
+const BYTES: u32 = 4;
+
+/// Index of the global which stores the address of free memory.
+const HEAP: u32 = 0;
+
+/// Index of the global which stores the number of running functions.
+const DEPTH: u32 = 1;
+
+/// Kinds of array elements, which are passed to the runtime functions.
+const KIND_INT: i32 = 0;
+const KIND_FLOAT: i32 = 1;
+const KIND_CHAR: i32 = 2;
+const KIND_STR: i32 = 3;
+const KIND_BOOL: i32 = 4;
+
+/// Functions which are imported from the host.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Host {
+    PrintInt,
+    PrintFloat,
+    PrintChar,
+    PrintStr,
+    PrintBool,
+    Println,
+    Fmod,
+    Fail,
+    FailIndex,
+}
+
+impl Host {
+    /// Returns the name which the function is imported by.
+    fn name(self) -> &'static str {
+        match self {
+            Host::PrintInt => "print_int",
+            Host::PrintFloat => "print_float",
+            Host::PrintChar => "print_char",
+            Host::PrintStr => "print_str",
+            Host::PrintBool => "print_bool",
+            Host::Println => "println",
+            Host::Fmod => "fmod",
+            Host::Fail => "fail",
+            Host::FailIndex => "fail_index",
+        }
+    }
+
+    fn signature(self) -> FuncType {
+        use ValType::*;
+
+        let (params, results) = match self {
+            Host::PrintInt => (vec![I64], vec![]),
+            Host::PrintFloat => (vec![F64], vec![]),
+            Host::PrintChar | Host::PrintStr | Host::PrintBool => (vec![I32], vec![]),
+            Host::Println => (vec![], vec![]),
+            Host::Fmod => (vec![F64, F64], vec![F64]),
+            Host::Fail => (vec![I32, I32, I32], vec![]),
+            Host::FailIndex => (vec![I32, I32, I64, I64], vec![]),
+        };
+        FuncType { params, results }
+    }
+}
+
+/// Functions of the runtime, which are added to the module if they are used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Helper {
+    Enter,
+    Alloc,
+    Div,
+    Mod,
+    Index,
+    StrEq,
+    ArrayEq,
+    PrintArray,
+}
+
+impl Helper {
+    fn name(self) -> &'static str {
+        match self {
+            Helper::Enter => "kl_enter",
+            Helper::Alloc => "kl_alloc",
+            Helper::Div => "kl_div",
+            Helper::Mod => "kl_mod",
+            Helper::Index => "kl_index",
+            Helper::StrEq => "kl_str_eq",
+            Helper::ArrayEq => "kl_array_eq",
+            Helper::PrintArray => "kl_print_array",
+        }
+    }
+
+    fn signature(self) -> FuncType {
+        use ValType::*;
+
+        let (params, results) = match self {
+            // Line and column of the function.
+            Helper::Enter => (vec![I32, I32], vec![]),
+            // Size, and the address of the allocated memory.
+            Helper::Alloc => (vec![I32], vec![I32]),
+            // Operands, and line and column of the operation.
+            Helper::Div | Helper::Mod => (vec![I64, I64, I32, I32], vec![I64]),
+            // Index, array, element size, and line and column of the
+            // expression. Returns the address of the element, minus `ELEMENTS`.
+            Helper::Index => (vec![I64, I32, I32, I32, I32], vec![I32]),
+            Helper::StrEq => (vec![I32, I32], vec![I32]),
+            // Arrays and the kind of their elements.
+            Helper::ArrayEq => (vec![I32, I32, I32], vec![I32]),
+            Helper::PrintArray => (vec![I32, I32], vec![]),
+        };
+        FuncType { params, results }
+    }
+}
+
+/// A called function. Indices of functions are only known when all
+/// imported and runtime functions are known, so calls refer to callees
+/// until the module is complete.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Callee {
+    /// Imported function.
+    Host(Host),
+    /// Function of the program, by its position in the program.
+    Function(usize),
+    /// Function of the runtime.
+    Helper(Helper),
+}
+
+/// Parameter and return types of a function.
+struct Signature {
+    /// Position of the function in the program.
+    index: usize,
+    /// Types of the parameters.
+    params: Vec<ast::Type>,
+    /// Return type. `None` if the function returns nothing.
+    return_type: Option<ast::Type>,
+}
+
+/// The `Generator` struct translates function definitions of a Kolang
+/// program to WebAssembly functions, one at a time.
+///
+/// Strings and arrays are stored in the linear memory and referred by
+/// their address. Both start with their length (`i32`). Bytes of strings
+/// come after the length, and elements of arrays after 8 bytes. Arrays are
+/// immutable and allocated from a heap which is never freed.
+struct Generator {
+    /// Function types of the module.
+    types: Vec<FuncType>,
+    /// Called functions. Calls refer to their position in this list.
+    callees: Vec<Callee>,
+    /// Defined functions, by name.
+    functions: HashMap<String, Signature>,
+    /// Data of the linear memory, which starts at `DATA_START`.
+    data: Vec<u8>,
+    /// Addresses of strings in the data.
+    strings: HashMap<Vec<u8>, u32>,
+    /// Number of parameters of the current function.
+    params: usize,
+    /// Types of the locals of the current function.
+    locals: Vec<ValType>,
+    /// Instructions of the current function.
+    body: Vec<Instr>,
+    /// Locals and types of the variables of each open scope, by name.
+    scopes: Vec<HashMap<String, (u32, ast::Type)>>,
+}
+
+impl Generator {
+    fn new() -> Self {
+        Self {
+            types: Vec::new(),
+            callees: Vec::new(),
+            functions: HashMap::new(),
+            data: Vec::new(),
+            strings: HashMap::new(),
+            params: 0,
+            locals: Vec::new(),
+            body: Vec::new(),
+            scopes: Vec::new(),
+        }
+    }
+
+    /// Translates a function definition.
+    fn function(&mut self, stmt: &ast::Stmt) -> Result<Function, CodegenError> {
+        let ast::Stmt::FnDef {
+            id,
+            params,
+            return_type,
+            body,
+            span,
+        } = stmt
+        else {
+            unreachable!("Only functions are translated");
+        };
+
+        let mut scope = HashMap::new();
+        let mut param_types = Vec::with_capacity(params.len());
+        for (i, (param, t)) in params.iter().enumerate() {
+            param_types.push(val_type(t)?);
+            scope.insert(param.clone(), (i as u32, t.clone()));
+        }
+        let results = match return_type {
+            Some(t) => vec![val_type(t)?],
+            None => Vec::new(),
+        };
+        self.start(param_types.len());
+        self.scopes = vec![scope];
+
+        self.emit(Instr::I32Const(span.line as i32));
+        self.emit(Instr::I32Const(span.column as i32));
+        self.call(Callee::Helper(Helper::Enter));
+        self.body(body)?;
+        match return_type {
+            Some(_) => {
+                let message = format!("Function `{}` finished without returning a value", id);
+                self.fail(&message, (span.line, span.column));
+            }
+            None => self.leave(),
+        }
+
+        let t = FuncType {
+            params: param_types,
+            results,
+        };
+        Ok(self.finish(format!("fn_{}", id), t))
+    }
+
+    /// Translates a statement which is the body of a function, loop or
+    /// conditional in a new scope.
+    fn body(&mut self, stmt: &ast::Stmt) -> Result<(), CodegenError> {
+        self.scopes.push(HashMap::new());
+        match stmt {
+            ast::Stmt::Block { stmts, .. } => {
+                for s in stmts {
+                    self.stmt(s)?;
+                }
+            }
+            s => self.stmt(s)?,
+        }
+        self.scopes.pop();
+        Ok(())
+    }
+
+    /// Translates a statement.
+    fn stmt(&mut self, stmt: &ast::Stmt) -> Result<(), CodegenError> {
+        match stmt {
+            ast::Stmt::Let {
+                id, var_type, expr, ..
+            } => {
+                let t = val_type(var_type)?;
+                match expr {
+                    Some(e) => {
+                        self.value(e)?;
+                    }
+                    None => {
+                        let default = match var_type {
+                            ast::Type::Int { .. } => Instr::I64Const(0),
+                            ast::Type::Float { .. } => Instr::F64Const(0.0),
+                            ast::Type::Str { .. } | ast::Type::Array { .. } => {
+                                // The empty string is also an empty array.
+                                Instr::I32Const(self.string(b"") as i32)
+                            }
+                            _ => Instr::I32Const(0),
+                        };
+                        self.emit(default);
+                    }
+                }
+                let local = self.define(id, var_type.clone(), t);
+                self.emit(Instr::LocalSet(local));
+            }
+            ast::Stmt::Expr { expr, .. } => {
+                if self.expr(expr)?.is_some() {
+                    self.emit(Instr::Drop);
+                }
+            }
+            ast::Stmt::If {
+                cond,
+                then_stmt,
+                else_stmt,
+                ..
+            } => {
+                self.value(cond)?;
+                self.emit(Instr::If(BlockType::Empty));
+                self.body(then_stmt)?;
+                if let Some(els) = else_stmt {
+                    self.emit(Instr::Else);
+                    self.body(els)?;
+                }
+                self.emit(Instr::End);
+            }
+            ast::Stmt::While { cond, body, .. } => {
+                self.emit(Instr::Block(BlockType::Empty));
+                self.emit(Instr::Loop(BlockType::Empty));
+                self.value(cond)?;
+                self.emit(Instr::I32Eqz);
+                self.emit(Instr::BrIf(1));
+                self.body(body)?;
+                self.emit(Instr::Br(0));
+                self.emit(Instr::End);
+                self.emit(Instr::End);
+            }
+            ast::Stmt::For {
+                id,
+                start,
+                end,
+                body,
+                span,
+            } => {
+                self.value(start)?;
+                self.value(end)?;
+                let last = self.local(ValType::I64);
+                self.emit(Instr::LocalSet(last));
+
+                self.scopes.push(HashMap::new());
+                let i = self.define(id, ast::Type::Int { span: *span }, ValType::I64);
+                self.emit(Instr::LocalSet(i));
+                self.emit(Instr::Block(BlockType::Empty));
+                self.emit(Instr::Loop(BlockType::Empty));
+                self.emit(Instr::LocalGet(i));
+                self.emit(Instr::LocalGet(last));
+                self.emit(Instr::I64GtS);
+                self.emit(Instr::BrIf(1));
+                self.body(body)?;
+                // The loop ends instead of overflowing.
+                self.emit(Instr::LocalGet(i));
+                self.emit(Instr::I64Const(i64::MAX));
+                self.emit(Instr::I64Eq);
+                self.emit(Instr::BrIf(1));
+                self.emit(Instr::LocalGet(i));
+                self.emit(Instr::I64Const(1));
+                self.emit(Instr::I64Add);
+                self.emit(Instr::LocalSet(i));
+                self.emit(Instr::Br(0));
+                self.emit(Instr::End);
+                self.emit(Instr::End);
+                self.scopes.pop();
+            }
+            ast::Stmt::Return { expr, .. } => {
+                self.value(expr)?;
+                self.leave();
+                self.emit(Instr::Return);
+            }
+            ast::Stmt::Block { .. } => self.body(stmt)?,
+            ast::Stmt::FnDef { span, .. } => {
+                return Err(CodegenError::InvalidCode {
+                    line: span.line,
+                    column: span.column,
+                })
+            }
+            ast::Stmt::Empty { .. } => {}
+        }
+
+        Ok(())
+    }
+
+    /// Translates an expression, whose value is left on the stack.
+    /// Returns the type of the expression, or `None` if it has no value.
+    fn expr(&mut self, expr: &ast::Expr) -> Result<Option<ast::Type>, CodegenError> {
+        let span = expr.span();
+        let pos = (span.line, span.column);
+
+        let t = match expr {
+            ast::Expr::LiteralInt { value, .. } => {
+                self.emit(Instr::I64Const(*value));
+                ast::Type::Int { span }
+            }
+            ast::Expr::LiteralStr { value, .. } => {
+                let address = self.string(value.as_bytes());
+                self.emit(Instr::I32Const(address as i32));
+                ast::Type::Str { span }
+            }
+            ast::Expr::LiteralChar { value, .. } => {
+                self.emit(Instr::I32Const(*value as i32));
+                ast::Type::Char { span }
+            }
+            ast::Expr::LiteralFloat { value, .. } => {
+                self.emit(Instr::F64Const(*value));
+                ast::Type::Float { span }
+            }
+            ast::Expr::LiteralBool { value, .. } => {
+                self.emit(Instr::I32Const(*value as i32));
+                ast::Type::Bool { span }
+            }
+            ast::Expr::LiteralArray { elements, .. } => self.array(elements, span)?,
+            ast::Expr::BinaryOp { l, op, r, .. } => match op {
+                ast::BinOp::LogAnd { .. } | ast::BinOp::LogOr { .. } => {
+                    self.value(l)?;
+                    self.emit(Instr::If(BlockType::Value(ValType::I32)));
+                    if let ast::BinOp::LogAnd { .. } = op {
+                        self.value(r)?;
+                        self.emit(Instr::Else);
+                        self.emit(Instr::I32Const(0));
+                    } else {
+                        self.emit(Instr::I32Const(1));
+                        self.emit(Instr::Else);
+                        self.value(r)?;
+                    }
+                    self.emit(Instr::End);
+                    ast::Type::Bool { span }
+                }
+                _ => {
+                    let t = self.value(l)?;
+                    self.value(r)?;
+                    self.binary(op, t, span)?
+                }
+            },
+            ast::Expr::UnaryOp { op, expr, .. } => {
+                let t = self.value(expr)?;
+                match (op, &t) {
+                    (ast::UnOp::Neg { .. }, ast::Type::Float { .. }) => self.emit(Instr::F64Neg),
+                    (ast::UnOp::Neg { .. }, _) => {
+                        self.emit(Instr::I64Const(-1));
+                        self.emit(Instr::I64Mul);
+                    }
+                    (ast::UnOp::LogNot { .. }, _) => self.emit(Instr::I32Eqz),
+                    (ast::UnOp::BitNot { .. }, _) => {
+                        self.emit(Instr::I64Const(-1));
+                        self.emit(Instr::I64Xor);
+                    }
+                }
+                t
+            }
+            ast::Expr::Identifier { id, .. } => {
+                let (local, t) = self.lookup(id, pos)?;
+                self.emit(Instr::LocalGet(local));
+                t
+            }
+            ast::Expr::Call { id, args, .. } => {
+                if id == "println" && !self.functions.contains_key(id) {
+                    self.println(args)?;
+                    return Ok(None);
+                }
+
+                let (index, params, return_type) = match self.functions.get(id) {
+                    Some(f) => (f.index, f.params.len(), f.return_type.clone()),
+                    None => {
+                        return Err(CodegenError::UndefinedFunction {
+                            id: id.clone(),
+                            line: pos.0,
+                            column: pos.1,
+                        })
+                    }
+                };
+                if params != args.len() {
+                    return Err(CodegenError::WrongArgumentCount {
+                        function: id.clone(),
+                        expected: params,
+                        found: args.len(),
+                        line: pos.0,
+                        column: pos.1,
+                    });
+                }
+
+                for arg in args {
+                    self.value(arg)?;
+                }
+                self.call(Callee::Function(index));
+                return Ok(return_type);
+            }
+            ast::Expr::ArrayExpr { id, index, .. } => {
+                self.value(index)?;
+                let (local, t) = self.lookup(id, pos)?;
+                let ast::Type::Array { element_type, .. } = t else {
+                    return Err(CodegenError::InvalidCode {
+                        line: pos.0,
+                        column: pos.1,
+                    });
+                };
+                let element = val_type(&element_type)?;
+
+                self.emit(Instr::LocalGet(local));
+                self.emit(Instr::I32Const(size(element) as i32));
+                self.emit(Instr::I32Const(pos.0 as i32));
+                self.emit(Instr::I32Const(pos.1 as i32));
+                self.call(Callee::Helper(Helper::Index));
+                self.emit(load(element, ELEMENTS));
+                *element_type
+            }
+            ast::Expr::Assign { id, expr, .. } => {
+                self.value(expr)?;
+                let (local, t) = self.lookup(id, pos)?;
+                self.emit(Instr::LocalTee(local));
+                t
+            }
+            ast::Expr::Error { .. } => {
+                return Err(CodegenError::InvalidCode {
+                    line: pos.0,
+                    column: pos.1,
+                })
+            }
+        };
+
+        Ok(Some(t))
+    }
+
+    /// Translates an expression which should have a value.
+    fn value(&mut self, expr: &ast::Expr) -> Result<ast::Type, CodegenError> {
+        match self.expr(expr)? {
+            Some(ast::Type::Error { .. }) | None => {
+                let span = expr.span();
+                Err(CodegenError::InvalidCode {
+                    line: span.line,
+                    column: span.column,
+                })
+            }
+            Some(t) => Ok(t),
+        }
+    }
+
+    /// Translates an array literal. Elements are evaluated to locals before
+    /// the array is allocated.
+    fn array(
+        &mut self,
+        elements: &[ast::Expr],
+        span: diagnostics::Span,
+    ) -> Result<ast::Type, CodegenError> {
+        let mut values = Vec::with_capacity(elements.len());
+        for e in elements {
+            let t = self.value(e)?;
+            let local = self.local(val_type(&t)?);
+            self.emit(Instr::LocalSet(local));
+            values.push((local, t));
+        }
+
+        let element_type = match values.first() {
+            Some((_, ast::Type::Array { .. })) => {
+                return Err(CodegenError::NestedArray {
+                    line: span.line,
+                    column: span.column,
+                })
+            }
+            Some((_, t)) => t.clone(),
+            None => ast::Type::Error { span },
+        };
+        let array_type = ast::Type::Array {
+            element_type: Box::new(element_type.clone()),
+            span,
+        };
+        if values.is_empty() {
+            let empty = self.string(b"");
+            self.emit(Instr::I32Const(empty as i32));
+            return Ok(array_type);
+        }
+
+        let element = val_type(&element_type)?;
+        let size = size(element);
+        let array = self.local(ValType::I32);
+        self.emit(Instr::I32Const(
+            (ELEMENTS + size * values.len() as u32) as i32,
+        ));
+        self.call(Callee::Helper(Helper::Alloc));
+        self.emit(Instr::LocalTee(array));
+        self.emit(Instr::I32Const(values.len() as i32));
+        self.emit(Instr::I32Store(0));
+        for (i, (local, _)) in values.iter().enumerate() {
+            self.emit(Instr::LocalGet(array));
+            self.emit(Instr::LocalGet(*local));
+            self.emit(store(element, ELEMENTS + size * i as u32));
+        }
+        self.emit(Instr::LocalGet(array));
+
+        Ok(array_type)
+    }
+
+    /// Translates a binary operation (except logical ones, which short-circuit)
+    /// of two values of type `t` on the stack.
+    fn binary(
+        &mut self,
+        op: &ast::BinOp,
+        t: ast::Type,
+        span: diagnostics::Span,
+    ) -> Result<ast::Type, CodegenError> {
+        use ast::BinOp as B;
+        use ast::Type as T;
+
+        let pos = (op.span().line, op.span().column);
+        let bool_type = T::Bool { span };
+        let instr = match (op, &t) {
+            (B::Add { .. }, T::Int { .. }) => Instr::I64Add,
+            (B::Sub { .. }, T::Int { .. }) => Instr::I64Sub,
+            (B::Mul { .. }, T::Int { .. }) => Instr::I64Mul,
+            (B::Div { .. } | B::Mod { .. }, T::Int { .. }) => {
+                self.emit(Instr::I32Const(pos.0 as i32));
+                self.emit(Instr::I32Const(pos.1 as i32));
+                let helper = if let B::Div { .. } = op {
+                    Helper::Div
+                } else {
+                    Helper::Mod
+                };
+                self.call(Callee::Helper(helper));
+                return Ok(t);
+            }
+            (B::BitAnd { .. }, T::Int { .. }) => Instr::I64And,
+            (B::BitOr { .. }, T::Int { .. }) => Instr::I64Or,
+            (B::Add { .. }, T::Float { .. }) => Instr::F64Add,
+            (B::Sub { .. }, T::Float { .. }) => Instr::F64Sub,
+            (B::Mul { .. }, T::Float { .. }) => Instr::F64Mul,
+            (B::Div { .. }, T::Float { .. }) => Instr::F64Div,
+            (B::Mod { .. }, T::Float { .. }) => {
+                self.call(Callee::Host(Host::Fmod));
+                return Ok(t);
+            }
+            (B::BitAnd { .. }, T::Bool { .. }) => Instr::I32And,
+            (B::BitOr { .. }, T::Bool { .. }) => Instr::I32Or,
+            (B::Eq { .. } | B::NEq { .. }, T::Str { .. } | T::Array { .. }) => {
+                match &t {
+                    T::Array { element_type, .. } => {
+                        self.emit(Instr::I32Const(kind(element_type, pos)?));
+                        self.call(Callee::Helper(Helper::ArrayEq));
+                    }
+                    _ => self.call(Callee::Helper(Helper::StrEq)),
+                }
+                if let B::NEq { .. } = op {
+                    self.emit(Instr::I32Eqz);
+                }
+                return Ok(bool_type);
+            }
+            (
+                B::Eq { .. }
+                | B::NEq { .. }
+                | B::LT { .. }
+                | B::GT { .. }
+                | B::LEq { .. }
+                | B::GEq { .. },
+                _,
+            ) => {
+                let instr = comparison(op, &t).ok_or(CodegenError::InvalidCode {
+                    line: pos.0,
+                    column: pos.1,
+                })?;
+                self.emit(instr);
+                return Ok(bool_type);
+            }
+            _ => {
+                return Err(CodegenError::InvalidCode {
+                    line: pos.0,
+                    column: pos.1,
+                })
+            }
+        };
+
+        self.emit(instr);
+        Ok(t)
+    }
+
+    /// Translates a call of the `println` builtin function. All arguments
+    /// (except literals) are evaluated to locals before printing.
+    fn println(&mut self, args: &[ast::Expr]) -> Result<(), CodegenError> {
+        let mut values = Vec::with_capacity(args.len());
+        for arg in args {
+            if is_literal(arg) {
+                values.push(None);
+                continue;
+            }
+            let t = self.value(arg)?;
+            let local = self.local(val_type(&t)?);
+            self.emit(Instr::LocalSet(local));
+            values.push(Some((local, t)));
+        }
+
+        for (arg, value) in args.iter().zip(values) {
+            let t = match value {
+                Some((local, t)) => {
+                    self.emit(Instr::LocalGet(local));
+                    t
+                }
+                None => self.value(arg)?,
+            };
+            let pos = (arg.span().line, arg.span().column);
+            let host = match t {
+                ast::Type::Int { .. } => Host::PrintInt,
+                ast::Type::Float { .. } => Host::PrintFloat,
+                ast::Type::Char { .. } => Host::PrintChar,
+                ast::Type::Str { .. } => Host::PrintStr,
+                ast::Type::Bool { .. } => Host::PrintBool,
+                ast::Type::Array { element_type, .. } => {
+                    self.emit(Instr::I32Const(kind(&element_type, pos)?));
+                    self.call(Callee::Helper(Helper::PrintArray));
+                    continue;
+                }
+                ast::Type::Error { .. } => unreachable!("Operands have values"),
+            };
+            self.call(Callee::Host(host));
+        }
+        self.call(Callee::Host(Host::Println));
+        Ok(())
+    }
+
+    /// Translates a function of the runtime.
+    fn helper(&mut self, helper: Helper) -> Function {
+        use Instr as I;
+
+        let t = helper.signature();
+        self.start(t.params.len());
+
+        match helper {
+            Helper::Enter => {
+                self.emit(I::GlobalGet(DEPTH));
+                self.emit(I::I32Const(MAX_CALL_DEPTH));
+                self.emit(I::I32GeU);
+                self.emit(I::If(BlockType::Empty));
+                self.fail_at_params("Stack overflow", 0);
+                self.emit(I::End);
+                self.emit(I::GlobalGet(DEPTH));
+                self.emit(I::I32Const(1));
+                self.emit(I::I32Add);
+                self.emit(I::GlobalSet(DEPTH));
+            }
+            Helper::Alloc => {
+                let address = self.local(ValType::I32);
+                self.emit(I::GlobalGet(HEAP));
+                self.emit(I::LocalSet(address));
+                // Allocated memory is aligned to 8 bytes.
+                self.emit(I::GlobalGet(HEAP));
+                self.emit(I::LocalGet(0));
+                self.emit(I::I32Add);
+                self.emit(I::I32Const(7));
+                self.emit(I::I32Add);
+                self.emit(I::I32Const(-8));
+                self.emit(I::I32And);
+                self.emit(I::GlobalSet(HEAP));
+
+                self.emit(I::Block(BlockType::Empty));
+                self.emit(I::GlobalGet(HEAP));
+                self.emit(I::MemorySize);
+                self.emit(I::I32Const(16));
+                self.emit(I::I32Shl);
+                self.emit(I::I32LeU);
+                self.emit(I::BrIf(0));
+                // Pages which are needed, minus pages of the memory.
+                self.emit(I::GlobalGet(HEAP));
+                self.emit(I::I32Const(0xFFFF));
+                self.emit(I::I32Add);
+                self.emit(I::I32Const(16));
+                self.emit(I::I32ShrU);
+                self.emit(I::MemorySize);
+                self.emit(I::I32Sub);
+                self.emit(I::MemoryGrow);
+                self.emit(I::I32Const(-1));
+                self.emit(I::I32Ne);
+                self.emit(I::BrIf(0));
+                self.emit(I::Unreachable);
+                self.emit(I::End);
+
+                self.emit(I::LocalGet(address));
+            }
+            Helper::Div | Helper::Mod => {
+                self.emit(I::LocalGet(1));
+                self.emit(I::I64Eqz);
+                self.emit(I::If(BlockType::Empty));
+                self.fail_at_params("Division by zero", 2);
+                self.emit(I::End);
+
+                // Division of the smallest integer by -1 traps.
+                self.emit(I::LocalGet(1));
+                self.emit(I::I64Const(-1));
+                self.emit(I::I64Eq);
+                self.emit(I::If(BlockType::Value(ValType::I64)));
+                if helper == Helper::Div {
+                    self.emit(I::I64Const(0));
+                    self.emit(I::LocalGet(0));
+                    self.emit(I::I64Sub);
+                } else {
+                    self.emit(I::I64Const(0));
+                }
+                self.emit(I::Else);
+                self.emit(I::LocalGet(0));
+                self.emit(I::LocalGet(1));
+                self.emit(if helper == Helper::Div {
+                    I::I64DivS
+                } else {
+                    I::I64RemS
+                });
+                self.emit(I::End);
+            }
+            Helper::Index => {
+                self.emit(I::LocalGet(0));
+                self.emit(I::I64Const(0));
+                self.emit(I::I64LtS);
+                self.emit(I::LocalGet(0));
+                self.emit(I::LocalGet(1));
+                self.emit(I::I32Load(0));
+                self.emit(I::I64ExtendI32U);
+                self.emit(I::I64GeS);
+                self.emit(I::I32Or);
+                self.emit(I::If(BlockType::Empty));
+                self.emit(I::LocalGet(3));
+                self.emit(I::LocalGet(4));
+                self.emit(I::LocalGet(1));
+                self.emit(I::I32Load(0));
+                self.emit(I::I64ExtendI32U);
+                self.emit(I::LocalGet(0));
+                self.call(Callee::Host(Host::FailIndex));
+                self.emit(I::Unreachable);
+                self.emit(I::End);
+
+                self.emit(I::LocalGet(1));
+                self.emit(I::LocalGet(0));
+                self.emit(I::I32WrapI64);
+                self.emit(I::LocalGet(2));
+                self.emit(I::I32Mul);
+                self.emit(I::I32Add);
+            }
+            Helper::StrEq => {
+                let i = self.local(ValType::I32);
+                self.emit(I::LocalGet(0));
+                self.emit(I::I32Load(0));
+                self.emit(I::LocalGet(1));
+                self.emit(I::I32Load(0));
+                self.emit(I::I32Ne);
+                self.emit(I::If(BlockType::Empty));
+                self.emit(I::I32Const(0));
+                self.emit(I::Return);
+                self.emit(I::End);
+
+                self.emit(I::Block(BlockType::Empty));
+                self.emit(I::Loop(BlockType::Empty));
+                self.emit(I::LocalGet(i));
+                self.emit(I::LocalGet(0));
+                self.emit(I::I32Load(0));
+                self.emit(I::I32GeU);
+                self.emit(I::BrIf(1));
+                for s in [0, 1] {
+                    self.emit(I::LocalGet(s));
+                    self.emit(I::LocalGet(i));
+                    self.emit(I::I32Add);
+                    self.emit(I::I32Load8U(BYTES));
+                }
+                self.emit(I::I32Ne);
+                self.emit(I::If(BlockType::Empty));
+                self.emit(I::I32Const(0));
+                self.emit(I::Return);
+                self.emit(I::End);
+                self.increment(i);
+                self.emit(I::Br(0));
+                self.emit(I::End);
+                self.emit(I::End);
+
+                self.emit(I::I32Const(1));
+            }
+            Helper::ArrayEq => {
+                let (i, size) = (self.local(ValType::I32), self.local(ValType::I32));
+                let (a, b) = (self.local(ValType::I32), self.local(ValType::I32));
+                self.emit(I::LocalGet(0));
+                self.emit(I::I32Load(0));
+                self.emit(I::LocalGet(1));
+                self.emit(I::I32Load(0));
+                self.emit(I::I32Ne);
+                self.emit(I::If(BlockType::Empty));
+                self.emit(I::I32Const(0));
+                self.emit(I::Return);
+                self.emit(I::End);
+                self.element_size(2, size);
+
+                self.emit(I::Block(BlockType::Empty));
+                self.emit(I::Loop(BlockType::Empty));
+                self.emit(I::LocalGet(i));
+                self.emit(I::LocalGet(0));
+                self.emit(I::I32Load(0));
+                self.emit(I::I32GeU);
+                self.emit(I::BrIf(1));
+                for (array, address) in [(0, a), (1, b)] {
+                    self.emit(I::LocalGet(array));
+                    self.emit(I::LocalGet(i));
+                    self.emit(I::LocalGet(size));
+                    self.emit(I::I32Mul);
+                    self.emit(I::I32Add);
+                    self.emit(I::LocalSet(address));
+                }
+
+                // Whether the elements are equal, by their kind.
+                self.emit(I::Block(BlockType::Value(ValType::I32)));
+                for (kind, element, eq) in [
+                    (KIND_INT, ValType::I64, I::I64Eq),
+                    (KIND_FLOAT, ValType::F64, I::F64Eq),
+                    (KIND_STR, ValType::I32, I::Call(0)),
+                ] {
+                    self.emit(I::LocalGet(2));
+                    self.emit(I::I32Const(kind));
+                    self.emit(I::I32Eq);
+                    self.emit(I::If(BlockType::Empty));
+                    self.emit(I::LocalGet(a));
+                    self.emit(load(element, ELEMENTS));
+                    self.emit(I::LocalGet(b));
+                    self.emit(load(element, ELEMENTS));
+                    match eq {
+                        I::Call(_) => self.call(Callee::Helper(Helper::StrEq)),
+                        eq => self.emit(eq),
+                    }
+                    self.emit(I::Br(1));
+                    self.emit(I::End);
+                }
+                // Characters and booleans.
+                self.emit(I::LocalGet(a));
+                self.emit(I::I32Load(ELEMENTS));
+                self.emit(I::LocalGet(b));
+                self.emit(I::I32Load(ELEMENTS));
+                self.emit(I::I32Eq);
+                self.emit(I::End);
+
+                self.emit(I::I32Eqz);
+                self.emit(I::If(BlockType::Empty));
+                self.emit(I::I32Const(0));
+                self.emit(I::Return);
+                self.emit(I::End);
+                self.increment(i);
+                self.emit(I::Br(0));
+                self.emit(I::End);
+                self.emit(I::End);
+
+                self.emit(I::I32Const(1));
+            }
+            Helper::PrintArray => {
+                let (i, size, address) = (
+                    self.local(ValType::I32),
+                    self.local(ValType::I32),
+                    self.local(ValType::I32),
+                );
+                let (open, separator, close) =
+                    (self.string(b"["), self.string(b", "), self.string(b"]"));
+                self.emit(I::I32Const(open as i32));
+                self.call(Callee::Host(Host::PrintStr));
+                self.element_size(1, size);
+
+                self.emit(I::Block(BlockType::Empty));
+                self.emit(I::Loop(BlockType::Empty));
+                self.emit(I::LocalGet(i));
+                self.emit(I::LocalGet(0));
+                self.emit(I::I32Load(0));
+                self.emit(I::I32GeU);
+                self.emit(I::BrIf(1));
+                self.emit(I::LocalGet(i));
+                self.emit(I::If(BlockType::Empty));
+                self.emit(I::I32Const(separator as i32));
+                self.call(Callee::Host(Host::PrintStr));
+                self.emit(I::End);
+                self.emit(I::LocalGet(0));
+                self.emit(I::LocalGet(i));
+                self.emit(I::LocalGet(size));
+                self.emit(I::I32Mul);
+                self.emit(I::I32Add);
+                self.emit(I::LocalSet(address));
+
+                // Prints the element by its kind.
+                self.emit(I::Block(BlockType::Empty));
+                for (kind, element, host) in [
+                    (KIND_INT, ValType::I64, Host::PrintInt),
+                    (KIND_FLOAT, ValType::F64, Host::PrintFloat),
+                    (KIND_CHAR, ValType::I32, Host::PrintChar),
+                    (KIND_STR, ValType::I32, Host::PrintStr),
+                ] {
+                    self.emit(I::LocalGet(1));
+                    self.emit(I::I32Const(kind));
+                    self.emit(I::I32Eq);
+                    self.emit(I::If(BlockType::Empty));
+                    self.emit(I::LocalGet(address));
+                    self.emit(load(element, ELEMENTS));
+                    self.call(Callee::Host(host));
+                    self.emit(I::Br(1));
+                    self.emit(I::End);
+                }
+                self.emit(I::LocalGet(address));
+                self.emit(I::I32Load(ELEMENTS));
+                self.call(Callee::Host(Host::PrintBool));
+                self.emit(I::End);
+
+                self.increment(i);
+                self.emit(I::Br(0));
+                self.emit(I::End);
+                self.emit(I::End);
+
+                self.emit(I::I32Const(close as i32));
+                self.call(Callee::Host(Host::PrintStr));
+            }
+        }
+
+        self.finish(helper.name().to_string(), t)
+    }
+
+    /// Stores the size of elements of the kind in parameter `kind` to a local.
+    fn element_size(&mut self, kind: u32, local: u32) {
+        self.emit(Instr::I32Const(8));
+        self.emit(Instr::I32Const(4));
+        self.emit(Instr::LocalGet(kind));
+        self.emit(Instr::I32Const(KIND_FLOAT));
+        self.emit(Instr::I32LeU);
+        self.emit(Instr::Select);
+        self.emit(Instr::LocalSet(local));
+    }
+
+    /// Adds one to an `i32` local.
+    fn increment(&mut self, local: u32) {
+        self.emit(Instr::LocalGet(local));
+        self.emit(Instr::I32Const(1));
+        self.emit(Instr::I32Add);
+        self.emit(Instr::LocalSet(local));
+    }
+
+    /// Reports a runtime error at a position of the Kolang code and traps.
+    fn fail(&mut self, message: &str, pos: (usize, usize)) {
+        self.emit(Instr::I32Const(pos.0 as i32));
+        self.emit(Instr::I32Const(pos.1 as i32));
+        let message = self.string(message.as_bytes());
+        self.emit(Instr::I32Const(message as i32));
+        self.call(Callee::Host(Host::Fail));
+        self.emit(Instr::Unreachable);
+    }
+
+    /// Reports a runtime error at the line and column which are passed
+    /// in parameters `line` and `line + 1`, and traps.
+    fn fail_at_params(&mut self, message: &str, line: u32) {
+        self.emit(Instr::LocalGet(line));
+        self.emit(Instr::LocalGet(line + 1));
+        let message = self.string(message.as_bytes());
+        self.emit(Instr::I32Const(message as i32));
+        self.call(Callee::Host(Host::Fail));
+        self.emit(Instr::Unreachable);
+    }
+
+    /// Leaves the running function.
+    fn leave(&mut self) {
+        self.emit(Instr::GlobalGet(DEPTH));
+        self.emit(Instr::I32Const(1));
+        self.emit(Instr::I32Sub);
+        self.emit(Instr::GlobalSet(DEPTH));
+    }
+
+    /// Starts a new function with provided number of parameters.
+    fn start(&mut self, params: usize) {
+        self.params = params;
+        self.locals.clear();
+        self.body.clear();
+    }
+
+    /// Returns the current function.
+    fn finish(&mut self, name: String, t: FuncType) -> Function {
+        Function {
+            name,
+            type_index: self.type_index(t),
+            locals: mem::take(&mut self.locals),
+            body: mem::take(&mut self.body),
+        }
+    }
+
+    /// Defines a variable in the current scope and returns its local.
+    fn define(&mut self, id: &str, t: ast::Type, value_type: ValType) -> u32 {
+        let local = self.local(value_type);
+        self.scopes
+            .last_mut()
+            .expect("There is an open scope")
+            .insert(id.to_string(), (local, t));
+        local
+    }
+
+    /// Returns the local and type of a variable.
+    fn lookup(&self, id: &str, pos: (usize, usize)) -> Result<(u32, ast::Type), CodegenError> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|s| s.get(id))
+            .cloned()
+            .ok_or_else(|| CodegenError::UndefinedVariable {
+                id: id.to_string(),
+                line: pos.0,
+                column: pos.1,
+            })
+    }
+
+    /// Adds a local to the current function and returns its index.
+    fn local(&mut self, t: ValType) -> u32 {
+        self.locals.push(t);
+        (self.params + self.locals.len() - 1) as u32
+    }
+
+    /// Returns the address of a string in the data, adding it if needed.
+    fn string(&mut self, bytes: &[u8]) -> u32 {
+        if let Some(&address) = self.strings.get(bytes) {
+            return address;
+        }
+
+        // Lengths are aligned to 4 bytes.
+        while !self.data.len().is_multiple_of(4) {
+            self.data.push(0);
+        }
+        let address = DATA_START + self.data.len() as u32;
+        self.data.extend((bytes.len() as u32).to_le_bytes());
+        self.data.extend(bytes);
+        self.strings.insert(bytes.to_vec(), address);
+        address
+    }
+
+    /// Returns the index of a function type, adding it if needed.
+    fn type_index(&mut self, t: FuncType) -> u32 {
+        let index = match self.types.iter().position(|other| *other == t) {
+            Some(index) => index,
+            None => {
+                self.types.push(t);
+                self.types.len() - 1
+            }
+        };
+        index as u32
+    }
+
+    /// Calls a function.
+    fn call(&mut self, callee: Callee) {
+        let index = match self.callees.iter().position(|c| *c == callee) {
+            Some(index) => index,
+            None => {
+                self.callees.push(callee);
+                self.callees.len() - 1
+            }
+        };
+        self.emit(Instr::Call(index as u32));
+    }
+
+    /// Appends an instruction to the current function.
+    fn emit(&mut self, instr: Instr) {
+        self.body.push(instr);
+    }
+}
+
+/// Returns the WebAssembly type of values of a Kolang type. Strings and
+/// arrays are addresses.
+fn val_type(t: &ast::Type) -> Result<ValType, CodegenError> {
+    Ok(match t {
+        ast::Type::Int { .. } => ValType::I64,
+        ast::Type::Float { .. } => ValType::F64,
+        ast::Type::Char { .. }
+        | ast::Type::Str { .. }
+        | ast::Type::Bool { .. }
+        | ast::Type::Array { .. } => ValType::I32,
+        ast::Type::Error { span } => {
+            return Err(CodegenError::InvalidCode {
+                line: span.line,
+                column: span.column,
+            })
+        }
+    })
+}
+
+/// Returns the kind of array elements of a type. `pos` is the position of
+/// the code which uses the array.
+fn kind(t: &ast::Type, pos: (usize, usize)) -> Result<i32, CodegenError> {
+    Ok(match t {
+        ast::Type::Float { .. } => KIND_FLOAT,
+        ast::Type::Char { .. } => KIND_CHAR,
+        ast::Type::Str { .. } => KIND_STR,
+        ast::Type::Bool { .. } => KIND_BOOL,
+        ast::Type::Array { .. } => {
+            return Err(CodegenError::NestedArray {
+                line: pos.0,
+                column: pos.1,
+            })
+        }
+        // Empty array literals have no element type.
+        ast::Type::Int { .. } | ast::Type::Error { .. } => KIND_INT,
+    })
+}
+
+/// Returns the size of array elements of a type in bytes.
+fn size(t: ValType) -> u32 {
+    match t {
+        ValType::I32 => 4,
+        ValType::I64 | ValType::F64 => 8,
+    }
+}
+
+/// Returns the instruction which loads a value of a type.
+fn load(t: ValType, offset: u32) -> Instr {
+    match t {
+        ValType::I32 => Instr::I32Load(offset),
+        ValType::I64 => Instr::I64Load(offset),
+        ValType::F64 => Instr::F64Load(offset),
+    }
+}
+
+/// Returns the instruction which stores a value of a type.
+fn store(t: ValType, offset: u32) -> Instr {
+    match t {
+        ValType::I32 => Instr::I32Store(offset),
+        ValType::I64 => Instr::I64Store(offset),
+        ValType::F64 => Instr::F64Store(offset),
+    }
+}
+
+/// Returns the instruction which compares two values of a type, or `None`
+/// if the values cannot be compared with an instruction.
+fn comparison(op: &ast::BinOp, t: &ast::Type) -> Option<Instr> {
+    use ast::BinOp as B;
+    use ast::Type as T;
+
+    Some(match (op, t) {
+        (B::Eq { .. }, T::Int { .. }) => Instr::I64Eq,
+        (B::NEq { .. }, T::Int { .. }) => Instr::I64Ne,
+        (B::LT { .. }, T::Int { .. }) => Instr::I64LtS,
+        (B::GT { .. }, T::Int { .. }) => Instr::I64GtS,
+        (B::LEq { .. }, T::Int { .. }) => Instr::I64LeS,
+        (B::GEq { .. }, T::Int { .. }) => Instr::I64GeS,
+        (B::Eq { .. }, T::Float { .. }) => Instr::F64Eq,
+        (B::NEq { .. }, T::Float { .. }) => Instr::F64Ne,
+        (B::LT { .. }, T::Float { .. }) => Instr::F64Lt,
+        (B::GT { .. }, T::Float { .. }) => Instr::F64Gt,
+        (B::LEq { .. }, T::Float { .. }) => Instr::F64Le,
+        (B::GEq { .. }, T::Float { .. }) => Instr::F64Ge,
+        (B::Eq { .. }, T::Char { .. } | T::Bool { .. }) => Instr::I32Eq,
+        (B::NEq { .. }, T::Char { .. } | T::Bool { .. }) => Instr::I32Ne,
+        (B::LT { .. }, T::Char { .. }) => Instr::I32LtU,
+        (B::GT { .. }, T::Char { .. }) => Instr::I32GtU,
+        (B::LEq { .. }, T::Char { .. }) => Instr::I32LeU,
+        (B::GEq { .. }, T::Char { .. }) => Instr::I32GeU,
+        _ => return None,
+    })
+}
+
+/// Checks whether an expression is a literal other than an array, which
+/// can be evaluated again without effects.
+fn is_literal(expr: &ast::Expr) -> bool {
+    matches!(
+        expr,
+        ast::Expr::LiteralInt { .. }
+            | ast::Expr::LiteralStr { .. }
+            | ast::Expr::LiteralChar { .. }
+            | ast::Expr::LiteralFloat { .. }
+            | ast::Expr::LiteralBool { .. }
+    )
+}
+
+/// Translates a type-checked Kolang program to a WebAssembly module, which
+/// exports its `main` function and its memory (as `main` and `memory`).
+///
+/// The module imports these functions from the `kolang` module of the host:
+///
+/// | Function                                   | Use                             |
+/// |--------------------------------------------|---------------------------------|
+/// | `print_int(i64)`                           | Print an `int`                  |
+/// | `print_float(f64)`                         | Print a `float`                 |
+/// | `print_char(i32)`                          | Print a `char` (Unicode scalar) |
+/// | `print_str(i32)`                           | Print a `str` at an address     |
+/// | `print_bool(i32)`                          | Print a `bool` (0 or 1)         |
+/// | `println()`                                | End the line of `println`       |
+/// | `fmod(f64, f64) -> f64`                    | Remainder of `float` division   |
+/// | `fail(line: i32, column: i32, message: i32)` | Report a runtime error        |
+/// | `fail_index(line: i32, column: i32, len: i64, index: i64)` | Report an index out of bounds |
+///
+/// A string at an address starts with its length in bytes (`i32`), which is
+/// followed by its UTF-8 bytes. Functions which report errors should not
+/// return; the module traps if they do. Only functions which the program
+/// uses are imported.
+///
+/// The functions which are translated are selected by [`ast::definitions`].
+///
+/// # Errors
+/// Returns the first error found in the code. Code which passes the semantic
+/// checks only fails if it has no `main` function or uses nested arrays.
+///
+/// # Examples
+///
+/// ```
+/// use lexer::Lexer;
+/// use parser::Parser;
+///
+/// let source = "fn main() { println(1 + 2); }".as_bytes();
+/// let (ast, _) = Parser::new(Lexer::new(source)).parse().unwrap();
+///
+/// let module = codegen::wasm::generate(&ast).unwrap();
+/// assert!(module.to_bytes().starts_with(b"\0asm"));
+/// assert!(module.to_string().contains("(import \"kolang\" \"print_int\""));
+/// ```
+pub fn generate(prog: &[ast::Stmt]) -> Result<Module, CodegenError> {
+    let mut g = Generator::new();
+
+    let definitions = ast::definitions(prog);
+    for (index, f) in definitions.iter().enumerate() {
+        let ast::Stmt::FnDef {
+            id,
+            params,
+            return_type,
+            ..
+        } = f
+        else {
+            unreachable!("Only functions are collected");
+        };
+        let signature = Signature {
+            index,
+            params: params.iter().map(|(_, t)| t.clone()).collect(),
+            return_type: return_type.clone(),
+        };
+        g.functions.insert(id.clone(), signature);
+    }
+
+    let main = definitions
+        .iter()
+        .find(|f| matches!(f, ast::Stmt::FnDef { id, .. } if id == "main"));
+    let main = match main {
+        Some(ast::Stmt::FnDef { params, span, .. }) if !params.is_empty() => {
+            return Err(CodegenError::WrongArgumentCount {
+                function: "main".into(),
+                expected: params.len(),
+                found: 0,
+                line: span.line,
+                column: span.column,
+            })
+        }
+        Some(_) => g.functions["main"].index,
+        None => return Err(CodegenError::NoMain),
+    };
+
+    let mut functions = Vec::with_capacity(definitions.len());
+    for f in &definitions {
+        functions.push(g.function(f)?);
+    }
+    // Runtime functions may call other functions, which are added to the
+    // callees while the runtime functions are translated.
+    let mut helpers = Vec::new();
+    let mut i = 0;
+    while let Some(&callee) = g.callees.get(i) {
+        if let Callee::Helper(helper) = callee {
+            helpers.push(g.helper(helper));
+        }
+        i += 1;
+    }
+
+    let mut imports = Vec::new();
+    for callee in g.callees.clone() {
+        if let Callee::Host(host) = callee {
+            let type_index = g.type_index(host.signature());
+            imports.push(Import {
+                module: HOST_MODULE.into(),
+                name: host.name().into(),
+                type_index,
+            });
+        }
+    }
+    // Imported functions, then functions of the program, then runtime functions.
+    let (mut next_import, mut next_helper) = (0, imports.len() + functions.len());
+    let indices: Vec<u32> = g
+        .callees
+        .iter()
+        .map(|callee| {
+            let index = match callee {
+                Callee::Host(_) => {
+                    next_import += 1;
+                    next_import - 1
+                }
+                Callee::Function(f) => imports.len() + f,
+                Callee::Helper(_) => {
+                    next_helper += 1;
+                    next_helper - 1
+                }
+            };
+            index as u32
+        })
+        .collect();
+    functions.extend(helpers);
+    for f in &mut functions {
+        for instr in &mut f.body {
+            if let Instr::Call(callee) = instr {
+                *callee = indices[*callee as usize];
+            }
+        }
+    }
+
+    // The heap starts after the data, aligned to 8 bytes.
+    let heap = (DATA_START + g.data.len() as u32).next_multiple_of(8);
+    let data = if g.data.is_empty() {
+        Vec::new()
+    } else {
+        vec![Data {
+            offset: DATA_START,
+            bytes: g.data,
+        }]
+    };
+    Ok(Module {
+        types: g.types,
+        memory_pages: heap.div_ceil(0x10000).max(1),
+        globals: vec![
+            Global {
+                name: "heap".into(),
+                value_type: ValType::I32,
+                mutable: true,
+                init: Instr::I32Const(heap as i32),
+            },
+            Global {
+                name: "depth".into(),
+                value_type: ValType::I32,
+                mutable: true,
+                init: Instr::I32Const(0),
+            },
+        ],
+        exports: vec![
+            Export {
+                name: "main".into(),
+                kind: ExportKind::Function,
+                index: (imports.len() + main) as u32,
+            },
+            Export {
+                name: "memory".into(),
+                kind: ExportKind::Memory,
+                index: 0,
+            },
+        ],
+        imports,
+        functions,
+        data,
+    })
+}
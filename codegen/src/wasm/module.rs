@@ -0,0 +1,660 @@
+/// Magic number and version at the start of every binary module.
+pub const HEADER: [u8; 8] = [0x00, 0x61, 0x73, 0x6D, 0x01, 0x00, 0x00, 0x00];
+
+/// IDs of the sections of a binary module.
+pub mod section {
+    /// Function types.
+    pub const TYPE: u8 = 1;
+    /// Imported functions.
+    pub const IMPORT: u8 = 2;
+    /// Types of the defined functions.
+    pub const FUNCTION: u8 = 3;
+    /// Linear memory.
+    pub const MEMORY: u8 = 5;
+    /// Global variables.
+    pub const GLOBAL: u8 = 6;
+    /// Exported functions and memory.
+    pub const EXPORT: u8 = 7;
+    /// Locals and bodies of the defined functions.
+    pub const CODE: u8 = 10;
+    /// Initial contents of the linear memory.
+    pub const DATA: u8 = 11;
+}
+
+/// Type of WebAssembly values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ValType {
+    /// 32-bit integer.
+    I32,
+    /// 64-bit integer.
+    I64,
+    /// 64-bit float.
+    F64,
+}
+
+impl ValType {
+    fn code(self) -> u8 {
+        match self {
+            ValType::I32 => 0x7F,
+            ValType::I64 => 0x7E,
+            ValType::F64 => 0x7C,
+        }
+    }
+
+    /// Returns the name of the type in the text format.
+    pub fn name(self) -> &'static str {
+        match self {
+            ValType::I32 => "i32",
+            ValType::I64 => "i64",
+            ValType::F64 => "f64",
+        }
+    }
+}
+
+/// Type of a function.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FuncType {
+    /// Types of the parameters.
+    pub params: Vec<ValType>,
+    /// Types of the results.
+    pub results: Vec<ValType>,
+}
+
+/// Type of the values which a block, loop or conditional leaves on the stack.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockType {
+    /// The block leaves nothing.
+    Empty,
+    /// The block leaves a value.
+    Value(ValType),
+}
+
+/// A WebAssembly instruction. Memory instructions have a constant offset
+/// which is added to the address.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Instr {
+    /// Trap.
+    Unreachable,
+    /// Start a block, which `br` jumps to the end of.
+    Block(BlockType),
+    /// Start a loop, which `br` jumps to the start of.
+    Loop(BlockType),
+    /// Pop a condition and start a conditional block.
+    If(BlockType),
+    /// Start the `else` part of a conditional block.
+    Else,
+    /// End a block, loop, conditional or function.
+    End,
+    /// Jump to the label of an enclosing block (0 for the innermost).
+    Br(u32),
+    /// Pop a condition and jump like `Br` if it is not zero.
+    BrIf(u32),
+    /// Return from the function.
+    Return,
+    /// Call a function by its index.
+    Call(u32),
+    /// Discard the top value.
+    Drop,
+    /// Pop a condition and two values below it, and push the first value
+    /// if the condition is not zero, or the second one otherwise.
+    Select,
+    /// Push the value of a local.
+    LocalGet(u32),
+    /// Pop a value and store it in a local.
+    LocalSet(u32),
+    /// Store the top value in a local without popping it.
+    LocalTee(u32),
+    /// Push the value of a global.
+    GlobalGet(u32),
+    /// Pop a value and store it in a global.
+    GlobalSet(u32),
+    /// Pop an address and push the `i32` at it (plus the offset).
+    I32Load(u32),
+    /// Pop an address and push the `i64` at it (plus the offset).
+    I64Load(u32),
+    /// Pop an address and push the `f64` at it (plus the offset).
+    F64Load(u32),
+    /// Pop an address and push the byte at it (plus the offset).
+    I32Load8U(u32),
+    /// Pop an `i32` and an address, and store the value at the address.
+    I32Store(u32),
+    /// Pop an `i64` and an address, and store the value at the address.
+    I64Store(u32),
+    /// Pop an `f64` and an address, and store the value at the address.
+    F64Store(u32),
+    /// Push the size of the memory in pages.
+    MemorySize,
+    /// Pop a number of pages, grow the memory and push its old size (-1 on failure).
+    MemoryGrow,
+    /// Push an `i32` constant.
+    I32Const(i32),
+    /// Push an `i64` constant.
+    I64Const(i64),
+    /// Push an `f64` constant.
+    F64Const(f64),
+    /// `i32` equality with zero.
+    I32Eqz,
+    /// `i32` equality.
+    I32Eq,
+    /// `i32` inequality.
+    I32Ne,
+    /// Unsigned `i32` less than.
+    I32LtU,
+    /// Unsigned `i32` greater than.
+    I32GtU,
+    /// Unsigned `i32` less than or equal.
+    I32LeU,
+    /// Unsigned `i32` greater than or equal.
+    I32GeU,
+    /// `i64` equality with zero.
+    I64Eqz,
+    /// `i64` equality.
+    I64Eq,
+    /// `i64` inequality.
+    I64Ne,
+    /// Signed `i64` less than.
+    I64LtS,
+    /// Signed `i64` greater than.
+    I64GtS,
+    /// Signed `i64` less than or equal.
+    I64LeS,
+    /// Signed `i64` greater than or equal.
+    I64GeS,
+    /// `f64` equality.
+    F64Eq,
+    /// `f64` inequality.
+    F64Ne,
+    /// `f64` less than.
+    F64Lt,
+    /// `f64` greater than.
+    F64Gt,
+    /// `f64` less than or equal.
+    F64Le,
+    /// `f64` greater than or equal.
+    F64Ge,
+    /// `i32` addition.
+    I32Add,
+    /// `i32` subtraction.
+    I32Sub,
+    /// `i32` multiplication.
+    I32Mul,
+    /// `i32` bitwise and.
+    I32And,
+    /// `i32` bitwise or.
+    I32Or,
+    /// `i32` shift left.
+    I32Shl,
+    /// Unsigned `i32` shift right.
+    I32ShrU,
+    /// `i64` addition.
+    I64Add,
+    /// `i64` subtraction.
+    I64Sub,
+    /// `i64` multiplication.
+    I64Mul,
+    /// Signed `i64` division.
+    I64DivS,
+    /// Signed `i64` remainder.
+    I64RemS,
+    /// `i64` bitwise and.
+    I64And,
+    /// `i64` bitwise or.
+    I64Or,
+    /// `i64` bitwise exclusive or.
+    I64Xor,
+    /// `f64` negation.
+    F64Neg,
+    /// `f64` addition.
+    F64Add,
+    /// `f64` subtraction.
+    F64Sub,
+    /// `f64` multiplication.
+    F64Mul,
+    /// `f64` division.
+    F64Div,
+    /// Convert an `i64` to `i32`, keeping the low bits.
+    I32WrapI64,
+    /// Convert an unsigned `i32` to `i64`.
+    I64ExtendI32U,
+}
+
+impl Instr {
+    /// Returns the opcode of the instruction.
+    pub fn opcode(&self) -> u8 {
+        use Instr as I;
+
+        match self {
+            I::Unreachable => 0x00,
+            I::Block(_) => 0x02,
+            I::Loop(_) => 0x03,
+            I::If(_) => 0x04,
+            I::Else => 0x05,
+            I::End => 0x0B,
+            I::Br(_) => 0x0C,
+            I::BrIf(_) => 0x0D,
+            I::Return => 0x0F,
+            I::Call(_) => 0x10,
+            I::Drop => 0x1A,
+            I::Select => 0x1B,
+            I::LocalGet(_) => 0x20,
+            I::LocalSet(_) => 0x21,
+            I::LocalTee(_) => 0x22,
+            I::GlobalGet(_) => 0x23,
+            I::GlobalSet(_) => 0x24,
+            I::I32Load(_) => 0x28,
+            I::I64Load(_) => 0x29,
+            I::F64Load(_) => 0x2B,
+            I::I32Load8U(_) => 0x2D,
+            I::I32Store(_) => 0x36,
+            I::I64Store(_) => 0x37,
+            I::F64Store(_) => 0x39,
+            I::MemorySize => 0x3F,
+            I::MemoryGrow => 0x40,
+            I::I32Const(_) => 0x41,
+            I::I64Const(_) => 0x42,
+            I::F64Const(_) => 0x44,
+            I::I32Eqz => 0x45,
+            I::I32Eq => 0x46,
+            I::I32Ne => 0x47,
+            I::I32LtU => 0x49,
+            I::I32GtU => 0x4B,
+            I::I32LeU => 0x4D,
+            I::I32GeU => 0x4F,
+            I::I64Eqz => 0x50,
+            I::I64Eq => 0x51,
+            I::I64Ne => 0x52,
+            I::I64LtS => 0x53,
+            I::I64GtS => 0x55,
+            I::I64LeS => 0x57,
+            I::I64GeS => 0x59,
+            I::F64Eq => 0x61,
+            I::F64Ne => 0x62,
+            I::F64Lt => 0x63,
+            I::F64Gt => 0x64,
+            I::F64Le => 0x65,
+            I::F64Ge => 0x66,
+            I::I32Add => 0x6A,
+            I::I32Sub => 0x6B,
+            I::I32Mul => 0x6C,
+            I::I32And => 0x71,
+            I::I32Or => 0x72,
+            I::I32Shl => 0x74,
+            I::I32ShrU => 0x76,
+            I::I64Add => 0x7C,
+            I::I64Sub => 0x7D,
+            I::I64Mul => 0x7E,
+            I::I64DivS => 0x7F,
+            I::I64RemS => 0x81,
+            I::I64And => 0x83,
+            I::I64Or => 0x84,
+            I::I64Xor => 0x85,
+            I::F64Neg => 0x9A,
+            I::F64Add => 0xA0,
+            I::F64Sub => 0xA1,
+            I::F64Mul => 0xA2,
+            I::F64Div => 0xA3,
+            I::I32WrapI64 => 0xA7,
+            I::I64ExtendI32U => 0xAD,
+        }
+    }
+
+    /// Returns the name of the instruction in the text format.
+    pub fn name(&self) -> &'static str {
+        use Instr as I;
+
+        match self {
+            I::Unreachable => "unreachable",
+            I::Block(_) => "block",
+            I::Loop(_) => "loop",
+            I::If(_) => "if",
+            I::Else => "else",
+            I::End => "end",
+            I::Br(_) => "br",
+            I::BrIf(_) => "br_if",
+            I::Return => "return",
+            I::Call(_) => "call",
+            I::Drop => "drop",
+            I::Select => "select",
+            I::LocalGet(_) => "local.get",
+            I::LocalSet(_) => "local.set",
+            I::LocalTee(_) => "local.tee",
+            I::GlobalGet(_) => "global.get",
+            I::GlobalSet(_) => "global.set",
+            I::I32Load(_) => "i32.load",
+            I::I64Load(_) => "i64.load",
+            I::F64Load(_) => "f64.load",
+            I::I32Load8U(_) => "i32.load8_u",
+            I::I32Store(_) => "i32.store",
+            I::I64Store(_) => "i64.store",
+            I::F64Store(_) => "f64.store",
+            I::MemorySize => "memory.size",
+            I::MemoryGrow => "memory.grow",
+            I::I32Const(_) => "i32.const",
+            I::I64Const(_) => "i64.const",
+            I::F64Const(_) => "f64.const",
+            I::I32Eqz => "i32.eqz",
+            I::I32Eq => "i32.eq",
+            I::I32Ne => "i32.ne",
+            I::I32LtU => "i32.lt_u",
+            I::I32GtU => "i32.gt_u",
+            I::I32LeU => "i32.le_u",
+            I::I32GeU => "i32.ge_u",
+            I::I64Eqz => "i64.eqz",
+            I::I64Eq => "i64.eq",
+            I::I64Ne => "i64.ne",
+            I::I64LtS => "i64.lt_s",
+            I::I64GtS => "i64.gt_s",
+            I::I64LeS => "i64.le_s",
+            I::I64GeS => "i64.ge_s",
+            I::F64Eq => "f64.eq",
+            I::F64Ne => "f64.ne",
+            I::F64Lt => "f64.lt",
+            I::F64Gt => "f64.gt",
+            I::F64Le => "f64.le",
+            I::F64Ge => "f64.ge",
+            I::I32Add => "i32.add",
+            I::I32Sub => "i32.sub",
+            I::I32Mul => "i32.mul",
+            I::I32And => "i32.and",
+            I::I32Or => "i32.or",
+            I::I32Shl => "i32.shl",
+            I::I32ShrU => "i32.shr_u",
+            I::I64Add => "i64.add",
+            I::I64Sub => "i64.sub",
+            I::I64Mul => "i64.mul",
+            I::I64DivS => "i64.div_s",
+            I::I64RemS => "i64.rem_s",
+            I::I64And => "i64.and",
+            I::I64Or => "i64.or",
+            I::I64Xor => "i64.xor",
+            I::F64Neg => "f64.neg",
+            I::F64Add => "f64.add",
+            I::F64Sub => "f64.sub",
+            I::F64Mul => "f64.mul",
+            I::F64Div => "f64.div",
+            I::I32WrapI64 => "i32.wrap_i64",
+            I::I64ExtendI32U => "i64.extend_i32_u",
+        }
+    }
+
+    /// Returns the base 2 logarithm of the natural alignment of a memory
+    /// instruction, or `None` for other instructions.
+    pub fn alignment(&self) -> Option<u32> {
+        match self {
+            Instr::I32Load8U(_) => Some(0),
+            Instr::I32Load(_) | Instr::I32Store(_) => Some(2),
+            Instr::I64Load(_) | Instr::F64Load(_) | Instr::I64Store(_) | Instr::F64Store(_) => {
+                Some(3)
+            }
+            _ => None,
+        }
+    }
+
+    /// Appends the binary encoding of the instruction.
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.push(self.opcode());
+        match *self {
+            Instr::Block(t) | Instr::Loop(t) | Instr::If(t) => out.push(match t {
+                BlockType::Empty => 0x40,
+                BlockType::Value(t) => t.code(),
+            }),
+            Instr::Br(n)
+            | Instr::BrIf(n)
+            | Instr::Call(n)
+            | Instr::LocalGet(n)
+            | Instr::LocalSet(n)
+            | Instr::LocalTee(n)
+            | Instr::GlobalGet(n)
+            | Instr::GlobalSet(n) => write_u32(out, n),
+            Instr::I32Load(offset)
+            | Instr::I64Load(offset)
+            | Instr::F64Load(offset)
+            | Instr::I32Load8U(offset)
+            | Instr::I32Store(offset)
+            | Instr::I64Store(offset)
+            | Instr::F64Store(offset) => {
+                write_u32(
+                    out,
+                    self.alignment().expect("Memory instructions are aligned"),
+                );
+                write_u32(out, offset);
+            }
+            // The memory index, which is always 0.
+            Instr::MemorySize | Instr::MemoryGrow => out.push(0x00),
+            Instr::I32Const(n) => write_i64(out, n.into()),
+            Instr::I64Const(n) => write_i64(out, n),
+            Instr::F64Const(x) => out.extend(x.to_le_bytes()),
+            _ => {}
+        }
+    }
+}
+
+/// A function imported from the host.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Import {
+    /// Name of the module which provides the function.
+    pub module: String,
+    /// Name of the function.
+    pub name: String,
+    /// Index of the type of the function.
+    pub type_index: u32,
+}
+
+/// A function defined in the module.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Function {
+    /// Name of the function, which is only used in the text format.
+    pub name: String,
+    /// Index of the type of the function.
+    pub type_index: u32,
+    /// Types of the locals, which come after the parameters.
+    pub locals: Vec<ValType>,
+    /// Instructions of the function, without the final `end`.
+    pub body: Vec<Instr>,
+}
+
+/// A global variable.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Global {
+    /// Name of the global, which is only used in the text format.
+    pub name: String,
+    /// Type of the global.
+    pub value_type: ValType,
+    /// Whether the global can be changed.
+    pub mutable: bool,
+    /// Constant instruction which gives the initial value.
+    pub init: Instr,
+}
+
+/// Kind of an exported item.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportKind {
+    /// A function (imported or defined).
+    Function,
+    /// The linear memory.
+    Memory,
+}
+
+/// An item which the host can use.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Export {
+    /// Name of the export.
+    pub name: String,
+    /// Kind of the item.
+    pub kind: ExportKind,
+    /// Index of the item.
+    pub index: u32,
+}
+
+/// Bytes which are copied to the linear memory when the module is instantiated.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Data {
+    /// Address of the first byte.
+    pub offset: u32,
+    /// The bytes.
+    pub bytes: Vec<u8>,
+}
+
+/// The `Module` struct is a WebAssembly module with one linear memory.
+/// Imported functions come before defined functions in the function
+/// index space.
+///
+/// Use [`Module::to_bytes`] to get the binary format and [`Display`]
+/// (`{}`) to get the text format.
+///
+/// [`Display`]: std::fmt::Display
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Module {
+    /// Function types, which are referred by their index.
+    pub types: Vec<FuncType>,
+    /// Imported functions.
+    pub imports: Vec<Import>,
+    /// Defined functions.
+    pub functions: Vec<Function>,
+    /// Initial size of the linear memory in 64 KiB pages.
+    pub memory_pages: u32,
+    /// Global variables.
+    pub globals: Vec<Global>,
+    /// Exported items.
+    pub exports: Vec<Export>,
+    /// Initial contents of the linear memory.
+    pub data: Vec<Data>,
+}
+
+impl Module {
+    /// Returns the binary format of the module. Sections without entries
+    /// (except the memory section) are left out.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = HEADER.to_vec();
+
+        write_section(&mut out, section::TYPE, &self.types, |out, t| {
+            out.push(0x60);
+            write_vec(out, &t.params, |out, p| out.push(p.code()));
+            write_vec(out, &t.results, |out, r| out.push(r.code()));
+        });
+        write_section(&mut out, section::IMPORT, &self.imports, |out, i| {
+            write_name(out, &i.module);
+            write_name(out, &i.name);
+            out.push(0x00);
+            write_u32(out, i.type_index);
+        });
+        write_section(&mut out, section::FUNCTION, &self.functions, |out, f| {
+            write_u32(out, f.type_index)
+        });
+        write_section(
+            &mut out,
+            section::MEMORY,
+            &[self.memory_pages],
+            |out, &pages| {
+                // Limits without maximum.
+                out.push(0x00);
+                write_u32(out, pages);
+            },
+        );
+        write_section(&mut out, section::GLOBAL, &self.globals, |out, g| {
+            out.push(g.value_type.code());
+            out.push(g.mutable.into());
+            g.init.encode(out);
+            Instr::End.encode(out);
+        });
+        write_section(&mut out, section::EXPORT, &self.exports, |out, e| {
+            write_name(out, &e.name);
+            out.push(match e.kind {
+                ExportKind::Function => 0x00,
+                ExportKind::Memory => 0x02,
+            });
+            write_u32(out, e.index);
+        });
+        write_section(&mut out, section::CODE, &self.functions, |out, f| {
+            let mut code = Vec::new();
+            // Locals are grouped by consecutive runs of the same type.
+            let mut groups: Vec<(u32, ValType)> = Vec::new();
+            for &t in &f.locals {
+                match groups.last_mut() {
+                    Some((count, last)) if *last == t => *count += 1,
+                    _ => groups.push((1, t)),
+                }
+            }
+            write_vec(&mut code, &groups, |out, &(count, t)| {
+                write_u32(out, count);
+                out.push(t.code());
+            });
+            for instr in &f.body {
+                instr.encode(&mut code);
+            }
+            Instr::End.encode(&mut code);
+
+            write_u32(out, code.len() as u32);
+            out.extend(code);
+        });
+        write_section(&mut out, section::DATA, &self.data, |out, d| {
+            // Active segment of memory 0.
+            out.push(0x00);
+            Instr::I32Const(d.offset as i32).encode(out);
+            Instr::End.encode(out);
+            write_u32(out, d.bytes.len() as u32);
+            out.extend(&d.bytes);
+        });
+
+        out
+    }
+}
+
+/// Appends a section with a vector of entries, unless there are no entries.
+fn write_section<T>(
+    out: &mut Vec<u8>,
+    id: u8,
+    entries: &[T],
+    write_entry: impl FnMut(&mut Vec<u8>, &T),
+) {
+    if entries.is_empty() {
+        return;
+    }
+
+    let mut payload = Vec::new();
+    write_vec(&mut payload, entries, write_entry);
+    out.push(id);
+    write_u32(out, payload.len() as u32);
+    out.extend(payload);
+}
+
+/// Appends a vector: its length followed by its entries.
+fn write_vec<T>(out: &mut Vec<u8>, entries: &[T], mut write_entry: impl FnMut(&mut Vec<u8>, &T)) {
+    write_u32(out, entries.len() as u32);
+    for e in entries {
+        write_entry(out, e);
+    }
+}
+
+/// Appends a UTF-8 name.
+fn write_name(out: &mut Vec<u8>, name: &str) {
+    write_u32(out, name.len() as u32);
+    out.extend(name.as_bytes());
+}
+
+/// Appends an unsigned integer in LEB128 encoding.
+fn write_u32(out: &mut Vec<u8>, mut n: u32) {
+    loop {
+        let byte = (n & 0x7F) as u8;
+        n >>= 7;
+        if n == 0 {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+/// Appends a signed integer in LEB128 encoding.
+fn write_i64(out: &mut Vec<u8>, mut n: i64) {
+    loop {
+        let byte = (n & 0x7F) as u8;
+        n >>= 7;
+        // The sign bit of the last byte extends to the rest of the value.
+        if (n == 0 && byte & 0x40 == 0) || (n == -1 && byte & 0x40 != 0) {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
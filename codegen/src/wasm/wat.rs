@@ -0,0 +1,168 @@
+use std::fmt;
+
+use super::module::{BlockType, ExportKind, FuncType, Instr, Module};
+
+/// Prints the module in the WebAssembly text format. Functions and globals
+/// are referred by their names, and locals by their indices.
+impl fmt::Display for Module {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "(module")?;
+
+        for (i, t) in self.types.iter().enumerate() {
+            writeln!(f, "  (type (;{};) (func{}))", i, signature(t))?;
+        }
+        for import in &self.imports {
+            writeln!(
+                f,
+                "  (import \"{}\" \"{}\" (func ${} (type {})))",
+                import.module, import.name, import.name, import.type_index
+            )?;
+        }
+        for function in &self.functions {
+            let t = &self.types[function.type_index as usize];
+            writeln!(
+                f,
+                "  (func ${} (type {}){}",
+                function.name,
+                function.type_index,
+                signature(t)
+            )?;
+            if !function.locals.is_empty() {
+                let locals: Vec<&str> = function.locals.iter().map(|l| l.name()).collect();
+                writeln!(f, "    (local {})", locals.join(" "))?;
+            }
+
+            let mut depth = 2;
+            for instr in &function.body {
+                if let Instr::Else | Instr::End = instr {
+                    depth -= 1;
+                }
+                writeln!(f, "{:width$}{}", "", self.instr(instr), width = 2 * depth)?;
+                if let Instr::Block(_) | Instr::Loop(_) | Instr::If(_) | Instr::Else = instr {
+                    depth += 1;
+                }
+            }
+            writeln!(f, "  )")?;
+        }
+        writeln!(f, "  (memory (;0;) {})", self.memory_pages)?;
+        for global in &self.globals {
+            let t = if global.mutable {
+                format!("(mut {})", global.value_type.name())
+            } else {
+                global.value_type.name().to_string()
+            };
+            writeln!(
+                f,
+                "  (global ${} {} ({}))",
+                global.name,
+                t,
+                self.instr(&global.init)
+            )?;
+        }
+        for export in &self.exports {
+            let item = match export.kind {
+                ExportKind::Function => format!("func ${}", self.function_name(export.index)),
+                ExportKind::Memory => format!("memory {}", export.index),
+            };
+            writeln!(f, "  (export \"{}\" ({}))", export.name, item)?;
+        }
+        for data in &self.data {
+            writeln!(
+                f,
+                "  (data (i32.const {}) \"{}\")",
+                data.offset,
+                escape(&data.bytes)
+            )?;
+        }
+
+        write!(f, ")")
+    }
+}
+
+impl Module {
+    /// Returns the name of a function (imported or defined) by its index.
+    fn function_name(&self, index: u32) -> &str {
+        let index = index as usize;
+        match self.imports.get(index) {
+            Some(import) => &import.name,
+            None => &self.functions[index - self.imports.len()].name,
+        }
+    }
+
+    /// Returns the text of an instruction.
+    fn instr(&self, instr: &Instr) -> String {
+        let name = instr.name();
+        match *instr {
+            Instr::Block(t) | Instr::Loop(t) | Instr::If(t) => match t {
+                BlockType::Empty => name.to_string(),
+                BlockType::Value(t) => format!("{} (result {})", name, t.name()),
+            },
+            Instr::Br(n)
+            | Instr::BrIf(n)
+            | Instr::LocalGet(n)
+            | Instr::LocalSet(n)
+            | Instr::LocalTee(n) => format!("{} {}", name, n),
+            Instr::Call(n) => format!("{} ${}", name, self.function_name(n)),
+            Instr::GlobalGet(n) | Instr::GlobalSet(n) => {
+                format!("{} ${}", name, self.globals[n as usize].name)
+            }
+            Instr::I32Load(0)
+            | Instr::I64Load(0)
+            | Instr::F64Load(0)
+            | Instr::I32Load8U(0)
+            | Instr::I32Store(0)
+            | Instr::I64Store(0)
+            | Instr::F64Store(0) => name.to_string(),
+            Instr::I32Load(offset)
+            | Instr::I64Load(offset)
+            | Instr::F64Load(offset)
+            | Instr::I32Load8U(offset)
+            | Instr::I32Store(offset)
+            | Instr::I64Store(offset)
+            | Instr::F64Store(offset) => format!("{} offset={}", name, offset),
+            Instr::I32Const(n) => format!("{} {}", name, n),
+            Instr::I64Const(n) => format!("{} {}", name, n),
+            Instr::F64Const(x) => format!("{} {}", name, float(x)),
+            _ => name.to_string(),
+        }
+    }
+}
+
+/// Returns the parameters and results of a function type.
+fn signature(t: &FuncType) -> String {
+    let mut s = String::new();
+    if !t.params.is_empty() {
+        let params: Vec<&str> = t.params.iter().map(|p| p.name()).collect();
+        s.push_str(&format!(" (param {})", params.join(" ")));
+    }
+    if !t.results.is_empty() {
+        let results: Vec<&str> = t.results.iter().map(|r| r.name()).collect();
+        s.push_str(&format!(" (result {})", results.join(" ")));
+    }
+    s
+}
+
+/// Returns a float literal, which reads back as the same value.
+fn float(x: f64) -> String {
+    if x.is_nan() {
+        "nan".into()
+    } else if x.is_infinite() {
+        if x > 0.0 { "inf" } else { "-inf" }.into()
+    } else {
+        format!("{:?}", x)
+    }
+}
+
+/// Returns the contents of a string literal of bytes. Bytes other than
+/// printable ASCII characters are written as hexadecimal escapes.
+fn escape(bytes: &[u8]) -> String {
+    let mut s = String::with_capacity(bytes.len());
+    for &b in bytes {
+        match b {
+            b'"' | b'\\' => s.push_str(&format!("\\{:02x}", b)),
+            b' '..=b'~' => s.push(b as char),
+            _ => s.push_str(&format!("\\{:02x}", b)),
+        }
+    }
+    s
+}
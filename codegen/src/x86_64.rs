@@ -0,0 +1,830 @@
+use std::{
+    collections::HashMap,
+    ffi::{OsStr, OsString},
+    fs,
+    path::Path,
+};
+
+use diagnostics::Span;
+use parser::ast;
+use semantic::{
+    layout::FrameLayout,
+    resolver::{ScopeId, ScopeKind, ScopeTree, SymbolId},
+};
+
+use crate::{
+    error::{BuildError, CodegenError},
+    tool,
+};
+
+/// The runtime, which is copied to the top of every generated file.
+pub const RUNTIME: &str = include_str!("x86_64/runtime.s");
+
+const INDENT: &str = "    ";
+
+/// Registers which pass the first arguments of a call, in order.
+const ARG_REGISTERS: [&str; 6] = ["%rdi", "%rsi", "%rdx", "%rcx", "%r8", "%r9"];
+
+/// Parameter and return types of a function.
+struct Signature {
+    /// Types of the parameters.
+    params: Vec<ast::Type>,
+    /// Return type. `None` if the function returns nothing.
+    return_type: Option<ast::Type>,
+}
+
+/// The `Generator` struct translates function definitions of a Kolang
+/// program to x86-64 assembly (GNU `as` syntax), one at a time.
+///
+/// Each function has a frame whose slots (laid out by the semantic analyzer)
+/// hold its parameters and variables. Expressions are evaluated to `%rax`,
+/// and operands which are evaluated before others are pushed to the stack,
+/// so no register needs to be saved across calls.
+struct Generator<'a> {
+    /// Scope tree of the program.
+    tree: &'a ScopeTree,
+    /// Generated code of the function definitions.
+    out: String,
+    /// Read-only data of the program (string literals and messages).
+    data: String,
+    /// Defined functions, by name.
+    functions: HashMap<String, Signature>,
+    /// Stack slots of the current function.
+    layout: FrameLayout,
+    /// Open scopes of the current function, with the number of their
+    /// nested scopes which are already translated.
+    scopes: Vec<(ScopeId, usize)>,
+    /// Size of the frame of the current function in bytes.
+    frame: usize,
+    /// Number of values pushed below the frame.
+    depth: usize,
+    /// Number of labels used by the program.
+    labels: usize,
+}
+
+impl<'a> Generator<'a> {
+    fn new(tree: &'a ScopeTree) -> Self {
+        Self {
+            tree,
+            out: String::new(),
+            data: String::new(),
+            functions: HashMap::new(),
+            layout: FrameLayout::default(),
+            scopes: Vec::new(),
+            frame: 0,
+            depth: 0,
+            labels: 0,
+        }
+    }
+
+    /// Translates a function definition. `scope` is the function scope
+    /// of the definition in the scope tree.
+    fn function(&mut self, stmt: &ast::Stmt, scope: ScopeId) -> Result<(), CodegenError> {
+        let ast::Stmt::FnDef {
+            id,
+            params,
+            return_type,
+            body,
+            span,
+        } = stmt
+        else {
+            unreachable!("Only functions are translated");
+        };
+
+        for (_, t) in params {
+            supported(t)?;
+        }
+        if let Some(t) = return_type {
+            supported(t)?;
+        }
+
+        self.layout = FrameLayout::of_function(self.tree, scope);
+        self.scopes = vec![(scope, 0)];
+        // The stack stays aligned to 16 bytes when nothing is pushed.
+        self.frame = (self.layout.size * 8).next_multiple_of(16);
+        self.depth = 0;
+
+        self.out.push_str(&format!("\nfn_{}:\n", id));
+        self.line("pushq %rbp".into());
+        self.line("movq %rsp, %rbp".into());
+        if self.frame != 0 {
+            self.line(format!("subq ${}, %rsp", self.frame));
+        }
+        for (i, (param, _)) in params.iter().enumerate() {
+            let slot = self.declared(param);
+            match ARG_REGISTERS.get(i) {
+                Some(reg) => self.line(format!("movq {}, {}", reg, slot)),
+                None => {
+                    let offset = 16 + 8 * (i - ARG_REGISTERS.len());
+                    self.line(format!("movq {}(%rbp), %rax", offset));
+                    self.line(format!("movq %rax, {}", slot));
+                }
+            }
+        }
+        self.line(format!("movl ${}, %edi", span.line));
+        self.line(format!("movl ${}, %esi", span.column));
+        self.call("kl_enter");
+
+        self.stmt(body)?;
+        match return_type {
+            Some(_) => {
+                let message = format!("Function `{}` finished without returning a value", id);
+                self.fail(&message, (span.line, span.column));
+            }
+            None => self.ret(),
+        }
+        Ok(())
+    }
+
+    /// Translates a statement.
+    fn stmt(&mut self, stmt: &ast::Stmt) -> Result<(), CodegenError> {
+        match stmt {
+            ast::Stmt::Let {
+                id, var_type, expr, ..
+            } => {
+                supported(var_type)?;
+                match expr {
+                    Some(e) => {
+                        self.value(e)?;
+                    }
+                    None => self.line("xorl %eax, %eax".into()),
+                }
+                let slot = self.declared(id);
+                self.line(format!("movq %rax, {}", slot));
+            }
+            ast::Stmt::Expr { expr, .. } => {
+                self.expr(expr)?;
+            }
+            ast::Stmt::If {
+                cond,
+                then_stmt,
+                else_stmt,
+                ..
+            } => {
+                let els = self.label();
+                self.value(cond)?;
+                self.line("testq %rax, %rax".into());
+                self.line(format!("jz {}", els));
+                self.stmt(then_stmt)?;
+                match else_stmt {
+                    Some(else_stmt) => {
+                        let end = self.label();
+                        self.line(format!("jmp {}", end));
+                        self.place(&els);
+                        self.stmt(else_stmt)?;
+                        self.place(&end);
+                    }
+                    None => self.place(&els),
+                }
+            }
+            ast::Stmt::While { cond, body, .. } => {
+                let (start, end) = (self.label(), self.label());
+                self.place(&start);
+                self.value(cond)?;
+                self.line("testq %rax, %rax".into());
+                self.line(format!("jz {}", end));
+                self.stmt(body)?;
+                self.line(format!("jmp {}", start));
+                self.place(&end);
+            }
+            ast::Stmt::For {
+                id,
+                start,
+                end,
+                body,
+                ..
+            } => {
+                self.value(start)?;
+                self.push();
+                self.value(end)?;
+                self.line("movq %rax, %rcx".into());
+                self.pop("%rax");
+
+                self.enter();
+                let i = self.declared(id);
+                self.line(format!("movq %rax, {}", i));
+                // The end of the range stays on the stack while the loop runs.
+                self.line("pushq %rcx".into());
+                self.depth += 1;
+                let last = self.top();
+
+                let (check, exit) = (self.label(), self.label());
+                self.place(&check);
+                self.line(format!("movq {}, %rax", i));
+                self.line(format!("cmpq {}, %rax", last));
+                self.line(format!("jg {}", exit));
+                self.stmt(body)?;
+                self.line(format!("movq {}, %rax", i));
+                self.line("incq %rax".into());
+                // The loop ends instead of overflowing.
+                self.line(format!("jo {}", exit));
+                self.line(format!("movq %rax, {}", i));
+                self.line(format!("jmp {}", check));
+                self.place(&exit);
+
+                self.line("addq $8, %rsp".into());
+                self.depth -= 1;
+                self.exit();
+            }
+            ast::Stmt::Return { expr, .. } => {
+                self.value(expr)?;
+                self.ret();
+            }
+            ast::Stmt::Block { stmts, .. } => {
+                self.enter();
+                for s in stmts {
+                    self.stmt(s)?;
+                }
+                self.exit();
+            }
+            ast::Stmt::FnDef { span, .. } => {
+                return Err(CodegenError::InvalidCode {
+                    line: span.line,
+                    column: span.column,
+                })
+            }
+            ast::Stmt::Empty { .. } => {}
+        }
+
+        Ok(())
+    }
+
+    /// Translates an expression, whose value is left in `%rax`.
+    /// Returns the type of the expression, or `None` if it has no value.
+    fn expr(&mut self, expr: &ast::Expr) -> Result<Option<ast::Type>, CodegenError> {
+        let span = expr.span();
+        let pos = (span.line, span.column);
+
+        let t = match expr {
+            ast::Expr::LiteralInt { value, .. } => {
+                if i32::try_from(*value).is_ok() {
+                    self.line(format!("movq ${}, %rax", value));
+                } else {
+                    self.line(format!("movabsq ${}, %rax", value));
+                }
+                ast::Type::Int { span }
+            }
+            ast::Expr::LiteralChar { value, .. } => {
+                self.line(format!("movl ${}, %eax", *value as u32));
+                ast::Type::Char { span }
+            }
+            ast::Expr::LiteralBool { value, .. } => {
+                self.line(format!("movl ${}, %eax", *value as u8));
+                ast::Type::Bool { span }
+            }
+            ast::Expr::LiteralStr { .. } => return Err(unsupported(&ast::Type::Str { span })),
+            ast::Expr::LiteralFloat { .. } => return Err(unsupported(&ast::Type::Float { span })),
+            ast::Expr::LiteralArray { .. } => {
+                let t = ast::Type::Array {
+                    element_type: Box::new(ast::Type::Error { span }),
+                    span,
+                };
+                return Err(unsupported(&t));
+            }
+            ast::Expr::BinaryOp { l, op, r, .. } => match op {
+                ast::BinOp::LogAnd { .. } | ast::BinOp::LogOr { .. } => self.logical(l, op, r)?,
+                _ => {
+                    let t = self.value(l)?;
+                    self.push();
+                    self.value(r)?;
+                    self.line("movq %rax, %rcx".into());
+                    self.pop("%rax");
+                    self.binary(op, t, span)
+                }
+            },
+            ast::Expr::UnaryOp { op, expr, .. } => {
+                let t = self.value(expr)?;
+                let code = match op {
+                    ast::UnOp::Neg { .. } => "negq %rax",
+                    ast::UnOp::LogNot { .. } => "xorq $1, %rax",
+                    ast::UnOp::BitNot { .. } => "notq %rax",
+                };
+                self.line(code.into());
+                t
+            }
+            ast::Expr::Identifier { .. } => {
+                let (slot, t) = self.lookup(expr)?;
+                self.line(format!("movq {}, %rax", slot));
+                t
+            }
+            ast::Expr::Call { id, args, .. } => {
+                if id == "println" && !self.functions.contains_key(id) {
+                    self.println(args)?;
+                    return Ok(None);
+                }
+
+                let (params, return_type) = match self.functions.get(id) {
+                    Some(f) => (f.params.len(), f.return_type.clone()),
+                    None => {
+                        return Err(CodegenError::UndefinedFunction {
+                            id: id.clone(),
+                            line: pos.0,
+                            column: pos.1,
+                        })
+                    }
+                };
+                if params != args.len() {
+                    return Err(CodegenError::WrongArgumentCount {
+                        function: id.clone(),
+                        expected: params,
+                        found: args.len(),
+                        line: pos.0,
+                        column: pos.1,
+                    });
+                }
+
+                for arg in args {
+                    self.value(arg)?;
+                    self.push();
+                }
+                self.call_function(id, args.len());
+                return Ok(return_type);
+            }
+            ast::Expr::ArrayExpr { .. } => {
+                let (_, t) = self.lookup(expr)?;
+                return Err(unsupported(&t));
+            }
+            ast::Expr::Assign { expr: value, .. } => {
+                self.value(value)?;
+                let (slot, t) = self.lookup(expr)?;
+                self.line(format!("movq %rax, {}", slot));
+                t
+            }
+            ast::Expr::Error { .. } => {
+                return Err(CodegenError::InvalidCode {
+                    line: pos.0,
+                    column: pos.1,
+                })
+            }
+        };
+
+        Ok(Some(t))
+    }
+
+    /// Translates an expression which should have a value.
+    fn value(&mut self, expr: &ast::Expr) -> Result<ast::Type, CodegenError> {
+        match self.expr(expr)? {
+            Some(ast::Type::Error { .. }) | None => {
+                let span = expr.span();
+                Err(CodegenError::InvalidCode {
+                    line: span.line,
+                    column: span.column,
+                })
+            }
+            Some(t) => Ok(t),
+        }
+    }
+
+    /// Translates a binary operation (except logical ones, which short-circuit)
+    /// of `%rax` and `%rcx`, whose operands are of type `t`.
+    fn binary(&mut self, op: &ast::BinOp, t: ast::Type, span: Span) -> ast::Type {
+        use ast::BinOp as B;
+
+        let code = match op {
+            B::Add { .. } => "addq %rcx, %rax",
+            B::Sub { .. } => "subq %rcx, %rax",
+            B::Mul { .. } => "imulq %rcx, %rax",
+            B::BitAnd { .. } => "andq %rcx, %rax",
+            B::BitOr { .. } => "orq %rcx, %rax",
+            B::Div { .. } | B::Mod { .. } => {
+                self.line("movq %rax, %rdi".into());
+                self.line("movq %rcx, %rsi".into());
+                self.line(format!("movl ${}, %edx", op.span().line));
+                self.line(format!("movl ${}, %ecx", op.span().column));
+                self.call(if let B::Div { .. } = op {
+                    "kl_div"
+                } else {
+                    "kl_mod"
+                });
+                return t;
+            }
+            B::LogAnd { .. } | B::LogOr { .. } => unreachable!("Logical operations short-circuit"),
+            _ => {
+                self.line("cmpq %rcx, %rax".into());
+                self.line(format!("set{} %al", condition(op)));
+                self.line("movzbq %al, %rax".into());
+                return ast::Type::Bool { span };
+            }
+        };
+
+        self.line(code.into());
+        t
+    }
+
+    /// Translates a short-circuiting logical operation.
+    fn logical(
+        &mut self,
+        l: &ast::Expr,
+        op: &ast::BinOp,
+        r: &ast::Expr,
+    ) -> Result<ast::Type, CodegenError> {
+        let span = op.span();
+        let end = self.label();
+        // The right operand is skipped if the left one is false for `and`,
+        // or true for `or`.
+        let skip = if let ast::BinOp::LogAnd { .. } = op {
+            "jz"
+        } else {
+            "jnz"
+        };
+
+        self.value(l)?;
+        self.line("testq %rax, %rax".into());
+        self.line(format!("{} {}", skip, end));
+        self.value(r)?;
+        self.place(&end);
+
+        Ok(ast::Type::Bool { span })
+    }
+
+    /// Translates a call of the `println` builtin function.
+    /// All arguments are evaluated before printing.
+    fn println(&mut self, args: &[ast::Expr]) -> Result<(), CodegenError> {
+        let mut types = Vec::with_capacity(args.len());
+        for arg in args {
+            if let ast::Expr::LiteralStr { .. } = arg {
+                types.push(None);
+                continue;
+            }
+            types.push(Some(self.value(arg)?));
+            self.push();
+        }
+
+        let pushed = types.iter().flatten().count();
+        let mut printed = 0;
+        for (arg, t) in args.iter().zip(types) {
+            let routine = match (arg, t) {
+                (ast::Expr::LiteralStr { value, .. }, _) => {
+                    let label = self.string(value);
+                    self.line(format!("leaq {}(%rip), %rdi", label));
+                    self.line(format!("movq ${}, %rsi", value.len()));
+                    "kl_print_str"
+                }
+                (_, Some(t)) => {
+                    let offset = 8 * (pushed - printed - 1);
+                    self.line(format!("movq {}(%rsp), %rdi", offset));
+                    printed += 1;
+                    match t {
+                        ast::Type::Char { .. } => "kl_print_char",
+                        ast::Type::Bool { .. } => "kl_print_bool",
+                        _ => "kl_print_int",
+                    }
+                }
+                (_, None) => unreachable!("Arguments which are not literals are pushed"),
+            };
+            self.call(routine);
+        }
+        self.call("kl_println");
+
+        if pushed != 0 {
+            self.line(format!("addq ${}, %rsp", 8 * pushed));
+            self.depth -= pushed;
+        }
+        Ok(())
+    }
+
+    /// Calls a function whose `count` arguments are pushed to the stack.
+    /// The arguments are moved to registers and (from the seventh argument)
+    /// the stack, as the System V calling convention passes them.
+    fn call_function(&mut self, id: &str, count: usize) {
+        for (i, reg) in ARG_REGISTERS.iter().enumerate().take(count) {
+            self.line(format!("movq {}(%rsp), {}", 8 * (count - i - 1), reg));
+        }
+
+        let on_stack = count.saturating_sub(ARG_REGISTERS.len());
+        let padding = (self.depth + on_stack) % 2;
+        if padding != 0 {
+            self.line("subq $8, %rsp".into());
+        }
+        // Arguments are pushed again in reverse order, so the seventh one is on top.
+        for i in (ARG_REGISTERS.len()..count).rev() {
+            let offset = 8 * padding + 16 * (count - i - 1);
+            self.line(format!("pushq {}(%rsp)", offset));
+        }
+        self.line(format!("call fn_{}", id));
+
+        let size = count + on_stack + padding;
+        if size != 0 {
+            self.line(format!("addq ${}, %rsp", 8 * size));
+        }
+        self.depth -= count;
+    }
+
+    /// Calls a routine of the runtime, keeping the stack aligned to 16 bytes.
+    fn call(&mut self, routine: &str) {
+        if self.depth.is_multiple_of(2) {
+            self.line(format!("call {}", routine));
+        } else {
+            self.line("subq $8, %rsp".into());
+            self.line(format!("call {}", routine));
+            self.line("addq $8, %rsp".into());
+        }
+    }
+
+    /// Reports a runtime error at a position of the Kolang code and exits.
+    fn fail(&mut self, message: &str, pos: (usize, usize)) {
+        let label = self.string(message);
+        self.line(format!("movl ${}, %edi", pos.0));
+        self.line(format!("movl ${}, %esi", pos.1));
+        self.line(format!("leaq {}(%rip), %rdx", label));
+        self.line(format!("movq ${}, %rcx", message.len()));
+        self.call("kl_fail");
+    }
+
+    /// Returns from the current function with the value in `%rax`.
+    fn ret(&mut self) {
+        self.line("decq kl_depth(%rip)".into());
+        self.line("leave".into());
+        self.line("ret".into());
+    }
+
+    /// Pushes `%rax` to the stack.
+    fn push(&mut self) {
+        self.line("pushq %rax".into());
+        self.depth += 1;
+    }
+
+    /// Pops the top of the stack to a register.
+    fn pop(&mut self, reg: &str) {
+        self.line(format!("popq {}", reg));
+        self.depth -= 1;
+    }
+
+    /// Returns the address of the value on top of the stack.
+    fn top(&self) -> String {
+        format!("-{}(%rbp)", self.frame + 8 * self.depth)
+    }
+
+    /// Enters the next scope nested in the current scope.
+    fn enter(&mut self) {
+        let (parent, translated) = self.scopes.last_mut().expect("There is an open scope");
+        let scope = self.tree.scope(*parent).children[*translated];
+        *translated += 1;
+        self.scopes.push((scope, 0));
+    }
+
+    /// Returns to the enclosing scope.
+    fn exit(&mut self) {
+        self.scopes.pop();
+    }
+
+    /// Returns the address of a variable declared in the current scope.
+    fn declared(&self, id: &str) -> String {
+        let (scope, _) = self.scopes.last().expect("There is an open scope");
+        let symbol = self.tree.scope(*scope).symbols[id];
+        self.address(symbol)
+    }
+
+    /// Returns the address and type of the variable which an identifier,
+    /// assignment or array expression refers to.
+    fn lookup(&self, expr: &ast::Expr) -> Result<(String, ast::Type), CodegenError> {
+        let Some(symbol) = self.tree.resolution(expr) else {
+            let (ast::Expr::Identifier { id, span }
+            | ast::Expr::Assign { id, span, .. }
+            | ast::Expr::ArrayExpr { id, span, .. }) = expr
+            else {
+                unreachable!("Only variables are looked up");
+            };
+            return Err(CodegenError::UndefinedVariable {
+                id: id.clone(),
+                line: span.line,
+                column: span.column,
+            });
+        };
+
+        let t = self.tree.symbol(symbol).symbol_type.clone();
+        Ok((self.address(symbol), t.expect("Variables have types")))
+    }
+
+    /// Returns the address of the stack slot of a parameter or variable.
+    fn address(&self, symbol: SymbolId) -> String {
+        let slot = self
+            .layout
+            .slot(symbol)
+            .expect("Variables of the function have slots");
+        format!("-{}(%rbp)", 8 * (slot + 1))
+    }
+
+    /// Adds a string to the read-only data and returns its label.
+    fn string(&mut self, value: &str) -> String {
+        let label = self.label();
+        self.data.push_str(&format!(
+            "{}:\n{}.ascii {}\n",
+            label,
+            INDENT,
+            asm_string(value)
+        ));
+        label
+    }
+
+    /// Returns a new local label.
+    fn label(&mut self) -> String {
+        self.labels += 1;
+        format!(".L{}", self.labels)
+    }
+
+    /// Places a label at the current position of the code.
+    fn place(&mut self, label: &str) {
+        self.out.push_str(label);
+        self.out.push_str(":\n");
+    }
+
+    /// Writes an instruction.
+    fn line(&mut self, code: String) {
+        self.out.push_str(INDENT);
+        self.out.push_str(&code);
+        self.out.push('\n');
+    }
+}
+
+/// Returns the condition code of `set` which checks the result of a comparison.
+fn condition(op: &ast::BinOp) -> &'static str {
+    match op {
+        ast::BinOp::Eq { .. } => "e",
+        ast::BinOp::NEq { .. } => "ne",
+        ast::BinOp::LT { .. } => "l",
+        ast::BinOp::GT { .. } => "g",
+        ast::BinOp::LEq { .. } => "le",
+        ast::BinOp::GEq { .. } => "ge",
+        _ => unreachable!("Only comparisons have conditions"),
+    }
+}
+
+/// Checks whether values of a type can be stored in a stack slot.
+fn supported(t: &ast::Type) -> Result<(), CodegenError> {
+    match t {
+        ast::Type::Int { .. } | ast::Type::Char { .. } | ast::Type::Bool { .. } => Ok(()),
+        ast::Type::Error { span } => Err(CodegenError::InvalidCode {
+            line: span.line,
+            column: span.column,
+        }),
+        t => Err(unsupported(t)),
+    }
+}
+
+/// Returns the error of using values of an unsupported type.
+fn unsupported(t: &ast::Type) -> CodegenError {
+    let span = match t {
+        ast::Type::Int { span }
+        | ast::Type::Float { span }
+        | ast::Type::Char { span }
+        | ast::Type::Str { span }
+        | ast::Type::Bool { span }
+        | ast::Type::Array { span, .. }
+        | ast::Type::Error { span } => span,
+    };
+    let name = match t {
+        ast::Type::Array { .. } => "array".to_string(),
+        t => t.to_string(),
+    };
+    CodegenError::UnsupportedType {
+        name,
+        line: span.line,
+        column: span.column,
+    }
+}
+
+/// Returns a GNU `as` string literal of UTF-8 bytes. Bytes other than
+/// printable ASCII characters are written as octal escapes.
+fn asm_string(s: &str) -> String {
+    let mut literal = String::with_capacity(s.len() + 2);
+    literal.push('"');
+    for b in s.bytes() {
+        match b {
+            b'"' | b'\\' => literal.push_str(&format!("\\{:03o}", b)),
+            b' '..=b'~' => literal.push(b as char),
+            _ => literal.push_str(&format!("\\{:03o}", b)),
+        }
+    }
+    literal.push('"');
+    literal
+}
+
+/// Translates a type-checked Kolang program to x86-64 assembly for Linux,
+/// in GNU `as` syntax. The program starts at `_start`, which runs the
+/// `main` function. The runtime is included in the generated code, so it
+/// can be built with an assembler and a linker alone. `source_name` is the
+/// name of the Kolang source file, which is mentioned in runtime errors.
+///
+/// Stack slots of variables are laid out by [`semantic::layout`], so
+/// variables of the program are resolved first. The functions which are
+/// translated are selected by [`ast::definitions`].
+///
+/// # Errors
+/// Returns the first error found in the code. Code which passes the semantic
+/// checks only fails if it has no `main` function or uses values of types
+/// other than `int`, `char` and `bool` (except string literals which are
+/// printed by `println`).
+///
+/// # Examples
+///
+/// ```
+/// use lexer::Lexer;
+/// use parser::Parser;
+///
+/// let source = "fn main() { println(1 + 2); }".as_bytes();
+/// let (ast, _) = Parser::new(Lexer::new(source)).parse().unwrap();
+///
+/// let asm = codegen::x86_64::generate(&ast, "main.kol").unwrap();
+/// assert!(asm.contains("\nfn_main:\n"));
+/// assert!(asm.contains("addq %rcx, %rax\n"));
+/// ```
+pub fn generate(prog: &[ast::Stmt], source_name: &str) -> Result<String, CodegenError> {
+    let (tree, _) = semantic::resolver::resolve(prog);
+    let mut g = Generator::new(&tree);
+
+    // Function definitions and their scopes, in order.
+    let scopes = tree
+        .scope(tree.global())
+        .children
+        .iter()
+        .copied()
+        .filter(|&s| tree.scope(s).kind == ScopeKind::Function);
+    let definitions = ast::definitions(prog);
+    let functions: Vec<(&ast::Stmt, ScopeId)> = prog
+        .iter()
+        .filter(|s| matches!(s, ast::Stmt::FnDef { .. }))
+        .zip(scopes)
+        .filter(|(stmt, _)| definitions.iter().any(|f| std::ptr::eq(*f, *stmt)))
+        .collect();
+    for (stmt, _) in &functions {
+        let ast::Stmt::FnDef {
+            id,
+            params,
+            return_type,
+            ..
+        } = stmt
+        else {
+            unreachable!("Only functions are collected");
+        };
+
+        let signature = Signature {
+            params: params.iter().map(|(_, t)| t.clone()).collect(),
+            return_type: return_type.clone(),
+        };
+        g.functions.insert(id.clone(), signature);
+    }
+
+    let main = functions
+        .iter()
+        .find(|(f, _)| matches!(f, ast::Stmt::FnDef { id, .. } if id == "main"));
+    match main {
+        Some((ast::Stmt::FnDef { params, span, .. }, _)) if !params.is_empty() => {
+            return Err(CodegenError::WrongArgumentCount {
+                function: "main".into(),
+                expected: params.len(),
+                found: 0,
+                line: span.line,
+                column: span.column,
+            })
+        }
+        Some(_) => {}
+        None => return Err(CodegenError::NoMain),
+    }
+
+    for (f, scope) in &functions {
+        g.function(f, *scope)?;
+    }
+
+    Ok(format!(
+        "# Generated by kolang from {name}.\n\n{}\n{INDENT}.text\n{}\n{INDENT}.section .rodata\nkl_source:\n{INDENT}.ascii {name}\n{INDENT}.p2align 3\nkl_source_len:\n{INDENT}.quad {}\n{}",
+        RUNTIME,
+        g.out,
+        source_name.len(),
+        g.data,
+        name = asm_string(source_name),
+    ))
+}
+
+/// Builds an executable from assembly generated by [`generate`]. The code
+/// is assembled by `as` (or the `AS` environment variable) to an object file
+/// next to the output, which is linked by `ld` (or the `LD` environment
+/// variable) and removed.
+///
+/// # Errors
+/// Returns [`BuildError::ToolNotFound`] if the assembler or the linker cannot
+/// be run, and [`BuildError::ToolFailed`] if it rejects its input.
+pub fn build(code: &str, output: &Path) -> Result<(), BuildError> {
+    let assembler = std::env::var_os("AS").unwrap_or_else(|| OsString::from("as"));
+    let linker = std::env::var_os("LD").unwrap_or_else(|| OsString::from("ld"));
+
+    let mut object = output.as_os_str().to_owned();
+    object.push(".o");
+    let object = Path::new(&object);
+
+    let args = [
+        OsStr::new("--64"),
+        OsStr::new("-o"),
+        object.as_os_str(),
+        OsStr::new("-"),
+    ];
+    tool::run(&assembler, &args, code)?;
+    let linked = tool::run(
+        &linker,
+        &[OsStr::new("-o"), output.as_os_str(), object.as_os_str()],
+        "",
+    );
+    let removed = fs::remove_file(object);
+    linked?;
+    Ok(removed?)
+}
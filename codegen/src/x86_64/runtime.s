@@ -0,0 +1,296 @@
+# Kolang runtime for programs compiled to x86-64 assembly.
+#
+# The code generator copies this file to the top of every generated file, so
+# the output can be built with an assembler and a linker alone:
+# `as -o f.o f.s && ld -o f f.o`. Programs run on Linux and make system calls
+# directly, without a C library. The generated code defines `kl_source` and
+# `kl_source_len`, the name of the Kolang source file, which is mentioned in
+# runtime errors.
+#
+# Values behave like in the interpreter: integer arithmetic wraps around and
+# runtime errors exit with status 3. Routines follow the System V calling
+# convention; `kl_fail` never returns.
+
+    .set KL_MAX_CALL_DEPTH, 10000
+    .set KL_EXIT_RUNTIME, 3
+    .set KL_BUF_SIZE, 4096
+    .set SYS_WRITE, 1
+    .set SYS_EXIT, 60
+
+    # The stack is not executable.
+    .section .note.GNU-stack, "", @progbits
+
+    .data
+    .p2align 3
+# File descriptor which the output buffer is written to.
+kl_fd:
+    .quad 1
+
+    .bss
+    .p2align 3
+# Number of running functions.
+kl_depth:
+    .zero 8
+# Number of bytes in the output buffer.
+kl_buf_len:
+    .zero 8
+kl_buf:
+    .zero KL_BUF_SIZE
+
+    .section .rodata
+kl_msg_overflow:
+    .ascii "Stack overflow"
+    .set KL_MSG_OVERFLOW_LEN, . - kl_msg_overflow
+kl_msg_div_zero:
+    .ascii "Division by zero"
+    .set KL_MSG_DIV_ZERO_LEN, . - kl_msg_div_zero
+kl_runtime_error:
+    .ascii ": Runtime error: "
+    .set KL_RUNTIME_ERROR_LEN, . - kl_runtime_error
+kl_true:
+    .ascii "true"
+kl_false:
+    .ascii "false"
+kl_colon:
+    .ascii ":"
+kl_newline:
+    .ascii "\n"
+
+    .text
+    .globl _start
+_start:
+    call fn_main
+    call kl_flush
+    xorl %edi, %edi
+    movl $SYS_EXIT, %eax
+    syscall
+
+# Writes the output buffer to `kl_fd` and empties it.
+# Output which cannot be written is dropped.
+kl_flush:
+    movq kl_fd(%rip), %rdi
+    leaq kl_buf(%rip), %rsi
+    movq kl_buf_len(%rip), %rdx
+1:
+    testq %rdx, %rdx
+    jle 2f
+    movl $SYS_WRITE, %eax
+    syscall
+    testq %rax, %rax
+    jle 2f
+    addq %rax, %rsi
+    subq %rax, %rdx
+    jmp 1b
+2:
+    movq $0, kl_buf_len(%rip)
+    ret
+
+# Appends %rsi bytes at %rdi to the output buffer.
+kl_print_str:
+kl_write:
+    testq %rsi, %rsi
+    jz 2f
+    movq kl_buf_len(%rip), %rax
+    cmpq $KL_BUF_SIZE, %rax
+    jb 1f
+    pushq %rdi
+    pushq %rsi
+    call kl_flush
+    popq %rsi
+    popq %rdi
+    xorl %eax, %eax
+1:
+    leaq kl_buf(%rip), %rdx
+    movb (%rdi), %cl
+    movb %cl, (%rdx,%rax)
+    incq %rax
+    movq %rax, kl_buf_len(%rip)
+    incq %rdi
+    decq %rsi
+    jmp kl_write
+2:
+    ret
+
+# Reports a runtime error at line %rdi and column %rsi of the Kolang code,
+# with a message of %rcx bytes at %rdx, and exits.
+kl_fail:
+    # The routine never returns, so callee-saved registers are not restored.
+    movq %rdi, %r12
+    movq %rsi, %r13
+    movq %rdx, %r14
+    movq %rcx, %r15
+    call kl_flush
+    movq $2, kl_fd(%rip)
+    leaq kl_source(%rip), %rdi
+    movq kl_source_len(%rip), %rsi
+    call kl_write
+    leaq kl_colon(%rip), %rdi
+    movl $1, %esi
+    call kl_write
+    movq %r12, %rdi
+    call kl_print_int
+    leaq kl_colon(%rip), %rdi
+    movl $1, %esi
+    call kl_write
+    movq %r13, %rdi
+    call kl_print_int
+    leaq kl_runtime_error(%rip), %rdi
+    movl $KL_RUNTIME_ERROR_LEN, %esi
+    call kl_write
+    movq %r14, %rdi
+    movq %r15, %rsi
+    call kl_write
+    call kl_println
+    call kl_flush
+    movl $KL_EXIT_RUNTIME, %edi
+    movl $SYS_EXIT, %eax
+    syscall
+
+# Enters a function defined at line %rdi and column %rsi.
+kl_enter:
+    cmpq $KL_MAX_CALL_DEPTH, kl_depth(%rip)
+    jae 1f
+    incq kl_depth(%rip)
+    ret
+1:
+    leaq kl_msg_overflow(%rip), %rdx
+    movl $KL_MSG_OVERFLOW_LEN, %ecx
+    jmp kl_fail
+
+# Divides %rdi by %rsi. Division by zero fails at line %rdx and column %rcx.
+kl_div:
+    testq %rsi, %rsi
+    jz kl_fail_div
+    movq %rdi, %rax
+    # `idiv` faults on overflow, which only happens when dividing by -1.
+    cmpq $-1, %rsi
+    je 1f
+    cqto
+    idivq %rsi
+    ret
+1:
+    negq %rax
+    ret
+
+# Remainder of dividing %rdi by %rsi, like `kl_div`.
+kl_mod:
+    testq %rsi, %rsi
+    jz kl_fail_div
+    xorl %eax, %eax
+    cmpq $-1, %rsi
+    je 1f
+    movq %rdi, %rax
+    cqto
+    idivq %rsi
+    movq %rdx, %rax
+1:
+    ret
+
+kl_fail_div:
+    movq %rdx, %rdi
+    movq %rcx, %rsi
+    leaq kl_msg_div_zero(%rip), %rdx
+    movl $KL_MSG_DIV_ZERO_LEN, %ecx
+    jmp kl_fail
+
+# Prints the integer in %rdi.
+kl_print_int:
+    subq $24, %rsp
+    # Digits are written backwards, from the end of a 24 byte buffer.
+    leaq 24(%rsp), %rsi
+    movq %rdi, %rax
+    testq %rax, %rax
+    jns 1f
+    # The most negative integer stays the same, which is right as unsigned.
+    negq %rax
+1:
+    movl $10, %ecx
+2:
+    xorl %edx, %edx
+    divq %rcx
+    addb $0x30, %dl
+    decq %rsi
+    movb %dl, (%rsi)
+    testq %rax, %rax
+    jnz 2b
+    testq %rdi, %rdi
+    jns 3f
+    decq %rsi
+    movb $0x2D, (%rsi)
+3:
+    leaq 24(%rsp), %rax
+    subq %rsi, %rax
+    movq %rsi, %rdi
+    movq %rax, %rsi
+    call kl_write
+    addq $24, %rsp
+    ret
+
+# Prints the character in %edi encoded in UTF-8.
+kl_print_char:
+    subq $8, %rsp
+    movl %edi, %eax
+    cmpl $0x80, %edi
+    jae 1f
+    movb %al, (%rsp)
+    movl $1, %esi
+    jmp 4f
+1:
+    cmpl $0x800, %edi
+    jae 2f
+    shrl $6, %eax
+    orl $0xC0, %eax
+    movb %al, (%rsp)
+    movl $2, %esi
+    jmp 5f
+2:
+    cmpl $0x10000, %edi
+    jae 3f
+    shrl $12, %eax
+    orl $0xE0, %eax
+    movb %al, (%rsp)
+    movl $3, %esi
+    jmp 6f
+3:
+    shrl $18, %eax
+    orl $0xF0, %eax
+    movb %al, (%rsp)
+    movl %edi, %eax
+    shrl $12, %eax
+    andl $0x3F, %eax
+    orl $0x80, %eax
+    movb %al, 1(%rsp)
+    movl $4, %esi
+6:
+    movl %edi, %eax
+    shrl $6, %eax
+    andl $0x3F, %eax
+    orl $0x80, %eax
+    movb %al, -2(%rsp,%rsi)
+5:
+    movl %edi, %eax
+    andl $0x3F, %eax
+    orl $0x80, %eax
+    movb %al, -1(%rsp,%rsi)
+4:
+    movq %rsp, %rdi
+    call kl_write
+    addq $8, %rsp
+    ret
+
+# Prints the boolean in %dil.
+kl_print_bool:
+    testb %dil, %dil
+    jz 1f
+    leaq kl_true(%rip), %rdi
+    movl $4, %esi
+    jmp kl_write
+1:
+    leaq kl_false(%rip), %rdi
+    movl $5, %esi
+    jmp kl_write
+
+kl_println:
+    leaq kl_newline(%rip), %rdi
+    movl $1, %esi
+    jmp kl_write
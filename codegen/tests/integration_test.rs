@@ -0,0 +1,843 @@
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use codegen::{
+    c,
+    error::{BuildError, CodegenError},
+    wasm::{self, module},
+    x86_64,
+};
+use lexer::Lexer;
+use parser::{ast, Parser};
+
+fn parse(code: &str) -> Vec<ast::Stmt> {
+    let l = Lexer::new(code.as_bytes());
+    let (ast, diagnostics) = Parser::new(l).parse().unwrap();
+    assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+    ast
+}
+
+/// Returns a new path for an executable in the temporary directory.
+fn temp_path() -> PathBuf {
+    static COUNT: AtomicUsize = AtomicUsize::new(0);
+    let n = COUNT.fetch_add(1, Ordering::Relaxed);
+    std::env::temp_dir().join(format!("kolang-codegen-{}-{}", std::process::id(), n))
+}
+
+/// Code generator of a backend and the function which builds its output.
+type Backend = (
+    fn(&[ast::Stmt], &str) -> Result<String, CodegenError>,
+    fn(&str, &Path) -> Result<(), BuildError>,
+);
+
+const C: Backend = (c::generate, c::build);
+const X86_64: Backend = (x86_64::generate, x86_64::build);
+
+/// Builds the code to an executable with the C backend and runs it.
+/// Returns the exit code, standard output and standard error of the program.
+fn build_and_run(code: &str) -> (Option<i32>, String, String) {
+    build_and_run_on(C, code)
+}
+
+/// Builds the code to an executable with a backend and runs it.
+fn build_and_run_on((generate, build): Backend, code: &str) -> (Option<i32>, String, String) {
+    let generated = generate(&parse(code), "test.kol").unwrap();
+    let exe = temp_path();
+    build(&generated, &exe).unwrap();
+
+    let o = Command::new(&exe).output().unwrap();
+    std::fs::remove_file(&exe).unwrap();
+    (
+        o.status.code(),
+        String::from_utf8(o.stdout).unwrap(),
+        String::from_utf8(o.stderr).unwrap(),
+    )
+}
+
+/// Runs the code as an executable built with the C backend and with the
+/// interpreter, checks that they behave the same and returns the output.
+fn same_as_interpreter(code: &str) -> String {
+    same_as_interpreter_on(C, code)
+}
+
+/// Like [`same_as_interpreter`], with the executable built by a backend.
+fn same_as_interpreter_on(backend: Backend, code: &str) -> String {
+    let (status, out, err) = build_and_run_on(backend, code);
+
+    let mut expected = Vec::new();
+    let expected_r = interpreter::run(parse(code), &mut expected);
+
+    assert_eq!(out, String::from_utf8(expected).unwrap());
+    match expected_r {
+        Ok(_) => {
+            assert_eq!(status, Some(0));
+            assert_eq!(err, "");
+        }
+        Err(e) => {
+            let (line, column) = e.position().unwrap();
+            assert_eq!(status, Some(3));
+            assert_eq!(
+                err,
+                format!("test.kol:{line}:{column}: Runtime error: {e}\n")
+            );
+        }
+    }
+
+    out
+}
+
+#[test]
+fn readme_example() {
+    let out = same_as_interpreter(
+        include_str!("../../README.md")
+            .split("``` Rust")
+            .nth(1)
+            .unwrap()
+            .split("```")
+            .next()
+            .unwrap(),
+    );
+
+    assert_eq!(
+        out,
+        concat!(
+            "Addition of 5 and 10 is: 15\n",
+            "Maximum of 5 and 10 is: 10\n",
+            "Sum of numbers from 1 to 10 is: 55\n",
+        )
+    );
+}
+
+#[test]
+fn operators() {
+    let out = same_as_interpreter("fn main() {
+        println(7 + 2, ' ', 7 - 2, ' ', 7 * 2, ' ', 7 / 2, ' ', 7 % 2, ' ', -7, ' ', -7 / -2, ' ', -7 % 2);
+        println(7.5 + 2.0, ' ', 7.5 - 2.0, ' ', 7.5 * 2.0, ' ', 7.5 / 2.0, ' ', 7.5 % 2.0, ' ', -7.5);
+        println(12 & 10, ' ', 12 | 10, ' ', ~0, ' ', true & false, ' ', true | false);
+        println(1 < 2, ' ', 2 > 1, ' ', 2 <= 2, ' ', 1 >= 2, ' ', 'a' < 'b', ' ', 1.5 > 2.5);
+        println(1 == 1, ' ', \"a\" != \"b\", ' ', 'c' == 'c', ' ', [1, 2] == [1, 2], ' ', [1] != [1, 2]);
+        println(true and false, ' ', true or false, ' ', not true, ' ', false or true and false);
+        println(9223372036854775807 + 1, ' ', -(-9223372036854775807 - 1), ' ', (-9223372036854775807 - 1) / -1);
+    }");
+
+    assert_eq!(
+        out.lines().last(),
+        Some("-9223372036854775808 -9223372036854775808 -9223372036854775808")
+    );
+}
+
+#[test]
+fn values() {
+    let out = same_as_interpreter("fn main() {
+        println(0.1 + 0.2, ' ', 1.0, ' ', -0.0, ' ', 1e21, ' ', 1.5e-7, ' ', 123456.789, ' ', 1.0 / 3.0);
+        println(1.0 / 0.0, ' ', -1.0 / 0.0, ' ', 0.0 / 0.0, ' ', 2.5e300 * 1e10);
+        println('a', 'é', '€', '😀', ' ', \"Hello \\\"?? world\\\"\\n\", \"\", \"کلنگ\");
+        let i: int; let f: float; let c: char; let s: str; let b: bool; let a: int[];
+        println(i, ' ', f, ' ', c == '\\0', ' ', s, ' ', b, ' ', a);
+        println([1.5, 2.0], [true], ['k', 'o'], [\"a\", \"b\"], []);
+    }");
+
+    assert!(out.starts_with("0.30000000000000004 1 -0 1000000000000000000000 0.00000015 "));
+}
+
+#[test]
+fn evaluation_order() {
+    let out = same_as_interpreter(
+        "
+    fn f(name: str, n: int): int {
+        println(name);
+        return n;
+    }
+
+    fn main() {
+        let x: int = 1;
+        println(x + (x = 5), ' ', x, ' ', (x = 2) * x);
+        println(f(\"a\", 1) + f(\"b\", 2) * f(\"c\", 3));
+        println(\"args: \", f(\"d\", 4), f(\"e\", 5));
+        let a: int[] = [f(\"f\", 6), f(\"g\", 7)];
+        println(a[f(\"h\", 1)]);
+        if f(\"i\", 0) == 1 and f(\"j\", 1) == 1 { println(\"no\"); }
+        if f(\"k\", 1) == 1 or f(\"l\", 1) == 1 { println(\"yes\"); }
+        let n: int = 0;
+        while f(\"m\", n) < 2 and (n = n + 1) > 0 { }
+        println(f(\"n\", 1) / f(\"o\", 0));
+    }",
+    );
+
+    assert!(out.starts_with("6 5 4\na\nb\nc\n7\nd\ne\nargs: 45\n"));
+}
+
+#[test]
+fn loops_and_scopes() {
+    same_as_interpreter(
+        "
+    fn main() {
+        let x: int = 1;
+        {
+            let x: int = x + 1;
+            println(x);
+            for x = x to x + 2 {
+                let y: int = x * 10;
+                println(y);
+            }
+            println(x);
+        }
+        println(x);
+
+        for i = 9223372036854775806 to 9223372036854775807 println(i);
+        for i = 5 to 1 println(\"never\");
+        for i = 1 to 10 {
+            i = i + 3;
+            println(i);
+        }
+
+        let n: int = 10;
+        while n > 0 {
+            n = n - 3;
+            if n % 2 == 0 println(\"even \", n); else { println(\"odd \", n); }
+        }
+    }",
+    );
+}
+
+#[test]
+fn calls() {
+    same_as_interpreter(
+        "
+    fn fib(n: int): int {
+        if n < 2 { return n; }
+        return fib(n - 1) + fib(n - 2);
+    }
+
+    fn sum(a: float[]): float {
+        let s: float = 0.0;
+        for i = 0 to 2 s = s + a[i];
+        return s;
+    }
+
+    fn greet(name: str, c: char) {
+        println(\"Hello \", name, c);
+    }
+
+    fn main(): int {
+        println(fib(20));
+        println(sum([0.5, 1.0, 2.0]));
+        greet(\"Kolang\", '!');
+        return later();
+    }
+
+    fn later(): int { return 42; }
+    ",
+    );
+}
+
+#[test]
+fn runtime_errors() {
+    same_as_interpreter("fn main() { println(1); println(10 % (2 - 2)); }");
+    same_as_interpreter("fn main() { let a: int[] = [1, 2]; println(a[2]); }");
+    same_as_interpreter("fn main() { let a: int[] = [1, 2]; println(a[-1]); }");
+    same_as_interpreter("fn f(): int { if false { return 1; } } fn main() { f(); }");
+
+    let (status, out, err) =
+        build_and_run("fn f(n: int): int { return f(n + 1); } fn main() { f(0); }");
+    assert_eq!(status, Some(3));
+    assert_eq!(out, "");
+    assert_eq!(err, "test.kol:1:1: Runtime error: Stack overflow\n");
+}
+
+#[test]
+fn generated_code() {
+    let code = c::generate(
+        &parse("fn main() { let a: int = 2; println(\"?\", a / a, [1, 2]); }"),
+        "dir/\"name\".kol",
+    )
+    .unwrap();
+
+    assert!(code.contains(c::RUNTIME));
+    assert!(code.contains("#define KL_SOURCE \"dir/\\042name\\042.kol\"\n"));
+    assert!(code.ends_with(
+        "\
+static void fn_main(void);
+
+static void fn_main(void)
+{
+    kl_enter(1, 1);
+    int64_t v_a = INT64_C(2);
+    int64_t t1 = kl_div(v_a, v_a, 1, 44);
+    kl_print_str(kl_str_of(\"\\077\", 1));
+    kl_print_int(t1);
+    kl_print_array(kl_array_of(2, (kl_value[]){{.i = INT64_C(1)}, {.i = INT64_C(2)}}), KL_INT);
+    kl_println();
+    kl_leave();
+}
+
+int main(void)
+{
+    fn_main();
+    return 0;
+}
+"
+    ));
+
+    // The code is portable C99 which does not need any extension.
+    let mut cc = Command::new(std::env::var("CC").unwrap_or_else(|_| "cc".into()))
+        .args(["-std=c99", "-pedantic", "-Wall", "-Wextra", "-Werror"])
+        .args(["-fsyntax-only", "-x", "c", "-"])
+        .stdin(Stdio::piped())
+        .spawn()
+        .unwrap();
+    cc.stdin.take().unwrap().write_all(code.as_bytes()).unwrap();
+    assert!(cc.wait().unwrap().success());
+}
+
+#[test]
+fn errors() {
+    let generate = |code| c::generate(&parse(code), "test.kol");
+
+    assert_eq!(generate("fn f() { }"), Err(CodegenError::NoMain));
+    assert_eq!(
+        generate("fn main(a: int) { }"),
+        Err(CodegenError::WrongArgumentCount {
+            function: "main".into(),
+            expected: 1,
+            found: 0,
+            line: 1,
+            column: 1
+        })
+    );
+    assert_eq!(
+        generate("fn main() { println([[1], [2]]); }"),
+        Err(CodegenError::NestedArray {
+            line: 1,
+            column: 21
+        })
+    );
+    assert_eq!(
+        generate("fn main() { println(a); }"),
+        Err(CodegenError::UndefinedVariable {
+            id: "a".into(),
+            line: 1,
+            column: 21
+        })
+    );
+    assert_eq!(
+        generate("fn main() { f(1); } fn f() { }"),
+        Err(CodegenError::WrongArgumentCount {
+            function: "f".into(),
+            expected: 0,
+            found: 1,
+            line: 1,
+            column: 13
+        })
+    );
+
+    let e = c::build("int main(void) { return x; }", &temp_path()).unwrap_err();
+    assert!(e.to_string().starts_with("Program `"), "{}", e);
+}
+
+#[test]
+fn x86_64_programs() {
+    let readme = include_str!("../../README.md")
+        .split("``` Rust")
+        .nth(1)
+        .unwrap()
+        .split("```")
+        .next()
+        .unwrap();
+    same_as_interpreter_on(X86_64, readme);
+
+    let out = same_as_interpreter_on(
+        X86_64,
+        "fn main() {
+        println(7 + 2, ' ', 7 - 2, ' ', 7 * 2, ' ', 7 / 2, ' ', 7 % 2, ' ', -7, ' ', -7 / -2, ' ', -7 % 2);
+        println(12 & 10, ' ', 12 | 10, ' ', ~0, ' ', true & false, ' ', true | false);
+        println(1 < 2, ' ', 2 > 1, ' ', 2 <= 2, ' ', 1 >= 2, ' ', 'a' < 'b', ' ', 'c' == 'c', ' ', 1 != 1);
+        println(true and false, ' ', true or false, ' ', not true, ' ', false or true and false);
+        println('a', 'é', '€', '😀', \"\\\"کلنگ\\\"\", \"\");
+        let i: int; let c: char; let b: bool;
+        println(i, ' ', c == '\\0', ' ', b, ' ', 9223372036854775807 + 1, ' ', (-9223372036854775807 - 1) / -1);
+    }",
+    );
+    assert!(out.ends_with("0 true false -9223372036854775808 -9223372036854775808\n"));
+}
+
+#[test]
+fn x86_64_control_flow_and_calls() {
+    same_as_interpreter_on(
+        X86_64,
+        "
+    fn fib(n: int): int {
+        if n < 2 { return n; }
+        return fib(n - 1) + fib(n - 2);
+    }
+
+    fn weighted(a: int, b: int, c: int, d: int, e: int, f: int, g: int, h: int, k: char): int {
+        println(k);
+        return a + 2 * b + 3 * c + 4 * d + 5 * e + 6 * f + 7 * g + 8 * h;
+    }
+
+    fn f(name: char, n: int): int {
+        println(name);
+        return n;
+    }
+
+    fn main(): int {
+        let x: int = 1;
+        {
+            let x: int = x + 1;
+            for x = x to x + 2 {
+                let y: int = x * 10;
+                println(y);
+            }
+            println(x);
+        }
+        for i = 9223372036854775806 to 9223372036854775807 println(i);
+        for i = 1 to 10 {
+            i = i + 3;
+            println(i, ' ', weighted(i, 2, 3, 4, 5, 6, 7, f('w', 8), 'k'));
+        }
+
+        let n: int = 10;
+        while n > 0 {
+            n = n - 3;
+            if n % 2 == 0 println(\"even \", n); else { println(\"odd \", n); }
+        }
+
+        println(x + (x = 5), ' ', x, ' ', (x = 2) * x, ' ', fib(20));
+        if f('i', 0) == 1 and f('j', 1) == 1 { println(\"no\"); }
+        if f('k', 1) == 1 or f('l', 1) == 1 { println(\"yes\"); }
+        return 42;
+    }
+    ",
+    );
+}
+
+#[test]
+fn x86_64_runtime_errors() {
+    same_as_interpreter_on(X86_64, "fn main() { println(1); println(10 % (2 - 2)); }");
+    same_as_interpreter_on(X86_64, "fn main() { println(1 / 0); }");
+    same_as_interpreter_on(
+        X86_64,
+        "fn f(): int { if false { return 1; } } fn main() { f(); }",
+    );
+
+    let (status, out, err) = build_and_run_on(
+        X86_64,
+        "fn f(n: int): int { return f(n + 1); } fn main() { println(0); f(0); }",
+    );
+    assert_eq!(status, Some(3));
+    assert_eq!(out, "0\n");
+    assert_eq!(err, "test.kol:1:1: Runtime error: Stack overflow\n");
+}
+
+#[test]
+fn x86_64_errors() {
+    let generate = |code| x86_64::generate(&parse(code), "test.kol");
+
+    assert!(generate("fn main() { }").unwrap().starts_with(&format!(
+        "# Generated by kolang from \"test.kol\".\n\n{}",
+        x86_64::RUNTIME
+    )));
+    assert_eq!(generate("fn f() { }"), Err(CodegenError::NoMain));
+    assert_eq!(
+        generate("fn main() { let f: float = 1.5; }"),
+        Err(CodegenError::UnsupportedType {
+            name: "float".into(),
+            line: 1,
+            column: 20
+        })
+    );
+    assert_eq!(
+        generate("fn main() { println(\"a\" == \"b\"); }"),
+        Err(CodegenError::UnsupportedType {
+            name: "str".into(),
+            line: 1,
+            column: 21
+        })
+    );
+    assert_eq!(
+        generate("fn f(a: int[]) { } fn main() { }"),
+        Err(CodegenError::UnsupportedType {
+            name: "array".into(),
+            line: 1,
+            column: 9
+        })
+    );
+    assert_eq!(
+        generate("fn main() { println(a); }"),
+        Err(CodegenError::UndefinedVariable {
+            id: "a".into(),
+            line: 1,
+            column: 21
+        })
+    );
+
+    let e = x86_64::build("fn_main: jmp nowhere", &temp_path()).unwrap_err();
+    assert!(e.to_string().starts_with("Program `"), "{}", e);
+}
+
+/// Host of WebAssembly modules for Node.js, which runs the module at the
+/// path in its argument like an executable built from `test.kol`.
+const WASM_HOST: &str = r#"
+const fs = require("fs");
+let out = "";
+let memory;
+const str = (p) => {
+    const len = new DataView(memory.buffer).getUint32(p, true);
+    return Buffer.from(memory.buffer, p + 4, len).toString();
+};
+const fail = (line, column, message) => {
+    process.stdout.write(out);
+    process.stderr.write(`test.kol:${line}:${column}: Runtime error: ${message}\n`);
+    process.exit(3);
+};
+const kolang = {
+    print_int: (n) => { out += n; },
+    print_float: (x) => { out += isFinite(x) ? x : (x > 0 ? "inf" : "-inf"); },
+    print_char: (c) => { out += String.fromCodePoint(c); },
+    print_str: (p) => { out += str(p); },
+    print_bool: (b) => { out += b ? "true" : "false"; },
+    println: () => { out += "\n"; },
+    fmod: (a, b) => a % b,
+    fail: (line, column, p) => fail(line, column, str(p)),
+    fail_index: (line, column, len, index) => fail(line, column,
+        `Index out of bounds: the length is ${len} but the index is ${index}`),
+};
+const m = new WebAssembly.Module(fs.readFileSync(process.argv[2]));
+const instance = new WebAssembly.Instance(m, { kolang });
+memory = instance.exports.memory;
+instance.exports.main();
+process.stdout.write(out);
+"#;
+
+/// Node.js, if it is installed.
+fn node() -> Option<String> {
+    let node = std::env::var("NODE").unwrap_or_else(|_| "node".into());
+    Command::new(&node)
+        .arg("--version")
+        .output()
+        .ok()
+        .map(|_| node)
+}
+
+/// Translates the code to WebAssembly and runs it with the host above.
+/// Returns `None` if Node.js is not installed.
+fn wasm_run(code: &str) -> Option<(Option<i32>, String, String)> {
+    let node = node()?;
+    let module = wasm::generate(&parse(code)).unwrap();
+    let path = temp_path();
+    let host = path.with_extension("js");
+    std::fs::write(&path, module.to_bytes()).unwrap();
+    std::fs::write(&host, WASM_HOST).unwrap();
+
+    let o = Command::new(node).arg(&host).arg(&path).output().unwrap();
+    std::fs::remove_file(&path).unwrap();
+    std::fs::remove_file(&host).unwrap();
+    Some((
+        o.status.code(),
+        String::from_utf8(o.stdout).unwrap(),
+        String::from_utf8(o.stderr).unwrap(),
+    ))
+}
+
+/// Runs the WebAssembly translation of the code and checks that it behaves
+/// like the interpreter. Does nothing if Node.js is not installed.
+fn wasm_same_as_interpreter(code: &str) {
+    let Some((status, out, err)) = wasm_run(code) else {
+        return;
+    };
+
+    let mut expected = Vec::new();
+    let expected_r = interpreter::run(parse(code), &mut expected);
+    assert_eq!(out, String::from_utf8(expected).unwrap());
+    match expected_r {
+        Ok(_) => {
+            assert_eq!(status, Some(0), "{}", err);
+            assert_eq!(err, "");
+        }
+        Err(e) => {
+            let (line, column) = e.position().unwrap();
+            assert_eq!(status, Some(3));
+            assert_eq!(
+                err,
+                format!("test.kol:{line}:{column}: Runtime error: {e}\n")
+            );
+        }
+    }
+}
+
+/// Reads an unsigned LEB128 integer.
+fn read_u32(bytes: &mut &[u8]) -> u32 {
+    let mut n = 0;
+    for shift in (0..).step_by(7) {
+        let b = bytes[0];
+        *bytes = &bytes[1..];
+        n |= ((b & 0x7F) as u32) << shift;
+        if b & 0x80 == 0 {
+            break;
+        }
+    }
+    n
+}
+
+#[test]
+fn wasm_sections() {
+    let module = wasm::generate(&parse("fn main() { }")).unwrap();
+    let bytes = module.to_bytes();
+
+    #[rustfmt::skip]
+    assert_eq!(
+        bytes,
+        [
+            0x00, 0x61, 0x73, 0x6D, 0x01, 0x00, 0x00, 0x00,
+            // Types: [] -> [], [i32 i32] -> [], [i32 i32 i32] -> []
+            0x01, 0x0F, 0x03,
+            0x60, 0x00, 0x00, 0x60, 0x02, 0x7F, 0x7F, 0x00, 0x60, 0x03, 0x7F, 0x7F, 0x7F, 0x00,
+            // Imports: kolang.fail
+            0x02, 0x0F, 0x01,
+            0x06, b'k', b'o', b'l', b'a', b'n', b'g', 0x04, b'f', b'a', b'i', b'l', 0x00, 0x02,
+            // Functions: fn_main, kl_enter
+            0x03, 0x03, 0x02, 0x00, 0x01,
+            // Memory of 1 page
+            0x05, 0x03, 0x01, 0x00, 0x01,
+            // Globals: heap = 32, depth = 0
+            0x06, 0x0B, 0x02, 0x7F, 0x01, 0x41, 0x20, 0x0B, 0x7F, 0x01, 0x41, 0x00, 0x0B,
+            // Exports: main, memory
+            0x07, 0x11, 0x02,
+            0x04, b'm', b'a', b'i', b'n', 0x00, 0x01,
+            0x06, b'm', b'e', b'm', b'o', b'r', b'y', 0x02, 0x00,
+            // Code
+            0x0A, 0x2E, 0x02,
+            // fn_main: kl_enter(1, 1) and depth - 1
+            0x0F, 0x00, 0x41, 0x01, 0x41, 0x01, 0x10, 0x02,
+            0x23, 0x01, 0x41, 0x01, 0x6B, 0x24, 0x01, 0x0B,
+            // kl_enter: overflow check and depth + 1
+            0x1C, 0x00,
+            0x23, 0x01, 0x41, 0x90, 0xCE, 0x00, 0x4F, 0x04, 0x40,
+            0x20, 0x00, 0x20, 0x01, 0x41, 0x08, 0x10, 0x00, 0x00, 0x0B,
+            0x23, 0x01, 0x41, 0x01, 0x6A, 0x24, 0x01, 0x0B,
+            // Data: "Stack overflow" at 8
+            0x0B, 0x18, 0x01, 0x00, 0x41, 0x08, 0x0B, 0x12,
+            0x0E, 0x00, 0x00, 0x00,
+        ]
+        .into_iter()
+        .chain(*b"Stack overflow")
+        .collect::<Vec<u8>>()
+    );
+
+    // Sections are in order and their sizes match their contents.
+    let module = wasm::generate(&parse(
+        "fn main() { let a: float[] = [1.5]; println(a, \"x\", 1 / 2); }",
+    ))
+    .unwrap();
+    let bytes = module.to_bytes();
+    let mut rest = &bytes[module::HEADER.len()..];
+    let mut ids = Vec::new();
+    while !rest.is_empty() {
+        ids.push(rest[0]);
+        rest = &rest[1..];
+        let size = read_u32(&mut rest) as usize;
+        rest = &rest[size..];
+    }
+    use module::section::*;
+    assert_eq!(
+        ids,
+        [TYPE, IMPORT, FUNCTION, MEMORY, GLOBAL, EXPORT, CODE, DATA]
+    );
+}
+
+#[test]
+fn wasm_text() {
+    let module = wasm::generate(&parse(
+        "fn f(a: int, b: bool): float { return 0.5; } fn main() { let c: char = 'a'; println(c, f(1, true)); }",
+    ))
+    .unwrap();
+
+    assert_eq!(
+        module.to_string(),
+        r#"(module
+  (type (;0;) (func (param i64 i32) (result f64)))
+  (type (;1;) (func))
+  (type (;2;) (func (param i32 i32)))
+  (type (;3;) (func (param i32 i32 i32)))
+  (type (;4;) (func (param i32)))
+  (type (;5;) (func (param f64)))
+  (import "kolang" "fail" (func $fail (type 3)))
+  (import "kolang" "print_char" (func $print_char (type 4)))
+  (import "kolang" "print_float" (func $print_float (type 5)))
+  (import "kolang" "println" (func $println (type 1)))
+  (func $fn_f (type 0) (param i64 i32) (result f64)
+    i32.const 1
+    i32.const 1
+    call $kl_enter
+    f64.const 0.5
+    global.get $depth
+    i32.const 1
+    i32.sub
+    global.set $depth
+    return
+    i32.const 1
+    i32.const 1
+    i32.const 8
+    call $fail
+    unreachable
+  )
+  (func $fn_main (type 1)
+    (local i32 i32 f64)
+    i32.const 1
+    i32.const 46
+    call $kl_enter
+    i32.const 97
+    local.set 0
+    local.get 0
+    local.set 1
+    i64.const 1
+    i32.const 1
+    call $fn_f
+    local.set 2
+    local.get 1
+    call $print_char
+    local.get 2
+    call $print_float
+    call $println
+    global.get $depth
+    i32.const 1
+    i32.sub
+    global.set $depth
+  )
+  (func $kl_enter (type 2) (param i32 i32)
+    global.get $depth
+    i32.const 10000
+    i32.ge_u
+    if
+      local.get 0
+      local.get 1
+      i32.const 60
+      call $fail
+      unreachable
+    end
+    global.get $depth
+    i32.const 1
+    i32.add
+    global.set $depth
+  )
+  (memory (;0;) 1)
+  (global $heap (mut i32) (i32.const 80))
+  (global $depth (mut i32) (i32.const 0))
+  (export "main" (func $fn_main))
+  (export "memory" (memory 0))
+  (data (i32.const 8) "/\00\00\00Function `f` finished without returning a value\00\0e\00\00\00Stack overflow")
+)"#
+    );
+}
+
+#[test]
+fn wasm_programs() {
+    let readme = include_str!("../../README.md")
+        .split("``` Rust")
+        .nth(1)
+        .unwrap()
+        .split("```")
+        .next()
+        .unwrap();
+    wasm_same_as_interpreter(readme);
+
+    wasm_same_as_interpreter(
+        "fn main() {
+        println(7 + 2, ' ', 7 - 2, ' ', 7 * 2, ' ', 7 / 2, ' ', 7 % 2, ' ', -7, ' ', -7 / -2, ' ', -7 % 2);
+        println(12 & 10, ' ', 12 | 10, ' ', ~0, ' ', true & false, ' ', true | false);
+        println(1.5 + 2.25, ' ', 1.5 * -2.0, ' ', 7.5 / 2.0, ' ', 7.5 % 2.0, ' ', -0.5 < 0.25, ' ', 1.0 / 0.0);
+        println('a' < 'b', ' ', 'c' == 'c', ' ', \"ab\" == \"ab\", ' ', \"ab\" != \"ac\", ' ', [1, 2] == [1, 2]);
+        println([\"x\", \"y\"] == [\"x\", \"y\"], ' ', [1.5] != [2.5], ' ', ['a'] == ['b'], ' ', [true] == [true]);
+        println(true and false, ' ', true or false, ' ', not true, ' ', false or true and false);
+        println('a', 'é', '€', '😀', \"\\\"کلنگ\\\"\", \"\");
+        println([1, 2, 3], [1.5], ['a', 'b'], [\"s\", \"t\"], [true, false], [], 9223372036854775807 + 1);
+        let i: int; let f: float; let c: char; let b: bool; let s: str; let a: int[];
+        println(i, ' ', f, ' ', c == '\\0', ' ', b, ' ', s, ' ', a, ' ', (-9223372036854775807 - 1) / -1);
+    }",
+    );
+
+    wasm_same_as_interpreter(
+        "
+    fn fib(n: int): int {
+        if n < 2 { return n; }
+        return fib(n - 1) + fib(n - 2);
+    }
+
+    fn f(name: char, n: int): int {
+        println(name);
+        return n;
+    }
+
+    fn sum(a: int[]): int {
+        let s: int = 0;
+        for i = 0 to 2 { s = s + a[i]; }
+        return s;
+    }
+
+    fn main(): int {
+        let x: int = 1;
+        {
+            let x: int = x + 1;
+            for x = x to x + 2 {
+                let y: int = x * 10;
+                println(y);
+            }
+            println(x);
+        }
+        for i = 9223372036854775806 to 9223372036854775807 println(i);
+        let n: int = 10;
+        while n > 0 {
+            n = n - 3;
+            if n % 2 == 0 println(\"even \", n); else { println(\"odd \", n); }
+        }
+        let a: int[] = [fib(10), x, 3];
+        println(x + (x = 5), ' ', x, ' ', (x = 2) * x, ' ', sum(a), ' ', a[f('i', 1)]);
+        if f('i', 0) == 1 and f('j', 1) == 1 { println(\"no\"); }
+        if f('k', 1) == 1 or f('l', 1) == 1 { println(\"yes\"); }
+        return 42;
+    }
+    ",
+    );
+}
+
+#[test]
+fn wasm_runtime_errors() {
+    wasm_same_as_interpreter("fn main() { println(1); println(10 % (2 - 2)); }");
+    wasm_same_as_interpreter("fn main() { let a: int[] = [1, 2]; println(a[2]); }");
+    wasm_same_as_interpreter("fn main() { let a: str[] = [\"a\"]; println(a[-1]); }");
+    wasm_same_as_interpreter("fn f(): int { if false { return 1; } } fn main() { f(); }");
+
+    let run = wasm_run("fn f(n: int): int { return f(n + 1); } fn main() { println(0); f(0); }");
+    if let Some((status, out, err)) = run {
+        assert_eq!(status, Some(3));
+        assert_eq!(out, "0\n");
+        assert_eq!(err, "test.kol:1:1: Runtime error: Stack overflow\n");
+    }
+}
+
+#[test]
+fn wasm_errors() {
+    let generate = |code| wasm::generate(&parse(code));
+
+    assert_eq!(generate("fn f() { }").unwrap_err(), CodegenError::NoMain);
+    assert_eq!(
+        generate("fn main() { println([[1], [2]]); }").unwrap_err(),
+        CodegenError::NestedArray {
+            line: 1,
+            column: 21
+        }
+    );
+    assert_eq!(
+        generate("fn main() { println(a); }").unwrap_err(),
+        CodegenError::UndefinedVariable {
+            id: "a".into(),
+            line: 1,
+            column: 21
+        }
+    );
+}
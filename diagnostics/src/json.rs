@@ -0,0 +1,51 @@
+use serde_json::{json, Value};
+
+use crate::{Diagnostic, Span};
+
+/// Converts the diagnostic which was found in the file with provided name
+/// to a single-line JSON object, for tools which process compiler output.
+///
+/// # Examples
+///
+/// ```
+/// use diagnostics::{json, Diagnostic, Span};
+///
+/// let span = Span { start: 0, end: 1, line: 1, column: 1, end_line: 1, end_column: 2 };
+/// let d = Diagnostic::error("Invalid token `$`".into(), span);
+///
+/// assert_eq!(
+///     json::to_json(&d, "main.kol"),
+///     "{\"file\":\"main.kol\",\"severity\":\"error\",\"message\":\"Invalid token `$`\",\
+///      \"span\":{\"start\":0,\"end\":1,\"line\":1,\"column\":1,\"end_line\":1,\"end_column\":2},\
+///      \"expected\":[],\"labels\":[],\"notes\":[],\"help\":[]}"
+/// );
+/// ```
+pub fn to_json(diagnostic: &Diagnostic, file_name: &str) -> String {
+    let labels: Vec<Value> = diagnostic
+        .labels
+        .iter()
+        .map(|label| json!({ "span": span(&label.span), "message": label.message }))
+        .collect();
+    json!({
+        "file": file_name,
+        "severity": diagnostic.severity.to_string(),
+        "message": diagnostic.message,
+        "span": span(&diagnostic.span),
+        "expected": diagnostic.expected,
+        "labels": labels,
+        "notes": diagnostic.notes,
+        "help": diagnostic.help,
+    })
+    .to_string()
+}
+
+fn span(span: &Span) -> Value {
+    json!({
+        "start": span.start,
+        "end": span.end,
+        "line": span.line,
+        "column": span.column,
+        "end_line": span.end_line,
+        "end_column": span.end_column,
+    })
+}
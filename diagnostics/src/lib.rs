@@ -0,0 +1,133 @@
+#![warn(missing_docs)]
+
+//! # Kolang diagnostics
+//! Utilities for storing and reporting errors found in Kolang code.
+//! Shared by all stages of the compiler, so every stage reports consistently.
+
+use std::fmt;
+
+/// This module includes conversion of diagnostics to JSON for other tools.
+pub mod json;
+/// This module includes a renderer which prints diagnostics with source code snippets.
+pub mod render;
+/// This module includes source code ranges of tokens and syntax tree nodes.
+pub mod span;
+
+pub use render::{Renderer, SourceFile};
+pub use span::Span;
+
+/// Severity of a [`Diagnostic`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// The code is invalid and cannot be compiled.
+    Error,
+    /// The code is valid, but probably not what the author meant.
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Severity::Error => f.write_str("error"),
+            Severity::Warning => f.write_str("warning"),
+        }
+    }
+}
+
+/// A secondary message attached to a source code range, which explains
+/// a [`Diagnostic`] (e.g. "function declared here").
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Label {
+    /// Source code range which this label points to.
+    pub span: Span,
+    /// Message of the label.
+    pub message: String,
+}
+
+/// The `Diagnostic` struct stores an error or warning found in Kolang code.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// Human readable description of the problem.
+    pub message: String,
+    /// Source code range which caused the problem.
+    pub span: Span,
+    /// Severity of the problem.
+    pub severity: Severity,
+    /// Tokens (or token classes, like `identifier`) which were acceptable
+    /// at the position of the problem. Empty if not applicable.
+    pub expected: Vec<String>,
+    /// Secondary source code ranges related to the problem.
+    pub labels: Vec<Label>,
+    /// Additional information about the problem.
+    pub notes: Vec<String>,
+    /// Suggestions to fix the problem.
+    pub help: Vec<String>,
+}
+
+impl Diagnostic {
+    /// Creates a new error `Diagnostic` with provided message in specified span.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use diagnostics::{Diagnostic, Severity, Span};
+    ///
+    /// let span = Span { start: 0, end: 1, line: 1, column: 1, end_line: 1, end_column: 2 };
+    /// let d = Diagnostic::error("Expected `fn`".into(), span);
+    /// assert_eq!(d.severity, Severity::Error);
+    /// ```
+    pub fn error(message: String, span: Span) -> Self {
+        Self::new(message, span, Severity::Error)
+    }
+
+    /// Creates a new warning `Diagnostic` with provided message in specified span.
+    pub fn warning(message: String, span: Span) -> Self {
+        Self::new(message, span, Severity::Warning)
+    }
+
+    fn new(message: String, span: Span, severity: Severity) -> Self {
+        Self {
+            message,
+            span,
+            severity,
+            expected: Vec::new(),
+            labels: Vec::new(),
+            notes: Vec::new(),
+            help: Vec::new(),
+        }
+    }
+
+    /// Sets the expected tokens of the diagnostic.
+    pub fn with_expected(mut self, expected: Vec<String>) -> Self {
+        self.expected = expected;
+        self
+    }
+
+    /// Adds a secondary label to the diagnostic.
+    pub fn with_label(mut self, span: Span, message: String) -> Self {
+        self.labels.push(Label { span, message });
+        self
+    }
+
+    /// Adds a note to the diagnostic.
+    pub fn with_note(mut self, note: String) -> Self {
+        self.notes.push(note);
+        self
+    }
+
+    /// Adds a help message to the diagnostic.
+    pub fn with_help(mut self, help: String) -> Self {
+        self.help.push(help);
+        self
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}: {}: {}",
+            self.span.line, self.span.column, self.severity, self.message
+        )
+    }
+}
@@ -0,0 +1,286 @@
+use std::{
+    fmt::Write,
+    io::{self, IsTerminal},
+};
+
+use crate::{Diagnostic, Severity, Span};
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const RED: &str = "\x1b[1;31m";
+const YELLOW: &str = "\x1b[1;33m";
+const BLUE: &str = "\x1b[1;34m";
+
+/// A named piece of source code which diagnostics refer to.
+#[derive(Debug, Clone)]
+pub struct SourceFile<'a> {
+    name: &'a str,
+    source: &'a str,
+    line_starts: Vec<usize>,
+}
+
+impl<'a> SourceFile<'a> {
+    /// Creates a new `SourceFile` with provided name (usually the path of
+    /// the file) and contents.
+    pub fn new(name: &'a str, source: &'a str) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(source.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        Self {
+            name,
+            source,
+            line_starts,
+        }
+    }
+
+    /// Creates a new `SourceFile` whose contents are not available, like a
+    /// compiled file. Diagnostics in this file are rendered without snippets.
+    pub fn without_source(name: &'a str) -> Self {
+        Self {
+            name,
+            source: "",
+            line_starts: Vec::new(),
+        }
+    }
+
+    /// Returns name of the file.
+    pub fn name(&self) -> &str {
+        self.name
+    }
+
+    /// Returns an empty span at the specified (one-based) line and column.
+    /// Columns are counted in characters. Useful for errors which only
+    /// store a position, like runtime errors.
+    pub fn span_at(&self, line: usize, column: usize) -> Span {
+        let (start, text) = self.line(line);
+        let offset = text
+            .char_indices()
+            .nth(column.saturating_sub(1))
+            .map_or(text.len(), |(i, _)| i);
+        Span::empty(start + offset, line, column)
+    }
+
+    /// Returns byte offset of the start of specified (one-based) line and its text
+    /// without the line terminator. Lines after the end of file are empty.
+    fn line(&self, line: usize) -> (usize, &'a str) {
+        let Some(&start) = self.line_starts.get(line.saturating_sub(1)) else {
+            return (self.source.len(), "");
+        };
+        let end = self
+            .line_starts
+            .get(line)
+            .copied()
+            .unwrap_or(self.source.len());
+        let text = &self.source[start..end];
+        let text = text.strip_suffix('\n').unwrap_or(text);
+        let text = text.strip_suffix('\r').unwrap_or(text);
+        (start, text)
+    }
+}
+
+/// A source code range to be underlined in the rendered snippet.
+struct Annotation<'d> {
+    span: Span,
+    primary: bool,
+    message: Option<&'d str>,
+}
+
+/// The `Renderer` struct formats [`Diagnostic`]s like rustc does: a header with
+/// severity and message, location of the problem, the offending source lines
+/// with the spans underlined, and notes and help messages at the end.
+///
+/// # Examples
+///
+/// ```
+/// use diagnostics::{Diagnostic, Renderer, SourceFile, Span};
+///
+/// let file = SourceFile::new("main.kol", "fn main() {\n    x = 1;\n}\n");
+/// let span = Span { start: 16, end: 17, line: 2, column: 5, end_line: 2, end_column: 6 };
+/// let d = Diagnostic::error("Variable `x` is not defined".into(), span);
+///
+/// assert_eq!(
+///     Renderer::new().render(&d, &file),
+///     "error: Variable `x` is not defined\n \
+///      --> main.kol:2:5\n  \
+///       |\n\
+///      2 |     x = 1;\n  \
+///       |     ^\n"
+/// );
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Renderer {
+    color: bool,
+}
+
+impl Renderer {
+    /// Creates a new `Renderer` which produces plain text.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a new `Renderer` for printing to the standard error stream.
+    /// Output is colored only if stderr is a terminal and the `NO_COLOR`
+    /// environment variable is not set.
+    pub fn for_stderr() -> Self {
+        let color = io::stderr().is_terminal() && std::env::var_os("NO_COLOR").is_none();
+        Self { color }
+    }
+
+    /// Sets whether output should be colored with ANSI escape codes.
+    pub fn with_color(mut self, color: bool) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Renders the diagnostic which was found in `file`.
+    /// The result ends with a line break.
+    pub fn render(&self, diagnostic: &Diagnostic, file: &SourceFile) -> String {
+        let mut annotations = vec![Annotation {
+            span: diagnostic.span,
+            primary: true,
+            message: None,
+        }];
+        annotations.extend(diagnostic.labels.iter().map(|label| Annotation {
+            span: label.span,
+            primary: false,
+            message: Some(&label.message),
+        }));
+        annotations.sort_by_key(|a| (a.span.line, a.span.start));
+
+        let max_line = annotations.iter().map(|a| a.span.line).max().unwrap_or(1);
+        let width = max_line.to_string().len();
+        let pad = " ".repeat(width);
+
+        let severity_color = match diagnostic.severity {
+            Severity::Error => RED,
+            Severity::Warning => YELLOW,
+        };
+
+        let mut out = String::new();
+        let _ = writeln!(
+            out,
+            "{}{}",
+            self.paint(severity_color, &diagnostic.severity.to_string()),
+            self.paint(BOLD, &format!(": {}", diagnostic.message))
+        );
+        let _ = writeln!(
+            out,
+            "{}{} {}:{}:{}",
+            pad,
+            self.paint(BLUE, "-->"),
+            file.name,
+            diagnostic.span.line,
+            diagnostic.span.column
+        );
+        if !file.line_starts.is_empty() {
+            let _ = writeln!(out, "{} {}", pad, self.paint(BLUE, "|"));
+        } else {
+            annotations.clear();
+        }
+
+        let mut previous_line = None;
+        for annotation in &annotations {
+            let line_number = annotation.span.line;
+            let (line_start, text) = file.line(line_number);
+
+            if previous_line != Some(line_number) {
+                if previous_line.is_some_and(|p| line_number > p + 1) {
+                    let _ = writeln!(out, "{}", self.paint(BLUE, "..."));
+                }
+                let _ = writeln!(
+                    out,
+                    "{} {}{}{}",
+                    self.paint(BLUE, &format!("{:>width$}", line_number)),
+                    self.paint(BLUE, "|"),
+                    if text.is_empty() { "" } else { " " },
+                    text
+                );
+                previous_line = Some(line_number);
+            }
+
+            // Multi-line spans are underlined until the end of their first line.
+            let start = annotation
+                .span
+                .start
+                .saturating_sub(line_start)
+                .min(text.len());
+            let end = if annotation.span.end_line == line_number {
+                annotation.span.end.saturating_sub(line_start)
+            } else {
+                text.len()
+            }
+            .clamp(start, text.len());
+
+            let prefix: String = text
+                .get(..start)
+                .unwrap_or_default()
+                .chars()
+                .map(|c| if c == '\t' { '\t' } else { ' ' })
+                .collect();
+            let length = text.get(start..end).unwrap_or_default().chars().count();
+            let (marker, color) = if annotation.primary {
+                ("^", severity_color)
+            } else {
+                ("-", BLUE)
+            };
+            let mut underline = marker.repeat(length.max(1));
+            if let Some(message) = annotation.message {
+                underline.push(' ');
+                underline.push_str(message);
+            }
+            let _ = writeln!(
+                out,
+                "{} {} {}{}",
+                pad,
+                self.paint(BLUE, "|"),
+                prefix,
+                self.paint(color, &underline)
+            );
+        }
+
+        if !diagnostic.notes.is_empty() || !diagnostic.help.is_empty() {
+            let _ = writeln!(out, "{} {}", pad, self.paint(BLUE, "|"));
+        }
+        for note in &diagnostic.notes {
+            let _ = writeln!(
+                out,
+                "{} {} {}",
+                pad,
+                self.paint(BLUE, "="),
+                self.note("note", note)
+            );
+        }
+        for help in &diagnostic.help {
+            let _ = writeln!(
+                out,
+                "{} {} {}",
+                pad,
+                self.paint(BLUE, "="),
+                self.note("help", help)
+            );
+        }
+
+        out
+    }
+
+    /// Renders all diagnostics which were found in `file`, separated by empty lines.
+    pub fn render_all(&self, diagnostics: &[Diagnostic], file: &SourceFile) -> String {
+        diagnostics
+            .iter()
+            .map(|d| self.render(d, file))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn note(&self, kind: &str, message: &str) -> String {
+        format!("{}: {}", self.paint(BOLD, kind), message)
+    }
+
+    fn paint(&self, color: &str, text: &str) -> String {
+        if self.color {
+            format!("{}{}{}", color, text, RESET)
+        } else {
+            text.to_string()
+        }
+    }
+}
@@ -0,0 +1,74 @@
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Range of source code. `start` and `end` are byte offsets of the range
+/// (`end` is exclusive), and line/column pairs are one-based positions of
+/// the first character of the range and the character after its end.
+///
+/// # Examples
+///
+/// ```
+/// use diagnostics::Span;
+///
+/// // `fn main`
+/// let fn_span = Span { start: 0, end: 2, line: 1, column: 1, end_line: 1, end_column: 3 };
+/// let main_span = Span { start: 3, end: 7, line: 1, column: 4, end_line: 1, end_column: 8 };
+///
+/// let whole = fn_span.to(main_span);
+/// assert_eq!((whole.start, whole.end), (0, 7));
+/// assert_eq!((whole.line, whole.column, whole.end_line, whole.end_column), (1, 1, 1, 8));
+/// assert_eq!(whole.len(), 7);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Span {
+    /// Byte offset of the start of this span.
+    pub start: usize,
+    /// Byte offset right after the end of this span.
+    pub end: usize,
+    /// Line of code where this span starts.
+    pub line: usize,
+    /// Column of code where this span starts.
+    pub column: usize,
+    /// Line of code right after the end of this span.
+    pub end_line: usize,
+    /// Column of code right after the end of this span.
+    pub end_column: usize,
+}
+
+impl Span {
+    /// Creates a new empty `Span` at the provided position.
+    pub fn empty(offset: usize, line: usize, column: usize) -> Self {
+        Self {
+            start: offset,
+            end: offset,
+            line,
+            column,
+            end_line: line,
+            end_column: column,
+        }
+    }
+
+    /// Returns a `Span` which starts at the start of this span and
+    /// ends at the end of `other` span.
+    pub fn to(self, other: Span) -> Self {
+        Self {
+            start: self.start,
+            end: other.end,
+            line: self.line,
+            column: self.column,
+            end_line: other.end_line,
+            end_column: other.end_column,
+        }
+    }
+
+    /// Returns length of this span in bytes.
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    /// Checks whether this span is empty.
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+}
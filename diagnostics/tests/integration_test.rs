@@ -0,0 +1,169 @@
+use diagnostics::{Diagnostic, Renderer, SourceFile, Span};
+
+const CODE: &str = "fn add(a: int, b: int): int {\n\
+                    \treturn a + b;\n\
+                    }\n\
+                    \n\
+                    fn main() {\n    \
+                        add(1);\n\
+                    }\n";
+
+/// Creates a span from byte offsets in `CODE`.
+fn span(start: usize, end: usize) -> Span {
+    let position = |offset: usize| {
+        let before = &CODE[..offset];
+        let line = before.matches('\n').count() + 1;
+        let column = before.len() - before.rfind('\n').map_or(0, |i| i + 1) + 1;
+        (line, column)
+    };
+    let (line, column) = position(start);
+    let (end_line, end_column) = position(end);
+    Span {
+        start,
+        end,
+        line,
+        column,
+        end_line,
+        end_column,
+    }
+}
+
+fn find(s: &str) -> Span {
+    let start = CODE.find(s).unwrap();
+    span(start, start + s.len())
+}
+
+#[test]
+fn labels() {
+    let file = SourceFile::new("test.kol", CODE);
+    let d = Diagnostic::error(
+        "Function `add` takes 2 arguments but 1 was supplied".into(),
+        find("add(1)"),
+    )
+    .with_label(find("fn add"), "function declared here".into());
+
+    assert_eq!(
+        Renderer::new().render(&d, &file),
+        "\
+error: Function `add` takes 2 arguments but 1 was supplied
+ --> test.kol:6:5
+  |
+1 | fn add(a: int, b: int): int {
+  | ------ function declared here
+...
+6 |     add(1);
+  |     ^^^^^^
+"
+    );
+}
+
+#[test]
+fn notes_and_help() {
+    let file = SourceFile::new("test.kol", CODE);
+    let d = Diagnostic::warning(
+        "Function `add` may finish without returning a value".into(),
+        find("int {"),
+    )
+    .with_label(find("a: int"), "first parameter".into())
+    .with_note("Expected one of `int`, `float`".into())
+    .with_help("Add a `return` statement at the end of the function".into());
+
+    assert_eq!(
+        Renderer::new().render(&d, &file),
+        "\
+warning: Function `add` may finish without returning a value
+ --> test.kol:1:25
+  |
+1 | fn add(a: int, b: int): int {
+  |        ------ first parameter
+  |                         ^^^^^
+  |
+  = note: Expected one of `int`, `float`
+  = help: Add a `return` statement at the end of the function
+"
+    );
+}
+
+#[test]
+fn tabs_and_multiline_spans() {
+    let file = SourceFile::new("test.kol", CODE);
+
+    // Tabs before the span are kept, so carets line up with the code.
+    let d = Diagnostic::error("Bad operand".into(), find("a + b"));
+    assert!(Renderer::new()
+        .render(&d, &file)
+        .ends_with("2 | \treturn a + b;\n  | \t       ^^^^^\n"));
+
+    // Multi-line spans are underlined until the end of their first line.
+    let start = CODE.find("{\n\t").unwrap();
+    let d = Diagnostic::error(
+        "Bad block".into(),
+        span(start, CODE.find("}\n").unwrap() + 1),
+    );
+    assert!(Renderer::new()
+        .render(&d, &file)
+        .ends_with("1 | fn add(a: int, b: int): int {\n  |                             ^\n"));
+}
+
+#[test]
+fn empty_span() {
+    let file = SourceFile::new("test.kol", CODE);
+    let d = Diagnostic::error("Expected `}`".into(), span(CODE.len(), CODE.len()));
+
+    assert_eq!(
+        Renderer::new().render(&d, &file),
+        "\
+error: Expected `}`
+ --> test.kol:8:1
+  |
+8 |
+  | ^
+"
+    );
+}
+
+#[test]
+fn color() {
+    let file = SourceFile::new("test.kol", CODE);
+    let d = Diagnostic::error("Variable `b` is not defined".into(), find("b;"));
+    let rendered = Renderer::new().with_color(true).render(&d, &file);
+
+    assert!(
+        rendered.starts_with("\x1b[1;31merror\x1b[0m\x1b[1m: Variable `b` is not defined\x1b[0m\n")
+    );
+    assert!(rendered.contains("\x1b[1;31m^^\x1b[0m"));
+    assert!(!Renderer::new().render(&d, &file).contains('\x1b'));
+}
+
+#[test]
+fn render_all() {
+    let file = SourceFile::new("test.kol", CODE);
+    let diagnostics = [
+        Diagnostic::error("First".into(), find("fn")),
+        Diagnostic::error("Second".into(), find("main")),
+    ];
+    let rendered = Renderer::new().render_all(&diagnostics, &file);
+
+    assert_eq!(rendered.matches("error: ").count(), 2);
+    assert!(rendered.contains("  | ^^\n\nerror: Second\n"));
+}
+
+#[test]
+fn without_source() {
+    let file = SourceFile::without_source("prog.kbc");
+    let d = Diagnostic::error(
+        "Runtime error: Division by zero".into(),
+        file.span_at(12, 5),
+    )
+    .with_note("Source code is not available".into());
+
+    assert_eq!(
+        Renderer::new().render(&d, &file),
+        "\
+error: Runtime error: Division by zero
+  --> prog.kbc:12:5
+   |
+   = note: Source code is not available
+"
+    );
+}
@@ -0,0 +1,167 @@
+use std::{error, fmt, io};
+
+/// Errors which may occur while running Kolang code.
+#[derive(Debug)]
+pub enum RuntimeError {
+    /// Integer division or modulo by zero.
+    DivisionByZero {
+        /// Line of code where the operator is.
+        line: usize,
+        /// Column of code where the operator is.
+        column: usize,
+    },
+    /// Array index is negative or not less than array length.
+    IndexOutOfBounds {
+        /// The index.
+        index: i64,
+        /// Length of the array.
+        len: usize,
+        /// Line of code where the array is indexed.
+        line: usize,
+        /// Column of code where the array is indexed.
+        column: usize,
+    },
+    /// Function which has a return type finished without returning a value.
+    MissingReturn {
+        /// Name of the function.
+        function: String,
+        /// Line of code where the function is defined.
+        line: usize,
+        /// Column of code where the function is defined.
+        column: usize,
+    },
+    /// Variable is used before definition.
+    UndefinedVariable {
+        /// Name of the variable.
+        id: String,
+        /// Line of code where the variable is used.
+        line: usize,
+        /// Column of code where the variable is used.
+        column: usize,
+    },
+    /// Called function is not defined.
+    UndefinedFunction {
+        /// Name of the function.
+        id: String,
+        /// Line of code where the function is called.
+        line: usize,
+        /// Column of code where the function is called.
+        column: usize,
+    },
+    /// Function is called with wrong number of arguments.
+    WrongArgumentCount {
+        /// Name of the function.
+        function: String,
+        /// Number of function parameters.
+        expected: usize,
+        /// Number of provided arguments.
+        found: usize,
+        /// Line of code where the function is called.
+        line: usize,
+        /// Column of code where the function is called.
+        column: usize,
+    },
+    /// Value is not of the expected type.
+    TypeMismatch {
+        /// Description of the problem.
+        message: String,
+        /// Line of code where the value is used.
+        line: usize,
+        /// Column of code where the value is used.
+        column: usize,
+    },
+    /// Code contains a syntax error, so it cannot be run.
+    InvalidCode {
+        /// Line of code where the error is.
+        line: usize,
+        /// Column of code where the error is.
+        column: usize,
+    },
+    /// Too many nested function calls (usually infinite recursion).
+    StackOverflow {
+        /// Line of code where the function is called.
+        line: usize,
+        /// Column of code where the function is called.
+        column: usize,
+    },
+    /// There is no `main` function to run.
+    NoMain,
+    /// Something went wrong while writing the program output.
+    Io(io::Error),
+}
+
+impl RuntimeError {
+    /// Returns line and column of code where the error happened,
+    /// if the error is related to a position in code.
+    pub fn position(&self) -> Option<(usize, usize)> {
+        match self {
+            RuntimeError::DivisionByZero { line, column }
+            | RuntimeError::IndexOutOfBounds { line, column, .. }
+            | RuntimeError::MissingReturn { line, column, .. }
+            | RuntimeError::UndefinedVariable { line, column, .. }
+            | RuntimeError::UndefinedFunction { line, column, .. }
+            | RuntimeError::WrongArgumentCount { line, column, .. }
+            | RuntimeError::TypeMismatch { line, column, .. }
+            | RuntimeError::InvalidCode { line, column }
+            | RuntimeError::StackOverflow { line, column } => Some((*line, *column)),
+            RuntimeError::NoMain | RuntimeError::Io(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RuntimeError::DivisionByZero { .. } => f.write_str("Division by zero"),
+            RuntimeError::IndexOutOfBounds { index, len, .. } => write!(
+                f,
+                "Index out of bounds: the length is {len} but the index is {index}"
+            ),
+            RuntimeError::MissingReturn { function, .. } => {
+                write!(
+                    f,
+                    "Function `{function}` finished without returning a value"
+                )
+            }
+            RuntimeError::UndefinedVariable { id, .. } => {
+                write!(f, "Variable `{id}` is not defined")
+            }
+            RuntimeError::UndefinedFunction { id, .. } => {
+                write!(f, "Function `{id}` is not defined")
+            }
+            RuntimeError::WrongArgumentCount {
+                function,
+                expected,
+                found,
+                ..
+            } => {
+                let s = if *expected == 1 { "" } else { "s" };
+                let verb = if *found == 1 { "was" } else { "were" };
+                write!(
+                    f,
+                    "Function `{function}` takes {expected} argument{s} but {found} {verb} supplied"
+                )
+            }
+            RuntimeError::TypeMismatch { message, .. } => f.write_str(message),
+            RuntimeError::InvalidCode { .. } => f.write_str("Code contains syntax errors"),
+            RuntimeError::StackOverflow { .. } => f.write_str("Stack overflow"),
+            RuntimeError::NoMain => f.write_str("`main` function is not defined"),
+            RuntimeError::Io(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl error::Error for RuntimeError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            RuntimeError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for RuntimeError {
+    fn from(e: io::Error) -> Self {
+        RuntimeError::Io(e)
+    }
+}
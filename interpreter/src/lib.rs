@@ -0,0 +1,747 @@
+#![warn(missing_docs)]
+
+//! # Kolang interpreter
+//! Utilities for running Kolang code by walking its syntax tree.
+
+use std::{cmp::Ordering, collections::HashMap, io::Write, mem, rc::Rc};
+
+use parser::ast;
+
+/// This module includes the errors which may occur while running Kolang code.
+pub mod error;
+/// This module includes the runtime representation of Kolang values.
+pub mod value;
+
+use error::RuntimeError;
+use value::Value;
+
+/// Maximum number of nested function calls.
+pub const MAX_CALL_DEPTH: usize = 10_000;
+
+/// Recommended stack size of the thread which runs the interpreter.
+/// Each nested Kolang function call uses a few kilobytes of native stack,
+/// so default thread stacks are too small for [`MAX_CALL_DEPTH`] calls.
+pub const STACK_SIZE: usize = 256 * 1024 * 1024;
+
+/// Result of executing a statement.
+enum Flow {
+    /// Execution continues with the next statement.
+    Normal,
+    /// A `return` statement is executed.
+    Return(Value),
+    /// A `return` statement of a call of the running function is executed.
+    /// The function runs again with the arguments instead of calling itself,
+    /// so tail recursion does not grow the stack.
+    TailCall {
+        /// Arguments of the call.
+        args: Vec<Value>,
+        /// Line of code where the function is called.
+        line: usize,
+        /// Column of code where the function is called.
+        column: usize,
+    },
+}
+
+/// The `Interpreter<W>` struct runs Kolang functions and writes
+/// the program output to any byte sink which implements [`Write`] trait.
+///
+/// # Examples
+///
+/// ```
+/// use lexer::Lexer;
+/// use parser::Parser;
+/// use interpreter::Interpreter;
+///
+/// let source = "fn main() { println(\"Hello \", 6 * 7); }".as_bytes();
+/// let (ast, _) = Parser::new(Lexer::new(source)).parse().unwrap();
+///
+/// let mut out = Vec::new();
+/// let mut i = Interpreter::new(&mut out);
+/// i.define(ast);
+/// i.run_main().unwrap();
+///
+/// assert_eq!(out, b"Hello 42\n");
+/// ```
+pub struct Interpreter<W: Write> {
+    /// Defined functions (`FnDef` statements) by name.
+    functions: HashMap<String, Rc<ast::Stmt>>,
+    /// Variable scopes of the running function. Inner scopes are at the end.
+    scopes: Vec<HashMap<String, Value>>,
+    /// Number of nested function calls.
+    depth: usize,
+    /// Definition of the running function.
+    function: Option<Rc<ast::Stmt>>,
+    /// Program output.
+    out: W,
+}
+
+impl<W: Write> Interpreter<W> {
+    /// Creates a new `Interpreter<W>` with no defined functions,
+    /// which writes program output to `out`.
+    pub fn new(out: W) -> Self {
+        Self {
+            functions: HashMap::new(),
+            scopes: vec![HashMap::new()],
+            depth: 0,
+            function: None,
+            out,
+        }
+    }
+
+    /// Defines the functions of provided program. Redefines functions
+    /// which are already defined with the same name.
+    pub fn define(&mut self, prog: Vec<ast::Stmt>) {
+        for stmt in prog {
+            if let ast::Stmt::FnDef { id, .. } = &stmt {
+                self.functions.insert(id.clone(), Rc::new(stmt));
+            }
+        }
+    }
+
+    /// Calls the `main` function and returns its result.
+    ///
+    /// # Errors
+    /// Returns [`RuntimeError::NoMain`] if `main` is not defined,
+    /// or any error which happens while running the program.
+    pub fn run_main(&mut self) -> Result<Value, RuntimeError> {
+        let span = match self.functions.get("main").map(|f| f.as_ref()) {
+            Some(ast::Stmt::FnDef { span, .. }) => *span,
+            _ => return Err(RuntimeError::NoMain),
+        };
+
+        // Errors of the call itself point at the definition of `main`.
+        self.call("main", Vec::new(), span.line, span.column)
+    }
+
+    /// Executes a statement outside of functions, like the input of an
+    /// interactive session. Variables which the statement declares stay
+    /// defined for the next statements and expressions (but not for
+    /// functions).
+    ///
+    /// # Errors
+    /// Returns any error which happens while running the statement, or
+    /// [`RuntimeError::InvalidCode`] for `return` statements and function
+    /// definitions, which are defined with [`Interpreter::define`] instead.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use interpreter::{value::Value, Interpreter};
+    /// use lexer::Lexer;
+    /// use parser::Parser;
+    ///
+    /// let source = "let x: int = 6; x = x * 7;".as_bytes();
+    /// let (stmts, _) = Parser::new(Lexer::new(source)).parse_items().unwrap();
+    /// let (expr, _) = Parser::new(Lexer::new("x".as_bytes())).parse_expr().unwrap();
+    ///
+    /// let mut i = Interpreter::new(Vec::new());
+    /// for stmt in &stmts {
+    ///     i.run_stmt(stmt).unwrap();
+    /// }
+    /// assert_eq!(i.eval_expr(&expr).unwrap(), Value::Int(42));
+    /// ```
+    pub fn run_stmt(&mut self, stmt: &ast::Stmt) -> Result<(), RuntimeError> {
+        let flow = self.exec(stmt);
+        // Blocks which fail leave their scopes.
+        self.scopes.truncate(1);
+
+        match flow? {
+            Flow::Normal => Ok(()),
+            _ => {
+                let span = stmt.span();
+                Err(RuntimeError::InvalidCode {
+                    line: span.line,
+                    column: span.column,
+                })
+            }
+        }
+    }
+
+    /// Evaluates an expression outside of functions, where the variables
+    /// declared by [`Interpreter::run_stmt`] are visible.
+    ///
+    /// # Errors
+    /// Returns any error which happens while evaluating the expression.
+    pub fn eval_expr(&mut self, expr: &ast::Expr) -> Result<Value, RuntimeError> {
+        self.eval(expr)
+    }
+
+    /// Calls a function (user defined or builtin) with provided arguments.
+    /// `line` and `column` are the position of call in code.
+    fn call(
+        &mut self,
+        id: &str,
+        args: Vec<Value>,
+        line: usize,
+        column: usize,
+    ) -> Result<Value, RuntimeError> {
+        let f = match self.functions.get(id) {
+            Some(f) => Rc::clone(f),
+            None => return self.call_builtin(id, args, line, column),
+        };
+
+        let ast::Stmt::FnDef {
+            id,
+            params,
+            return_type,
+            body,
+            span: fn_span,
+        } = f.as_ref()
+        else {
+            unreachable!("Only functions are defined");
+        };
+
+        // Tail calls of the function run it again in this loop.
+        let (mut args, mut line, mut column) = (args, line, column);
+        loop {
+            if params.len() != args.len() {
+                return Err(RuntimeError::WrongArgumentCount {
+                    function: id.clone(),
+                    expected: params.len(),
+                    found: args.len(),
+                    line,
+                    column,
+                });
+            }
+
+            if self.depth >= MAX_CALL_DEPTH {
+                return Err(RuntimeError::StackOverflow { line, column });
+            }
+
+            let mut scope = HashMap::new();
+            for ((param, t), arg) in params.iter().zip(args) {
+                if !arg.is_of(t) {
+                    return Err(RuntimeError::TypeMismatch {
+                        message: format!(
+                            "Expected `{}` for parameter `{}`, found `{}`",
+                            t,
+                            param,
+                            arg.type_name()
+                        ),
+                        line,
+                        column,
+                    });
+                }
+                scope.insert(param.clone(), arg);
+            }
+
+            let caller_scopes = mem::replace(&mut self.scopes, vec![scope]);
+            let caller = self.function.replace(Rc::clone(&f));
+            self.depth += 1;
+            let flow = self.exec(body);
+            self.depth -= 1;
+            self.function = caller;
+            self.scopes = caller_scopes;
+
+            return match (flow?, return_type) {
+                (
+                    Flow::TailCall {
+                        args: a,
+                        line: l,
+                        column: c,
+                    },
+                    _,
+                ) => {
+                    (args, line, column) = (a, l, c);
+                    continue;
+                }
+                (Flow::Return(v), Some(t)) if v.is_of(t) => Ok(v),
+                (Flow::Return(v), Some(t)) => Err(RuntimeError::TypeMismatch {
+                    message: format!(
+                        "Function `{}` should return `{}`, but returned `{}`",
+                        id,
+                        t,
+                        v.type_name()
+                    ),
+                    line,
+                    column,
+                }),
+                (Flow::Return(_), None) => Err(RuntimeError::TypeMismatch {
+                    message: format!("Function `{}` should not return a value", id),
+                    line,
+                    column,
+                }),
+                (Flow::Normal, Some(_)) => Err(RuntimeError::MissingReturn {
+                    function: id.clone(),
+                    line: fn_span.line,
+                    column: fn_span.column,
+                }),
+                (Flow::Normal, None) => Ok(Value::Void),
+            };
+        }
+    }
+
+    /// Calls a builtin function.
+    fn call_builtin(
+        &mut self,
+        id: &str,
+        args: Vec<Value>,
+        line: usize,
+        column: usize,
+    ) -> Result<Value, RuntimeError> {
+        match id {
+            "println" => {
+                for arg in args {
+                    write!(self.out, "{}", arg)?;
+                }
+                writeln!(self.out)?;
+                Ok(Value::Void)
+            }
+            _ => Err(RuntimeError::UndefinedFunction {
+                id: id.to_string(),
+                line,
+                column,
+            }),
+        }
+    }
+
+    /// Executes a statement.
+    fn exec(&mut self, stmt: &ast::Stmt) -> Result<Flow, RuntimeError> {
+        match stmt {
+            ast::Stmt::Let { .. } => self.exec_let(stmt),
+            ast::Stmt::Expr { expr, .. } => {
+                self.eval(expr)?;
+                Ok(Flow::Normal)
+            }
+            ast::Stmt::If {
+                cond,
+                then_stmt,
+                else_stmt,
+                ..
+            } => {
+                if self.eval_cond(cond)? {
+                    self.exec(then_stmt)
+                } else if let Some(els) = else_stmt {
+                    self.exec(els)
+                } else {
+                    Ok(Flow::Normal)
+                }
+            }
+            ast::Stmt::While { cond, body, .. } => {
+                while self.eval_cond(cond)? {
+                    let flow = self.exec(body)?;
+                    if !matches!(flow, Flow::Normal) {
+                        return Ok(flow);
+                    }
+                }
+                Ok(Flow::Normal)
+            }
+            ast::Stmt::For { .. } => self.exec_for(stmt),
+            ast::Stmt::Return {
+                expr: ast::Expr::Call { id, args, span },
+                ..
+            } if self.is_tail_call(id) => {
+                let mut values = Vec::with_capacity(args.len());
+                for arg in args {
+                    values.push(self.eval(arg)?);
+                }
+                Ok(Flow::TailCall {
+                    args: values,
+                    line: span.line,
+                    column: span.column,
+                })
+            }
+            ast::Stmt::Return { expr, .. } => Ok(Flow::Return(self.eval(expr)?)),
+            ast::Stmt::Block { stmts, .. } => {
+                self.scopes.push(HashMap::new());
+
+                let mut flow = Flow::Normal;
+                for s in stmts {
+                    flow = self.exec(s)?;
+                    if !matches!(flow, Flow::Normal) {
+                        break;
+                    }
+                }
+
+                self.scopes.pop();
+                Ok(flow)
+            }
+            ast::Stmt::FnDef { span, .. } => Err(RuntimeError::InvalidCode {
+                line: span.line,
+                column: span.column,
+            }),
+            ast::Stmt::Empty { .. } => Ok(Flow::Normal),
+        }
+    }
+
+    /// Checks whether a `return` of a call is a tail call: the running
+    /// function, which has a return type, calls itself.
+    fn is_tail_call(&self, id: &str) -> bool {
+        matches!(
+            self.function.as_deref(),
+            Some(ast::Stmt::FnDef { id: f, return_type: Some(_), .. }) if f == id
+        )
+    }
+
+    /// Executes a `let` statement.
+    fn exec_let(&mut self, stmt: &ast::Stmt) -> Result<Flow, RuntimeError> {
+        let ast::Stmt::Let {
+            id,
+            var_type,
+            expr,
+            span,
+        } = stmt
+        else {
+            unreachable!();
+        };
+
+        let value = match expr {
+            Some(e) => self.eval(e)?,
+            None => Value::default_of(var_type).ok_or(RuntimeError::InvalidCode {
+                line: span.line,
+                column: span.column,
+            })?,
+        };
+
+        if !value.is_of(var_type) {
+            return Err(RuntimeError::TypeMismatch {
+                message: format!(
+                    "Expected `{}` for variable `{}`, found `{}`",
+                    var_type,
+                    id,
+                    value.type_name()
+                ),
+                line: span.line,
+                column: span.column,
+            });
+        }
+
+        self.scopes
+            .last_mut()
+            .expect("There is always a scope")
+            .insert(id.clone(), value);
+        Ok(Flow::Normal)
+    }
+
+    /// Executes a `for` loop. The range is inclusive and evaluated once.
+    /// The loop variable is incremented after each iteration.
+    fn exec_for(&mut self, stmt: &ast::Stmt) -> Result<Flow, RuntimeError> {
+        let ast::Stmt::For {
+            id,
+            start,
+            end,
+            body,
+            ..
+        } = stmt
+        else {
+            unreachable!();
+        };
+
+        let start = self.eval_int(start)?;
+        let end = self.eval_int(end)?;
+
+        self.scopes
+            .push(HashMap::from([(id.clone(), Value::Int(start))]));
+
+        let mut flow = Flow::Normal;
+        loop {
+            let i = match self.scopes.last().and_then(|s| s.get(id)) {
+                Some(Value::Int(i)) => *i,
+                _ => unreachable!("Loop variable is always int"),
+            };
+            if i > end {
+                break;
+            }
+
+            flow = self.exec(body)?;
+            if !matches!(flow, Flow::Normal) {
+                break;
+            }
+
+            match self.scopes.last_mut().and_then(|s| s.get_mut(id)) {
+                Some(Value::Int(i)) if *i < i64::MAX => *i += 1,
+                _ => break,
+            }
+        }
+
+        self.scopes.pop();
+        Ok(flow)
+    }
+
+    /// Evaluates an expression.
+    fn eval(&mut self, expr: &ast::Expr) -> Result<Value, RuntimeError> {
+        let v = match expr {
+            ast::Expr::LiteralInt { value, .. } => Value::Int(*value),
+            ast::Expr::LiteralStr { value, .. } => Value::Str(value.as_str().into()),
+            ast::Expr::LiteralChar { value, .. } => Value::Char(*value),
+            ast::Expr::LiteralFloat { value, .. } => Value::Float(*value),
+            ast::Expr::LiteralBool { value, .. } => Value::Bool(*value),
+            ast::Expr::LiteralArray { .. } => self.eval_array(expr)?,
+            ast::Expr::BinaryOp { l, op, r, .. } => match op {
+                ast::BinOp::LogAnd { .. } => Value::Bool(self.eval_cond(l)? && self.eval_cond(r)?),
+                ast::BinOp::LogOr { .. } => Value::Bool(self.eval_cond(l)? || self.eval_cond(r)?),
+                _ => {
+                    let l = self.eval(l)?;
+                    let r = self.eval(r)?;
+                    binary(op, l, r)?
+                }
+            },
+            ast::Expr::UnaryOp { op, expr, .. } => {
+                let v = self.eval(expr)?;
+                unary(op, v)?
+            }
+            ast::Expr::Identifier { id, span } => self.lookup(id, span.line, span.column)?.clone(),
+            ast::Expr::Call { id, args, span } => {
+                let mut values = Vec::with_capacity(args.len());
+                for arg in args {
+                    values.push(self.eval(arg)?);
+                }
+                self.call(id, values, span.line, span.column)?
+            }
+            ast::Expr::ArrayExpr { .. } => self.eval_index(expr)?,
+            ast::Expr::Assign { .. } => self.eval_assign(expr)?,
+            ast::Expr::Error { span } => {
+                return Err(RuntimeError::InvalidCode {
+                    line: span.line,
+                    column: span.column,
+                })
+            }
+        };
+
+        Ok(v)
+    }
+
+    /// Evaluates an array literal.
+    fn eval_array(&mut self, expr: &ast::Expr) -> Result<Value, RuntimeError> {
+        let ast::Expr::LiteralArray { elements, span } = expr else {
+            unreachable!();
+        };
+
+        let mut values = Vec::with_capacity(elements.len());
+        for e in elements {
+            let v = self.eval(e)?;
+            if let Some(first) = values.first() {
+                if mem::discriminant(first) != mem::discriminant(&v) {
+                    return Err(RuntimeError::TypeMismatch {
+                        message: "Array elements should be of the same type".into(),
+                        line: span.line,
+                        column: span.column,
+                    });
+                }
+            }
+            values.push(v);
+        }
+
+        Ok(Value::Array(values.into()))
+    }
+
+    /// Evaluates an array indexing expression.
+    fn eval_index(&mut self, expr: &ast::Expr) -> Result<Value, RuntimeError> {
+        let ast::Expr::ArrayExpr { id, index, span } = expr else {
+            unreachable!();
+        };
+
+        let index = self.eval_int(index)?;
+        let elements = match self.lookup(id, span.line, span.column)? {
+            Value::Array(elements) => elements,
+            v => {
+                return Err(RuntimeError::TypeMismatch {
+                    message: format!("Cannot index into `{}`", v.type_name()),
+                    line: span.line,
+                    column: span.column,
+                })
+            }
+        };
+
+        match usize::try_from(index).ok().and_then(|i| elements.get(i)) {
+            Some(v) => Ok(v.clone()),
+            None => Err(RuntimeError::IndexOutOfBounds {
+                index,
+                len: elements.len(),
+                line: span.line,
+                column: span.column,
+            }),
+        }
+    }
+
+    /// Evaluates an assignment expression.
+    fn eval_assign(&mut self, expr: &ast::Expr) -> Result<Value, RuntimeError> {
+        let ast::Expr::Assign { id, expr, span } = expr else {
+            unreachable!();
+        };
+
+        let v = self.eval(expr)?;
+        let var = self
+            .scopes
+            .iter_mut()
+            .rev()
+            .find_map(|s| s.get_mut(id))
+            .ok_or_else(|| RuntimeError::UndefinedVariable {
+                id: id.clone(),
+                line: span.line,
+                column: span.column,
+            })?;
+
+        if mem::discriminant(var) != mem::discriminant(&v) {
+            return Err(RuntimeError::TypeMismatch {
+                message: format!(
+                    "Cannot assign `{}` to variable `{}` of type `{}`",
+                    v.type_name(),
+                    id,
+                    var.type_name()
+                ),
+                line: span.line,
+                column: span.column,
+            });
+        }
+
+        *var = v.clone();
+        Ok(v)
+    }
+
+    /// Evaluates an expression which should be `bool`.
+    fn eval_cond(&mut self, expr: &ast::Expr) -> Result<bool, RuntimeError> {
+        match self.eval(expr)? {
+            Value::Bool(b) => Ok(b),
+            v => {
+                let span = expr.span();
+                let (line, column) = (span.line, span.column);
+                Err(RuntimeError::TypeMismatch {
+                    message: format!("Expected `bool`, found `{}`", v.type_name()),
+                    line,
+                    column,
+                })
+            }
+        }
+    }
+
+    /// Evaluates an expression which should be `int`.
+    fn eval_int(&mut self, expr: &ast::Expr) -> Result<i64, RuntimeError> {
+        match self.eval(expr)? {
+            Value::Int(n) => Ok(n),
+            v => {
+                let span = expr.span();
+                let (line, column) = (span.line, span.column);
+                Err(RuntimeError::TypeMismatch {
+                    message: format!("Expected `int`, found `{}`", v.type_name()),
+                    line,
+                    column,
+                })
+            }
+        }
+    }
+
+    /// Finds the value of a variable.
+    fn lookup(&self, id: &str, line: usize, column: usize) -> Result<&Value, RuntimeError> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|s| s.get(id))
+            .ok_or_else(|| RuntimeError::UndefinedVariable {
+                id: id.to_string(),
+                line,
+                column,
+            })
+    }
+}
+
+/// Applies a binary operator (except logical ones, which short-circuit) on values.
+fn binary(op: &ast::BinOp, l: Value, r: Value) -> Result<Value, RuntimeError> {
+    use ast::BinOp as B;
+    use Value::*;
+
+    let span = op.span();
+    let (line, column) = (span.line, span.column);
+
+    let v = match (op, &l, &r) {
+        (B::Add { .. }, Int(a), Int(b)) => Int(a.wrapping_add(*b)),
+        (B::Add { .. }, Float(a), Float(b)) => Float(a + b),
+        (B::Sub { .. }, Int(a), Int(b)) => Int(a.wrapping_sub(*b)),
+        (B::Sub { .. }, Float(a), Float(b)) => Float(a - b),
+        (B::Mul { .. }, Int(a), Int(b)) => Int(a.wrapping_mul(*b)),
+        (B::Mul { .. }, Float(a), Float(b)) => Float(a * b),
+        (B::Div { .. } | B::Mod { .. }, Int(_), Int(0)) => {
+            return Err(RuntimeError::DivisionByZero { line, column })
+        }
+        (B::Div { .. }, Int(a), Int(b)) => Int(a.wrapping_div(*b)),
+        (B::Div { .. }, Float(a), Float(b)) => Float(a / b),
+        (B::Mod { .. }, Int(a), Int(b)) => Int(a.wrapping_rem(*b)),
+        (B::Mod { .. }, Float(a), Float(b)) => Float(a % b),
+        (B::BitAnd { .. }, Int(a), Int(b)) => Int(a & b),
+        (B::BitAnd { .. }, Bool(a), Bool(b)) => Bool(a & b),
+        (B::BitOr { .. }, Int(a), Int(b)) => Int(a | b),
+        (B::BitOr { .. }, Bool(a), Bool(b)) => Bool(a | b),
+        (B::Eq { .. }, a, b) if mem::discriminant(a) == mem::discriminant(b) => Bool(a == b),
+        (B::NEq { .. }, a, b) if mem::discriminant(a) == mem::discriminant(b) => Bool(a != b),
+        (B::LT { .. } | B::GT { .. } | B::LEq { .. } | B::GEq { .. }, a, b) => {
+            let ord = match (a, b) {
+                (Int(a), Int(b)) => a.partial_cmp(b),
+                (Float(a), Float(b)) => a.partial_cmp(b),
+                (Char(a), Char(b)) => a.partial_cmp(b),
+                _ => return Err(operand_mismatch(op, &l, &r)),
+            };
+
+            Bool(match op {
+                B::LT { .. } => ord == Some(Ordering::Less),
+                B::GT { .. } => ord == Some(Ordering::Greater),
+                B::LEq { .. } => matches!(ord, Some(Ordering::Less | Ordering::Equal)),
+                _ => matches!(ord, Some(Ordering::Greater | Ordering::Equal)),
+            })
+        }
+        _ => return Err(operand_mismatch(op, &l, &r)),
+    };
+
+    Ok(v)
+}
+
+/// Applies a unary operator on a value.
+fn unary(op: &ast::UnOp, v: Value) -> Result<Value, RuntimeError> {
+    let v = match (op, v) {
+        (ast::UnOp::Neg { .. }, Value::Int(n)) => Value::Int(n.wrapping_neg()),
+        (ast::UnOp::Neg { .. }, Value::Float(n)) => Value::Float(-n),
+        (ast::UnOp::LogNot { .. }, Value::Bool(b)) => Value::Bool(!b),
+        (ast::UnOp::BitNot { .. }, Value::Int(n)) => Value::Int(!n),
+        (op, v) => {
+            let span = op.span();
+            let (line, column) = (span.line, span.column);
+            return Err(RuntimeError::TypeMismatch {
+                message: format!("Cannot apply `{}` to `{}`", op, v.type_name()),
+                line,
+                column,
+            });
+        }
+    };
+
+    Ok(v)
+}
+
+/// Creates the error of applying a binary operator on wrong operands.
+fn operand_mismatch(op: &ast::BinOp, l: &Value, r: &Value) -> RuntimeError {
+    let span = op.span();
+    let (line, column) = (span.line, span.column);
+
+    RuntimeError::TypeMismatch {
+        message: format!(
+            "Cannot apply `{}` to `{}` and `{}`",
+            op,
+            l.type_name(),
+            r.type_name()
+        ),
+        line,
+        column,
+    }
+}
+
+/// Runs the `main` function of provided program and writes its output to `out`.
+///
+/// # Errors
+/// Returns any error which happens while running the program.
+///
+/// # Examples
+///
+/// ```
+/// use lexer::Lexer;
+/// use parser::Parser;
+///
+/// let source = "fn main() { println(1 + 2); }".as_bytes();
+/// let (ast, _) = Parser::new(Lexer::new(source)).parse().unwrap();
+///
+/// let mut out = Vec::new();
+/// interpreter::run(ast, &mut out).unwrap();
+/// assert_eq!(out, b"3\n");
+/// ```
+pub fn run<W: Write>(prog: Vec<ast::Stmt>, out: W) -> Result<Value, RuntimeError> {
+    let mut i = Interpreter::new(out);
+    i.define(prog);
+    i.run_main()
+}
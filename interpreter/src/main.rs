@@ -0,0 +1,61 @@
+use std::{
+    fs,
+    io::{self, BufRead, Write},
+};
+
+use diagnostics::{Diagnostic, Renderer, SourceFile, Span};
+use lexer::Lexer;
+use parser::Parser;
+
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+fn main() -> io::Result<()> {
+    let args: Vec<String> = std::env::args().collect();
+
+    let path = if args.len() > 1 {
+        args[1].clone()
+    } else {
+        println!("⛏️  Kolang interpreter v{}\n", VERSION);
+        println!("Code file path (relative or absolute):");
+        print!(">>> ");
+        io::stdout().flush()?;
+
+        let mut buf = String::new();
+        io::stdin().lock().read_line(&mut buf)?;
+        buf.trim_end().to_string()
+    };
+
+    let source = fs::read(&path)?;
+    let l = Lexer::new(source.as_slice());
+    let mut p = Parser::new(l);
+
+    let (ast, diagnostics) = p.parse()?;
+
+    let text = String::from_utf8_lossy(&source);
+    let file = SourceFile::new(&path, &text);
+    let renderer = Renderer::for_stderr();
+    for d in &diagnostics {
+        eprintln!("{}", renderer.render(d, &file));
+    }
+    if !diagnostics.is_empty() {
+        std::process::exit(1);
+    }
+
+    let r = std::thread::Builder::new()
+        .stack_size(interpreter::STACK_SIZE)
+        .spawn(move || interpreter::run(ast, io::stdout().lock()).map(|_| ()))?
+        .join()
+        .expect("Interpreter thread panicked");
+
+    if let Err(e) = r {
+        let span = match e.position() {
+            Some((line, column)) => file.span_at(line, column),
+            None => Span::empty(0, 1, 1),
+        };
+        let d = Diagnostic::error(format!("Runtime error: {}", e), span);
+        eprintln!("{}", renderer.render(&d, &file));
+        std::process::exit(1);
+    }
+
+    Ok(())
+}
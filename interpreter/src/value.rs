@@ -0,0 +1,89 @@
+use std::{fmt, rc::Rc};
+
+use parser::ast;
+
+/// The `Value` enum stores the runtime value of a Kolang expression.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    /// `int` value
+    Int(i64),
+    /// `float` value
+    Float(f64),
+    /// `char` value
+    Char(char),
+    /// `str` value
+    Str(Rc<str>),
+    /// `bool` value
+    Bool(bool),
+    /// Array value. Arrays are immutable, so elements are shared between copies.
+    Array(Rc<[Value]>),
+    /// Result of calling a function which returns nothing.
+    Void,
+}
+
+impl Value {
+    /// Returns the default value of a variable of provided type,
+    /// which is used when a variable is defined without initial value.
+    pub fn default_of(t: &ast::Type) -> Option<Self> {
+        match t {
+            ast::Type::Int { .. } => Some(Value::Int(0)),
+            ast::Type::Float { .. } => Some(Value::Float(0.0)),
+            ast::Type::Char { .. } => Some(Value::Char('\0')),
+            ast::Type::Str { .. } => Some(Value::Str("".into())),
+            ast::Type::Bool { .. } => Some(Value::Bool(false)),
+            ast::Type::Array { .. } => Some(Value::Array(Rc::new([]))),
+            ast::Type::Error { .. } => None,
+        }
+    }
+
+    /// Checks whether the value is of provided type.
+    pub fn is_of(&self, t: &ast::Type) -> bool {
+        match (self, t) {
+            (Value::Int(_), ast::Type::Int { .. })
+            | (Value::Float(_), ast::Type::Float { .. })
+            | (Value::Char(_), ast::Type::Char { .. })
+            | (Value::Str(_), ast::Type::Str { .. })
+            | (Value::Bool(_), ast::Type::Bool { .. }) => true,
+            (Value::Array(elements), ast::Type::Array { element_type, .. }) => {
+                elements.iter().all(|e| e.is_of(element_type))
+            }
+            _ => false,
+        }
+    }
+
+    /// Returns the name of the value type, as written in Kolang code.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Int(_) => "int",
+            Value::Float(_) => "float",
+            Value::Char(_) => "char",
+            Value::Str(_) => "str",
+            Value::Bool(_) => "bool",
+            Value::Array(_) => "array",
+            Value::Void => "nothing",
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Int(n) => write!(f, "{}", n),
+            Value::Float(n) => write!(f, "{}", n),
+            Value::Char(c) => write!(f, "{}", c),
+            Value::Str(s) => write!(f, "{}", s),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::Array(elements) => {
+                write!(f, "[")?;
+                for (i, e) in elements.iter().enumerate() {
+                    write!(f, "{}", e)?;
+                    if i != elements.len() - 1 {
+                        write!(f, ", ")?;
+                    }
+                }
+                write!(f, "]")
+            }
+            Value::Void => write!(f, "()"),
+        }
+    }
+}
@@ -0,0 +1,339 @@
+use interpreter::{error::RuntimeError, value::Value};
+use lexer::Lexer;
+use parser::Parser;
+
+fn run(code: &str) -> (Result<Value, RuntimeError>, String) {
+    let l = Lexer::new(code.as_bytes());
+    let (ast, diagnostics) = Parser::new(l).parse().unwrap();
+    assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+
+    let mut out = Vec::new();
+    let r = interpreter::run(ast, &mut out);
+
+    (r, String::from_utf8(out).unwrap())
+}
+
+#[test]
+fn readme_example() {
+    let (r, out) = run(include_str!("../../README.md")
+        .split("``` Rust")
+        .nth(1)
+        .unwrap()
+        .split("```")
+        .next()
+        .unwrap());
+
+    assert!(r.is_ok());
+    assert_eq!(
+        out,
+        concat!(
+            "Addition of 5 and 10 is: 15\n",
+            "Maximum of 5 and 10 is: 10\n",
+            "Sum of numbers from 1 to 10 is: 55\n",
+        )
+    );
+}
+
+#[test]
+fn operators() {
+    let (r, out) = run("fn main() {
+        println(7 + 2, ' ', 7 - 2, ' ', 7 * 2, ' ', 7 / 2, ' ', 7 % 2, ' ', -7);
+        println(7.5 + 2.0, ' ', 7.5 - 2.0, ' ', 7.5 * 2.0, ' ', 7.5 / 2.0, ' ', -7.5);
+        println(12 & 10, ' ', 12 | 10, ' ', ~0, ' ', true & false, ' ', true | false);
+        println(1 < 2, ' ', 2 > 1, ' ', 2 <= 2, ' ', 1 >= 2, ' ', 'a' < 'b', ' ', 1.5 > 2.5);
+        println(1 == 1, ' ', \"a\" != \"b\", ' ', 'c' == 'c', ' ', [1, 2] == [1, 2]);
+        println(true and false, ' ', true or false, ' ', not true);
+    }");
+
+    assert!(r.is_ok());
+    assert_eq!(
+        out,
+        concat!(
+            "9 5 14 3 1 -7\n",
+            "9.5 5.5 15 3.75 -7.5\n",
+            "8 14 -1 false true\n",
+            "true true true false true false\n",
+            "true true true true\n",
+            "false true false\n",
+        )
+    );
+}
+
+#[test]
+fn short_circuit() {
+    let (r, out) = run("fn side(b: bool): bool {
+        println(\"side\");
+        return b;
+    }
+
+    fn main() {
+        if false and side(true) { println(\"unreachable\"); }
+        if true or side(true) { println(\"done\"); }
+    }");
+
+    assert!(r.is_ok());
+    assert_eq!(out, "done\n");
+}
+
+#[test]
+fn loops_and_scopes() {
+    let (r, out) = run("fn main() {
+        let i: int = 100;
+        for i = 1 to 3 {
+            let sq: int = i * i;
+            println(sq);
+        }
+        while i > 98 {
+            i = i - 1;
+        }
+        println(i);
+        for j = 5 to 1 println(\"never\");
+    }");
+
+    assert!(r.is_ok());
+    assert_eq!(out, "1\n4\n9\n98\n");
+}
+
+#[test]
+fn arrays() {
+    let (r, out) = run("fn sum(arr: int[], n: int): int {
+        let s: int = 0;
+        for i = 0 to n - 1 s = s + arr[i];
+        return s;
+    }
+
+    fn main() {
+        let a: int[] = [1, 2, 3, 4];
+        let words: str[] = [\"salam\", \"donya\"];
+        let empty: float[];
+        println(sum(a, 4), ' ', words[1], ' ', a, ' ', empty);
+    }");
+
+    assert!(r.is_ok());
+    assert_eq!(out, "10 donya [1, 2, 3, 4] []\n");
+}
+
+#[test]
+fn recursion() {
+    let (r, out) = run("fn fib(n: int): int {
+        if n < 2 return n;
+        return fib(n - 1) + fib(n - 2);
+    }
+
+    fn main(): int {
+        println(fib(15));
+        return fib(10);
+    }");
+
+    assert_eq!(r.unwrap(), Value::Int(55));
+    assert_eq!(out, "610\n");
+}
+
+#[test]
+fn division_by_zero() {
+    let (r, out) = run("fn main() {
+        println(\"before\");
+        let a: int = 0;
+        println(1 / a);
+    }");
+
+    assert!(matches!(
+        r,
+        Err(RuntimeError::DivisionByZero {
+            line: 4,
+            column: 19
+        })
+    ));
+    assert_eq!(out, "before\n");
+
+    let (r, _) = run("fn main() { println(1 % 0); }");
+    assert!(matches!(
+        r,
+        Err(RuntimeError::DivisionByZero {
+            line: 1,
+            column: 23
+        })
+    ));
+}
+
+#[test]
+fn index_out_of_bounds() {
+    let (r, _) = run("fn main() {
+        let a: int[] = [1, 2, 3];
+        println(a[3]);
+    }");
+
+    assert!(matches!(
+        r,
+        Err(RuntimeError::IndexOutOfBounds {
+            index: 3,
+            len: 3,
+            line: 3,
+            column: 17
+        })
+    ));
+
+    let (r, _) = run("fn main() { let a: int[] = [1]; println(a[-1]); }");
+    assert!(matches!(
+        r,
+        Err(RuntimeError::IndexOutOfBounds { index: -1, .. })
+    ));
+}
+
+#[test]
+fn missing_return() {
+    let (r, _) = run("fn f(a: int): int {
+        if a > 0 return a;
+    }
+
+    fn main() {
+        f(1);
+        f(0);
+    }");
+
+    match r {
+        Err(RuntimeError::MissingReturn {
+            function,
+            line,
+            column,
+        }) => {
+            assert_eq!(function, "f");
+            assert_eq!((line, column), (1, 1));
+        }
+        _ => panic!("Expected missing return error"),
+    }
+}
+
+#[test]
+fn type_errors() {
+    let (r, _) = run("fn main() { println(1 + \"a\"); }");
+    match r {
+        Err(e @ RuntimeError::TypeMismatch { .. }) => {
+            assert_eq!(e.to_string(), "Cannot apply `+` to `int` and `str`");
+            assert_eq!(e.position(), Some((1, 23)));
+        }
+        _ => panic!("Expected type mismatch"),
+    }
+
+    let (r, _) = run("fn main() { if 1 { } }");
+    assert!(matches!(
+        r,
+        Err(RuntimeError::TypeMismatch {
+            line: 1,
+            column: 16,
+            ..
+        })
+    ));
+
+    let (r, _) = run("fn main() { let a: int = 'a'; }");
+    assert!(matches!(r, Err(RuntimeError::TypeMismatch { .. })));
+}
+
+#[test]
+fn call_errors() {
+    let (r, _) = run("fn f(a: int) {} fn main() { f(1, 2); }");
+    assert!(matches!(
+        r,
+        Err(RuntimeError::WrongArgumentCount {
+            expected: 1,
+            found: 2,
+            ..
+        })
+    ));
+
+    let (r, _) = run("fn main() { g(); }");
+    assert!(matches!(r, Err(RuntimeError::UndefinedFunction { .. })));
+
+    let (r, _) = run("fn main() { x = 1; }");
+    assert!(matches!(r, Err(RuntimeError::UndefinedVariable { .. })));
+
+    let (r, _) = run("fn not_main() {}");
+    assert!(matches!(r, Err(RuntimeError::NoMain)));
+
+    // Code which is not checked may define `main` with parameters.
+    let (r, _) = run("fn f() {}\nfn main(x: int) {}");
+    let e = r.unwrap_err();
+    assert!(matches!(
+        e,
+        RuntimeError::WrongArgumentCount {
+            expected: 1,
+            found: 0,
+            ..
+        }
+    ));
+    assert_eq!(e.position(), Some((2, 1)));
+    assert_eq!(
+        e.to_string(),
+        "Function `main` takes 1 argument but 0 were supplied"
+    );
+}
+
+#[test]
+fn stack_overflow() {
+    let r = std::thread::Builder::new()
+        .stack_size(interpreter::STACK_SIZE)
+        .spawn(|| {
+            let (r, out) = run("fn down(n: int): int {
+                if n == 0 return 0;
+                return down(n - 1) + 1;
+            }
+
+            fn inf(n: int): int { return inf(n + 1) + 1; }
+
+            fn main(): int {
+                println(down(5000));
+                return inf(0);
+            }");
+            (r.map(|_| ()), out)
+        })
+        .unwrap()
+        .join()
+        .unwrap();
+
+    assert!(matches!(
+        r.0,
+        Err(RuntimeError::StackOverflow {
+            line: 6,
+            column: 42
+        })
+    ));
+    assert_eq!(r.1, "5000\n");
+}
+
+#[test]
+fn tail_calls() {
+    // Tail calls of the running function do not use the stack, so this
+    // runs on the default thread stack too.
+    let (r, out) = run("fn sum(n: int, acc: int): int {
+        if n == 0 return acc;
+        return sum(n - 1, acc + n);
+    }
+
+    fn gcd(a: int, b: int): int {
+        while b != 0 {
+            return gcd(b, a % b);
+        }
+        return a;
+    }
+
+    fn count(n: int): int {
+        for i = 1 to 10 {
+            if i == n return count(n + 1);
+        }
+        return 100 / (n - 11);
+    }
+
+    fn main() {
+        println(sum(100000, 0), ' ', gcd(1071, 462));
+        println(count(1));
+    }");
+
+    assert!(matches!(
+        r,
+        Err(RuntimeError::DivisionByZero {
+            line: 17,
+            column: 20
+        })
+    ));
+    assert_eq!(out, "5000050000 21\n");
+}
@@ -0,0 +1,147 @@
+use crate::{instr::BlockId, program::Function};
+
+/// The dominator tree of a function. A block dominates another block if
+/// every path from the entry to the other block goes through it.
+///
+/// The tree is built with the algorithm of Cooper, Harvey and Kennedy
+/// ("A Simple, Fast Dominance Algorithm"). Blocks which are not reachable
+/// from the entry are not in the tree.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dominators {
+    /// Immediate dominator of each block, by block index. The entry is its
+    /// own immediate dominator, and unreachable blocks have none.
+    idom: Vec<Option<BlockId>>,
+    /// Position of each block in `order`, by block index.
+    positions: Vec<usize>,
+    /// Reachable blocks in reverse postorder.
+    order: Vec<BlockId>,
+}
+
+impl Dominators {
+    /// Builds the dominator tree of a function.
+    pub fn new(function: &Function) -> Self {
+        let order = function.reverse_postorder();
+        let mut positions = vec![usize::MAX; function.blocks.len()];
+        for (i, id) in order.iter().enumerate() {
+            positions[id.0 as usize] = i;
+        }
+        let predecessors = function.predecessors();
+
+        let mut idom = vec![None; function.blocks.len()];
+        if let Some(entry) = order.first() {
+            idom[entry.0 as usize] = Some(*entry);
+        }
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for &id in order.iter().skip(1) {
+                let mut new_idom = None;
+                for &p in &predecessors[id.0 as usize] {
+                    if idom[p.0 as usize].is_none() {
+                        continue;
+                    }
+                    new_idom = Some(match new_idom {
+                        None => p,
+                        Some(d) => intersect(&idom, &positions, p, d),
+                    });
+                }
+                if new_idom.is_some() && idom[id.0 as usize] != new_idom {
+                    idom[id.0 as usize] = new_idom;
+                    changed = true;
+                }
+            }
+        }
+
+        Self {
+            idom,
+            positions,
+            order,
+        }
+    }
+
+    /// Returns the immediate dominator of a block, or `None` for the entry
+    /// and unreachable blocks.
+    pub fn idom(&self, id: BlockId) -> Option<BlockId> {
+        self.idom[id.0 as usize].filter(|&d| d != id)
+    }
+
+    /// Checks whether a block is reachable from the entry.
+    pub fn is_reachable(&self, id: BlockId) -> bool {
+        self.idom[id.0 as usize].is_some()
+    }
+
+    /// Checks whether block `a` dominates block `b`. Every block dominates
+    /// itself.
+    pub fn dominates(&self, a: BlockId, b: BlockId) -> bool {
+        if !self.is_reachable(a) || !self.is_reachable(b) {
+            return false;
+        }
+        let mut b = b;
+        while self.positions[b.0 as usize] > self.positions[a.0 as usize] {
+            b = self.idom[b.0 as usize].expect("Reachable blocks have dominators");
+        }
+        a == b
+    }
+
+    /// Returns the reachable blocks in reverse postorder.
+    pub fn order(&self) -> &[BlockId] {
+        &self.order
+    }
+
+    /// Returns the children of each block in the dominator tree, by block
+    /// index. Children come in reverse postorder.
+    pub fn children(&self) -> Vec<Vec<BlockId>> {
+        let mut children = vec![Vec::new(); self.idom.len()];
+        for &id in &self.order {
+            if let Some(d) = self.idom(id) {
+                children[d.0 as usize].push(id);
+            }
+        }
+        children
+    }
+
+    /// Returns the dominance frontier of each block, by block index: the
+    /// blocks where the dominance of the block ends, which are the places
+    /// of phis for the values which the block defines.
+    pub fn frontiers(&self, function: &Function) -> Vec<Vec<BlockId>> {
+        let mut frontiers: Vec<Vec<BlockId>> = vec![Vec::new(); self.idom.len()];
+        for (id, predecessors) in function.predecessors().into_iter().enumerate() {
+            let id = BlockId(id as u32);
+            if predecessors.len() < 2 || !self.is_reachable(id) {
+                continue;
+            }
+            for p in predecessors {
+                let mut runner = p;
+                while self.is_reachable(runner) && Some(runner) != self.idom(id) {
+                    let frontier = &mut frontiers[runner.0 as usize];
+                    if !frontier.contains(&id) {
+                        frontier.push(id);
+                    }
+                    match self.idom(runner) {
+                        Some(d) => runner = d,
+                        None => break,
+                    }
+                }
+            }
+        }
+        frontiers
+    }
+}
+
+/// Returns the closest common dominator of two blocks.
+fn intersect(
+    idom: &[Option<BlockId>],
+    positions: &[usize],
+    mut a: BlockId,
+    mut b: BlockId,
+) -> BlockId {
+    while a != b {
+        while positions[a.0 as usize] > positions[b.0 as usize] {
+            a = idom[a.0 as usize].expect("Processed blocks have dominators");
+        }
+        while positions[b.0 as usize] > positions[a.0 as usize] {
+            b = idom[b.0 as usize].expect("Processed blocks have dominators");
+        }
+    }
+    a
+}
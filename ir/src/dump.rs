@@ -0,0 +1,110 @@
+use std::fmt;
+
+use crate::{
+    instr::{Instr, Reg, Terminator},
+    program::{Function, Program},
+};
+
+/// Prints the functions of the program, separated by empty lines.
+impl fmt::Display for Program {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, function) in self.functions.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}", function)?;
+        }
+        Ok(())
+    }
+}
+
+/// Prints the signature of the function, followed by its blocks. Each
+/// instruction which writes a register also shows the type of the register:
+///
+/// ```text
+/// fn max(r0: int, r1: int): int {
+/// b0:
+///     r2: bool = gt r0, r1
+///     branch r2, b1, b2
+/// b1:
+///     return r0
+/// b2:
+///     return r1
+/// }
+/// ```
+impl fmt::Display for Function {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let params: Vec<String> = self
+            .params()
+            .map(|p| format!("{}: {}", p, self.reg_type(p)))
+            .collect();
+        write!(f, "fn {}({})", self.name, params.join(", "))?;
+        if let Some(t) = &self.return_type {
+            write!(f, ": {}", t)?;
+        }
+        writeln!(f, " {{")?;
+
+        for id in self.block_ids() {
+            writeln!(f, "{}:", id)?;
+            let block = self.block(id);
+            for instr in &block.instrs {
+                write!(f, "    ")?;
+                if let Some(dest) = instr.dest() {
+                    write!(f, "{}: {} = ", dest, self.reg_type(dest))?;
+                }
+                writeln!(f, "{}", instr)?;
+            }
+            match &block.terminator {
+                Some(t) => writeln!(f, "    {}", t)?,
+                None => writeln!(f, "    <no terminator>")?,
+            }
+        }
+
+        writeln!(f, "}}")
+    }
+}
+
+/// Prints the operation of the instruction, without its destination.
+impl fmt::Display for Instr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Instr::Const { value, .. } => write!(f, "const {}", value),
+            Instr::Copy { src, .. } => write!(f, "copy {}", src),
+            Instr::Binary { op, l, r, .. } => write!(f, "{} {}, {}", op.name(), l, r),
+            Instr::Unary { op, src, .. } => write!(f, "{} {}", op.name(), src),
+            Instr::Array { elements, .. } => write!(f, "array {}", list(elements)),
+            Instr::Index { array, index, .. } => write!(f, "index {}, {}", array, index),
+            Instr::Call { function, args, .. } => write!(f, "call {}({})", function, list(args)),
+            Instr::Println { args } => write!(f, "println {}", list(args)),
+            Instr::Phi { args, .. } => {
+                let args: Vec<String> = args
+                    .iter()
+                    .map(|(block, reg)| format!("[{}: {}]", block, reg))
+                    .collect();
+                write!(f, "phi {}", args.join(", "))
+            }
+        }
+    }
+}
+
+impl fmt::Display for Terminator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Terminator::Jump(target) => write!(f, "jump {}", target),
+            Terminator::Branch {
+                cond,
+                then_block,
+                else_block,
+            } => write!(f, "branch {}, {}, {}", cond, then_block, else_block),
+            Terminator::Return(Some(value)) => write!(f, "return {}", value),
+            Terminator::Return(None) => write!(f, "return"),
+            Terminator::MissingReturn { .. } => write!(f, "missing_return"),
+        }
+    }
+}
+
+/// Returns registers separated by commas.
+fn list(regs: &[Reg]) -> String {
+    let regs: Vec<String> = regs.iter().map(|r| r.to_string()).collect();
+    regs.join(", ")
+}
@@ -0,0 +1,195 @@
+use std::{error, fmt};
+
+use crate::instr::{BlockId, Reg};
+
+/// Errors which may occur while lowering Kolang code to the intermediate
+/// representation. Code which passes the semantic checks lowers without
+/// errors.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LowerError {
+    /// Variable is used before definition.
+    UndefinedVariable {
+        /// Name of the variable.
+        id: String,
+        /// Line of code where the variable is used.
+        line: usize,
+        /// Column of code where the variable is used.
+        column: usize,
+    },
+    /// Called function is not defined.
+    UndefinedFunction {
+        /// Name of the function.
+        id: String,
+        /// Line of code where the function is called.
+        line: usize,
+        /// Column of code where the function is called.
+        column: usize,
+    },
+    /// Function is called with wrong number of arguments.
+    WrongArgumentCount {
+        /// Name of the function.
+        function: String,
+        /// Number of function parameters.
+        expected: usize,
+        /// Number of provided arguments.
+        found: usize,
+        /// Line of code where the function is called.
+        line: usize,
+        /// Column of code where the function is called.
+        column: usize,
+    },
+    /// Code contains a syntax or type error, so it cannot be lowered.
+    InvalidCode {
+        /// Line of code where the error is.
+        line: usize,
+        /// Column of code where the error is.
+        column: usize,
+    },
+}
+
+impl LowerError {
+    /// Returns line and column of code where the error happened.
+    pub fn position(&self) -> (usize, usize) {
+        match self {
+            LowerError::UndefinedVariable { line, column, .. }
+            | LowerError::UndefinedFunction { line, column, .. }
+            | LowerError::WrongArgumentCount { line, column, .. }
+            | LowerError::InvalidCode { line, column } => (*line, *column),
+        }
+    }
+}
+
+impl fmt::Display for LowerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LowerError::UndefinedVariable { id, .. } => {
+                write!(f, "Variable `{id}` is not defined")
+            }
+            LowerError::UndefinedFunction { id, .. } => {
+                write!(f, "Function `{id}` is not defined")
+            }
+            LowerError::WrongArgumentCount {
+                function,
+                expected,
+                found,
+                ..
+            } => {
+                let s = if *expected == 1 { "" } else { "s" };
+                let verb = if *found == 1 { "was" } else { "were" };
+                write!(
+                    f,
+                    "Function `{function}` takes {expected} argument{s} but {found} {verb} supplied"
+                )
+            }
+            LowerError::InvalidCode { .. } => f.write_str("Code contains errors"),
+        }
+    }
+}
+
+impl error::Error for LowerError {}
+
+/// Problems which the [verifier](crate::verify) finds in a function of
+/// the intermediate representation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VerifyError {
+    /// The function has no blocks, so it has no entry.
+    NoEntry {
+        /// Name of the function.
+        function: String,
+    },
+    /// A block does not end in a terminator.
+    MissingTerminator {
+        /// Name of the function.
+        function: String,
+        /// The block.
+        block: BlockId,
+    },
+    /// A terminator continues from a block which does not exist.
+    UnknownBlock {
+        /// Name of the function.
+        function: String,
+        /// Block of the terminator.
+        block: BlockId,
+        /// The block which does not exist.
+        target: BlockId,
+    },
+    /// An instruction uses a register which does not exist.
+    UnknownRegister {
+        /// Name of the function.
+        function: String,
+        /// Block of the instruction.
+        block: BlockId,
+        /// The register which does not exist.
+        reg: Reg,
+    },
+    /// An instruction calls a function which does not exist, or with wrong
+    /// number of arguments.
+    InvalidCall {
+        /// Name of the function.
+        function: String,
+        /// Block of the instruction.
+        block: BlockId,
+        /// Name of the called function.
+        callee: String,
+    },
+    /// A phi does not come first in its block, or its arguments do not
+    /// match the predecessors of the block.
+    InvalidPhi {
+        /// Name of the function.
+        function: String,
+        /// Block of the phi.
+        block: BlockId,
+        /// Register which the phi writes.
+        dest: Reg,
+    },
+}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VerifyError::NoEntry { function } => {
+                write!(f, "Function `{function}` has no blocks")
+            }
+            VerifyError::MissingTerminator { function, block } => {
+                write!(
+                    f,
+                    "Block {block} of `{function}` does not end in a terminator"
+                )
+            }
+            VerifyError::UnknownBlock {
+                function,
+                block,
+                target,
+            } => write!(
+                f,
+                "Block {block} of `{function}` continues from {target}, which does not exist"
+            ),
+            VerifyError::UnknownRegister {
+                function,
+                block,
+                reg,
+            } => write!(
+                f,
+                "Block {block} of `{function}` uses {reg}, which does not exist"
+            ),
+            VerifyError::InvalidCall {
+                function,
+                block,
+                callee,
+            } => write!(
+                f,
+                "Block {block} of `{function}` calls `{callee}` with wrong arguments"
+            ),
+            VerifyError::InvalidPhi {
+                function,
+                block,
+                dest,
+            } => write!(
+                f,
+                "Block {block} of `{function}` has an invalid phi for {dest}"
+            ),
+        }
+    }
+}
+
+impl error::Error for VerifyError {}
@@ -0,0 +1,489 @@
+use std::fmt;
+
+use parser::ast;
+
+use crate::program::Function;
+
+/// Type of a virtual register.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Type {
+    /// `int` values
+    Int,
+    /// `float` values
+    Float,
+    /// `char` values
+    Char,
+    /// `str` values
+    Str,
+    /// `bool` values
+    Bool,
+    /// Arrays of elements of a type.
+    Array(Box<Type>),
+}
+
+impl Type {
+    /// Returns the type of values of a Kolang type, or `None` for
+    /// [`ast::Type::Error`].
+    pub fn of(t: &ast::Type) -> Option<Self> {
+        Some(match t {
+            ast::Type::Int { .. } => Type::Int,
+            ast::Type::Float { .. } => Type::Float,
+            ast::Type::Char { .. } => Type::Char,
+            ast::Type::Str { .. } => Type::Str,
+            ast::Type::Bool { .. } => Type::Bool,
+            ast::Type::Array { element_type, .. } => Type::Array(Box::new(Type::of(element_type)?)),
+            ast::Type::Error { .. } => return None,
+        })
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Type::Int => write!(f, "int"),
+            Type::Float => write!(f, "float"),
+            Type::Char => write!(f, "char"),
+            Type::Str => write!(f, "str"),
+            Type::Bool => write!(f, "bool"),
+            Type::Array(element_type) => write!(f, "{}[]", element_type),
+        }
+    }
+}
+
+/// A virtual register of a function. Registers are numbered from 0 in each
+/// function, and the first ones hold the parameters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Reg(pub u32);
+
+impl fmt::Display for Reg {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "r{}", self.0)
+    }
+}
+
+/// A basic block of a function, by its index. Block 0 is the entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BlockId(pub u32);
+
+impl fmt::Display for BlockId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "b{}", self.0)
+    }
+}
+
+/// A constant value.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Constant {
+    /// `int` value
+    Int(i64),
+    /// `float` value
+    Float(f64),
+    /// `char` value
+    Char(char),
+    /// `str` value
+    Str(String),
+    /// `bool` value
+    Bool(bool),
+    /// Empty array of elements of a type.
+    EmptyArray(Type),
+}
+
+impl Constant {
+    /// Returns the type of the value.
+    pub fn value_type(&self) -> Type {
+        match self {
+            Constant::Int(_) => Type::Int,
+            Constant::Float(_) => Type::Float,
+            Constant::Char(_) => Type::Char,
+            Constant::Str(_) => Type::Str,
+            Constant::Bool(_) => Type::Bool,
+            Constant::EmptyArray(t) => Type::Array(Box::new(t.clone())),
+        }
+    }
+
+    /// Returns the value which variables of a type have when they are
+    /// defined without initial value.
+    pub fn default_of(t: &Type) -> Self {
+        match t {
+            Type::Int => Constant::Int(0),
+            Type::Float => Constant::Float(0.0),
+            Type::Char => Constant::Char('\0'),
+            Type::Str => Constant::Str(String::new()),
+            Type::Bool => Constant::Bool(false),
+            Type::Array(element_type) => Constant::EmptyArray(element_type.as_ref().clone()),
+        }
+    }
+}
+
+impl fmt::Display for Constant {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Constant::Int(n) => write!(f, "{}", n),
+            Constant::Float(x) => write!(f, "{:?}", x),
+            Constant::Char(c) => write!(f, "{:?}", c),
+            Constant::Str(s) => write!(f, "{:?}", s),
+            Constant::Bool(b) => write!(f, "{}", b),
+            Constant::EmptyArray(_) => write!(f, "[]"),
+        }
+    }
+}
+
+/// Binary operators. Logical operators are lowered to branches, because
+/// their right operand is only evaluated if needed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BinOp {
+    /// Addition: `+`
+    Add,
+    /// Subtraction: `-`
+    Sub,
+    /// Multiplication: `*`
+    Mul,
+    /// Division: `/`
+    Div,
+    /// Modulo: `%`
+    Mod,
+    /// Bitwise and: `&`
+    BitAnd,
+    /// Bitwise or: `|`
+    BitOr,
+    /// Equals: `==`
+    Eq,
+    /// Not equal: `!=`
+    NEq,
+    /// Less than: `<`
+    LT,
+    /// Greater than: `>`
+    GT,
+    /// Less than or equal: `<=`
+    LEq,
+    /// Greater than or equal: `>=`
+    GEq,
+}
+
+impl BinOp {
+    /// Returns the operator of a Kolang binary operation, or `None` for
+    /// logical operators.
+    pub fn of(op: &ast::BinOp) -> Option<Self> {
+        Some(match op {
+            ast::BinOp::Add { .. } => BinOp::Add,
+            ast::BinOp::Sub { .. } => BinOp::Sub,
+            ast::BinOp::Mul { .. } => BinOp::Mul,
+            ast::BinOp::Div { .. } => BinOp::Div,
+            ast::BinOp::Mod { .. } => BinOp::Mod,
+            ast::BinOp::BitAnd { .. } => BinOp::BitAnd,
+            ast::BinOp::BitOr { .. } => BinOp::BitOr,
+            ast::BinOp::Eq { .. } => BinOp::Eq,
+            ast::BinOp::NEq { .. } => BinOp::NEq,
+            ast::BinOp::LT { .. } => BinOp::LT,
+            ast::BinOp::GT { .. } => BinOp::GT,
+            ast::BinOp::LEq { .. } => BinOp::LEq,
+            ast::BinOp::GEq { .. } => BinOp::GEq,
+            ast::BinOp::LogAnd { .. } | ast::BinOp::LogOr { .. } => return None,
+        })
+    }
+
+    /// Checks whether the operator compares its operands, so its result
+    /// is a `bool`.
+    pub fn is_comparison(self) -> bool {
+        matches!(
+            self,
+            BinOp::Eq | BinOp::NEq | BinOp::LT | BinOp::GT | BinOp::LEq | BinOp::GEq
+        )
+    }
+
+    /// Returns the name of the operator in the textual format.
+    pub fn name(self) -> &'static str {
+        match self {
+            BinOp::Add => "add",
+            BinOp::Sub => "sub",
+            BinOp::Mul => "mul",
+            BinOp::Div => "div",
+            BinOp::Mod => "mod",
+            BinOp::BitAnd => "bit_and",
+            BinOp::BitOr => "bit_or",
+            BinOp::Eq => "eq",
+            BinOp::NEq => "neq",
+            BinOp::LT => "lt",
+            BinOp::GT => "gt",
+            BinOp::LEq => "leq",
+            BinOp::GEq => "geq",
+        }
+    }
+}
+
+/// Unary operators.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum UnOp {
+    /// Negation: `-`
+    Neg,
+    /// Logical not: `not`
+    LogNot,
+    /// Bitwise not: `~`
+    BitNot,
+}
+
+impl UnOp {
+    /// Returns the operator of a Kolang unary operation.
+    pub fn of(op: &ast::UnOp) -> Self {
+        match op {
+            ast::UnOp::Neg { .. } => UnOp::Neg,
+            ast::UnOp::LogNot { .. } => UnOp::LogNot,
+            ast::UnOp::BitNot { .. } => UnOp::BitNot,
+        }
+    }
+
+    /// Returns the name of the operator in the textual format.
+    pub fn name(self) -> &'static str {
+        match self {
+            UnOp::Neg => "neg",
+            UnOp::LogNot => "not",
+            UnOp::BitNot => "bit_not",
+        }
+    }
+}
+
+/// An instruction of a basic block. Instructions read their operands from
+/// registers and write their result (if any) to the `dest` register.
+///
+/// Instructions which may fail at runtime keep the line and column of their
+/// code, for error messages.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Instr {
+    /// `dest = value`
+    Const {
+        /// Destination register.
+        dest: Reg,
+        /// The value.
+        value: Constant,
+    },
+    /// `dest = src`
+    Copy {
+        /// Destination register.
+        dest: Reg,
+        /// Source register.
+        src: Reg,
+    },
+    /// `dest = l op r`
+    Binary {
+        /// Destination register.
+        dest: Reg,
+        /// The operator.
+        op: BinOp,
+        /// Left operand.
+        l: Reg,
+        /// Right operand.
+        r: Reg,
+        /// Line and column of the operator.
+        pos: (usize, usize),
+    },
+    /// `dest = op src`
+    Unary {
+        /// Destination register.
+        dest: Reg,
+        /// The operator.
+        op: UnOp,
+        /// The operand.
+        src: Reg,
+    },
+    /// `dest = [elements...]`
+    Array {
+        /// Destination register.
+        dest: Reg,
+        /// Elements of the array. There is at least one element.
+        elements: Vec<Reg>,
+    },
+    /// `dest = array[index]`
+    Index {
+        /// Destination register.
+        dest: Reg,
+        /// The array.
+        array: Reg,
+        /// The index.
+        index: Reg,
+        /// Line and column of the array expression.
+        pos: (usize, usize),
+    },
+    /// `dest = function(args...)`
+    Call {
+        /// Destination register. `None` if the function returns nothing.
+        dest: Option<Reg>,
+        /// Name of the called function.
+        function: String,
+        /// Arguments of the call.
+        args: Vec<Reg>,
+        /// Line and column of the call.
+        pos: (usize, usize),
+    },
+    /// Print the values and a new line.
+    Println {
+        /// The printed values.
+        args: Vec<Reg>,
+    },
+    /// `dest = value from the block which ran before`. Only in SSA form,
+    /// where phis come first in their block and have one argument for each
+    /// predecessor.
+    Phi {
+        /// Destination register.
+        dest: Reg,
+        /// Predecessor blocks and the values which come from them.
+        args: Vec<(BlockId, Reg)>,
+    },
+}
+
+impl Instr {
+    /// Returns the register which the instruction writes.
+    pub fn dest(&self) -> Option<Reg> {
+        match self {
+            Instr::Const { dest, .. }
+            | Instr::Copy { dest, .. }
+            | Instr::Binary { dest, .. }
+            | Instr::Unary { dest, .. }
+            | Instr::Array { dest, .. }
+            | Instr::Index { dest, .. }
+            | Instr::Phi { dest, .. } => Some(*dest),
+            Instr::Call { dest, .. } => *dest,
+            Instr::Println { .. } => None,
+        }
+    }
+
+    /// Returns a mutable reference to the register which the instruction
+    /// writes.
+    pub fn dest_mut(&mut self) -> Option<&mut Reg> {
+        match self {
+            Instr::Const { dest, .. }
+            | Instr::Copy { dest, .. }
+            | Instr::Binary { dest, .. }
+            | Instr::Unary { dest, .. }
+            | Instr::Array { dest, .. }
+            | Instr::Index { dest, .. }
+            | Instr::Phi { dest, .. } => Some(dest),
+            Instr::Call { dest, .. } => dest.as_mut(),
+            Instr::Println { .. } => None,
+        }
+    }
+
+    /// Returns the registers which the instruction reads, in order.
+    pub fn uses(&self) -> Vec<Reg> {
+        match self {
+            Instr::Const { .. } => Vec::new(),
+            Instr::Copy { src, .. } | Instr::Unary { src, .. } => vec![*src],
+            Instr::Binary { l, r, .. } => vec![*l, *r],
+            Instr::Index { array, index, .. } => vec![*array, *index],
+            Instr::Array { elements: regs, .. }
+            | Instr::Call { args: regs, .. }
+            | Instr::Println { args: regs } => regs.clone(),
+            Instr::Phi { args, .. } => args.iter().map(|(_, reg)| *reg).collect(),
+        }
+    }
+
+    /// Returns mutable references to the registers which the instruction
+    /// reads, in order.
+    pub fn uses_mut(&mut self) -> Vec<&mut Reg> {
+        match self {
+            Instr::Const { .. } => Vec::new(),
+            Instr::Copy { src, .. } | Instr::Unary { src, .. } => vec![src],
+            Instr::Binary { l, r, .. } => vec![l, r],
+            Instr::Index { array, index, .. } => vec![array, index],
+            Instr::Array { elements: regs, .. }
+            | Instr::Call { args: regs, .. }
+            | Instr::Println { args: regs } => regs.iter_mut().collect(),
+            Instr::Phi { args, .. } => args.iter_mut().map(|(_, reg)| reg).collect(),
+        }
+    }
+
+    /// Checks whether the instruction only computes its result: it has no
+    /// effects, and removing it or running it more often does not change
+    /// what the program does. Division and modulo of `int` values are not
+    /// pure, because they fail when the divisor is zero.
+    pub fn is_pure(&self, function: &Function) -> bool {
+        match self {
+            Instr::Const { .. }
+            | Instr::Copy { .. }
+            | Instr::Unary { .. }
+            | Instr::Array { .. }
+            | Instr::Phi { .. } => true,
+            Instr::Binary { dest, op, .. } => {
+                !matches!(op, BinOp::Div | BinOp::Mod) || *function.reg_type(*dest) != Type::Int
+            }
+            Instr::Index { .. } | Instr::Call { .. } | Instr::Println { .. } => false,
+        }
+    }
+}
+
+/// The instruction which ends a basic block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Terminator {
+    /// Continue from a block.
+    Jump(BlockId),
+    /// Continue from `then_block` if `cond` is `true`, and from
+    /// `else_block` otherwise.
+    Branch {
+        /// A `bool` register.
+        cond: Reg,
+        /// Block which runs if the condition is `true`.
+        then_block: BlockId,
+        /// Block which runs if the condition is `false`.
+        else_block: BlockId,
+    },
+    /// Return from the function, with a value if it has a return type.
+    Return(Option<Reg>),
+    /// Report that a function which has a return type finished without
+    /// returning a value.
+    MissingReturn {
+        /// Line and column of the function.
+        pos: (usize, usize),
+    },
+}
+
+impl Terminator {
+    /// Returns the blocks which may run after the block, without duplicates.
+    pub fn successors(&self) -> Vec<BlockId> {
+        match self {
+            Terminator::Jump(target) => vec![*target],
+            Terminator::Branch {
+                then_block,
+                else_block,
+                ..
+            } if then_block == else_block => vec![*then_block],
+            Terminator::Branch {
+                then_block,
+                else_block,
+                ..
+            } => vec![*then_block, *else_block],
+            Terminator::Return(_) | Terminator::MissingReturn { .. } => Vec::new(),
+        }
+    }
+
+    /// Returns the registers which the terminator reads.
+    pub fn uses(&self) -> Vec<Reg> {
+        match self {
+            Terminator::Branch { cond, .. } => vec![*cond],
+            Terminator::Return(Some(reg)) => vec![*reg],
+            _ => Vec::new(),
+        }
+    }
+
+    /// Returns mutable references to the registers which the terminator
+    /// reads.
+    pub fn uses_mut(&mut self) -> Vec<&mut Reg> {
+        match self {
+            Terminator::Branch { cond, .. } => vec![cond],
+            Terminator::Return(Some(reg)) => vec![reg],
+            _ => Vec::new(),
+        }
+    }
+
+    /// Returns mutable references to the blocks which the terminator
+    /// continues from.
+    pub fn targets_mut(&mut self) -> Vec<&mut BlockId> {
+        match self {
+            Terminator::Jump(target) => vec![target],
+            Terminator::Branch {
+                then_block,
+                else_block,
+                ..
+            } => vec![then_block, else_block],
+            Terminator::Return(_) | Terminator::MissingReturn { .. } => Vec::new(),
+        }
+    }
+}
@@ -0,0 +1,32 @@
+#![warn(missing_docs)]
+
+//! # Kolang intermediate representation
+//! Utilities for lowering Kolang code to three-address code in control flow
+//! graphs of basic blocks, which optimizations and code generators share.
+//! Optimizations work on the static single assignment form of the code.
+
+/// This module includes the dominator tree of control flow graphs.
+pub mod dominators;
+/// This module includes the textual format of the intermediate representation.
+mod dump;
+/// This module includes the errors which may occur while lowering Kolang code
+/// and verifying the intermediate representation.
+pub mod error;
+/// This module includes the types, registers, instructions and terminators.
+pub mod instr;
+/// This module includes the lowering from syntax tree to control flow graphs.
+pub mod lower;
+/// This module includes the optimization passes and the pass manager.
+pub mod opt;
+/// This module includes basic blocks, functions and programs.
+pub mod program;
+/// This module includes the conversion of functions to and from static
+/// single assignment form.
+pub mod ssa;
+/// This module includes the verifier of control flow graphs.
+pub mod verifier;
+
+pub use lower::lower;
+pub use opt::{optimize, Pass};
+pub use program::{Function, Program};
+pub use verifier::verify;
@@ -0,0 +1,745 @@
+use std::collections::{HashMap, HashSet};
+
+use parser::ast;
+
+use crate::{
+    error::LowerError,
+    instr::{BinOp, BlockId, Constant, Instr, Reg, Terminator, Type, UnOp},
+    program::{Block, Function, Program},
+};
+
+/// The `Lowerer` struct lowers function definitions of a Kolang program to
+/// control flow graphs, one at a time.
+///
+/// Each variable gets its own register, which is written by `let`
+/// statements and assignments. Other registers hold temporary values.
+struct Lowerer {
+    /// Parameter types and return type of the defined functions, by name.
+    signatures: HashMap<String, (Vec<Type>, Option<Type>)>,
+    /// Types of the registers of the function being lowered.
+    regs: Vec<Type>,
+    /// Registers of the variables of the function being lowered.
+    variables: HashSet<Reg>,
+    /// Blocks of the function being lowered.
+    blocks: Vec<Block>,
+    /// Block which instructions are appended to. `None` after a terminator,
+    /// until the next block starts. Code which comes after a terminator is
+    /// appended to a new block, which is unreachable.
+    current: Option<BlockId>,
+    /// Registers of the variables of each open scope, by name.
+    scopes: Vec<HashMap<String, Reg>>,
+    /// Return type of the function being lowered.
+    return_type: Option<Type>,
+    /// Name of the function being lowered and the block which its tail
+    /// calls jump to, if it returns a value and calls itself in `return`
+    /// statements.
+    tail_calls: Option<(String, BlockId)>,
+}
+
+impl Lowerer {
+    fn new() -> Self {
+        Self {
+            signatures: HashMap::new(),
+            regs: Vec::new(),
+            variables: HashSet::new(),
+            blocks: Vec::new(),
+            current: None,
+            scopes: Vec::new(),
+            return_type: None,
+            tail_calls: None,
+        }
+    }
+
+    /// Lowers a function definition.
+    fn function(&mut self, stmt: &ast::Stmt) -> Result<Function, LowerError> {
+        let ast::Stmt::FnDef {
+            id,
+            params,
+            body,
+            span,
+            ..
+        } = stmt
+        else {
+            unreachable!("Only functions are lowered");
+        };
+
+        let (param_types, return_type) = self.signatures[id].clone();
+        self.regs.clear();
+        self.variables.clear();
+        self.blocks.clear();
+        self.return_type = return_type.clone();
+        let mut scope = HashMap::new();
+        for ((param, _), t) in params.iter().zip(param_types) {
+            scope.insert(param.clone(), self.variable(t));
+        }
+        self.scopes = vec![scope];
+        let entry = self.new_block();
+        self.switch_to(entry);
+        // Tail calls jump to the block after the entry, because the entry
+        // has no predecessors.
+        self.tail_calls = None;
+        if return_type.is_some() && returns_call(body, id) {
+            let start = self.new_block();
+            self.terminate(Terminator::Jump(start));
+            self.switch_to(start);
+            self.tail_calls = Some((id.clone(), start));
+        }
+
+        self.body(body)?;
+        let pos = (span.line, span.column);
+        match return_type {
+            Some(_) => self.terminate(Terminator::MissingReturn { pos }),
+            None => self.terminate(Terminator::Return(None)),
+        }
+
+        Ok(Function {
+            name: id.clone(),
+            arity: params.len() as u32,
+            return_type: self.return_type.take(),
+            regs: std::mem::take(&mut self.regs),
+            blocks: std::mem::take(&mut self.blocks),
+            pos,
+        })
+    }
+
+    /// Lowers a statement which is the body of a function, loop or
+    /// conditional in a new scope.
+    fn body(&mut self, stmt: &ast::Stmt) -> Result<(), LowerError> {
+        self.scopes.push(HashMap::new());
+        match stmt {
+            ast::Stmt::Block { stmts, .. } => {
+                for s in stmts {
+                    self.stmt(s)?;
+                }
+            }
+            s => self.stmt(s)?,
+        }
+        self.scopes.pop();
+        Ok(())
+    }
+
+    /// Lowers a statement.
+    fn stmt(&mut self, stmt: &ast::Stmt) -> Result<(), LowerError> {
+        match stmt {
+            ast::Stmt::Let {
+                id,
+                var_type,
+                expr,
+                span,
+            } => {
+                let t = Type::of(var_type).ok_or(LowerError::InvalidCode {
+                    line: span.line,
+                    column: span.column,
+                })?;
+                match expr {
+                    Some(e) => {
+                        let dest = self.variable(t.clone());
+                        let value = self.value(e, Some(&t))?;
+                        self.assign(dest, value);
+                        self.define(id, dest);
+                    }
+                    None => {
+                        let dest = self.variable(t.clone());
+                        let value = Constant::default_of(&t);
+                        self.emit(Instr::Const { dest, value });
+                        self.define(id, dest);
+                    }
+                }
+            }
+            ast::Stmt::Expr { expr, .. } => {
+                self.expr(expr, None)?;
+            }
+            ast::Stmt::If {
+                cond,
+                then_stmt,
+                else_stmt,
+                ..
+            } => {
+                let cond = self.value(cond, Some(&Type::Bool))?;
+                let then_block = self.new_block();
+                let else_block = else_stmt.as_ref().map(|_| self.new_block());
+                let end = self.new_block();
+                self.terminate(Terminator::Branch {
+                    cond,
+                    then_block,
+                    else_block: else_block.unwrap_or(end),
+                });
+
+                self.switch_to(then_block);
+                self.body(then_stmt)?;
+                self.terminate(Terminator::Jump(end));
+                if let (Some(else_block), Some(els)) = (else_block, else_stmt) {
+                    self.switch_to(else_block);
+                    self.body(els)?;
+                    self.terminate(Terminator::Jump(end));
+                }
+                self.switch_to(end);
+            }
+            ast::Stmt::While { cond, body, .. } => {
+                let header = self.new_block();
+                let body_block = self.new_block();
+                let exit = self.new_block();
+                self.terminate(Terminator::Jump(header));
+
+                self.switch_to(header);
+                let cond = self.value(cond, Some(&Type::Bool))?;
+                self.terminate(Terminator::Branch {
+                    cond,
+                    then_block: body_block,
+                    else_block: exit,
+                });
+                self.switch_to(body_block);
+                self.body(body)?;
+                self.terminate(Terminator::Jump(header));
+                self.switch_to(exit);
+            }
+            ast::Stmt::For {
+                id,
+                start,
+                end,
+                body,
+                span,
+            } => {
+                let int = Some(&Type::Int);
+                let range = self.operands(&[start, end], &[int, int])?;
+                let (start, end) = (range[0], range[1]);
+                // The end is evaluated once, before the loop starts.
+                let last = if self.variables.contains(&end) {
+                    let last = self.temp(Type::Int);
+                    self.emit(Instr::Copy {
+                        dest: last,
+                        src: end,
+                    });
+                    last
+                } else {
+                    end
+                };
+                self.scopes.push(HashMap::new());
+                let i = if self.variables.contains(&start) {
+                    let i = self.variable(Type::Int);
+                    self.emit(Instr::Copy {
+                        dest: i,
+                        src: start,
+                    });
+                    i
+                } else {
+                    self.variables.insert(start);
+                    start
+                };
+                self.define(id, i);
+
+                let header = self.new_block();
+                let body_block = self.new_block();
+                let step = self.new_block();
+                let increment = self.new_block();
+                let exit = self.new_block();
+                let pos = (span.line, span.column);
+                self.terminate(Terminator::Jump(header));
+
+                // The loop ends when the variable is greater than the end.
+                self.switch_to(header);
+                let done = self.temp(Type::Bool);
+                self.emit(Instr::Binary {
+                    dest: done,
+                    op: BinOp::GT,
+                    l: i,
+                    r: last,
+                    pos,
+                });
+                self.terminate(Terminator::Branch {
+                    cond: done,
+                    then_block: exit,
+                    else_block: body_block,
+                });
+                self.switch_to(body_block);
+                self.body(body)?;
+                self.terminate(Terminator::Jump(step));
+
+                // The loop also ends instead of overflowing the variable.
+                self.switch_to(step);
+                let max = self.constant(Constant::Int(i64::MAX));
+                let done = self.temp(Type::Bool);
+                self.emit(Instr::Binary {
+                    dest: done,
+                    op: BinOp::Eq,
+                    l: i,
+                    r: max,
+                    pos,
+                });
+                self.terminate(Terminator::Branch {
+                    cond: done,
+                    then_block: exit,
+                    else_block: increment,
+                });
+                self.switch_to(increment);
+                let one = self.constant(Constant::Int(1));
+                self.emit(Instr::Binary {
+                    dest: i,
+                    op: BinOp::Add,
+                    l: i,
+                    r: one,
+                    pos,
+                });
+                self.terminate(Terminator::Jump(header));
+
+                self.scopes.pop();
+                self.switch_to(exit);
+            }
+            ast::Stmt::Return {
+                expr: ast::Expr::Call { id, args, .. },
+                ..
+            } if matches!(&self.tail_calls, Some((f, _)) if f == id)
+                && self.signatures[id].0.len() == args.len() =>
+            {
+                // The arguments replace the parameters, and the function
+                // starts again. Variables are copied first, because they
+                // may be parameters which are replaced.
+                let (params, _) = self.signatures[id].clone();
+                let hints: Vec<Option<&Type>> = params.iter().map(Some).collect();
+                let args = self.operands(&args.iter().collect::<Vec<_>>(), &hints)?;
+                let mut values = Vec::with_capacity(args.len());
+                for src in args {
+                    if self.variables.contains(&src) {
+                        let dest = self.temp(self.regs[src.0 as usize].clone());
+                        self.emit(Instr::Copy { dest, src });
+                        values.push(dest);
+                    } else {
+                        values.push(src);
+                    }
+                }
+                for (i, src) in values.into_iter().enumerate() {
+                    self.emit(Instr::Copy {
+                        dest: Reg(i as u32),
+                        src,
+                    });
+                }
+                let start = self.tail_calls.as_ref().map(|(_, start)| *start);
+                self.terminate(Terminator::Jump(start.expect("Tail calls have a start")));
+            }
+            ast::Stmt::Return { expr, .. } => {
+                let hint = self.return_type.clone();
+                let value = self.value(expr, hint.as_ref())?;
+                self.terminate(Terminator::Return(Some(value)));
+            }
+            ast::Stmt::Block { .. } => self.body(stmt)?,
+            ast::Stmt::FnDef { span, .. } => {
+                return Err(LowerError::InvalidCode {
+                    line: span.line,
+                    column: span.column,
+                })
+            }
+            ast::Stmt::Empty { .. } => {}
+        }
+
+        Ok(())
+    }
+
+    /// Lowers an expression and returns the register of its value, or
+    /// `None` if it has no value.
+    ///
+    /// `hint` is the expected type of the value, which is the type of empty
+    /// array literals. Without a hint, they are arrays of `int`.
+    fn expr(&mut self, expr: &ast::Expr, hint: Option<&Type>) -> Result<Option<Reg>, LowerError> {
+        let span = expr.span();
+        let pos = (span.line, span.column);
+
+        let reg = match expr {
+            ast::Expr::LiteralInt { value, .. } => self.constant(Constant::Int(*value)),
+            ast::Expr::LiteralStr { value, .. } => self.constant(Constant::Str(value.clone())),
+            ast::Expr::LiteralChar { value, .. } => self.constant(Constant::Char(*value)),
+            ast::Expr::LiteralFloat { value, .. } => self.constant(Constant::Float(*value)),
+            ast::Expr::LiteralBool { value, .. } => self.constant(Constant::Bool(*value)),
+            ast::Expr::LiteralArray { elements, .. } => {
+                let element_hint = match hint {
+                    Some(Type::Array(element_type)) => Some(element_type.as_ref()),
+                    _ => None,
+                };
+                if elements.is_empty() {
+                    let element_type = element_hint.cloned().unwrap_or(Type::Int);
+                    return Ok(Some(self.constant(Constant::EmptyArray(element_type))));
+                }
+
+                let hints = vec![element_hint; elements.len()];
+                let elements = self.operands(&elements.iter().collect::<Vec<_>>(), &hints)?;
+                let element_type = self.regs[elements[0].0 as usize].clone();
+                let dest = self.temp(Type::Array(Box::new(element_type)));
+                self.emit(Instr::Array { dest, elements });
+                dest
+            }
+            ast::Expr::BinaryOp { l, op, r, .. } => match BinOp::of(op) {
+                Some(bin_op) => {
+                    let operands = self.operands(&[l, r], &[None, None])?;
+                    let (l, r) = (operands[0], operands[1]);
+                    let t = if bin_op.is_comparison() {
+                        Type::Bool
+                    } else {
+                        self.regs[l.0 as usize].clone()
+                    };
+                    let dest = self.temp(t);
+                    self.emit(Instr::Binary {
+                        dest,
+                        op: bin_op,
+                        l,
+                        r,
+                        pos: (op.span().line, op.span().column),
+                    });
+                    dest
+                }
+                None => {
+                    // `l and r` is `if l { r } else { false }`, and
+                    // `l or r` is `if l { true } else { r }`.
+                    let l = self.value(l, Some(&Type::Bool))?;
+                    let dest = if self.variables.contains(&l) {
+                        let dest = self.temp(Type::Bool);
+                        self.emit(Instr::Copy { dest, src: l });
+                        dest
+                    } else {
+                        l
+                    };
+                    let right = self.new_block();
+                    let end = self.new_block();
+                    let (then_block, else_block) = match op {
+                        ast::BinOp::LogAnd { .. } => (right, end),
+                        _ => (end, right),
+                    };
+                    self.terminate(Terminator::Branch {
+                        cond: dest,
+                        then_block,
+                        else_block,
+                    });
+
+                    self.switch_to(right);
+                    let r = self.value(r, Some(&Type::Bool))?;
+                    self.assign(dest, r);
+                    self.terminate(Terminator::Jump(end));
+                    self.switch_to(end);
+                    dest
+                }
+            },
+            ast::Expr::UnaryOp { op, expr, .. } => {
+                let src = self.value(expr, None)?;
+                let dest = self.temp(self.regs[src.0 as usize].clone());
+                self.emit(Instr::Unary {
+                    dest,
+                    op: UnOp::of(op),
+                    src,
+                });
+                dest
+            }
+            ast::Expr::Identifier { id, .. } => self.lookup(id, pos)?,
+            ast::Expr::Call { id, args, .. } => {
+                let Some((params, return_type)) = self.signatures.get(id).cloned() else {
+                    if id == "println" {
+                        let args = self.operands(&args.iter().collect::<Vec<_>>(), &[])?;
+                        self.emit(Instr::Println { args });
+                        return Ok(None);
+                    }
+                    return Err(LowerError::UndefinedFunction {
+                        id: id.clone(),
+                        line: pos.0,
+                        column: pos.1,
+                    });
+                };
+                if params.len() != args.len() {
+                    return Err(LowerError::WrongArgumentCount {
+                        function: id.clone(),
+                        expected: params.len(),
+                        found: args.len(),
+                        line: pos.0,
+                        column: pos.1,
+                    });
+                }
+
+                let hints: Vec<Option<&Type>> = params.iter().map(Some).collect();
+                let args = self.operands(&args.iter().collect::<Vec<_>>(), &hints)?;
+                let dest = return_type.map(|t| self.temp(t));
+                self.emit(Instr::Call {
+                    dest,
+                    function: id.clone(),
+                    args,
+                    pos,
+                });
+                return Ok(dest);
+            }
+            ast::Expr::ArrayExpr { id, index, .. } => {
+                // The index is evaluated before the array is read.
+                let index = self.value(index, Some(&Type::Int))?;
+                let array = self.lookup(id, pos)?;
+                let Type::Array(element_type) = self.regs[array.0 as usize].clone() else {
+                    return Err(LowerError::InvalidCode {
+                        line: pos.0,
+                        column: pos.1,
+                    });
+                };
+                let dest = self.temp(*element_type);
+                self.emit(Instr::Index {
+                    dest,
+                    array,
+                    index,
+                    pos,
+                });
+                dest
+            }
+            ast::Expr::Assign { id, expr, .. } => {
+                let dest = self.lookup(id, pos)?;
+                let t = self.regs[dest.0 as usize].clone();
+                let value = self.value(expr, Some(&t))?;
+                self.assign(dest, value);
+                dest
+            }
+            ast::Expr::Error { .. } => {
+                return Err(LowerError::InvalidCode {
+                    line: pos.0,
+                    column: pos.1,
+                })
+            }
+        };
+
+        Ok(Some(reg))
+    }
+
+    /// Lowers an expression which should have a value.
+    fn value(&mut self, expr: &ast::Expr, hint: Option<&Type>) -> Result<Reg, LowerError> {
+        self.expr(expr, hint)?.ok_or_else(|| {
+            let span = expr.span();
+            LowerError::InvalidCode {
+                line: span.line,
+                column: span.column,
+            }
+        })
+    }
+
+    /// Lowers operands which are evaluated from left to right, and returns
+    /// the registers of their values. `hints` are the expected types of the
+    /// operands, if known.
+    ///
+    /// A variable operand is copied to a temporary register if a later
+    /// operand assigns a variable, so the operand keeps its value.
+    fn operands(
+        &mut self,
+        exprs: &[&ast::Expr],
+        hints: &[Option<&Type>],
+    ) -> Result<Vec<Reg>, LowerError> {
+        let mut regs = Vec::with_capacity(exprs.len());
+        for (i, e) in exprs.iter().enumerate() {
+            let mut hint = hints.get(i).copied().flatten();
+            let element_hint;
+            if hint.is_none() {
+                // Operands of binary operations and elements of arrays have
+                // the same type.
+                element_hint = regs.first().map(|r: &Reg| self.regs[r.0 as usize].clone());
+                hint = element_hint.as_ref();
+            }
+            let mut reg = self.value(e, hint)?;
+            if self.variables.contains(&reg) && exprs[i + 1..].iter().any(|e| assigns(e)) {
+                let src = reg;
+                reg = self.temp(self.regs[src.0 as usize].clone());
+                self.emit(Instr::Copy { dest: reg, src });
+            }
+            regs.push(reg);
+        }
+        Ok(regs)
+    }
+
+    /// Copies a value to a register. If the value is a temporary result of
+    /// the last instruction, the instruction writes the register instead,
+    /// and the temporary register is removed if it is the last one.
+    fn assign(&mut self, dest: Reg, value: Reg) {
+        if !self.variables.contains(&value) {
+            if let Some(id) = self.current {
+                let last = self.blocks[id.0 as usize].instrs.last_mut();
+                if let Some(instr) = last.filter(|i| i.dest() == Some(value)) {
+                    set_dest(instr, dest);
+                    if value.0 as usize == self.regs.len() - 1 {
+                        self.regs.pop();
+                    }
+                    return;
+                }
+            }
+        }
+        self.emit(Instr::Copy { dest, src: value });
+    }
+
+    /// Loads a constant to a new register.
+    fn constant(&mut self, value: Constant) -> Reg {
+        let dest = self.temp(value.value_type());
+        self.emit(Instr::Const { dest, value });
+        dest
+    }
+
+    /// Adds a temporary register of a type to the function.
+    fn temp(&mut self, t: Type) -> Reg {
+        self.regs.push(t);
+        Reg(self.regs.len() as u32 - 1)
+    }
+
+    /// Adds a variable register of a type to the function.
+    fn variable(&mut self, t: Type) -> Reg {
+        let reg = self.temp(t);
+        self.variables.insert(reg);
+        reg
+    }
+
+    /// Makes a variable visible in the current scope.
+    fn define(&mut self, id: &str, reg: Reg) {
+        self.scopes
+            .last_mut()
+            .expect("There is an open scope")
+            .insert(id.to_string(), reg);
+    }
+
+    /// Returns the register of a variable.
+    fn lookup(&self, id: &str, pos: (usize, usize)) -> Result<Reg, LowerError> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|s| s.get(id))
+            .copied()
+            .ok_or_else(|| LowerError::UndefinedVariable {
+                id: id.to_string(),
+                line: pos.0,
+                column: pos.1,
+            })
+    }
+
+    /// Adds an empty block to the function.
+    fn new_block(&mut self) -> BlockId {
+        self.blocks.push(Block::default());
+        BlockId(self.blocks.len() as u32 - 1)
+    }
+
+    /// Appends the next instructions to a block.
+    fn switch_to(&mut self, id: BlockId) {
+        self.current = Some(id);
+    }
+
+    /// Appends an instruction to the current block.
+    fn emit(&mut self, instr: Instr) {
+        let id = match self.current {
+            Some(id) => id,
+            None => {
+                let id = self.new_block();
+                self.current = Some(id);
+                id
+            }
+        };
+        self.blocks[id.0 as usize].instrs.push(instr);
+    }
+
+    /// Ends the current block. Does nothing in unreachable code which has
+    /// no instructions.
+    fn terminate(&mut self, terminator: Terminator) {
+        if let Some(id) = self.current.take() {
+            self.blocks[id.0 as usize].terminator = Some(terminator);
+        }
+    }
+}
+
+/// Changes the register which an instruction writes.
+fn set_dest(instr: &mut Instr, reg: Reg) {
+    match instr {
+        Instr::Const { dest, .. }
+        | Instr::Copy { dest, .. }
+        | Instr::Binary { dest, .. }
+        | Instr::Unary { dest, .. }
+        | Instr::Array { dest, .. }
+        | Instr::Index { dest, .. }
+        | Instr::Phi { dest, .. } => *dest = reg,
+        Instr::Call { dest, .. } => *dest = Some(reg),
+        Instr::Println { .. } => {}
+    }
+}
+
+/// Checks whether an expression assigns a variable.
+fn assigns(expr: &ast::Expr) -> bool {
+    match expr {
+        ast::Expr::Assign { .. } => true,
+        ast::Expr::LiteralArray { elements, .. } | ast::Expr::Call { args: elements, .. } => {
+            elements.iter().any(assigns)
+        }
+        ast::Expr::BinaryOp { l, r, .. } => assigns(l) || assigns(r),
+        ast::Expr::UnaryOp { expr, .. } | ast::Expr::ArrayExpr { index: expr, .. } => assigns(expr),
+        _ => false,
+    }
+}
+
+/// Checks whether a statement returns the result of a call of a function.
+fn returns_call(stmt: &ast::Stmt, function: &str) -> bool {
+    match stmt {
+        ast::Stmt::Return {
+            expr: ast::Expr::Call { id, .. },
+            ..
+        } => id == function,
+        ast::Stmt::If {
+            then_stmt,
+            else_stmt,
+            ..
+        } => {
+            returns_call(then_stmt, function)
+                || else_stmt
+                    .as_ref()
+                    .is_some_and(|s| returns_call(s, function))
+        }
+        ast::Stmt::While { body, .. } | ast::Stmt::For { body, .. } => returns_call(body, function),
+        ast::Stmt::Block { stmts, .. } => stmts.iter().any(|s| returns_call(s, function)),
+        _ => false,
+    }
+}
+
+/// Lowers the function definitions of a program, which are selected by
+/// [`ast::definitions`], to control flow graphs. `return` statements which
+/// call the function itself are lowered to jumps, so tail recursion does not
+/// use the stack.
+///
+/// # Errors
+/// Returns the first error found in the code. Code which passes the
+/// semantic checks lowers without errors.
+///
+/// # Examples
+///
+/// ```
+/// use lexer::Lexer;
+/// use parser::Parser;
+///
+/// let source = "fn main() { println(1 + 2); }".as_bytes();
+/// let (ast, _) = Parser::new(Lexer::new(source)).parse().unwrap();
+///
+/// let program = ir::lower(&ast).unwrap();
+/// assert!(ir::verify(&program).is_ok());
+/// assert_eq!(program.function("main").unwrap().blocks.len(), 1);
+/// ```
+pub fn lower(prog: &[ast::Stmt]) -> Result<Program, LowerError> {
+    let mut l = Lowerer::new();
+
+    let definitions = ast::definitions(prog);
+    for stmt in &definitions {
+        if let ast::Stmt::FnDef {
+            id,
+            params,
+            return_type,
+            span,
+            ..
+        } = stmt
+        {
+            let invalid = LowerError::InvalidCode {
+                line: span.line,
+                column: span.column,
+            };
+            let params = params
+                .iter()
+                .map(|(_, t)| Type::of(t).ok_or(invalid.clone()))
+                .collect::<Result<_, _>>()?;
+            let return_type = match return_type {
+                Some(t) => Some(Type::of(t).ok_or(invalid)?),
+                None => None,
+            };
+            l.signatures.insert(id.clone(), (params, return_type));
+        }
+    }
+
+    let mut program = Program::default();
+    for stmt in definitions {
+        program.functions.push(l.function(stmt)?);
+    }
+    Ok(program)
+}
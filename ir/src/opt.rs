@@ -0,0 +1,255 @@
+use std::{collections::HashMap, fmt};
+
+use crate::{
+    instr::{BinOp, BlockId, Constant, Instr, Reg},
+    program::{Function, Program},
+    ssa,
+};
+
+/// This module includes constant propagation and folding.
+mod constprop;
+/// This module includes copy propagation.
+mod copyprop;
+/// This module includes common subexpression elimination.
+mod cse;
+/// This module includes dead code elimination.
+mod dce;
+/// This module includes function inlining.
+mod inline;
+/// This module includes loop-invariant code motion.
+mod licm;
+
+/// An optimization pass. Passes work on functions in SSA form, and keep
+/// them in SSA form.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Pass {
+    /// Replaces calls of small functions which are not recursive with the
+    /// code of the functions.
+    Inline,
+    /// Replaces instructions and phis whose operands are constants with
+    /// their results, and branches on constants with jumps.
+    ConstProp,
+    /// Replaces the reads of copies (and of phis which merge a single
+    /// value) with the reads of their sources.
+    CopyProp,
+    /// Removes instructions whose results are not used and which have no
+    /// effects, removes unreachable blocks, and merges blocks which always
+    /// run one after another.
+    Dce,
+    /// Replaces instructions which compute a value which an instruction of
+    /// a dominating block already computed.
+    Cse,
+    /// Moves instructions which compute the same value in each iteration of
+    /// a loop to the block before the loop.
+    Licm,
+}
+
+impl Pass {
+    /// All passes, in the order in which `-O2` runs them.
+    pub const ALL: [Pass; 6] = [
+        Pass::Inline,
+        Pass::ConstProp,
+        Pass::CopyProp,
+        Pass::Cse,
+        Pass::Licm,
+        Pass::Dce,
+    ];
+
+    /// Returns the passes of an optimization level: none for `0`,
+    /// the cheap passes for `1`, and all passes for `2` and above.
+    pub fn level(level: u32) -> &'static [Pass] {
+        match level {
+            0 => &[],
+            1 => &[Pass::ConstProp, Pass::CopyProp, Pass::Dce],
+            _ => &Pass::ALL,
+        }
+    }
+
+    /// Returns the pass with a name, as printed by [`Pass::name`].
+    pub fn from_name(name: &str) -> Option<Self> {
+        Pass::ALL.into_iter().find(|p| p.name() == name)
+    }
+
+    /// Returns the name of the pass.
+    pub fn name(self) -> &'static str {
+        match self {
+            Pass::Inline => "inline",
+            Pass::ConstProp => "constprop",
+            Pass::CopyProp => "copyprop",
+            Pass::Dce => "dce",
+            Pass::Cse => "cse",
+            Pass::Licm => "licm",
+        }
+    }
+
+    /// Runs the pass on the functions of a program in SSA form.
+    pub fn run(self, program: &mut Program) {
+        let run = match self {
+            Pass::Inline => return inline::run(program),
+            Pass::ConstProp => constprop::run,
+            Pass::CopyProp => copyprop::run,
+            Pass::Dce => dce::run,
+            Pass::Cse => cse::run,
+            Pass::Licm => licm::run,
+        };
+        program.functions.iter_mut().for_each(run);
+    }
+}
+
+impl fmt::Display for Pass {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Converts the functions of a program to SSA form and runs the passes on
+/// them in order. Then blocks are numbered in reverse postorder and
+/// registers in order of their writes, so the output is easy to read.
+///
+/// # Examples
+///
+/// ```
+/// use ir::Pass;
+/// use lexer::Lexer;
+/// use parser::Parser;
+///
+/// let source = "fn main() { let x: int = 6; println(x * 7); }".as_bytes();
+/// let (ast, _) = Parser::new(Lexer::new(source)).parse().unwrap();
+/// let mut program = ir::lower(&ast).unwrap();
+///
+/// ir::optimize(&mut program, Pass::level(2));
+/// assert_eq!(
+///     program.to_string(),
+///     "\
+/// fn main() {
+/// b0:
+///     r0: int = const 42
+///     println r0
+///     return
+/// }
+/// "
+/// );
+/// ```
+pub fn optimize(program: &mut Program, passes: &[Pass]) {
+    program.functions.iter_mut().for_each(ssa::construct);
+    for pass in passes {
+        pass.run(program);
+        debug_assert_eq!(
+            crate::verify(program),
+            Ok(()),
+            "Invalid code after {}",
+            pass
+        );
+    }
+    program.functions.iter_mut().for_each(renumber);
+}
+
+/// Numbers blocks in reverse postorder and registers in order of their
+/// writes, after the parameters. Unreachable blocks and registers which are
+/// never written are removed.
+fn renumber(function: &mut Function) {
+    let order = function.reverse_postorder();
+    let mut block_ids = vec![None; function.blocks.len()];
+    for (i, id) in order.iter().enumerate() {
+        block_ids[id.0 as usize] = Some(BlockId(i as u32));
+    }
+    let mut blocks: Vec<_> = order
+        .iter()
+        .map(|id| std::mem::take(&mut function.blocks[id.0 as usize]))
+        .collect();
+
+    let mut reg_ids: HashMap<Reg, Reg> = function.params().map(|p| (p, p)).collect();
+    let mut regs: Vec<_> = function
+        .params()
+        .map(|p| function.reg_type(p).clone())
+        .collect();
+    let mut number = |reg: Reg, reg_ids: &mut HashMap<Reg, Reg>| {
+        *reg_ids.entry(reg).or_insert_with(|| {
+            regs.push(function.regs[reg.0 as usize].clone());
+            Reg(regs.len() as u32 - 1)
+        })
+    };
+    for block in &blocks {
+        for dest in block.instrs.iter().filter_map(|i| i.dest()) {
+            number(dest, &mut reg_ids);
+        }
+    }
+
+    for block in &mut blocks {
+        for instr in &mut block.instrs {
+            for reg in instr.uses_mut() {
+                *reg = number(*reg, &mut reg_ids);
+            }
+            if let Some(dest) = instr.dest_mut() {
+                *dest = reg_ids[dest];
+            }
+            if let Instr::Phi { args, .. } = instr {
+                for (b, _) in args.iter_mut() {
+                    *b = block_ids[b.0 as usize].expect("Predecessors are reachable");
+                }
+            }
+        }
+        if let Some(t) = &mut block.terminator {
+            for reg in t.uses_mut() {
+                *reg = number(*reg, &mut reg_ids);
+            }
+            for target in t.targets_mut() {
+                *target = block_ids[target.0 as usize].expect("Successors are reachable");
+            }
+        }
+    }
+
+    function.blocks = blocks;
+    function.regs = regs;
+}
+
+/// Replaces the reads of registers with the reads of other registers.
+/// Replacements may be chained.
+fn substitute(function: &mut Function, replacements: &HashMap<Reg, Reg>) {
+    let replace = |reg: &mut Reg| {
+        // Stops after visiting every replacement once, in case of cycles.
+        for _ in 0..replacements.len() {
+            match replacements.get(reg) {
+                Some(r) => *reg = *r,
+                None => break,
+            }
+        }
+    };
+    for block in &mut function.blocks {
+        for instr in &mut block.instrs {
+            instr.uses_mut().into_iter().for_each(replace);
+        }
+        if let Some(t) = &mut block.terminator {
+            t.uses_mut().into_iter().for_each(replace);
+        }
+    }
+}
+
+/// Returns the values of the registers which constant instructions write.
+fn constants(function: &Function) -> HashMap<Reg, Constant> {
+    let mut constants = HashMap::new();
+    for block in &function.blocks {
+        for instr in &block.instrs {
+            if let Instr::Const { dest, value } = instr {
+                constants.insert(*dest, value.clone());
+            }
+        }
+    }
+    constants
+}
+
+/// Checks whether an instruction may be removed or moved. Unlike
+/// [`Instr::is_pure`], division and modulo of `int` values by a nonzero
+/// constant cannot fail, so they are pure too.
+fn is_pure(instr: &Instr, function: &Function, constants: &HashMap<Reg, Constant>) -> bool {
+    match instr {
+        Instr::Binary {
+            op: BinOp::Div | BinOp::Mod,
+            r,
+            ..
+        } if !instr.is_pure(function) => {
+            matches!(constants.get(r), Some(Constant::Int(n)) if *n != 0)
+        }
+        _ => instr.is_pure(function),
+    }
+}
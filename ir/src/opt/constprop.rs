@@ -0,0 +1,158 @@
+use std::{cmp::Ordering, collections::HashMap, mem};
+
+use crate::{
+    instr::{BinOp, Constant, Instr, Reg, Terminator, UnOp},
+    program::Function,
+};
+
+/// Folds instructions whose operands are constants, until nothing changes.
+/// Results are computed like the interpreter computes them, and operations
+/// which would fail at runtime (division by zero) are not folded, so they
+/// still fail.
+pub(super) fn run(function: &mut Function) {
+    loop {
+        let mut constants: HashMap<Reg, Constant> = HashMap::new();
+        let mut changed = false;
+
+        for id in function.reverse_postorder() {
+            let block = &mut function.blocks[id.0 as usize];
+            let mut folded_phi = false;
+            for instr in &mut block.instrs {
+                let value = match instr {
+                    Instr::Const { dest, value } => {
+                        constants.insert(*dest, value.clone());
+                        continue;
+                    }
+                    Instr::Copy { src, .. } => constants.get(src).cloned(),
+                    Instr::Binary { op, l, r, .. } => match (constants.get(l), constants.get(r)) {
+                        (Some(l), Some(r)) => binary(*op, l, r),
+                        _ => None,
+                    },
+                    Instr::Unary { op, src, .. } => constants.get(src).and_then(|v| unary(*op, v)),
+                    Instr::Phi { dest, args } => {
+                        let mut values = args
+                            .iter()
+                            .filter(|(_, r)| r != dest)
+                            .map(|(_, r)| constants.get(r));
+                        match values.next().flatten() {
+                            Some(first) if values.all(|v| v.is_some_and(|v| same(v, first))) => {
+                                Some(first.clone())
+                            }
+                            _ => None,
+                        }
+                    }
+                    _ => None,
+                };
+
+                if let Some(value) = value {
+                    let dest = instr.dest().expect("Folded instructions have results");
+                    folded_phi |= matches!(instr, Instr::Phi { .. });
+                    constants.insert(dest, value.clone());
+                    *instr = Instr::Const { dest, value };
+                    changed = true;
+                }
+            }
+            if folded_phi {
+                // Phis must start the block, so the constants of folded
+                // phis are moved after the remaining phis.
+                block
+                    .instrs
+                    .sort_by_key(|i| !matches!(i, Instr::Phi { .. }));
+            }
+
+            if let Some(Terminator::Branch {
+                cond,
+                then_block,
+                else_block,
+            }) = &block.terminator
+            {
+                if let Some(Constant::Bool(b)) = constants.get(cond) {
+                    let target = if *b { *then_block } else { *else_block };
+                    block.terminator = Some(Terminator::Jump(target));
+                    changed = true;
+                }
+            }
+        }
+
+        if !changed {
+            break;
+        }
+        // Phis of blocks which lost predecessors may be folded now.
+        function.remove_unreachable_blocks();
+    }
+}
+
+/// Checks whether two constants are the same. Unlike `==`, floats are
+/// compared by their bits, so `0.0` and `-0.0` are different constants.
+fn same(a: &Constant, b: &Constant) -> bool {
+    match (a, b) {
+        (Constant::Float(a), Constant::Float(b)) => a.to_bits() == b.to_bits(),
+        _ => a == b,
+    }
+}
+
+/// Checks whether two constants of the same kind are equal at runtime.
+/// Empty arrays are equal whatever their element type is, because an empty
+/// array literal may get a different type than the array it is compared to.
+fn equal(a: &Constant, b: &Constant) -> bool {
+    match (a, b) {
+        (Constant::EmptyArray(_), Constant::EmptyArray(_)) => true,
+        _ => a == b,
+    }
+}
+
+/// Applies a binary operation on constants. Returns `None` if the
+/// operation fails or the operands are not of the expected types.
+fn binary(op: BinOp, l: &Constant, r: &Constant) -> Option<Constant> {
+    use Constant::*;
+
+    let v = match (op, l, r) {
+        (BinOp::Add, Int(a), Int(b)) => Int(a.wrapping_add(*b)),
+        (BinOp::Add, Float(a), Float(b)) => Float(a + b),
+        (BinOp::Sub, Int(a), Int(b)) => Int(a.wrapping_sub(*b)),
+        (BinOp::Sub, Float(a), Float(b)) => Float(a - b),
+        (BinOp::Mul, Int(a), Int(b)) => Int(a.wrapping_mul(*b)),
+        (BinOp::Mul, Float(a), Float(b)) => Float(a * b),
+        (BinOp::Div | BinOp::Mod, Int(_), Int(0)) => return None,
+        (BinOp::Div, Int(a), Int(b)) => Int(a.wrapping_div(*b)),
+        (BinOp::Div, Float(a), Float(b)) => Float(a / b),
+        (BinOp::Mod, Int(a), Int(b)) => Int(a.wrapping_rem(*b)),
+        (BinOp::Mod, Float(a), Float(b)) => Float(a % b),
+        (BinOp::BitAnd, Int(a), Int(b)) => Int(a & b),
+        (BinOp::BitAnd, Bool(a), Bool(b)) => Bool(a & b),
+        (BinOp::BitOr, Int(a), Int(b)) => Int(a | b),
+        (BinOp::BitOr, Bool(a), Bool(b)) => Bool(a | b),
+        (BinOp::Eq, a, b) if mem::discriminant(a) == mem::discriminant(b) => Bool(equal(a, b)),
+        (BinOp::NEq, a, b) if mem::discriminant(a) == mem::discriminant(b) => Bool(!equal(a, b)),
+        (BinOp::LT | BinOp::GT | BinOp::LEq | BinOp::GEq, a, b) => {
+            let ord = match (a, b) {
+                (Int(a), Int(b)) => a.partial_cmp(b),
+                (Float(a), Float(b)) => a.partial_cmp(b),
+                (Char(a), Char(b)) => a.partial_cmp(b),
+                _ => return None,
+            };
+
+            Bool(match op {
+                BinOp::LT => ord == Some(Ordering::Less),
+                BinOp::GT => ord == Some(Ordering::Greater),
+                BinOp::LEq => matches!(ord, Some(Ordering::Less | Ordering::Equal)),
+                _ => matches!(ord, Some(Ordering::Greater | Ordering::Equal)),
+            })
+        }
+        _ => return None,
+    };
+
+    Some(v)
+}
+
+/// Applies a unary operation on a constant. Returns `None` if the operand
+/// is not of the expected type.
+fn unary(op: UnOp, v: &Constant) -> Option<Constant> {
+    Some(match (op, v) {
+        (UnOp::Neg, Constant::Int(n)) => Constant::Int(n.wrapping_neg()),
+        (UnOp::Neg, Constant::Float(n)) => Constant::Float(-n),
+        (UnOp::LogNot, Constant::Bool(b)) => Constant::Bool(!b),
+        (UnOp::BitNot, Constant::Int(n)) => Constant::Int(!n),
+        _ => return None,
+    })
+}
@@ -0,0 +1,40 @@
+use std::collections::HashMap;
+
+use crate::{instr::Instr, program::Function};
+
+/// Removes copies, and phis whose arguments are all the same register
+/// (or the phi itself), and makes their readers read the source register,
+/// until nothing changes.
+pub(super) fn run(function: &mut Function) {
+    loop {
+        let mut replacements = HashMap::new();
+        for block in &function.blocks {
+            for instr in &block.instrs {
+                match instr {
+                    Instr::Copy { dest, src } => {
+                        replacements.insert(*dest, *src);
+                    }
+                    Instr::Phi { dest, args } => {
+                        let mut sources = args.iter().map(|(_, r)| *r).filter(|r| r != dest);
+                        if let Some(first) = sources.next() {
+                            if sources.all(|r| r == first) {
+                                replacements.insert(*dest, first);
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        if replacements.is_empty() {
+            break;
+        }
+        for block in &mut function.blocks {
+            block
+                .instrs
+                .retain(|i| i.dest().is_none_or(|d| !replacements.contains_key(&d)));
+        }
+        super::substitute(function, &replacements);
+    }
+}
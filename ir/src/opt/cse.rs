@@ -0,0 +1,115 @@
+use std::collections::HashMap;
+
+use crate::{
+    dominators::Dominators,
+    instr::{BinOp, BlockId, Instr, Reg, Type, UnOp},
+    program::Function,
+};
+
+/// The value which an instruction computes, for looking up instructions
+/// which compute the same value.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Expr {
+    /// A constant of a type, in the textual format.
+    Const(Type, String),
+    /// A binary operation. Operands of commutative operators are sorted.
+    Binary(BinOp, Reg, Reg),
+    /// A unary operation.
+    Unary(UnOp, Reg),
+    /// An array of elements.
+    Array(Vec<Reg>),
+    /// An element of an array.
+    Index(Reg, Reg),
+}
+
+impl Expr {
+    /// Returns the value which an instruction computes, or `None` if the
+    /// instruction has effects or computes a different value each time.
+    fn of(instr: &Instr, function: &Function) -> Option<Self> {
+        Some(match instr {
+            Instr::Const { dest, value } => {
+                Expr::Const(function.reg_type(*dest).clone(), value.to_string())
+            }
+            Instr::Binary { op, l, r, .. } => {
+                let commutative = matches!(
+                    op,
+                    BinOp::Add | BinOp::Mul | BinOp::BitAnd | BinOp::BitOr | BinOp::Eq | BinOp::NEq
+                );
+                if commutative && r < l {
+                    Expr::Binary(*op, *r, *l)
+                } else {
+                    Expr::Binary(*op, *l, *r)
+                }
+            }
+            Instr::Unary { op, src, .. } => Expr::Unary(*op, *src),
+            Instr::Array { elements, .. } => Expr::Array(elements.clone()),
+            Instr::Index { array, index, .. } => Expr::Index(*array, *index),
+            _ => return None,
+        })
+    }
+}
+
+/// A step of the walk over the dominator tree.
+enum Step {
+    /// Look for values of a block which dominating blocks computed.
+    Enter(BlockId),
+    /// Forget the values which a block computed, once its subtree is done.
+    Exit(Vec<Expr>),
+}
+
+/// Removes instructions which compute a value which an instruction of the
+/// same block or a dominating block computed before, and makes their
+/// readers read the earlier result. Arrays are immutable, so indexing the
+/// same array with the same index gives the same element, and an operation
+/// which may fail (like division) fails at the first instruction.
+pub(super) fn run(function: &mut Function) {
+    let dominators = Dominators::new(function);
+    let children = dominators.children();
+    let mut available: HashMap<Expr, Reg> = HashMap::new();
+    let mut replacements: HashMap<Reg, Reg> = HashMap::new();
+
+    let mut steps = vec![Step::Enter(BlockId(0))];
+    while let Some(step) = steps.pop() {
+        let id = match step {
+            Step::Enter(id) => id,
+            Step::Exit(computed) => {
+                for e in computed {
+                    available.remove(&e);
+                }
+                continue;
+            }
+        };
+
+        let mut computed = Vec::new();
+        let mut instrs = std::mem::take(&mut function.blocks[id.0 as usize].instrs);
+        instrs.retain_mut(|instr| {
+            for reg in instr.uses_mut() {
+                if let Some(r) = replacements.get(reg) {
+                    *reg = *r;
+                }
+            }
+            let (Some(e), Some(dest)) = (Expr::of(instr, function), instr.dest()) else {
+                return true;
+            };
+            match available.get(&e) {
+                Some(&r) => {
+                    replacements.insert(dest, r);
+                    false
+                }
+                None => {
+                    available.insert(e.clone(), dest);
+                    computed.push(e);
+                    true
+                }
+            }
+        });
+        function.blocks[id.0 as usize].instrs = instrs;
+
+        steps.push(Step::Exit(computed));
+        for &child in children[id.0 as usize].iter().rev() {
+            steps.push(Step::Enter(child));
+        }
+    }
+
+    super::substitute(function, &replacements);
+}
@@ -0,0 +1,114 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    instr::{BlockId, Instr, Reg, Terminator},
+    program::Function,
+};
+
+/// Removes unreachable blocks, merges blocks which always run one after
+/// another, and removes instructions without effects whose results are
+/// not used (directly or through other such instructions).
+pub(super) fn run(function: &mut Function) {
+    function.remove_unreachable_blocks();
+    merge_blocks(function);
+    remove_dead_instrs(function);
+}
+
+/// Appends each block which has a single predecessor to that predecessor,
+/// if the predecessor always continues from the block.
+fn merge_blocks(function: &mut Function) {
+    'merge: loop {
+        let predecessors = function.predecessors();
+        for id in function.block_ids() {
+            let Some(Terminator::Jump(next)) = function.block(id).terminator else {
+                continue;
+            };
+            if next == id || next == BlockId(0) || predecessors[next.0 as usize] != [id] {
+                continue;
+            }
+
+            // Phis of a block with a single predecessor have a single argument.
+            let next_block = std::mem::take(&mut function.blocks[next.0 as usize]);
+            let mut replacements = HashMap::new();
+            let block = &mut function.blocks[id.0 as usize];
+            for instr in next_block.instrs {
+                match instr {
+                    Instr::Phi { dest, args } => {
+                        replacements.insert(dest, args[0].1);
+                    }
+                    instr => block.instrs.push(instr),
+                }
+            }
+            block.terminator = next_block.terminator;
+
+            for s in function.successors(id) {
+                for instr in &mut function.blocks[s.0 as usize].instrs {
+                    if let Instr::Phi { args, .. } = instr {
+                        for (b, _) in args.iter_mut().filter(|(b, _)| *b == next) {
+                            *b = id;
+                        }
+                    }
+                }
+            }
+            super::substitute(function, &replacements);
+            continue 'merge;
+        }
+        break;
+    }
+    function.remove_unreachable_blocks();
+}
+
+/// Removes pure instructions whose results are not used by instructions
+/// with effects or terminators. Calls whose results are not used stop
+/// writing them.
+fn remove_dead_instrs(function: &mut Function) {
+    let constants = super::constants(function);
+    let mut writers: HashMap<Reg, &Instr> = HashMap::new();
+    let mut work: Vec<Reg> = Vec::new();
+    for block in &function.blocks {
+        for instr in &block.instrs {
+            if let Some(dest) = instr.dest() {
+                writers.insert(dest, instr);
+            }
+            if !super::is_pure(instr, function, &constants) {
+                work.extend(instr.uses());
+            }
+        }
+        if let Some(t) = &block.terminator {
+            work.extend(t.uses());
+        }
+    }
+
+    let mut live = HashSet::new();
+    while let Some(reg) = work.pop() {
+        if live.insert(reg) {
+            if let Some(instr) = writers.get(&reg) {
+                work.extend(instr.uses());
+            }
+        }
+    }
+
+    let pure: Vec<Vec<bool>> = function
+        .blocks
+        .iter()
+        .map(|b| {
+            b.instrs
+                .iter()
+                .map(|i| super::is_pure(i, function, &constants))
+                .collect()
+        })
+        .collect();
+    for (block, pure) in function.blocks.iter_mut().zip(pure) {
+        let mut pure = pure.into_iter();
+        block.instrs.retain_mut(|instr| {
+            let pure = pure.next().expect("Each instruction is checked");
+            let used = instr.dest().is_some_and(|d| live.contains(&d));
+            if let Instr::Call { dest, .. } = instr {
+                if !used {
+                    *dest = None;
+                }
+            }
+            used || !pure
+        });
+    }
+}
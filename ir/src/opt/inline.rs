@@ -0,0 +1,234 @@
+use std::collections::HashMap;
+
+use crate::{
+    instr::{BlockId, Instr, Reg, Terminator},
+    program::{Block, Function, Program},
+};
+
+/// Maximum number of instructions of the functions which are inlined.
+const MAX_SIZE: usize = 16;
+
+/// Replaces calls of small functions which do not call themselves (directly
+/// or through other functions) with their code.
+///
+/// Functions are visited after the functions they call, so calls in the
+/// inlined code are already inlined. A function is inlined if it has at
+/// most [`MAX_SIZE`] instructions after that. Functions which may finish
+/// without returning a value are not inlined, because the error names the
+/// function.
+pub(super) fn run(program: &mut Program) {
+    let index: HashMap<String, usize> = program
+        .functions
+        .iter()
+        .enumerate()
+        .map(|(i, f)| (f.name.clone(), i))
+        .collect();
+    let calls: Vec<Vec<usize>> = program
+        .functions
+        .iter()
+        .map(|f| {
+            let mut callees = Vec::new();
+            for block in &f.blocks {
+                for instr in &block.instrs {
+                    if let Instr::Call { function, .. } = instr {
+                        callees.extend(index.get(function));
+                    }
+                }
+            }
+            callees
+        })
+        .collect();
+    let recursive = recursive(&calls);
+
+    // Copies of the functions which are inlined, by name.
+    let mut inlined: HashMap<String, Function> = HashMap::new();
+    for f in postorder(&calls) {
+        let function = &mut program.functions[f];
+        inline_calls(function, &inlined);
+        if !recursive[f] && is_inlinable(function) {
+            inlined.insert(function.name.clone(), function.clone());
+        }
+    }
+}
+
+/// Returns the functions which may call themselves, directly or through
+/// other functions, by function index.
+fn recursive(calls: &[Vec<usize>]) -> Vec<bool> {
+    (0..calls.len())
+        .map(|f| {
+            let mut visited = vec![false; calls.len()];
+            let mut work = calls[f].clone();
+            while let Some(g) = work.pop() {
+                if g == f {
+                    return true;
+                }
+                if !visited[g] {
+                    visited[g] = true;
+                    work.extend(&calls[g]);
+                }
+            }
+            false
+        })
+        .collect()
+}
+
+/// Returns the indexes of the functions in postorder of the call graph:
+/// each function comes after the functions it calls, except in cycles.
+fn postorder(calls: &[Vec<usize>]) -> Vec<usize> {
+    let mut visited = vec![false; calls.len()];
+    let mut order = Vec::with_capacity(calls.len());
+    for root in 0..calls.len() {
+        if visited[root] {
+            continue;
+        }
+        visited[root] = true;
+        // Functions and the number of their callees which are visited.
+        let mut stack = vec![(root, 0)];
+        while let Some((f, next)) = stack.pop() {
+            match calls[f].get(next) {
+                Some(&g) => {
+                    stack.push((f, next + 1));
+                    if !visited[g] {
+                        visited[g] = true;
+                        stack.push((g, 0));
+                    }
+                }
+                None => order.push(f),
+            }
+        }
+    }
+    order
+}
+
+/// Checks whether a function is small enough to be inlined, always
+/// returns when it finishes, and starts with a block without predecessors,
+/// which the caller jumps to.
+fn is_inlinable(function: &Function) -> bool {
+    let size: usize = function
+        .blocks
+        .iter()
+        .map(|b| {
+            b.instrs
+                .iter()
+                .filter(|i| !matches!(i, Instr::Phi { .. }))
+                .count()
+        })
+        .sum();
+    let returns = function
+        .blocks
+        .iter()
+        .all(|b| !matches!(b.terminator, Some(Terminator::MissingReturn { .. })));
+    !function.blocks.is_empty()
+        && size <= MAX_SIZE
+        && returns
+        && function.predecessors()[0].is_empty()
+}
+
+/// Inlines the calls of a function to the inlined functions. Calls in the
+/// inlined code are not inlined again.
+fn inline_calls(function: &mut Function, inlined: &HashMap<String, Function>) {
+    let mut changed = false;
+    let mut work: Vec<BlockId> = function.block_ids().collect();
+    while let Some(id) = work.pop() {
+        let call = function.block(id).instrs.iter().position(
+            |i| matches!(i, Instr::Call { function, .. } if inlined.contains_key(function)),
+        );
+        if let Some(index) = call {
+            work.push(inline_call(function, id, index, inlined));
+            changed = true;
+        }
+    }
+    if changed {
+        function.remove_unreachable_blocks();
+    }
+}
+
+/// Replaces a call with the code of the called function. The block of the
+/// call is split: the instructions after the call move to a new block,
+/// which the returns of the inlined code jump to. A phi there merges the
+/// returned values. Returns the new block.
+fn inline_call(
+    function: &mut Function,
+    id: BlockId,
+    index: usize,
+    inlined: &HashMap<String, Function>,
+) -> BlockId {
+    let block = &mut function.blocks[id.0 as usize];
+    let mut rest = block.instrs.split_off(index);
+    let Instr::Call {
+        dest,
+        function: name,
+        args,
+        ..
+    } = rest.remove(0)
+    else {
+        unreachable!("Only calls are inlined");
+    };
+    let callee = &inlined[&name];
+
+    let terminator = block.terminator.take();
+    let next = BlockId(function.blocks.len() as u32);
+    function.blocks.push(Block {
+        instrs: rest,
+        terminator,
+    });
+    for s in function.successors(next) {
+        for instr in &mut function.blocks[s.0 as usize].instrs {
+            if let Instr::Phi { args, .. } = instr {
+                for (b, _) in args.iter_mut().filter(|(b, _)| *b == id) {
+                    *b = next;
+                }
+            }
+        }
+    }
+
+    // Parameters are replaced with the arguments, and other registers get
+    // new registers of the caller.
+    let mut regs = args;
+    for t in &callee.regs[regs.len()..] {
+        function.regs.push(t.clone());
+        regs.push(Reg(function.regs.len() as u32 - 1));
+    }
+    let start = function.blocks.len() as u32;
+    let mut returns = Vec::new();
+    for (i, block) in callee.blocks.iter().enumerate() {
+        let mut block = block.clone();
+        for instr in &mut block.instrs {
+            for reg in instr.uses_mut() {
+                *reg = regs[reg.0 as usize];
+            }
+            if let Some(dest) = instr.dest_mut() {
+                *dest = regs[dest.0 as usize];
+            }
+            if let Instr::Phi { args, .. } = instr {
+                for (b, _) in args.iter_mut() {
+                    b.0 += start;
+                }
+            }
+        }
+        if let Some(t) = &mut block.terminator {
+            for target in t.targets_mut() {
+                target.0 += start;
+            }
+            if let Terminator::Return(value) = t {
+                if let Some(value) = value {
+                    returns.push((BlockId(start + i as u32), regs[value.0 as usize]));
+                }
+                *t = Terminator::Jump(next);
+            }
+            for reg in t.uses_mut() {
+                *reg = regs[reg.0 as usize];
+            }
+        }
+        function.blocks.push(block);
+    }
+
+    if let Some(dest) = dest.filter(|_| !returns.is_empty()) {
+        let args = returns;
+        function.blocks[next.0 as usize]
+            .instrs
+            .insert(0, Instr::Phi { dest, args });
+    }
+    function.blocks[id.0 as usize].terminator = Some(Terminator::Jump(BlockId(start)));
+    next
+}
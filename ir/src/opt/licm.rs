@@ -0,0 +1,146 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use crate::{
+    dominators::Dominators,
+    instr::{BlockId, Instr, Reg, Terminator},
+    program::{Block, Function},
+};
+
+/// Moves pure instructions whose operands are written outside of a loop to
+/// the preheader of the loop: the block which runs before the loop starts.
+/// Inner loops come first, so instructions may leave several loops.
+///
+/// The moved instructions cannot fail, so it does not matter that they
+/// run even if the loop runs zero times, or if they were in a branch of
+/// the loop which does not run. Loops which are entered from several
+/// blocks outside of the loop are not changed.
+pub(super) fn run(function: &mut Function) {
+    let dominators = Dominators::new(function);
+    let predecessors = function.predecessors();
+
+    // Natural loops by their headers. Loops with the same header are merged.
+    let mut loops: BTreeMap<BlockId, HashSet<BlockId>> = BTreeMap::new();
+    for &id in dominators.order() {
+        for header in function.successors(id) {
+            if dominators.dominates(header, id) {
+                let body = loops.entry(header).or_default();
+                body.insert(header);
+                let mut work = vec![id];
+                while let Some(b) = work.pop() {
+                    if body.insert(b) {
+                        work.extend(&predecessors[b.0 as usize]);
+                    }
+                }
+            }
+        }
+    }
+    let mut loops: Vec<(BlockId, HashSet<BlockId>)> = loops.into_iter().collect();
+    loops.sort_by_key(|(_, body)| body.len());
+
+    let mut writers: HashMap<Reg, BlockId> = HashMap::new();
+    for id in function.block_ids() {
+        for dest in function.block(id).instrs.iter().filter_map(|i| i.dest()) {
+            writers.insert(dest, id);
+        }
+    }
+
+    for i in 0..loops.len() {
+        let (header, body) = &loops[i];
+        let header = *header;
+        let Some(preheader) = preheader(function, header, body) else {
+            continue;
+        };
+        // The new preheader belongs to the loops around the loop.
+        for (_, outer) in &mut loops[i + 1..] {
+            if outer.contains(&header) {
+                outer.insert(preheader);
+            }
+        }
+        hoist(function, &dominators, &loops[i].1, preheader, &mut writers);
+    }
+}
+
+/// Returns the preheader of a loop. If the only block outside of the loop
+/// which continues from the header has other successors, a new block
+/// between them becomes the preheader.
+fn preheader(function: &mut Function, header: BlockId, body: &HashSet<BlockId>) -> Option<BlockId> {
+    let predecessors = function.predecessors();
+    let outside: Vec<BlockId> = predecessors[header.0 as usize]
+        .iter()
+        .copied()
+        .filter(|p| !body.contains(p))
+        .collect();
+    let [entry] = outside[..] else {
+        return None;
+    };
+    if function.successors(entry) == [header] {
+        return Some(entry);
+    }
+
+    let preheader = BlockId(function.blocks.len() as u32);
+    function.blocks.push(Block {
+        instrs: Vec::new(),
+        terminator: Some(Terminator::Jump(header)),
+    });
+    if let Some(t) = &mut function.blocks[entry.0 as usize].terminator {
+        for target in t.targets_mut().into_iter().filter(|t| **t == header) {
+            *target = preheader;
+        }
+    }
+    for instr in &mut function.blocks[header.0 as usize].instrs {
+        if let Instr::Phi { args, .. } = instr {
+            for (b, _) in args.iter_mut().filter(|(b, _)| *b == entry) {
+                *b = preheader;
+            }
+        }
+    }
+    Some(preheader)
+}
+
+/// Moves the invariant instructions of a loop to its preheader, until
+/// nothing changes.
+fn hoist(
+    function: &mut Function,
+    dominators: &Dominators,
+    body: &HashSet<BlockId>,
+    preheader: BlockId,
+    writers: &mut HashMap<Reg, BlockId>,
+) {
+    let constants = super::constants(function);
+    let blocks: Vec<BlockId> = dominators
+        .order()
+        .iter()
+        .copied()
+        .filter(|b| body.contains(b))
+        .collect();
+
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for &id in &blocks {
+            let instrs = std::mem::take(&mut function.blocks[id.0 as usize].instrs);
+            let mut kept = Vec::with_capacity(instrs.len());
+            let mut hoisted = Vec::new();
+            for instr in instrs {
+                let invariant = !matches!(instr, Instr::Phi { .. })
+                    && super::is_pure(&instr, function, &constants)
+                    && instr
+                        .uses()
+                        .iter()
+                        .all(|r| writers.get(r).is_none_or(|b| !body.contains(b)));
+                if invariant {
+                    if let Some(dest) = instr.dest() {
+                        writers.insert(dest, preheader);
+                    }
+                    hoisted.push(instr);
+                } else {
+                    kept.push(instr);
+                }
+            }
+            function.blocks[id.0 as usize].instrs = kept;
+
+            changed |= !hoisted.is_empty();
+            function.blocks[preheader.0 as usize].instrs.extend(hoisted);
+        }
+    }
+}
@@ -0,0 +1,166 @@
+use crate::instr::{BlockId, Instr, Reg, Terminator, Type};
+
+/// A basic block: instructions which run in order, and the terminator which
+/// chooses the next block.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Block {
+    /// Instructions of the block.
+    pub instrs: Vec<Instr>,
+    /// The terminator. Only `None` while the block is being built, which
+    /// the [verifier](crate::verify) reports.
+    pub terminator: Option<Terminator>,
+}
+
+/// A Kolang function as a control flow graph of basic blocks.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Function {
+    /// Name of the function.
+    pub name: String,
+    /// Number of parameters. Arguments are stored in the first registers.
+    pub arity: u32,
+    /// Return type. `None` if the function returns nothing.
+    pub return_type: Option<Type>,
+    /// Types of the registers, by their index.
+    pub regs: Vec<Type>,
+    /// Basic blocks of the function, by their index. Block 0 is the entry.
+    pub blocks: Vec<Block>,
+    /// Line and column of the function definition.
+    pub pos: (usize, usize),
+}
+
+impl Function {
+    /// Returns the parameter registers.
+    pub fn params(&self) -> impl Iterator<Item = Reg> {
+        (0..self.arity).map(Reg)
+    }
+
+    /// Returns the type of a register.
+    pub fn reg_type(&self, reg: Reg) -> &Type {
+        &self.regs[reg.0 as usize]
+    }
+
+    /// Returns a block.
+    pub fn block(&self, id: BlockId) -> &Block {
+        &self.blocks[id.0 as usize]
+    }
+
+    /// Returns the ids of all blocks, in order.
+    pub fn block_ids(&self) -> impl Iterator<Item = BlockId> {
+        (0..self.blocks.len() as u32).map(BlockId)
+    }
+
+    /// Returns the blocks which may run after a block.
+    pub fn successors(&self, id: BlockId) -> Vec<BlockId> {
+        match &self.block(id).terminator {
+            Some(t) => t.successors(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Returns the blocks which may run before each block, by block index.
+    /// Successors which do not exist are ignored.
+    pub fn predecessors(&self) -> Vec<Vec<BlockId>> {
+        let mut predecessors = vec![Vec::new(); self.blocks.len()];
+        for id in self.block_ids() {
+            for s in self.successors(id) {
+                if let Some(p) = predecessors.get_mut(s.0 as usize) {
+                    p.push(id);
+                }
+            }
+        }
+        predecessors
+    }
+
+    /// Returns the blocks which are reachable from the entry, in reverse
+    /// postorder (each block comes before its successors, except along
+    /// loops). Successors are visited last to first, so the `then_block` of
+    /// a branch comes before its `else_block`.
+    pub fn reverse_postorder(&self) -> Vec<BlockId> {
+        if self.blocks.is_empty() {
+            return Vec::new();
+        }
+
+        let mut visited = vec![false; self.blocks.len()];
+        let mut order = Vec::with_capacity(self.blocks.len());
+        // Blocks and the number of their successors which are visited.
+        let mut stack = vec![(BlockId(0), 0)];
+        visited[0] = true;
+
+        while let Some((id, next)) = stack.pop() {
+            let successors = self.successors(id);
+            match successors.iter().rev().nth(next) {
+                Some(&s) => {
+                    stack.push((id, next + 1));
+                    if !visited[s.0 as usize] {
+                        visited[s.0 as usize] = true;
+                        stack.push((s, 0));
+                    }
+                }
+                None => order.push(id),
+            }
+        }
+
+        order.reverse();
+        order
+    }
+
+    /// Removes the blocks which are not reachable from the entry and
+    /// renumbers the remaining blocks, keeping their order. Phis lose the
+    /// arguments of blocks which are no longer their predecessors.
+    pub fn remove_unreachable_blocks(&mut self) {
+        let mut reachable = vec![false; self.blocks.len()];
+        for id in self.reverse_postorder() {
+            reachable[id.0 as usize] = true;
+        }
+
+        // New ids of the reachable blocks, by old block index.
+        let mut ids = Vec::with_capacity(self.blocks.len());
+        let mut next = 0;
+        for &r in &reachable {
+            ids.push(r.then_some(BlockId(next)));
+            next += r as u32;
+        }
+        let blocks = std::mem::take(&mut self.blocks);
+        self.blocks = blocks
+            .into_iter()
+            .zip(&reachable)
+            .filter_map(|(block, &r)| r.then_some(block))
+            .collect();
+        for block in &mut self.blocks {
+            if let Some(t) = &mut block.terminator {
+                for target in t.targets_mut() {
+                    *target = ids[target.0 as usize].expect("Successors are reachable");
+                }
+            }
+        }
+
+        let predecessors = self.predecessors();
+        for (block, predecessors) in self.blocks.iter_mut().zip(predecessors) {
+            for instr in &mut block.instrs {
+                if let Instr::Phi { args, .. } = instr {
+                    args.retain_mut(|(b, _)| match ids[b.0 as usize] {
+                        Some(id) if predecessors.contains(&id) => {
+                            *b = id;
+                            true
+                        }
+                        _ => false,
+                    });
+                }
+            }
+        }
+    }
+}
+
+/// A Kolang program in the intermediate representation.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Program {
+    /// Functions of the program.
+    pub functions: Vec<Function>,
+}
+
+impl Program {
+    /// Returns a function by name.
+    pub fn function(&self, name: &str) -> Option<&Function> {
+        self.functions.iter().find(|f| f.name == name)
+    }
+}
@@ -0,0 +1,276 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use crate::{
+    dominators::Dominators,
+    instr::{BlockId, Instr, Reg},
+    program::Function,
+};
+
+/// Converts a function to static single assignment (SSA) form, where each
+/// register is written by exactly one instruction.
+///
+/// Registers which are written more than once (the registers of variables)
+/// get a new register for each write, except the first one in the dominator
+/// tree, which keeps the original register. Phis merge the values of a
+/// register where control flow joins, and only where the register is read
+/// later. Unreachable blocks are removed first.
+///
+/// # Examples
+///
+/// ```
+/// use lexer::Lexer;
+/// use parser::Parser;
+///
+/// let source = "fn f(n: int): int { if n < 0 { n = 0; } return n; }".as_bytes();
+/// let (ast, _) = Parser::new(Lexer::new(source)).parse().unwrap();
+/// let mut program = ir::lower(&ast).unwrap();
+///
+/// ir::ssa::construct(&mut program.functions[0]);
+/// assert!(program.to_string().contains("phi [b0: r0], [b1: r3]"));
+/// assert_eq!(ir::verify(&program), Ok(()));
+/// ```
+pub fn construct(function: &mut Function) {
+    function.remove_unreachable_blocks();
+    if function.blocks.is_empty() {
+        return;
+    }
+
+    let dominators = Dominators::new(function);
+    let variables = variables(function);
+    let phis = place_phis(function, &dominators, &variables);
+    rename(function, &dominators, &variables, &phis);
+}
+
+/// Converts a function out of SSA form, replacing each phi with copies.
+///
+/// Each phi gets a new register, which the predecessors of its block write
+/// just before their terminators. The phi becomes a copy of that register,
+/// so phis which read each other's values see the values from before the
+/// block.
+pub fn destruct(function: &mut Function) {
+    let mut copies: Vec<Vec<Instr>> = vec![Vec::new(); function.blocks.len()];
+    for id in function.block_ids() {
+        let block = &mut function.blocks[id.0 as usize];
+        for instr in &mut block.instrs {
+            let Instr::Phi { dest, args } = instr else {
+                continue;
+            };
+            let temp = Reg(function.regs.len() as u32);
+            function.regs.push(function.regs[dest.0 as usize].clone());
+            for (p, src) in args.iter() {
+                copies[p.0 as usize].push(Instr::Copy {
+                    dest: temp,
+                    src: *src,
+                });
+            }
+            *instr = Instr::Copy {
+                dest: *dest,
+                src: temp,
+            };
+        }
+    }
+
+    for (block, copies) in function.blocks.iter_mut().zip(copies) {
+        block.instrs.extend(copies);
+    }
+}
+
+/// Returns the registers which are written more than once. Parameters are
+/// written when the function is called.
+fn variables(function: &Function) -> HashSet<Reg> {
+    let mut written: HashSet<Reg> = function.params().collect();
+    let mut variables = HashSet::new();
+    for block in &function.blocks {
+        for dest in block.instrs.iter().filter_map(|i| i.dest()) {
+            if !written.insert(dest) {
+                variables.insert(dest);
+            }
+        }
+    }
+    variables
+}
+
+/// Returns the registers which are read before they are written in each
+/// block, by block index.
+fn live_in(function: &Function) -> Vec<HashSet<Reg>> {
+    let mut upward = Vec::with_capacity(function.blocks.len());
+    let mut written = Vec::with_capacity(function.blocks.len());
+    for block in &function.blocks {
+        let mut reads = HashSet::new();
+        let mut writes = HashSet::new();
+        for instr in &block.instrs {
+            reads.extend(instr.uses().into_iter().filter(|r| !writes.contains(r)));
+            writes.extend(instr.dest());
+        }
+        if let Some(t) = &block.terminator {
+            reads.extend(t.uses().into_iter().filter(|r| !writes.contains(r)));
+        }
+        upward.push(reads);
+        written.push(writes);
+    }
+
+    let mut live_in = upward.clone();
+    let mut order = function.reverse_postorder();
+    order.reverse();
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for &id in &order {
+            let i = id.0 as usize;
+            let mut live = upward[i].clone();
+            for s in function.successors(id) {
+                live.extend(
+                    live_in[s.0 as usize]
+                        .iter()
+                        .filter(|r| !written[i].contains(r)),
+                );
+            }
+            if live.len() != live_in[i].len() {
+                live_in[i] = live;
+                changed = true;
+            }
+        }
+    }
+    live_in
+}
+
+/// Inserts phis for the variables at the start of blocks in the iterated
+/// dominance frontiers of their writes, where the variables are live.
+/// Returns the variable of each phi, by block index.
+fn place_phis(
+    function: &mut Function,
+    dominators: &Dominators,
+    variables: &HashSet<Reg>,
+) -> Vec<Vec<Reg>> {
+    let live_in = live_in(function);
+    let frontiers = dominators.frontiers(function);
+    let predecessors = function.predecessors();
+
+    let mut writes: HashMap<Reg, BTreeSet<BlockId>> = HashMap::new();
+    for v in function.params().filter(|p| variables.contains(p)) {
+        writes.entry(v).or_default().insert(BlockId(0));
+    }
+    for id in function.block_ids() {
+        for dest in function.block(id).instrs.iter().filter_map(|i| i.dest()) {
+            if variables.contains(&dest) {
+                writes.entry(dest).or_default().insert(id);
+            }
+        }
+    }
+
+    let mut phis = vec![Vec::new(); function.blocks.len()];
+    let mut sorted: Vec<Reg> = variables.iter().copied().collect();
+    sorted.sort();
+    for v in sorted {
+        let mut work: Vec<BlockId> = writes[&v].iter().copied().collect();
+        let mut placed = HashSet::new();
+        while let Some(id) = work.pop() {
+            for &f in &frontiers[id.0 as usize] {
+                if live_in[f.0 as usize].contains(&v) && placed.insert(f) {
+                    phis[f.0 as usize].push(v);
+                    work.push(f);
+                }
+            }
+        }
+    }
+
+    for (id, vars) in phis.iter().enumerate() {
+        let block = &mut function.blocks[id];
+        let new_phis = vars.iter().map(|&v| Instr::Phi {
+            dest: v,
+            args: predecessors[id].iter().map(|&p| (p, v)).collect(),
+        });
+        block.instrs.splice(0..0, new_phis);
+    }
+    phis
+}
+
+/// A step of the walk over the dominator tree in [`rename`].
+enum Step {
+    /// Rename the registers of a block.
+    Enter(BlockId),
+    /// Forget the registers written in a block, once its subtree is done.
+    Exit(Vec<Reg>),
+}
+
+/// Gives each write of a variable its own register, and makes the reads
+/// use the register of the closest write before them.
+fn rename(
+    function: &mut Function,
+    dominators: &Dominators,
+    variables: &HashSet<Reg>,
+    phis: &[Vec<Reg>],
+) {
+    let children = dominators.children();
+    // Registers of the writes of each variable which are visible in the
+    // current block, the closest one last.
+    let mut names: HashMap<Reg, Vec<Reg>> = HashMap::new();
+    let mut renamed: HashSet<Reg> = HashSet::new();
+    for v in function.params().filter(|p| variables.contains(p)) {
+        names.entry(v).or_default().push(v);
+        renamed.insert(v);
+    }
+    let current = |names: &HashMap<Reg, Vec<Reg>>, v: Reg| {
+        names.get(&v).and_then(|n| n.last()).copied().unwrap_or(v)
+    };
+
+    let mut steps = vec![Step::Enter(BlockId(0))];
+    while let Some(step) = steps.pop() {
+        let id = match step {
+            Step::Enter(id) => id,
+            Step::Exit(written) => {
+                for v in written {
+                    names.get_mut(&v).and_then(|n| n.pop());
+                }
+                continue;
+            }
+        };
+
+        let mut written = Vec::new();
+        let regs = &mut function.regs;
+        let block = &mut function.blocks[id.0 as usize];
+        for instr in &mut block.instrs {
+            if !matches!(instr, Instr::Phi { .. }) {
+                for reg in instr.uses_mut() {
+                    if variables.contains(reg) {
+                        *reg = current(&names, *reg);
+                    }
+                }
+            }
+            if let Some(dest) = instr.dest_mut() {
+                let v = *dest;
+                if variables.contains(&v) {
+                    if !renamed.insert(v) {
+                        *dest = Reg(regs.len() as u32);
+                        regs.push(regs[v.0 as usize].clone());
+                    }
+                    names.entry(v).or_default().push(*dest);
+                    written.push(v);
+                }
+            }
+        }
+        if let Some(t) = &mut block.terminator {
+            for reg in t.uses_mut() {
+                if variables.contains(reg) {
+                    *reg = current(&names, *reg);
+                }
+            }
+        }
+
+        for s in function.successors(id) {
+            let block = &mut function.blocks[s.0 as usize];
+            for (instr, &v) in block.instrs.iter_mut().zip(&phis[s.0 as usize]) {
+                if let Instr::Phi { args, .. } = instr {
+                    for (_, reg) in args.iter_mut().filter(|(p, _)| *p == id) {
+                        *reg = current(&names, v);
+                    }
+                }
+            }
+        }
+
+        steps.push(Step::Exit(written));
+        for &child in children[id.0 as usize].iter().rev() {
+            steps.push(Step::Enter(child));
+        }
+    }
+}
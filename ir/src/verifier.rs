@@ -0,0 +1,128 @@
+use crate::{
+    error::VerifyError,
+    instr::{BlockId, Instr, Reg},
+    program::{Function, Program},
+};
+
+/// Checks the structure of the control flow graphs of a program: every
+/// function has an entry, every block ends in a terminator which continues
+/// from existing blocks, instructions use existing registers, calls
+/// pass the right number of arguments to existing functions, and phis come
+/// first in their blocks with one argument for each predecessor.
+///
+/// # Errors
+/// Returns all problems found in the program.
+///
+/// # Examples
+///
+/// ```
+/// use ir::{error::VerifyError, instr::BlockId, program::Block};
+/// use lexer::Lexer;
+/// use parser::Parser;
+///
+/// let source = "fn main() { }".as_bytes();
+/// let (ast, _) = Parser::new(Lexer::new(source)).parse().unwrap();
+/// let mut program = ir::lower(&ast).unwrap();
+///
+/// program.functions[0].blocks.push(Block::default());
+/// assert_eq!(
+///     ir::verify(&program),
+///     Err(vec![VerifyError::MissingTerminator {
+///         function: "main".into(),
+///         block: BlockId(1)
+///     }])
+/// );
+/// ```
+pub fn verify(program: &Program) -> Result<(), Vec<VerifyError>> {
+    let mut errors = Vec::new();
+    for function in &program.functions {
+        verify_function(program, function, &mut errors);
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+/// Checks a function and adds the problems found to `errors`.
+fn verify_function(program: &Program, function: &Function, errors: &mut Vec<VerifyError>) {
+    let name = || function.name.clone();
+    if function.blocks.is_empty() {
+        errors.push(VerifyError::NoEntry { function: name() });
+    }
+
+    let predecessors = function.predecessors();
+    for id in function.block_ids() {
+        let block = function.block(id);
+        let check_reg = |reg: Reg, errors: &mut Vec<VerifyError>| {
+            if reg.0 as usize >= function.regs.len() {
+                errors.push(VerifyError::UnknownRegister {
+                    function: name(),
+                    block: id,
+                    reg,
+                });
+            }
+        };
+
+        let phis = block
+            .instrs
+            .iter()
+            .take_while(|i| matches!(i, Instr::Phi { .. }))
+            .count();
+        for (i, instr) in block.instrs.iter().enumerate() {
+            for reg in instr.uses().into_iter().chain(instr.dest()) {
+                check_reg(reg, errors);
+            }
+            if let Instr::Phi { dest, args } = instr {
+                let mut sources: Vec<BlockId> = args.iter().map(|(b, _)| *b).collect();
+                sources.sort();
+                let mut expected = predecessors[id.0 as usize].clone();
+                expected.sort();
+                if i >= phis || sources != expected {
+                    errors.push(VerifyError::InvalidPhi {
+                        function: name(),
+                        block: id,
+                        dest: *dest,
+                    });
+                }
+            }
+            if let Instr::Call {
+                function: f, args, ..
+            } = instr
+            {
+                let valid = program
+                    .function(f)
+                    .is_some_and(|callee| callee.arity as usize == args.len());
+                if !valid {
+                    errors.push(VerifyError::InvalidCall {
+                        function: name(),
+                        block: id,
+                        callee: f.clone(),
+                    });
+                }
+            }
+        }
+
+        let Some(terminator) = &block.terminator else {
+            errors.push(VerifyError::MissingTerminator {
+                function: name(),
+                block: id,
+            });
+            continue;
+        };
+        for reg in terminator.uses() {
+            check_reg(reg, errors);
+        }
+        for target in terminator.successors() {
+            if target.0 as usize >= function.blocks.len() {
+                errors.push(VerifyError::UnknownBlock {
+                    function: name(),
+                    block: id,
+                    target,
+                });
+            }
+        }
+    }
+}
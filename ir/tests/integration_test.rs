@@ -0,0 +1,1175 @@
+use ir::{
+    error::{LowerError, VerifyError},
+    instr::{BlockId, Constant, Instr, Reg, Terminator, Type},
+    program::{Block, Function},
+    Pass,
+};
+use lexer::Lexer;
+use parser::{ast, Parser};
+
+fn parse(code: &str) -> Vec<ast::Stmt> {
+    let l = Lexer::new(code.as_bytes());
+    let (ast, diagnostics) = Parser::new(l).parse().unwrap();
+    assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+    ast
+}
+
+/// Lowers the code, checks that it is valid and returns its dump.
+fn dump(code: &str) -> String {
+    let program = ir::lower(&parse(code)).unwrap();
+    assert_eq!(ir::verify(&program), Ok(()));
+    program.to_string()
+}
+
+/// Lowers the code and converts it to SSA form. Returns the dumps of the
+/// code before and after the passes run, after checking that both are valid.
+fn optimize(code: &str, passes: &[Pass]) -> (String, String) {
+    let ast = parse(code);
+    let mut before = ir::lower(&ast).unwrap();
+    let mut after = before.clone();
+    ir::optimize(&mut before, &[]);
+    ir::optimize(&mut after, passes);
+
+    assert_eq!(ir::verify(&before), Ok(()));
+    assert_eq!(ir::verify(&after), Ok(()));
+    (before.to_string(), after.to_string())
+}
+
+#[test]
+fn readme_example() {
+    let program = ir::lower(&parse(
+        include_str!("../../README.md")
+            .split("``` Rust")
+            .nth(1)
+            .unwrap()
+            .split("```")
+            .next()
+            .unwrap(),
+    ))
+    .unwrap();
+
+    assert_eq!(ir::verify(&program), Ok(()));
+    assert!(program.function("main").is_some());
+}
+
+#[test]
+fn expressions() {
+    assert_eq!(
+        dump(
+            "fn f(a: int, b: float[]): float {
+                let c: char;
+                let x: int = a * 2 + -a;
+                x = ~x;
+                return b[x % 3];
+            }
+            fn main() { println(\"x\", f(1, [0.5, 1.5]), 'c', true, []); }"
+        ),
+        "\
+fn f(r0: int, r1: float[]): float {
+b0:
+    r2: char = const '\\0'
+    r4: int = const 2
+    r5: int = mul r0, r4
+    r6: int = neg r0
+    r3: int = add r5, r6
+    r3: int = bit_not r3
+    r7: int = const 3
+    r8: int = mod r3, r7
+    r9: float = index r1, r8
+    return r9
+}
+
+fn main() {
+b0:
+    r0: str = const \"x\"
+    r1: int = const 1
+    r2: float = const 0.5
+    r3: float = const 1.5
+    r4: float[] = array r2, r3
+    r5: float = call f(r1, r4)
+    r6: char = const 'c'
+    r7: bool = const true
+    r8: int[] = const []
+    println r0, r5, r6, r7, r8
+    return
+}
+"
+    );
+}
+
+#[test]
+fn control_flow() {
+    assert_eq!(
+        dump(
+            "fn max(a: int, b: int): int {
+                if a > b { return a; } else { return b; }
+            }
+            fn main() {
+                let n: int = 10;
+                while n > 0 {
+                    if n % 2 == 0 println(n);
+                    n = n - 1;
+                }
+            }"
+        ),
+        "\
+fn max(r0: int, r1: int): int {
+b0:
+    r2: bool = gt r0, r1
+    branch r2, b1, b2
+b1:
+    return r0
+b2:
+    return r1
+b3:
+    missing_return
+}
+
+fn main() {
+b0:
+    r0: int = const 10
+    jump b1
+b1:
+    r1: int = const 0
+    r2: bool = gt r0, r1
+    branch r2, b2, b3
+b2:
+    r3: int = const 2
+    r4: int = mod r0, r3
+    r5: int = const 0
+    r6: bool = eq r4, r5
+    branch r6, b4, b5
+b3:
+    return
+b4:
+    println r0
+    jump b5
+b5:
+    r7: int = const 1
+    r0: int = sub r0, r7
+    jump b1
+}
+"
+    );
+}
+
+#[test]
+fn for_loops() {
+    // The end is evaluated once, the variable can be assigned in the body,
+    // and the loop ends instead of overflowing.
+    assert_eq!(
+        dump("fn main() { let n: int = 3; for i = n - 2 to n { n = i; } }"),
+        "\
+fn main() {
+b0:
+    r0: int = const 3
+    r1: int = const 2
+    r2: int = sub r0, r1
+    r3: int = copy r0
+    jump b1
+b1:
+    r4: bool = gt r2, r3
+    branch r4, b5, b2
+b2:
+    r0: int = copy r2
+    jump b3
+b3:
+    r5: int = const 9223372036854775807
+    r6: bool = eq r2, r5
+    branch r6, b5, b4
+b4:
+    r7: int = const 1
+    r2: int = add r2, r7
+    jump b1
+b5:
+    return
+}
+"
+    );
+}
+
+#[test]
+fn short_circuit() {
+    assert_eq!(
+        dump("fn f(): bool { return true; } fn g(a: bool) { a = f() and not f() or a; }"),
+        "\
+fn f(): bool {
+b0:
+    r0: bool = const true
+    return r0
+}
+
+fn g(r0: bool) {
+b0:
+    r1: bool = call f()
+    branch r1, b1, b2
+b1:
+    r2: bool = call f()
+    r1: bool = not r2
+    jump b2
+b2:
+    branch r1, b4, b3
+b3:
+    r1: bool = copy r0
+    jump b4
+b4:
+    r0: bool = copy r1
+    return
+}
+"
+    );
+}
+
+#[test]
+fn evaluation_order() {
+    // Operands keep their values when later operands assign variables.
+    assert_eq!(
+        dump("fn main() { let x: int = 1; println(x + (x = 5), x); }"),
+        "\
+fn main() {
+b0:
+    r0: int = const 1
+    r1: int = copy r0
+    r0: int = const 5
+    r2: int = add r1, r0
+    println r2, r0
+    return
+}
+"
+    );
+}
+
+#[test]
+fn unreachable_code() {
+    let program = ir::lower(&parse(
+        "fn f(): int { return 1; println(2); return 3; } fn main() { }",
+    ))
+    .unwrap();
+    let f = program.function("f").unwrap();
+
+    assert_eq!(ir::verify(&program), Ok(()));
+    assert_eq!(f.blocks.len(), 2);
+    assert_eq!(f.reverse_postorder(), [BlockId(0)]);
+    assert_eq!(f.predecessors(), [vec![], vec![]]);
+    assert_eq!(
+        f.block(BlockId(1)).terminator,
+        Some(Terminator::Return(Some(Reg(2))))
+    );
+}
+
+#[test]
+fn duplicate_functions() {
+    let program = ir::lower(&parse(
+        "fn f(): int { return 1; } fn f(): int { return 2; }",
+    ))
+    .unwrap();
+
+    assert_eq!(program.functions.len(), 1);
+    assert_eq!(
+        program.functions[0].blocks[0].instrs,
+        [Instr::Const {
+            dest: Reg(0),
+            value: Constant::Int(2)
+        }]
+    );
+}
+
+#[test]
+fn tail_calls() {
+    // Arguments are copied before the parameters change, and the function
+    // starts again from the block after the entry.
+    assert_eq!(
+        dump(
+            "fn gcd(a: int, b: int): int {
+                if b == 0 return a;
+                return gcd(b, a % b);
+            }
+
+            fn fact(n: int): int {
+                if n <= 1 return 1;
+                return n * fact(n - 1);
+            }"
+        ),
+        "\
+fn gcd(r0: int, r1: int): int {
+b0:
+    jump b1
+b1:
+    r2: int = const 0
+    r3: bool = eq r1, r2
+    branch r3, b2, b3
+b2:
+    return r0
+b3:
+    r4: int = mod r0, r1
+    r5: int = copy r1
+    r0: int = copy r5
+    r1: int = copy r4
+    jump b1
+}
+
+fn fact(r0: int): int {
+b0:
+    r1: int = const 1
+    r2: bool = leq r0, r1
+    branch r2, b1, b2
+b1:
+    r3: int = const 1
+    return r3
+b2:
+    r4: int = const 1
+    r5: int = sub r0, r4
+    r6: int = call fact(r5)
+    r7: int = mul r0, r6
+    return r7
+}
+"
+    );
+}
+
+#[test]
+fn verifier() {
+    let mut program = ir::lower(&parse("fn f(a: int) { } fn main() { f(1); }")).unwrap();
+    assert_eq!(ir::verify(&program), Ok(()));
+
+    let main = &mut program.functions[1];
+    main.blocks[0].terminator = Some(Terminator::Branch {
+        cond: Reg(7),
+        then_block: BlockId(0),
+        else_block: BlockId(3),
+    });
+    main.blocks[0].instrs.push(Instr::Call {
+        dest: None,
+        function: "f".into(),
+        args: vec![],
+        pos: (1, 1),
+    });
+    main.blocks.push(Block::default());
+    program.functions[0].blocks.clear();
+
+    let errors = ir::verify(&program).unwrap_err();
+    assert_eq!(
+        errors,
+        [
+            VerifyError::NoEntry {
+                function: "f".into()
+            },
+            VerifyError::InvalidCall {
+                function: "main".into(),
+                block: BlockId(0),
+                callee: "f".into()
+            },
+            VerifyError::UnknownRegister {
+                function: "main".into(),
+                block: BlockId(0),
+                reg: Reg(7)
+            },
+            VerifyError::UnknownBlock {
+                function: "main".into(),
+                block: BlockId(0),
+                target: BlockId(3)
+            },
+            VerifyError::MissingTerminator {
+                function: "main".into(),
+                block: BlockId(1)
+            },
+        ]
+    );
+    assert_eq!(
+        errors[4].to_string(),
+        "Block b1 of `main` does not end in a terminator"
+    );
+    assert!(program
+        .to_string()
+        .ends_with("b1:\n    <no terminator>\n}\n"));
+}
+
+#[test]
+fn errors() {
+    let lower = |code| ir::lower(&parse(code)).unwrap_err();
+
+    assert_eq!(
+        lower("fn main() { println(a); }"),
+        LowerError::UndefinedVariable {
+            id: "a".into(),
+            line: 1,
+            column: 21
+        }
+    );
+    assert_eq!(
+        lower("fn main() { g(); }"),
+        LowerError::UndefinedFunction {
+            id: "g".into(),
+            line: 1,
+            column: 13
+        }
+    );
+    assert_eq!(
+        lower("fn main() { f(1); } fn f() { }"),
+        LowerError::WrongArgumentCount {
+            function: "f".into(),
+            expected: 0,
+            found: 1,
+            line: 1,
+            column: 13
+        }
+    );
+    assert_eq!(
+        lower("fn f() { } fn main() { let a: int = f(); }"),
+        LowerError::InvalidCode {
+            line: 1,
+            column: 37
+        }
+    );
+}
+
+#[test]
+fn ssa_construction() {
+    let (ssa, _) = optimize(
+        "fn f(n: int): int {
+            let s: int = 0;
+            while n > 0 {
+                if n % 2 == 0 { s = s + n; }
+                n = n - 1;
+            }
+            return s;
+        }",
+        &[],
+    );
+    assert_eq!(
+        ssa,
+        "\
+fn f(r0: int): int {
+b0:
+    r1: int = const 0
+    jump b1
+b1:
+    r2: int = phi [b0: r0], [b4: r13]
+    r3: int = phi [b0: r1], [b4: r11]
+    r4: int = const 0
+    r5: bool = gt r2, r4
+    branch r5, b2, b5
+b2:
+    r6: int = const 2
+    r7: int = mod r2, r6
+    r8: int = const 0
+    r9: bool = eq r7, r8
+    branch r9, b3, b4
+b3:
+    r10: int = add r3, r2
+    jump b4
+b4:
+    r11: int = phi [b2: r3], [b3: r10]
+    r12: int = const 1
+    r13: int = sub r2, r12
+    jump b1
+b5:
+    return r3
+}
+"
+    );
+}
+
+#[test]
+fn constprop() {
+    let (before, after) = optimize(
+        "fn main() {
+            let x: int = 4;
+            let y: int = x * 2 + 1;
+            if y > 5 { println(y / 0, 7 % 2); } else { println(-y); }
+        }",
+        &[Pass::ConstProp],
+    );
+    assert_eq!(
+        before,
+        "\
+fn main() {
+b0:
+    r0: int = const 4
+    r1: int = const 2
+    r2: int = mul r0, r1
+    r3: int = const 1
+    r4: int = add r2, r3
+    r5: int = const 5
+    r6: bool = gt r4, r5
+    branch r6, b1, b2
+b1:
+    r7: int = const 0
+    r8: int = div r4, r7
+    r9: int = const 7
+    r10: int = const 2
+    r11: int = mod r9, r10
+    println r8, r11
+    jump b3
+b2:
+    r12: int = neg r4
+    println r12
+    jump b3
+b3:
+    return
+}
+"
+    );
+    assert_eq!(
+        after,
+        "\
+fn main() {
+b0:
+    r0: int = const 4
+    r1: int = const 2
+    r2: int = const 8
+    r3: int = const 1
+    r4: int = const 9
+    r5: int = const 5
+    r6: bool = const true
+    jump b1
+b1:
+    r7: int = const 0
+    r8: int = div r4, r7
+    r9: int = const 7
+    r10: int = const 2
+    r11: int = const 1
+    println r8, r11
+    jump b2
+b2:
+    return
+}
+"
+    );
+
+    // Phis which are not folded stay before the constants of folded phis.
+    let (_, after) = optimize(
+        "fn id(x: int): int { return x; }
+        fn main() {
+            let a: int = 0;
+            let b: int = 7;
+            if id(2) == 2 { a = 0; b = id(3); }
+            println(a, b);
+        }",
+        Pass::level(1),
+    );
+    assert!(after.contains("phi"));
+}
+
+#[test]
+fn copyprop() {
+    let (before, after) = optimize(
+        "fn f(a: int, k: bool): int {
+            let b: int = a;
+            if k { b = b; }
+            return b * b;
+        }",
+        &[Pass::CopyProp],
+    );
+    assert_eq!(
+        before,
+        "\
+fn f(r0: int, r1: bool): int {
+b0:
+    r2: int = copy r0
+    branch r1, b1, b2
+b1:
+    r3: int = copy r2
+    jump b2
+b2:
+    r4: int = phi [b0: r2], [b1: r3]
+    r5: int = mul r4, r4
+    return r5
+}
+"
+    );
+    assert_eq!(
+        after,
+        "\
+fn f(r0: int, r1: bool): int {
+b0:
+    branch r1, b1, b2
+b1:
+    jump b2
+b2:
+    r2: int = mul r0, r0
+    return r2
+}
+"
+    );
+}
+
+#[test]
+fn dce() {
+    let (before, after) = optimize(
+        "fn g(): int { return 1; }
+        fn f(a: int, b: int): int {
+            let x: int = a * b;
+            let q: int = a / b;
+            let r: int = a / 2;
+            let s: float = 1.0 / 0.0;
+            let t: int = g();
+            while x > 0 { x = x - 1; }
+            return a;
+        }",
+        &[Pass::Dce],
+    );
+    assert_eq!(
+        before,
+        "\
+fn g(): int {
+b0:
+    r0: int = const 1
+    return r0
+}
+
+fn f(r0: int, r1: int): int {
+b0:
+    r2: int = mul r0, r1
+    r3: int = div r0, r1
+    r4: int = const 2
+    r5: int = div r0, r4
+    r6: float = const 1.0
+    r7: float = const 0.0
+    r8: float = div r6, r7
+    r9: int = call g()
+    jump b1
+b1:
+    r10: int = phi [b0: r2], [b2: r14]
+    r11: int = const 0
+    r12: bool = gt r10, r11
+    branch r12, b2, b3
+b2:
+    r13: int = const 1
+    r14: int = sub r10, r13
+    jump b1
+b3:
+    return r0
+}
+"
+    );
+    assert_eq!(
+        after,
+        "\
+fn g(): int {
+b0:
+    r0: int = const 1
+    return r0
+}
+
+fn f(r0: int, r1: int): int {
+b0:
+    r2: int = mul r0, r1
+    r3: int = div r0, r1
+    call g()
+    jump b1
+b1:
+    r4: int = phi [b0: r2], [b2: r8]
+    r5: int = const 0
+    r6: bool = gt r4, r5
+    branch r6, b2, b3
+b2:
+    r7: int = const 1
+    r8: int = sub r4, r7
+    jump b1
+b3:
+    return r0
+}
+"
+    );
+}
+
+#[test]
+fn cse() {
+    let (before, after) = optimize(
+        "fn f(a: int, b: int, c: int[]): int {
+            let x: int = a * b + c[a];
+            if a > 0 { return b * a + c[a]; }
+            return a - b + (a - b);
+        }",
+        &[Pass::Cse],
+    );
+    assert_eq!(
+        before,
+        "\
+fn f(r0: int, r1: int, r2: int[]): int {
+b0:
+    r3: int = mul r0, r1
+    r4: int = index r2, r0
+    r5: int = add r3, r4
+    r6: int = const 0
+    r7: bool = gt r0, r6
+    branch r7, b1, b2
+b1:
+    r8: int = mul r1, r0
+    r9: int = index r2, r0
+    r10: int = add r8, r9
+    return r10
+b2:
+    r11: int = sub r0, r1
+    r12: int = sub r0, r1
+    r13: int = add r11, r12
+    return r13
+}
+"
+    );
+    assert_eq!(
+        after,
+        "\
+fn f(r0: int, r1: int, r2: int[]): int {
+b0:
+    r3: int = mul r0, r1
+    r4: int = index r2, r0
+    r5: int = add r3, r4
+    r6: int = const 0
+    r7: bool = gt r0, r6
+    branch r7, b1, b2
+b1:
+    return r5
+b2:
+    r8: int = sub r0, r1
+    r9: int = add r8, r8
+    return r9
+}
+"
+    );
+}
+
+#[test]
+fn licm() {
+    let (before, after) = optimize(
+        "fn f(n: int, k: int): int {
+            let s: int = 0;
+            for i = 1 to n {
+                for j = 1 to n { s = s + k * n + i / 2; }
+            }
+            return s;
+        }",
+        &[Pass::Licm],
+    );
+    assert_eq!(
+        before,
+        "\
+fn f(r0: int, r1: int): int {
+b0:
+    r2: int = const 0
+    r3: int = const 1
+    r4: int = copy r0
+    jump b1
+b1:
+    r5: int = phi [b0: r2], [b9: r20]
+    r6: int = phi [b0: r3], [b9: r25]
+    r7: bool = gt r6, r4
+    branch r7, b8, b2
+b2:
+    r8: int = const 1
+    r9: int = copy r0
+    jump b3
+b3:
+    r10: int = phi [b2: r5], [b10: r17]
+    r11: int = phi [b2: r8], [b10: r27]
+    r12: bool = gt r11, r9
+    branch r12, b6, b4
+b4:
+    r13: int = mul r1, r0
+    r14: int = add r10, r13
+    r15: int = const 2
+    r16: int = div r6, r15
+    r17: int = add r14, r16
+    jump b5
+b5:
+    r18: int = const 9223372036854775807
+    r19: bool = eq r11, r18
+    branch r19, b6, b10
+b6:
+    r20: int = phi [b3: r10], [b5: r17]
+    jump b7
+b7:
+    r21: int = const 9223372036854775807
+    r22: bool = eq r6, r21
+    branch r22, b8, b9
+b8:
+    r23: int = phi [b1: r5], [b7: r20]
+    return r23
+b9:
+    r24: int = const 1
+    r25: int = add r6, r24
+    jump b1
+b10:
+    r26: int = const 1
+    r27: int = add r11, r26
+    jump b3
+}
+"
+    );
+    assert_eq!(
+        after,
+        "\
+fn f(r0: int, r1: int): int {
+b0:
+    r2: int = const 0
+    r3: int = const 1
+    r4: int = copy r0
+    r5: int = const 1
+    r6: int = copy r0
+    r7: int = mul r1, r0
+    r8: int = const 2
+    r9: int = const 9223372036854775807
+    r10: int = const 1
+    r11: int = const 9223372036854775807
+    r12: int = const 1
+    jump b1
+b1:
+    r13: int = phi [b0: r2], [b9: r23]
+    r14: int = phi [b0: r3], [b9: r26]
+    r15: bool = gt r14, r4
+    branch r15, b8, b2
+b2:
+    r16: int = div r14, r8
+    jump b3
+b3:
+    r17: int = phi [b2: r13], [b10: r21]
+    r18: int = phi [b2: r5], [b10: r27]
+    r19: bool = gt r18, r6
+    branch r19, b6, b4
+b4:
+    r20: int = add r17, r7
+    r21: int = add r20, r16
+    jump b5
+b5:
+    r22: bool = eq r18, r9
+    branch r22, b6, b10
+b6:
+    r23: int = phi [b3: r17], [b5: r21]
+    jump b7
+b7:
+    r24: bool = eq r14, r11
+    branch r24, b8, b9
+b8:
+    r25: int = phi [b1: r13], [b7: r23]
+    return r25
+b9:
+    r26: int = add r14, r12
+    jump b1
+b10:
+    r27: int = add r18, r10
+    jump b3
+}
+"
+    );
+}
+
+#[test]
+fn inline() {
+    let code = "fn add(a: int, b: int): int {
+            return a + b;
+        }
+
+        fn max(a: int, b: int): int {
+            if a > b {
+                return a;
+            }
+            return b;
+        }
+
+        fn fact(n: int): int {
+            if n <= 1 return 1;
+            return n * fact(n - 1);
+        }
+
+        fn check(n: int): int {
+            if n > 0 return n;
+        }
+
+        fn main() {
+            println(max(add(1, 2), 4), fact(3), check(1));
+        }";
+
+    // Recursive functions and functions which may not return a value are
+    // not inlined.
+    let (_, after) = optimize(code, &[Pass::Inline]);
+    let main = after.split("fn main").nth(1).unwrap();
+    assert_eq!(
+        main,
+        "() {
+b0:
+    r0: int = const 1
+    r1: int = const 2
+    jump b1
+b1:
+    r2: int = add r0, r1
+    jump b2
+b2:
+    r3: int = phi [b1: r2]
+    r4: int = const 4
+    jump b3
+b3:
+    r5: bool = gt r3, r4
+    branch r5, b4, b5
+b4:
+    jump b6
+b5:
+    jump b6
+b6:
+    r6: int = phi [b4: r3], [b5: r4]
+    r7: int = const 3
+    r8: int = call fact(r7)
+    r9: int = const 1
+    r10: int = call check(r9)
+    println r6, r8, r10
+    return
+}
+"
+    );
+
+    let (_, after) = optimize(code, Pass::level(2));
+    let main = after.split("fn main").nth(1).unwrap();
+    assert_eq!(
+        main,
+        "() {
+b0:
+    r0: int = const 1
+    r1: int = const 3
+    r2: int = const 4
+    r3: int = call fact(r1)
+    r4: int = call check(r0)
+    println r2, r3, r4
+    return
+}
+"
+    );
+
+    // Tail calls are jumps, so the function is not recursive.
+    let (_, after) = optimize(
+        "fn sum(n: int, acc: int): int {
+            if n == 0 return acc;
+            return sum(n - 1, acc + n);
+        }
+
+        fn main() { println(sum(10, 0)); }",
+        &[Pass::Inline, Pass::CopyProp, Pass::Dce],
+    );
+    assert!(!after.split("fn main").nth(1).unwrap().contains("call"));
+}
+
+#[test]
+fn ssa_destruction() {
+    let mut program = ir::lower(&parse(
+        "fn f(a: int, b: int): int {
+            while a > 0 {
+                let t: int = a;
+                a = b;
+                b = t;
+            }
+            return b;
+        }",
+    ))
+    .unwrap();
+    ir::optimize(&mut program, &[Pass::CopyProp]);
+    let f = &mut program.functions[0];
+    assert!(f.to_string().contains("phi [b0: r1], [b2: r2]"));
+
+    ir::ssa::destruct(f);
+    assert_eq!(ir::verify(&program), Ok(()));
+    // Copies of the phis read the values from before the loop header.
+    assert_eq!(
+        program.to_string(),
+        "\
+fn f(r0: int, r1: int): int {
+b0:
+    r6: int = copy r0
+    r7: int = copy r1
+    jump b1
+b1:
+    r2: int = copy r6
+    r3: int = copy r7
+    r4: int = const 0
+    r5: bool = gt r2, r4
+    branch r5, b2, b3
+b2:
+    r6: int = copy r3
+    r7: int = copy r2
+    jump b1
+b3:
+    return r3
+}
+"
+    );
+}
+
+#[test]
+fn loop_preheader() {
+    // The loop is entered from a branch, so it needs a new preheader.
+    let branch = Terminator::Branch {
+        cond: Reg(0),
+        then_block: BlockId(1),
+        else_block: BlockId(2),
+    };
+    let mut program = ir::Program {
+        functions: vec![Function {
+            name: "f".into(),
+            arity: 1,
+            return_type: None,
+            regs: vec![Type::Bool, Type::Int],
+            blocks: vec![
+                Block {
+                    instrs: vec![],
+                    terminator: Some(branch.clone()),
+                },
+                Block {
+                    instrs: vec![
+                        Instr::Const {
+                            dest: Reg(1),
+                            value: Constant::Int(5),
+                        },
+                        Instr::Println { args: vec![Reg(1)] },
+                    ],
+                    terminator: Some(branch),
+                },
+                Block {
+                    instrs: vec![],
+                    terminator: Some(Terminator::Return(None)),
+                },
+            ],
+            pos: (1, 1),
+        }],
+    };
+
+    ir::optimize(&mut program, &[Pass::Licm]);
+    assert_eq!(ir::verify(&program), Ok(()));
+    assert_eq!(
+        program.to_string(),
+        "\
+fn f(r0: bool) {
+b0:
+    branch r0, b1, b3
+b1:
+    r1: int = const 5
+    jump b2
+b2:
+    println r1
+    branch r0, b2, b3
+b3:
+    return
+}
+"
+    );
+}
+
+#[test]
+fn optimization_levels() {
+    let code = "fn main() {
+            let debug: bool = false;
+            let n: int = 10;
+            if debug { println(\"n = \", n); }
+            let i: int = 0;
+            while i < n {
+                println(i * (n + 1));
+                i = i + 1;
+            }
+        }";
+
+    let (_, o1) = optimize(code, Pass::level(1));
+    assert_eq!(
+        o1,
+        "\
+fn main() {
+b0:
+    r0: int = const 10
+    r1: int = const 0
+    jump b1
+b1:
+    r2: int = phi [b0: r1], [b2: r7]
+    r3: bool = lt r2, r0
+    branch r3, b2, b3
+b2:
+    r4: int = const 11
+    r5: int = mul r2, r4
+    println r5
+    r6: int = const 1
+    r7: int = add r2, r6
+    jump b1
+b3:
+    return
+}
+"
+    );
+    let (_, o2) = optimize(code, Pass::level(2));
+    assert_eq!(
+        o2,
+        "\
+fn main() {
+b0:
+    r0: int = const 10
+    r1: int = const 0
+    r2: int = const 1
+    r3: int = const 11
+    jump b1
+b1:
+    r4: int = phi [b0: r1], [b2: r7]
+    r5: bool = lt r4, r0
+    branch r5, b2, b3
+b2:
+    r6: int = mul r4, r3
+    println r6
+    r7: int = add r4, r2
+    jump b1
+b3:
+    return
+}
+"
+    );
+
+    assert!(Pass::level(0).is_empty());
+    for pass in Pass::ALL {
+        assert_eq!(Pass::from_name(pass.name()), Some(pass));
+    }
+    assert_eq!(Pass::from_name("unroll"), None);
+}
+
+#[test]
+fn invalid_phis() {
+    let mut program = ir::lower(&parse(
+        "fn f(n: int): int { if n < 0 { n = 0; } return n; }",
+    ))
+    .unwrap();
+    ir::optimize(&mut program, &[]);
+    let f = &mut program.functions[0];
+    f.blocks[1].instrs.push(Instr::Phi {
+        dest: Reg(5),
+        args: vec![(BlockId(0), Reg(0))],
+    });
+    f.blocks[2].instrs.insert(
+        0,
+        Instr::Const {
+            dest: Reg(1),
+            value: Constant::Int(1),
+        },
+    );
+    f.blocks[2].instrs.push(Instr::Phi {
+        dest: Reg(6),
+        args: vec![(BlockId(1), Reg(0)), (BlockId(2), Reg(0))],
+    });
+    f.regs.extend([Type::Int, Type::Int]);
+
+    let errors = ir::verify(&program).unwrap_err();
+    assert_eq!(
+        errors,
+        [
+            VerifyError::InvalidPhi {
+                function: "f".into(),
+                block: BlockId(1),
+                dest: Reg(5)
+            },
+            VerifyError::InvalidPhi {
+                function: "f".into(),
+                block: BlockId(2),
+                dest: Reg(4)
+            },
+            VerifyError::InvalidPhi {
+                function: "f".into(),
+                block: BlockId(2),
+                dest: Reg(6)
+            },
+        ]
+    );
+    assert_eq!(
+        errors[0].to_string(),
+        "Block b1 of `f` has an invalid phi for r5"
+    );
+}
@@ -0,0 +1,331 @@
+use std::path::Path;
+
+use ir::Pass;
+
+/// Help message of the command line interface.
+pub const USAGE: &str = "\
+Usage: kolang <COMMAND> [OPTIONS] [FILE]
+
+Reads Kolang code from FILE, or from standard input if FILE is `-` or missing.
+`run` and `check` also accept bytecode files written by `compile`.
+
+Commands:
+  tokens    Print the tokens of the code
+  parse     Print the syntax tree of the code
+  check     Check the code for errors without running it
+  run       Check and run the code
+  compile   Check the code and write its bytecode to a .kbc file
+  build     Check the code and build an executable with the C compiler (`CC` or `cc`),
+            or the assembler and linker (`AS` or `as`, `LD` or `ld`), or write a
+            WebAssembly module
+  fmt       Print the code in the canonical style, keeping its comments
+  repl      Run code entered line by line on the interpreter, printing the values of
+            expressions (reads no FILE)
+
+Options:
+  --emit=<KIND>[,<KIND>...]     Also print intermediate forms of the code: tokens, ast, ir, bytecode,
+                                c, asm, wat, tokens-json, ast-json
+  --backend=<BACKEND>           Code generator which `build` uses: c (default), x86-64, wasm
+  --engine=<ENGINE>             Engine which runs the code: vm (default), interpreter
+  -O<LEVEL>                     Optimize the intermediate representation before compiling it to
+                                bytecode: 0 (default, no optimization), 1, 2
+  --passes=<PASS>[,<PASS>...]   Optimize with these passes instead of a level: inline, constprop,
+                                copyprop, cse, licm, dce
+  --error-format=<FORMAT>       Format of errors: human (default), json
+  --check                       With `fmt`, print nothing and exit with 1 if the code is not
+                                formatted
+  -o, --output=<FILE>           Output of `compile` and `build` (default: FILE with .kbc
+                                extension, FILE without extension, or FILE with .wasm extension)
+  -h, --help                    Print this help message
+  -V, --version                 Print version
+
+Exit codes:
+  0  Success
+  1  The code has errors
+  2  Invalid command line arguments
+  3  The program failed at runtime
+  4  The input could not be read
+  5  The C compiler, assembler or linker failed
+";
+
+/// Subcommand of the command line interface.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    /// Print the tokens of the code.
+    Tokens,
+    /// Print the syntax tree of the code.
+    Parse,
+    /// Check the code for errors.
+    Check,
+    /// Check and run the code.
+    Run,
+    /// Check the code and write its bytecode.
+    Compile,
+    /// Check the code and build an executable.
+    Build,
+    /// Print the code in the canonical style.
+    Fmt,
+    /// Run code interactively.
+    Repl,
+}
+
+/// Intermediate form of the code which can be printed with `--emit`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Emit {
+    /// Tokens produced by the lexer.
+    Tokens,
+    /// Syntax tree produced by the parser.
+    Ast,
+    /// Tokens produced by the lexer, as a JSON array.
+    TokensJson,
+    /// Syntax tree produced by the parser, as a JSON array of top level
+    /// statements.
+    AstJson,
+    /// Control flow graphs of the intermediate representation.
+    Ir,
+    /// Bytecode produced by the compiler.
+    Bytecode,
+    /// C code produced by the code generator.
+    C,
+    /// x86-64 assembly produced by the code generator.
+    Asm,
+    /// WebAssembly text of the module produced by the code generator.
+    Wat,
+}
+
+/// Engine which runs the code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Engine {
+    /// Compile the code to bytecode and run it on the virtual machine.
+    #[default]
+    Vm,
+    /// Walk the syntax tree.
+    Interpreter,
+}
+
+/// Code generator which builds executables.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Backend {
+    /// Generate C code and build it with the C compiler.
+    #[default]
+    C,
+    /// Generate x86-64 assembly and build it with the assembler and linker.
+    X86_64,
+    /// Generate a WebAssembly module, which runs on a host.
+    Wasm,
+}
+
+/// Format of the reported errors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ErrorFormat {
+    /// Source snippets for humans.
+    #[default]
+    Human,
+    /// One JSON object per line for other tools.
+    Json,
+}
+
+/// Options of a subcommand which processes code.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Options {
+    /// The subcommand.
+    pub command: Command,
+    /// Path of the input file. `None` for standard input.
+    pub input: Option<String>,
+    /// Path of the output file of `compile` and `build`.
+    pub output: Option<String>,
+    /// Intermediate forms to print.
+    pub emit: Vec<Emit>,
+    /// Engine which runs the code.
+    pub engine: Engine,
+    /// Optimization passes which run on the intermediate representation
+    /// before it is compiled to bytecode. `None` if the syntax tree is
+    /// compiled to bytecode directly.
+    pub passes: Option<Vec<Pass>>,
+    /// Code generator which builds executables.
+    pub backend: Backend,
+    /// Format of the reported errors.
+    pub error_format: ErrorFormat,
+    /// Whether `fmt` only checks that the code is formatted.
+    pub check: bool,
+}
+
+impl Options {
+    /// Checks whether the intermediate form should be printed.
+    pub fn emits(&self, emit: Emit) -> bool {
+        self.emit.contains(&emit)
+    }
+}
+
+/// What the command line asks the driver to do.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Action {
+    /// Print help message.
+    Help,
+    /// Print version.
+    Version,
+    /// Process code.
+    Process(Options),
+}
+
+/// Parses command line arguments (without the program name).
+/// Returns a message describing the problem if arguments are invalid.
+pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Action, String> {
+    let mut args = args.into_iter();
+    let mut command = None;
+    let mut input = None;
+    let mut output = None;
+    let mut emit = Vec::new();
+    let mut engine = Engine::default();
+    let mut passes = None;
+    let mut backend = Backend::default();
+    let mut error_format = ErrorFormat::default();
+    let mut check = false;
+    let mut only_files = false;
+
+    while let Some(arg) = args.next() {
+        if only_files || arg == "-" || !arg.starts_with('-') {
+            if command.is_none() {
+                command = Some(parse_command(&arg)?);
+            } else if input.is_none() {
+                input = Some(arg);
+            } else {
+                return Err(format!("Unexpected argument `{}`", arg));
+            }
+            continue;
+        }
+
+        let (name, value) = match arg.split_once('=') {
+            Some((name, value)) => (name, Some(value.to_string())),
+            None => (arg.as_str(), None),
+        };
+        match name {
+            "-h" | "--help" => return Ok(Action::Help),
+            "-V" | "--version" => return Ok(Action::Version),
+            "--" => only_files = true,
+            "--emit" => {
+                for kind in option_value(name, value, &mut args)?.split(',') {
+                    emit.push(match kind {
+                        "tokens" => Emit::Tokens,
+                        "ast" => Emit::Ast,
+                        "tokens-json" => Emit::TokensJson,
+                        "ast-json" => Emit::AstJson,
+                        "ir" => Emit::Ir,
+                        "bytecode" => Emit::Bytecode,
+                        "c" => Emit::C,
+                        "asm" => Emit::Asm,
+                        "wat" => Emit::Wat,
+                        _ => return Err(format!("Unknown emit kind `{}`", kind)),
+                    });
+                }
+            }
+            "-o" | "--output" => output = Some(option_value(name, value, &mut args)?),
+            "--engine" => {
+                engine = match option_value(name, value, &mut args)?.as_str() {
+                    "vm" => Engine::Vm,
+                    "interpreter" => Engine::Interpreter,
+                    e => return Err(format!("Unknown engine `{}`", e)),
+                };
+            }
+            "-O0" => passes = None,
+            "-O1" => passes = Some(Pass::level(1).to_vec()),
+            "-O2" => passes = Some(Pass::level(2).to_vec()),
+            "--passes" => {
+                let value = option_value(name, value, &mut args)?;
+                let mut list = Vec::new();
+                for pass in value.split(',').filter(|p| !p.is_empty()) {
+                    list.push(
+                        Pass::from_name(pass).ok_or_else(|| format!("Unknown pass `{}`", pass))?,
+                    );
+                }
+                passes = Some(list);
+            }
+            "--backend" => {
+                backend = match option_value(name, value, &mut args)?.as_str() {
+                    "c" => Backend::C,
+                    "x86-64" => Backend::X86_64,
+                    "wasm" => Backend::Wasm,
+                    b => return Err(format!("Unknown backend `{}`", b)),
+                };
+            }
+            "--error-format" => {
+                error_format = match option_value(name, value, &mut args)?.as_str() {
+                    "human" => ErrorFormat::Human,
+                    "json" => ErrorFormat::Json,
+                    f => return Err(format!("Unknown error format `{}`", f)),
+                };
+            }
+            "--check" => check = true,
+            _ => return Err(format!("Unknown option `{}`", name)),
+        }
+    }
+
+    let command = command.ok_or("Missing command")?;
+    let input = input.filter(|i| i != "-");
+    if command == Command::Repl && input.is_some() {
+        return Err("Command `repl` does not read FILE".into());
+    }
+    if check && command != Command::Fmt {
+        return Err("Option `--check` is only used by `fmt`".into());
+    }
+    let extension = match command {
+        Command::Compile => Some("kbc"),
+        Command::Build if backend == Backend::Wasm => Some("wasm"),
+        Command::Build => Some(std::env::consts::EXE_EXTENSION),
+        _ => None,
+    };
+    let output = match (extension, output, &input) {
+        (Some(_), Some(output), _) => Some(output),
+        (Some(extension), None, Some(path)) => {
+            let output = Path::new(path).with_extension(extension);
+            if output == Path::new(path) {
+                return Err("Option `--output` is required when FILE has no extension".into());
+            }
+            Some(output.to_string_lossy().into_owned())
+        }
+        (Some(_), None, None) => {
+            return Err("Option `--output` is required when reading standard input".into())
+        }
+        (None, Some(_), _) => {
+            return Err("Option `--output` is only used by `compile` and `build`".into())
+        }
+        (None, None, _) => None,
+    };
+    Ok(Action::Process(Options {
+        command,
+        input,
+        output,
+        emit,
+        engine,
+        passes,
+        backend,
+        error_format,
+        check,
+    }))
+}
+
+/// Returns value of an option, which is either after `=` in the same argument
+/// (`--emit=ast`) or the next argument (`--emit ast`).
+fn option_value(
+    name: &str,
+    value: Option<String>,
+    args: &mut impl Iterator<Item = String>,
+) -> Result<String, String> {
+    value
+        .or_else(|| args.next())
+        .ok_or_else(|| format!("Option `{}` requires a value", name))
+}
+
+fn parse_command(arg: &str) -> Result<Command, String> {
+    match arg {
+        "tokens" => Ok(Command::Tokens),
+        "parse" => Ok(Command::Parse),
+        "check" => Ok(Command::Check),
+        "run" => Ok(Command::Run),
+        "compile" => Ok(Command::Compile),
+        "build" => Ok(Command::Build),
+        "fmt" => Ok(Command::Fmt),
+        "repl" => Ok(Command::Repl),
+        _ => Err(format!("Unknown command `{}`", arg)),
+    }
+}
@@ -0,0 +1,118 @@
+//! # Kolang formatter
+//! Formats Kolang files in place in the canonical style, keeping their
+//! comments.
+
+use std::{
+    fs,
+    io::{self, Read, Write},
+};
+
+use diagnostics::{Renderer, SourceFile};
+
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// The code has syntax errors, or is not formatted.
+const EXIT_ERRORS: i32 = 1;
+/// Invalid command line arguments.
+const EXIT_USAGE: i32 = 2;
+/// A file could not be read or written.
+const EXIT_IO: i32 = 4;
+
+const USAGE: &str = "\
+Usage: kolangfmt [OPTIONS] [FILE]...
+
+Formats Kolang files in place. Reads code from standard input and prints it
+formatted if no FILE is given, or FILE is `-`.
+
+Options:
+  --check        Print the names of the files which are not formatted instead of
+                 formatting them
+  -h, --help     Print this help message
+  -V, --version  Print version
+
+Exit codes:
+  0  Success
+  1  The code has syntax errors, or is not formatted (with `--check`)
+  2  Invalid command line arguments
+  4  A file could not be read or written
+";
+
+fn main() {
+    let mut check = false;
+    let mut files = Vec::new();
+    let mut only_files = false;
+    for arg in std::env::args().skip(1) {
+        match arg.as_str() {
+            _ if only_files || arg == "-" || !arg.starts_with('-') => files.push(arg),
+            "--" => only_files = true,
+            "--check" => check = true,
+            "-h" | "--help" => {
+                print!("{}", USAGE);
+                return;
+            }
+            "-V" | "--version" => {
+                println!("kolangfmt {}", VERSION);
+                return;
+            }
+            _ => {
+                eprintln!("kolangfmt: Unknown option `{}`\n\n{}", arg, USAGE);
+                std::process::exit(EXIT_USAGE);
+            }
+        }
+    }
+    if files.is_empty() {
+        files.push("-".to_string());
+    }
+
+    let mut code = 0;
+    for file in &files {
+        match format_file(file, check) {
+            Ok(c) => code = code.max(c),
+            Err(e) => {
+                eprintln!("kolangfmt: {}: {}", file, e);
+                code = EXIT_IO;
+            }
+        }
+    }
+    std::process::exit(code);
+}
+
+/// Formats a file (or standard input if the path is `-`) and returns the
+/// exit code.
+fn format_file(path: &str, check: bool) -> io::Result<i32> {
+    let (name, text) = if path == "-" {
+        let mut text = String::new();
+        io::stdin().lock().read_to_string(&mut text)?;
+        ("<stdin>", text)
+    } else {
+        (path, fs::read_to_string(path)?)
+    };
+
+    let formatted = match parser::format::format(&text) {
+        Ok(formatted) => formatted,
+        Err(diagnostics) => {
+            let file = SourceFile::new(name, &text);
+            let renderer = Renderer::for_stderr();
+            for d in &diagnostics {
+                eprintln!("{}", renderer.render(d, &file));
+            }
+            return Ok(EXIT_ERRORS);
+        }
+    };
+
+    if check {
+        if formatted == text {
+            return Ok(0);
+        }
+        println!("{}", name);
+        return Ok(EXIT_ERRORS);
+    }
+    if path == "-" {
+        let mut out = io::stdout().lock();
+        out.write_all(formatted.as_bytes())?;
+        out.flush()?;
+    } else if formatted != text {
+        fs::write(path, formatted)?;
+    }
+    Ok(0)
+}
@@ -0,0 +1,443 @@
+//! # Kolang
+//! Command line driver which runs the Kolang tools (lexer, parser,
+//! semantic analyzer, interpreter, virtual machine and code generator)
+//! on a file or standard input.
+
+use std::{
+    fs::{self, File},
+    io::{self, BufWriter, Read, Write},
+    path::Path,
+};
+
+use args::{Action, Backend, Command, Emit, Engine, ErrorFormat, Options};
+use codegen::error::CodegenError;
+use diagnostics::{json, Diagnostic, Renderer, Severity, SourceFile, Span};
+use interpreter::error::RuntimeError;
+use lexer::{
+    token::{Token, TokenType},
+    LexError, Lexer,
+};
+use parser::{ast, Parser};
+use serde::Serialize;
+
+mod args;
+mod repl;
+
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// The code has errors.
+const EXIT_ERRORS: i32 = 1;
+/// Invalid command line arguments.
+const EXIT_USAGE: i32 = 2;
+/// The program failed at runtime.
+const EXIT_RUNTIME: i32 = 3;
+/// The input could not be read.
+const EXIT_IO: i32 = 4;
+/// The C compiler, assembler or linker failed.
+const EXIT_BUILD: i32 = 5;
+
+fn main() {
+    let code = match args::parse(std::env::args().skip(1)) {
+        Ok(Action::Help) => {
+            print!("{}", args::USAGE);
+            0
+        }
+        Ok(Action::Version) => {
+            println!("kolang {}", VERSION);
+            0
+        }
+        Ok(Action::Process(options)) => match process(&options) {
+            Ok(code) => code,
+            Err(e) => {
+                eprintln!("kolang: {}", e);
+                EXIT_IO
+            }
+        },
+        Err(e) => {
+            eprintln!("kolang: {}\n\n{}", e, args::USAGE);
+            EXIT_USAGE
+        }
+    };
+
+    std::process::exit(code);
+}
+
+/// Runs the subcommand and returns the exit code.
+fn process(options: &Options) -> io::Result<i32> {
+    if options.command == Command::Repl {
+        return repl::run(options.error_format);
+    }
+
+    let (name, source) = match &options.input {
+        Some(path) => (path.as_str(), fs::read(path)?),
+        None => {
+            let mut buf = Vec::new();
+            io::stdin().lock().read_to_end(&mut buf)?;
+            ("<stdin>", buf)
+        }
+    };
+    if vm::kbc::is_bytecode(&source) {
+        let file = SourceFile::without_source(name);
+        return process_bytecode(options, &source, Reporter::new(options.error_format, &file));
+    }
+
+    let text = String::from_utf8_lossy(&source);
+    let file = SourceFile::new(name, &text);
+    let mut reporter = Reporter::new(options.error_format, &file);
+
+    let emits_tokens = options.command == Command::Tokens || options.emits(Emit::Tokens);
+    if emits_tokens || options.emits(Emit::TokensJson) {
+        let (tokens, diagnostics) = tokenize(&source)?;
+        if emits_tokens {
+            print_tokens(&tokens)?;
+        }
+        if options.emits(Emit::TokensJson) {
+            print_json(&tokens)?;
+        }
+        // Other commands report these errors while parsing.
+        if options.command == Command::Tokens {
+            return Ok(reporter.report(&diagnostics));
+        }
+    }
+
+    if options.command == Command::Fmt {
+        return format_code(options.check, name, &text, &mut reporter);
+    }
+
+    let (ast, diagnostics) = Parser::new(Lexer::new(source.as_slice())).parse()?;
+    if reporter.report(&diagnostics) != 0 {
+        return Ok(EXIT_ERRORS);
+    }
+
+    if options.command == Command::Parse || options.emits(Emit::Ast) {
+        print_ast(&ast)?;
+    }
+    if options.emits(Emit::AstJson) {
+        print_json(&ast)?;
+    }
+    if options.command == Command::Parse {
+        return Ok(0);
+    }
+
+    let (_, diagnostics) = semantic::resolver::resolve(&ast);
+    if reporter.report(&diagnostics) != 0 {
+        return Ok(EXIT_ERRORS);
+    }
+    let code = reporter.report(&semantic::type_checker::check(&ast));
+    if code != 0 {
+        return Ok(code);
+    }
+
+    let compiled = (options.engine == Engine::Vm && options.command != Command::Build)
+        || options.emits(Emit::Bytecode)
+        || options.command == Command::Compile;
+    let optimized = compiled && options.passes.is_some();
+    let ir = if options.emits(Emit::Ir) || optimized {
+        match ir::lower(&ast) {
+            Ok(mut program) => {
+                if let Some(passes) = &options.passes {
+                    ir::optimize(&mut program, passes);
+                }
+                Some(program)
+            }
+            Err(e) => {
+                let (line, column) = e.position();
+                return Ok(reporter
+                    .report(&[Diagnostic::error(e.to_string(), file.span_at(line, column))]));
+            }
+        }
+    } else {
+        None
+    };
+    if options.emits(Emit::Ir) {
+        if let Some(program) = &ir {
+            write!(io::stdout().lock(), "{}", program)?;
+        }
+    }
+
+    let program = match &ir {
+        Some(ir) if optimized => Some(vm::compile_ir(ir)),
+        _ if compiled => match vm::compile(&ast) {
+            Ok(program) => Some(program),
+            Err(e) => {
+                let (line, column) = e.position();
+                return Ok(reporter
+                    .report(&[Diagnostic::error(e.to_string(), file.span_at(line, column))]));
+            }
+        },
+        _ => None,
+    };
+    if options.emits(Emit::Bytecode) {
+        if let Some(program) = &program {
+            print_bytecode(program)?;
+        }
+    }
+
+    let builds = |backend| options.command == Command::Build && options.backend == backend;
+    let c_code = if builds(Backend::C) || options.emits(Emit::C) {
+        match codegen::c::generate(&ast, name) {
+            Ok(code) => Some(code),
+            Err(e) => return Ok(reporter.report(&[codegen_error(&file, &e)])),
+        }
+    } else {
+        None
+    };
+    if options.emits(Emit::C) {
+        if let Some(code) = &c_code {
+            io::stdout().lock().write_all(code.as_bytes())?;
+        }
+    }
+    let asm = if builds(Backend::X86_64) || options.emits(Emit::Asm) {
+        match codegen::x86_64::generate(&ast, name) {
+            Ok(code) => Some(code),
+            Err(e) => return Ok(reporter.report(&[codegen_error(&file, &e)])),
+        }
+    } else {
+        None
+    };
+    if options.emits(Emit::Asm) {
+        if let Some(code) = &asm {
+            io::stdout().lock().write_all(code.as_bytes())?;
+        }
+    }
+    let module = if builds(Backend::Wasm) || options.emits(Emit::Wat) {
+        match codegen::wasm::generate(&ast) {
+            Ok(module) => Some(module),
+            Err(e) => return Ok(reporter.report(&[codegen_error(&file, &e)])),
+        }
+    } else {
+        None
+    };
+    if options.emits(Emit::Wat) {
+        if let Some(module) = &module {
+            writeln!(io::stdout().lock(), "{}", module)?;
+        }
+    }
+
+    match (options.command, &program, &options.output) {
+        (Command::Check, _, _) => return Ok(0),
+        (Command::Compile, Some(program), Some(output)) => {
+            vm::kbc::write(program, BufWriter::new(File::create(output)?))?;
+            return Ok(0);
+        }
+        (Command::Build, _, Some(output)) => {
+            let built = match options.backend {
+                Backend::C => {
+                    let code = c_code.expect("C code is generated for `build`");
+                    codegen::c::build(&code, Path::new(output))
+                }
+                Backend::X86_64 => {
+                    let code = asm.expect("Assembly is generated for `build`");
+                    codegen::x86_64::build(&code, Path::new(output))
+                }
+                Backend::Wasm => {
+                    let module = module.expect("WebAssembly is generated for `build`");
+                    std::fs::write(output, module.to_bytes())?;
+                    return Ok(0);
+                }
+            };
+            return Ok(match built {
+                Ok(()) => 0,
+                Err(e) => {
+                    eprintln!("kolang: {}", e);
+                    EXIT_BUILD
+                }
+            });
+        }
+        _ => {}
+    }
+
+    let result = match program {
+        Some(program) if options.engine == Engine::Vm => {
+            vm::run(&program, io::stdout().lock()).map(|_| ())
+        }
+        _ => std::thread::Builder::new()
+            .stack_size(interpreter::STACK_SIZE)
+            .spawn(move || interpreter::run(ast, io::stdout().lock()).map(|_| ()))?
+            .join()
+            .expect("Interpreter thread panicked"),
+    };
+    reporter.report_runtime(result)
+}
+
+/// Checks or runs a bytecode file written by `compile`, and returns the exit code.
+fn process_bytecode(
+    options: &Options,
+    bytes: &[u8],
+    mut reporter: Reporter<'_>,
+) -> io::Result<i32> {
+    let source_only = [Emit::Tokens, Emit::Ast, Emit::TokensJson, Emit::AstJson]
+        .into_iter()
+        .any(|emit| options.emits(emit));
+    if !matches!(options.command, Command::Check | Command::Run) || source_only {
+        eprintln!("kolang: Bytecode files can only be checked or run");
+        return Ok(EXIT_USAGE);
+    }
+    if options.engine != Engine::Vm {
+        eprintln!("kolang: Bytecode files can only run on the `vm` engine");
+        return Ok(EXIT_USAGE);
+    }
+
+    let program = match vm::kbc::from_bytes(bytes) {
+        Ok(program) => program,
+        Err(e) => {
+            eprintln!("kolang: {}: {}", reporter.file.name(), e);
+            return Ok(EXIT_ERRORS);
+        }
+    };
+    if options.emits(Emit::Bytecode) {
+        print_bytecode(&program)?;
+    }
+    if options.command == Command::Check {
+        return Ok(0);
+    }
+
+    let result = vm::run(&program, io::stdout().lock()).map(|_| ());
+    reporter.report_runtime(result)
+}
+
+/// Returns the diagnostic of an error found while generating code.
+fn codegen_error(file: &SourceFile, e: &CodegenError) -> Diagnostic {
+    let span = match e.position() {
+        Some((line, column)) => file.span_at(line, column),
+        None => Span::empty(0, 1, 1),
+    };
+    Diagnostic::error(e.to_string(), span)
+}
+
+/// Splits the code into tokens, and returns them with the errors found.
+fn tokenize(source: &[u8]) -> io::Result<(Vec<Token>, Vec<Diagnostic>)> {
+    let mut tokens = Vec::new();
+    let mut diagnostics = Vec::new();
+
+    for tok in Lexer::new(source) {
+        match tok {
+            Ok(tok) => {
+                if let TokenType::Invalid(_) = tok.token_type {
+                    diagnostics.push(Diagnostic::error(
+                        format!("Invalid token `{}`", tok.token_type),
+                        tok.span,
+                    ));
+                }
+                tokens.push(tok);
+            }
+            Err(LexError::Io(e)) => return Err(e),
+            Err(e) => diagnostics.extend(e.to_diagnostic()),
+        }
+    }
+
+    Ok((tokens, diagnostics))
+}
+
+/// Prints tokens, one per line.
+fn print_tokens(tokens: &[Token]) -> io::Result<()> {
+    let mut out = BufWriter::new(io::stdout().lock());
+    for tok in tokens {
+        writeln!(out, "{}", tok)?;
+    }
+    out.flush()
+}
+
+/// Prints the top level statements of a program.
+fn print_ast(ast: &[ast::Stmt]) -> io::Result<()> {
+    let mut out = BufWriter::new(io::stdout().lock());
+    for stmt in ast {
+        writeln!(out, "{:#}", stmt)?;
+    }
+    out.flush()
+}
+
+/// Prints a value as JSON on a single line.
+fn print_json<T: Serialize + ?Sized>(value: &T) -> io::Result<()> {
+    let mut out = BufWriter::new(io::stdout().lock());
+    serde_json::to_writer(&mut out, value)?;
+    writeln!(out)?;
+    out.flush()
+}
+
+/// Prints the code in the canonical style, or only checks that it is
+/// formatted, and returns the exit code.
+fn format_code(check: bool, name: &str, text: &str, reporter: &mut Reporter) -> io::Result<i32> {
+    let formatted = match parser::format::format(text) {
+        Ok(formatted) => formatted,
+        Err(diagnostics) => return Ok(reporter.report(&diagnostics)),
+    };
+
+    if !check {
+        let mut out = io::stdout().lock();
+        out.write_all(formatted.as_bytes())?;
+        out.flush()?;
+        Ok(0)
+    } else if formatted == text {
+        Ok(0)
+    } else {
+        eprintln!("kolang: {} is not formatted", name);
+        Ok(EXIT_ERRORS)
+    }
+}
+
+/// Prints the disassembled bytecode of all functions, separated by empty lines.
+fn print_bytecode(program: &vm::Program) -> io::Result<()> {
+    let mut out = BufWriter::new(io::stdout().lock());
+    for (i, f) in program.functions.iter().enumerate() {
+        if i != 0 {
+            writeln!(out)?;
+        }
+        write!(out, "{}", vm::disassemble(program, f))?;
+    }
+    out.flush()
+}
+
+/// Prints diagnostics to the standard error stream in the requested format.
+struct Reporter<'a> {
+    format: ErrorFormat,
+    file: &'a SourceFile<'a>,
+    renderer: Renderer,
+    errors: bool,
+}
+
+impl<'a> Reporter<'a> {
+    fn new(format: ErrorFormat, file: &'a SourceFile<'a>) -> Self {
+        Self {
+            format,
+            file,
+            renderer: Renderer::for_stderr(),
+            errors: false,
+        }
+    }
+
+    /// Prints the diagnostics and returns the exit code for the errors
+    /// reported so far (`0` if there are only warnings).
+    fn report(&mut self, diagnostics: &[Diagnostic]) -> i32 {
+        let mut err = io::stderr().lock();
+        for d in diagnostics {
+            self.errors |= d.severity == Severity::Error;
+            let _ = match self.format {
+                ErrorFormat::Human => writeln!(err, "{}", self.renderer.render(d, self.file)),
+                ErrorFormat::Json => writeln!(err, "{}", json::to_json(d, self.file.name())),
+            };
+        }
+
+        if self.errors {
+            EXIT_ERRORS
+        } else {
+            0
+        }
+    }
+
+    /// Prints the error of running the program (if any) and returns the exit code.
+    fn report_runtime(&mut self, result: Result<(), RuntimeError>) -> io::Result<i32> {
+        match result {
+            Ok(()) => Ok(0),
+            Err(RuntimeError::Io(e)) => Err(e),
+            Err(e) => {
+                let span = match e.position() {
+                    Some((line, column)) => self.file.span_at(line, column),
+                    None => Span::empty(0, 1, 1),
+                };
+                self.report(&[Diagnostic::error(format!("Runtime error: {}", e), span)]);
+                Ok(EXIT_RUNTIME)
+            }
+        }
+    }
+}
@@ -0,0 +1,332 @@
+use std::{
+    collections::{HashMap, HashSet},
+    io::{self, BufRead, IsTerminal, Write},
+};
+
+use diagnostics::{Diagnostic, Severity, SourceFile, Span};
+use interpreter::{error::RuntimeError, value::Value, Interpreter};
+use lexer::{token::TokenType, Lexer};
+use parser::{ast, Parser};
+use semantic::type_checker::TypeChecker;
+
+use crate::{args::ErrorFormat, print_tokens, tokenize, Reporter, VERSION};
+
+/// Name of the session in error messages.
+const NAME: &str = "<repl>";
+
+/// Help message of the session commands.
+const HELP: &str = "\
+Enter `let` statements, function definitions and other statements to run them,
+or an expression (without `;`) to print its value. Input continues on the next
+line while brackets, a string literal or a block comment are open.
+
+Commands:
+  :type <EXPR>     Print the type of the expression
+  :ast <EXPR>      Print the syntax tree of the expression
+  :tokens <CODE>   Print the tokens of the code
+  :history         Print the inputs of the session
+  :help            Print this help message
+  :quit            End the session (or press Ctrl-D)
+";
+
+/// State of an interactive session.
+struct Session {
+    /// Format of the reported errors.
+    format: ErrorFormat,
+    /// All code entered so far, so errors can show code of earlier inputs.
+    source: String,
+    /// Line of `source` where the next input starts.
+    line: usize,
+    /// Signatures of the functions defined so far (definitions with empty
+    /// bodies), by name.
+    functions: HashMap<String, ast::Stmt>,
+    /// Types of the variables defined so far, by name.
+    variables: HashMap<String, ast::Type>,
+    /// Inputs entered so far, including commands.
+    history: Vec<String>,
+    /// Interpreter which keeps the values of the variables.
+    interpreter: Interpreter<io::Stdout>,
+}
+
+/// Reads code and commands from the standard input until its end or
+/// `:quit`, and returns the exit code. Prompts are only printed when the
+/// input is a terminal. Code runs on the interpreter, in a thread with
+/// enough stack for deep recursion.
+pub fn run(format: ErrorFormat) -> io::Result<i32> {
+    std::thread::Builder::new()
+        .stack_size(interpreter::STACK_SIZE)
+        .spawn(move || Session::new(format).run())?
+        .join()
+        .expect("REPL thread panicked")
+}
+
+impl Session {
+    fn new(format: ErrorFormat) -> Self {
+        Self {
+            format,
+            source: String::new(),
+            line: 1,
+            functions: HashMap::new(),
+            variables: HashMap::new(),
+            history: Vec::new(),
+            interpreter: Interpreter::new(io::stdout()),
+        }
+    }
+
+    fn run(&mut self) -> io::Result<i32> {
+        let stdin = io::stdin();
+        let interactive = stdin.is_terminal();
+        if interactive {
+            println!("Kolang {} REPL. Type `:help` for help.", VERSION);
+        }
+
+        let mut input = String::new();
+        loop {
+            if interactive {
+                print!("{}", if input.is_empty() { "> " } else { ". " });
+                io::stdout().flush()?;
+            }
+
+            let mut line = String::new();
+            let end = stdin.lock().read_line(&mut line)? == 0;
+            input.push_str(&line);
+            if !input.is_empty() && !input.ends_with('\n') {
+                input.push('\n');
+            }
+            // Incomplete code at the end of input is run, so its errors are reported.
+            if !end && !input.trim_start().starts_with(':') && is_incomplete(&input) {
+                continue;
+            }
+
+            let input = std::mem::take(&mut input);
+            let trimmed = input.trim();
+            if !trimmed.is_empty() {
+                self.history.push(trimmed.to_string());
+                if !self.handle(&input)? {
+                    return Ok(0);
+                }
+            }
+            if end {
+                if interactive {
+                    println!();
+                }
+                return Ok(0);
+            }
+        }
+    }
+
+    /// Runs a command or code. Returns `false` if the session should end.
+    fn handle(&mut self, input: &str) -> io::Result<bool> {
+        let Some(command) = input.trim().strip_prefix(':') else {
+            self.run_code(input)?;
+            return Ok(true);
+        };
+
+        let (name, arg) = command
+            .split_once(char::is_whitespace)
+            .unwrap_or((command, ""));
+        let arg = arg.trim();
+        match name {
+            "type" | "ast" | "tokens" if arg.is_empty() => {
+                eprintln!("kolang: Command `:{}` requires an expression", name);
+            }
+            "type" => self.print_type(arg)?,
+            "ast" => {
+                let (expr, diagnostics) = Parser::new(Lexer::new(arg.as_bytes())).parse_expr()?;
+                if self.report(arg, &diagnostics) == 0 {
+                    println!("{}", expr);
+                }
+            }
+            "tokens" => {
+                let (tokens, diagnostics) = tokenize(arg.as_bytes())?;
+                print_tokens(&tokens)?;
+                self.report(arg, &diagnostics);
+            }
+            "history" => {
+                for (i, input) in self.history.iter().enumerate() {
+                    println!("{:>4}  {}", i + 1, input.replace('\n', "\n      "));
+                }
+            }
+            "help" => print!("{}", HELP),
+            "quit" | "q" => return Ok(false),
+            _ => eprintln!(
+                "kolang: Unknown command `:{}`, type `:help` for the commands",
+                name
+            ),
+        }
+        Ok(true)
+    }
+
+    /// Prints the type of an expression, or `no value` for calls of
+    /// functions which return nothing.
+    fn print_type(&mut self, code: &str) -> io::Result<()> {
+        let (expr, diagnostics) = Parser::new(Lexer::new(code.as_bytes())).parse_expr()?;
+        if self.report(code, &diagnostics) != 0 {
+            return Ok(());
+        }
+
+        let mut tc = self.checker(&[]);
+        let t = tc.expr_type(&expr);
+        if self.report(code, tc.diagnostics()) == 0 {
+            match t {
+                Some(t) => println!("{}", t),
+                None => println!("no value"),
+            }
+        }
+        Ok(())
+    }
+
+    /// Runs code: prints the value of an expression, or checks and runs
+    /// statements and function definitions. The code is added to the source
+    /// of the session, so positions in errors count from the first input.
+    fn run_code(&mut self, code: &str) -> io::Result<()> {
+        let (offset, line) = (self.source.len(), self.line);
+        self.source.push_str(code);
+        self.line += code.matches('\n').count();
+        let lexer = || Lexer::new(code.as_bytes()).starting_at(offset, line);
+
+        let (expr, diagnostics) = Parser::new(lexer()).parse_expr()?;
+        if !diagnostics.iter().any(|d| d.severity == Severity::Error) {
+            let mut tc = self.checker(&[]);
+            tc.expr_type(&expr);
+            if self.report_session(tc.diagnostics()) != 0 {
+                return Ok(());
+            }
+
+            return match self.interpreter.eval_expr(&expr) {
+                Ok(Value::Void) => Ok(()),
+                Ok(value) => {
+                    println!("{}", value);
+                    Ok(())
+                }
+                Err(e) => self.report_runtime(e),
+            };
+        }
+
+        let (items, diagnostics) = Parser::new(lexer()).parse_items()?;
+        if self.report_session(&diagnostics) != 0 {
+            return Ok(());
+        }
+        let (functions, stmts): (Vec<_>, Vec<_>) = items
+            .into_iter()
+            .partition(|s| matches!(s, ast::Stmt::FnDef { .. }));
+
+        // The statements are checked in a block, so their variables may
+        // shadow the variables of the session.
+        let mut tc = self.checker(&functions);
+        let block = ast::Stmt::Block {
+            stmts,
+            span: Span::default(),
+        };
+        tc.check_stmt(&block);
+        if self.report_session(tc.diagnostics()) != 0 {
+            return Ok(());
+        }
+        let ast::Stmt::Block { stmts, .. } = block else {
+            unreachable!();
+        };
+
+        for f in &functions {
+            if let ast::Stmt::FnDef {
+                id,
+                params,
+                return_type,
+                span,
+                ..
+            } = f
+            {
+                let signature = ast::Stmt::FnDef {
+                    id: id.clone(),
+                    params: params.clone(),
+                    return_type: return_type.clone(),
+                    body: Box::new(ast::Stmt::Empty { span: *span }),
+                    span: *span,
+                };
+                self.functions.insert(id.clone(), signature);
+            }
+        }
+        self.interpreter.define(functions);
+        for stmt in &stmts {
+            if let Err(e) = self.interpreter.run_stmt(stmt) {
+                return self.report_runtime(e);
+            }
+            if let ast::Stmt::Let { id, var_type, .. } = stmt {
+                self.variables.insert(id.clone(), var_type.clone());
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns a type checker which knows the functions and variables of the
+    /// session, and has checked the new functions. New functions replace the
+    /// functions of the session with the same name. Functions do not see the
+    /// variables, like in the interpreter.
+    fn checker(&self, functions: &[ast::Stmt]) -> TypeChecker {
+        let mut tc = TypeChecker::new();
+        let names: HashSet<&str> = functions
+            .iter()
+            .filter_map(|f| match f {
+                ast::Stmt::FnDef { id, .. } => Some(id.as_str()),
+                _ => None,
+            })
+            .collect();
+        for (_, f) in self
+            .functions
+            .iter()
+            .filter(|(id, _)| !names.contains(id.as_str()))
+        {
+            tc.define_function(f);
+        }
+        for f in functions {
+            tc.define_function(f);
+        }
+        for f in functions {
+            tc.check_stmt(f);
+        }
+
+        for (id, var_type) in &self.variables {
+            tc.check_stmt(&ast::Stmt::Let {
+                id: id.clone(),
+                var_type: var_type.clone(),
+                expr: None,
+                span: var_type.span(),
+            });
+        }
+        tc
+    }
+
+    /// Prints the diagnostics of code of a command, and returns a nonzero
+    /// code if there are errors.
+    fn report(&self, code: &str, diagnostics: &[Diagnostic]) -> i32 {
+        let file = SourceFile::new(NAME, code);
+        Reporter::new(self.format, &file).report(diagnostics)
+    }
+
+    /// Prints the diagnostics of the code of the session, and returns a
+    /// nonzero code if there are errors.
+    fn report_session(&self, diagnostics: &[Diagnostic]) -> i32 {
+        self.report(&self.source, diagnostics)
+    }
+
+    /// Prints an error of running code of the session.
+    fn report_runtime(&self, e: RuntimeError) -> io::Result<()> {
+        let file = SourceFile::new(NAME, &self.source);
+        Reporter::new(self.format, &file).report_runtime(Err(e))?;
+        Ok(())
+    }
+}
+
+/// Checks whether code ends inside brackets, a string literal or a block
+/// comment, so the next line continues it.
+fn is_incomplete(code: &str) -> bool {
+    let mut depth = 0;
+    for tok in Lexer::new(code.as_bytes()).flatten() {
+        match tok.token_type {
+            TokenType::LPar | TokenType::LBracket | TokenType::LBrace => depth += 1,
+            TokenType::RPar | TokenType::RBracket | TokenType::RBrace => depth -= 1,
+            t if t.is_unterminated() => return true,
+            _ => {}
+        }
+    }
+    depth > 0
+}
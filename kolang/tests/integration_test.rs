@@ -0,0 +1,486 @@
+use std::{
+    io::Write,
+    process::{Command, Output, Stdio},
+};
+
+/// Runs `kolang` with provided arguments and standard input.
+fn kolang(args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_kolang"))
+        .args(args)
+        .env("NO_COLOR", "1")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    // `kolang` may exit before it reads the input, e.g. when arguments are
+    // invalid, so a broken pipe is not an error.
+    let _ = child.stdin.take().unwrap().write_all(stdin.as_bytes());
+    child.wait_with_output().unwrap()
+}
+
+fn stdout(o: &Output) -> String {
+    String::from_utf8_lossy(&o.stdout).into_owned()
+}
+
+fn stderr(o: &Output) -> String {
+    String::from_utf8_lossy(&o.stderr).into_owned()
+}
+
+#[test]
+fn run() {
+    let o = kolang(
+        &["run"],
+        "fn main() {
+            for i = 1 to 3 {
+                println(i * i);
+            }
+        }",
+    );
+    assert_eq!(o.status.code(), Some(0));
+    assert_eq!(stdout(&o), "1\n4\n9\n");
+    assert_eq!(stderr(&o), "");
+}
+
+#[test]
+fn engines() {
+    let code = "fn fact(n: int): int {
+        if n <= 1 return 1;
+        return n * fact(n - 1);
+    }
+
+    fn main() {
+        println(fact(20), ' ', [1.5, 2.5], ' ', 'k', \"olang\");
+        println(10 / (fact(1) - 1));
+    }";
+
+    for engine in ["--engine=vm", "--engine=interpreter"] {
+        let o = kolang(&["run", engine], code);
+        assert_eq!(o.status.code(), Some(3));
+        assert_eq!(stdout(&o), "2432902008176640000 [1.5, 2.5] kolang\n");
+        assert!(
+            stderr(&o).starts_with("error: Runtime error: Division by zero\n --> <stdin>:8:20\n")
+        );
+    }
+}
+
+#[test]
+fn bytecode_files() {
+    let dir = std::env::temp_dir().join(format!("kolang-test-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let source = dir.join("prog.kol");
+    let compiled = dir.join("prog.kbc");
+    let path = |p: &std::path::Path| p.to_str().unwrap().to_string();
+
+    std::fs::write(
+        &source,
+        "fn main() {
+    for i = 1 to 3 println(i * i);
+    println(1 / (1 - 1));
+}
+",
+    )
+    .unwrap();
+    let o = kolang(&["compile", &path(&source)], "");
+    assert_eq!(o.status.code(), Some(0));
+    assert!(std::fs::read(&compiled).unwrap().starts_with(b"KBC\0"));
+
+    let o = kolang(&["run", &path(&compiled)], "");
+    assert_eq!(o.status.code(), Some(3));
+    assert_eq!(stdout(&o), "1\n4\n9\n");
+    assert_eq!(
+        stderr(&o),
+        format!(
+            "error: Runtime error: Division by zero\n --> {}:3:15\n\n",
+            path(&compiled)
+        )
+    );
+
+    let other = dir.join("other.kbc");
+    let o = kolang(
+        &["compile", "-o", &path(&other)],
+        "fn main() { println(42); }",
+    );
+    assert_eq!(o.status.code(), Some(0));
+    let o = kolang(&["run", "--emit=bytecode", &path(&other)], "");
+    assert_eq!(stdout(&o).lines().last(), Some("42"));
+    assert!(stdout(&o).starts_with("fn main (0 params, 0 locals)\n"));
+    assert_eq!(kolang(&["parse", &path(&other)], "").status.code(), Some(2));
+
+    let mut damaged = std::fs::read(&other).unwrap();
+    damaged.truncate(damaged.len() - 1);
+    std::fs::write(&other, damaged).unwrap();
+    let o = kolang(&["check", &path(&other)], "");
+    assert_eq!(o.status.code(), Some(1));
+    assert!(stderr(&o).contains("Bytecode file is truncated"));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn build() {
+    let dir = std::env::temp_dir().join(format!("kolang-build-test-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let source = dir.join("prog.kol");
+    let exe = dir
+        .join("prog")
+        .with_extension(std::env::consts::EXE_EXTENSION);
+    let path = |p: &std::path::Path| p.to_str().unwrap().to_string();
+
+    std::fs::write(
+        &source,
+        "fn main() {
+    for i = 1 to 3 println(i * i);
+    println(1 / (1 - 1));
+}
+",
+    )
+    .unwrap();
+    let o = kolang(&["build", &path(&source)], "");
+    assert_eq!(o.status.code(), Some(0), "{}", stderr(&o));
+
+    let o = Command::new(&exe).output().unwrap();
+    assert_eq!(o.status.code(), Some(3));
+    assert_eq!(stdout(&o), "1\n4\n9\n");
+    assert_eq!(
+        stderr(&o),
+        format!("{}:3:15: Runtime error: Division by zero\n", path(&source))
+    );
+
+    let o = kolang(&["build", "--backend=x86-64", &path(&source)], "");
+    assert_eq!(o.status.code(), Some(0), "{}", stderr(&o));
+    let o = Command::new(&exe).output().unwrap();
+    assert_eq!(o.status.code(), Some(3));
+    assert_eq!(stdout(&o), "1\n4\n9\n");
+    assert_eq!(
+        stderr(&o),
+        format!("{}:3:15: Runtime error: Division by zero\n", path(&source))
+    );
+
+    let o = kolang(&["check", "--emit=asm"], "fn main() { println(42); }");
+    assert!(stdout(&o).contains("\nfn_main:\n"));
+    assert!(stdout(&o).ends_with(
+        "kl_source:\n    .ascii \"<stdin>\"\n    .p2align 3\nkl_source_len:\n    .quad 7\n"
+    ));
+
+    let o = kolang(
+        &["build", "--backend=x86-64", "-o", &path(&exe)],
+        "fn main() { println(1.5); }",
+    );
+    assert_eq!(o.status.code(), Some(1));
+    assert!(stderr(&o)
+        .starts_with("error: Values of type `float` are not supported by this code generator\n"));
+
+    let o = kolang(&["build", "--backend=wasm", &path(&source)], "");
+    assert_eq!(o.status.code(), Some(0), "{}", stderr(&o));
+    let module = std::fs::read(source.with_extension("wasm")).unwrap();
+    assert!(module.starts_with(b"\0asm\x01\0\0\0"));
+
+    let o = kolang(&["check", "--emit=wat"], "fn main() { println(42); }");
+    assert!(stdout(&o).starts_with("(module\n"));
+    assert!(stdout(&o).contains("    i64.const 42\n    call $print_int\n    call $println\n"));
+
+    let o = kolang(&["check", "--emit=c"], "fn main() { println(42); }");
+    assert!(stdout(&o).contains("#define KL_SOURCE \"<stdin>\""));
+    assert!(stdout(&o).ends_with("kl_print_int(INT64_C(42));\n    kl_println();\n    kl_leave();\n}\n\nint main(void)\n{\n    fn_main();\n    return 0;\n}\n"));
+
+    let o = kolang(&["build", "-o", &path(&exe)], "fn f() { }");
+    assert_eq!(o.status.code(), Some(1));
+    assert!(stderr(&o).starts_with("error: `main` function is not defined\n"));
+
+    let o = Command::new(env!("CARGO_BIN_EXE_kolang"))
+        .args(["build", &path(&source)])
+        .env("CC", "/nonexistent/cc")
+        .output()
+        .unwrap();
+    assert_eq!(o.status.code(), Some(5));
+    assert!(stderr(&o).contains("Program `/nonexistent/cc` was not found"));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn optimization() {
+    let code = "fn main() {
+        let x: int = 6;
+        let sum: int = 0;
+        for i = 1 to 3 {
+            sum = sum + x * 7;
+        }
+        println(sum);
+    }";
+
+    for level in ["-O0", "-O1", "-O2"] {
+        let o = kolang(&["run", level], code);
+        assert_eq!(o.status.code(), Some(0));
+        assert_eq!(stdout(&o), "126\n");
+    }
+
+    let o = kolang(
+        &["run", "-O2", "--emit=ir"],
+        "fn main() { let x: int = 6; println(x * 7); }",
+    );
+    assert_eq!(
+        stdout(&o),
+        "\
+fn main() {
+b0:
+    r0: int = const 42
+    println r0
+    return
+}
+42
+"
+    );
+
+    let o = kolang(
+        &["run", "--passes=copyprop,dce", "--emit=ir"],
+        "fn main() { let x: int = 6; println(x * 7); }",
+    );
+    assert!(stdout(&o).contains("mul"));
+    assert!(stdout(&o).ends_with("42\n"));
+
+    let o = kolang(&["run", "--passes=unroll"], "fn main() { }");
+    assert_eq!(o.status.code(), Some(2));
+    assert!(stderr(&o).contains("Unknown pass `unroll`"));
+}
+
+#[test]
+fn repl() {
+    let o = kolang(
+        &["repl"],
+        "let x: int = 6;
+x * 7
+fn fact(n: int): int {
+    if n <= 1 { return 1; }
+    return n * fact(n - 1);
+}
+fact(5)
+let s: str = \"two
+lines\";
+s
+:type fact(2) > 1
+:ast 1 + 2 * x
+:tokens x+1
+y
+fact(1 / 0)
+:quit
+x
+",
+    );
+    assert_eq!(o.status.code(), Some(0));
+    assert_eq!(
+        stdout(&o),
+        "\
+42
+120
+two
+lines
+bool
+(1 + (2 * x))
+x, Ln: 1, Col: 1
++, Ln: 1, Col: 2
+1, Ln: 1, Col: 3
+EOF, Ln: 1, Col: 4
+"
+    );
+    // Errors show the code of the session.
+    assert!(stderr(&o).contains("error: Variable `y` is not defined\n  --> <repl>:11:1"));
+    assert!(stderr(&o).contains("error: Runtime error: Division by zero\n  --> <repl>:12:8"));
+
+    let o = kolang(&["repl"], "fn f() {\n:history\n:help\n");
+    assert!(stderr(&o).contains("error: Expected `}`"));
+    assert!(stdout(&o).is_empty());
+
+    let o = kolang(&["repl"], "1\n:history\n:load x\n");
+    assert_eq!(stdout(&o), "1\n   1  1\n   2  :history\n");
+    assert!(stderr(&o).contains("Unknown command `:load`"));
+
+    assert_eq!(kolang(&["repl", "prog.kol"], "").status.code(), Some(2));
+}
+
+#[test]
+fn exit_codes() {
+    let code = |args: &[&str], stdin| kolang(args, stdin).status.code();
+
+    assert_eq!(code(&["check"], "fn main() { }"), Some(0));
+    assert_eq!(
+        code(&["check", "-"], "fn main() { let a: int = 1.5; }"),
+        Some(1)
+    );
+    assert_eq!(code(&["parse"], "fn main() { let }"), Some(1));
+    assert_eq!(code(&["tokens"], "fn $"), Some(1));
+    assert_eq!(code(&["run"], "fn main() { println(1 / 0); }"), Some(3));
+    assert_eq!(code(&["compile"], ""), Some(2));
+    assert_eq!(code(&["run", "-o", "a.kbc"], ""), Some(2));
+    assert_eq!(code(&["build"], "fn main() { }"), Some(2));
+    assert_eq!(code(&["build", "prog"], ""), Some(2));
+    assert_eq!(code(&["bake"], ""), Some(2));
+    assert_eq!(code(&["run", "--emit=llvm"], ""), Some(2));
+    assert_eq!(code(&["run", "--engine=jit"], ""), Some(2));
+    assert_eq!(code(&["run", "--error-format"], ""), Some(2));
+    assert_eq!(code(&[], ""), Some(2));
+    assert_eq!(code(&["check", "/nonexistent/file.kol"], ""), Some(4));
+    assert_eq!(code(&["--help"], ""), Some(0));
+
+    // Warnings alone do not fail.
+    assert_eq!(code(&["check"], "fn f(): int { } fn main() { }"), Some(0));
+}
+
+#[test]
+fn human_errors() {
+    let o = kolang(
+        &["check"],
+        "fn add(a: int, b: int): int {
+    return a + b;
+}
+
+fn main() {
+    add(1);
+}
+",
+    );
+    assert_eq!(
+        stderr(&o),
+        "\
+error: Function `add` takes 2 arguments but 1 was supplied
+ --> <stdin>:6:5
+  |
+1 | fn add(a: int, b: int): int {
+  | ----------------------------- function declared here
+...
+6 |     add(1);
+  |     ^^^^^^
+
+"
+    );
+}
+
+#[test]
+fn json_errors() {
+    let o = kolang(
+        &["check", "--error-format", "json"],
+        "fn main() { let a: int = true; }",
+    );
+    assert_eq!(o.status.code(), Some(1));
+    assert_eq!(
+        stderr(&o),
+        "{\"file\":\"<stdin>\",\"severity\":\"error\",\"message\":\"Expected `int`, found `bool`\",\
+         \"span\":{\"start\":25,\"end\":29,\"line\":1,\"column\":26,\"end_line\":1,\"end_column\":30},\
+         \"expected\":[],\"labels\":[],\"notes\":[],\"help\":[]}\n"
+    );
+
+    let o = kolang(&["parse", "--error-format=json"], "fn main() { let a: ; }");
+    assert!(stderr(&o)
+        .contains("\"notes\":[\"Expected one of `int`, `float`, `char`, `str`, `bool`\"]"));
+}
+
+#[test]
+fn emit() {
+    let o = kolang(&["tokens"], "fn main");
+    assert_eq!(
+        stdout(&o),
+        "fn, Ln: 1, Col: 1\nmain, Ln: 1, Col: 4\nEOF, Ln: 1, Col: 8\n"
+    );
+
+    let o = kolang(
+        &["run", "--emit=tokens,ast"],
+        "fn main() { println(1 + 2 * 3); }",
+    );
+    let out = stdout(&o);
+    assert!(out.starts_with("fn, Ln: 1, Col: 1\n"));
+    assert!(out.contains("println((1 + (2 * 3)))"));
+    assert!(out.ends_with("7\n"));
+
+    let o = kolang(
+        &["check", "--emit=tokens-json,ast-json"],
+        "fn main() { f(-x); }",
+    );
+    assert_eq!(o.status.code(), Some(1));
+    let out = stdout(&o);
+    let lines: Vec<_> = out.lines().collect();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].starts_with(
+        r#"[{"span":{"start":0,"end":2,"line":1,"column":1,"end_line":1,"end_column":3},"token_type":"KwFn"},"#
+    ));
+    assert!(lines[0].ends_with(r#""token_type":"EOF"}]"#));
+    assert!(lines[1].starts_with(r#"[{"FnDef":{"id":"main","params":[],"return_type":null,"#));
+    assert!(lines[1].contains(r#"{"Call":{"id":"f","args":[{"UnaryOp":{"op":{"Neg":{"span":"#));
+
+    let o = kolang(&["check", "--emit=bytecode"], "fn main() { println(1); }");
+    assert_eq!(
+        stdout(&o),
+        "\
+fn main (0 params, 0 locals)
+0000    1:21  const 0             ; 1
+0001    1:13  println 1
+0002     |    pop
+0003    1:26  void
+0004     |    return
+"
+    );
+
+    let o = kolang(&["run", "--emit=ir"], "fn main() { println(1); }");
+    assert_eq!(
+        stdout(&o),
+        "\
+fn main() {
+b0:
+    r0: int = const 1
+    println r0
+    return
+}
+1
+"
+    );
+
+    let o = kolang(&["parse"], "fn f() { } fn main() { f(); }");
+    assert_eq!(stdout(&o).matches("fn ").count(), 2);
+}
+
+#[test]
+fn fmt() {
+    let code = "fn main(){ // Entry point\n  println( 1+2 ) ;}";
+    let formatted = "fn main() {\n    // Entry point\n    println(1 + 2);\n}\n";
+
+    let o = kolang(&["fmt"], code);
+    assert_eq!(o.status.code(), Some(0));
+    assert_eq!(stdout(&o), formatted);
+
+    let o = kolang(&["fmt", "--check"], code);
+    assert_eq!(o.status.code(), Some(1));
+    assert_eq!(stdout(&o), "");
+    assert_eq!(stderr(&o), "kolang: <stdin> is not formatted\n");
+    assert_eq!(
+        kolang(&["fmt", "--check"], formatted).status.code(),
+        Some(0)
+    );
+    assert_eq!(kolang(&["fmt"], "fn main() { let }").status.code(), Some(1));
+    assert_eq!(
+        kolang(&["run", "--check"], formatted).status.code(),
+        Some(2)
+    );
+
+    // `kolangfmt` formats files in place.
+    let dir = std::env::temp_dir().join(format!("kolang-fmt-test-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let source = dir.join("prog.kol");
+    let path = source.to_str().unwrap();
+    std::fs::write(&source, code).unwrap();
+    let kolangfmt = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_kolangfmt"))
+            .args(args)
+            .output()
+            .unwrap()
+    };
+
+    let o = kolangfmt(&["--check", path]);
+    assert_eq!(o.status.code(), Some(1));
+    assert_eq!(stdout(&o), format!("{}\n", path));
+    assert_eq!(kolangfmt(&[path]).status.code(), Some(0));
+    assert_eq!(std::fs::read_to_string(&source).unwrap(), formatted);
+    assert_eq!(kolangfmt(&["--check", path]).status.code(), Some(0));
+    assert_eq!(kolangfmt(&["--verbose"]).status.code(), Some(2));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,75 @@
+//! Compares throughput of the streaming `Lexer<R>` and the zero-copy `StrLexer`.
+//! Run with `cargo bench -p lexer`.
+
+use std::time::{Duration, Instant};
+
+use lexer::{Lexer, StrLexer};
+
+const SAMPLE: &str = "/*
+ * Generated function
+ */
+fn sum_to_n_{}(n: int, scale: float): float {
+    let sum: int = 0;
+    let name: str = \"function number {}\";
+    for i = 1 to n {
+        sum = sum + i * 0x1F % 7; // accumulate
+        if sum > 1000 and not (sum == 0b1010) {
+            sum = sum - 1000;
+        }
+    }
+    return scale * 1.5e3;
+}
+
+";
+
+/// Size of the generated source code in bytes (about 8 MB).
+const SIZE: usize = 8 * 1024 * 1024;
+const ROUNDS: usize = 5;
+
+fn main() {
+    let mut source = String::new();
+    let mut i = 0;
+    while source.len() < SIZE {
+        source.push_str(&SAMPLE.replace("{}", &i.to_string()));
+        i += 1;
+    }
+
+    let streaming = measure(|| {
+        Lexer::new(source.as_bytes())
+            .try_fold(0, |n, t| t.map(|_| n + 1))
+            .unwrap()
+    });
+    let zero_copy = measure(|| StrLexer::new(&source).count());
+
+    assert_eq!(
+        streaming.0, zero_copy.0,
+        "Lexers produced different token counts"
+    );
+
+    let mb = source.len() as f64 / (1024.0 * 1024.0);
+    println!("source: {:.1} MB, {} tokens", mb, streaming.0);
+    for (name, (_, time)) in [("Lexer<&[u8]>", streaming), ("StrLexer", zero_copy)] {
+        println!(
+            "{:<14} {:>8.2} ms {:>10.1} MB/s",
+            name,
+            time.as_secs_f64() * 1000.0,
+            mb / time.as_secs_f64()
+        );
+    }
+    println!(
+        "StrLexer is {:.1}x faster",
+        streaming.1.as_secs_f64() / zero_copy.1.as_secs_f64()
+    );
+}
+
+/// Runs `f` several times and returns its result and the fastest run time.
+fn measure(mut f: impl FnMut() -> usize) -> (usize, Duration) {
+    let mut best = Duration::MAX;
+    let mut result = 0;
+    for _ in 0..ROUNDS {
+        let start = Instant::now();
+        result = std::hint::black_box(f());
+        best = best.min(start.elapsed());
+    }
+    (result, best)
+}
@@ -0,0 +1,63 @@
+use std::{error, fmt, io};
+
+use diagnostics::{Diagnostic, Span};
+
+/// Errors which may occur while scanning Kolang code.
+#[derive(Debug)]
+pub enum LexError {
+    /// Something went wrong while reading bytes from source.
+    Io(io::Error),
+    /// Source contains bytes which are not valid UTF-8.
+    /// The lexer skips these bytes, so scanning may continue after this error.
+    InvalidUtf8 {
+        /// Source range of the invalid sequence.
+        span: Span,
+        /// The invalid bytes.
+        bytes: Vec<u8>,
+    },
+}
+
+impl LexError {
+    /// Converts the error to a [`Diagnostic`] which points to the offending
+    /// source range. Returns `None` for errors which are not related to
+    /// the source code itself (e.g. I/O errors).
+    pub fn to_diagnostic(&self) -> Option<Diagnostic> {
+        match self {
+            LexError::Io(_) => None,
+            LexError::InvalidUtf8 { span, .. } => Some(
+                Diagnostic::error(self.to_string(), *span)
+                    .with_help("Kolang source files must be encoded in UTF-8".to_string()),
+            ),
+        }
+    }
+}
+
+impl fmt::Display for LexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LexError::Io(e) => write!(f, "{e}"),
+            LexError::InvalidUtf8 { bytes, .. } => {
+                f.write_str("Invalid UTF-8 sequence `")?;
+                for b in bytes {
+                    write!(f, "\\x{b:02X}")?;
+                }
+                f.write_str("`")
+            }
+        }
+    }
+}
+
+impl error::Error for LexError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            LexError::Io(e) => Some(e),
+            LexError::InvalidUtf8 { .. } => None,
+        }
+    }
+}
+
+impl From<io::Error> for LexError {
+    fn from(e: io::Error) -> Self {
+        LexError::Io(e)
+    }
+}
@@ -3,13 +3,43 @@
 //! # Kolang lexer
 //! Utilities for tokenizing Kolang code.
 
-use std::io::{self, BufReader, Read};
-
+use std::{
+    collections::VecDeque,
+    io::{self, BufRead, BufReader, Read},
+    iter::FusedIterator,
+};
+
+use diagnostics::Span;
+use scan::Scanner;
 use token::{Token, TokenType};
 
+/// This module includes the errors which may occur while scanning Kolang code.
+pub mod error;
+/// This module includes the scanning of tokens which both lexers share.
+mod scan;
+/// This module includes a lexer which borrows token text from in-memory source code.
+pub mod str_lexer;
+/// This module includes a token stream with lookahead over a `Lexer<R>`.
+pub mod stream;
 /// This module includes some utilities to store and represent Kolang tokens.
 pub mod token;
 
+pub use error::LexError;
+pub use str_lexer::StrLexer;
+pub use stream::TokenStream;
+
+/// Unit which the `Lexer<R>` uses to count columns.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColumnUnit {
+    /// Each Unicode character (scalar value) is one column.
+    #[default]
+    Char,
+    /// Each UTF-16 code unit is one column. Characters outside
+    /// the Basic Multilingual Plane take two columns. Useful for
+    /// editors (e.g. Language Server Protocol clients).
+    Utf16,
+}
+
 /// The `Lexer<R>` struct allows you to scan Kolang code from any byte source
 /// which implements [`Read`] trait (file, network, in-memory buffer, etc.)
 /// and get tokens.
@@ -22,15 +52,15 @@ pub mod token;
 /// let source = "fn main(): int {}".as_bytes();
 /// let mut l = Lexer::new(source);
 ///
-/// assert_eq!(l.next().unwrap().token_type, TokenType::KwFn);
-/// assert_eq!(l.next().unwrap().token_type, TokenType::Iden("main".to_string()));
-/// assert_eq!(l.next().unwrap().token_type, TokenType::LPar);
-/// assert_eq!(l.next().unwrap().token_type, TokenType::RPar);
-/// assert_eq!(l.next().unwrap().token_type, TokenType::Colon);
-/// assert_eq!(l.next().unwrap().token_type, TokenType::KwInt);
-/// assert_eq!(l.next().unwrap().token_type, TokenType::LBrace);
-/// assert_eq!(l.next().unwrap().token_type, TokenType::RBrace);
-/// assert_eq!(l.next().unwrap().token_type, TokenType::EOF);
+/// assert_eq!(l.next_token().unwrap().token_type, TokenType::KwFn);
+/// assert_eq!(l.next_token().unwrap().token_type, TokenType::Iden("main".to_string()));
+/// assert_eq!(l.next_token().unwrap().token_type, TokenType::LPar);
+/// assert_eq!(l.next_token().unwrap().token_type, TokenType::RPar);
+/// assert_eq!(l.next_token().unwrap().token_type, TokenType::Colon);
+/// assert_eq!(l.next_token().unwrap().token_type, TokenType::KwInt);
+/// assert_eq!(l.next_token().unwrap().token_type, TokenType::LBrace);
+/// assert_eq!(l.next_token().unwrap().token_type, TokenType::RBrace);
+/// assert_eq!(l.next_token().unwrap().token_type, TokenType::EOF);
 /// ```
 #[derive(Debug)]
 pub struct Lexer<R: Read> {
@@ -38,10 +68,28 @@ pub struct Lexer<R: Read> {
     line: usize,
     /// Current column (character in line) of source code.
     column: usize,
+    /// Byte offset of `current` in source code.
+    offset: usize,
+    /// Number of bytes of `current` in source code.
+    current_len: usize,
+    /// Unit of `column`.
+    column_unit: ColumnUnit,
     /// Byte stream which provides source code.
     stream: BufReader<R>,
     /// Current character of source code.
     current: char,
+    /// Whether `current` replaces an invalid UTF-8 sequence.
+    current_invalid: bool,
+    /// Text of the token which is being scanned.
+    text: String,
+    /// Errors found while scanning the next token, which are not returned yet.
+    errors: VecDeque<LexError>,
+    /// Scanned token which will be returned after `errors`.
+    pending: Option<Token>,
+    /// Whether the iterator has returned the `EOF` token (or an I/O error).
+    finished: bool,
+    /// Whether whitespace is returned as [`TokenType::Whitespace`] tokens.
+    whitespace: bool,
 }
 
 impl<R: Read> Lexer<R> {
@@ -56,22 +104,102 @@ impl<R: Read> Lexer<R> {
     /// let mut l = Lexer::new(source);
     /// ```
     pub fn new(stream: R) -> Self {
+        Self::with_column_unit(stream, ColumnUnit::Char)
+    }
+
+    /// Creates a new `Lexer<R>` with provided byte stream as the token source,
+    /// which counts columns of tokens in the provided unit.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use lexer::{ColumnUnit, Lexer};
+    ///
+    /// let source = "\"😀\" fn".as_bytes();
+    /// let mut l = Lexer::with_column_unit(source, ColumnUnit::Utf16);
+    ///
+    /// l.next_token().unwrap();
+    /// assert_eq!(l.next_token().unwrap().span.column, 6);
+    /// ```
+    pub fn with_column_unit(stream: R, column_unit: ColumnUnit) -> Self {
         Self {
             line: 1,
-            column: 0,
+            column: 1,
+            offset: 0,
+            current_len: 0,
+            column_unit,
             stream: BufReader::new(stream),
             current: ' ',
+            current_invalid: false,
+            text: String::new(),
+            errors: VecDeque::new(),
+            pending: None,
+            finished: false,
+            whitespace: false,
         }
     }
 
+    /// Makes the lexer count positions of tokens from a byte offset and line,
+    /// as if the byte stream continued earlier source code which ended with
+    /// a newline. Useful when source code arrives in parts, like the input
+    /// of an interactive session.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use lexer::Lexer;
+    ///
+    /// let source = "let x".as_bytes();
+    /// let mut l = Lexer::new(source).starting_at(20, 3);
+    ///
+    /// l.next_token().unwrap();
+    /// let span = l.next_token().unwrap().span;
+    /// assert_eq!((span.start, span.line, span.column), (24, 3, 5));
+    /// ```
+    pub fn starting_at(mut self, offset: usize, line: usize) -> Self {
+        self.offset = offset;
+        self.line = line;
+        self
+    }
+
+    /// Sets whether whitespace is returned as [`TokenType::Whitespace`] tokens
+    /// instead of being skipped. Together with comments, these tokens cover
+    /// the whole source code, which allows to print it back unchanged.
+    /// Whitespace is skipped by default.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use lexer::{Lexer, token::TokenType};
+    ///
+    /// let source = "x  // comment\n".as_bytes();
+    /// let mut l = Lexer::new(source).with_whitespace(true);
+    ///
+    /// assert_eq!(l.next_token().unwrap().token_type, TokenType::Iden("x".to_string()));
+    /// assert_eq!(l.next_token().unwrap().token_type, TokenType::Whitespace("  ".to_string()));
+    /// assert_eq!(l.next_token().unwrap().token_type, TokenType::LC("// comment".to_string()));
+    /// assert_eq!(l.next_token().unwrap().token_type, TokenType::Whitespace("\n".to_string()));
+    /// assert_eq!(l.next_token().unwrap().token_type, TokenType::EOF);
+    /// ```
+    pub fn with_whitespace(mut self, whitespace: bool) -> Self {
+        self.whitespace = whitespace;
+        self
+    }
+
     /// Reads next token from provided byte stream, constructs and returns it.
     /// If Lexer reaches end of stream, it will return [`TokenType::EOF`] tokens
-    /// until there are new bytes on the stream.
+    /// until there are new bytes on the stream. Use the [`Iterator`]
+    /// implementation to stop after the first `EOF` token instead.
     ///
     /// # Errors
-    /// May return I/O error if something goes wrong while reading bytes
+    /// May return [`LexError::Io`] if something goes wrong while reading bytes
     /// from source.
     ///
+    /// Returns [`LexError::InvalidUtf8`] for each invalid UTF-8 sequence
+    /// in source. Invalid sequences are reported before the token which
+    /// contains them, and are replaced by `U+FFFD` in the token
+    /// (or skipped, if they are not part of any token).
+    ///
     /// # Examples
     ///
     /// ```
@@ -80,210 +208,51 @@ impl<R: Read> Lexer<R> {
     /// let source = "fn main".as_bytes();
     /// let mut l = Lexer::new(source);
     ///
-    /// assert_eq!(l.next().unwrap().token_type, TokenType::KwFn);
-    /// assert_eq!(l.next().unwrap().token_type, TokenType::Iden("main".to_string()));
-    /// assert_eq!(l.next().unwrap().token_type, TokenType::EOF);
+    /// assert_eq!(l.next_token().unwrap().token_type, TokenType::KwFn);
+    /// assert_eq!(l.next_token().unwrap().token_type, TokenType::Iden("main".to_string()));
+    /// assert_eq!(l.next_token().unwrap().token_type, TokenType::EOF);
     /// ```
-    pub fn next(&mut self) -> io::Result<Token> {
-        self.consume_whitespace()?;
-
-        let line = self.line;
-        let column = self.column;
-        let mut consumed = false;
-
-        let tok = match self.current {
-            '(' => TokenType::LPar,
-            ')' => TokenType::RPar,
-            '[' => TokenType::LBracket,
-            ']' => TokenType::RBracket,
-            '{' => TokenType::LBrace,
-            '}' => TokenType::RBrace,
-            '+' => TokenType::Plus,
-            '-' => TokenType::Minus,
-            '*' => TokenType::Asterisk,
-            '%' => TokenType::Percent,
-            '|' => TokenType::Pipe,
-            '&' => TokenType::Amp,
-            '~' => TokenType::Tilde,
-            ';' => TokenType::Semicolon,
-            ':' => TokenType::Colon,
-            ',' => TokenType::Comma,
-            '\0' => TokenType::EOF,
-            '<' => {
-                self.next_char()?;
-                if self.current == '=' {
-                    TokenType::LEq
-                } else {
-                    consumed = true;
-                    TokenType::LT
-                }
-            }
-            '>' => {
-                self.next_char()?;
-                if self.current == '=' {
-                    TokenType::GEq
-                } else {
-                    consumed = true;
-                    TokenType::GT
-                }
-            }
-            '!' => {
-                self.next_char()?;
-                if self.current == '=' {
-                    TokenType::NEq
-                } else {
-                    consumed = true;
-                    TokenType::Invalid("!".into())
-                }
-            }
-            '=' => {
-                self.next_char()?;
-                if self.current == '=' {
-                    TokenType::Eq
-                } else {
-                    consumed = true;
-                    TokenType::Assign
-                }
+    pub fn next_token(&mut self) -> Result<Token, LexError> {
+        if let Some(e) = self.errors.pop_front() {
+            return Err(e);
+        }
+        if let Some(tok) = self.pending.take() {
+            return Ok(tok);
+        }
+
+        let tok = self.scan()?;
+
+        match self.errors.pop_front() {
+            Some(e) => {
+                self.pending = Some(tok);
+                Err(e)
             }
-            '/' => {
+            None => Ok(tok),
+        }
+    }
+
+    /// Scans next token from stream.
+    fn scan(&mut self) -> io::Result<Token> {
+        loop {
+            if self.current_invalid {
+                // Already reported, skip it.
                 self.next_char()?;
-                consumed = true;
-                match self.current {
-                    '/' => {
-                        self.next_char()?;
-                        TokenType::LC("//".to_string() + &self.match_line_comment()?)
-                    }
-                    '*' => {
-                        self.next_char()?;
-                        let mut comment = "/*".to_string();
-                        comment.push_str(&self.match_block_comment()?);
-
-                        TokenType::BC(comment)
-                    }
-                    _ => TokenType::Slash,
-                }
-            }
-            '\'' => {
-                consumed = true;
-                let c = self.match_char()?;
-                match c.as_bytes().last() {
-                    Some(b'\'') => TokenType::LiteralChar(c),
-                    _ => TokenType::Invalid(c),
-                }
-            }
-            '"' => {
-                consumed = true;
-                let s = self.match_str()?;
-                match s.as_bytes().last() {
-                    Some(b'"') => TokenType::LiteralStr(s),
-                    _ => TokenType::Invalid(s),
-                }
-            }
-            '.' => {
+            } else if !self.current.is_whitespace() {
+                break;
+            } else if !self.whitespace {
+                self.consume_whitespace()?;
+            } else if self.current_len > 0 {
+                break;
+            } else {
+                // Placeholder before the first character of stream.
                 self.next_char()?;
-                consumed = true;
-                if self.current.is_digit(10) {
-                    // float literal
-                    let mut f = '.'.to_string();
-                    f.push_str(&self.match_scientific()?);
-                    TokenType::LiteralFloat(f)
-                } else {
-                    TokenType::Period
-                }
             }
-            c => {
-                consumed = true;
-                let mut tmp = String::new();
-                if c.is_ascii_alphabetic() || c == '_' {
-                    // identifier or keyword
-                    tmp.push_str(&self.match_iden()?);
-                    match tmp.as_str() {
-                        "for" => TokenType::KwFor,
-                        "to" => TokenType::KwTo,
-                        "while" => TokenType::KwWhile,
-                        "if" => TokenType::KwIf,
-                        "else" => TokenType::KwElse,
-                        "true" => TokenType::KwTrue,
-                        "false" => TokenType::KwFalse,
-                        "or" => TokenType::KwOr,
-                        "and" => TokenType::KwAnd,
-                        "not" => TokenType::KwNot,
-                        "let" => TokenType::KwLet,
-                        "fn" => TokenType::KwFn,
-                        "return" => TokenType::KwReturn,
-                        "int" => TokenType::KwInt,
-                        "char" => TokenType::KwChar,
-                        "bool" => TokenType::KwBool,
-                        "float" => TokenType::KwFloat,
-                        "str" => TokenType::KwStr,
-                        _ => TokenType::Iden(tmp),
-                    }
-                } else if c.is_digit(10) {
-                    // numeric (int or float)
-                    tmp.push_str(&self.match_num(10)?);
-
-                    if tmp.as_str() == "0" {
-                        // prefixed int?
-                        match self.current {
-                            'b' | 'B' => {
-                                tmp.push(self.current);
-                                self.next_char()?;
-                                tmp.push_str(&self.match_num(2)?);
-                                TokenType::LiteralIntBin(tmp)
-                            }
-                            'o' | 'O' => {
-                                tmp.push(self.current);
-                                self.next_char()?;
-                                tmp.push_str(&self.match_num(8)?);
-                                TokenType::LiteralIntOct(tmp)
-                            }
-                            'x' | 'X' => {
-                                tmp.push(self.current);
-                                self.next_char()?;
-                                tmp.push_str(&self.match_num(16)?);
-                                TokenType::LiteralIntHex(tmp)
-                            }
-                            '.' => {
-                                tmp.push(self.current);
-                                tmp.push_str(&self.match_scientific()?);
-                                TokenType::LiteralFloat(tmp)
-                            }
-                            'e' => {
-                                tmp.push_str(&self.match_scientific()?);
-                                TokenType::LiteralFloat(tmp)
-                            }
-                            _ => TokenType::LiteralIntDec(tmp),
-                        }
-                    } else {
-                        match self.current {
-                            '.' => {
-                                tmp.push(self.current);
-                                self.next_char()?;
-                                tmp.push_str(&self.match_scientific()?);
-                                TokenType::LiteralFloat(tmp)
-                            }
-                            'e' => {
-                                tmp.push_str(&self.match_scientific()?);
-                                TokenType::LiteralFloat(tmp)
-                            }
-                            _ => TokenType::LiteralIntDec(tmp),
-                        }
-                    }
-                } else {
-                    TokenType::Invalid(tmp)
-                }
-            }
-        };
-
-        if !consumed && !matches!(tok, TokenType::Invalid(_)) {
-            self.next_char()?;
         }
 
-        Ok(Token {
-            token_type: tok,
-            line,
-            column,
-        })
+        let (span, tok) = self.scan_token()?;
+        let text = std::mem::take(&mut self.text);
+
+        Ok(Token::new(span, tok.map(|()| text)))
     }
 
     /// Discards whitespace characters until it reaches a non-whitespace character
@@ -296,165 +265,208 @@ impl<R: Read> Lexer<R> {
         Ok(())
     }
 
-    /// Reads next identifier (or keyword) token from stream and returns
-    /// it as a string. Consumes all bytes of token. May return empty string.
-    fn match_iden(&mut self) -> io::Result<String> {
-        let mut id = String::new();
+    /// Decodes next UTF-8 character from stream and puts it in `self.current`.
+    /// If reaches end of stream, it will put `'\0'` to indicate end of file.
+    /// Also updates `self.line` and `self.column` based on next character.
+    ///
+    /// Invalid UTF-8 sequences are replaced by [`char::REPLACEMENT_CHARACTER`]
+    /// and queued in `self.errors`.
+    ///
+    /// # Errors
+    /// May return I/O error if something goes wrong while reading bytes
+    /// from source.
+    fn next_char(&mut self) -> io::Result<()> {
+        if self.current_len > 0 {
+            // Move past the current character.
+            self.offset += self.current_len;
+            self.current_len = 0;
 
-        while self.current.is_ascii_alphanumeric() || self.current == '_' {
-            id.push(self.current);
-            self.next_char()?;
+            if self.current == '\n' {
+                self.line += 1;
+                self.column = 1;
+            } else {
+                self.column += match self.column_unit {
+                    ColumnUnit::Char => 1,
+                    ColumnUnit::Utf16 => self.current.len_utf16(),
+                };
+            }
         }
 
-        Ok(id)
-    }
-
-    /// Reads next integer numeric token from stream and returns
-    /// it as a string. Doesn't match prefixes (0b, 0x, etc.).
-    /// `base` parameter defines radix or base of number
-    /// (binary, octal, decimal, hexadecimal, etc. ).
-    /// Consumes all bytes of token. May return empty string.
-    fn match_num(&mut self, base: u32) -> io::Result<String> {
-        let mut num = String::new();
+        let first = match self.peek_byte()? {
+            Some(b) => b,
+            None => {
+                self.current = '\0';
+                self.current_invalid = false;
+                return Ok(());
+            }
+        };
+        self.stream.consume(1);
+
+        let len = match first {
+            0x00..=0x7F => 1,
+            0xC2..=0xDF => 2,
+            0xE0..=0xEF => 3,
+            0xF0..=0xF4 => 4,
+            _ => 0,
+        };
 
-        while self.current.is_digit(base) {
-            num.push(self.current);
-            self.next_char()?;
+        let mut bytes = vec![first];
+        while bytes.len() < len {
+            match self.peek_byte()? {
+                Some(b @ 0x80..=0xBF) => {
+                    bytes.push(b);
+                    self.stream.consume(1);
+                }
+                _ => break,
+            }
         }
 
-        Ok(num)
-    }
+        let decoded = match std::str::from_utf8(&bytes) {
+            Ok(s) if bytes.len() == len => s.chars().next(),
+            _ => None,
+        };
 
-    /// Reads next scientific number token from stream and returns
-    /// it as a string. Consumes all bytes of token.
-    fn match_scientific(&mut self) -> io::Result<String> {
-        let mut num = self.match_num(10)?;
+        self.current_len = bytes.len();
 
-        if self.current == 'e' || self.current == 'E' {
-            num.push(self.current);
-            self.next_char()?;
-            if self.current == '+' || self.current == '-' {
-                num.push(self.current);
-                self.next_char()?;
+        match decoded {
+            Some(c) => {
+                self.current = c;
+                self.current_invalid = false;
+            }
+            None => {
+                self.current = char::REPLACEMENT_CHARACTER;
+                self.current_invalid = true;
+                let start = Span::empty(self.offset, self.line, self.column);
+                let end = Span::empty(self.offset + bytes.len(), self.line, self.column + 1);
+                self.errors.push_back(LexError::InvalidUtf8 {
+                    span: start.to(end),
+                    bytes,
+                });
             }
-            num.push_str(&self.match_num(10)?);
         }
 
-        Ok(num)
+        Ok(())
     }
 
-    /// Reads next character literal token from stream and returns
-    /// it as a string. Consumes two (normal) or three (escaped) bytes,
-    /// including starting and ending `'`.
-    fn match_char(&mut self) -> io::Result<String> {
-        let mut ch = String::from(self.current);
-        self.next_char()?;
-
-        ch.push(self.current);
-        self.next_char()?;
-
-        if ch == "'\\" && self.current != '\0' {
-            ch.push(self.current);
-            self.next_char()?;
+    /// Returns next byte of stream without consuming it,
+    /// or `None` if reaches end of stream.
+    fn peek_byte(&mut self) -> io::Result<Option<u8>> {
+        loop {
+            match self.stream.fill_buf() {
+                Ok(buf) => return Ok(buf.first().copied()),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            }
         }
+    }
+}
 
-        if self.current == '\'' {
-            ch.push(self.current);
-            self.next_char()?;
-        }
+/// Iterates over tokens of the source, including the [`TokenType::EOF`] token.
+/// The iterator ends after returning `EOF` or an [`LexError::Io`] error.
+///
+/// # Examples
+///
+/// ```
+/// use lexer::{Lexer, token::TokenType};
+///
+/// let source = "fn main".as_bytes();
+/// let types: Vec<_> = Lexer::new(source).map(|t| t.unwrap().token_type).collect();
+///
+/// assert_eq!(types, vec![TokenType::KwFn, TokenType::Iden("main".into()), TokenType::EOF]);
+/// ```
+impl<R: Read> Scanner for Lexer<R> {
+    type Error = io::Error;
 
-        Ok(ch)
+    fn current(&self) -> char {
+        self.current
     }
 
-    /// Reads next string literal token from stream and returns
-    /// it as a string. Consumes all bytes of token, including
-    /// starting and ending `"`;
-    fn match_str(&mut self) -> io::Result<String> {
-        let mut s = String::from(self.current);
-        self.next_char()?;
-
-        let mut escape = false;
+    fn position(&self) -> Span {
+        Span::empty(self.offset, self.line, self.column)
+    }
 
-        while (self.current != '\"' && self.current != '\0') || escape {
-            s.push(self.current);
-            escape = self.current == '\\';
-            self.next_char()?;
-        }
+    fn begin(&mut self) {
+        self.text.clear();
+    }
 
-        if self.current == '"' {
-            s.push(self.current);
-            self.next_char()?;
+    fn advance(&mut self) -> io::Result<()> {
+        if self.current_len > 0 {
+            self.text.push(self.current);
         }
+        self.next_char()
+    }
 
-        Ok(s)
+    fn text(&self) -> &str {
+        &self.text
     }
+}
 
-    /// Reads next line comment token from stream and returns
-    /// it as a string. Consumes all bytes of token
-    /// excluding starting `//`.
-    fn match_line_comment(&mut self) -> io::Result<String> {
-        let mut comment = String::new();
+impl<R: Read> Iterator for Lexer<R> {
+    type Item = Result<Token, LexError>;
 
-        while self.current != '\n' && self.current != '\0' {
-            comment.push(self.current);
-            self.next_char()?;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
         }
 
-        Ok(comment)
+        let item = self.next_token();
+        self.finished = match &item {
+            Ok(tok) => tok.token_type == TokenType::EOF,
+            Err(e) => matches!(e, LexError::Io(_)),
+        };
+        Some(item)
     }
+}
 
-    /// Reads next block comment token from stream and returns
-    /// it as a string. Consumes all bytes of token excluding
-    /// starting `/*` but including final `*/`.
-    fn match_block_comment(&mut self) -> io::Result<String> {
-        let mut comment = String::new();
-        let mut asterisk = false;
+impl<R: Read> FusedIterator for Lexer<R> {}
 
-        while self.current != '\0' && !(asterisk && self.current == '/') {
-            comment.push(self.current);
-            asterisk = self.current == '*';
-            self.next_char()?;
+#[cfg(test)]
+mod tests {
+    use super::{ColumnUnit, LexError, Lexer};
+    use crate::scan::Scanner;
+    use crate::token::{Token, TokenType};
+    use diagnostics::Span;
+    use std::io::{self, Read};
+
+    /// Scans part of a token like [`Scanner::scan_token`] and returns its text.
+    impl<R: Read> Lexer<R> {
+        fn matched(
+            &mut self,
+            skip: impl FnOnce(&mut Self) -> io::Result<()>,
+        ) -> io::Result<String> {
+            self.begin();
+            skip(self)?;
+            Ok(std::mem::take(&mut self.text))
         }
-        // Push final slash
-        if self.current != '\0' {
-            comment.push(self.current);
-            self.next_char()?;
+
+        fn match_iden(&mut self) -> io::Result<String> {
+            self.matched(Self::skip_iden)
         }
 
-        Ok(comment)
-    }
+        fn match_num(&mut self, base: u32) -> io::Result<String> {
+            self.matched(|l| l.skip_num(base))
+        }
 
-    /// Reads next byte from stream and puts it in `self.current` as `char`.
-    /// If reaches end of stream, it will put `'\0'` to indicate end of file.
-    /// Also updates `self.line` and `self.column` based on next character.
-    ///
-    /// # Errors
-    /// May return I/O error if something goes wrong while reading bytes
-    /// from source.
-    fn next_char(&mut self) -> io::Result<()> {
-        let mut buf = [0u8];
-        let c = self.stream.read(&mut buf)?;
+        fn match_scientific(&mut self) -> io::Result<String> {
+            self.matched(Self::skip_scientific)
+        }
 
-        self.current = if c == 1 {
-            if self.current == '\n' {
-                self.line += 1;
-                self.column = 1;
-            } else {
-                self.column += 1;
-            }
+        fn match_char(&mut self) -> io::Result<String> {
+            self.matched(Self::skip_char)
+        }
 
-            buf[0] as char
-        } else {
-            '\0'
-        };
+        fn match_str(&mut self) -> io::Result<String> {
+            self.matched(Self::skip_str)
+        }
 
-        Ok(())
-    }
-}
+        fn match_line_comment(&mut self) -> io::Result<String> {
+            self.matched(Self::skip_line_comment)
+        }
 
-#[cfg(test)]
-mod tests {
-    use super::Lexer;
+        fn match_block_comment(&mut self) -> io::Result<String> {
+            self.matched(Self::skip_block_comment)
+        }
+    }
 
     fn create_lexer(code: &str) -> Lexer<&[u8]> {
         let source = code.as_bytes();
@@ -503,6 +515,78 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn next_char_utf8() -> std::io::Result<()> {
+        let source_str = "سلام دنیا!\n'é' // 😀\n";
+        let mut l = create_lexer(source_str);
+
+        let mut col = 1;
+        for c in source_str.chars() {
+            assert_eq!(l.current, c);
+            assert_eq!(l.column, col);
+            assert!(!l.current_invalid);
+            col = if c == '\n' { 1 } else { col + 1 };
+            l.next_char()?;
+        }
+
+        assert_eq!(l.current, '\0');
+        assert!(l.errors.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn next_char_utf16() -> std::io::Result<()> {
+        let source = "a😀b".as_bytes();
+        let mut l = Lexer::with_column_unit(source, ColumnUnit::Utf16);
+        l.next_char()?;
+
+        assert_eq!((l.current, l.column, l.offset), ('a', 1, 0));
+        l.next_char()?;
+        assert_eq!((l.current, l.column, l.offset), ('😀', 2, 1));
+        l.next_char()?;
+        assert_eq!((l.current, l.column, l.offset), ('b', 4, 5));
+
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_utf8() -> Result<(), LexError> {
+        // Lone continuation byte, truncated sequence and overlong encoding.
+        let source: &[u8] = b"\"a\x80b\" \xC3( \xC0\xAF x";
+        let mut l = Lexer::new(source);
+
+        match l.next_token() {
+            Err(LexError::InvalidUtf8 { span, bytes }) => {
+                assert_eq!((span.line, span.column), (1, 3));
+                assert_eq!((span.start, span.end), (2, 3));
+                assert_eq!(bytes, vec![0x80]);
+            }
+            r => panic!("Expected invalid UTF-8 error, got {:?}", r),
+        }
+        assert_eq!(
+            l.next_token()?.token_type,
+            TokenType::LiteralStr("\"a\u{FFFD}b\"".into())
+        );
+
+        match l.next_token() {
+            Err(LexError::InvalidUtf8 { span, bytes }) => {
+                assert_eq!((span.column, span.start), (7, 6));
+                assert_eq!(bytes, vec![0xC3]);
+            }
+            r => panic!("Expected invalid UTF-8 error, got {:?}", r),
+        }
+        assert_eq!(l.next_token()?.token_type, TokenType::LPar);
+
+        assert!(matches!(l.next_token(), Err(LexError::InvalidUtf8 { span: Span { column: 10, start: 9, .. }, .. })));
+        assert!(matches!(l.next_token(), Err(LexError::InvalidUtf8 { span: Span { column: 11, start: 10, .. }, .. })));
+        let span = Span { start: 12, end: 13, line: 1, column: 13, end_line: 1, end_column: 14 };
+        assert_eq!(l.next_token()?, Token::new(span, TokenType::Iden("x".into())));
+        assert_eq!(l.next_token()?.token_type, TokenType::EOF);
+
+        Ok(())
+    }
+
     #[test]
     fn whitespace() -> std::io::Result<()> {
         let source_str = concat!(
@@ -510,7 +594,7 @@ mod tests {
             "Never gonna \t make you cry\n",
             "Never gonna say goodbye \n"
         );
-        let mut l = create_lexer(&source_str);
+        let mut l = create_lexer(source_str);
 
         for word in source_str.to_string().split_ascii_whitespace() {
             l.consume_whitespace()?;
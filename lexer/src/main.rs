@@ -1,17 +1,33 @@
 use std::{
-    fs::File,
+    fs,
     io::{self, BufRead, Write},
 };
 
-use lexer::{token::TokenType, Lexer};
+use diagnostics::{Renderer, SourceFile};
+use lexer::{LexError, Lexer};
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 
 fn main() -> io::Result<()> {
-    let args: Vec<String> = std::env::args().collect();
+    let mut json = false;
+    let mut path = None;
+    for arg in std::env::args().skip(1) {
+        match arg.as_str() {
+            "--emit=tokens-json" if cfg!(feature = "serde") => json = true,
+            "--emit=tokens-json" => {
+                eprintln!("`--emit=tokens-json` requires the `serde` feature");
+                std::process::exit(2);
+            }
+            _ if arg.starts_with("--") => {
+                eprintln!("Unknown option `{}`, expected `--emit=tokens-json`", arg);
+                std::process::exit(2);
+            }
+            _ => path = Some(arg),
+        }
+    }
 
-    let path = if args.len() > 1 {
-        args[1].clone()
+    let path = if let Some(path) = path {
+        path
     } else {
         println!("⛏️  Kolang lexer v{}\n", VERSION);
         println!("Code file path (relative or absolute):");
@@ -23,15 +39,31 @@ fn main() -> io::Result<()> {
         buf.trim_end().to_string()
     };
 
-    let f = File::open(path)?;
-    let mut l = Lexer::new(f);
+    let source = fs::read(&path)?;
+    let l = Lexer::new(source.as_slice());
+
+    let text = String::from_utf8_lossy(&source);
+    let file = SourceFile::new(&path, &text);
+    let renderer = Renderer::for_stderr();
 
-    while let Ok(tok) = l.next() {
-        println!("{}", tok);
-        if tok.token_type == TokenType::EOF {
-            break;
+    let mut tokens = Vec::new();
+    for tok in l {
+        match tok {
+            Ok(tok) if json => tokens.push(tok),
+            Ok(tok) => println!("{}", tok),
+            Err(LexError::Io(e)) => return Err(e),
+            Err(e) => {
+                if let Some(d) = e.to_diagnostic() {
+                    eprintln!("{}", renderer.render(&d, &file));
+                }
+            }
         }
     }
 
+    #[cfg(feature = "serde")]
+    if json {
+        println!("{}", serde_json::to_string(&tokens)?);
+    }
+
     Ok(())
 }
@@ -0,0 +1,266 @@
+use diagnostics::Span;
+
+use crate::token::TokenType;
+
+/// Source of characters which tokens are scanned from. [`Lexer<R>`](crate::Lexer)
+/// reads characters from a byte stream and collects the text of tokens,
+/// while [`StrLexer`](crate::StrLexer) borrows it from the source code.
+/// Both scan tokens with [`Scanner::scan_token`], which knows the syntax
+/// of tokens.
+pub(crate) trait Scanner {
+    /// Error which may occur while reading the next character.
+    type Error;
+
+    /// Returns the current character, or `'\0'` at end of source.
+    fn current(&self) -> char;
+
+    /// Returns an empty span at the current character.
+    fn position(&self) -> Span;
+
+    /// Starts a new token at the current character.
+    fn begin(&mut self);
+
+    /// Adds the current character to the token and moves to the next one.
+    fn advance(&mut self) -> Result<(), Self::Error>;
+
+    /// Returns the text of the token, up to the current character.
+    fn text(&self) -> &str;
+
+    /// Scans a token which starts at the current character, which is not
+    /// skipped whitespace. Returns its span and type, whose text is the text
+    /// of the token.
+    fn scan_token(&mut self) -> Result<(Span, TokenType<()>), Self::Error> {
+        self.begin();
+        let start = self.position();
+        let mut consumed = false;
+
+        let tok = match self.current() {
+            '(' => TokenType::LPar,
+            ')' => TokenType::RPar,
+            '[' => TokenType::LBracket,
+            ']' => TokenType::RBracket,
+            '{' => TokenType::LBrace,
+            '}' => TokenType::RBrace,
+            '+' => TokenType::Plus,
+            '-' => TokenType::Minus,
+            '*' => TokenType::Asterisk,
+            '%' => TokenType::Percent,
+            '|' => TokenType::Pipe,
+            '&' => TokenType::Amp,
+            '~' => TokenType::Tilde,
+            ';' => TokenType::Semicolon,
+            ':' => TokenType::Colon,
+            ',' => TokenType::Comma,
+            '\0' => TokenType::EOF,
+            c if c.is_whitespace() => {
+                consumed = true;
+                while self.current().is_whitespace() {
+                    self.advance()?;
+                }
+                TokenType::Whitespace(())
+            }
+            first @ ('<' | '>' | '!' | '=') => {
+                self.advance()?;
+                if self.current() == '=' {
+                    match first {
+                        '<' => TokenType::LEq,
+                        '>' => TokenType::GEq,
+                        '!' => TokenType::NEq,
+                        _ => TokenType::Eq,
+                    }
+                } else {
+                    consumed = true;
+                    match first {
+                        '<' => TokenType::LT,
+                        '>' => TokenType::GT,
+                        '!' => TokenType::Invalid(()),
+                        _ => TokenType::Assign,
+                    }
+                }
+            }
+            '/' => {
+                self.advance()?;
+                consumed = true;
+                match self.current() {
+                    '/' => {
+                        self.skip_line_comment()?;
+                        TokenType::LC(())
+                    }
+                    '*' => {
+                        self.advance()?;
+                        self.skip_block_comment()?;
+                        TokenType::BC(())
+                    }
+                    _ => TokenType::Slash,
+                }
+            }
+            '\'' => {
+                consumed = true;
+                self.skip_char()?;
+                let text = self.text();
+                if text.len() > 1 && text.ends_with('\'') {
+                    TokenType::LiteralChar(())
+                } else {
+                    TokenType::Invalid(())
+                }
+            }
+            '"' => {
+                consumed = true;
+                self.skip_str()?;
+                let text = self.text();
+                if text.len() > 1 && text.ends_with('"') {
+                    TokenType::LiteralStr(())
+                } else {
+                    TokenType::Invalid(())
+                }
+            }
+            '.' => {
+                self.advance()?;
+                consumed = true;
+                if self.current().is_ascii_digit() {
+                    self.skip_scientific()?;
+                    TokenType::LiteralFloat(())
+                } else {
+                    TokenType::Period
+                }
+            }
+            c => {
+                consumed = true;
+                if c.is_ascii_alphabetic() || c == '_' {
+                    self.skip_iden()?;
+                    TokenType::keyword(self.text()).unwrap_or(TokenType::Iden(()))
+                } else if c.is_ascii_digit() {
+                    self.skip_num(10)?;
+                    let zero = self.text() == "0";
+
+                    match self.current() {
+                        'b' | 'B' if zero => {
+                            self.advance()?;
+                            self.skip_num(2)?;
+                            TokenType::LiteralIntBin(())
+                        }
+                        'o' | 'O' if zero => {
+                            self.advance()?;
+                            self.skip_num(8)?;
+                            TokenType::LiteralIntOct(())
+                        }
+                        'x' | 'X' if zero => {
+                            self.advance()?;
+                            self.skip_num(16)?;
+                            TokenType::LiteralIntHex(())
+                        }
+                        '.' => {
+                            self.advance()?;
+                            self.skip_scientific()?;
+                            TokenType::LiteralFloat(())
+                        }
+                        'e' => {
+                            self.skip_scientific()?;
+                            TokenType::LiteralFloat(())
+                        }
+                        _ => TokenType::LiteralIntDec(()),
+                    }
+                } else {
+                    self.advance()?;
+                    TokenType::Invalid(())
+                }
+            }
+        };
+
+        if !consumed && !matches!(tok, TokenType::Invalid(_)) {
+            self.advance()?;
+        }
+
+        Ok((start.to(self.position()), tok))
+    }
+
+    /// Skips an identifier (or keyword). Also skips digits, so it may skip
+    /// identifiers which start with a digit.
+    fn skip_iden(&mut self) -> Result<(), Self::Error> {
+        while self.current().is_ascii_alphanumeric() || self.current() == '_' {
+            self.advance()?;
+        }
+        Ok(())
+    }
+
+    /// Skips digits of provided radix. Doesn't skip prefixes (0b, 0x, etc.).
+    fn skip_num(&mut self, base: u32) -> Result<(), Self::Error> {
+        while self.current().is_digit(base) {
+            self.advance()?;
+        }
+        Ok(())
+    }
+
+    /// Skips the digits and the exponent of a floating-point literal.
+    fn skip_scientific(&mut self) -> Result<(), Self::Error> {
+        self.skip_num(10)?;
+
+        if self.current() == 'e' || self.current() == 'E' {
+            self.advance()?;
+            if self.current() == '+' || self.current() == '-' {
+                self.advance()?;
+            }
+            self.skip_num(10)?;
+        }
+        Ok(())
+    }
+
+    /// Skips a character literal, including starting and ending `'`. Skips
+    /// two (normal) or three (escaped) characters before the ending `'`.
+    fn skip_char(&mut self) -> Result<(), Self::Error> {
+        self.advance()?;
+
+        if self.current() != '\0' {
+            let escape = self.current() == '\\';
+            self.advance()?;
+
+            if escape && self.current() != '\0' {
+                self.advance()?;
+            }
+        }
+
+        if self.current() == '\'' {
+            self.advance()?;
+        }
+        Ok(())
+    }
+
+    /// Skips a string literal, including starting and ending `"`.
+    fn skip_str(&mut self) -> Result<(), Self::Error> {
+        self.advance()?;
+
+        let mut escape = false;
+        while self.current() != '\0' && (self.current() != '"' || escape) {
+            escape = self.current() == '\\';
+            self.advance()?;
+        }
+
+        if self.current() == '"' {
+            self.advance()?;
+        }
+        Ok(())
+    }
+
+    /// Skips a line comment, until the end of line.
+    fn skip_line_comment(&mut self) -> Result<(), Self::Error> {
+        while self.current() != '\n' && self.current() != '\0' {
+            self.advance()?;
+        }
+        Ok(())
+    }
+
+    /// Skips a block comment after the starting `/*`, including final `*/`.
+    fn skip_block_comment(&mut self) -> Result<(), Self::Error> {
+        let mut asterisk = false;
+
+        while self.current() != '\0' && !(asterisk && self.current() == '/') {
+            asterisk = self.current() == '*';
+            self.advance()?;
+        }
+        // Skip final slash
+        if self.current() != '\0' {
+            self.advance()?;
+        }
+        Ok(())
+    }
+}
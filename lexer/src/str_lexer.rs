@@ -0,0 +1,195 @@
+use std::{convert::Infallible, iter::FusedIterator};
+
+use diagnostics::Span;
+
+use crate::{
+    scan::Scanner,
+    token::{Token, TokenType},
+    ColumnUnit,
+};
+
+/// The `StrLexer<'a>` struct scans Kolang code which is already in memory.
+/// Unlike [`Lexer<R>`](crate::Lexer), it does not copy the text of tokens:
+/// identifiers, literals and comments borrow slices of the source. For the
+/// same code, both lexers produce the same tokens.
+///
+/// Since the source is a `&str`, scanning never fails.
+///
+/// # Examples
+///
+/// ```
+/// use lexer::{token::TokenType, StrLexer};
+///
+/// let source = "fn main(): int {}";
+/// let mut l = StrLexer::new(source);
+///
+/// assert_eq!(l.next_token().token_type, TokenType::KwFn);
+/// assert_eq!(l.next_token().token_type, TokenType::Iden("main"));
+///
+/// let rest: Vec<_> = l.map(|t| t.token_type).collect();
+/// assert_eq!(rest.len(), 7);
+/// assert_eq!(rest.last(), Some(&TokenType::EOF));
+/// ```
+#[derive(Debug, Clone)]
+pub struct StrLexer<'a> {
+    /// Source code.
+    source: &'a str,
+    /// Byte offset of `current` in source code.
+    offset: usize,
+    /// Byte offset of the token which is being scanned.
+    start: usize,
+    /// Current line of source code.
+    line: usize,
+    /// Current column (character in line) of source code.
+    column: usize,
+    /// Unit of `column`.
+    column_unit: ColumnUnit,
+    /// Current character of source code, or `'\0'` at end of source.
+    current: char,
+    /// Whether the iterator has returned the `EOF` token.
+    finished: bool,
+    /// Whether whitespace is returned as [`TokenType::Whitespace`] tokens.
+    whitespace: bool,
+}
+
+impl<'a> StrLexer<'a> {
+    /// Creates a new `StrLexer<'a>` which scans provided source code.
+    pub fn new(source: &'a str) -> Self {
+        Self::with_column_unit(source, ColumnUnit::Char)
+    }
+
+    /// Creates a new `StrLexer<'a>` which scans provided source code
+    /// and counts columns of tokens in the provided unit.
+    pub fn with_column_unit(source: &'a str, column_unit: ColumnUnit) -> Self {
+        let mut l = Self {
+            source,
+            offset: 0,
+            start: 0,
+            line: 1,
+            column: 1,
+            column_unit,
+            current: '\0',
+            finished: false,
+            whitespace: false,
+        };
+        l.current = l.char_at(0);
+        l
+    }
+
+    /// Sets whether whitespace is returned as [`TokenType::Whitespace`] tokens
+    /// instead of being skipped, like [`Lexer::with_whitespace`](crate::Lexer::with_whitespace).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use lexer::StrLexer;
+    ///
+    /// let source = "fn main() {\n}\n";
+    /// let text: String = StrLexer::new(source)
+    ///     .with_whitespace(true)
+    ///     .map(|t| t.token_type.to_string())
+    ///     .filter(|t| t != "EOF")
+    ///     .collect::<Vec<_>>()
+    ///     .join("|");
+    ///
+    /// assert_eq!(text, "fn|whitespace|main|(|)|whitespace|{|whitespace|}|whitespace");
+    /// ```
+    pub fn with_whitespace(mut self, whitespace: bool) -> Self {
+        self.whitespace = whitespace;
+        self
+    }
+
+    /// Scans and returns the next token. If Lexer reaches end of source,
+    /// it will return [`TokenType::EOF`] tokens.
+    pub fn next_token(&mut self) -> Token<&'a str> {
+        while !self.whitespace && self.current.is_whitespace() {
+            self.next_char();
+        }
+
+        let (span, tok) = match self.scan_token() {
+            Ok(scanned) => scanned,
+            Err(e) => match e {},
+        };
+        let text = &self.source[self.start..self.offset];
+
+        Token {
+            span,
+            token_type: tok.map(|()| text),
+        }
+    }
+
+    /// Moves to the next character of source and updates `self.line`
+    /// and `self.column`. Puts `'\0'` in `self.current` at end of source.
+    fn next_char(&mut self) {
+        if self.offset >= self.source.len() {
+            return;
+        }
+
+        self.offset += self.current.len_utf8();
+        if self.current == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += match self.column_unit {
+                ColumnUnit::Char => 1,
+                ColumnUnit::Utf16 => self.current.len_utf16(),
+            };
+        }
+
+        self.current = self.char_at(self.offset);
+    }
+
+    /// Returns the character which starts at provided byte offset,
+    /// or `'\0'` at end of source.
+    fn char_at(&self, offset: usize) -> char {
+        match self.source.as_bytes().get(offset) {
+            None => '\0',
+            // Fast path for ASCII.
+            Some(&b) if b.is_ascii() => b as char,
+            Some(_) => self.source[offset..].chars().next().unwrap_or('\0'),
+        }
+    }
+}
+
+impl Scanner for StrLexer<'_> {
+    type Error = Infallible;
+
+    fn current(&self) -> char {
+        self.current
+    }
+
+    fn position(&self) -> Span {
+        Span::empty(self.offset, self.line, self.column)
+    }
+
+    fn begin(&mut self) {
+        self.start = self.offset;
+    }
+
+    fn advance(&mut self) -> Result<(), Infallible> {
+        self.next_char();
+        Ok(())
+    }
+
+    fn text(&self) -> &str {
+        &self.source[self.start..self.offset]
+    }
+}
+
+/// Iterates over tokens of the source, including the [`TokenType::EOF`] token.
+/// The iterator ends after returning `EOF`.
+impl<'a> Iterator for StrLexer<'a> {
+    type Item = Token<&'a str>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+
+        let tok = self.next_token();
+        self.finished = tok.token_type == TokenType::EOF;
+        Some(tok)
+    }
+}
+
+impl FusedIterator for StrLexer<'_> {}
@@ -0,0 +1,96 @@
+use std::{collections::VecDeque, io::Read, iter::FusedIterator};
+
+use crate::{
+    token::{Token, TokenType},
+    LexError, Lexer,
+};
+
+/// The `TokenStream<R>` struct iterates over tokens of a [`Lexer<R>`] and
+/// allows looking any number of tokens ahead without consuming them.
+/// Like the lexer iterator, the stream ends after the `EOF` token.
+///
+/// # Examples
+///
+/// ```
+/// use lexer::{token::TokenType, Lexer, TokenStream};
+///
+/// let source = "a = // comment\n b;".as_bytes();
+/// let mut s = TokenStream::new(Lexer::new(source)).with_comments(false);
+///
+/// assert_eq!(s.peek_nth(1).unwrap().as_ref().unwrap().token_type, TokenType::Assign);
+/// assert_eq!(s.peek_nth(2).unwrap().as_ref().unwrap().token_type, TokenType::Iden("b".into()));
+/// assert_eq!(s.next().unwrap().unwrap().token_type, TokenType::Iden("a".into()));
+/// assert_eq!(s.count(), 4);
+/// ```
+#[derive(Debug)]
+pub struct TokenStream<R: Read> {
+    /// The token source.
+    lexer: Lexer<R>,
+    /// Scanned tokens (and errors) which are not consumed yet.
+    buffer: VecDeque<Result<Token, LexError>>,
+    /// Whether trivia tokens (comments and whitespace) are returned.
+    comments: bool,
+}
+
+impl<R: Read> TokenStream<R> {
+    /// Creates a new `TokenStream<R>` which reads tokens from provided lexer.
+    pub fn new(lexer: Lexer<R>) -> Self {
+        Self {
+            lexer,
+            buffer: VecDeque::new(),
+            comments: true,
+        }
+    }
+
+    /// Sets whether comment tokens ([`TokenType::LC`] and [`TokenType::BC`])
+    /// are returned. Comments are returned by default. Without comments,
+    /// [`TokenType::Whitespace`] tokens are skipped too.
+    pub fn with_comments(mut self, comments: bool) -> Self {
+        self.comments = comments;
+        self
+    }
+
+    /// Returns the next item without consuming it.
+    /// Returns `None` if the stream has ended.
+    pub fn peek(&mut self) -> Option<&Result<Token, LexError>> {
+        self.peek_nth(0)
+    }
+
+    /// Returns the `n`th next item (zero-based) without consuming it.
+    /// Errors count as items too. Returns `None` if the stream ends before
+    /// that item.
+    pub fn peek_nth(&mut self, n: usize) -> Option<&Result<Token, LexError>> {
+        while self.buffer.len() <= n {
+            let item = self.scan()?;
+            self.buffer.push_back(item);
+        }
+        self.buffer.get(n)
+    }
+
+    /// Reads the next item from the lexer, skipping trivia if needed.
+    fn scan(&mut self) -> Option<Result<Token, LexError>> {
+        loop {
+            let item = self.lexer.next()?;
+            match &item {
+                Ok(Token {
+                    token_type: TokenType::LC(_) | TokenType::BC(_) | TokenType::Whitespace(_),
+                    ..
+                }) if !self.comments => continue,
+                _ => return Some(item),
+            }
+        }
+    }
+}
+
+impl<R: Read> Iterator for TokenStream<R> {
+    type Item = Result<Token, LexError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.buffer.pop_front() {
+            Some(item) => Some(item),
+            None => self.scan(),
+        }
+    }
+}
+
+impl<R: Read> FusedIterator for TokenStream<R> {}
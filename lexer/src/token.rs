@@ -1,62 +1,73 @@
-#[derive(Debug, PartialEq, Eq)]
+use diagnostics::Span;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 /// The `Token` struct stores and represents a token of Kolang code.
-pub struct Token {
-    /// Line of code where this token starts.
-    pub line: usize,
-    /// Column of code where this token starts.
-    pub column: usize,
+/// `S` is the type of token text: owned `String` by default, or `&str`
+/// for tokens which borrow the source (see [`StrLexer`](crate::StrLexer)).
+pub struct Token<S = String> {
+    /// Source range of this token.
+    pub span: Span,
     /// Type of this token.
-    pub token_type: TokenType,
+    pub token_type: TokenType<S>,
 }
 
 impl Token {
-    /// Creates a new `Token` with provided type in specified position.
+    /// Creates a new `Token` with provided type in specified range.
     ///
     /// # Examples
     ///
     /// ```
+    /// use diagnostics::Span;
     /// use lexer::token::{Token, TokenType};
     ///
-    /// let tok = Token::new(1, 1, TokenType::KwFn);
+    /// let span = Span { start: 0, end: 2, line: 1, column: 1, end_line: 1, end_column: 3 };
+    /// let tok = Token::new(span, TokenType::KwFn);
     /// ```
-    pub fn new(line: usize, column: usize, token_type: TokenType) -> Self {
-        Self {
-            line,
-            column,
-            token_type,
-        }
+    pub fn new(span: Span, token_type: TokenType) -> Self {
+        Self { span, token_type }
+    }
+}
+
+impl Token<&str> {
+    /// Converts the borrowed token to a token which owns its text.
+    pub fn into_owned(self) -> Token {
+        Token::new(self.span, self.token_type.into_owned())
     }
 }
 
-impl std::fmt::Display for Token {
+impl<S: std::fmt::Display> std::fmt::Display for Token<S> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
             "{}, Ln: {}, Col: {}",
-            self.token_type, self.line, self.column
+            self.token_type, self.span.line, self.span.column
         )
     }
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 /// A set of Kolang token types. Some types also store the value of token as string.
-pub enum TokenType {
+pub enum TokenType<S = String> {
     /// Identifier: variable name, function name
-    Iden(String),
+    Iden(S),
     /// Decimal integer literal: `123`, `0`
-    LiteralIntDec(String),
+    LiteralIntDec(S),
     /// Binary integer literal: `0b1101`, `0B1`
-    LiteralIntBin(String),
+    LiteralIntBin(S),
     /// Octal integer literal: `0o7231`, `0O44`
-    LiteralIntOct(String),
+    LiteralIntOct(S),
     /// Hexadecimal integer literal: `0xff`, `0XA1`
-    LiteralIntHex(String),
+    LiteralIntHex(S),
     /// Character literal: `'a'`, `'\0'`
-    LiteralChar(String),
+    LiteralChar(S),
     /// Floating-point literal: `9.1`, `2e3`, `.05`
-    LiteralFloat(String),
+    LiteralFloat(S),
     /// String literal: `"Hello\tworld!"`
-    LiteralStr(String),
+    LiteralStr(S),
     /// Left parenthesis
     LPar,
     /// Right parenthesis
@@ -108,9 +119,11 @@ pub enum TokenType {
     /// Period                                 
     Period,
     /// `// Line comment`                          
-    LC(String),
+    LC(S),
     /// `/*Block comment*/` (not nested)             
-    BC(String),
+    BC(S),
+    /// Spaces, tabs and newlines (only returned by lexers which keep whitespace)
+    Whitespace(S),
     /// `for` keyword (loop)                   
     KwFor,
     /// `to` keyword (loop range)              
@@ -148,12 +161,167 @@ pub enum TokenType {
     /// `str` keyword (string type)            
     KwStr,
     /// Invalid (unmatched) token
-    Invalid(String),
+    Invalid(S),
     /// End of file
     EOF,
 }
 
-impl std::fmt::Display for TokenType {
+/// Words which [`TokenType::keyword`] recognizes, e.g. for completion
+/// in editors.
+///
+/// # Examples
+///
+/// ```
+/// use lexer::token::{TokenType, KEYWORDS};
+///
+/// assert!(KEYWORDS.iter().all(|k| TokenType::<String>::keyword(k).is_some()));
+/// ```
+pub const KEYWORDS: [&str; 18] = [
+    "for", "to", "while", "if", "else", "true", "false", "or", "and", "not", "let", "fn", "return",
+    "int", "char", "bool", "float", "str",
+];
+
+impl<S> TokenType<S> {
+    /// Returns the keyword token type of provided word,
+    /// or `None` if it is not a keyword.
+    pub fn keyword(word: &str) -> Option<Self> {
+        Some(match word {
+            "for" => TokenType::KwFor,
+            "to" => TokenType::KwTo,
+            "while" => TokenType::KwWhile,
+            "if" => TokenType::KwIf,
+            "else" => TokenType::KwElse,
+            "true" => TokenType::KwTrue,
+            "false" => TokenType::KwFalse,
+            "or" => TokenType::KwOr,
+            "and" => TokenType::KwAnd,
+            "not" => TokenType::KwNot,
+            "let" => TokenType::KwLet,
+            "fn" => TokenType::KwFn,
+            "return" => TokenType::KwReturn,
+            "int" => TokenType::KwInt,
+            "char" => TokenType::KwChar,
+            "bool" => TokenType::KwBool,
+            "float" => TokenType::KwFloat,
+            "str" => TokenType::KwStr,
+            _ => return None,
+        })
+    }
+
+    /// Checks whether the token is trivia (whitespace or a comment), which
+    /// does not affect the meaning of the code.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use lexer::token::TokenType;
+    ///
+    /// assert!(TokenType::Whitespace(" ").is_trivia());
+    /// assert!(TokenType::LC("// comment").is_trivia());
+    /// assert!(!TokenType::Iden("x").is_trivia());
+    /// ```
+    pub fn is_trivia(&self) -> bool {
+        matches!(
+            self,
+            TokenType::Whitespace(_) | TokenType::LC(_) | TokenType::BC(_)
+        )
+    }
+
+    /// Converts the text stored in this token type (if any) using `f`.
+    pub fn map<T>(self, f: impl FnOnce(S) -> T) -> TokenType<T> {
+        match self {
+            TokenType::Iden(s) => TokenType::Iden(f(s)),
+            TokenType::LiteralIntDec(s) => TokenType::LiteralIntDec(f(s)),
+            TokenType::LiteralIntBin(s) => TokenType::LiteralIntBin(f(s)),
+            TokenType::LiteralIntOct(s) => TokenType::LiteralIntOct(f(s)),
+            TokenType::LiteralIntHex(s) => TokenType::LiteralIntHex(f(s)),
+            TokenType::LiteralChar(s) => TokenType::LiteralChar(f(s)),
+            TokenType::LiteralFloat(s) => TokenType::LiteralFloat(f(s)),
+            TokenType::LiteralStr(s) => TokenType::LiteralStr(f(s)),
+            TokenType::LC(s) => TokenType::LC(f(s)),
+            TokenType::BC(s) => TokenType::BC(f(s)),
+            TokenType::Whitespace(s) => TokenType::Whitespace(f(s)),
+            TokenType::Invalid(s) => TokenType::Invalid(f(s)),
+            TokenType::LPar => TokenType::LPar,
+            TokenType::RPar => TokenType::RPar,
+            TokenType::LBracket => TokenType::LBracket,
+            TokenType::RBracket => TokenType::RBracket,
+            TokenType::LBrace => TokenType::LBrace,
+            TokenType::RBrace => TokenType::RBrace,
+            TokenType::LT => TokenType::LT,
+            TokenType::GT => TokenType::GT,
+            TokenType::LEq => TokenType::LEq,
+            TokenType::GEq => TokenType::GEq,
+            TokenType::Eq => TokenType::Eq,
+            TokenType::NEq => TokenType::NEq,
+            TokenType::Assign => TokenType::Assign,
+            TokenType::Plus => TokenType::Plus,
+            TokenType::Minus => TokenType::Minus,
+            TokenType::Asterisk => TokenType::Asterisk,
+            TokenType::Slash => TokenType::Slash,
+            TokenType::Percent => TokenType::Percent,
+            TokenType::Pipe => TokenType::Pipe,
+            TokenType::Amp => TokenType::Amp,
+            TokenType::Tilde => TokenType::Tilde,
+            TokenType::Semicolon => TokenType::Semicolon,
+            TokenType::Colon => TokenType::Colon,
+            TokenType::Comma => TokenType::Comma,
+            TokenType::Period => TokenType::Period,
+            TokenType::KwFor => TokenType::KwFor,
+            TokenType::KwTo => TokenType::KwTo,
+            TokenType::KwWhile => TokenType::KwWhile,
+            TokenType::KwIf => TokenType::KwIf,
+            TokenType::KwElse => TokenType::KwElse,
+            TokenType::KwTrue => TokenType::KwTrue,
+            TokenType::KwFalse => TokenType::KwFalse,
+            TokenType::KwOr => TokenType::KwOr,
+            TokenType::KwAnd => TokenType::KwAnd,
+            TokenType::KwNot => TokenType::KwNot,
+            TokenType::KwLet => TokenType::KwLet,
+            TokenType::KwFn => TokenType::KwFn,
+            TokenType::KwReturn => TokenType::KwReturn,
+            TokenType::KwInt => TokenType::KwInt,
+            TokenType::KwChar => TokenType::KwChar,
+            TokenType::KwBool => TokenType::KwBool,
+            TokenType::KwFloat => TokenType::KwFloat,
+            TokenType::KwStr => TokenType::KwStr,
+            TokenType::EOF => TokenType::EOF,
+        }
+    }
+}
+
+impl TokenType<&str> {
+    /// Converts the borrowed token type to a token type which owns its text.
+    pub fn into_owned(self) -> TokenType {
+        self.map(str::to_string)
+    }
+}
+
+impl<S: AsRef<str>> TokenType<S> {
+    /// Checks whether the token is a string literal or a block comment which
+    /// the source code ends before closing, so more code may complete it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use lexer::token::TokenType;
+    ///
+    /// assert!(TokenType::Invalid("\"open\\\"").is_unterminated());
+    /// assert!(TokenType::BC("/* open */ *").is_unterminated());
+    /// assert!(TokenType::BC("/*/").is_unterminated());
+    /// assert!(!TokenType::BC("/**/").is_unterminated());
+    /// assert!(!TokenType::LiteralStr("\"closed\"").is_unterminated());
+    /// ```
+    pub fn is_unterminated(&self) -> bool {
+        match self {
+            TokenType::Invalid(s) => s.as_ref().starts_with('"'),
+            TokenType::BC(s) => s.as_ref().len() < 4 || !s.as_ref().ends_with("*/"),
+            _ => false,
+        }
+    }
+}
+
+impl<S: std::fmt::Display> std::fmt::Display for TokenType<S> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             TokenType::Iden(id) => write!(f, "{id}"),
@@ -190,6 +358,7 @@ impl std::fmt::Display for TokenType {
             TokenType::Comma => f.write_str(","),
             TokenType::Period => f.write_str("."),
             TokenType::LC(_) | TokenType::BC(_) => f.write_str("comment"),
+            TokenType::Whitespace(_) => f.write_str("whitespace"),
             TokenType::KwFor => f.write_str("for"),
             TokenType::KwTo => f.write_str("to"),
             TokenType::KwWhile => f.write_str("while"),
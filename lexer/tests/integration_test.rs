@@ -30,169 +30,386 @@ const SOUCE_CODE: &str = "fn main() {
     return;
 }";
 
-use lexer::token::Token as TK;
 use lexer::token::TokenType::*;
-use lexer::Lexer;
+use lexer::token::{Token, TokenType};
+use lexer::{ColumnUnit, LexError, Lexer, StrLexer, TokenStream};
+
+fn pos(tok: Token) -> (usize, usize, TokenType) {
+    (tok.span.line, tok.span.column, tok.token_type)
+}
 
 #[test]
-fn lexer_test() -> std::io::Result<()> {
+fn lexer_test() -> Result<(), LexError> {
     let stream = SOUCE_CODE.as_bytes();
     let mut l = Lexer::new(stream);
 
-    assert_eq!(l.next()?, TK::new(1, 1, KwFn));
-    assert_eq!(l.next()?, TK::new(1, 4, Iden("main".into())));
-    assert_eq!(l.next()?, TK::new(1, 8, LPar));
-    assert_eq!(l.next()?, TK::new(1, 9, RPar));
-    assert_eq!(l.next()?, TK::new(1, 11, LBrace));
-
-    assert_eq!(l.next()?, TK::new(2, 5, KwLet));
-    assert_eq!(l.next()?, TK::new(2, 9, Iden("a".into())));
-    assert_eq!(l.next()?, TK::new(2, 10, Colon));
-    assert_eq!(l.next()?, TK::new(2, 12, KwInt));
-    assert_eq!(l.next()?, TK::new(2, 16, Assign));
-    assert_eq!(l.next()?, TK::new(2, 18, Minus));
-    assert_eq!(l.next()?, TK::new(2, 19, LiteralIntDec("25".into())));
-    assert_eq!(l.next()?, TK::new(2, 21, Semicolon));
-
-    l.next()?; // let
-    l.next()?; // b
-    l.next()?; // =
-    assert_eq!(l.next()?, TK::new(3, 13, LiteralFloat("3.1e-1".into())));
-    l.next()?; // ;
-
-    l.next()?; // let
-    l.next()?; // c
-    l.next()?; // :
-    assert_eq!(l.next()?, TK::new(4, 12, KwFloat));
-    l.next()?; // ;
-
-    assert_eq!(l.next()?, TK::new(5, 5, Iden("c".into())));
-    assert_eq!(l.next()?, TK::new(5, 7, Assign));
-    assert_eq!(l.next()?, TK::new(5, 9, Iden("a".into())));
-    assert_eq!(l.next()?, TK::new(5, 11, Plus));
-    assert_eq!(l.next()?, TK::new(5, 13, Iden("b".into())));
-    assert_eq!(l.next()?, TK::new(5, 15, Asterisk));
-    assert_eq!(l.next()?, TK::new(5, 17, Iden("b".into())));
-    assert_eq!(l.next()?, TK::new(5, 18, Slash));
-    assert_eq!(l.next()?, TK::new(5, 20, Iden("a".into())));
-    assert_eq!(l.next()?, TK::new(5, 21, Percent));
-    assert_eq!(l.next()?, TK::new(5, 22, Iden("b".into())));
-    assert_eq!(l.next()?, TK::new(5, 23, Semicolon));
-
-    l.next()?; // let
-    l.next()?; // d
-    l.next()?; // =
-    assert_eq!(l.next()?, TK::new(7, 13, LBracket));
-    l.next()?; // 1
-    assert_eq!(l.next()?, TK::new(7, 15, Comma));
-    l.next()?; // 2
-    l.next()?; // ,
-    l.next()?; // 3
-    assert_eq!(l.next()?, TK::new(7, 19, RBracket));
-    l.next()?; // ;
+    assert_eq!(pos(l.next_token()?), (1, 1, KwFn));
+    assert_eq!(pos(l.next_token()?), (1, 4, Iden("main".into())));
+    assert_eq!(pos(l.next_token()?), (1, 8, LPar));
+    assert_eq!(pos(l.next_token()?), (1, 9, RPar));
+    assert_eq!(pos(l.next_token()?), (1, 11, LBrace));
+
+    assert_eq!(pos(l.next_token()?), (2, 5, KwLet));
+    assert_eq!(pos(l.next_token()?), (2, 9, Iden("a".into())));
+    assert_eq!(pos(l.next_token()?), (2, 10, Colon));
+    assert_eq!(pos(l.next_token()?), (2, 12, KwInt));
+    assert_eq!(pos(l.next_token()?), (2, 16, Assign));
+    assert_eq!(pos(l.next_token()?), (2, 18, Minus));
+    assert_eq!(pos(l.next_token()?), (2, 19, LiteralIntDec("25".into())));
+    assert_eq!(pos(l.next_token()?), (2, 21, Semicolon));
+
+    l.next_token()?; // let
+    l.next_token()?; // b
+    l.next_token()?; // =
+    assert_eq!(pos(l.next_token()?), (3, 13, LiteralFloat("3.1e-1".into())));
+    l.next_token()?; // ;
+
+    l.next_token()?; // let
+    l.next_token()?; // c
+    l.next_token()?; // :
+    assert_eq!(pos(l.next_token()?), (4, 12, KwFloat));
+    l.next_token()?; // ;
+
+    assert_eq!(pos(l.next_token()?), (5, 5, Iden("c".into())));
+    assert_eq!(pos(l.next_token()?), (5, 7, Assign));
+    assert_eq!(pos(l.next_token()?), (5, 9, Iden("a".into())));
+    assert_eq!(pos(l.next_token()?), (5, 11, Plus));
+    assert_eq!(pos(l.next_token()?), (5, 13, Iden("b".into())));
+    assert_eq!(pos(l.next_token()?), (5, 15, Asterisk));
+    assert_eq!(pos(l.next_token()?), (5, 17, Iden("b".into())));
+    assert_eq!(pos(l.next_token()?), (5, 18, Slash));
+    assert_eq!(pos(l.next_token()?), (5, 20, Iden("a".into())));
+    assert_eq!(pos(l.next_token()?), (5, 21, Percent));
+    assert_eq!(pos(l.next_token()?), (5, 22, Iden("b".into())));
+    assert_eq!(pos(l.next_token()?), (5, 23, Semicolon));
+
+    l.next_token()?; // let
+    l.next_token()?; // d
+    l.next_token()?; // =
+    assert_eq!(pos(l.next_token()?), (7, 13, LBracket));
+    l.next_token()?; // 1
+    assert_eq!(pos(l.next_token()?), (7, 15, Comma));
+    l.next_token()?; // 2
+    l.next_token()?; // ,
+    l.next_token()?; // 3
+    assert_eq!(pos(l.next_token()?), (7, 19, RBracket));
+    l.next_token()?; // ;
     
-    l.next()?; // let
-    l.next()?; // cond
-    l.next()?; // :
-    assert_eq!(l.next()?, TK::new(9, 15, KwBool));
-    l.next()?; // ;
+    l.next_token()?; // let
+    l.next_token()?; // cond
+    l.next_token()?; // :
+    assert_eq!(pos(l.next_token()?), (9, 15, KwBool));
+    l.next_token()?; // ;
     
-    l.next()?; // cond
-    l.next()?; // =
-    assert_eq!(l.next()?, TK::new(10, 12, LiteralIntHex("0x1fA".into())));
-    assert_eq!(l.next()?, TK::new(10, 18, Pipe));
-    assert_eq!(l.next()?, TK::new(10, 20, LiteralIntHex("0XAA".into())));
-    l.next()?; // +
-    assert_eq!(l.next()?, TK::new(10, 27, Tilde));
-    assert_eq!(l.next()?, TK::new(10, 28, LiteralIntBin("0B1001".into())));
-    assert_eq!(l.next()?, TK::new(10, 35, Amp));
-    assert_eq!(l.next()?, TK::new(10, 37, LiteralIntBin("0b1011".into())));
-    l.next()?; // +
-    l.next()?; // a
-    assert_eq!(l.next()?, TK::new(10, 48, LT));
-    l.next()?; // b
-    l.next()?; // ;
+    l.next_token()?; // cond
+    l.next_token()?; // =
+    assert_eq!(pos(l.next_token()?), (10, 12, LiteralIntHex("0x1fA".into())));
+    assert_eq!(pos(l.next_token()?), (10, 18, Pipe));
+    assert_eq!(pos(l.next_token()?), (10, 20, LiteralIntHex("0XAA".into())));
+    l.next_token()?; // +
+    assert_eq!(pos(l.next_token()?), (10, 27, Tilde));
+    assert_eq!(pos(l.next_token()?), (10, 28, LiteralIntBin("0B1001".into())));
+    assert_eq!(pos(l.next_token()?), (10, 35, Amp));
+    assert_eq!(pos(l.next_token()?), (10, 37, LiteralIntBin("0b1011".into())));
+    l.next_token()?; // +
+    l.next_token()?; // a
+    assert_eq!(pos(l.next_token()?), (10, 48, LT));
+    l.next_token()?; // b
+    l.next_token()?; // ;
     
-    assert_eq!(l.next()?, TK::new(11, 5, KwIf));
-    l.next()?; // cond
-    assert_eq!(l.next()?, TK::new(11, 13, Eq));
-    assert_eq!(l.next()?, TK::new(11, 16, KwTrue));
-    assert_eq!(l.next()?, TK::new(11, 21, KwOr));
-    assert_eq!(l.next()?, TK::new(11, 24, LiteralIntOct("0o5".into())));
-    assert_eq!(l.next()?, TK::new(11, 28, GEq));
-    assert_eq!(l.next()?, TK::new(11, 31, LiteralIntOct("0O5".into())));
-    l.next()?; // {
+    assert_eq!(pos(l.next_token()?), (11, 5, KwIf));
+    l.next_token()?; // cond
+    assert_eq!(pos(l.next_token()?), (11, 13, Eq));
+    assert_eq!(pos(l.next_token()?), (11, 16, KwTrue));
+    assert_eq!(pos(l.next_token()?), (11, 21, KwOr));
+    assert_eq!(pos(l.next_token()?), (11, 24, LiteralIntOct("0o5".into())));
+    assert_eq!(pos(l.next_token()?), (11, 28, GEq));
+    assert_eq!(pos(l.next_token()?), (11, 31, LiteralIntOct("0O5".into())));
+    l.next_token()?; // {
     
-    assert_eq!(l.next()?, TK::new(12, 2, Iden("print".into())));
-    l.next()?; // (
-    assert_eq!(l.next()?, TK::new(12, 8, LiteralChar("'t'".into())));
-    l.next()?; // )
-    l.next()?; // ;
+    assert_eq!(pos(l.next_token()?), (12, 2, Iden("print".into())));
+    l.next_token()?; // (
+    assert_eq!(pos(l.next_token()?), (12, 8, LiteralChar("'t'".into())));
+    l.next_token()?; // )
+    l.next_token()?; // ;
     
-    assert_eq!(l.next()?, TK::new(13, 5, RBrace));
-    assert_eq!(l.next()?, TK::new(13, 7, KwElse));
-    l.next()?; // {
-    l.next()?; // print
-    l.next()?; // (
-    assert_eq!(l.next()?, TK::new(13, 18, LiteralStr("\"hello!\\nworld!\"".into())));
-    l.next()?; // )
-    l.next()?; // }
+    assert_eq!(pos(l.next_token()?), (13, 5, RBrace));
+    assert_eq!(pos(l.next_token()?), (13, 7, KwElse));
+    l.next_token()?; // {
+    l.next_token()?; // print
+    l.next_token()?; // (
+    assert_eq!(pos(l.next_token()?), (13, 18, LiteralStr("\"hello!\\nworld!\"".into())));
+    l.next_token()?; // )
+    l.next_token()?; // }
     
-    assert_eq!(l.next()?, TK::new(15, 5, LC("// Comment".into())));
-
-    assert_eq!(l.next()?, TK::new(16, 5, KwWhile));
-    assert_eq!(l.next()?, TK::new(16, 11, KwFalse));
-    assert_eq!(l.next()?, TK::new(16, 17, NEq));
-    l.next()?; // true
-    l.next()?; // or
-    l.next()?; // 3
-    assert_eq!(l.next()?, TK::new(16, 30, GT));
-    l.next()?; // 4
-    assert_eq!(l.next()?, TK::new(16, 33, KwAnd));
-    assert_eq!(l.next()?, TK::new(16, 37, KwNot));
-    l.next()?; // 5
-    assert_eq!(l.next()?, TK::new(16, 43, LEq));
-    l.next()?; // 5.0
-    l.next()?; // {
-
-    l.next()?; // let
-    l.next()?; // s
-    l.next()?; // :
-    assert_eq!(l.next()?, TK::new(17, 15, KwStr));
-    l.next()?; // =
-    assert_eq!(l.next()?, TK::new(17, 21, LiteralStr("\"multiline\n        string\"".into())));
-    l.next()?; // ;
-
-    l.next()?; // let
-    l.next()?; // ch
-    l.next()?; // =
-    assert_eq!(l.next()?, TK::new(19, 16, LiteralChar("'\\0'".into())));
-    l.next()?; // ;
+    assert_eq!(pos(l.next_token()?), (15, 5, LC("// Comment".into())));
+
+    assert_eq!(pos(l.next_token()?), (16, 5, KwWhile));
+    assert_eq!(pos(l.next_token()?), (16, 11, KwFalse));
+    assert_eq!(pos(l.next_token()?), (16, 17, NEq));
+    l.next_token()?; // true
+    l.next_token()?; // or
+    l.next_token()?; // 3
+    assert_eq!(pos(l.next_token()?), (16, 30, GT));
+    l.next_token()?; // 4
+    assert_eq!(pos(l.next_token()?), (16, 33, KwAnd));
+    assert_eq!(pos(l.next_token()?), (16, 37, KwNot));
+    l.next_token()?; // 5
+    assert_eq!(pos(l.next_token()?), (16, 43, LEq));
+    l.next_token()?; // 5.0
+    l.next_token()?; // {
+
+    l.next_token()?; // let
+    l.next_token()?; // s
+    l.next_token()?; // :
+    assert_eq!(pos(l.next_token()?), (17, 15, KwStr));
+    l.next_token()?; // =
+    assert_eq!(pos(l.next_token()?), (17, 21, LiteralStr("\"multiline\n        string\"".into())));
+    l.next_token()?; // ;
+
+    l.next_token()?; // let
+    l.next_token()?; // ch
+    l.next_token()?; // =
+    assert_eq!(pos(l.next_token()?), (19, 16, LiteralChar("'\\0'".into())));
+    l.next_token()?; // ;
     
-    l.next()?; // }
+    l.next_token()?; // }
     
-    assert_eq!(l.next()?, TK::new(22, 5, BC("/*\n     * stylish\n     * multiline \n     * comment\n     */".into())));
+    assert_eq!(pos(l.next_token()?), (22, 5, BC("/*\n     * stylish\n     * multiline \n     * comment\n     */".into())));
     
-    assert_eq!(l.next()?, TK::new(27, 5, KwFor));
-    assert_eq!(l.next()?, TK::new(27, 9, Iden("i".into())));
-    assert_eq!(l.next()?, TK::new(27, 10, Assign));
-    assert_eq!(l.next()?, TK::new(27, 11, LiteralIntDec("0".into())));
-    assert_eq!(l.next()?, TK::new(27, 12, KwTo));
-    assert_eq!(l.next()?, TK::new(27, 15, LiteralIntDec("50".into())));
+    assert_eq!(pos(l.next_token()?), (27, 5, KwFor));
+    assert_eq!(pos(l.next_token()?), (27, 9, Iden("i".into())));
+    assert_eq!(pos(l.next_token()?), (27, 10, Assign));
+    assert_eq!(pos(l.next_token()?), (27, 11, LiteralIntDec("0".into())));
+    assert_eq!(pos(l.next_token()?), (27, 12, KwTo));
+    assert_eq!(pos(l.next_token()?), (27, 15, LiteralIntDec("50".into())));
     
-    l.next()?; // i
-    assert_eq!(l.next()?, TK::new(28, 10, Period));
-    l.next()?; // something
-    l.next()?; // (
-    l.next()?; // )
-    l.next()?; // ;
+    l.next_token()?; // i
+    assert_eq!(pos(l.next_token()?), (28, 10, Period));
+    l.next_token()?; // something
+    l.next_token()?; // (
+    l.next_token()?; // )
+    l.next_token()?; // ;
+
+    assert_eq!(pos(l.next_token()?), (30, 5, KwReturn));
+    l.next_token()?; // ;
+
+    l.next_token()?; // }
+
+    assert_eq!(pos(l.next_token()?), (31, 2, EOF));
+
+    Ok(())
+}
+
+#[test]
+fn unicode_test() -> Result<(), LexError> {
+    let source = "// توضیح\nlet s: str = \"سلام\"; let c: char = 'é';";
+    let mut l = Lexer::new(source.as_bytes());
+
+    assert_eq!(pos(l.next_token()?), (1, 1, LC("// توضیح".into())));
+    l.next_token()?; // let
+    l.next_token()?; // s
+    l.next_token()?; // :
+    l.next_token()?; // str
+    l.next_token()?; // =
+    assert_eq!(pos(l.next_token()?), (2, 14, LiteralStr("\"سلام\"".into())));
+    assert_eq!(pos(l.next_token()?), (2, 20, Semicolon));
+    l.next_token()?; // let
+    l.next_token()?; // c
+    l.next_token()?; // :
+    l.next_token()?; // char
+    l.next_token()?; // =
+    assert_eq!(pos(l.next_token()?), (2, 36, LiteralChar("'é'".into())));
+    assert_eq!(pos(l.next_token()?), (2, 39, Semicolon));
+
+    Ok(())
+}
+
+#[test]
+fn span_test() -> Result<(), LexError> {
+    let source = "let s: str = \"سلام\";\n/* é */ 0x1F\n";
+    let mut l = Lexer::new(source.as_bytes());
+
+    let spans: Vec<_> = std::iter::from_fn(|| match l.next_token() {
+        Ok(tok) if tok.token_type == EOF => None,
+        tok => Some(tok.map(|t| {
+            let s = t.span;
+            (s.start, s.end, s.line, s.column, s.end_line, s.end_column)
+        })),
+    })
+    .collect::<Result<_, _>>()?;
+
+    assert_eq!(
+        spans,
+        vec![
+            (0, 3, 1, 1, 1, 4),     // let
+            (4, 5, 1, 5, 1, 6),     // s
+            (5, 6, 1, 6, 1, 7),     // :
+            (7, 10, 1, 8, 1, 11),   // str
+            (11, 12, 1, 12, 1, 13), // =
+            (13, 23, 1, 14, 1, 20), // "سلام"
+            (23, 24, 1, 20, 1, 21), // ;
+            (25, 33, 2, 1, 2, 8),   // /* é */
+            (34, 38, 2, 9, 2, 13),  // 0x1F
+        ]
+    );
+
+    let eof = l.next_token()?.span;
+    assert_eq!((eof.start, eof.end, eof.line, eof.column), (39, 39, 3, 1));
+
+    Ok(())
+}
+
+#[test]
+fn iterator_test() -> Result<(), LexError> {
+    let mut l = Lexer::new("let a;".as_bytes());
+    let types = l
+        .by_ref()
+        .map(|t| t.map(|t| t.token_type))
+        .collect::<Result<Vec<_>, _>>()?;
 
-    assert_eq!(l.next()?, TK::new(30, 5, KwReturn));
-    l.next()?; // ;
+    assert_eq!(types, vec![KwLet, Iden("a".into()), Semicolon, EOF]);
+    assert!(l.next().is_none());
+    // The inherent method keeps returning `EOF`.
+    assert_eq!(l.next_token()?.token_type, EOF);
 
-    l.next()?; // }
+    // Invalid UTF-8 does not end the iterator.
+    let items: Vec<_> = Lexer::new(&b"a \xFF b"[..]).collect();
+    assert_eq!(items.len(), 4);
+    assert!(matches!(items[1], Err(LexError::InvalidUtf8 { .. })));
+
+    Ok(())
+}
+
+#[test]
+fn token_stream_test() -> Result<(), LexError> {
+    let source = "/* f */ fn f(x: int) // end";
+    let mut s = TokenStream::new(Lexer::new(source.as_bytes()));
+
+    let peeked = |s: &mut TokenStream<&[u8]>, n| match s.peek_nth(n) {
+        Some(Ok(tok)) => Some(tok.token_type.clone()),
+        Some(Err(e)) => panic!("{}", e),
+        None => None,
+    };
+
+    assert_eq!(peeked(&mut s, 0), Some(BC("/* f */".into())));
+    assert_eq!(peeked(&mut s, 3), Some(LPar));
+    assert_eq!(peeked(&mut s, 9), Some(EOF));
+    assert_eq!(peeked(&mut s, 10), None);
+    assert_eq!(s.next().unwrap()?.token_type, BC("/* f */".into()));
+    assert_eq!(peeked(&mut s, 0), Some(KwFn));
+    assert_eq!(s.count(), 9);
+
+    let mut s = TokenStream::new(Lexer::new(source.as_bytes())).with_comments(false);
+    assert_eq!(peeked(&mut s, 0), Some(KwFn));
+    assert_eq!(peeked(&mut s, 7), Some(EOF));
+    assert_eq!(peeked(&mut s, 8), None);
+    assert!(s.peek().is_some());
+
+    Ok(())
+}
+
+#[test]
+fn str_lexer_test() -> Result<(), LexError> {
+    let sources = [
+        SOUCE_CODE,
+        "0.5 0e3 0x1F 0b12 0o78 1.e2 1E5 .5e-3 0.",
+        "'a' '\\n' '' 'ab' ' \"abc\\\"d\" \"unterminated",
+        "/* unterminated block * / comment",
+        "a\0b // line comment\n! != = == < <= > >= $ # é",
+        "\"😀\"\u{2003}ä\tfn\r\n",
+        "'",
+        "\"",
+        "\"\\",
+        "'\\",
+    ];
+
+    for source in sources {
+        for unit in [ColumnUnit::Char, ColumnUnit::Utf16] {
+            let owned = Lexer::with_column_unit(source.as_bytes(), unit).collect::<Result<Vec<_>, _>>()?;
+            let borrowed: Vec<_> = StrLexer::with_column_unit(source, unit)
+                .map(|t| t.into_owned())
+                .collect();
+            assert_eq!(owned, borrowed, "{:?}", source);
+
+            // Token text is a slice of the source.
+            for tok in StrLexer::new(source) {
+                if let Iden(s) | LiteralStr(s) | LC(s) | BC(s) | Invalid(s) = tok.token_type {
+                    assert_eq!(s, &source[tok.span.start..tok.span.end]);
+                }
+            }
+        }
+    }
+
+    let mut l = StrLexer::new("0.5");
+    assert_eq!(pos(l.next_token().into_owned()), (1, 1, LiteralFloat("0.5".into())));
+    assert_eq!(l.next_token().token_type, EOF);
+    assert_eq!(l.next_token().token_type, EOF);
+
+    Ok(())
+}
+
+#[test]
+fn whitespace_test() -> Result<(), LexError> {
+    let sources = [
+        SOUCE_CODE,
+        "  fn\tmain ( ) {\r\n}\n\n",
+        "a /* block */ b // line\n  ",
+        "\"😀\"\u{2003}ä\tfn\r\n",
+        "' '\"unterminated  ",
+    ];
+
+    for source in sources {
+        let owned = Lexer::new(source.as_bytes())
+            .with_whitespace(true)
+            .collect::<Result<Vec<_>, _>>()?;
+        let borrowed: Vec<_> = StrLexer::new(source)
+            .with_whitespace(true)
+            .map(|t| t.into_owned())
+            .collect();
+        assert_eq!(owned, borrowed, "{:?}", source);
+
+        // Tokens cover the whole source without gaps.
+        let mut end = 0;
+        for tok in borrowed.iter().filter(|t| t.token_type != EOF) {
+            assert_eq!(tok.span.start, end, "{:?}", source);
+            end = tok.span.end;
+        }
+        assert_eq!(end, source.len());
+
+        // Without whitespace, the lexer returns the remaining tokens.
+        let filtered: Vec<_> = borrowed
+            .into_iter()
+            .filter(|t| !matches!(t.token_type, Whitespace(_)))
+            .collect();
+        assert_eq!(
+            filtered,
+            Lexer::new(source.as_bytes()).collect::<Result<Vec<_>, _>>()?
+        );
+    }
+
+    let mut s =
+        TokenStream::new(Lexer::new(" a ".as_bytes()).with_whitespace(true)).with_comments(false);
+    assert_eq!(s.next().unwrap()?.token_type, Iden("a".into()));
+    assert_eq!(s.next().unwrap()?.token_type, EOF);
+
+    Ok(())
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serde_test() -> Result<(), LexError> {
+    let tokens = Lexer::new(SOUCE_CODE.as_bytes()).collect::<Result<Vec<_>, _>>()?;
+    let json = serde_json::to_string(&tokens).unwrap();
+    assert!(json.starts_with(
+        r#"[{"span":{"start":0,"end":2,"line":1,"column":1,"end_line":1,"end_column":3},"token_type":"KwFn"},"#
+    ));
+    assert!(json.contains(r#""token_type":{"Iden":"main"}"#));
+    assert_eq!(serde_json::from_str::<Vec<Token>>(&json).unwrap(), tokens);
 
-    assert_eq!(l.next()?, TK::new(31, 1, EOF));
+    // Borrowed tokens serialize the same way.
+    let borrowed: Vec<_> = StrLexer::new(SOUCE_CODE).collect();
+    assert_eq!(serde_json::to_string(&borrowed).unwrap(), json);
 
     Ok(())
 }
@@ -0,0 +1,318 @@
+use diagnostics::{Diagnostic, Severity, Span};
+use lexer::{
+    token::{TokenType, KEYWORDS},
+    ColumnUnit, Lexer,
+};
+use parser::{ast, Parser};
+use semantic::resolver::{self, ScopeTree, SymbolId, SymbolKind, BUILTINS};
+
+/// Position in a document: zero-based line, and zero-based column in
+/// UTF-16 code units, like in the Language Server Protocol.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Position {
+    /// Line of the position, from `0`.
+    pub line: usize,
+    /// Column of the position in UTF-16 code units, from `0`.
+    pub character: usize,
+}
+
+/// A function defined in a document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FunctionSymbol {
+    /// Name of the function.
+    pub name: String,
+    /// Signature of the function, e.g. `fn add(a: int, b: int): int`.
+    pub signature: String,
+    /// Source range of the whole definition.
+    pub span: Span,
+    /// Source range of the name.
+    pub name_span: Span,
+}
+
+/// Kind of a completion item.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompletionKind {
+    /// Keyword of the language.
+    Keyword,
+    /// Defined or builtin function.
+    Function,
+    /// Variable, parameter or loop variable.
+    Variable,
+}
+
+/// A name which may be written at a position.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Completion {
+    /// The name.
+    pub label: String,
+    /// Kind of the name.
+    pub kind: CompletionKind,
+    /// Type of a variable, or signature of a function.
+    pub detail: Option<String>,
+}
+
+/// The `Document` struct stores Kolang code which is open in an editor,
+/// and the results of analyzing it. Source ranges count columns in UTF-16
+/// code units.
+///
+/// # Examples
+///
+/// ```
+/// use lsp::document::{Document, Position};
+///
+/// let doc = Document::new("fn f(): int { let x: int = 1; return x; }".to_string());
+/// assert!(doc.diagnostics().is_empty());
+///
+/// let offset = doc.offset(Position { line: 0, character: 37 });
+/// let (hover, _) = doc.hover(offset).unwrap();
+/// assert_eq!(hover, "```kolang\nx: int\n```\nvariable");
+/// assert_eq!(doc.definition(offset).unwrap().column, 19);
+/// ```
+pub struct Document {
+    /// Source code.
+    text: String,
+    /// Byte offsets of the starts of lines.
+    line_starts: Vec<usize>,
+    /// Syntax tree, which may be partial if the code has syntax errors.
+    ast: Vec<ast::Stmt>,
+    /// Scopes and symbols of the code.
+    tree: ScopeTree,
+    /// Errors and warnings of the code.
+    diagnostics: Vec<Diagnostic>,
+    /// Source ranges of the names of declared symbols.
+    declarations: Vec<(Span, SymbolId)>,
+}
+
+impl Document {
+    /// Analyzes code. Diagnostics are collected like the `check` command
+    /// does: the resolver only reports errors if there are no syntax errors,
+    /// and the type checker only if there are no other errors.
+    pub fn new(text: String) -> Self {
+        let lexer = || Lexer::with_column_unit(text.as_bytes(), ColumnUnit::Utf16);
+        let (ast, mut diagnostics) = Parser::new(lexer())
+            .parse()
+            .expect("Reading code from memory cannot fail");
+        let (tree, resolver_diagnostics) = resolver::resolve(&ast);
+        if !has_errors(&diagnostics) {
+            diagnostics.extend(resolver_diagnostics);
+            if !has_errors(&diagnostics) {
+                diagnostics.extend(semantic::type_checker::check(&ast));
+            }
+        }
+
+        let identifiers: Vec<(String, Span)> = lexer()
+            .flatten()
+            .filter_map(|tok| match tok.token_type {
+                TokenType::Iden(id) => Some((id, tok.span)),
+                _ => None,
+            })
+            .collect();
+        let declarations = tree
+            .symbols()
+            .filter_map(|(id, symbol)| {
+                let mut names = identifiers
+                    .iter()
+                    .filter(|(_, span)| {
+                        symbol.span.start <= span.start && span.end <= symbol.span.end
+                    })
+                    // The name of the function comes before its parameters.
+                    .skip((symbol.kind == SymbolKind::Parameter) as usize);
+                let (_, span) = names.find(|(name, _)| *name == symbol.identifier)?;
+                Some((*span, id))
+            })
+            .collect();
+
+        let line_starts = std::iter::once(0)
+            .chain(text.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        Self {
+            text,
+            line_starts,
+            ast,
+            tree,
+            diagnostics,
+            declarations,
+        }
+    }
+
+    /// Returns the errors and warnings of the code.
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
+    /// Returns the byte offset of a position. Positions after the end of a
+    /// line (or of the code) are moved to its end.
+    pub fn offset(&self, position: Position) -> usize {
+        let Some(&start) = self.line_starts.get(position.line) else {
+            return self.text.len();
+        };
+        let mut offset = start;
+        let mut character = 0;
+        for c in self.text[start..].chars() {
+            if c == '\n' || character >= position.character {
+                break;
+            }
+            character += c.len_utf16();
+            offset += c.len_utf8();
+        }
+        offset
+    }
+
+    /// Returns the hover text of the identifier at a byte offset: its type
+    /// (or signature) as Markdown, and the source range of the identifier.
+    pub fn hover(&self, offset: usize) -> Option<(String, Span)> {
+        let (span, id) = self.symbol_at(offset)?;
+        let symbol = self.tree.symbol(id);
+        let (code, kind) = match symbol.kind {
+            SymbolKind::Function => (self.signature(&symbol.identifier)?, "function"),
+            kind => {
+                let t = symbol.symbol_type.as_ref()?;
+                let code = format!("{}: {}", symbol.identifier, t);
+                match kind {
+                    SymbolKind::Parameter => (code, "parameter"),
+                    SymbolKind::LoopVariable => (code, "loop variable"),
+                    _ => (code, "variable"),
+                }
+            }
+        };
+        Some((format!("```kolang\n{}\n```\n{}", code, kind), span))
+    }
+
+    /// Returns the source range of the name in the declaration of the symbol
+    /// which the identifier at a byte offset refers to.
+    pub fn definition(&self, offset: usize) -> Option<Span> {
+        let (_, id) = self.symbol_at(offset)?;
+        Some(self.declaration(id))
+    }
+
+    /// Returns the source ranges of the identifiers which refer to the same
+    /// symbol as the identifier at a byte offset, in order of appearance.
+    /// The declaration comes first if `include_declaration` is set.
+    pub fn references(&self, offset: usize, include_declaration: bool) -> Vec<Span> {
+        let Some((_, id)) = self.symbol_at(offset) else {
+            return Vec::new();
+        };
+        let mut refs = self.tree.references(id);
+        if include_declaration {
+            refs.insert(0, self.declaration(id));
+        }
+        refs
+    }
+
+    /// Returns the functions defined in the code, in order.
+    pub fn functions(&self) -> Vec<FunctionSymbol> {
+        self.ast
+            .iter()
+            .filter_map(|stmt| match stmt {
+                ast::Stmt::FnDef { id, span, .. } => {
+                    // Functions which are defined twice are only declared once.
+                    let name_span = match self.tree.lookup_function(id) {
+                        Some(f) if self.tree.symbol(f).span == *span => self.declaration(f),
+                        _ => *span,
+                    };
+                    Some(FunctionSymbol {
+                        name: id.clone(),
+                        signature: signature(stmt)?,
+                        span: *span,
+                        name_span,
+                    })
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Returns the names which may be written at a byte offset: variables
+    /// and functions which are visible there, builtin functions and keywords.
+    pub fn completions(&self, offset: usize) -> Vec<Completion> {
+        let scope = self.tree.scope_at(offset);
+        let mut completions: Vec<Completion> = self
+            .tree
+            .visible_symbols(scope, offset)
+            .into_iter()
+            .map(|id| {
+                let symbol = self.tree.symbol(id);
+                match symbol.kind {
+                    SymbolKind::Function => Completion {
+                        label: symbol.identifier.clone(),
+                        kind: CompletionKind::Function,
+                        detail: self.signature(&symbol.identifier),
+                    },
+                    _ => Completion {
+                        label: symbol.identifier.clone(),
+                        kind: CompletionKind::Variable,
+                        detail: symbol.symbol_type.as_ref().map(|t| t.to_string()),
+                    },
+                }
+            })
+            .collect();
+
+        completions.extend(BUILTINS.iter().map(|f| Completion {
+            label: f.to_string(),
+            kind: CompletionKind::Function,
+            detail: None,
+        }));
+        completions.extend(KEYWORDS.iter().map(|k| Completion {
+            label: k.to_string(),
+            kind: CompletionKind::Keyword,
+            detail: None,
+        }));
+        completions
+    }
+
+    /// Returns the identifier at (or ending at) a byte offset and the symbol
+    /// it refers to, which is either a reference or a declaration.
+    fn symbol_at(&self, offset: usize) -> Option<(Span, SymbolId)> {
+        self.tree.reference_at(offset).or_else(|| {
+            self.declarations
+                .iter()
+                .find(|(span, _)| span.start <= offset && offset <= span.end)
+                .copied()
+        })
+    }
+
+    /// Returns the source range of the name of a symbol, or of its whole
+    /// declaration if the name is not found.
+    fn declaration(&self, id: SymbolId) -> Span {
+        self.declarations
+            .iter()
+            .find(|(_, s)| *s == id)
+            .map_or(self.tree.symbol(id).span, |(span, _)| *span)
+    }
+
+    /// Returns the signature of the function with provided name.
+    fn signature(&self, name: &str) -> Option<String> {
+        self.ast
+            .iter()
+            .find(|stmt| matches!(stmt, ast::Stmt::FnDef { id, .. } if id == name))
+            .and_then(signature)
+    }
+}
+
+/// Returns the signature of a function definition, e.g.
+/// `fn add(a: int, b: int): int`.
+fn signature(stmt: &ast::Stmt) -> Option<String> {
+    let ast::Stmt::FnDef {
+        id,
+        params,
+        return_type,
+        ..
+    } = stmt
+    else {
+        return None;
+    };
+
+    let params: Vec<String> = params
+        .iter()
+        .map(|(p, t)| format!("{}: {}", p, t))
+        .collect();
+    let mut s = format!("fn {}({})", id, params.join(", "));
+    if let Some(t) = return_type {
+        s.push_str(&format!(": {}", t));
+    }
+    Some(s)
+}
+
+fn has_errors(diagnostics: &[Diagnostic]) -> bool {
+    diagnostics.iter().any(|d| d.severity == Severity::Error)
+}
@@ -0,0 +1,16 @@
+#![warn(missing_docs)]
+
+//! # Kolang language server
+//! Language Server Protocol server which provides diagnostics, hover,
+//! go-to-definition, references, document symbols and completion for
+//! Kolang code. Messages are JSON-RPC over standard input and output.
+
+/// This module includes the analysis of open documents, which answers the
+/// queries of the editor.
+pub mod document;
+/// This module includes the framing of messages with `Content-Length` headers.
+pub mod rpc;
+/// This module includes the server which handles the messages of the editor.
+pub mod server;
+
+pub use server::{run, Server};
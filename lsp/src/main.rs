@@ -0,0 +1,14 @@
+//! # Kolang language server
+//! Runs the Language Server Protocol server on standard input and output.
+
+use std::io;
+
+fn main() {
+    match lsp::run(io::stdin().lock(), io::stdout().lock()) {
+        Ok(code) => std::process::exit(code),
+        Err(e) => {
+            eprintln!("kolang-lsp: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
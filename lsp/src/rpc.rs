@@ -0,0 +1,62 @@
+use std::io::{self, BufRead, Write};
+
+/// Reads the next message: header lines, an empty line and a body of
+/// `Content-Length` bytes. Returns `None` at the end of input.
+///
+/// # Errors
+/// May return I/O error if something goes wrong while reading the input.
+/// Returns [`io::ErrorKind::InvalidData`] if the `Content-Length` header is
+/// missing or invalid, or the body is not valid UTF-8.
+///
+/// # Examples
+///
+/// ```
+/// use lsp::rpc;
+///
+/// let mut input = "Content-Length: 2\r\n\r\n{}".as_bytes();
+/// assert_eq!(rpc::read_message(&mut input).unwrap().as_deref(), Some("{}"));
+/// assert_eq!(rpc::read_message(&mut input).unwrap(), None);
+/// ```
+pub fn read_message(input: &mut impl BufRead) -> io::Result<Option<String>> {
+    let mut length = None;
+    loop {
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            return match length {
+                None => Ok(None),
+                Some(_) => Err(invalid_data("Unexpected end of headers")),
+            };
+        }
+
+        let line = line.trim_end_matches(['\r', '\n']);
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("Content-Length") {
+                let value = value.trim().parse::<usize>();
+                length = Some(value.map_err(|_| invalid_data("Invalid `Content-Length` header"))?);
+            }
+        }
+    }
+
+    let length = length.ok_or_else(|| invalid_data("Missing `Content-Length` header"))?;
+    let mut body = vec![0; length];
+    input.read_exact(&mut body)?;
+    String::from_utf8(body)
+        .map(Some)
+        .map_err(|_| invalid_data("Message is not valid UTF-8"))
+}
+
+/// Writes a message with its `Content-Length` header and flushes the output.
+///
+/// # Errors
+/// May return I/O error if something goes wrong while writing the output.
+pub fn write_message(output: &mut impl Write, body: &str) -> io::Result<()> {
+    write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    output.flush()
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
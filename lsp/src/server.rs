@@ -0,0 +1,400 @@
+use std::{
+    collections::HashMap,
+    io::{self, BufRead, Write},
+};
+
+use diagnostics::{Diagnostic, Severity, Span};
+use serde_json::{json, Value};
+
+use crate::{
+    document::{CompletionKind, Document, Position},
+    rpc,
+};
+
+/// Error code of messages which are not valid JSON.
+const PARSE_ERROR: i32 = -32700;
+/// Error code of messages which are not valid requests.
+const INVALID_REQUEST: i32 = -32600;
+/// Error code of requests of unknown methods.
+const METHOD_NOT_FOUND: i32 = -32601;
+/// Error code of requests with invalid parameters.
+const INVALID_PARAMS: i32 = -32602;
+
+/// Error of a request, which is sent back in its response.
+struct RequestError {
+    code: i32,
+    message: String,
+}
+
+impl RequestError {
+    fn invalid_params(message: &str) -> Self {
+        Self {
+            code: INVALID_PARAMS,
+            message: message.to_string(),
+        }
+    }
+}
+
+/// The `Server` struct handles the messages of a Language Server Protocol
+/// client and keeps the documents which are open in the client.
+/// Documents are always synchronized in full.
+///
+/// # Examples
+///
+/// ```
+/// use lsp::Server;
+/// use serde_json::Value;
+///
+/// let mut server = Server::new();
+/// let open: Value = serde_json::from_str(r#"{"jsonrpc": "2.0", "method": "textDocument/didOpen",
+///     "params": {"textDocument": {"uri": "file:///a.kol", "text": "fn main() { x; }"}}}"#)
+///     .unwrap();
+///
+/// let messages = server.handle(&open);
+/// assert_eq!(
+///     messages[0]["params"]["diagnostics"][0]["message"],
+///     "Variable `x` is not defined"
+/// );
+/// ```
+pub struct Server {
+    /// Open documents by URI.
+    documents: HashMap<String, Document>,
+    /// Whether the client sent a `shutdown` request.
+    shutdown: bool,
+    /// Whether the client sent an `exit` notification.
+    exited: bool,
+}
+
+impl Default for Server {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Server {
+    /// Creates a new `Server` with no open documents.
+    pub fn new() -> Self {
+        Self {
+            documents: HashMap::new(),
+            shutdown: false,
+            exited: false,
+        }
+    }
+
+    /// Checks whether the client asked the server to exit.
+    pub fn has_exited(&self) -> bool {
+        self.exited
+    }
+
+    /// Returns the exit code of the server: `0` if the client asked it to
+    /// shut down before it exited, `1` otherwise.
+    pub fn exit_code(&self) -> i32 {
+        if self.shutdown {
+            0
+        } else {
+            1
+        }
+    }
+
+    /// Handles a message of the client and returns the messages to send
+    /// back: the response of a request, or the diagnostics of a document
+    /// which is opened or changed.
+    pub fn handle(&mut self, message: &Value) -> Vec<Value> {
+        let params = message.get("params").unwrap_or(&Value::Null);
+        match (
+            message.get("id"),
+            message.get("method").and_then(Value::as_str),
+        ) {
+            (Some(id), Some(method)) => {
+                let result = if self.shutdown {
+                    Err(RequestError {
+                        code: INVALID_REQUEST,
+                        message: "Server is shut down".to_string(),
+                    })
+                } else {
+                    self.request(method, params)
+                };
+                vec![response(id.clone(), result)]
+            }
+            (None, Some(method)) => self.notification(method, params),
+            // Responses to requests of the server, which sends none.
+            (Some(_), None)
+                if message.get("result").is_some() || message.get("error").is_some() =>
+            {
+                Vec::new()
+            }
+            _ => vec![response(
+                Value::Null,
+                Err(RequestError {
+                    code: INVALID_REQUEST,
+                    message: "Message is not a request or notification".to_string(),
+                }),
+            )],
+        }
+    }
+
+    /// Handles a request and returns its result.
+    fn request(&mut self, method: &str, params: &Value) -> Result<Value, RequestError> {
+        match method {
+            "initialize" => Ok(json!({
+                "capabilities": {
+                    "textDocumentSync": 1,
+                    "hoverProvider": true,
+                    "definitionProvider": true,
+                    "referencesProvider": true,
+                    "documentSymbolProvider": true,
+                    "completionProvider": {},
+                },
+                "serverInfo": {
+                    "name": "kolang-lsp",
+                    "version": env!("CARGO_PKG_VERSION"),
+                },
+            })),
+            "shutdown" => {
+                self.shutdown = true;
+                Ok(Value::Null)
+            }
+            "textDocument/hover" => {
+                let (_, doc, offset) = self.position(params)?;
+                Ok(doc.hover(offset).map_or(Value::Null, |(text, span)| {
+                    json!({
+                        "contents": { "kind": "markdown", "value": text },
+                        "range": range(span),
+                    })
+                }))
+            }
+            "textDocument/definition" => {
+                let (uri, doc, offset) = self.position(params)?;
+                Ok(doc
+                    .definition(offset)
+                    .map_or(Value::Null, |span| location(uri, span)))
+            }
+            "textDocument/references" => {
+                let (uri, doc, offset) = self.position(params)?;
+                let include_declaration = params
+                    .pointer("/context/includeDeclaration")
+                    .and_then(Value::as_bool)
+                    .unwrap_or(false);
+                let refs = doc.references(offset, include_declaration);
+                Ok(refs
+                    .into_iter()
+                    .map(|span| location(uri, span))
+                    .collect::<Vec<_>>()
+                    .into())
+            }
+            "textDocument/documentSymbol" => {
+                let (_, doc) = self.document(params)?;
+                let symbols: Vec<Value> = doc
+                    .functions()
+                    .into_iter()
+                    .map(|f| {
+                        json!({
+                            "name": f.name,
+                            "detail": f.signature,
+                            // Function
+                            "kind": 12,
+                            "range": range(f.span),
+                            "selectionRange": range(f.name_span),
+                        })
+                    })
+                    .collect();
+                Ok(symbols.into())
+            }
+            "textDocument/completion" => {
+                let (_, doc, offset) = self.position(params)?;
+                let items: Vec<Value> = doc
+                    .completions(offset)
+                    .into_iter()
+                    .map(|c| {
+                        let kind = match c.kind {
+                            CompletionKind::Function => 3,
+                            CompletionKind::Variable => 6,
+                            CompletionKind::Keyword => 14,
+                        };
+                        let mut item = json!({ "label": c.label, "kind": kind });
+                        if let Some(detail) = c.detail {
+                            item["detail"] = detail.into();
+                        }
+                        item
+                    })
+                    .collect();
+                Ok(items.into())
+            }
+            _ => Err(RequestError {
+                code: METHOD_NOT_FOUND,
+                message: format!("Unknown method `{}`", method),
+            }),
+        }
+    }
+
+    /// Handles a notification and returns the notifications to send back.
+    /// Unknown notifications are ignored.
+    fn notification(&mut self, method: &str, params: &Value) -> Vec<Value> {
+        let uri = params.pointer("/textDocument/uri").and_then(Value::as_str);
+        let text = match method {
+            "exit" => {
+                self.exited = true;
+                return Vec::new();
+            }
+            "textDocument/didOpen" => params.pointer("/textDocument/text"),
+            // The last change contains the full text.
+            "textDocument/didChange" => params
+                .get("contentChanges")
+                .and_then(Value::as_array)
+                .and_then(|changes| changes.last())
+                .and_then(|change| change.get("text")),
+            "textDocument/didClose" => {
+                let Some(uri) = uri else {
+                    return Vec::new();
+                };
+                self.documents.remove(uri);
+                return vec![publish_diagnostics(uri, &[])];
+            }
+            _ => return Vec::new(),
+        };
+
+        match (uri, text.and_then(Value::as_str)) {
+            (Some(uri), Some(text)) => {
+                let doc = Document::new(text.to_string());
+                let message = publish_diagnostics(uri, doc.diagnostics());
+                self.documents.insert(uri.to_string(), doc);
+                vec![message]
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// Returns the URI and the document of the `textDocument` parameter.
+    fn document<'a>(&'a self, params: &'a Value) -> Result<(&'a str, &'a Document), RequestError> {
+        let uri = params
+            .pointer("/textDocument/uri")
+            .and_then(Value::as_str)
+            .ok_or_else(|| RequestError::invalid_params("Missing `textDocument.uri`"))?;
+        let doc = self.documents.get(uri).ok_or_else(|| {
+            RequestError::invalid_params(&format!("Document `{}` is not open", uri))
+        })?;
+        Ok((uri, doc))
+    }
+
+    /// Returns the URI and the document of the `textDocument` parameter, and
+    /// the byte offset of the `position` parameter in the document.
+    fn position<'a>(
+        &'a self,
+        params: &'a Value,
+    ) -> Result<(&'a str, &'a Document, usize), RequestError> {
+        let (uri, doc) = self.document(params)?;
+        let field = |name: &str| params["position"][name].as_u64().map(|n| n as usize);
+        match (field("line"), field("character")) {
+            (Some(line), Some(character)) => {
+                Ok((uri, doc, doc.offset(Position { line, character })))
+            }
+            _ => Err(RequestError::invalid_params("Missing `position`")),
+        }
+    }
+}
+
+/// Reads messages from `input` and writes the messages of the server to
+/// `output`, until the `exit` notification or the end of input. Returns the
+/// exit code of the server.
+///
+/// # Errors
+/// May return I/O error if something goes wrong while reading or writing
+/// messages, or if a message has invalid headers.
+///
+/// # Examples
+///
+/// ```
+/// let input = "Content-Length: 33\r\n\r\n{\"jsonrpc\":\"2.0\",\"method\":\"exit\"}";
+/// let mut output = Vec::new();
+///
+/// assert_eq!(lsp::run(input.as_bytes(), &mut output).unwrap(), 1);
+/// assert!(output.is_empty());
+/// ```
+pub fn run(mut input: impl BufRead, mut output: impl Write) -> io::Result<i32> {
+    let mut server = Server::new();
+    while let Some(body) = rpc::read_message(&mut input)? {
+        let messages = match serde_json::from_str(&body) {
+            Ok(message) => server.handle(&message),
+            Err(e) => vec![response(
+                Value::Null,
+                Err(RequestError {
+                    code: PARSE_ERROR,
+                    message: e.to_string(),
+                }),
+            )],
+        };
+        for message in messages {
+            rpc::write_message(&mut output, &message.to_string())?;
+        }
+        if server.has_exited() {
+            break;
+        }
+    }
+    Ok(server.exit_code())
+}
+
+/// Creates the response of a request.
+fn response(id: Value, result: Result<Value, RequestError>) -> Value {
+    match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(e) => json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "error": { "code": e.code, "message": e.message },
+        }),
+    }
+}
+
+/// Creates the notification which replaces the diagnostics of a document.
+fn publish_diagnostics(uri: &str, diagnostics: &[Diagnostic]) -> Value {
+    let diagnostics: Vec<Value> = diagnostics
+        .iter()
+        .map(|d| {
+            let severity = match d.severity {
+                Severity::Error => 1,
+                Severity::Warning => 2,
+            };
+            let mut message = d.message.clone();
+            for note in d.notes.iter().chain(&d.help) {
+                message.push('\n');
+                message.push_str(note);
+            }
+            let related: Vec<Value> = d
+                .labels
+                .iter()
+                .map(|l| json!({ "location": location(uri, l.span), "message": l.message }))
+                .collect();
+            json!({
+                "range": range(d.span),
+                "severity": severity,
+                "source": "kolang",
+                "message": message,
+                "relatedInformation": related,
+            })
+        })
+        .collect();
+
+    json!({
+        "jsonrpc": "2.0",
+        "method": "textDocument/publishDiagnostics",
+        "params": { "uri": uri, "diagnostics": diagnostics },
+    })
+}
+
+/// Converts a source range to a range of zero-based positions.
+fn range(span: Span) -> Value {
+    let position = |line: usize, column: usize| {
+        json!({
+            "line": line.saturating_sub(1),
+            "character": column.saturating_sub(1),
+        })
+    };
+    json!({
+        "start": position(span.line, span.column),
+        "end": position(span.end_line, span.end_column),
+    })
+}
+
+fn location(uri: &str, span: Span) -> Value {
+    json!({ "uri": uri, "range": range(span) })
+}
@@ -0,0 +1,420 @@
+use serde_json::Value;
+
+const URI: &str = "file:///test.kol";
+
+const CODE: &str = "\
+fn add(a: int, b: int): int {
+    return a + b;
+}
+
+fn main() {
+    let total: int = 0;
+    for i = 0 to 10 {
+        total = add(total, i);
+    }
+    println(total);
+}
+";
+
+/// Frames messages like an editor does.
+fn frame(messages: &[String]) -> String {
+    messages
+        .iter()
+        .map(|m| format!("Content-Length: {}\r\n\r\n{}", m.len(), m))
+        .collect()
+}
+
+/// Runs the server on scripted messages and returns its exit code and the
+/// messages it sent.
+fn run(messages: &[String]) -> (i32, Vec<Value>) {
+    let input = frame(messages);
+    let mut output = Vec::new();
+    let code = lsp::run(input.as_bytes(), &mut output).unwrap();
+
+    let mut output = output.as_slice();
+    let mut sent = Vec::new();
+    while let Some(body) = lsp::rpc::read_message(&mut output).unwrap() {
+        sent.push(serde_json::from_str(&body).unwrap());
+    }
+    (code, sent)
+}
+
+fn request(id: usize, method: &str, params: &str) -> String {
+    format!(
+        r#"{{"jsonrpc": "2.0", "id": {}, "method": "{}", "params": {}}}"#,
+        id, method, params
+    )
+}
+
+fn notification(method: &str, params: &str) -> String {
+    format!(
+        r#"{{"jsonrpc": "2.0", "method": "{}", "params": {}}}"#,
+        method, params
+    )
+}
+
+fn did_open(text: &str) -> String {
+    notification(
+        "textDocument/didOpen",
+        &format!(
+            r#"{{"textDocument": {{"uri": "{}", "languageId": "kolang", "version": 1, "text": {}}}}}"#,
+            URI,
+            Value::from(text)
+        ),
+    )
+}
+
+fn position_request(id: usize, method: &str, line: usize, character: usize) -> String {
+    request(
+        id,
+        method,
+        &format!(
+            r#"{{"textDocument": {{"uri": "{}"}}, "position": {{"line": {}, "character": {}}}, "context": {{"includeDeclaration": true}}}}"#,
+            URI, line, character
+        ),
+    )
+}
+
+/// Opens `CODE` and returns the result of one request.
+fn query(message: String) -> Value {
+    let (_, sent) = run(&[did_open(CODE), message]);
+    assert_eq!(sent.len(), 2);
+    sent[1].get("result").unwrap().clone()
+}
+
+/// Returns the zero-based start line and character of a range.
+fn start(range: &Value) -> (usize, usize) {
+    let field = |name: &str| range["start"][name].as_u64().unwrap() as usize;
+    (field("line"), field("character"))
+}
+
+fn diagnostics(notification: &Value) -> Vec<(usize, usize, &str)> {
+    assert_eq!(
+        notification.get("method").unwrap().as_str(),
+        Some("textDocument/publishDiagnostics")
+    );
+    notification
+        .pointer("/params/diagnostics")
+        .unwrap()
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|d| {
+            let (line, character) = start(d.get("range").unwrap());
+            (line, character, d.get("message").unwrap().as_str().unwrap())
+        })
+        .collect()
+}
+
+#[test]
+fn lifecycle() {
+    let (code, sent) = run(&[
+        request(1, "initialize", r#"{"capabilities": {}}"#),
+        notification("initialized", "{}"),
+        request(2, "shutdown", "null"),
+        request(3, "textDocument/hover", "{}"),
+        notification("exit", "null"),
+        // Not read after `exit`.
+        request(4, "shutdown", "null"),
+    ]);
+    assert_eq!(code, 0);
+    assert_eq!(sent.len(), 3);
+
+    let capabilities = sent[0].pointer("/result/capabilities").unwrap();
+    assert_eq!(
+        capabilities.get("textDocumentSync").unwrap().as_u64(),
+        Some(1)
+    );
+    for provider in [
+        "hoverProvider",
+        "definitionProvider",
+        "referencesProvider",
+        "documentSymbolProvider",
+    ] {
+        assert_eq!(capabilities.get(provider).unwrap().as_bool(), Some(true));
+    }
+    assert!(capabilities.get("completionProvider").is_some());
+
+    assert_eq!(sent[1].get("id").unwrap().as_u64(), Some(2));
+    assert_eq!(sent[1].get("result"), Some(&Value::Null));
+    assert_eq!(
+        sent[2].pointer("/error/message").unwrap().as_str(),
+        Some("Server is shut down")
+    );
+
+    // Exiting without shutting down, or at the end of input.
+    assert_eq!(run(&[notification("exit", "null")]).0, 1);
+    assert_eq!(run(&[]).0, 1);
+}
+
+#[test]
+fn errors() {
+    let (_, sent) = run(&[
+        "{not json".to_string(),
+        request(1, "workspace/unknown", "{}"),
+        position_request(2, "textDocument/hover", 0, 0),
+        request(3, "textDocument/documentSymbol", "{}"),
+        notification("$/unknown", "{}"),
+    ]);
+    let codes: Vec<_> = sent
+        .iter()
+        .map(|m| {
+            let code = m.pointer("/error/code").unwrap();
+            (m.get("id").unwrap().as_u64(), code.to_string())
+        })
+        .collect();
+    assert_eq!(
+        codes,
+        [
+            (None, "-32700".to_string()),
+            (Some(1), "-32601".to_string()),
+            (Some(2), "-32602".to_string()),
+            (Some(3), "-32602".to_string()),
+        ]
+    );
+
+    let mut output = Vec::new();
+    let e = lsp::run("Content-Type: x\r\n\r\n{}".as_bytes(), &mut output).unwrap_err();
+    assert_eq!(e.to_string(), "Missing `Content-Length` header");
+}
+
+#[test]
+fn publish_diagnostics() {
+    let (_, sent) = run(&[
+        did_open(CODE),
+        notification(
+            "textDocument/didChange",
+            &format!(
+                r#"{{"textDocument": {{"uri": "{}", "version": 2}}, "contentChanges": [{{"text": "fn main() {{\n    let x: int = y;\n    let z: int = \"s\";\n}}"}}]}}"#,
+                URI
+            ),
+        ),
+        notification(
+            "textDocument/didChange",
+            &format!(
+                r#"{{"textDocument": {{"uri": "{}", "version": 3}}, "contentChanges": [{{"text": "fn main() {{\n    let x: int = \"s\";\n}}"}}]}}"#,
+                URI
+            ),
+        ),
+        notification(
+            "textDocument/didChange",
+            &format!(
+                r#"{{"textDocument": {{"uri": "{}", "version": 4}}, "contentChanges": [{{"text": "fn main( {{}}"}}]}}"#,
+                URI
+            ),
+        ),
+        notification(
+            "textDocument/didClose",
+            &format!(r#"{{"textDocument": {{"uri": "{}"}}}}"#, URI),
+        ),
+    ]);
+    assert_eq!(sent.len(), 5);
+    assert_eq!(sent[0].pointer("/params/uri").unwrap().as_str(), Some(URI));
+    assert_eq!(diagnostics(&sent[0]), []);
+
+    // Type errors are only reported if there are no resolver errors.
+    assert_eq!(
+        diagnostics(&sent[1]),
+        [(1, 17, "Variable `y` is not defined")]
+    );
+    let d = &diagnostics(&sent[2]);
+    assert_eq!(d.len(), 1);
+    assert_eq!((d[0].0, d[0].1), (1, 17));
+    let first = sent[2]
+        .pointer("/params/diagnostics")
+        .unwrap()
+        .as_array()
+        .unwrap()[0]
+        .clone();
+    assert_eq!(first.get("severity").unwrap().as_u64(), Some(1));
+    assert_eq!(first.get("source").unwrap().as_str(), Some("kolang"));
+
+    let d = diagnostics(&sent[3]);
+    assert!(!d.is_empty());
+    assert_eq!((d[0].0, d[0].1), (0, 9));
+
+    assert_eq!(diagnostics(&sent[4]), []);
+}
+
+#[test]
+fn recovers_without_progress() {
+    let (_, sent) = run(&[
+        did_open("fn main() { x; else }"),
+        did_open("fn main() { -{} else }"),
+    ]);
+    assert_eq!(sent.len(), 2);
+    assert_eq!(diagnostics(&sent[0]), [(0, 15, "Expected expression")]);
+    assert_eq!(
+        diagnostics(&sent[1]),
+        [
+            (0, 13, "Expected expression"),
+            (0, 16, "Expected expression")
+        ]
+    );
+}
+
+#[test]
+fn hover() {
+    let hover = |line, character| query(position_request(1, "textDocument/hover", line, character));
+    let text = |v: &Value| {
+        v.pointer("/contents/value")
+            .unwrap()
+            .as_str()
+            .unwrap()
+            .to_string()
+    };
+
+    let total = hover(7, 21);
+    assert_eq!(text(&total), "```kolang\ntotal: int\n```\nvariable");
+    assert_eq!(start(total.get("range").unwrap()), (7, 20));
+
+    let add = hover(7, 17);
+    assert_eq!(
+        text(&add),
+        "```kolang\nfn add(a: int, b: int): int\n```\nfunction"
+    );
+    assert_eq!(text(&hover(1, 11)), "```kolang\na: int\n```\nparameter");
+    assert_eq!(text(&hover(6, 8)), "```kolang\ni: int\n```\nloop variable");
+    // The name in a declaration.
+    assert_eq!(text(&hover(5, 9)), "```kolang\ntotal: int\n```\nvariable");
+
+    assert_eq!(hover(4, 0), Value::Null);
+    assert_eq!(hover(9, 5), Value::Null);
+}
+
+#[test]
+fn definition() {
+    let definition = |line, character| {
+        let result = query(position_request(
+            1,
+            "textDocument/definition",
+            line,
+            character,
+        ));
+        assert_eq!(result.get("uri").unwrap().as_str(), Some(URI));
+        start(result.get("range").unwrap())
+    };
+
+    assert_eq!(definition(7, 17), (0, 3));
+    assert_eq!(definition(7, 20), (5, 8));
+    assert_eq!(definition(7, 28), (6, 8));
+    assert_eq!(definition(1, 15), (0, 15));
+    assert_eq!(
+        query(position_request(1, "textDocument/definition", 9, 5)),
+        Value::Null
+    );
+}
+
+#[test]
+fn references() {
+    let result = query(position_request(1, "textDocument/references", 5, 9));
+    let refs: Vec<_> = result
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|l| start(l.get("range").unwrap()))
+        .collect();
+    assert_eq!(refs, [(5, 8), (7, 8), (7, 20), (9, 12)]);
+
+    let (_, sent) = run(&[
+        did_open(CODE),
+        request(
+            1,
+            "textDocument/references",
+            &format!(
+                r#"{{"textDocument": {{"uri": "{}"}}, "position": {{"line": 7, "character": 17}}, "context": {{"includeDeclaration": false}}}}"#,
+                URI
+            ),
+        ),
+    ]);
+    let refs = sent[1].get("result").unwrap().as_array().unwrap();
+    assert_eq!(refs.len(), 1);
+    assert_eq!(start(refs[0].get("range").unwrap()), (7, 16));
+}
+
+#[test]
+fn document_symbols() {
+    let result = query(request(
+        1,
+        "textDocument/documentSymbol",
+        &format!(r#"{{"textDocument": {{"uri": "{}"}}}}"#, URI),
+    ));
+    let symbols: Vec<_> = result
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|s| {
+            (
+                s.get("name").unwrap().as_str().unwrap(),
+                s.get("detail").unwrap().as_str().unwrap(),
+                s.get("kind").unwrap().as_u64().unwrap(),
+                start(s.get("range").unwrap()),
+                start(s.get("selectionRange").unwrap()),
+            )
+        })
+        .collect();
+    assert_eq!(
+        symbols,
+        [
+            ("add", "fn add(a: int, b: int): int", 12, (0, 0), (0, 3)),
+            ("main", "fn main()", 12, (4, 0), (4, 3)),
+        ]
+    );
+}
+
+#[test]
+fn completion() {
+    let labels = |line, character| {
+        let result = query(position_request(
+            1,
+            "textDocument/completion",
+            line,
+            character,
+        ));
+        result
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|c| {
+                (
+                    c.get("label").unwrap().as_str().unwrap().to_string(),
+                    c.get("kind").unwrap().as_u64().unwrap(),
+                )
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let inside_loop = labels(7, 8);
+    assert_eq!(
+        inside_loop[..4],
+        [
+            ("i".to_string(), 6),
+            ("total".to_string(), 6),
+            ("add".to_string(), 3),
+            ("main".to_string(), 3),
+        ]
+    );
+    assert!(inside_loop.contains(&("println".to_string(), 3)));
+    assert!(inside_loop.contains(&("while".to_string(), 14)));
+    assert!(inside_loop.contains(&("return".to_string(), 14)));
+
+    // `total` is declared after the position, and `i` is out of scope.
+    let before = labels(5, 4);
+    assert_eq!(before[0], ("add".to_string(), 3));
+    assert!(!before.iter().any(|(l, _)| l == "total" || l == "i"));
+}
+
+#[test]
+fn utf16_positions() {
+    let code = "fn main() {\n    let s: str = \"😀\"; let n: int = 1;\n    println(n);\n}\n";
+    let (_, sent) = run(&[
+        did_open(code),
+        position_request(1, "textDocument/hover", 1, 27),
+        position_request(2, "textDocument/definition", 2, 12),
+    ]);
+    assert_eq!(
+        sent[1].pointer("/result/contents/value").unwrap().as_str(),
+        Some("```kolang\nn: int\n```\nvariable")
+    );
+    assert_eq!(start(sent[2].pointer("/result/range").unwrap()), (1, 27));
+}
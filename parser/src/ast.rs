@@ -1,191 +1,482 @@
 use std::fmt;
 
-#[derive(PartialEq)]
+use diagnostics::Span;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// This module includes a trait which rebuilds syntax trees.
+pub mod fold;
+/// This module includes traits which traverse syntax trees by reference.
+pub mod visit;
+
+pub use fold::Fold;
+pub use visit::{Visitor, VisitorMut};
+
+/// Kolang expression.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Expr {
+    /// Integer literal: `123`, `0b101`, `0o17`, `0xff`
     LiteralInt {
+        /// Value of the literal.
         value: i64,
-        line: usize,
-        column: usize,
+        /// Source range of this expression.
+        span: Span,
     },
+    /// String literal: `"Hello"`
     LiteralStr {
+        /// Value of the literal.
         value: String,
-        line: usize,
-        column: usize,
+        /// Source range of this expression.
+        span: Span,
     },
+    /// Character literal: `'a'`
     LiteralChar {
+        /// Value of the literal.
         value: char,
-        line: usize,
-        column: usize,
+        /// Source range of this expression.
+        span: Span,
     },
+    /// Floating-point literal: `9.1`, `2e3`
     LiteralFloat {
+        /// Value of the literal.
         value: f64,
-        line: usize,
-        column: usize,
+        /// Source range of this expression.
+        span: Span,
     },
+    /// Boolean literal: `true`, `false`
     LiteralBool {
+        /// Value of the literal.
         value: bool,
-        line: usize,
-        column: usize,
+        /// Source range of this expression.
+        span: Span,
     },
+    /// Array literal: `[1, 2, 3]`
     LiteralArray {
+        /// Elements of the array.
         elements: Vec<Expr>,
-        line: usize,
-        column: usize,
+        /// Source range of this expression.
+        span: Span,
     },
+    /// Binary operation: `a + b`
     BinaryOp {
+        /// Left operand.
         l: Box<Expr>,
+        /// Operator.
         op: BinOp,
+        /// Right operand.
         r: Box<Expr>,
+        /// Source range of this expression.
+        span: Span,
     },
+    /// Unary operation: `-a`
     UnaryOp {
+        /// Operator.
         op: UnOp,
+        /// Operand.
         expr: Box<Expr>,
+        /// Source range of this expression.
+        span: Span,
     },
+    /// Variable: `a`
     Identifier {
+        /// Name of the variable.
         id: String,
-        line: usize,
-        column: usize,
+        /// Source range of this expression.
+        span: Span,
     },
+    /// Function call: `f(a, b)`
     Call {
+        /// Name of the function.
         id: String,
+        /// Arguments of the call.
         args: Vec<Expr>,
-        line: usize,
-        column: usize,
+        /// Source range of this expression.
+        span: Span,
     },
+    /// Array indexing: `a[i]`
     ArrayExpr {
+        /// Name of the array.
         id: String,
+        /// Index expression.
         index: Box<Expr>,
-        line: usize,
-        column: usize,
+        /// Source range of this expression.
+        span: Span,
     },
+    /// Assignment: `a = b`
     Assign {
+        /// Name of the variable.
         id: String,
+        /// Assigned value.
         expr: Box<Expr>,
-        line: usize,
-        column: usize,
+        /// Source range of this expression.
+        span: Span,
     },
+    /// Erroneous expression (result of a syntax error)
     Error {
-        line: usize,
-        column: usize,
+        /// Source range of this expression.
+        span: Span,
     },
 }
 
-#[derive(PartialEq, Eq)]
+/// Binary operator. Stores the source range of the operator itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum BinOp {
-    Add { line: usize, column: usize },
-    Sub { line: usize, column: usize },
-    Mul { line: usize, column: usize },
-    Div { line: usize, column: usize },
-    Mod { line: usize, column: usize },
-    LogAnd { line: usize, column: usize },
-    LogOr { line: usize, column: usize },
-    BitAnd { line: usize, column: usize },
-    BitOr { line: usize, column: usize },
-    Eq { line: usize, column: usize },
-    NEq { line: usize, column: usize },
-    LT { line: usize, column: usize },
-    GT { line: usize, column: usize },
-    LEq { line: usize, column: usize },
-    GEq { line: usize, column: usize },
+    /// Addition: `+`
+    Add {
+        /// Source range of this operator.
+        span: Span,
+    },
+    /// Subtraction: `-`
+    Sub {
+        /// Source range of this operator.
+        span: Span,
+    },
+    /// Multiplication: `*`
+    Mul {
+        /// Source range of this operator.
+        span: Span,
+    },
+    /// Division: `/`
+    Div {
+        /// Source range of this operator.
+        span: Span,
+    },
+    /// Modulo: `%`
+    Mod {
+        /// Source range of this operator.
+        span: Span,
+    },
+    /// Logical and: `and`
+    LogAnd {
+        /// Source range of this operator.
+        span: Span,
+    },
+    /// Logical or: `or`
+    LogOr {
+        /// Source range of this operator.
+        span: Span,
+    },
+    /// Bitwise and: `&`
+    BitAnd {
+        /// Source range of this operator.
+        span: Span,
+    },
+    /// Bitwise or: `|`
+    BitOr {
+        /// Source range of this operator.
+        span: Span,
+    },
+    /// Equals: `==`
+    Eq {
+        /// Source range of this operator.
+        span: Span,
+    },
+    /// Not equal: `!=`
+    NEq {
+        /// Source range of this operator.
+        span: Span,
+    },
+    /// Less than: `<`
+    LT {
+        /// Source range of this operator.
+        span: Span,
+    },
+    /// Greater than: `>`
+    GT {
+        /// Source range of this operator.
+        span: Span,
+    },
+    /// Less than or equal: `<=`
+    LEq {
+        /// Source range of this operator.
+        span: Span,
+    },
+    /// Greater than or equal: `>=`
+    GEq {
+        /// Source range of this operator.
+        span: Span,
+    },
 }
 
-#[derive(PartialEq, Eq)]
+/// Unary operator. Stores the source range of the operator itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum UnOp {
-    Neg { line: usize, column: usize },
-    LogNot { line: usize, column: usize },
-    BitNot { line: usize, column: usize },
+    /// Negation: `-`
+    Neg {
+        /// Source range of this operator.
+        span: Span,
+    },
+    /// Logical not: `not`
+    LogNot {
+        /// Source range of this operator.
+        span: Span,
+    },
+    /// Bitwise not: `~`
+    BitNot {
+        /// Source range of this operator.
+        span: Span,
+    },
 }
 
-#[derive(PartialEq)]
+/// Kolang statement.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Stmt {
+    /// Variable definition: `let a: int = 5;`
     Let {
+        /// Name of the variable.
         id: String,
+        /// Type of the variable.
         var_type: Type,
+        /// Initial value of the variable.
         expr: Option<Expr>,
-        line: usize,
-        column: usize,
+        /// Source range of this statement.
+        span: Span,
     },
+    /// Expression statement: `f(a);`
     Expr {
+        /// The expression.
         expr: Expr,
+        /// Source range of this statement.
+        span: Span,
     },
+    /// Conditional: `if cond stmt else stmt`
     If {
+        /// Condition.
         cond: Expr,
+        /// Statement executed if condition is true.
         then_stmt: Box<Stmt>,
+        /// Statement executed if condition is false.
         else_stmt: Option<Box<Stmt>>,
-        line: usize,
-        column: usize,
+        /// Source range of this statement.
+        span: Span,
     },
+    /// While loop: `while cond stmt`
     While {
+        /// Condition.
         cond: Expr,
+        /// Body of the loop.
         body: Box<Stmt>,
-        line: usize,
-        column: usize,
+        /// Source range of this statement.
+        span: Span,
     },
+    /// For loop: `for i = start to end stmt`
     For {
+        /// Name of the loop variable.
         id: String,
+        /// Start of the range (inclusive).
         start: Expr,
+        /// End of the range (inclusive).
         end: Expr,
+        /// Body of the loop.
         body: Box<Stmt>,
-        line: usize,
-        column: usize,
+        /// Source range of this statement.
+        span: Span,
     },
+    /// Return statement: `return expr;`
     Return {
+        /// Returned value.
         expr: Expr,
-        line: usize,
-        column: usize,
+        /// Source range of this statement.
+        span: Span,
     },
+    /// Block of statements: `{ stmt* }`
     Block {
+        /// Statements of the block.
         stmts: Vec<Stmt>,
-        line: usize,
-        column: usize,
+        /// Source range of this statement.
+        span: Span,
     },
+    /// Function definition: `fn f(a: int): int stmt`
     FnDef {
+        /// Name of the function.
         id: String,
+        /// Parameters of the function.
         params: Vec<(String, Type)>,
+        /// Return type of the function. `None` if function returns nothing.
         return_type: Option<Type>,
+        /// Body of the function.
         body: Box<Stmt>,
-        line: usize,
-        column: usize,
+        /// Source range of this statement.
+        span: Span,
     },
+    /// Empty statement: `;`
     Empty {
-        line: usize,
-        column: usize,
+        /// Source range of this statement.
+        span: Span,
     },
 }
 
-#[derive(PartialEq, Eq)]
+/// Kolang data type.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Type {
+    /// `int` type
     Int {
-        line: usize,
-        column: usize,
+        /// Source range of this type.
+        span: Span,
     },
+    /// `float` type
     Float {
-        line: usize,
-        column: usize,
+        /// Source range of this type.
+        span: Span,
     },
+    /// `char` type
     Char {
-        line: usize,
-        column: usize,
+        /// Source range of this type.
+        span: Span,
     },
+    /// `str` type
     Str {
-        line: usize,
-        column: usize,
+        /// Source range of this type.
+        span: Span,
     },
+    /// `bool` type
     Bool {
-        line: usize,
-        column: usize,
+        /// Source range of this type.
+        span: Span,
     },
+    /// Array type: `int[]`
     Array {
+        /// Type of the array elements.
         element_type: Box<Type>,
-        line: usize,
-        column: usize,
+        /// Source range of this type.
+        span: Span,
     },
+    /// Erroneous type (result of a syntax error)
     Error {
-        line: usize,
-        column: usize,
+        /// Source range of this type.
+        span: Span,
     },
 }
 
+impl Expr {
+    /// Returns the source range of this expression.
+    pub fn span(&self) -> Span {
+        match self {
+            Expr::LiteralInt { span, .. }
+            | Expr::LiteralStr { span, .. }
+            | Expr::LiteralChar { span, .. }
+            | Expr::LiteralFloat { span, .. }
+            | Expr::LiteralBool { span, .. }
+            | Expr::LiteralArray { span, .. }
+            | Expr::BinaryOp { span, .. }
+            | Expr::UnaryOp { span, .. }
+            | Expr::Identifier { span, .. }
+            | Expr::Call { span, .. }
+            | Expr::ArrayExpr { span, .. }
+            | Expr::Assign { span, .. }
+            | Expr::Error { span } => *span,
+        }
+    }
+}
+
+impl BinOp {
+    /// Returns the source range of this operator.
+    pub fn span(&self) -> Span {
+        match self {
+            BinOp::Add { span }
+            | BinOp::Sub { span }
+            | BinOp::Mul { span }
+            | BinOp::Div { span }
+            | BinOp::Mod { span }
+            | BinOp::LogAnd { span }
+            | BinOp::LogOr { span }
+            | BinOp::BitAnd { span }
+            | BinOp::BitOr { span }
+            | BinOp::Eq { span }
+            | BinOp::NEq { span }
+            | BinOp::LT { span }
+            | BinOp::GT { span }
+            | BinOp::LEq { span }
+            | BinOp::GEq { span } => *span,
+        }
+    }
+}
+
+impl UnOp {
+    /// Returns the source range of this operator.
+    pub fn span(&self) -> Span {
+        match self {
+            UnOp::Neg { span } | UnOp::LogNot { span } | UnOp::BitNot { span } => *span,
+        }
+    }
+}
+
+impl Stmt {
+    /// Returns the source range of this statement.
+    pub fn span(&self) -> Span {
+        match self {
+            Stmt::Let { span, .. }
+            | Stmt::Expr { span, .. }
+            | Stmt::If { span, .. }
+            | Stmt::While { span, .. }
+            | Stmt::For { span, .. }
+            | Stmt::Return { span, .. }
+            | Stmt::Block { span, .. }
+            | Stmt::FnDef { span, .. }
+            | Stmt::Empty { span } => *span,
+        }
+    }
+}
+
+/// Returns the function definitions of a program which are compiled, in
+/// order. Functions which are defined more than once are compiled from their
+/// last definition, and other top level statements are ignored, like the
+/// interpreter does.
+///
+/// # Examples
+///
+/// ```
+/// use lexer::Lexer;
+/// use parser::{ast, Parser};
+///
+/// let source = "fn f() {}\nfn main() {}\nfn f(a: int) {}".as_bytes();
+/// let (prog, _) = Parser::new(Lexer::new(source)).parse().unwrap();
+///
+/// let lines: Vec<_> = ast::definitions(&prog).iter().map(|f| f.span().line).collect();
+/// assert_eq!(lines, vec![2, 3]);
+/// ```
+pub fn definitions(prog: &[Stmt]) -> Vec<&Stmt> {
+    let mut definitions: Vec<&Stmt> = Vec::new();
+    for stmt in prog {
+        if let Stmt::FnDef { id, .. } = stmt {
+            definitions.retain(|f| !matches!(f, Stmt::FnDef { id: other, .. } if other == id));
+            definitions.push(stmt);
+        }
+    }
+    definitions
+}
+
+impl Type {
+    /// Returns the source range of this type.
+    pub fn span(&self) -> Span {
+        match self {
+            Type::Int { span }
+            | Type::Float { span }
+            | Type::Char { span }
+            | Type::Str { span }
+            | Type::Bool { span }
+            | Type::Array { span, .. }
+            | Type::Error { span } => *span,
+        }
+    }
+
+    /// Checks whether two types are the same, regardless of their position in code.
+    pub fn same_as(&self, other: &Type) -> bool {
+        match (self, other) {
+            (
+                Type::Array { element_type: a, .. },
+                Type::Array { element_type: b, .. },
+            ) => a.same_as(b),
+            (a, b) => std::mem::discriminant(a) == std::mem::discriminant(b),
+        }
+    }
+}
+
 impl fmt::Display for Expr {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
@@ -204,8 +495,8 @@ impl fmt::Display for Expr {
                 }
                 write!(f, "]")
             }
-            Expr::BinaryOp { l, op, r } => write!(f, "({} {} {})", l, op, r),
-            Expr::UnaryOp { op, expr } => write!(f, "({} {})", op, expr),
+            Expr::BinaryOp { l, op, r, .. } => write!(f, "({} {} {})", l, op, r),
+            Expr::UnaryOp { op, expr, .. } => write!(f, "({} {})", op, expr),
             Expr::Identifier { id, .. } => write!(f, "{}", id),
             Expr::Call {
                 id, args, ..
@@ -287,7 +578,7 @@ impl Stmt {
                 }
                 Ok(())
             }
-            Stmt::Expr { expr } => write!(f, "{}", expr),
+            Stmt::Expr { expr, .. } => write!(f, "{}", expr),
             Stmt::If {
                 cond,
                 then_stmt,
@@ -0,0 +1,194 @@
+use super::{BinOp, Expr, Stmt, Type, UnOp};
+
+/// The `Fold` trait rebuilds syntax trees. Each method takes a node by value
+/// and returns the node which replaces it. By default, methods rebuild the
+/// node from its folded children with the matching `walk_*` function, and
+/// operators are kept as they are.
+///
+/// # Examples
+///
+/// ```
+/// use lexer::Lexer;
+/// use parser::{
+///     ast::{fold, BinOp, Expr, Fold},
+///     Parser,
+/// };
+///
+/// /// Computes the sums of integer literals.
+/// struct AddConstants;
+///
+/// impl Fold for AddConstants {
+///     fn fold_expr(&mut self, expr: Expr) -> Expr {
+///         // Fold the operands first.
+///         match fold::walk_expr(self, expr) {
+///             Expr::BinaryOp { l, op: op @ BinOp::Add { .. }, r, span } => match (*l, *r) {
+///                 (Expr::LiteralInt { value: a, .. }, Expr::LiteralInt { value: b, .. }) => {
+///                     Expr::LiteralInt { value: a.wrapping_add(b), span }
+///                 }
+///                 (l, r) => Expr::BinaryOp { l: Box::new(l), op, r: Box::new(r), span },
+///             },
+///             expr => expr,
+///         }
+///     }
+/// }
+///
+/// let mut p = Parser::new(Lexer::new("f(1 + 2 + 3, x + 1)".as_bytes()));
+/// let (expr, _) = p.parse_expr().unwrap();
+///
+/// assert_eq!(AddConstants.fold_expr(expr).to_string(), "f(6, (x + 1))");
+/// ```
+pub trait Fold {
+    /// Folds a statement.
+    fn fold_stmt(&mut self, stmt: Stmt) -> Stmt {
+        walk_stmt(self, stmt)
+    }
+
+    /// Folds an expression.
+    fn fold_expr(&mut self, expr: Expr) -> Expr {
+        walk_expr(self, expr)
+    }
+
+    /// Folds a type.
+    fn fold_type(&mut self, t: Type) -> Type {
+        walk_type(self, t)
+    }
+
+    /// Folds a binary operator.
+    fn fold_bin_op(&mut self, op: BinOp) -> BinOp {
+        op
+    }
+
+    /// Folds a unary operator.
+    fn fold_un_op(&mut self, op: UnOp) -> UnOp {
+        op
+    }
+}
+
+/// Rebuilds a statement from its folded children, in source order.
+pub fn walk_stmt<F: Fold + ?Sized>(f: &mut F, stmt: Stmt) -> Stmt {
+    match stmt {
+        Stmt::Let {
+            id,
+            var_type,
+            expr,
+            span,
+        } => Stmt::Let {
+            id,
+            var_type: f.fold_type(var_type),
+            expr: expr.map(|e| f.fold_expr(e)),
+            span,
+        },
+        Stmt::Expr { expr, span } => Stmt::Expr {
+            expr: f.fold_expr(expr),
+            span,
+        },
+        Stmt::If {
+            cond,
+            then_stmt,
+            else_stmt,
+            span,
+        } => Stmt::If {
+            cond: f.fold_expr(cond),
+            then_stmt: Box::new(f.fold_stmt(*then_stmt)),
+            else_stmt: else_stmt.map(|s| Box::new(f.fold_stmt(*s))),
+            span,
+        },
+        Stmt::While { cond, body, span } => Stmt::While {
+            cond: f.fold_expr(cond),
+            body: Box::new(f.fold_stmt(*body)),
+            span,
+        },
+        Stmt::For {
+            id,
+            start,
+            end,
+            body,
+            span,
+        } => Stmt::For {
+            id,
+            start: f.fold_expr(start),
+            end: f.fold_expr(end),
+            body: Box::new(f.fold_stmt(*body)),
+            span,
+        },
+        Stmt::Return { expr, span } => Stmt::Return {
+            expr: f.fold_expr(expr),
+            span,
+        },
+        Stmt::Block { stmts, span } => Stmt::Block {
+            stmts: stmts.into_iter().map(|s| f.fold_stmt(s)).collect(),
+            span,
+        },
+        Stmt::FnDef {
+            id,
+            params,
+            return_type,
+            body,
+            span,
+        } => Stmt::FnDef {
+            id,
+            params: params
+                .into_iter()
+                .map(|(name, t)| (name, f.fold_type(t)))
+                .collect(),
+            return_type: return_type.map(|t| f.fold_type(t)),
+            body: Box::new(f.fold_stmt(*body)),
+            span,
+        },
+        Stmt::Empty { span } => Stmt::Empty { span },
+    }
+}
+
+/// Rebuilds an expression from its folded children, in source order.
+pub fn walk_expr<F: Fold + ?Sized>(f: &mut F, expr: Expr) -> Expr {
+    match expr {
+        Expr::LiteralArray { elements, span } => Expr::LiteralArray {
+            elements: elements.into_iter().map(|e| f.fold_expr(e)).collect(),
+            span,
+        },
+        Expr::BinaryOp { l, op, r, span } => {
+            let l = Box::new(f.fold_expr(*l));
+            let op = f.fold_bin_op(op);
+            let r = Box::new(f.fold_expr(*r));
+            Expr::BinaryOp { l, op, r, span }
+        }
+        Expr::UnaryOp { op, expr, span } => {
+            let op = f.fold_un_op(op);
+            let expr = Box::new(f.fold_expr(*expr));
+            Expr::UnaryOp { op, expr, span }
+        }
+        Expr::Call { id, args, span } => Expr::Call {
+            id,
+            args: args.into_iter().map(|e| f.fold_expr(e)).collect(),
+            span,
+        },
+        Expr::ArrayExpr { id, index, span } => Expr::ArrayExpr {
+            id,
+            index: Box::new(f.fold_expr(*index)),
+            span,
+        },
+        Expr::Assign { id, expr, span } => Expr::Assign {
+            id,
+            expr: Box::new(f.fold_expr(*expr)),
+            span,
+        },
+        expr @ (Expr::LiteralInt { .. }
+        | Expr::LiteralStr { .. }
+        | Expr::LiteralChar { .. }
+        | Expr::LiteralFloat { .. }
+        | Expr::LiteralBool { .. }
+        | Expr::Identifier { .. }
+        | Expr::Error { .. }) => expr,
+    }
+}
+
+/// Rebuilds a type from its folded children.
+pub fn walk_type<F: Fold + ?Sized>(f: &mut F, t: Type) -> Type {
+    match t {
+        Type::Array { element_type, span } => Type::Array {
+            element_type: Box::new(f.fold_type(*element_type)),
+            span,
+        },
+        t => t,
+    }
+}
@@ -0,0 +1,282 @@
+use super::{BinOp, Expr, Stmt, Type, UnOp};
+
+/// The `Visitor` trait traverses syntax trees by reference. Each method
+/// visits a node and by default continues into its children with the
+/// matching `walk_*` function, so implementations only override the
+/// methods of the nodes they care about. Overridden methods call the
+/// `walk_*` function themselves if the children should be visited too.
+///
+/// # Examples
+///
+/// ```
+/// use lexer::Lexer;
+/// use parser::{
+///     ast::{visit, Expr, Visitor},
+///     Parser,
+/// };
+///
+/// /// Collects the names of the called functions.
+/// struct Calls(Vec<String>);
+///
+/// impl Visitor for Calls {
+///     fn visit_expr(&mut self, expr: &Expr) {
+///         if let Expr::Call { id, .. } = expr {
+///             self.0.push(id.clone());
+///         }
+///         visit::walk_expr(self, expr);
+///     }
+/// }
+///
+/// let source = "fn main() { f(g(1), 2); if h() { return; } }".as_bytes();
+/// let (ast, _) = Parser::new(Lexer::new(source)).parse().unwrap();
+///
+/// let mut calls = Calls(Vec::new());
+/// ast.iter().for_each(|s| calls.visit_stmt(s));
+/// assert_eq!(calls.0, ["f", "g", "h"]);
+/// ```
+pub trait Visitor {
+    /// Visits a statement.
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        walk_stmt(self, stmt);
+    }
+
+    /// Visits an expression.
+    fn visit_expr(&mut self, expr: &Expr) {
+        walk_expr(self, expr);
+    }
+
+    /// Visits a type.
+    fn visit_type(&mut self, t: &Type) {
+        walk_type(self, t);
+    }
+
+    /// Visits a binary operator.
+    fn visit_bin_op(&mut self, _op: &BinOp) {}
+
+    /// Visits a unary operator.
+    fn visit_un_op(&mut self, _op: &UnOp) {}
+}
+
+/// Visits the children of a statement in source order.
+pub fn walk_stmt<V: Visitor + ?Sized>(v: &mut V, stmt: &Stmt) {
+    match stmt {
+        Stmt::Let { var_type, expr, .. } => {
+            v.visit_type(var_type);
+            if let Some(expr) = expr {
+                v.visit_expr(expr);
+            }
+        }
+        Stmt::Expr { expr, .. } | Stmt::Return { expr, .. } => v.visit_expr(expr),
+        Stmt::If {
+            cond,
+            then_stmt,
+            else_stmt,
+            ..
+        } => {
+            v.visit_expr(cond);
+            v.visit_stmt(then_stmt);
+            if let Some(else_stmt) = else_stmt {
+                v.visit_stmt(else_stmt);
+            }
+        }
+        Stmt::While { cond, body, .. } => {
+            v.visit_expr(cond);
+            v.visit_stmt(body);
+        }
+        Stmt::For {
+            start, end, body, ..
+        } => {
+            v.visit_expr(start);
+            v.visit_expr(end);
+            v.visit_stmt(body);
+        }
+        Stmt::Block { stmts, .. } => stmts.iter().for_each(|s| v.visit_stmt(s)),
+        Stmt::FnDef {
+            params,
+            return_type,
+            body,
+            ..
+        } => {
+            params.iter().for_each(|(_, t)| v.visit_type(t));
+            if let Some(return_type) = return_type {
+                v.visit_type(return_type);
+            }
+            v.visit_stmt(body);
+        }
+        Stmt::Empty { .. } => {}
+    }
+}
+
+/// Visits the children of an expression in source order.
+pub fn walk_expr<V: Visitor + ?Sized>(v: &mut V, expr: &Expr) {
+    match expr {
+        Expr::LiteralArray { elements, .. } => elements.iter().for_each(|e| v.visit_expr(e)),
+        Expr::BinaryOp { l, op, r, .. } => {
+            v.visit_expr(l);
+            v.visit_bin_op(op);
+            v.visit_expr(r);
+        }
+        Expr::UnaryOp { op, expr, .. } => {
+            v.visit_un_op(op);
+            v.visit_expr(expr);
+        }
+        Expr::Call { args, .. } => args.iter().for_each(|e| v.visit_expr(e)),
+        Expr::ArrayExpr { index, .. } => v.visit_expr(index),
+        Expr::Assign { expr, .. } => v.visit_expr(expr),
+        Expr::LiteralInt { .. }
+        | Expr::LiteralStr { .. }
+        | Expr::LiteralChar { .. }
+        | Expr::LiteralFloat { .. }
+        | Expr::LiteralBool { .. }
+        | Expr::Identifier { .. }
+        | Expr::Error { .. } => {}
+    }
+}
+
+/// Visits the children of a type.
+pub fn walk_type<V: Visitor + ?Sized>(v: &mut V, t: &Type) {
+    if let Type::Array { element_type, .. } = t {
+        v.visit_type(element_type);
+    }
+}
+
+/// The `VisitorMut` trait traverses syntax trees by mutable reference, so
+/// nodes can be changed in place. It works like [`Visitor`], with
+/// `walk_*_mut` functions.
+///
+/// # Examples
+///
+/// ```
+/// use lexer::Lexer;
+/// use parser::{
+///     ast::{visit, Expr, VisitorMut},
+///     Parser,
+/// };
+///
+/// /// Renames the variable `x` to `y`.
+/// struct Rename;
+///
+/// impl VisitorMut for Rename {
+///     fn visit_expr_mut(&mut self, expr: &mut Expr) {
+///         if let Expr::Identifier { id, .. } | Expr::Assign { id, .. } = expr {
+///             if id == "x" {
+///                 *id = "y".to_string();
+///             }
+///         }
+///         visit::walk_expr_mut(self, expr);
+///     }
+/// }
+///
+/// let mut p = Parser::new(Lexer::new("x = x + 1".as_bytes()));
+/// let (mut expr, _) = p.parse_expr().unwrap();
+///
+/// Rename.visit_expr_mut(&mut expr);
+/// assert_eq!(expr.to_string(), "y = (y + 1)");
+/// ```
+pub trait VisitorMut {
+    /// Visits a statement.
+    fn visit_stmt_mut(&mut self, stmt: &mut Stmt) {
+        walk_stmt_mut(self, stmt);
+    }
+
+    /// Visits an expression.
+    fn visit_expr_mut(&mut self, expr: &mut Expr) {
+        walk_expr_mut(self, expr);
+    }
+
+    /// Visits a type.
+    fn visit_type_mut(&mut self, t: &mut Type) {
+        walk_type_mut(self, t);
+    }
+
+    /// Visits a binary operator.
+    fn visit_bin_op_mut(&mut self, _op: &mut BinOp) {}
+
+    /// Visits a unary operator.
+    fn visit_un_op_mut(&mut self, _op: &mut UnOp) {}
+}
+
+/// Visits the children of a statement in source order.
+pub fn walk_stmt_mut<V: VisitorMut + ?Sized>(v: &mut V, stmt: &mut Stmt) {
+    match stmt {
+        Stmt::Let { var_type, expr, .. } => {
+            v.visit_type_mut(var_type);
+            if let Some(expr) = expr {
+                v.visit_expr_mut(expr);
+            }
+        }
+        Stmt::Expr { expr, .. } | Stmt::Return { expr, .. } => v.visit_expr_mut(expr),
+        Stmt::If {
+            cond,
+            then_stmt,
+            else_stmt,
+            ..
+        } => {
+            v.visit_expr_mut(cond);
+            v.visit_stmt_mut(then_stmt);
+            if let Some(else_stmt) = else_stmt {
+                v.visit_stmt_mut(else_stmt);
+            }
+        }
+        Stmt::While { cond, body, .. } => {
+            v.visit_expr_mut(cond);
+            v.visit_stmt_mut(body);
+        }
+        Stmt::For {
+            start, end, body, ..
+        } => {
+            v.visit_expr_mut(start);
+            v.visit_expr_mut(end);
+            v.visit_stmt_mut(body);
+        }
+        Stmt::Block { stmts, .. } => stmts.iter_mut().for_each(|s| v.visit_stmt_mut(s)),
+        Stmt::FnDef {
+            params,
+            return_type,
+            body,
+            ..
+        } => {
+            params.iter_mut().for_each(|(_, t)| v.visit_type_mut(t));
+            if let Some(return_type) = return_type {
+                v.visit_type_mut(return_type);
+            }
+            v.visit_stmt_mut(body);
+        }
+        Stmt::Empty { .. } => {}
+    }
+}
+
+/// Visits the children of an expression in source order.
+pub fn walk_expr_mut<V: VisitorMut + ?Sized>(v: &mut V, expr: &mut Expr) {
+    match expr {
+        Expr::LiteralArray { elements, .. } => {
+            elements.iter_mut().for_each(|e| v.visit_expr_mut(e))
+        }
+        Expr::BinaryOp { l, op, r, .. } => {
+            v.visit_expr_mut(l);
+            v.visit_bin_op_mut(op);
+            v.visit_expr_mut(r);
+        }
+        Expr::UnaryOp { op, expr, .. } => {
+            v.visit_un_op_mut(op);
+            v.visit_expr_mut(expr);
+        }
+        Expr::Call { args, .. } => args.iter_mut().for_each(|e| v.visit_expr_mut(e)),
+        Expr::ArrayExpr { index, .. } => v.visit_expr_mut(index),
+        Expr::Assign { expr, .. } => v.visit_expr_mut(expr),
+        Expr::LiteralInt { .. }
+        | Expr::LiteralStr { .. }
+        | Expr::LiteralChar { .. }
+        | Expr::LiteralFloat { .. }
+        | Expr::LiteralBool { .. }
+        | Expr::Identifier { .. }
+        | Expr::Error { .. } => {}
+    }
+}
+
+/// Visits the children of a type.
+pub fn walk_type_mut<V: VisitorMut + ?Sized>(v: &mut V, t: &mut Type) {
+    if let Type::Array { element_type, .. } = t {
+        v.visit_type_mut(element_type);
+    }
+}
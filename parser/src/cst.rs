@@ -0,0 +1,736 @@
+use std::{fmt, ops::Range, rc::Rc};
+
+use diagnostics::Diagnostic;
+use lexer::{
+    token::{Token, TokenType},
+    Lexer, TokenStream,
+};
+
+use crate::Parser;
+
+mod lower;
+
+pub use lower::{lower, lower_expr, lower_stmt};
+
+/// Kind of a node of the concrete syntax tree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NodeKind {
+    /// The whole file, including the trivia before the first and after
+    /// the last token.
+    Program,
+    /// Function definition: `fn add(a: int, b: int): int { ... }`
+    FnDef,
+    /// Parameters of a function definition, including the parentheses.
+    ParamList,
+    /// Function parameter: `a: int`
+    Param,
+    /// Type: `int`, `str[]`
+    Type,
+    /// Block statement: `{ ... }`
+    Block,
+    /// Let statement: `let x: int = 1;`
+    LetStmt,
+    /// Expression statement: `f(x);`
+    ExprStmt,
+    /// If statement, including the `else` branch.
+    IfStmt,
+    /// While statement.
+    WhileStmt,
+    /// For statement.
+    ForStmt,
+    /// Return statement.
+    ReturnStmt,
+    /// Empty statement: `;`
+    EmptyStmt,
+    /// Literal: `1`, `"text"`, `true`
+    Literal,
+    /// Array literal: `[1, 2, 3]`
+    ArrayLiteral,
+    /// Parenthesized expression: `(a + b)`
+    ParenExpr,
+    /// Binary expression: `a + b`
+    BinaryExpr,
+    /// Unary expression: `-a`, `not b`
+    UnaryExpr,
+    /// Variable reference: `a`
+    NameRef,
+    /// Function call: `f(a, b)`
+    CallExpr,
+    /// Arguments of a function call, including the parentheses.
+    ArgList,
+    /// Array element: `a[i]`
+    IndexExpr,
+    /// Assignment: `a = b`
+    AssignExpr,
+    /// Tokens which the parser skipped to recover from a syntax error.
+    Error,
+}
+
+impl NodeKind {
+    /// Checks whether nodes of this kind are statements.
+    pub fn is_stmt(self) -> bool {
+        matches!(
+            self,
+            NodeKind::FnDef
+                | NodeKind::Block
+                | NodeKind::LetStmt
+                | NodeKind::ExprStmt
+                | NodeKind::IfStmt
+                | NodeKind::WhileStmt
+                | NodeKind::ForStmt
+                | NodeKind::ReturnStmt
+                | NodeKind::EmptyStmt
+        )
+    }
+
+    /// Checks whether nodes of this kind are expressions.
+    pub fn is_expr(self) -> bool {
+        matches!(
+            self,
+            NodeKind::Literal
+                | NodeKind::ArrayLiteral
+                | NodeKind::ParenExpr
+                | NodeKind::BinaryExpr
+                | NodeKind::UnaryExpr
+                | NodeKind::NameRef
+                | NodeKind::CallExpr
+                | NodeKind::IndexExpr
+                | NodeKind::AssignExpr
+        )
+    }
+}
+
+/// A token of the green tree: its type and exact source text. Green
+/// elements do not know their position, so they can be shared between
+/// trees and reused after edits.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GreenToken {
+    kind: TokenType<()>,
+    text: String,
+}
+
+impl GreenToken {
+    /// Creates a new `GreenToken` of provided type and text.
+    pub fn new(kind: TokenType<()>, text: impl Into<String>) -> Self {
+        Self {
+            kind,
+            text: text.into(),
+        }
+    }
+
+    /// Returns the type of this token.
+    pub fn kind(&self) -> &TokenType<()> {
+        &self.kind
+    }
+
+    /// Returns the source text of this token.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Returns the length of this token in bytes.
+    pub fn len(&self) -> usize {
+        self.text.len()
+    }
+
+    /// Checks whether the text of this token is empty.
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty()
+    }
+}
+
+/// A node of the green tree: its kind and children, which cover all of
+/// its source text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GreenNode {
+    kind: NodeKind,
+    /// Length of the source text in bytes.
+    len: usize,
+    children: Vec<GreenElement>,
+}
+
+impl GreenNode {
+    /// Creates a new `GreenNode` of provided kind with provided children.
+    pub fn new(kind: NodeKind, children: Vec<GreenElement>) -> Self {
+        Self {
+            kind,
+            len: children.iter().map(GreenElement::len).sum(),
+            children,
+        }
+    }
+
+    /// Returns the kind of this node.
+    pub fn kind(&self) -> NodeKind {
+        self.kind
+    }
+
+    /// Returns the length of the source text of this node in bytes.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Checks whether the source text of this node is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the children of this node.
+    pub fn children(&self) -> &[GreenElement] {
+        &self.children
+    }
+
+    /// Returns a copy of this node where the child at `index` is replaced.
+    /// Other children are shared.
+    fn with_child(&self, index: usize, child: GreenElement) -> Self {
+        let mut children = self.children.clone();
+        children[index] = child;
+        Self::new(self.kind, children)
+    }
+}
+
+/// A child of a [`GreenNode`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GreenElement {
+    /// Inner node.
+    Node(Rc<GreenNode>),
+    /// Token.
+    Token(Rc<GreenToken>),
+}
+
+impl GreenElement {
+    /// Returns the length of the source text of this element in bytes.
+    pub fn len(&self) -> usize {
+        match self {
+            GreenElement::Node(node) => node.len(),
+            GreenElement::Token(token) => token.len(),
+        }
+    }
+
+    /// Checks whether the source text of this element is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl From<GreenNode> for GreenElement {
+    fn from(node: GreenNode) -> Self {
+        GreenElement::Node(Rc::new(node))
+    }
+}
+
+impl From<GreenToken> for GreenElement {
+    fn from(token: GreenToken) -> Self {
+        GreenElement::Token(Rc::new(token))
+    }
+}
+
+impl fmt::Display for GreenToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+impl fmt::Display for GreenNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.children
+            .iter()
+            .try_for_each(|child| write!(f, "{}", child))
+    }
+}
+
+impl fmt::Display for GreenElement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GreenElement::Node(node) => write!(f, "{}", node),
+            GreenElement::Token(token) => write!(f, "{}", token),
+        }
+    }
+}
+
+/// A node of the red tree: a [`GreenNode`] with its parent and position in
+/// the source text. Red nodes are created on demand while walking the tree,
+/// and are cheap to clone.
+#[derive(Clone)]
+pub struct SyntaxNode(Rc<NodeData>);
+
+struct NodeData {
+    green: Rc<GreenNode>,
+    parent: Option<SyntaxNode>,
+    /// Index of this node among the children of its parent.
+    index: usize,
+    /// Byte offset of this node in source code.
+    offset: usize,
+}
+
+/// A token of the red tree: a [`GreenToken`] with its parent and position
+/// in the source text.
+#[derive(Clone)]
+pub struct SyntaxToken {
+    green: Rc<GreenToken>,
+    parent: SyntaxNode,
+    /// Index of this token among the children of its parent.
+    index: usize,
+    /// Byte offset of this token in source code.
+    offset: usize,
+}
+
+/// A child of a [`SyntaxNode`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SyntaxElement {
+    /// Inner node.
+    Node(SyntaxNode),
+    /// Token.
+    Token(SyntaxToken),
+}
+
+impl SyntaxNode {
+    /// Creates the root of a red tree from a green node.
+    pub fn new_root(green: GreenNode) -> Self {
+        Self(Rc::new(NodeData {
+            green: Rc::new(green),
+            parent: None,
+            index: 0,
+            offset: 0,
+        }))
+    }
+
+    /// Returns the kind of this node.
+    pub fn kind(&self) -> NodeKind {
+        self.0.green.kind()
+    }
+
+    /// Returns the green node of this node.
+    pub fn green(&self) -> &GreenNode {
+        &self.0.green
+    }
+
+    /// Returns the byte range of this node in source code.
+    pub fn range(&self) -> Range<usize> {
+        self.0.offset..self.0.offset + self.0.green.len()
+    }
+
+    /// Returns the source text of this node.
+    pub fn text(&self) -> String {
+        self.0.green.to_string()
+    }
+
+    /// Returns the parent of this node, or `None` for the root.
+    pub fn parent(&self) -> Option<SyntaxNode> {
+        self.0.parent.clone()
+    }
+
+    /// Returns the root of the tree which contains this node.
+    pub fn root(&self) -> SyntaxNode {
+        let mut node = self.clone();
+        while let Some(parent) = node.parent() {
+            node = parent;
+        }
+        node
+    }
+
+    /// Returns the children (nodes and tokens) of this node.
+    pub fn children(&self) -> Vec<SyntaxElement> {
+        let mut offset = self.0.offset;
+
+        self.0
+            .green
+            .children()
+            .iter()
+            .enumerate()
+            .map(|(index, child)| {
+                let start = offset;
+                offset += child.len();
+
+                match child {
+                    GreenElement::Node(green) => {
+                        SyntaxElement::Node(SyntaxNode(Rc::new(NodeData {
+                            green: green.clone(),
+                            parent: Some(self.clone()),
+                            index,
+                            offset: start,
+                        })))
+                    }
+                    GreenElement::Token(green) => SyntaxElement::Token(SyntaxToken {
+                        green: green.clone(),
+                        parent: self.clone(),
+                        index,
+                        offset: start,
+                    }),
+                }
+            })
+            .collect()
+    }
+
+    /// Returns the child nodes of this node.
+    pub fn child_nodes(&self) -> Vec<SyntaxNode> {
+        self.children()
+            .into_iter()
+            .filter_map(|child| match child {
+                SyntaxElement::Node(node) => Some(node),
+                SyntaxElement::Token(_) => None,
+            })
+            .collect()
+    }
+
+    /// Returns the child tokens of this node (without the tokens of its
+    /// child nodes).
+    pub fn child_tokens(&self) -> Vec<SyntaxToken> {
+        self.children()
+            .into_iter()
+            .filter_map(|child| match child {
+                SyntaxElement::Node(_) => None,
+                SyntaxElement::Token(token) => Some(token),
+            })
+            .collect()
+    }
+
+    /// Returns this node and all nodes below it in source order.
+    pub fn descendants(&self) -> Vec<SyntaxNode> {
+        let mut nodes = vec![self.clone()];
+        for child in self.child_nodes() {
+            nodes.append(&mut child.descendants());
+        }
+        nodes
+    }
+
+    /// Returns all tokens of this node (including trivia) in source order.
+    pub fn tokens(&self) -> Vec<SyntaxToken> {
+        let mut tokens = Vec::new();
+        for child in self.children() {
+            match child {
+                SyntaxElement::Node(node) => tokens.append(&mut node.tokens()),
+                SyntaxElement::Token(token) => tokens.push(token),
+            }
+        }
+        tokens
+    }
+
+    /// Returns the green tree of the whole file where this node is replaced
+    /// by provided node. The rest of the tree is shared with the original
+    /// tree, so its text stays untouched.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use lexer::token::TokenType;
+    /// use parser::cst::{self, GreenNode, GreenToken, NodeKind, SyntaxNode};
+    ///
+    /// let source = "fn main() {\n    let x: int = 1; // one\n}\n";
+    /// let (root, _) = cst::parse(source);
+    ///
+    /// let literal = root.descendants().into_iter().find(|n| n.kind() == NodeKind::Literal).unwrap();
+    /// let two = GreenToken::new(TokenType::LiteralIntDec(()), "2");
+    /// let edited = SyntaxNode::new_root(literal.replace_with(GreenNode::new(NodeKind::Literal, vec![two.into()])));
+    ///
+    /// assert_eq!(edited.text(), "fn main() {\n    let x: int = 2; // one\n}\n");
+    /// ```
+    pub fn replace_with(&self, replacement: GreenNode) -> GreenNode {
+        match &self.0.parent {
+            Some(parent) => {
+                parent.replace_with(parent.green().with_child(self.0.index, replacement.into()))
+            }
+            None => replacement,
+        }
+    }
+
+    /// Returns an indented outline of the tree below this node, which shows
+    /// the kinds and ranges of nodes and tokens. Useful for debugging.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use parser::cst;
+    ///
+    /// let (root, _) = cst::parse("fn f() {}");
+    ///
+    /// assert_eq!(
+    ///     root.debug_tree(),
+    ///     "\
+    /// Program@0..9
+    ///   FnDef@0..9
+    ///     KwFn@0..2 \"fn\"
+    ///     Whitespace@2..3 \" \"
+    ///     Iden@3..4 \"f\"
+    ///     ParamList@4..6
+    ///       LPar@4..5 \"(\"
+    ///       RPar@5..6 \")\"
+    ///     Whitespace@6..7 \" \"
+    ///     Block@7..9
+    ///       LBrace@7..8 \"{\"
+    ///       RBrace@8..9 \"}\"
+    /// "
+    /// );
+    /// ```
+    pub fn debug_tree(&self) -> String {
+        let mut out = String::new();
+        self.write_tree(&mut out, 0);
+        out
+    }
+
+    /// Writes the outline of the tree below this node at provided depth.
+    fn write_tree(&self, out: &mut String, depth: usize) {
+        out.push_str(&format!("{}{:?}\n", "  ".repeat(depth), self));
+        for child in self.children() {
+            match child {
+                SyntaxElement::Node(node) => node.write_tree(out, depth + 1),
+                SyntaxElement::Token(token) => {
+                    out.push_str(&format!("{}{:?}\n", "  ".repeat(depth + 1), token))
+                }
+            }
+        }
+    }
+}
+
+impl SyntaxToken {
+    /// Returns the type of this token.
+    pub fn kind(&self) -> &TokenType<()> {
+        self.green.kind()
+    }
+
+    /// Returns the source text of this token.
+    pub fn text(&self) -> &str {
+        self.green.text()
+    }
+
+    /// Returns the green token of this token.
+    pub fn green(&self) -> &GreenToken {
+        &self.green
+    }
+
+    /// Returns the byte range of this token in source code.
+    pub fn range(&self) -> Range<usize> {
+        self.offset..self.offset + self.green.len()
+    }
+
+    /// Returns the node which contains this token.
+    pub fn parent(&self) -> SyntaxNode {
+        self.parent.clone()
+    }
+
+    /// Checks whether this token is trivia: whitespace, a comment, or an
+    /// invalid token which the parser skipped.
+    pub fn is_trivia(&self) -> bool {
+        self.kind().is_trivia() || matches!(self.kind(), TokenType::Invalid(()))
+    }
+
+    /// Returns the green tree of the whole file where this token is replaced
+    /// by provided token, like [`SyntaxNode::replace_with`].
+    pub fn replace_with(&self, replacement: GreenToken) -> GreenNode {
+        self.parent.replace_with(
+            self.parent
+                .green()
+                .with_child(self.index, replacement.into()),
+        )
+    }
+}
+
+impl PartialEq for SyntaxNode {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0.green, &other.0.green) && self.0.offset == other.0.offset
+    }
+}
+
+impl Eq for SyntaxNode {}
+
+impl PartialEq for SyntaxToken {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.green, &other.green) && self.offset == other.offset
+    }
+}
+
+impl Eq for SyntaxToken {}
+
+impl fmt::Display for SyntaxNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0.green)
+    }
+}
+
+impl fmt::Display for SyntaxToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.text())
+    }
+}
+
+impl fmt::Debug for SyntaxNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let range = self.range();
+        write!(f, "{:?}@{}..{}", self.kind(), range.start, range.end)
+    }
+}
+
+impl fmt::Debug for SyntaxToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = format!("{:?}", self.kind());
+        let range = self.range();
+        write!(
+            f,
+            "{}@{}..{} {:?}",
+            kind.trim_end_matches("(())"),
+            range.start,
+            range.end,
+            self.text()
+        )
+    }
+}
+
+/// Builds a green tree from the tokens which the parser consumes and the
+/// nodes which it starts and finishes.
+#[derive(Debug, Default)]
+pub(crate) struct Builder {
+    /// Kinds of the unfinished nodes and the indices of their first children
+    /// in `children`.
+    parents: Vec<(NodeKind, usize)>,
+    /// Finished children of the unfinished nodes.
+    children: Vec<GreenElement>,
+}
+
+impl Builder {
+    /// Adds a token to the current node. Empty tokens (end of file) are
+    /// skipped, since they take no space in the source.
+    pub(crate) fn token(&mut self, token: &Token) {
+        let mut text = None;
+        let kind = token.token_type.clone().map(|s| text = Some(s));
+        let text = text.unwrap_or_else(|| token.token_type.to_string());
+
+        if kind != TokenType::EOF && !text.is_empty() {
+            self.children.push(GreenToken::new(kind, text).into());
+        }
+    }
+
+    /// Returns a position from which a node can be started later, wrapping
+    /// the children which are added in the meantime.
+    pub(crate) fn checkpoint(&self) -> usize {
+        self.children.len()
+    }
+
+    /// Starts a node of provided kind after the current children.
+    pub(crate) fn start_node(&mut self, kind: NodeKind) {
+        self.parents.push((kind, self.children.len()));
+    }
+
+    /// Starts a node of provided kind at a checkpoint.
+    pub(crate) fn start_node_at(&mut self, checkpoint: usize, kind: NodeKind) {
+        self.parents.push((kind, checkpoint));
+    }
+
+    /// Finishes the last started node.
+    pub(crate) fn finish_node(&mut self) {
+        if let Some((kind, first)) = self.parents.pop() {
+            let children = self.children.split_off(first);
+            self.children.push(GreenNode::new(kind, children).into());
+        }
+    }
+
+    /// Finishes the root node and returns it.
+    pub(crate) fn finish(mut self) -> GreenNode {
+        while self.parents.len() > 1 {
+            self.finish_node();
+        }
+
+        match self.parents.pop() {
+            Some((kind, first)) => GreenNode::new(kind, self.children.split_off(first)),
+            None => GreenNode::new(NodeKind::Program, self.children),
+        }
+    }
+}
+
+/// Parses Kolang code into a lossless concrete syntax tree, which keeps
+/// all whitespace, comments and erroneous code, so its text is always
+/// equal to the source. Syntax errors are reported like in
+/// [`Parser::parse`], and [`lower`] derives the abstract syntax tree from
+/// the returned tree.
+///
+/// # Examples
+///
+/// ```
+/// use parser::cst::{self, NodeKind};
+///
+/// let source = "// add\nfn add(a: int, b: int): int { return a+b; }\n";
+/// let (root, diagnostics) = cst::parse(source);
+///
+/// assert!(diagnostics.is_empty());
+/// assert_eq!(root.text(), source);
+/// assert_eq!(root.child_nodes()[0].kind(), NodeKind::FnDef);
+/// assert_eq!(cst::lower(&root).len(), 1);
+/// ```
+pub fn parse(source: &str) -> (SyntaxNode, Vec<Diagnostic>) {
+    let lexer = Lexer::new(source.as_bytes()).with_whitespace(true);
+    let mut p = Parser::with_stream(TokenStream::new(lexer));
+    p.cst = Some(Builder::default());
+
+    p.start_node(NodeKind::Program);
+    // Reading from memory can not fail.
+    let _ = p.next().and_then(|()| p.prog());
+
+    let mut builder = p.cst.take().unwrap_or_default();
+    for token in p.trivia.drain(..) {
+        builder.token(&token);
+    }
+    let mut root = builder.finish();
+
+    if root.len() < source.len() {
+        // The lexer stops at a null character, keep the rest as is.
+        let rest = &source[root.len()..];
+        root.children
+            .push(GreenToken::new(TokenType::Invalid(()), rest).into());
+        root = GreenNode::new(root.kind, root.children);
+    }
+
+    (
+        SyntaxNode::new_root(root),
+        std::mem::take(&mut p.diagnostics),
+    )
+}
+
+impl<R: std::io::Read> Parser<R> {
+    /// Starts a node of the concrete syntax tree (if the parser builds one)
+    /// before the current token.
+    pub(crate) fn start_node(&mut self, kind: NodeKind) {
+        if let Some(builder) = &mut self.cst {
+            for token in self.trivia.drain(..) {
+                builder.token(&token);
+            }
+            builder.start_node(kind);
+        }
+    }
+
+    /// Returns a checkpoint before the current token, where a node of the
+    /// concrete syntax tree can be started later.
+    pub(crate) fn checkpoint(&mut self) -> usize {
+        match &mut self.cst {
+            Some(builder) => {
+                for token in self.trivia.drain(..) {
+                    builder.token(&token);
+                }
+                builder.checkpoint()
+            }
+            None => 0,
+        }
+    }
+
+    /// Starts a node of the concrete syntax tree at a checkpoint.
+    pub(crate) fn start_node_at(&mut self, checkpoint: usize, kind: NodeKind) {
+        if let Some(builder) = &mut self.cst {
+            builder.start_node_at(checkpoint, kind);
+        }
+    }
+
+    /// Finishes the last started node of the concrete syntax tree.
+    pub(crate) fn finish_node(&mut self) {
+        if let Some(builder) = &mut self.cst {
+            builder.finish_node();
+        }
+    }
+
+    /// Adds the consumed current token and the trivia before it to the
+    /// concrete syntax tree.
+    pub(crate) fn record_current(&mut self) {
+        if let Some(builder) = &mut self.cst {
+            for token in self.trivia.drain(..) {
+                builder.token(&token);
+            }
+            builder.token(&self.current);
+        }
+    }
+}
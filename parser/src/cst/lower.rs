@@ -0,0 +1,384 @@
+use std::ops::Range;
+
+use diagnostics::Span;
+use lexer::token::TokenType;
+
+use super::{NodeKind, SyntaxNode, SyntaxToken};
+use crate::{ast, syntax};
+
+/// Derives the abstract syntax tree of a file from its concrete syntax tree.
+/// For code without syntax errors, the result is equal to the result of
+/// [`Parser::parse`](crate::Parser::parse), including the spans. Erroneous
+/// code is lowered as far as possible, with [`ast::Expr::Error`] and
+/// [`ast::Type::Error`] in place of missing parts.
+///
+/// # Examples
+///
+/// ```
+/// use lexer::Lexer;
+/// use parser::{cst, Parser};
+///
+/// let source = "fn main() {\n    let x: int = -(1 + 2); // three\n}\n";
+/// let (root, _) = cst::parse(source);
+/// let (ast, _) = Parser::new(Lexer::new(source.as_bytes())).parse().unwrap();
+///
+/// assert!(cst::lower(&root) == ast);
+/// ```
+pub fn lower(root: &SyntaxNode) -> Vec<ast::Stmt> {
+    let lowerer = Lowerer::new(root);
+    root.child_nodes()
+        .iter()
+        .filter_map(|node| lowerer.stmt(node))
+        .collect()
+}
+
+/// Derives the abstract syntax tree of a statement node (including function
+/// definitions). Returns `None` if the node is not a statement.
+pub fn lower_stmt(node: &SyntaxNode) -> Option<ast::Stmt> {
+    Lowerer::new(node).stmt(node)
+}
+
+/// Derives the abstract syntax tree of an expression node. Returns `None` if
+/// the node is not an expression.
+pub fn lower_expr(node: &SyntaxNode) -> Option<ast::Expr> {
+    Lowerer::new(node).expr(node)
+}
+
+/// Converts concrete syntax tree nodes of a file to abstract syntax tree
+/// nodes.
+struct Lowerer {
+    /// Source code of the whole file.
+    text: String,
+    /// Byte offsets of the starts of lines.
+    lines: Vec<usize>,
+}
+
+impl Lowerer {
+    /// Creates a new `Lowerer` for the file which contains provided node.
+    fn new(node: &SyntaxNode) -> Self {
+        let text = node.root().text();
+        let lines = std::iter::once(0)
+            .chain(text.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+
+        Self { text, lines }
+    }
+
+    /// Returns the line and column (in characters) of a byte offset.
+    fn position(&self, offset: usize) -> (usize, usize) {
+        let line = self.lines.partition_point(|&start| start <= offset) - 1;
+        let column = self.text[self.lines[line]..offset].chars().count() + 1;
+
+        (line + 1, column)
+    }
+
+    /// Returns the span of a byte range.
+    fn span(&self, range: Range<usize>) -> Span {
+        let (line, column) = self.position(range.start);
+        let (end_line, end_column) = self.position(range.end);
+
+        Span {
+            start: range.start,
+            end: range.end,
+            line,
+            column,
+            end_line,
+            end_column,
+        }
+    }
+
+    /// Returns an empty span at the end of a node, for missing parts.
+    fn missing(&self, node: &SyntaxNode) -> Span {
+        let end = node.range().end;
+        self.span(end..end)
+    }
+
+    /// Lowers a statement node.
+    fn stmt(&self, node: &SyntaxNode) -> Option<ast::Stmt> {
+        let span = self.span(node.range());
+
+        let s = match node.kind() {
+            NodeKind::FnDef => {
+                let params = match child(node, NodeKind::ParamList) {
+                    Some(list) => list
+                        .child_nodes()
+                        .iter()
+                        .filter(|param| param.kind() == NodeKind::Param)
+                        .map(|param| (iden(param), self.type_of(param)))
+                        .collect(),
+                    None => Vec::new(),
+                };
+
+                ast::Stmt::FnDef {
+                    id: iden(node),
+                    params,
+                    return_type: child(node, NodeKind::Type).map(|t| self.ty(&t)),
+                    body: Box::new(self.nth_stmt(node, 0)),
+                    span,
+                }
+            }
+            NodeKind::Block => ast::Stmt::Block {
+                stmts: node
+                    .child_nodes()
+                    .iter()
+                    .filter_map(|s| self.stmt(s))
+                    .collect(),
+                span,
+            },
+            NodeKind::LetStmt => ast::Stmt::Let {
+                id: iden(node),
+                var_type: self.type_of(node),
+                expr: find_token(node, |t| *t == TokenType::Assign).map(|_| self.nth_expr(node, 0)),
+                span,
+            },
+            NodeKind::ExprStmt => ast::Stmt::Expr {
+                expr: self.nth_expr(node, 0),
+                span,
+            },
+            NodeKind::IfStmt => ast::Stmt::If {
+                cond: self.nth_expr(node, 0),
+                then_stmt: Box::new(self.nth_stmt(node, 0)),
+                else_stmt: find_token(node, |t| *t == TokenType::KwElse)
+                    .map(|_| Box::new(self.nth_stmt(node, 1))),
+                span,
+            },
+            NodeKind::WhileStmt => ast::Stmt::While {
+                cond: self.nth_expr(node, 0),
+                body: Box::new(self.nth_stmt(node, 0)),
+                span,
+            },
+            NodeKind::ForStmt => ast::Stmt::For {
+                id: iden(node),
+                start: self.nth_expr(node, 0),
+                end: self.nth_expr(node, 1),
+                body: Box::new(self.nth_stmt(node, 0)),
+                span,
+            },
+            NodeKind::ReturnStmt => ast::Stmt::Return {
+                expr: self.nth_expr(node, 0),
+                span,
+            },
+            NodeKind::EmptyStmt => ast::Stmt::Empty { span },
+            _ => return None,
+        };
+
+        Some(s)
+    }
+
+    /// Lowers an expression node.
+    fn expr(&self, node: &SyntaxNode) -> Option<ast::Expr> {
+        let span = self.span(node.range());
+
+        let e = match node.kind() {
+            NodeKind::Literal => {
+                let literal = find_token(node, |_| true).and_then(|token| {
+                    let token_type = token.kind().clone().map(|()| token.text().to_string());
+                    syntax::literal(&token_type, span)
+                });
+
+                match literal {
+                    Some(Ok(expr)) => expr,
+                    _ => ast::Expr::Error { span },
+                }
+            }
+            NodeKind::ArrayLiteral => ast::Expr::LiteralArray {
+                elements: self.exprs(node),
+                span,
+            },
+            NodeKind::ParenExpr => self.nth_expr(node, 0),
+            NodeKind::BinaryExpr => {
+                let op = find_token(node, |_| true).and_then(|op| {
+                    let span = self.span(op.range());
+                    let op = match op.kind() {
+                        TokenType::KwOr => ast::BinOp::LogOr { span },
+                        TokenType::KwAnd => ast::BinOp::LogAnd { span },
+                        TokenType::Eq => ast::BinOp::Eq { span },
+                        TokenType::NEq => ast::BinOp::NEq { span },
+                        TokenType::LT => ast::BinOp::LT { span },
+                        TokenType::GT => ast::BinOp::GT { span },
+                        TokenType::LEq => ast::BinOp::LEq { span },
+                        TokenType::GEq => ast::BinOp::GEq { span },
+                        TokenType::Pipe => ast::BinOp::BitOr { span },
+                        TokenType::Amp => ast::BinOp::BitAnd { span },
+                        TokenType::Plus => ast::BinOp::Add { span },
+                        TokenType::Minus => ast::BinOp::Sub { span },
+                        TokenType::Asterisk => ast::BinOp::Mul { span },
+                        TokenType::Slash => ast::BinOp::Div { span },
+                        TokenType::Percent => ast::BinOp::Mod { span },
+                        _ => return None,
+                    };
+                    Some((op, span))
+                });
+
+                match op {
+                    // Operands are on both sides of the operator, either may be missing.
+                    Some((op, op_span)) => ast::Expr::BinaryOp {
+                        l: Box::new(self.expr_in(node, span.start..op_span.start)),
+                        op,
+                        r: Box::new(self.expr_in(node, op_span.end..span.end)),
+                        span,
+                    },
+                    None => ast::Expr::Error { span },
+                }
+            }
+            NodeKind::UnaryExpr => {
+                let op = find_token(node, |_| true).and_then(|op| {
+                    let span = self.span(op.range());
+                    match op.kind() {
+                        TokenType::Minus => Some(ast::UnOp::Neg { span }),
+                        TokenType::KwNot => Some(ast::UnOp::LogNot { span }),
+                        TokenType::Tilde => Some(ast::UnOp::BitNot { span }),
+                        _ => None,
+                    }
+                });
+
+                match op {
+                    Some(op) => ast::Expr::UnaryOp {
+                        op,
+                        expr: Box::new(self.nth_expr(node, 0)),
+                        span,
+                    },
+                    // Unary plus does not change the value.
+                    None => self.nth_expr(node, 0),
+                }
+            }
+            NodeKind::NameRef => ast::Expr::Identifier {
+                id: iden(node),
+                span,
+            },
+            NodeKind::CallExpr => ast::Expr::Call {
+                id: iden(node),
+                args: match child(node, NodeKind::ArgList) {
+                    Some(list) => self.exprs(&list),
+                    None => Vec::new(),
+                },
+                span,
+            },
+            NodeKind::IndexExpr => ast::Expr::ArrayExpr {
+                id: iden(node),
+                index: Box::new(self.nth_expr(node, 0)),
+                span,
+            },
+            NodeKind::AssignExpr => ast::Expr::Assign {
+                id: iden(node),
+                expr: Box::new(self.nth_expr(node, 0)),
+                span,
+            },
+            _ => return None,
+        };
+
+        Some(e)
+    }
+
+    /// Lowers a type node.
+    fn ty(&self, node: &SyntaxNode) -> ast::Type {
+        let tokens: Vec<_> = node
+            .child_tokens()
+            .into_iter()
+            .filter(|t| !t.is_trivia())
+            .collect();
+
+        let t = match tokens.first() {
+            Some(token) => {
+                let span = self.span(token.range());
+                match token.kind() {
+                    TokenType::KwInt => ast::Type::Int { span },
+                    TokenType::KwFloat => ast::Type::Float { span },
+                    TokenType::KwChar => ast::Type::Char { span },
+                    TokenType::KwStr => ast::Type::Str { span },
+                    TokenType::KwBool => ast::Type::Bool { span },
+                    _ => ast::Type::Error { span },
+                }
+            }
+            None => ast::Type::Error {
+                span: self.missing(node),
+            },
+        };
+
+        if tokens.iter().any(|t| *t.kind() == TokenType::LBracket) {
+            ast::Type::Array {
+                element_type: Box::new(t),
+                span: self.span(node.range()),
+            }
+        } else {
+            t
+        }
+    }
+
+    /// Lowers the type node of a parameter or a let statement.
+    fn type_of(&self, node: &SyntaxNode) -> ast::Type {
+        match child(node, NodeKind::Type) {
+            Some(t) => self.ty(&t),
+            None => ast::Type::Error {
+                span: self.missing(node),
+            },
+        }
+    }
+
+    /// Lowers the expression children of a node.
+    fn exprs(&self, node: &SyntaxNode) -> Vec<ast::Expr> {
+        node.child_nodes()
+            .iter()
+            .filter_map(|e| self.expr(e))
+            .collect()
+    }
+
+    /// Lowers the `n`th expression child of a node (zero-based), or returns
+    /// an error expression if it is missing.
+    fn nth_expr(&self, node: &SyntaxNode, n: usize) -> ast::Expr {
+        node.child_nodes()
+            .iter()
+            .filter(|e| e.kind().is_expr())
+            .nth(n)
+            .and_then(|e| self.expr(e))
+            .unwrap_or_else(|| ast::Expr::Error {
+                span: self.missing(node),
+            })
+    }
+
+    /// Lowers the first expression child of a node within a byte range,
+    /// or returns an error expression if it is missing.
+    fn expr_in(&self, node: &SyntaxNode, range: Range<usize>) -> ast::Expr {
+        node.child_nodes()
+            .iter()
+            .filter(|e| e.kind().is_expr())
+            .find(|e| range.start <= e.range().start && e.range().end <= range.end)
+            .and_then(|e| self.expr(e))
+            .unwrap_or_else(|| ast::Expr::Error {
+                span: self.span(range.end..range.end),
+            })
+    }
+
+    /// Lowers the `n`th statement child of a node (zero-based), or returns
+    /// an empty statement if it is missing.
+    fn nth_stmt(&self, node: &SyntaxNode, n: usize) -> ast::Stmt {
+        node.child_nodes()
+            .iter()
+            .filter(|s| s.kind().is_stmt())
+            .nth(n)
+            .and_then(|s| self.stmt(s))
+            .unwrap_or_else(|| ast::Stmt::Empty {
+                span: self.missing(node),
+            })
+    }
+}
+
+/// Returns the first child node of provided kind.
+fn child(node: &SyntaxNode, kind: NodeKind) -> Option<SyntaxNode> {
+    node.child_nodes().into_iter().find(|n| n.kind() == kind)
+}
+
+/// Returns the first child token (excluding trivia) which satisfies
+/// the predicate.
+fn find_token(node: &SyntaxNode, f: impl Fn(&TokenType<()>) -> bool) -> Option<SyntaxToken> {
+    node.child_tokens()
+        .into_iter()
+        .find(|t| !t.is_trivia() && f(t.kind()))
+}
+
+/// Returns the identifier among the child tokens of a node, or an empty
+/// string if it is missing.
+fn iden(node: &SyntaxNode) -> String {
+    find_token(node, |t| matches!(t, TokenType::Iden(())))
+        .map_or(String::new(), |t| t.text().to_string())
+}
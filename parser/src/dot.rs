@@ -0,0 +1,330 @@
+use std::fmt::Write;
+
+use diagnostics::Span;
+
+use crate::ast::{visit, Expr, Stmt, Visitor};
+
+/// Exports a program to a Graphviz DOT graph of its syntax tree. Statements
+/// are boxes and expressions are ellipses, labelled by their variant, the
+/// name, operator or value they store and their position in code.
+///
+/// # Examples
+///
+/// ```
+/// use lexer::Lexer;
+/// use parser::{dot, Parser};
+///
+/// let source = "fn main() { println(-1); }".as_bytes();
+/// let (ast, _) = Parser::new(Lexer::new(source)).parse().unwrap();
+///
+/// let dot = dot::syntax_tree(&ast);
+/// assert!(dot.starts_with("digraph ast {\n"));
+/// assert!(dot.contains("n1 [label=\"FnDef main\\n1:1\", shape=box];\n    n0 -> n1;\n"));
+/// assert!(dot.contains("n5 [label=\"UnaryOp -\\n1:21\", shape=ellipse];\n    n4 -> n5;\n"));
+/// ```
+pub fn syntax_tree(ast: &[Stmt]) -> String {
+    let mut w = TreeWriter {
+        out: String::from("digraph ast {\n    node [fontname=\"monospace\"];\n"),
+        parents: Vec::new(),
+        count: 0,
+    };
+    w.node("Program", None, "box");
+    ast.iter().for_each(|s| w.visit_stmt(s));
+    w.out.push_str("}\n");
+    w.out
+}
+
+/// Writes the nodes of a syntax tree and the edges from their parents.
+struct TreeWriter {
+    out: String,
+    /// Identifiers of the nodes which contain the visited node.
+    parents: Vec<usize>,
+    /// Number of written nodes.
+    count: usize,
+}
+
+impl TreeWriter {
+    /// Writes a node, and makes it the parent of the next nodes.
+    fn node(&mut self, label: &str, span: Option<Span>, shape: &str) {
+        let id = self.count;
+        self.count += 1;
+
+        let mut label = escape(label);
+        if let Some(span) = span {
+            write!(label, "\\n{}:{}", span.line, span.column).unwrap();
+        }
+        writeln!(
+            self.out,
+            "    n{} [label=\"{}\", shape={}];",
+            id, label, shape
+        )
+        .unwrap();
+        if let Some(parent) = self.parents.last() {
+            writeln!(self.out, "    n{} -> n{};", parent, id).unwrap();
+        }
+        self.parents.push(id);
+    }
+}
+
+impl Visitor for TreeWriter {
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        let label = match stmt {
+            Stmt::Let { id, var_type, .. } => format!("Let {}: {}", id, var_type),
+            Stmt::Expr { .. } => "Expr".to_string(),
+            Stmt::If { .. } => "If".to_string(),
+            Stmt::While { .. } => "While".to_string(),
+            Stmt::For { id, .. } => format!("For {}", id),
+            Stmt::Return { .. } => "Return".to_string(),
+            Stmt::Block { .. } => "Block".to_string(),
+            Stmt::FnDef { id, .. } => format!("FnDef {}", id),
+            Stmt::Empty { .. } => "Empty".to_string(),
+        };
+        self.node(&label, Some(stmt.span()), "box");
+        visit::walk_stmt(self, stmt);
+        self.parents.pop();
+    }
+
+    fn visit_expr(&mut self, expr: &Expr) {
+        let label = match expr {
+            Expr::LiteralInt { value, .. } => format!("LiteralInt {}", value),
+            Expr::LiteralStr { value, .. } => format!("LiteralStr {:?}", value),
+            Expr::LiteralChar { value, .. } => format!("LiteralChar {:?}", value),
+            Expr::LiteralFloat { value, .. } => format!("LiteralFloat {:?}", value),
+            Expr::LiteralBool { value, .. } => format!("LiteralBool {}", value),
+            Expr::LiteralArray { .. } => "LiteralArray".to_string(),
+            Expr::BinaryOp { op, .. } => format!("BinaryOp {}", op),
+            Expr::UnaryOp { op, .. } => format!("UnaryOp {}", op),
+            Expr::Identifier { id, .. } => format!("Identifier {}", id),
+            Expr::Call { id, .. } => format!("Call {}", id),
+            Expr::ArrayExpr { id, .. } => format!("ArrayExpr {}", id),
+            Expr::Assign { id, .. } => format!("Assign {}", id),
+            Expr::Error { .. } => "Error".to_string(),
+        };
+        self.node(&label, Some(expr.span()), "ellipse");
+        visit::walk_expr(self, expr);
+        self.parents.pop();
+    }
+}
+
+/// Exports the functions of a program to Graphviz DOT control flow graphs,
+/// one cluster per function. Nodes are basic blocks: straight-line
+/// statements, each prefixed with its line, which end at `if`, `while`,
+/// `for` and `return`. Branches are labelled `true`/`false`, and the edges
+/// of `for` loops `loop`/`done`. Code after `return` is in blocks without
+/// incoming edges.
+///
+/// # Examples
+///
+/// ```
+/// use lexer::Lexer;
+/// use parser::{dot, Parser};
+///
+/// let source = "fn f(x: int): int {\n    if x > 0 {\n        return x;\n    }\n    return 0;\n}";
+/// let (ast, _) = Parser::new(Lexer::new(source.as_bytes())).parse().unwrap();
+///
+/// let dot = dot::control_flow(&ast);
+/// assert!(dot.contains("f0_b2 [label=\"2: if (x > 0)\\l\"];\n"));
+/// assert!(dot.contains("f0_b2 -> f0_b3 [label=\"true\"];\n"));
+/// assert!(dot.contains("f0_b2 -> f0_b4 [label=\"false\"];\n"));
+/// assert!(dot.contains("f0_b3 -> f0_b1;\n"));
+/// ```
+pub fn control_flow(ast: &[Stmt]) -> String {
+    let mut out = String::from("digraph cfg {\n    node [shape=box, fontname=\"monospace\"];\n");
+    let functions = ast.iter().filter_map(|stmt| match stmt {
+        Stmt::FnDef {
+            id,
+            params,
+            return_type,
+            body,
+            ..
+        } => Some((id, params, return_type, body)),
+        _ => None,
+    });
+
+    for (i, (id, params, return_type, body)) in functions.enumerate() {
+        let mut signature = format!("fn {}(", id);
+        for (j, (name, t)) in params.iter().enumerate() {
+            if j > 0 {
+                signature.push_str(", ");
+            }
+            write!(signature, "{}: {}", name, t).unwrap();
+        }
+        signature.push(')');
+        if let Some(t) = return_type {
+            write!(signature, ": {}", t).unwrap();
+        }
+
+        let mut g = Cfg::new();
+        g.stmt(body);
+        let exits = g.exits();
+        g.connect(exits, EXIT);
+
+        writeln!(out, "    subgraph cluster_{} {{", i).unwrap();
+        writeln!(out, "        label=\"{}\";", escape(&signature)).unwrap();
+        writeln!(
+            out,
+            "        f{}_b{} [label=\"entry\", shape=oval];",
+            i, ENTRY
+        )
+        .unwrap();
+        writeln!(
+            out,
+            "        f{}_b{} [label=\"exit\", shape=oval];",
+            i, EXIT
+        )
+        .unwrap();
+        for (b, lines) in g.blocks.iter().enumerate().skip(2) {
+            let label: String = lines.iter().map(|l| escape(l) + "\\l").collect();
+            writeln!(out, "        f{}_b{} [label=\"{}\"];", i, b, label).unwrap();
+        }
+        for (from, to, label) in &g.edges {
+            write!(out, "        f{}_b{} -> f{}_b{}", i, from, i, to).unwrap();
+            match label {
+                Some(label) => writeln!(out, " [label=\"{}\"];", label).unwrap(),
+                None => writeln!(out, ";").unwrap(),
+            }
+        }
+        writeln!(out, "    }}").unwrap();
+    }
+
+    out.push_str("}\n");
+    out
+}
+
+/// Block where a function starts.
+const ENTRY: usize = 0;
+/// Block where a function returns.
+const EXIT: usize = 1;
+
+/// Edge which leaves a block, and its label.
+type Exit = (usize, Option<&'static str>);
+
+/// Control flow graph of a function, built statement by statement.
+struct Cfg {
+    /// Lines of the basic blocks. The first two blocks are the entry and
+    /// the exit, which have no lines.
+    blocks: Vec<Vec<String>>,
+    /// Edges between blocks, with their labels.
+    edges: Vec<(usize, usize, Option<&'static str>)>,
+    /// Block which the next straight-line statement is added to.
+    current: Option<usize>,
+    /// Edges into the next block, if there is no current block.
+    pending: Vec<Exit>,
+}
+
+impl Cfg {
+    fn new() -> Self {
+        Self {
+            blocks: vec![Vec::new(), Vec::new()],
+            edges: Vec::new(),
+            current: None,
+            pending: vec![(ENTRY, None)],
+        }
+    }
+
+    /// Adds the statement and the statements in it to the graph.
+    fn stmt(&mut self, stmt: &Stmt) {
+        let line = stmt.span().line;
+        match stmt {
+            Stmt::Let { .. } | Stmt::Expr { .. } => {
+                self.push(format!("{}: {}", line, stmt));
+            }
+            Stmt::Return { .. } => {
+                let b = self.push(format!("{}: {}", line, stmt));
+                self.connect(vec![(b, None)], EXIT);
+                self.current = None;
+            }
+            Stmt::If {
+                cond,
+                then_stmt,
+                else_stmt,
+                ..
+            } => {
+                let b = self.push(format!("{}: if {}", line, cond));
+                self.branch(b, "true");
+                self.stmt(then_stmt);
+                let mut exits = self.exits();
+                self.branch(b, "false");
+                if let Some(else_stmt) = else_stmt {
+                    self.stmt(else_stmt);
+                }
+                exits.extend(self.exits());
+                self.pending = exits;
+            }
+            Stmt::While { cond, body, .. } => {
+                self.pending = self.exits();
+                let b = self.push(format!("{}: while {}", line, cond));
+                self.loop_body(b, body, ("true", "false"));
+            }
+            Stmt::For {
+                id,
+                start,
+                end,
+                body,
+                ..
+            } => {
+                self.pending = self.exits();
+                let b = self.push(format!("{}: for {} = {} to {}", line, id, start, end));
+                self.loop_body(b, body, ("loop", "done"));
+            }
+            Stmt::Block { stmts, .. } => stmts.iter().for_each(|s| self.stmt(s)),
+            Stmt::FnDef { .. } | Stmt::Empty { .. } => {}
+        }
+    }
+
+    /// Adds the body of a loop whose condition is in block `b`, and
+    /// continues after the loop.
+    fn loop_body(&mut self, b: usize, body: &Stmt, (enter, leave): (&'static str, &'static str)) {
+        self.branch(b, enter);
+        self.stmt(body);
+        let exits = self.exits();
+        self.connect(exits, b);
+        self.branch(b, leave);
+    }
+
+    /// Adds a line to the current block, and returns the block. Starts
+    /// a new block if there is no current block.
+    fn push(&mut self, line: String) -> usize {
+        let b = match self.current {
+            Some(b) => b,
+            None => {
+                let b = self.blocks.len();
+                self.blocks.push(Vec::new());
+                let pending = std::mem::take(&mut self.pending);
+                self.connect(pending, b);
+                self.current = Some(b);
+                b
+            }
+        };
+        self.blocks[b].push(line);
+        b
+    }
+
+    /// Ends the current block, so the next block is entered from block `b`
+    /// through an edge with the label.
+    fn branch(&mut self, b: usize, label: &'static str) {
+        self.current = None;
+        self.pending = vec![(b, Some(label))];
+    }
+
+    /// Ends the current block, and returns the edges which leave the code
+    /// added so far.
+    fn exits(&mut self) -> Vec<Exit> {
+        match self.current.take() {
+            Some(b) => vec![(b, None)],
+            None => std::mem::take(&mut self.pending),
+        }
+    }
+
+    fn connect(&mut self, exits: Vec<Exit>, to: usize) {
+        self.edges
+            .extend(exits.into_iter().map(|(from, label)| (from, to, label)));
+    }
+}
+
+/// Escapes a string for a quoted DOT label.
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
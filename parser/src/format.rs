@@ -0,0 +1,469 @@
+use diagnostics::{Diagnostic, Span};
+use lexer::{token::TokenType, Lexer};
+
+use crate::{ast, Parser};
+
+/// Width which formatted lines are kept to by wrapping long argument lists
+/// and array literals, one element per line.
+pub const MAX_WIDTH: usize = 100;
+
+const INDENT: &str = "    ";
+
+/// Formats Kolang code in the canonical style: one statement per line,
+/// four spaces of indentation, single spaces around binary operators and
+/// only the parentheses which the precedence of operators requires.
+///
+/// Comments and blank lines between statements are kept (several blank
+/// lines become one). A comment on the line where a statement ends stays
+/// after the statement; comments inside a statement (e.g. between function
+/// arguments) are moved before it. Functions are separated by blank lines.
+/// Literals are written as they are in the code, e.g. `0xff` stays in
+/// hexadecimal. Formatting formatted code does not change it.
+///
+/// # Errors
+/// Returns the syntax errors of the code, which is not formatted if it has
+/// any.
+///
+/// # Examples
+///
+/// ```
+/// use parser::format::format;
+///
+/// let code = "fn  main( ) {  // Entry point\n  let x:int=(1+2)*3;\n\n\n  println( x ) ;}";
+/// assert_eq!(
+///     format(code).unwrap(),
+///     "fn main() {\n    // Entry point\n    let x: int = (1 + 2) * 3;\n\n    println(x);\n}\n"
+/// );
+///
+/// assert_eq!(format("fn main() { let x = 1; }").unwrap_err().len(), 1);
+/// ```
+pub fn format(source: &str) -> Result<String, Vec<Diagnostic>> {
+    let (ast, diagnostics) = Parser::new(Lexer::new(source.as_bytes()))
+        .parse()
+        .expect("Reading code from memory cannot fail");
+    if !diagnostics.is_empty() {
+        return Err(diagnostics);
+    }
+
+    let comments = Lexer::new(source.as_bytes())
+        .flatten()
+        .filter_map(|tok| match tok.token_type {
+            TokenType::LC(text) | TokenType::BC(text) => Some(Comment {
+                text: text.trim_end().to_string(),
+                span: tok.span,
+                written: false,
+            }),
+            _ => None,
+        })
+        .collect();
+
+    let mut f = Formatter {
+        source,
+        blank_lines: source.lines().map(|l| l.trim().is_empty()).collect(),
+        comments,
+        out: String::new(),
+        indent: 0,
+        last_line: None,
+    };
+    f.items(&ast);
+    Ok(f.out)
+}
+
+/// A comment of the formatted code.
+struct Comment {
+    /// Text of the comment, including `//` or `/* */`.
+    text: String,
+    /// Source range of the comment.
+    span: Span,
+    /// Whether the comment is already written to the output.
+    written: bool,
+}
+
+/// Writes the formatted code of a syntax tree, interleaved with the
+/// comments of its source.
+struct Formatter<'a> {
+    /// Source code, which literals are copied from.
+    source: &'a str,
+    /// Whether each line of the source code (from line 1) is empty.
+    blank_lines: Vec<bool>,
+    /// Comments of the source code, in order.
+    comments: Vec<Comment>,
+    /// Formatted code.
+    out: String,
+    /// Current indentation level.
+    indent: usize,
+    /// Source line where the previously written statement or comment ends.
+    /// `None` at the start of the code and of blocks.
+    last_line: Option<usize>,
+}
+
+impl Formatter<'_> {
+    /// Writes the functions of the program and the comments around them.
+    fn items(&mut self, items: &[ast::Stmt]) {
+        let mut after_fn = false;
+        for (i, item) in items.iter().enumerate() {
+            let is_fn = matches!(item, ast::Stmt::FnDef { .. });
+            let next = items.get(i + 1).map_or(usize::MAX, |s| s.span().start);
+            self.statement(item, after_fn && is_fn, next);
+            after_fn = is_fn;
+        }
+        self.comments_before(usize::MAX);
+    }
+
+    /// Writes a statement on its own lines, after the comments which come
+    /// before it or inside it, and followed by a comment on the line where
+    /// it ends. `separate` requires a blank line before the statement, and
+    /// `next` is the source offset where the next statement starts.
+    fn statement(&mut self, stmt: &ast::Stmt, separate: bool, next: usize) {
+        let span = stmt.span();
+        let mut blocks = Vec::new();
+        nested_blocks(stmt, &mut blocks);
+
+        let mut separate = separate;
+        for i in 0..self.comments.len() {
+            let c = &self.comments[i];
+            let inside = span.start <= c.span.start && c.span.end <= span.end;
+            let in_block = blocks
+                .iter()
+                .any(|b| b.start <= c.span.start && c.span.end <= b.end);
+            if !c.written && (c.span.start < span.start || (inside && !in_block)) {
+                self.comment(i, separate);
+                separate = false;
+            }
+        }
+
+        self.blank_line(span.line, separate);
+        self.write_indent();
+        self.stmt(stmt);
+        self.last_line = Some(span.end_line);
+
+        for i in 0..self.comments.len() {
+            let c = &self.comments[i];
+            if !c.written && c.span.line == span.end_line && c.span.start < next {
+                self.out.push(' ');
+                self.out.push_str(&c.text);
+                self.last_line = Some(c.span.end_line);
+                self.comments[i].written = true;
+            }
+        }
+        self.out.push('\n');
+    }
+
+    /// Writes the comments which start before a source offset on their own
+    /// lines.
+    fn comments_before(&mut self, offset: usize) {
+        for i in 0..self.comments.len() {
+            if !self.comments[i].written && self.comments[i].span.start < offset {
+                self.comment(i, false);
+            }
+        }
+    }
+
+    /// Writes a comment on its own line.
+    fn comment(&mut self, i: usize, separate: bool) {
+        self.blank_line(self.comments[i].span.line, separate);
+        self.write_indent();
+        let c = &mut self.comments[i];
+        self.out.push_str(&c.text);
+        self.out.push('\n');
+        self.last_line = Some(c.span.end_line);
+        c.written = true;
+    }
+
+    /// Writes a blank line if it is required, or if the source has empty
+    /// lines between the previously written line and `line`. There are no
+    /// blank lines at the start of the code and of blocks.
+    fn blank_line(&mut self, line: usize, required: bool) {
+        if let Some(last) = self.last_line {
+            let empty = (last + 1..line).any(|l| self.blank_lines.get(l - 1) == Some(&true));
+            if required || empty {
+                self.out.push('\n');
+            }
+        }
+    }
+
+    fn write_indent(&mut self) {
+        self.out.push_str(&INDENT.repeat(self.indent));
+    }
+
+    /// Returns the column where the next character is written, from `0`.
+    fn column(&self) -> usize {
+        let line_start = self.out.rfind('\n').map_or(0, |i| i + 1);
+        self.out[line_start..].chars().count()
+    }
+
+    /// Writes a statement, without indenting its first line.
+    fn stmt(&mut self, stmt: &ast::Stmt) {
+        match stmt {
+            ast::Stmt::Let {
+                id, var_type, expr, ..
+            } => {
+                self.out.push_str(&format!("let {}: {}", id, var_type));
+                if let Some(expr) = expr {
+                    self.out.push_str(" = ");
+                    self.expr(expr, 1);
+                }
+                self.out.push(';');
+            }
+            ast::Stmt::Expr { expr, .. } => {
+                self.expr(expr, 1);
+                self.out.push(';');
+            }
+            ast::Stmt::If {
+                cond,
+                then_stmt,
+                else_stmt,
+                ..
+            } => {
+                self.out.push_str("if ");
+                self.expr(cond, 2);
+                self.body(then_stmt);
+                if let Some(else_stmt) = else_stmt {
+                    self.out.push_str(" else");
+                    self.body(else_stmt);
+                }
+            }
+            ast::Stmt::While { cond, body, .. } => {
+                self.out.push_str("while ");
+                self.expr(cond, 2);
+                self.body(body);
+            }
+            ast::Stmt::For {
+                id,
+                start,
+                end,
+                body,
+                ..
+            } => {
+                let start = self.flat(start);
+                let end = self.flat(end);
+                self.out
+                    .push_str(&format!("for {} = {} to {}", id, start, end));
+                self.body(body);
+            }
+            ast::Stmt::Return { expr, .. } => {
+                self.out.push_str("return ");
+                self.expr(expr, 1);
+                self.out.push(';');
+            }
+            ast::Stmt::Block { stmts, span } => self.block(stmts, *span),
+            ast::Stmt::FnDef {
+                id,
+                params,
+                return_type,
+                body,
+                ..
+            } => {
+                let params: Vec<String> = params
+                    .iter()
+                    .map(|(id, t)| format!("{}: {}", id, t))
+                    .collect();
+                self.out
+                    .push_str(&format!("fn {}({})", id, params.join(", ")));
+                if let Some(t) = return_type {
+                    self.out.push_str(&format!(": {}", t));
+                }
+                self.body(body);
+            }
+            ast::Stmt::Empty { .. } => self.out.push(';'),
+        }
+    }
+
+    /// Writes the body of a statement after its header.
+    fn body(&mut self, body: &ast::Stmt) {
+        if !matches!(body, ast::Stmt::Empty { .. }) {
+            self.out.push(' ');
+        }
+        self.stmt(body);
+    }
+
+    /// Writes a block and the comments inside it. Empty blocks are `{}`.
+    fn block(&mut self, stmts: &[ast::Stmt], span: Span) {
+        let has_comments = self
+            .comments
+            .iter()
+            .any(|c| !c.written && c.span.start < span.end);
+        if stmts.is_empty() && !has_comments {
+            self.out.push_str("{}");
+            return;
+        }
+
+        self.out.push_str("{\n");
+        self.indent += 1;
+        self.last_line = None;
+        for (i, stmt) in stmts.iter().enumerate() {
+            let next = stmts.get(i + 1).map_or(span.end, |s| s.span().start);
+            self.statement(stmt, false, next);
+        }
+        self.comments_before(span.end);
+        self.indent -= 1;
+        self.write_indent();
+        self.out.push('}');
+    }
+
+    /// Writes an expression. If it does not fit in the line, together with
+    /// `trailing` characters which follow it, the arguments of the call (or
+    /// the elements of the array literal) which ends it are wrapped.
+    fn expr(&mut self, expr: &ast::Expr, trailing: usize) {
+        let flat = self.flat(expr);
+        if self.column() + flat.chars().count() + trailing <= MAX_WIDTH {
+            self.out.push_str(&flat);
+            return;
+        }
+
+        match expr {
+            ast::Expr::Call { id, args, .. } if !args.is_empty() => {
+                self.out.push_str(id);
+                self.list('(', args, ')');
+            }
+            ast::Expr::LiteralArray { elements, .. } if !elements.is_empty() => {
+                self.list('[', elements, ']');
+            }
+            ast::Expr::Assign { id, expr, .. } => {
+                self.out.push_str(&format!("{} = ", id));
+                self.expr(expr, trailing);
+            }
+            ast::Expr::BinaryOp { l, op, r, .. } => {
+                let l = self.operand(l, parenthesize_left(l, op));
+                self.out.push_str(&format!("{} {} ", l, op));
+                if parenthesize_right(r, op) {
+                    self.out.push('(');
+                    self.expr(r, trailing + 1);
+                    self.out.push(')');
+                } else {
+                    self.expr(r, trailing);
+                }
+            }
+            _ => self.out.push_str(&flat),
+        }
+    }
+
+    /// Writes a list of expressions, one per line.
+    fn list(&mut self, open: char, exprs: &[ast::Expr], close: char) {
+        self.out.push(open);
+        self.out.push('\n');
+        self.indent += 1;
+        for (i, e) in exprs.iter().enumerate() {
+            let last = i == exprs.len() - 1;
+            self.write_indent();
+            self.expr(e, if last { 0 } else { 1 });
+            if !last {
+                self.out.push(',');
+            }
+            self.out.push('\n');
+        }
+        self.indent -= 1;
+        self.write_indent();
+        self.out.push(close);
+    }
+
+    /// Returns an expression formatted on a single line.
+    fn flat(&self, expr: &ast::Expr) -> String {
+        let list = |exprs: &[ast::Expr]| -> String {
+            let exprs: Vec<String> = exprs.iter().map(|e| self.flat(e)).collect();
+            exprs.join(", ")
+        };
+
+        match expr {
+            ast::Expr::LiteralInt { span, .. }
+            | ast::Expr::LiteralStr { span, .. }
+            | ast::Expr::LiteralChar { span, .. }
+            | ast::Expr::LiteralFloat { span, .. }
+            | ast::Expr::Error { span } => self.source[span.start..span.end].to_string(),
+            ast::Expr::LiteralBool { value, .. } => value.to_string(),
+            ast::Expr::LiteralArray { elements, .. } => format!("[{}]", list(elements)),
+            ast::Expr::BinaryOp { l, op, r, .. } => format!(
+                "{} {} {}",
+                self.operand(l, parenthesize_left(l, op)),
+                op,
+                self.operand(r, parenthesize_right(r, op))
+            ),
+            ast::Expr::UnaryOp { op, expr, .. } => {
+                // Unary operators only apply to primary expressions.
+                let primary = !matches!(
+                    **expr,
+                    ast::Expr::BinaryOp { .. }
+                        | ast::Expr::UnaryOp { .. }
+                        | ast::Expr::Assign { .. }
+                );
+                let operand = self.operand(expr, !primary);
+                match op {
+                    ast::UnOp::LogNot { .. } => format!("not {}", operand),
+                    _ => format!("{}{}", op, operand),
+                }
+            }
+            ast::Expr::Identifier { id, .. } => id.clone(),
+            ast::Expr::Call { id, args, .. } => format!("{}({})", id, list(args)),
+            ast::Expr::ArrayExpr { id, index, .. } => format!("{}[{}]", id, self.flat(index)),
+            ast::Expr::Assign { id, expr, .. } => format!("{} = {}", id, self.flat(expr)),
+        }
+    }
+
+    fn operand(&self, expr: &ast::Expr, parenthesize: bool) -> String {
+        if parenthesize {
+            format!("({})", self.flat(expr))
+        } else {
+            self.flat(expr)
+        }
+    }
+}
+
+/// Returns the precedence of a binary operator: operators with higher
+/// precedence bind tighter.
+fn precedence(op: &ast::BinOp) -> u8 {
+    match op {
+        ast::BinOp::LogOr { .. } => 1,
+        ast::BinOp::LogAnd { .. } => 2,
+        ast::BinOp::Eq { .. } | ast::BinOp::NEq { .. } => 3,
+        ast::BinOp::LT { .. }
+        | ast::BinOp::GT { .. }
+        | ast::BinOp::LEq { .. }
+        | ast::BinOp::GEq { .. } => 4,
+        ast::BinOp::BitOr { .. } => 5,
+        ast::BinOp::BitAnd { .. } => 6,
+        ast::BinOp::Add { .. } | ast::BinOp::Sub { .. } => 7,
+        ast::BinOp::Mul { .. } | ast::BinOp::Div { .. } | ast::BinOp::Mod { .. } => 8,
+    }
+}
+
+/// Checks whether the left operand of a binary operator needs parentheses.
+/// Binary operators are left-associative.
+fn parenthesize_left(l: &ast::Expr, op: &ast::BinOp) -> bool {
+    match l {
+        ast::Expr::BinaryOp { op: l_op, .. } => precedence(l_op) < precedence(op),
+        ast::Expr::Assign { .. } => true,
+        _ => false,
+    }
+}
+
+/// Checks whether the right operand of a binary operator needs parentheses.
+fn parenthesize_right(r: &ast::Expr, op: &ast::BinOp) -> bool {
+    match r {
+        ast::Expr::BinaryOp { op: r_op, .. } => precedence(r_op) <= precedence(op),
+        ast::Expr::Assign { .. } => true,
+        _ => false,
+    }
+}
+
+/// Collects the source ranges of the blocks which are (or are the bodies
+/// of) a statement. Comments inside them are written with the statements
+/// of the blocks.
+fn nested_blocks(stmt: &ast::Stmt, blocks: &mut Vec<Span>) {
+    match stmt {
+        ast::Stmt::Block { span, .. } => blocks.push(*span),
+        ast::Stmt::If {
+            then_stmt,
+            else_stmt,
+            ..
+        } => {
+            nested_blocks(then_stmt, blocks);
+            if let Some(else_stmt) = else_stmt {
+                nested_blocks(else_stmt, blocks);
+            }
+        }
+        ast::Stmt::While { body, .. }
+        | ast::Stmt::For { body, .. }
+        | ast::Stmt::FnDef { body, .. } => nested_blocks(body, blocks),
+        _ => {}
+    }
+}
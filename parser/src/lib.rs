@@ -7,19 +7,43 @@ use std::io::{self, Read};
 
 use lexer::{
     token::{Token, TokenType},
-    Lexer,
+    LexError, Lexer, TokenStream,
 };
 
 mod syntax;
+/// This module includes Kolang abstract syntax tree nodes.
 pub mod ast;
+/// This module includes the lossless concrete syntax tree, which keeps whitespace and comments.
+pub mod cst;
+/// This module includes exporters of syntax trees and control flow graphs to the Graphviz DOT language.
+pub mod dot;
+/// This module includes the formatter which prints Kolang code in the canonical style.
+pub mod format;
+
+use diagnostics::{Diagnostic, Span};
 
 /// The `Parser<R>` struct allows you to parse Kolang code from any byte source
 /// which implements [`Read`] trait (file, network, in-memory buffer, etc.).
 pub struct Parser<R: Read> {
-    /// The `Lexer<R>` instance which provides source code tokens.
-    lexer: Lexer<R>,
+    /// Source code tokens (without trivia, unless the parser builds
+    /// a concrete syntax tree).
+    tokens: TokenStream<R>,
     /// The current token being processed.
     current: Token,
+    /// Source range of the previously consumed token.
+    previous: Span,
+    /// Whether the previously consumed token ends a statement (`;` or `}`).
+    after_terminator: bool,
+    /// Whether the parser is recovering from a syntax error (panic mode).
+    /// Syntax errors are not reported while recovering.
+    recovering: bool,
+    /// Errors found so far.
+    diagnostics: Vec<Diagnostic>,
+    /// Trivia and invalid tokens read before `current`, which are not added
+    /// to the concrete syntax tree yet.
+    trivia: Vec<Token>,
+    /// Builder of the concrete syntax tree, if the parser builds one.
+    cst: Option<cst::Builder>,
 }
 
 impl<R: Read> Parser<R> {
@@ -36,13 +60,34 @@ impl<R: Read> Parser<R> {
     /// let p = Parser::new(l);
     /// ```
     pub fn new(lexer: Lexer<R>) -> Self {
+        Self::with_stream(TokenStream::new(lexer).with_comments(false))
+    }
+
+    /// Creates a new `Parser<R>` which reads tokens from provided stream.
+    fn with_stream(tokens: TokenStream<R>) -> Self {
         Self {
-            lexer,
-            current: Token::new(0, 0, TokenType::LC("".to_string())),
+            tokens,
+            current: Token::new(Span::default(), TokenType::LC("".to_string())),
+            previous: Span::default(),
+            after_terminator: false,
+            recovering: false,
+            diagnostics: Vec::new(),
+            trivia: Vec::new(),
+            cst: None,
         }
     }
 
     /// Starts parsing the provided souce code.
+    ///
+    /// Syntax errors do not stop the parser. Instead, it skips the erroneous
+    /// code until the next `;`, `}` or `fn` and continues, so the returned
+    /// syntax tree may be partial (containing [`ast::Expr::Error`] and
+    /// [`ast::Type::Error`] nodes) and all errors are returned alongside it.
+    ///
+    /// # Errors
+    /// May return I/O error if something goes wrong while reading bytes
+    /// from source.
+    ///
     /// # Examples
     ///
     /// ```
@@ -52,36 +97,142 @@ impl<R: Read> Parser<R> {
     /// let source = "fn main(): int {}".as_bytes();
     /// let l = Lexer::new(source);
     /// let mut p = Parser::new(l);
-    /// p.parse();
+    /// let (ast, diagnostics) = p.parse().unwrap();
+    ///
+    /// assert_eq!(ast.len(), 1);
+    /// assert!(diagnostics.is_empty());
     /// ```
-    pub fn parse(&mut self) -> io::Result<Vec<ast::Stmt>> {
+    pub fn parse(&mut self) -> io::Result<(Vec<ast::Stmt>, Vec<Diagnostic>)> {
         self.next()?;
         let p = self.prog()?;
 
-        Ok(p)
+        Ok((p, std::mem::take(&mut self.diagnostics)))
+    }
+
+    /// Parses the provided source code as function definitions and statements
+    /// in any order, like the input of an interactive session. Errors are
+    /// handled like in [`Parser::parse`].
+    ///
+    /// # Errors
+    /// May return I/O error if something goes wrong while reading bytes
+    /// from source.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use lexer::Lexer;
+    /// use parser::{ast::Stmt, Parser};
+    ///
+    /// let source = "let x: int = 1; fn f() {} f();".as_bytes();
+    /// let mut p = Parser::new(Lexer::new(source));
+    /// let (items, diagnostics) = p.parse_items().unwrap();
+    ///
+    /// assert!(matches!(items[..], [Stmt::Let { .. }, Stmt::FnDef { .. }, Stmt::Expr { .. }]));
+    /// assert!(diagnostics.is_empty());
+    /// ```
+    pub fn parse_items(&mut self) -> io::Result<(Vec<ast::Stmt>, Vec<Diagnostic>)> {
+        self.next()?;
+        let items = self.items()?;
+
+        Ok((items, std::mem::take(&mut self.diagnostics)))
+    }
+
+    /// Parses the provided source code as a single expression, without a
+    /// terminating `;`.
+    ///
+    /// # Errors
+    /// May return I/O error if something goes wrong while reading bytes
+    /// from source.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use lexer::Lexer;
+    /// use parser::Parser;
+    ///
+    /// let mut p = Parser::new(Lexer::new("1 + 2 * 3".as_bytes()));
+    /// let (expr, diagnostics) = p.parse_expr().unwrap();
+    ///
+    /// assert_eq!(expr.to_string(), "(1 + (2 * 3))");
+    /// assert!(diagnostics.is_empty());
+    ///
+    /// let mut p = Parser::new(Lexer::new("1 + 2;".as_bytes()));
+    /// assert_eq!(p.parse_expr().unwrap().1.len(), 1);
+    /// ```
+    pub fn parse_expr(&mut self) -> io::Result<(ast::Expr, Vec<Diagnostic>)> {
+        self.next()?;
+        let expr = self.lone_expr()?;
+
+        Ok((expr, std::mem::take(&mut self.diagnostics)))
     }
 
     /// Advances to the next token.
     fn next(&mut self) -> io::Result<()> {
+        self.after_terminator = matches!(
+            self.current.token_type,
+            TokenType::Semicolon | TokenType::RBrace
+        );
+        self.previous = self.current.span;
+        self.record_current();
+
         loop {
-            self.current = self.lexer.next()?;
+            self.current = match self.tokens.next() {
+                Some(Ok(tok)) => tok,
+                Some(Err(LexError::Io(e))) => return Err(e),
+                Some(Err(e)) => {
+                    self.diagnostics.extend(e.to_diagnostic());
+                    continue;
+                }
+                // The stream ends after `EOF`, which is kept as the current token.
+                None => break,
+            };
 
             match self.current.token_type {
-                TokenType::LC(_) | TokenType::BC(_) => continue,
                 TokenType::Invalid(_) => {
-                    self.syntax_error(format!("Invalid token `{}`", self.current));
+                    let span = self.current.span;
+                    self.error(format!("Invalid token `{}`", self.current.token_type), span);
                 }
+                _ if self.current.token_type.is_trivia() => {}
                 _ => break,
             }
+            if self.cst.is_some() {
+                // Skipped tokens are kept in the concrete syntax tree.
+                self.trivia.push(self.current.clone());
+            }
         }
 
         Ok(())
     }
 
-    fn syntax_error(&mut self, msg: String) {
-        panic!(
-            "{}:{}: Syntax error: {}",
-            self.current.line, self.current.column, msg
-        );
+    /// Records a syntax error at the current token and enters panic mode.
+    /// `expected` is the list of tokens which were acceptable instead.
+    fn syntax_error(&mut self, msg: String, expected: &[&str]) {
+        if !self.recovering {
+            let mut d = Diagnostic::error(msg, self.current.span);
+            if expected.len() > 1 {
+                d = d.with_note(format!("Expected one of {}", expected.join(", ")));
+            }
+            let expected = expected.iter().map(|e| e.to_string()).collect();
+            self.diagnostics.push(d.with_expected(expected));
+        }
+
+        self.recovering = true;
+    }
+
+    /// Returns the source range from the start of `start` to the end of
+    /// the previously consumed token.
+    fn span_from(&self, start: Span) -> Span {
+        if self.previous.end < start.start {
+            // Nothing is consumed since `start`.
+            Span::empty(start.start, start.line, start.column)
+        } else {
+            start.to(self.previous)
+        }
+    }
+
+    /// Records an error which does not affect the structure of the code
+    /// (e.g. an invalid literal), so there is no need to enter panic mode.
+    fn error(&mut self, msg: String, span: Span) {
+        self.diagnostics.push(Diagnostic::error(msg, span));
     }
 }
@@ -1,18 +1,57 @@
 use std::{
-    fs::File,
+    fs,
     io::{self, BufRead, Write},
 };
 
+use diagnostics::{Renderer, SourceFile};
 use lexer::Lexer;
-use parser::Parser;
+use parser::{dot, Parser};
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 
+/// Output of the parser.
+enum Output {
+    /// Pretty-printed syntax tree.
+    Ast,
+    /// Graphviz DOT graph of the syntax tree (`--dot=ast`).
+    DotAst,
+    /// Graphviz DOT control flow graphs of the functions (`--dot=cfg`).
+    DotCfg,
+    /// Syntax tree as a JSON array of top level statements (`--emit=ast-json`).
+    #[cfg(feature = "serde")]
+    AstJson,
+}
+
 fn main() -> io::Result<()> {
-    let args: Vec<String> = std::env::args().collect();
+    let mut output = Output::Ast;
+    let mut path = None;
+    for arg in std::env::args().skip(1) {
+        output = match arg.as_str() {
+            "--dot=ast" => Output::DotAst,
+            "--dot=cfg" => Output::DotCfg,
+            #[cfg(feature = "serde")]
+            "--emit=ast-json" => Output::AstJson,
+            #[cfg(not(feature = "serde"))]
+            "--emit=ast-json" => {
+                eprintln!("`--emit=ast-json` requires the `serde` feature");
+                std::process::exit(2);
+            }
+            _ if arg.starts_with("--") => {
+                eprintln!(
+                    "Unknown option `{}`, expected `--dot=ast`, `--dot=cfg` or `--emit=ast-json`",
+                    arg
+                );
+                std::process::exit(2);
+            }
+            _ => {
+                path = Some(arg);
+                continue;
+            }
+        };
+    }
 
-    let path = if args.len() > 1 {
-        args[1].clone()
+    let path = if let Some(path) = path {
+        path
     } else {
         println!("⛏️  Kolang parser v{}\n", VERSION);
         println!("Code file path (relative or absolute):");
@@ -24,14 +63,32 @@ fn main() -> io::Result<()> {
         buf.trim_end().to_string()
     };
 
-    let f = File::open(path)?;
-    let l = Lexer::new(f);
+    let source = fs::read(&path)?;
+    let l = Lexer::new(source.as_slice());
     let mut p = Parser::new(l);
 
-    let ast = p.parse()?;
+    let (ast, diagnostics) = p.parse()?;
 
-    for stmt in ast {
-        println!("{:#}", stmt);
+    match output {
+        Output::Ast => {
+            for stmt in &ast {
+                println!("{:#}", stmt);
+            }
+        }
+        Output::DotAst => print!("{}", dot::syntax_tree(&ast)),
+        Output::DotCfg => print!("{}", dot::control_flow(&ast)),
+        #[cfg(feature = "serde")]
+        Output::AstJson => println!("{}", serde_json::to_string(&ast)?),
+    }
+
+    let text = String::from_utf8_lossy(&source);
+    let file = SourceFile::new(&path, &text);
+    let renderer = Renderer::for_stderr();
+    for d in &diagnostics {
+        eprintln!("{}", renderer.render(d, &file));
+    }
+    if !diagnostics.is_empty() {
+        std::process::exit(1);
     }
 
     Ok(())
@@ -1,9 +1,10 @@
 use std::io::{self, Read};
 
+use diagnostics::Span;
 use lexer::token::TokenType;
 
 use super::Parser;
-use crate::ast;
+use crate::{ast, cst::NodeKind};
 
 impl<R: Read> Parser<R> {
     /// Parses the program.
@@ -19,46 +20,125 @@ impl<R: Read> Parser<R> {
             }
             TokenType::EOF => {}
             _ => {
-                self.syntax_error("Expected `fn`".into());
+                self.syntax_error("Expected `fn`".into(), &["`fn`"]);
+                // skip to the next function
+                self.start_node(NodeKind::Error);
+                while !matches!(self.current.token_type, TokenType::KwFn | TokenType::EOF) {
+                    self.next()?;
+                }
+                self.finish_node();
+                self.recovering = false;
+
+                let mut crd = self.prog()?;
+                p.append(&mut crd);
             }
         };
 
         Ok(p)
     }
 
+    /// Parses function definitions and statements in any order, until the
+    /// end of file.
+    pub(super) fn items(&mut self) -> io::Result<Vec<ast::Stmt>> {
+        let mut items = Vec::new();
+
+        loop {
+            match self.current.token_type {
+                TokenType::KwFn => items.push(self.func()?),
+                TokenType::EOF => break,
+                TokenType::RBrace => {
+                    // `stmt` stops before an unbalanced `}`.
+                    self.syntax_error("Unexpected `}`".into(), &[]);
+                    self.start_node(NodeKind::Error);
+                    self.next()?;
+                    self.finish_node();
+                    self.recovering = false;
+                }
+                _ => items.push(self.stmt()?),
+            }
+        }
+
+        Ok(items)
+    }
+
+    /// Parses an expression which makes up the whole file.
+    pub(super) fn lone_expr(&mut self) -> io::Result<ast::Expr> {
+        let expr = self.expr()?;
+
+        if self.current.token_type != TokenType::EOF {
+            self.syntax_error("Expected end of file".into(), &["end of file"]);
+        }
+
+        Ok(expr)
+    }
+
     /// Expects a token. Consumes the token if matches,
     /// otherwise raises syntax error.
     fn expect(&mut self, expected: TokenType) -> io::Result<()> {
         if self.current.token_type == expected {
             self.next()?;
         } else {
-            self.syntax_error(format!("Expected `{}`", expected));
+            let tok = format!("`{}`", expected);
+            self.syntax_error(format!("Expected {}", tok), &[&tok]);
+        }
+
+        Ok(())
+    }
+
+    /// Discards tokens until the end of the erroneous statement to recover
+    /// from a syntax error. Stops after a `;` or a balanced `{ ... }` block,
+    /// or before an unbalanced `}`, `fn` or end of file.
+    fn synchronize(&mut self) -> io::Result<()> {
+        let mut depth = 0;
+
+        self.start_node(NodeKind::Error);
+        loop {
+            match self.current.token_type {
+                TokenType::Semicolon if depth == 0 => {
+                    self.next()?;
+                    break;
+                }
+                TokenType::LBrace => depth += 1,
+                TokenType::RBrace if depth == 0 => break,
+                TokenType::RBrace if depth == 1 => {
+                    self.next()?;
+                    break;
+                }
+                TokenType::RBrace => depth -= 1,
+                TokenType::KwFn | TokenType::EOF => break,
+                _ => {}
+            }
+            self.next()?;
         }
+        self.finish_node();
+
+        self.recovering = false;
 
         Ok(())
     }
 
     /// Parses the function.
     fn func(&mut self) -> io::Result<ast::Stmt> {
+        let start = self.current.span;
+        self.start_node(NodeKind::FnDef);
         self.expect(TokenType::KwFn)?;
 
-        let line = self.current.line;
-        let column = self.current.column;
-
         let id = match &self.current.token_type {
             TokenType::Iden(id) => id.to_string(),
             _ => {
-                self.syntax_error("Expected identifier".into());
+                self.syntax_error("Expected identifier".into(), &["identifier"]);
                 "".to_string()
             }
         };
         self.next()?;
 
+        self.start_node(NodeKind::ParamList);
         self.expect(TokenType::LPar)?;
 
         let params = self.param_list()?;
 
         self.expect(TokenType::RPar)?;
+        self.finish_node();
 
         let return_type = match &self.current.token_type {
             TokenType::Colon => {
@@ -69,14 +149,14 @@ impl<R: Read> Parser<R> {
         };
 
         let body = Box::new(self.stmt()?);
+        self.finish_node();
 
         Ok(ast::Stmt::FnDef {
             id,
             params,
             return_type,
             body,
-            line,
-            column,
+            span: self.span_from(start),
         })
     }
 
@@ -89,7 +169,9 @@ impl<R: Read> Parser<R> {
             return Ok(params);
         }
 
+        self.start_node(NodeKind::Param);
         let idt = self.typed_ident()?;
+        self.finish_node();
         params.push(idt);
 
         if self.current.token_type == TokenType::Comma {
@@ -101,8 +183,10 @@ impl<R: Read> Parser<R> {
         Ok(params)
     }
 
-    /// Parses the statement.
+    /// Parses the statement. If the statement contains a syntax error,
+    /// skips the rest of it.
     fn stmt(&mut self) -> io::Result<ast::Stmt> {
+        let start = self.current.span.start;
         let s = match self.current.token_type {
             TokenType::KwLet => self.let_stmt()?,
             TokenType::KwIf => self.if_stmt()?,
@@ -111,25 +195,35 @@ impl<R: Read> Parser<R> {
             TokenType::KwReturn => self.return_stmt()?,
             TokenType::LBrace => self.block_stmt()?,
             TokenType::Semicolon => {
+                let span = self.current.span;
+                self.start_node(NodeKind::EmptyStmt);
                 self.next()?;
-                ast::Stmt::Empty {
-                    line: self.current.line,
-                    column: self.current.column,
-                }
+                self.finish_node();
+                ast::Stmt::Empty { span }
             }
             _ => self.expr_stmt()?,
         };
 
+        if self.recovering {
+            // A statement which consumes nothing may follow a terminator, so
+            // it is only complete if it has consumed at least one token.
+            if self.after_terminator && self.previous.end > start {
+                // The statement is erroneous, but it is completely consumed.
+                self.recovering = false;
+            } else {
+                self.synchronize()?;
+            }
+        }
+
         Ok(s)
     }
 
     /// Parses the let statement.
     fn let_stmt(&mut self) -> io::Result<ast::Stmt> {
+        let start = self.current.span;
+        self.start_node(NodeKind::LetStmt);
         self.expect(TokenType::KwLet)?;
 
-        let line = self.current.line;
-        let column = self.current.column;
-
         let (id, var_type) = self.typed_ident()?;
 
         let expr = match &self.current.token_type {
@@ -141,32 +235,37 @@ impl<R: Read> Parser<R> {
         };
 
         self.expect(TokenType::Semicolon)?;
+        self.finish_node();
 
         Ok(ast::Stmt::Let {
             id,
             var_type,
             expr,
-            line,
-            column,
+            span: self.span_from(start),
         })
     }
 
     /// Parses the expression statement.
     fn expr_stmt(&mut self) -> io::Result<ast::Stmt> {
+        let start = self.current.span;
+        self.start_node(NodeKind::ExprStmt);
         let expr = self.expr()?;
 
         self.expect(TokenType::Semicolon)?;
+        self.finish_node();
 
-        Ok(ast::Stmt::Expr { expr })
+        Ok(ast::Stmt::Expr {
+            expr,
+            span: self.span_from(start),
+        })
     }
 
     /// Parses the if statement.
     fn if_stmt(&mut self) -> io::Result<ast::Stmt> {
+        let start = self.current.span;
+        self.start_node(NodeKind::IfStmt);
         self.expect(TokenType::KwIf)?;
 
-        let line = self.current.line;
-        let column = self.current.column;
-
         let cond = self.expr()?;
 
         let then_stmt = Box::new(self.stmt()?);
@@ -178,46 +277,44 @@ impl<R: Read> Parser<R> {
             }
             _ => None,
         };
+        self.finish_node();
 
         Ok(ast::Stmt::If {
             cond,
             then_stmt,
             else_stmt,
-            line,
-            column,
+            span: self.span_from(start),
         })
     }
 
     /// Parses the while statement.
     fn while_stmt(&mut self) -> io::Result<ast::Stmt> {
+        let start = self.current.span;
+        self.start_node(NodeKind::WhileStmt);
         self.expect(TokenType::KwWhile)?;
 
-        let line = self.current.line;
-        let column = self.current.column;
-
         let cond = self.expr()?;
 
         let body = Box::new(self.stmt()?);
+        self.finish_node();
 
         Ok(ast::Stmt::While {
             cond,
             body,
-            line,
-            column,
+            span: self.span_from(start),
         })
     }
 
     /// Parses the for statement.
     fn for_stmt(&mut self) -> io::Result<ast::Stmt> {
+        let first = self.current.span;
+        self.start_node(NodeKind::ForStmt);
         self.expect(TokenType::KwFor)?;
 
-        let line = self.current.line;
-        let column = self.current.column;
-
         let id = match &self.current.token_type {
             TokenType::Iden(id) => id.to_string(),
             _ => {
-                self.syntax_error("Expected identifier".into());
+                self.syntax_error("Expected identifier".into(), &["identifier"]);
                 "".to_string()
             }
         };
@@ -232,46 +329,48 @@ impl<R: Read> Parser<R> {
         let end = self.expr()?;
 
         let body = Box::new(self.stmt()?);
+        self.finish_node();
 
         Ok(ast::Stmt::For {
             id,
             start,
             end,
             body,
-            line,
-            column,
+            span: self.span_from(first),
         })
     }
 
     /// Parses the return statement.
     fn return_stmt(&mut self) -> io::Result<ast::Stmt> {
+        let start = self.current.span;
+        self.start_node(NodeKind::ReturnStmt);
         self.expect(TokenType::KwReturn)?;
 
-        let line = self.current.line;
-        let column = self.current.column;
-
         let expr = self.expr()?;
 
         self.expect(TokenType::Semicolon)?;
+        self.finish_node();
 
-        Ok(ast::Stmt::Return { expr, line, column })
+        Ok(ast::Stmt::Return {
+            expr,
+            span: self.span_from(start),
+        })
     }
 
     /// Parses the block statement.
     fn block_stmt(&mut self) -> io::Result<ast::Stmt> {
+        let start = self.current.span;
+        self.start_node(NodeKind::Block);
         self.expect(TokenType::LBrace)?;
 
-        let line = self.current.line;
-        let column = self.current.column;
-
         let stmts = self.multi_stmt()?;
 
         self.expect(TokenType::RBrace)?;
+        self.finish_node();
 
         Ok(ast::Stmt::Block {
             stmts,
-            line,
-            column,
+            span: self.span_from(start),
         })
     }
 
@@ -279,7 +378,11 @@ impl<R: Read> Parser<R> {
     fn multi_stmt(&mut self) -> io::Result<Vec<ast::Stmt>> {
         let mut stmts: Vec<ast::Stmt> = Vec::new();
 
-        if self.current.token_type == TokenType::RBrace {
+        if matches!(
+            self.current.token_type,
+            TokenType::RBrace | TokenType::EOF | TokenType::KwFn
+        ) {
+            // `fn` and EOF mean that `}` is missing.
             return Ok(stmts);
         }
 
@@ -297,7 +400,7 @@ impl<R: Read> Parser<R> {
         let id = match &self.current.token_type {
             TokenType::Iden(id) => id.to_string(),
             _ => {
-                self.syntax_error("Expected identifier".into());
+                self.syntax_error("Expected identifier".into(), &["identifier"]);
                 "".to_string()
             }
         };
@@ -312,31 +415,35 @@ impl<R: Read> Parser<R> {
 
     /// Parses the types.
     fn types(&mut self) -> io::Result<ast::Type> {
-        let line = self.current.line;
-        let column = self.current.column;
+        let span = self.current.span;
+        self.start_node(NodeKind::Type);
 
         let mut t = match self.current.token_type {
-            TokenType::KwInt => ast::Type::Int { line, column },
-            TokenType::KwFloat => ast::Type::Float { line, column },
-            TokenType::KwChar => ast::Type::Char { line, column },
-            TokenType::KwStr => ast::Type::Str { line, column },
-            TokenType::KwBool => ast::Type::Bool { line, column },
+            TokenType::KwInt => ast::Type::Int { span },
+            TokenType::KwFloat => ast::Type::Float { span },
+            TokenType::KwChar => ast::Type::Char { span },
+            TokenType::KwStr => ast::Type::Str { span },
+            TokenType::KwBool => ast::Type::Bool { span },
             _ => {
-                self.syntax_error("Expected type".into());
-                ast::Type::Error { line, column }
+                self.syntax_error(
+                    "Expected type".into(),
+                    &["`int`", "`float`", "`char`", "`str`", "`bool`"],
+                );
+                self.finish_node();
+                return Ok(ast::Type::Error { span });
             }
         };
         self.next()?;
 
         if self.current.token_type == TokenType::LBracket {
             self.next()?;
+            self.expect(TokenType::RBracket)?;
             t = ast::Type::Array {
                 element_type: Box::new(t),
-                line,
-                column,
+                span: self.span_from(span),
             };
-            self.expect(TokenType::RBracket)?;
         }
+        self.finish_node();
 
         Ok(t)
     }
@@ -348,20 +455,24 @@ impl<R: Read> Parser<R> {
 
     /// Parses the logical or expression.
     fn log_or_expr(&mut self) -> io::Result<ast::Expr> {
+        let start = self.current.span;
+        let checkpoint = self.checkpoint();
         let mut l = self.log_and_expr()?;
 
         while self.current.token_type == TokenType::KwOr {
+            self.start_node_at(checkpoint, NodeKind::BinaryExpr);
             let op = ast::BinOp::LogOr {
-                line: self.current.line,
-                column: self.current.column,
+                span: self.current.span,
             };
             self.next()?;
 
             let r = self.log_and_expr()?;
+            self.finish_node();
             l = ast::Expr::BinaryOp {
                 l: Box::new(l),
                 op,
                 r: Box::new(r),
+                span: self.span_from(start),
             };
         }
 
@@ -370,20 +481,24 @@ impl<R: Read> Parser<R> {
 
     /// Parses the logical and expression.
     fn log_and_expr(&mut self) -> io::Result<ast::Expr> {
+        let start = self.current.span;
+        let checkpoint = self.checkpoint();
         let mut l = self.eq_neq_expr()?;
 
         while self.current.token_type == TokenType::KwAnd {
+            self.start_node_at(checkpoint, NodeKind::BinaryExpr);
             let op = ast::BinOp::LogAnd {
-                line: self.current.line,
-                column: self.current.column,
+                span: self.current.span,
             };
             self.next()?;
 
             let r = self.eq_neq_expr()?;
+            self.finish_node();
             l = ast::Expr::BinaryOp {
                 l: Box::new(l),
                 op,
                 r: Box::new(r),
+                span: self.span_from(start),
             };
         }
 
@@ -392,24 +507,27 @@ impl<R: Read> Parser<R> {
 
     /// Parses the equality and inequality expression.
     fn eq_neq_expr(&mut self) -> io::Result<ast::Expr> {
+        let start = self.current.span;
+        let checkpoint = self.checkpoint();
         let mut l = self.comp_expr()?;
 
         loop {
-            let line = self.current.line;
-            let column = self.current.column;
-
+            let span = self.current.span;
             let op = match self.current.token_type {
-                TokenType::Eq => ast::BinOp::Eq { line, column },
-                TokenType::NEq => ast::BinOp::NEq { line, column },
+                TokenType::Eq => ast::BinOp::Eq { span },
+                TokenType::NEq => ast::BinOp::NEq { span },
                 _ => break,
             };
+            self.start_node_at(checkpoint, NodeKind::BinaryExpr);
             self.next()?;
 
             let r = self.comp_expr()?;
+            self.finish_node();
             l = ast::Expr::BinaryOp {
                 l: Box::new(l),
                 op,
                 r: Box::new(r),
+                span: self.span_from(start),
             };
         }
 
@@ -418,26 +536,29 @@ impl<R: Read> Parser<R> {
 
     /// Parses the comparison expression.
     fn comp_expr(&mut self) -> io::Result<ast::Expr> {
+        let start = self.current.span;
+        let checkpoint = self.checkpoint();
         let mut l = self.bit_or()?;
 
         loop {
-            let line = self.current.line;
-            let column = self.current.column;
-
+            let span = self.current.span;
             let op = match self.current.token_type {
-                TokenType::LT => ast::BinOp::LT { line, column },
-                TokenType::GT => ast::BinOp::GT { line, column },
-                TokenType::LEq => ast::BinOp::LEq { line, column },
-                TokenType::GEq => ast::BinOp::GEq { line, column },
+                TokenType::LT => ast::BinOp::LT { span },
+                TokenType::GT => ast::BinOp::GT { span },
+                TokenType::LEq => ast::BinOp::LEq { span },
+                TokenType::GEq => ast::BinOp::GEq { span },
                 _ => break,
             };
+            self.start_node_at(checkpoint, NodeKind::BinaryExpr);
             self.next()?;
 
             let r = self.bit_or()?;
+            self.finish_node();
             l = ast::Expr::BinaryOp {
                 l: Box::new(l),
                 op,
                 r: Box::new(r),
+                span: self.span_from(start),
             };
         }
 
@@ -446,20 +567,24 @@ impl<R: Read> Parser<R> {
 
     /// Parses the bitwise or expression.
     fn bit_or(&mut self) -> io::Result<ast::Expr> {
+        let start = self.current.span;
+        let checkpoint = self.checkpoint();
         let mut l = self.bit_and_expr()?;
 
         while self.current.token_type == TokenType::Pipe {
+            self.start_node_at(checkpoint, NodeKind::BinaryExpr);
             let op = ast::BinOp::BitOr {
-                line: self.current.line,
-                column: self.current.column,
+                span: self.current.span,
             };
             self.next()?;
 
             let r = self.bit_and_expr()?;
+            self.finish_node();
             l = ast::Expr::BinaryOp {
                 l: Box::new(l),
                 op,
                 r: Box::new(r),
+                span: self.span_from(start),
             };
         }
 
@@ -468,20 +593,24 @@ impl<R: Read> Parser<R> {
 
     /// Parses the bitwise and expression.
     fn bit_and_expr(&mut self) -> io::Result<ast::Expr> {
+        let start = self.current.span;
+        let checkpoint = self.checkpoint();
         let mut l = self.add_sub_expr()?;
 
         while self.current.token_type == TokenType::Amp {
+            self.start_node_at(checkpoint, NodeKind::BinaryExpr);
             let op = ast::BinOp::BitAnd {
-                line: self.current.line,
-                column: self.current.column,
+                span: self.current.span,
             };
             self.next()?;
 
             let r = self.add_sub_expr()?;
+            self.finish_node();
             l = ast::Expr::BinaryOp {
                 l: Box::new(l),
                 op,
                 r: Box::new(r),
+                span: self.span_from(start),
             };
         }
 
@@ -490,24 +619,27 @@ impl<R: Read> Parser<R> {
 
     /// Parses the addition and subtraction expression.
     fn add_sub_expr(&mut self) -> io::Result<ast::Expr> {
+        let start = self.current.span;
+        let checkpoint = self.checkpoint();
         let mut l = self.mul_div_mod_expr()?;
 
         loop {
-            let line = self.current.line;
-            let column = self.current.column;
-
+            let span = self.current.span;
             let op = match self.current.token_type {
-                TokenType::Plus => ast::BinOp::Add { line, column },
-                TokenType::Minus => ast::BinOp::Sub { line, column },
+                TokenType::Plus => ast::BinOp::Add { span },
+                TokenType::Minus => ast::BinOp::Sub { span },
                 _ => break,
             };
+            self.start_node_at(checkpoint, NodeKind::BinaryExpr);
             self.next()?;
 
             let r = self.mul_div_mod_expr()?;
+            self.finish_node();
             l = ast::Expr::BinaryOp {
                 l: Box::new(l),
                 op,
                 r: Box::new(r),
+                span: self.span_from(start),
             };
         }
 
@@ -516,25 +648,28 @@ impl<R: Read> Parser<R> {
 
     /// Parses the multiplication, division and modulo expression.
     fn mul_div_mod_expr(&mut self) -> io::Result<ast::Expr> {
+        let start = self.current.span;
+        let checkpoint = self.checkpoint();
         let mut l = self.unary_expr()?;
 
         loop {
-            let line = self.current.line;
-            let column = self.current.column;
-
+            let span = self.current.span;
             let op = match self.current.token_type {
-                TokenType::Asterisk => ast::BinOp::Mul { line, column },
-                TokenType::Slash => ast::BinOp::Div { line, column },
-                TokenType::Percent => ast::BinOp::Mod { line, column },
+                TokenType::Asterisk => ast::BinOp::Mul { span },
+                TokenType::Slash => ast::BinOp::Div { span },
+                TokenType::Percent => ast::BinOp::Mod { span },
                 _ => break,
             };
+            self.start_node_at(checkpoint, NodeKind::BinaryExpr);
             self.next()?;
 
             let r = self.unary_expr()?;
+            self.finish_node();
             l = ast::Expr::BinaryOp {
                 l: Box::new(l),
                 op,
                 r: Box::new(r),
+                span: self.span_from(start),
             };
         }
 
@@ -543,34 +678,54 @@ impl<R: Read> Parser<R> {
 
     /// Parses unary expressions.
     fn unary_expr(&mut self) -> io::Result<ast::Expr> {
-        let line = self.current.line;
-        let column = self.current.column;
+        let start = self.current.span;
 
         let e = match self.current.token_type {
             TokenType::Plus => {
+                self.start_node(NodeKind::UnaryExpr);
                 self.next()?;
-                self.primary_expr()?
+                let expr = self.primary_expr()?;
+                self.finish_node();
+                expr
             }
             TokenType::Minus => {
-                let op = ast::UnOp::Neg { line, column };
+                let op = ast::UnOp::Neg { span: start };
+                self.start_node(NodeKind::UnaryExpr);
                 self.next()?;
 
                 let expr = Box::new(self.primary_expr()?);
-                ast::Expr::UnaryOp { op, expr }
+                self.finish_node();
+                ast::Expr::UnaryOp {
+                    op,
+                    expr,
+                    span: self.span_from(start),
+                }
             }
             TokenType::KwNot => {
-                let op = ast::UnOp::LogNot { line, column };
+                let op = ast::UnOp::LogNot { span: start };
+                self.start_node(NodeKind::UnaryExpr);
                 self.next()?;
 
                 let expr = Box::new(self.primary_expr()?);
-                ast::Expr::UnaryOp { op, expr }
+                self.finish_node();
+                ast::Expr::UnaryOp {
+                    op,
+                    expr,
+                    span: self.span_from(start),
+                }
             }
             TokenType::Tilde => {
-                let op = ast::UnOp::BitNot { line, column };
+                let op = ast::UnOp::BitNot { span: start };
+                self.start_node(NodeKind::UnaryExpr);
                 self.next()?;
 
                 let expr = Box::new(self.primary_expr()?);
-                ast::Expr::UnaryOp { op, expr }
+                self.finish_node();
+                ast::Expr::UnaryOp {
+                    op,
+                    expr,
+                    span: self.span_from(start),
+                }
             }
             _ => self.primary_expr()?,
         };
@@ -580,185 +735,99 @@ impl<R: Read> Parser<R> {
 
     /// Parses the primary expressions.
     fn primary_expr(&mut self) -> io::Result<ast::Expr> {
-        let line = self.current.line;
-        let column = self.current.column;
+        let span = self.current.span;
 
-        let e = match &self.current.token_type {
-            TokenType::LiteralStr(s) => {
-                let raw = s.trim_matches('"').to_string();
-                let value = raw
-                    .replace("\\n", "\n")
-                    .replace("\\\"", "\"")
-                    .replace("\\t", "\t")
-                    .replace("\\\\", "\\")
-                    .replace("\\r", "\r")
-                    .replace("\\'", "'")
-                    .replace("\\0", "\0");
+        if let Some(literal) = literal(&self.current.token_type, span) {
+            let expr = literal.unwrap_or_else(|msg| {
+                self.error(msg, span);
+                ast::Expr::Error { span }
+            });
 
-                self.next()?;
-
-                ast::Expr::LiteralStr {
-                    value,
-                    line,
-                    column,
-                }
-            }
-            TokenType::LiteralChar(c) => {
-                let trimmed = c.trim_matches('\'');
-
-                let parsed_char = match trimmed.len() {
-                    1 => trimmed.chars().next(),
-                    _ => match trimmed {
-                        "\\n" => Some('\n'),
-                        "\\'" => Some('\''),
-                        "\\\"" => Some('"'),
-                        "\\t" => Some('\t'),
-                        "\\\\" => Some('\\'),
-                        "\\r" => Some('\r'),
-                        "\\0" => Some('\0'),
-                        _ => None,
-                    },
-                };
-
-                self.next()?;
-
-                match parsed_char {
-                    Some(value) => ast::Expr::LiteralChar {
-                        value,
-                        line,
-                        column,
-                    },
-                    None => {
-                        self.syntax_error("Invalid character".into());
-                        ast::Expr::Error { line, column }
-                    }
-                }
-            }
-            TokenType::LiteralFloat(f) => {
-                let expr = match f.parse::<f64>() {
-                    Ok(value) => ast::Expr::LiteralFloat { value, line, column },
-                    Err(e) => {
-                        self.syntax_error(format!("Invalid integer, {}", e.to_string()));
-                        ast::Expr::Error { line, column }
-                    }
-                };
-
-                self.next()?;
-
-                expr
-            }
-            TokenType::LiteralIntDec(n) => {
-                let expr = match i64::from_str_radix(&n, 10) {
-                    Ok(value) => ast::Expr::LiteralInt { value, line, column },
-                    Err(e) => {
-                        self.syntax_error(format!("Invalid integer, {}", e.to_string()));
-                        ast::Expr::Error { line, column }
-                    }
-                };
-
-                self.next()?;
-                expr
-            }
-            TokenType::LiteralIntHex(n) => {
-                let trimmed = &n[2..];
-
-                let expr = match i64::from_str_radix(trimmed, 16) {
-                    Ok(value) => ast::Expr::LiteralInt { value, line, column },
-                    Err(e) => {
-                        self.syntax_error(format!("Invalid integer, {}", e.to_string()));
-                        ast::Expr::Error { line, column }
-                    }
-                };
-
-                self.next()?;
-
-                expr
-            }
-            TokenType::LiteralIntBin(n) => {
-                let trimmed = &n[2..];
-
-                let expr = match i64::from_str_radix(trimmed, 2) {
-                    Ok(value) => ast::Expr::LiteralInt { value, line, column },
-                    Err(e) => {
-                        self.syntax_error(format!("Invalid integer, {}", e.to_string()));
-                        ast::Expr::Error { line, column }
-                    }
-                };
-
-                self.next()?;
-
-                expr
-            }
-            TokenType::LiteralIntOct(n) => {
-                let trimmed = &n[2..];
-
-                let expr = match i64::from_str_radix(trimmed, 8) {
-                    Ok(value) => ast::Expr::LiteralInt { value, line, column },
-                    Err(e) => {
-                        self.syntax_error(format!("Invalid integer, {}", e.to_string()));
-                        ast::Expr::Error { line, column }
-                    }
-                };
+            self.start_node(NodeKind::Literal);
+            self.next()?;
+            self.finish_node();
 
-                self.next()?;
+            return Ok(expr);
+        }
 
-                expr
-            }
-            TokenType::KwTrue => {
-                self.next()?;
-                ast::Expr::LiteralBool { value: true, line, column }
-            }
-            TokenType::KwFalse => {
-                self.next()?;
-                ast::Expr::LiteralBool { value: false, line, column }
-            }
+        let e = match &self.current.token_type {
             TokenType::LBracket => {
                 // array_lit
+                self.start_node(NodeKind::ArrayLiteral);
                 self.next()?;
                 let elements = self.comma_list()?;
                 self.expect(TokenType::RBracket)?;
-                ast::Expr::LiteralArray { elements, line, column }
+                self.finish_node();
+                ast::Expr::LiteralArray {
+                    elements,
+                    span: self.span_from(span),
+                }
             }
             TokenType::LPar => {
+                self.start_node(NodeKind::ParenExpr);
                 self.next()?;
                 let expr = self.expr()?;
                 self.expect(TokenType::RPar)?;
+                self.finish_node();
                 expr
             }
             TokenType::Iden(id) => {
                 let id = id.to_string();
+                let checkpoint = self.checkpoint();
                 self.next()?;
 
                 match self.current.token_type {
                     TokenType::Assign => {
                         // iden = expr
+                        self.start_node_at(checkpoint, NodeKind::AssignExpr);
                         self.next()?;
                         let expr = Box::new(self.expr()?);
-                        ast::Expr::Assign { id, expr, line, column }
+                        self.finish_node();
+                        ast::Expr::Assign {
+                            id,
+                            expr,
+                            span: self.span_from(span),
+                        }
                     }
                     TokenType::LPar => {
                         // iden ( comma_list )
+                        self.start_node_at(checkpoint, NodeKind::CallExpr);
+                        self.start_node(NodeKind::ArgList);
                         self.next()?;
                         let args = self.comma_list()?;
                         self.expect(TokenType::RPar)?;
-                        ast::Expr::Call { id, args, line, column }
+                        self.finish_node();
+                        self.finish_node();
+                        ast::Expr::Call {
+                            id,
+                            args,
+                            span: self.span_from(span),
+                        }
                     }
                     TokenType::LBracket => {
                         // iden [ expr ]
+                        self.start_node_at(checkpoint, NodeKind::IndexExpr);
                         self.next()?;
                         let index = Box::new(self.expr()?);
                         self.expect(TokenType::RBracket)?;
-                        ast::Expr::ArrayExpr { id, index, line, column }
+                        self.finish_node();
+                        ast::Expr::ArrayExpr {
+                            id,
+                            index,
+                            span: self.span_from(span),
+                        }
                     }
                     _ => {
                         // iden
-                        ast::Expr::Identifier { id, line, column }
+                        self.start_node_at(checkpoint, NodeKind::NameRef);
+                        self.finish_node();
+                        ast::Expr::Identifier { id, span }
                     }
                 }
             }
             _ => {
-                self.syntax_error("Expected expression".into());
-                ast::Expr::Error { line, column }
+                self.syntax_error("Expected expression".into(), &["expression"]);
+                ast::Expr::Error { span }
             }
         };
 
@@ -788,3 +857,66 @@ impl<R: Read> Parser<R> {
         Ok(lst)
     }
 }
+
+/// Converts a literal token to an expression. Returns `None` if the token is
+/// not a literal, or the error message if the literal is invalid.
+pub(crate) fn literal(token_type: &TokenType, span: Span) -> Option<Result<ast::Expr, String>> {
+    let expr = match token_type {
+        TokenType::LiteralStr(s) => {
+            let raw = s.trim_matches('"').to_string();
+            let value = raw
+                .replace("\\n", "\n")
+                .replace("\\\"", "\"")
+                .replace("\\t", "\t")
+                .replace("\\\\", "\\")
+                .replace("\\r", "\r")
+                .replace("\\'", "'")
+                .replace("\\0", "\0");
+
+            Ok(ast::Expr::LiteralStr { value, span })
+        }
+        TokenType::LiteralChar(c) => {
+            let trimmed = c.trim_matches('\'');
+
+            let parsed_char = match trimmed.chars().count() {
+                1 => trimmed.chars().next(),
+                _ => match trimmed {
+                    "\\n" => Some('\n'),
+                    "\\'" => Some('\''),
+                    "\\\"" => Some('"'),
+                    "\\t" => Some('\t'),
+                    "\\\\" => Some('\\'),
+                    "\\r" => Some('\r'),
+                    "\\0" => Some('\0'),
+                    _ => None,
+                },
+            };
+
+            match parsed_char {
+                Some(value) => Ok(ast::Expr::LiteralChar { value, span }),
+                None => Err("Invalid character".into()),
+            }
+        }
+        TokenType::LiteralFloat(f) => match f.parse::<f64>() {
+            Ok(value) => Ok(ast::Expr::LiteralFloat { value, span }),
+            Err(e) => Err(format!("Invalid float, {}", e)),
+        },
+        TokenType::LiteralIntDec(n) => int_literal(n, 10, span),
+        TokenType::LiteralIntHex(n) => int_literal(&n[2..], 16, span),
+        TokenType::LiteralIntBin(n) => int_literal(&n[2..], 2, span),
+        TokenType::LiteralIntOct(n) => int_literal(&n[2..], 8, span),
+        TokenType::KwTrue => Ok(ast::Expr::LiteralBool { value: true, span }),
+        TokenType::KwFalse => Ok(ast::Expr::LiteralBool { value: false, span }),
+        _ => return None,
+    };
+
+    Some(expr)
+}
+
+/// Converts the digits of an integer literal in provided radix to an expression.
+fn int_literal(digits: &str, radix: u32, span: Span) -> Result<ast::Expr, String> {
+    match i64::from_str_radix(digits, radix) {
+        Ok(value) => Ok(ast::Expr::LiteralInt { value, span }),
+        Err(e) => Err(format!("Invalid integer, {}", e)),
+    }
+}
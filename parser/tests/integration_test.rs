@@ -0,0 +1,694 @@
+use std::rc::Rc;
+
+use diagnostics::Diagnostic;
+use lexer::{token::TokenType, Lexer};
+use parser::{
+    ast::{self, fold, visit, Fold, Visitor, VisitorMut},
+    cst::{self, GreenElement, GreenToken, NodeKind, SyntaxNode},
+    dot, Parser,
+};
+
+fn parse(code: &str) -> (Vec<ast::Stmt>, Vec<Diagnostic>) {
+    let l = Lexer::new(code.as_bytes());
+    let mut p = Parser::new(l);
+
+    p.parse().unwrap()
+}
+
+#[test]
+fn valid_code() {
+    let (ast, diagnostics) = parse(
+        "fn add(a: int, b: int): int {
+            return a + b;
+        }
+
+        fn main() {
+            let arr: int[] = [1, 2, 3];
+            let sum: int = 0;
+            for i = 0 to 2 {
+                sum = add(sum, arr[i]);
+            }
+        }",
+    );
+
+    assert!(diagnostics.is_empty());
+    assert_eq!(ast.len(), 2);
+}
+
+#[test]
+fn reports_all_errors() {
+    let (ast, diagnostics) = parse(
+        "fn main() {
+            let a: int = ;
+            let b: int = 2
+            let c: = 3;
+            a = b +;
+        }
+
+        fn second() {
+            return 1;
+        }",
+    );
+
+    let positions: Vec<(usize, usize)> = diagnostics
+        .iter()
+        .map(|d| (d.span.line, d.span.column))
+        .collect();
+    assert_eq!(positions, vec![(2, 26), (4, 13), (5, 20)]);
+
+    assert_eq!(diagnostics[0].message, "Expected expression");
+    assert_eq!(diagnostics[0].expected, vec!["expression"]);
+    assert_eq!(diagnostics[1].message, "Expected `;`");
+    assert_eq!(diagnostics[1].expected, vec!["`;`"]);
+
+    // Both functions are still in the (partial) tree.
+    assert_eq!(ast.len(), 2);
+}
+
+#[test]
+fn partial_tree() {
+    let (ast, diagnostics) = parse("fn main() { let a: = 1 + ; }");
+
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].expected.len(), 5);
+
+    match &ast[0] {
+        ast::Stmt::FnDef { body, .. } => match body.as_ref() {
+            ast::Stmt::Block { stmts, .. } => match &stmts[0] {
+                ast::Stmt::Let { var_type, .. } => {
+                    assert!(matches!(var_type, ast::Type::Error { .. }));
+                }
+                _ => panic!("Expected let statement"),
+            },
+            _ => panic!("Expected block"),
+        },
+        _ => panic!("Expected function"),
+    }
+}
+
+#[test]
+fn error_expression() {
+    let (ast, diagnostics) = parse("fn main() { a = ; }");
+
+    assert_eq!(diagnostics.len(), 1);
+
+    let ast::Stmt::FnDef { body, .. } = &ast[0] else {
+        panic!("Expected function");
+    };
+    let ast::Stmt::Block { stmts, .. } = body.as_ref() else {
+        panic!("Expected block");
+    };
+    let ast::Stmt::Expr {
+        expr: ast::Expr::Assign { expr, .. },
+        ..
+    } = &stmts[0]
+    else {
+        panic!("Expected assignment");
+    };
+    assert!(matches!(expr.as_ref(), ast::Expr::Error { .. }));
+}
+
+#[test]
+fn recovers_at_fn() {
+    let (ast, diagnostics) = parse(
+        "let x: int = 0;
+        fn first() {
+            if { }
+        fn second() {}
+        }",
+    );
+
+    let messages: Vec<&str> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+    assert_eq!(
+        messages,
+        vec!["Expected `fn`", "Expected expression", "Expected `}`", "Expected `fn`"]
+    );
+    assert_eq!(ast.len(), 2);
+}
+
+#[test]
+fn items() {
+    let code = "let x: int = 0; } fn f() {} x = 1 f();";
+    let (items, diagnostics) = Parser::new(Lexer::new(code.as_bytes()))
+        .parse_items()
+        .unwrap();
+
+    let messages: Vec<&str> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+    assert_eq!(messages, vec!["Unexpected `}`", "Expected `;`"]);
+    assert_eq!(items.len(), 3);
+    assert!(matches!(items[1], ast::Stmt::FnDef { .. }));
+
+    let (expr, diagnostics) = Parser::new(Lexer::new("f(1) x".as_bytes()))
+        .parse_expr()
+        .unwrap();
+    assert_eq!(diagnostics[0].message, "Expected end of file");
+    assert!(matches!(expr, ast::Expr::Call { .. }));
+}
+
+#[test]
+fn recovers_without_progress() {
+    // Statements which consume nothing after a terminator are skipped.
+    let codes = [
+        "fn main() { x; else }",
+        "fn main() { -{} else }",
+        "fn f() { let x: int = 1; else; }",
+    ];
+    for code in codes {
+        let (ast, diagnostics) = parse(code);
+        assert_eq!(ast.len(), 1, "{}", code);
+        assert_eq!(diagnostics[0].message, "Expected expression", "{}", code);
+    }
+
+    let (items, diagnostics) = Parser::new(Lexer::new("x; else y; } -{} else".as_bytes()))
+        .parse_items()
+        .unwrap();
+    let messages: Vec<&str> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+    assert_eq!(
+        messages,
+        vec![
+            "Expected expression",
+            "Unexpected `}`",
+            "Expected expression",
+            "Expected expression"
+        ]
+    );
+    assert_eq!(items.len(), 4);
+}
+
+#[test]
+fn invalid_tokens() {
+    let (ast, diagnostics) = parse("fn main() { let a: char = '\\q'; @ }");
+
+    assert_eq!(ast.len(), 1);
+    assert_eq!(diagnostics.len(), 2);
+    assert_eq!(diagnostics[0].message, "Invalid character");
+    assert_eq!(diagnostics[1].message, "Invalid token `@`");
+}
+
+#[test]
+fn unexpected_eof() {
+    let (ast, diagnostics) = parse("fn main() { let a: int = 1;");
+
+    assert_eq!(ast.len(), 1);
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].message, "Expected `}`");
+}
+
+#[test]
+fn unicode_literals() {
+    let (ast, diagnostics) = parse("fn main() { let c: char = 'é'; let s: str = \"درود\"; }");
+
+    assert!(diagnostics.is_empty());
+
+    let ast::Stmt::FnDef { body, .. } = &ast[0] else {
+        panic!("Expected function");
+    };
+    let ast::Stmt::Block { stmts, .. } = body.as_ref() else {
+        panic!("Expected block");
+    };
+    assert!(matches!(
+        &stmts[0],
+        ast::Stmt::Let { expr: Some(ast::Expr::LiteralChar { value: 'é', .. }), .. }
+    ));
+    assert!(matches!(
+        &stmts[1],
+        ast::Stmt::Let { expr: Some(ast::Expr::LiteralStr { value, .. }), .. } if value == "درود"
+    ));
+}
+
+#[test]
+fn invalid_utf8() {
+    let l = Lexer::new(&b"fn main() { let c: char = '\xFF'; }"[..]);
+    let (_, diagnostics) = Parser::new(l).parse().unwrap();
+
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].message, "Invalid UTF-8 sequence `\\xFF`");
+    assert_eq!((diagnostics[0].span.line, diagnostics[0].span.column), (1, 28));
+}
+
+#[test]
+fn spans() {
+    let code = "fn f(a: int): int[] {\n    return [(a + 1) * 2, -a];\n}";
+    let (ast, diagnostics) = parse(code);
+    assert!(diagnostics.is_empty());
+
+    let text = |span: diagnostics::Span| &code[span.start..span.end];
+
+    let ast::Stmt::FnDef {
+        return_type: Some(return_type),
+        body,
+        span,
+        ..
+    } = &ast[0]
+    else {
+        panic!("Expected function");
+    };
+    assert_eq!(text(*span), code);
+    assert_eq!((span.end_line, span.end_column), (3, 2));
+    assert_eq!(text(return_type.span()), "int[]");
+    assert_eq!(text(body.span()), &code[20..]);
+
+    let ast::Stmt::Block { stmts, .. } = body.as_ref() else {
+        panic!("Expected block");
+    };
+    let ast::Stmt::Return { expr, span } = &stmts[0] else {
+        panic!("Expected return statement");
+    };
+    assert_eq!(text(*span), "return [(a + 1) * 2, -a];");
+    assert_eq!((span.line, span.column, span.end_column), (2, 5, 30));
+    assert_eq!(text(expr.span()), "[(a + 1) * 2, -a]");
+
+    let ast::Expr::LiteralArray { elements, .. } = expr else {
+        panic!("Expected array literal");
+    };
+    assert_eq!(text(elements[0].span()), "(a + 1) * 2");
+    assert_eq!(text(elements[1].span()), "-a");
+
+    let ast::Expr::BinaryOp { l, op, .. } = &elements[0] else {
+        panic!("Expected binary operation");
+    };
+    assert_eq!(text(l.span()), "a + 1");
+    assert_eq!(text(op.span()), "*");
+}
+
+#[test]
+fn format() {
+    let code = "\
+// Adds numbers.
+fn add(a:int,b:int):int{return a+b;} /* trailing */
+
+
+
+fn main() {
+    let x: int = -(1 + 2) * (3 - (4 - 5)); // Keeps literals: 0xff
+    if x > 0 and not (x == 5) { println(x); } else if x < 0 println(0b11); else {
+        // Nothing
+
+
+        ;
+    }
+    x = add(/* moved */ x, 1);
+    println(\"first argument which is long enough to wrap\", add(x, 2), add(add(x, 3), add(4, 5)), x, x);
+}
+";
+    let formatted = parser::format::format(code).unwrap();
+    assert_eq!(
+        formatted,
+        "\
+// Adds numbers.
+fn add(a: int, b: int): int {
+    return a + b;
+} /* trailing */
+
+fn main() {
+    let x: int = -(1 + 2) * (3 - (4 - 5)); // Keeps literals: 0xff
+    if x > 0 and not (x == 5) {
+        println(x);
+    } else if x < 0 println(0b11); else {
+        // Nothing
+
+        ;
+    }
+    /* moved */
+    x = add(x, 1);
+    println(
+        \"first argument which is long enough to wrap\",
+        add(x, 2),
+        add(add(x, 3), add(4, 5)),
+        x,
+        x
+    );
+}
+"
+    );
+    assert_eq!(parser::format::format(&formatted).unwrap(), formatted);
+
+    // The formatted code has the same syntax tree.
+    let (ast, _) = parse(code);
+    let (formatted_ast, _) = parse(&formatted);
+    let print = |ast: &[ast::Stmt]| ast.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+    assert_eq!(print(&ast), print(&formatted_ast));
+}
+
+#[test]
+fn format_blank_lines() {
+    // Comments moved out of conditions and from before `else` are not
+    // separated by blank lines which are not in the source.
+    let code = "\
+fn main() {
+    let x: int = 1;
+    if x /* cond */ > 0 {
+        x = 2;
+    }
+    /* before else */
+    else {
+        x = 3;
+    }
+
+    while x /* loop */ > 0
+        x = x - 1;
+}
+";
+    let formatted = parser::format::format(code).unwrap();
+    assert_eq!(
+        formatted,
+        "\
+fn main() {
+    let x: int = 1;
+    /* cond */
+    /* before else */
+    if x > 0 {
+        x = 2;
+    } else {
+        x = 3;
+    }
+
+    /* loop */
+    while x > 0 x = x - 1;
+}
+"
+    );
+    assert_eq!(parser::format::format(&formatted).unwrap(), formatted);
+}
+
+#[test]
+fn format_errors() {
+    assert_eq!(parser::format::format("").unwrap(), "");
+    assert_eq!(
+        parser::format::format("// Only\n\n\n// comments").unwrap(),
+        "// Only\n\n// comments\n"
+    );
+
+    let diagnostics = parser::format::format("fn main() { let x: int = ; }").unwrap_err();
+    assert_eq!(diagnostics[0].message, "Expected expression");
+}
+
+#[test]
+fn cst_lossless() {
+    let sources = [
+        "",
+        "  // only a comment\n",
+        "/* header */\nfn main() {\n\tlet x: int[] = [1, 2,]; // trailing\r\n    f(x[0], -(1 + 2));\n}\n",
+        "fn main() { let x: int = 1 +; y = ; if (x { } } fn g(a: int, : ) { return 0x; } } @ let",
+        "x fn f() { '\\q'; $ } }\0 after null",
+    ];
+
+    for source in sources {
+        let (root, diagnostics) = cst::parse(source);
+        assert_eq!(root.text(), source);
+        assert_eq!(root.kind(), NodeKind::Program);
+
+        // The same errors as the parser.
+        let (ast, expected) = parse(source);
+        let messages = |d: &[Diagnostic]| {
+            d.iter()
+                .map(|d| (d.message.clone(), d.span))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(messages(&diagnostics), messages(&expected), "{:?}", source);
+
+        // Lowering gives the same statements.
+        let print = |ast: &[ast::Stmt]| ast.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        assert_eq!(print(&cst::lower(&root)), print(&ast), "{:?}", source);
+    }
+}
+
+#[test]
+fn cst_lowering() {
+    let source = "\
+// Computes Fibonacci numbers.
+fn fib(n: int): int {
+    if n < 2 { return n; } else return fib(n - 1) + fib(n - 2);
+}
+
+/* entry */ fn main() {
+    let a: int[] = [1, 0x1f, 0b101, 0o17,];   // trailing
+    let s: str = \"h\\\"i\"; let c: char = '\\n'; let f: float = .5e3;
+    let b: bool = not true or false and 1 == 2 | 3 & 4 != 5 <= 6 >= 7 > -8 * (9 % +10) / ~11;
+    for i = 0 to len(a) - 1 {
+        b = a[i] * 2 > 0;
+        ;
+    }
+    while b { b = false; }
+    println(fib(10));
+}
+";
+    let (root, diagnostics) = cst::parse(source);
+    assert!(diagnostics.is_empty());
+
+    // Equal including spans.
+    let (ast, _) = parse(source);
+    assert!(cst::lower(&root) == ast);
+
+    let call = root
+        .descendants()
+        .into_iter()
+        .find(|n| n.kind() == NodeKind::CallExpr)
+        .unwrap();
+    assert_eq!(call.text(), "fib(n - 1)");
+    assert_eq!(&source[call.range()], "fib(n - 1)");
+    assert_eq!(call.parent().unwrap().kind(), NodeKind::BinaryExpr);
+    assert_eq!(cst::lower_expr(&call).unwrap().to_string(), "fib((n - 1))");
+    assert!(cst::lower_stmt(&call).is_none());
+}
+
+#[test]
+fn cst_edit() {
+    let source = "fn main() {\n    // Print.\n    println(1 +  2); /* done */\n}\n";
+    let (root, _) = cst::parse(source);
+
+    // Replace a token.
+    let two = root.tokens().into_iter().find(|t| t.text() == "2").unwrap();
+    let edited = two.replace_with(GreenToken::new(TokenType::Iden(()), "x"));
+    assert_eq!(
+        edited.to_string(),
+        "fn main() {\n    // Print.\n    println(1 +  x); /* done */\n}\n"
+    );
+
+    // Replace a node with a node of another tree.
+    let (other, _) = cst::parse("fn f() { g(a[0] * b); }");
+    let index = other
+        .descendants()
+        .into_iter()
+        .find(|n| n.kind() == NodeKind::BinaryExpr)
+        .unwrap();
+    let sum = root
+        .descendants()
+        .into_iter()
+        .find(|n| n.kind() == NodeKind::BinaryExpr)
+        .unwrap();
+    let edited = SyntaxNode::new_root(sum.replace_with(index.green().clone()));
+    assert_eq!(
+        edited.text(),
+        "fn main() {\n    // Print.\n    println(a[0] * b); /* done */\n}\n"
+    );
+
+    // The unchanged parts of the tree are shared.
+    let function = |root: &SyntaxNode| root.child_nodes()[0].clone();
+    let block = |root: &SyntaxNode| function(root).child_nodes()[1].green().children()[0].clone();
+    match (block(&root), block(&edited)) {
+        (GreenElement::Token(a), GreenElement::Token(b)) => assert!(Rc::ptr_eq(&a, &b)),
+        _ => panic!("Expected `{{`"),
+    }
+    assert!(cst::lower(&edited)[0]
+        .to_string()
+        .contains("println((a[0] * b))"));
+}
+
+#[test]
+fn visitors() {
+    let code = "\
+fn f(a: int[], b: bool): float[] {
+    let x: int = -a[0] * (2 + 3);
+    if b and not false { x = f(a, b); } else { return [1.5, 2.]; }
+    for i = 0 to ~x { while i < 3 | 1 { ; } }
+}";
+    let (ast, diagnostics) = parse(code);
+    assert!(diagnostics.is_empty());
+
+    /// Records the visited nodes.
+    #[derive(Default)]
+    struct Record(Vec<String>);
+
+    impl Visitor for Record {
+        fn visit_expr(&mut self, expr: &ast::Expr) {
+            self.0.push(expr.to_string());
+            visit::walk_expr(self, expr);
+        }
+
+        fn visit_type(&mut self, t: &ast::Type) {
+            self.0.push(t.to_string());
+            visit::walk_type(self, t);
+        }
+
+        fn visit_bin_op(&mut self, op: &ast::BinOp) {
+            self.0.push(op.to_string());
+        }
+
+        fn visit_un_op(&mut self, op: &ast::UnOp) {
+            self.0.push(op.to_string());
+        }
+    }
+
+    let mut record = Record::default();
+    ast.iter().for_each(|s| record.visit_stmt(s));
+    assert_eq!(
+        record.0[..12],
+        [
+            "int[]",
+            "int",
+            "bool",
+            "float[]",
+            "float",
+            "int",
+            "((- a[0]) * (2 + 3))",
+            "(- a[0])",
+            "-",
+            "a[0]",
+            "0",
+            "*"
+        ]
+    );
+    assert_eq!(record.0.len(), 40);
+
+    /// Negates integer literals.
+    struct Negate;
+
+    impl VisitorMut for Negate {
+        fn visit_expr_mut(&mut self, expr: &mut ast::Expr) {
+            if let ast::Expr::LiteralInt { value, .. } = expr {
+                *value = -*value;
+            }
+            visit::walk_expr_mut(self, expr);
+        }
+    }
+
+    let (mut negated, _) = parse(code);
+    negated.iter_mut().for_each(|s| Negate.visit_stmt_mut(s));
+    assert!(negated != ast);
+    assert!(negated[0].to_string().contains("((- a[0]) * (-2 + -3))"));
+    negated.iter_mut().for_each(|s| Negate.visit_stmt_mut(s));
+    assert!(negated == ast);
+}
+
+#[test]
+fn fold() {
+    let code = "fn main() { let a: int[] = [1 - 2, f(3 - x)]; while 4 - 5 > 0 { a = -(6 - 7); } }";
+    let (ast, _) = parse(code);
+
+    // The default methods rebuild the same tree.
+    struct Identity;
+    impl Fold for Identity {}
+    let (original, _) = parse(code);
+    let rebuilt: Vec<_> = original
+        .into_iter()
+        .map(|s| Identity.fold_stmt(s))
+        .collect();
+    assert!(rebuilt == ast);
+
+    /// Swaps the operands of subtractions and turns them into additions.
+    struct Swap;
+
+    impl Fold for Swap {
+        fn fold_expr(&mut self, expr: ast::Expr) -> ast::Expr {
+            match fold::walk_expr(self, expr) {
+                ast::Expr::BinaryOp { l, op, r, span } => ast::Expr::BinaryOp {
+                    l: r,
+                    op,
+                    r: l,
+                    span,
+                },
+                expr => expr,
+            }
+        }
+
+        fn fold_bin_op(&mut self, op: ast::BinOp) -> ast::BinOp {
+            match op {
+                ast::BinOp::Sub { span } => ast::BinOp::Add { span },
+                op => op,
+            }
+        }
+    }
+
+    let folded = ast
+        .into_iter()
+        .map(|s| Swap.fold_stmt(s).to_string())
+        .collect::<String>();
+    assert!(folded.contains("[(2 + 1), f((x + 3))]"), "{}", folded);
+    assert!(folded.contains("(0 > (5 + 4))"), "{}", folded);
+    assert!(folded.contains("(- (7 + 6))"), "{}", folded);
+}
+
+#[test]
+fn dot() {
+    let (ast, _) = parse(
+        "fn f(n: int): int {
+    let s: int = 0;
+    for i = 1 to n {
+        if i % 2 == 0 {
+            s = s + i;
+        }
+    }
+    while s > 10 {
+        s = s - 10;
+    }
+    return s;
+    println(\"dead\");
+}",
+    );
+
+    let tree = dot::syntax_tree(&ast);
+    assert!(tree.starts_with("digraph ast {\n"));
+    assert!(tree.ends_with("}\n"));
+    assert!(tree.contains("n1 [label=\"FnDef f\\n1:1\", shape=box];\n    n0 -> n1;\n"));
+    assert!(tree.contains("n5 [label=\"For i\\n3:5\", shape=box];\n    n2 -> n5;\n"));
+    assert!(tree.contains("[label=\"LiteralStr \\\"dead\\\"\\n12:13\", shape=ellipse];"));
+    // Every node but the root has one parent.
+    let nodes = tree.matches(" [label=").count();
+    assert_eq!(tree.matches(" -> ").count(), nodes - 1);
+
+    let cfg = dot::control_flow(&ast);
+    let edges: Vec<_> = cfg
+        .lines()
+        .filter(|l| l.contains(" -> "))
+        .map(str::trim)
+        .collect();
+    assert!(cfg.contains("label=\"fn f(n: int): int\";"));
+    assert!(cfg.contains("f0_b3 [label=\"3: for i = 1 to n\\l\"];"));
+    assert!(cfg.contains("f0_b8 [label=\"11: return s\\l\"];"));
+    assert!(cfg.contains("f0_b9 [label=\"12: println(\\\"dead\\\")\\l\"];"));
+    assert_eq!(
+        edges,
+        [
+            "f0_b0 -> f0_b2;",
+            "f0_b2 -> f0_b3;",
+            "f0_b3 -> f0_b4 [label=\"loop\"];",
+            "f0_b4 -> f0_b5 [label=\"true\"];",
+            "f0_b5 -> f0_b3;",
+            "f0_b4 -> f0_b3 [label=\"false\"];",
+            "f0_b3 -> f0_b6 [label=\"done\"];",
+            "f0_b6 -> f0_b7 [label=\"true\"];",
+            "f0_b7 -> f0_b6;",
+            "f0_b6 -> f0_b8 [label=\"false\"];",
+            "f0_b8 -> f0_b1;",
+            "f0_b9 -> f0_b1;",
+        ]
+    );
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serde() {
+    let (ast, diagnostics) = parse(
+        "fn f(a: int[], b: bool): float { if not b { return -a[0] + 1.5; } else { x = 'c'; } }",
+    );
+    assert!(diagnostics.is_empty());
+
+    let json = serde_json::to_string(&ast).unwrap();
+    assert!(
+        json.starts_with(r#"[{"FnDef":{"id":"f","params":[["a",{"Array":{"element_type":{"Int":"#)
+    );
+    assert!(json.contains(r#"{"UnaryOp":{"op":{"LogNot":{"span":"#));
+    assert!(json.contains(r#"{"LiteralChar":{"value":"c","span":"#));
+    assert_eq!(serde_json::from_str::<Vec<ast::Stmt>>(&json).unwrap(), ast);
+}
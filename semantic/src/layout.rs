@@ -0,0 +1,90 @@
+use std::collections::HashMap;
+
+use crate::resolver::{ScopeId, ScopeKind, ScopeTree, SymbolId};
+
+/// The `FrameLayout` struct assigns a stack slot to each parameter and
+/// variable of a function. Parameters take the first slots, in order.
+/// Variables of scopes which are never open at the same time (like sibling
+/// blocks) share slots, so the frame of the function needs `size` slots.
+///
+/// # Examples
+///
+/// ```
+/// use lexer::Lexer;
+/// use parser::Parser;
+/// use semantic::layout::FrameLayout;
+///
+/// let source = "fn f(n: int) { { let a: int = n; } { let b: int = n; } }".as_bytes();
+/// let (ast, _) = Parser::new(Lexer::new(source)).parse().unwrap();
+/// let (tree, _) = semantic::resolver::resolve(&ast);
+///
+/// let function = tree.scope(tree.global()).children[0];
+/// let layout = FrameLayout::of_function(&tree, function);
+/// assert_eq!(layout.size, 2);
+///
+/// let a = tree.symbols().find(|(_, s)| s.identifier == "a").unwrap().0;
+/// let b = tree.symbols().find(|(_, s)| s.identifier == "b").unwrap().0;
+/// assert_eq!(layout.slot(a), Some(1));
+/// assert_eq!(layout.slot(b), Some(1));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct FrameLayout {
+    /// Slot of each parameter and variable of the function.
+    pub slots: HashMap<SymbolId, usize>,
+    /// Number of slots of the frame.
+    pub size: usize,
+}
+
+impl FrameLayout {
+    /// Computes the layout of the function with provided scope
+    /// (a child of the global scope).
+    ///
+    /// # Panics
+    /// Panics if the scope is not a function scope.
+    pub fn of_function(tree: &ScopeTree, function: ScopeId) -> Self {
+        assert_eq!(
+            tree.scope(function).kind,
+            ScopeKind::Function,
+            "Expected a function scope"
+        );
+
+        let mut layout = Self::default();
+        layout.assign(tree, function, 0);
+        layout
+    }
+
+    /// Returns the slot of a parameter or variable of the function.
+    pub fn slot(&self, symbol: SymbolId) -> Option<usize> {
+        self.slots.get(&symbol).copied()
+    }
+
+    /// Assigns slots to the symbols of a scope and its nested scopes,
+    /// starting from slot `next`.
+    fn assign(&mut self, tree: &ScopeTree, scope: ScopeId, mut next: usize) {
+        let scope = tree.scope(scope);
+
+        // Symbol IDs are in order of declaration.
+        let mut symbols: Vec<SymbolId> = scope.symbols.values().copied().collect();
+        symbols.sort_unstable();
+        for symbol in symbols {
+            self.slots.insert(symbol, next);
+            next += 1;
+        }
+        self.size = self.size.max(next);
+
+        for &child in &scope.children {
+            self.assign(tree, child, next);
+        }
+    }
+}
+
+/// Computes the frame layouts of all functions of a program,
+/// in order of definition.
+pub fn layout(tree: &ScopeTree) -> Vec<FrameLayout> {
+    tree.scope(tree.global())
+        .children
+        .iter()
+        .filter(|&&s| tree.scope(s).kind == ScopeKind::Function)
+        .map(|&s| FrameLayout::of_function(tree, s))
+        .collect()
+}
@@ -1 +1,14 @@
-mod symbol_table;
\ No newline at end of file
+#![warn(missing_docs)]
+
+//! # Kolang semantic analyzer
+//! Utilities for checking the meaning of Kolang code.
+
+/// This module includes the stack frame layout of functions, which is used
+/// by code generators.
+pub mod layout;
+/// This module includes the name resolver which builds the scope tree of Kolang code.
+pub mod resolver;
+/// This module includes the symbol table which stores defined names in nested scopes.
+pub mod symbol_table;
+/// This module includes the static type checker of Kolang code.
+pub mod type_checker;
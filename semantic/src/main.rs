@@ -1,3 +1,56 @@
-fn main() {
-    println!("Hello, world!");
+use std::{
+    fs,
+    io::{self, BufRead, Write},
+};
+
+use diagnostics::{Diagnostic, Renderer, Severity, SourceFile};
+use lexer::Lexer;
+use parser::Parser;
+
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+fn main() -> io::Result<()> {
+    let args: Vec<String> = std::env::args().collect();
+
+    let path = if args.len() > 1 {
+        args[1].clone()
+    } else {
+        println!("⛏️  Kolang semantic analyzer v{}\n", VERSION);
+        println!("Code file path (relative or absolute):");
+        print!(">>> ");
+        io::stdout().flush()?;
+
+        let mut buf = String::new();
+        io::stdin().lock().read_line(&mut buf)?;
+        buf.trim_end().to_string()
+    };
+
+    let source = fs::read(&path)?;
+    let l = Lexer::new(source.as_slice());
+    let mut p = Parser::new(l);
+
+    let (ast, mut diagnostics) = p.parse()?;
+    if diagnostics.is_empty() {
+        let (_, resolve_diagnostics) = semantic::resolver::resolve(&ast);
+        diagnostics = resolve_diagnostics;
+    }
+    if !has_errors(&diagnostics) {
+        diagnostics.append(&mut semantic::type_checker::check(&ast));
+    }
+
+    let text = String::from_utf8_lossy(&source);
+    let file = SourceFile::new(&path, &text);
+    let renderer = Renderer::for_stderr();
+    for d in &diagnostics {
+        eprintln!("{}", renderer.render(d, &file));
+    }
+    if has_errors(&diagnostics) {
+        std::process::exit(1);
+    }
+
+    Ok(())
+}
+
+fn has_errors(diagnostics: &[Diagnostic]) -> bool {
+    diagnostics.iter().any(|d| d.severity == Severity::Error)
 }
@@ -0,0 +1,574 @@
+use std::collections::HashMap;
+
+use diagnostics::{Diagnostic, Span};
+use parser::ast;
+
+/// Names of builtin functions, which are not declared in Kolang code.
+pub const BUILTINS: [&str; 1] = ["println"];
+
+/// Unique identifier of a declared symbol in a [`ScopeTree`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct SymbolId(pub usize);
+
+/// Unique identifier of a scope in a [`ScopeTree`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ScopeId(pub usize);
+
+/// Kind of a declared symbol.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolKind {
+    /// Function declared by `fn`.
+    Function,
+    /// Parameter of a function.
+    Parameter,
+    /// Variable declared by `let`.
+    Variable,
+    /// Variable of a `for` loop.
+    LoopVariable,
+}
+
+/// Information about a declared symbol.
+#[derive(Debug, Clone)]
+pub struct SymbolInfo {
+    /// Name of the symbol.
+    pub identifier: String,
+    /// Kind of the symbol.
+    pub kind: SymbolKind,
+    /// Type of the symbol (return type for functions).
+    /// `None` if the symbol has no type (function which returns nothing).
+    pub symbol_type: Option<ast::Type>,
+    /// Scope which the symbol is declared in.
+    pub scope: ScopeId,
+    /// Source range of the declaration.
+    pub span: Span,
+}
+
+/// Kind of a scope.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScopeKind {
+    /// The root scope, which contains functions.
+    Global,
+    /// Scope of a function, which contains its parameters.
+    Function,
+    /// Scope of a block statement (`{ ... }`).
+    Block,
+    /// Scope of a `for` loop, which contains the loop variable.
+    For,
+}
+
+/// A scope of Kolang code.
+#[derive(Debug, Clone)]
+pub struct Scope {
+    /// Kind of the scope.
+    pub kind: ScopeKind,
+    /// The enclosing scope. `None` for the global scope.
+    pub parent: Option<ScopeId>,
+    /// Scopes directly nested in this scope, in order of appearance.
+    pub children: Vec<ScopeId>,
+    /// Symbols declared in this scope.
+    pub symbols: HashMap<String, SymbolId>,
+    /// Source range of the statement which creates the scope.
+    /// Empty for the global scope, which contains all code.
+    pub span: Span,
+}
+
+/// Policy for declarations which shadow a variable of an enclosing scope.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ShadowingPolicy {
+    /// Shadowing is allowed silently.
+    Allow,
+    /// Shadowing is reported as a warning.
+    #[default]
+    Warn,
+    /// Shadowing is reported as an error.
+    Deny,
+}
+
+/// The `ScopeTree` struct stores all scopes and symbols of a program,
+/// and the symbol which each identifier refers to.
+/// Scopes and symbols are stored in arenas and referred by their IDs.
+#[derive(Debug, Clone)]
+pub struct ScopeTree {
+    scopes: Vec<Scope>,
+    symbols: Vec<SymbolInfo>,
+    /// Resolved symbols of identifier expressions, by their start offset.
+    /// Also stores the source range of the identifier itself.
+    references: HashMap<usize, (Span, SymbolId)>,
+}
+
+impl ScopeTree {
+    /// Creates a new `ScopeTree` which only contains the global scope.
+    fn new() -> Self {
+        Self {
+            scopes: vec![Scope {
+                kind: ScopeKind::Global,
+                parent: None,
+                children: Vec::new(),
+                symbols: HashMap::new(),
+                span: Span::default(),
+            }],
+            symbols: Vec::new(),
+            references: HashMap::new(),
+        }
+    }
+
+    /// Returns the ID of the global scope.
+    pub fn global(&self) -> ScopeId {
+        ScopeId(0)
+    }
+
+    /// Returns the scope with provided ID.
+    ///
+    /// # Panics
+    /// Panics if the ID does not belong to this tree.
+    pub fn scope(&self, id: ScopeId) -> &Scope {
+        &self.scopes[id.0]
+    }
+
+    /// Returns the symbol with provided ID.
+    ///
+    /// # Panics
+    /// Panics if the ID does not belong to this tree.
+    pub fn symbol(&self, id: SymbolId) -> &SymbolInfo {
+        &self.symbols[id.0]
+    }
+
+    /// Returns all symbols of the program with their IDs, in order of declaration.
+    pub fn symbols(&self) -> impl Iterator<Item = (SymbolId, &SymbolInfo)> {
+        self.symbols
+            .iter()
+            .enumerate()
+            .map(|(i, s)| (SymbolId(i), s))
+    }
+
+    /// Finds a variable (or parameter) visible in the provided scope.
+    pub fn lookup_variable(&self, scope: ScopeId, identifier: &str) -> Option<SymbolId> {
+        let mut current = Some(scope);
+        while let Some(s) = current {
+            let scope = self.scope(s);
+            if let Some(&id) = scope.symbols.get(identifier) {
+                if self.symbol(id).kind != SymbolKind::Function {
+                    return Some(id);
+                }
+            }
+            current = scope.parent;
+        }
+
+        None
+    }
+
+    /// Finds a function by its name.
+    pub fn lookup_function(&self, identifier: &str) -> Option<SymbolId> {
+        self.scope(self.global())
+            .symbols
+            .get(identifier)
+            .copied()
+            .filter(|&id| self.symbol(id).kind == SymbolKind::Function)
+    }
+
+    /// Returns the symbol which an identifier, assignment, call or array
+    /// expression refers to. Returns `None` for other expressions,
+    /// undeclared identifiers and builtin functions.
+    pub fn resolution(&self, expr: &ast::Expr) -> Option<SymbolId> {
+        match expr {
+            ast::Expr::Identifier { .. }
+            | ast::Expr::Assign { .. }
+            | ast::Expr::Call { .. }
+            | ast::Expr::ArrayExpr { .. } => self
+                .references
+                .get(&expr.span().start)
+                .map(|&(_, symbol)| symbol),
+            _ => None,
+        }
+    }
+
+    /// Returns source ranges of all identifiers which refer to the provided
+    /// symbol, in order of appearance.
+    pub fn references(&self, symbol: SymbolId) -> Vec<Span> {
+        let mut refs: Vec<Span> = self
+            .references
+            .values()
+            .filter(|&&(_, s)| s == symbol)
+            .map(|&(span, _)| span)
+            .collect();
+        refs.sort_unstable_by_key(|span| span.start);
+        refs
+    }
+
+    /// Returns the source range of the identifier which contains (or ends
+    /// at) the provided byte offset, and the symbol it refers to.
+    pub fn reference_at(&self, offset: usize) -> Option<(Span, SymbolId)> {
+        self.references
+            .values()
+            .find(|(span, _)| span.start <= offset && offset <= span.end)
+            .copied()
+    }
+
+    /// Returns the innermost scope which contains the provided byte offset.
+    pub fn scope_at(&self, offset: usize) -> ScopeId {
+        let mut current = self.global();
+        while let Some(&child) = self.scope(current).children.iter().find(|&&c| {
+            let span = self.scope(c).span;
+            span.start <= offset && offset < span.end
+        }) {
+            current = child;
+        }
+        current
+    }
+
+    /// Returns the symbols which code at the provided byte offset in the
+    /// provided scope may refer to: all functions, and the variables of the
+    /// scope and its enclosing scopes which are declared before the offset.
+    /// Variables shadow variables of enclosing scopes with the same name.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use lexer::Lexer;
+    /// use parser::Parser;
+    /// use semantic::resolver;
+    ///
+    /// let source = "fn f(a: int) { let b: int = a; { let a: str = \"\"; } }";
+    /// let (ast, _) = Parser::new(Lexer::new(source.as_bytes())).parse().unwrap();
+    /// let (tree, _) = resolver::resolve(&ast);
+    ///
+    /// let offset = source.find("{ let a").unwrap() + 1;
+    /// let visible = tree.visible_symbols(tree.scope_at(offset), offset);
+    /// let names: Vec<&str> = visible.iter().map(|&s| tree.symbol(s).identifier.as_str()).collect();
+    /// assert_eq!(names, ["b", "a", "f"]);
+    /// ```
+    pub fn visible_symbols(&self, scope: ScopeId, offset: usize) -> Vec<SymbolId> {
+        let mut visible: Vec<SymbolId> = Vec::new();
+        let mut current = Some(scope);
+        while let Some(s) = current {
+            let scope = self.scope(s);
+            let mut symbols: Vec<SymbolId> = scope
+                .symbols
+                .values()
+                .copied()
+                .filter(|&id| {
+                    let symbol = self.symbol(id);
+                    symbol.kind == SymbolKind::Function || symbol.span.start < offset
+                })
+                .filter(|&id| {
+                    let name = &self.symbol(id).identifier;
+                    !visible.iter().any(|&v| &self.symbol(v).identifier == name)
+                })
+                .collect();
+            symbols.sort_unstable();
+            visible.append(&mut symbols);
+            current = scope.parent;
+        }
+        visible
+    }
+}
+
+/// The `Resolver` struct walks a Kolang program, builds its [`ScopeTree`]
+/// and resolves every identifier to the symbol it refers to.
+///
+/// # Examples
+///
+/// ```
+/// use lexer::Lexer;
+/// use parser::Parser;
+/// use semantic::resolver::{Resolver, ShadowingPolicy, SymbolKind};
+///
+/// let source = "fn main() { let a: int = 1; { let a: int = a; } }".as_bytes();
+/// let (ast, _) = Parser::new(Lexer::new(source)).parse().unwrap();
+///
+/// let (tree, diagnostics) = Resolver::new()
+///     .with_shadowing(ShadowingPolicy::Deny)
+///     .resolve(&ast);
+///
+/// assert_eq!(diagnostics[0].message, "Variable `a` shadows a variable declared at 1:13");
+///
+/// let outer = tree.symbols().find(|(_, s)| s.kind == SymbolKind::Variable).unwrap().0;
+/// assert_eq!((tree.symbol(outer).span.line, tree.symbol(outer).span.column), (1, 13));
+/// assert_eq!(tree.references(outer)[0].column, 44);
+/// ```
+pub struct Resolver {
+    tree: ScopeTree,
+    current: ScopeId,
+    shadowing: ShadowingPolicy,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Default for Resolver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Resolver {
+    /// Creates a new `Resolver` with the default [`ShadowingPolicy`].
+    pub fn new() -> Self {
+        let tree = ScopeTree::new();
+        let current = tree.global();
+
+        Self {
+            tree,
+            current,
+            shadowing: ShadowingPolicy::default(),
+            diagnostics: Vec::new(),
+        }
+    }
+
+    /// Sets how declarations which shadow variables of enclosing scopes are reported.
+    pub fn with_shadowing(mut self, policy: ShadowingPolicy) -> Self {
+        self.shadowing = policy;
+        self
+    }
+
+    /// Resolves a program and returns its scope tree and the errors found.
+    /// All functions are declared before resolving function bodies, so
+    /// functions may be called before their definition.
+    pub fn resolve(mut self, prog: &[ast::Stmt]) -> (ScopeTree, Vec<Diagnostic>) {
+        for stmt in prog {
+            if let ast::Stmt::FnDef {
+                id,
+                return_type,
+                span,
+                ..
+            } = stmt
+            {
+                if BUILTINS.contains(&id.as_str()) {
+                    self.error(format!("Function `{}` is a builtin function", id), *span);
+                } else if let Some(f) = self.tree.lookup_function(id) {
+                    let d =
+                        Diagnostic::error(format!("Function `{}` is already defined", id), *span)
+                            .with_label(
+                                self.tree.symbol(f).span,
+                                "previous definition here".into(),
+                            );
+                    self.diagnostics.push(d);
+                } else {
+                    self.declare(id, SymbolKind::Function, return_type.clone(), *span);
+                }
+            }
+        }
+
+        for stmt in prog {
+            self.resolve_stmt(stmt);
+        }
+
+        (self.tree, self.diagnostics)
+    }
+
+    /// Resolves identifiers of a statement.
+    fn resolve_stmt(&mut self, stmt: &ast::Stmt) {
+        match stmt {
+            ast::Stmt::Let {
+                id,
+                var_type,
+                expr,
+                span,
+            } => {
+                if let Some(e) = expr {
+                    self.resolve_expr(e);
+                }
+                self.declare_variable(id, SymbolKind::Variable, var_type, *span);
+            }
+            ast::Stmt::Expr { expr, .. } => self.resolve_expr(expr),
+            ast::Stmt::If {
+                cond,
+                then_stmt,
+                else_stmt,
+                ..
+            } => {
+                self.resolve_expr(cond);
+                self.resolve_stmt(then_stmt);
+                if let Some(els) = else_stmt {
+                    self.resolve_stmt(els);
+                }
+            }
+            ast::Stmt::While { cond, body, .. } => {
+                self.resolve_expr(cond);
+                self.resolve_stmt(body);
+            }
+            ast::Stmt::For {
+                id,
+                start,
+                end,
+                body,
+                span,
+            } => {
+                self.resolve_expr(start);
+                self.resolve_expr(end);
+
+                self.enter_scope(ScopeKind::For, *span);
+                let int = ast::Type::Int { span: *span };
+                self.declare_variable(id, SymbolKind::LoopVariable, &int, *span);
+                self.resolve_stmt(body);
+                self.exit_scope();
+            }
+            ast::Stmt::Return { expr, .. } => self.resolve_expr(expr),
+            ast::Stmt::Block { stmts, span } => {
+                self.enter_scope(ScopeKind::Block, *span);
+                for s in stmts {
+                    self.resolve_stmt(s);
+                }
+                self.exit_scope();
+            }
+            ast::Stmt::FnDef {
+                params, body, span, ..
+            } => {
+                self.enter_scope(ScopeKind::Function, *span);
+                for (param, t) in params {
+                    self.declare_variable(param, SymbolKind::Parameter, t, *span);
+                }
+                self.resolve_stmt(body);
+                self.exit_scope();
+            }
+            ast::Stmt::Empty { .. } => {}
+        }
+    }
+
+    /// Resolves identifiers of an expression.
+    fn resolve_expr(&mut self, expr: &ast::Expr) {
+        let span = expr.span();
+
+        match expr {
+            ast::Expr::LiteralArray { elements, .. } => {
+                for e in elements {
+                    self.resolve_expr(e);
+                }
+            }
+            ast::Expr::BinaryOp { l, r, .. } => {
+                self.resolve_expr(l);
+                self.resolve_expr(r);
+            }
+            ast::Expr::UnaryOp { expr, .. } => self.resolve_expr(expr),
+            ast::Expr::Identifier { id, .. } => self.reference_variable(id, span),
+            ast::Expr::Call { id, args, .. } => {
+                for arg in args {
+                    self.resolve_expr(arg);
+                }
+
+                if let Some(f) = self.tree.lookup_function(id) {
+                    self.add_reference(id, span, f);
+                } else if !BUILTINS.contains(&id.as_str()) {
+                    self.error(format!("Function `{}` is not defined", id), span);
+                }
+            }
+            ast::Expr::ArrayExpr { id, index, .. } => {
+                self.resolve_expr(index);
+                self.reference_variable(id, span);
+            }
+            ast::Expr::Assign { id, expr, .. } => {
+                self.resolve_expr(expr);
+                self.reference_variable(id, span);
+            }
+            ast::Expr::LiteralInt { .. }
+            | ast::Expr::LiteralStr { .. }
+            | ast::Expr::LiteralChar { .. }
+            | ast::Expr::LiteralFloat { .. }
+            | ast::Expr::LiteralBool { .. }
+            | ast::Expr::Error { .. } => {}
+        }
+    }
+
+    /// Records the variable which the expression in provided source range
+    /// (which starts with the identifier) refers to.
+    fn reference_variable(&mut self, id: &str, span: Span) {
+        match self.tree.lookup_variable(self.current, id) {
+            Some(v) => self.add_reference(id, span, v),
+            None => self.error(format!("Variable `{}` is not defined", id), span),
+        }
+    }
+
+    /// Records the symbol which the expression in provided source range
+    /// (which starts with the identifier) refers to.
+    fn add_reference(&mut self, id: &str, span: Span, symbol: SymbolId) {
+        // Identifiers are ASCII, so each character is one byte and one column.
+        let id_span = Span {
+            end: span.start + id.len(),
+            end_line: span.line,
+            end_column: span.column + id.len(),
+            ..span
+        };
+        self.tree.references.insert(span.start, (id_span, symbol));
+    }
+
+    /// Declares a variable (or parameter) in the current scope.
+    fn declare_variable(&mut self, id: &str, kind: SymbolKind, var_type: &ast::Type, span: Span) {
+        if let Some(&previous) = self.tree.scope(self.current).symbols.get(id) {
+            let message = format!("Variable `{}` is already defined in this scope", id);
+            let d = Diagnostic::error(message, span).with_label(
+                self.tree.symbol(previous).span,
+                "previous definition here".into(),
+            );
+            self.diagnostics.push(d);
+            return;
+        }
+
+        if let Some(shadowed) = self.tree.lookup_variable(self.current, id) {
+            let s = self.tree.symbol(shadowed);
+            let message = format!(
+                "Variable `{}` shadows a variable declared at {}:{}",
+                id, s.span.line, s.span.column
+            );
+
+            let d = match self.shadowing {
+                ShadowingPolicy::Allow => None,
+                ShadowingPolicy::Warn => Some(Diagnostic::warning(message, span)),
+                ShadowingPolicy::Deny => Some(Diagnostic::error(message, span)),
+            };
+            if let Some(d) = d {
+                let d = d.with_label(s.span, "shadowed variable declared here".into());
+                self.diagnostics.push(d);
+            }
+        }
+
+        self.declare(id, kind, Some(var_type.clone()), span);
+    }
+
+    /// Adds a symbol to the current scope.
+    fn declare(&mut self, id: &str, kind: SymbolKind, symbol_type: Option<ast::Type>, span: Span) {
+        let symbol = SymbolId(self.tree.symbols.len());
+        self.tree.symbols.push(SymbolInfo {
+            identifier: id.to_string(),
+            kind,
+            symbol_type,
+            scope: self.current,
+            span,
+        });
+        self.tree.scopes[self.current.0]
+            .symbols
+            .insert(id.to_string(), symbol);
+    }
+
+    /// Enters a new scope, which the statement in provided source range
+    /// creates, nested in the current scope.
+    fn enter_scope(&mut self, kind: ScopeKind, span: Span) {
+        let scope = ScopeId(self.tree.scopes.len());
+        self.tree.scopes.push(Scope {
+            kind,
+            parent: Some(self.current),
+            children: Vec::new(),
+            symbols: HashMap::new(),
+            span,
+        });
+        self.tree.scopes[self.current.0].children.push(scope);
+        self.current = scope;
+    }
+
+    /// Returns to the enclosing scope.
+    fn exit_scope(&mut self) {
+        self.current = self
+            .tree
+            .scope(self.current)
+            .parent
+            .expect("Cannot exit the global scope");
+    }
+
+    /// Reports an error in the provided source range.
+    fn error(&mut self, message: String, span: Span) {
+        self.diagnostics.push(Diagnostic::error(message, span));
+    }
+}
+
+/// Resolves a program with the default [`ShadowingPolicy`].
+/// See [`Resolver::resolve`].
+pub fn resolve(prog: &[ast::Stmt]) -> (ScopeTree, Vec<Diagnostic>) {
+    Resolver::new().resolve(prog)
+}
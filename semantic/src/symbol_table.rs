@@ -1,60 +1,108 @@
-use std::collections::HashMap;
+use std::collections::{hash_map::Entry, HashMap};
 
+use diagnostics::Span;
+use parser::ast;
+
+/// A named entity of Kolang code which can be stored in a [`SymbolTable`].
 pub trait Symbol {
+    /// Name of the symbol.
     fn identifier(&self) -> &str;
-    fn symbol_type(&self) -> &str;
+    /// Type of the symbol (return type for functions).
+    /// `None` if the symbol has no type (function which returns nothing).
+    fn symbol_type(&self) -> Option<&ast::Type>;
 }
 
+/// A function definition.
+#[derive(Debug, Clone)]
 pub struct Function {
+    /// Name of the function.
     pub identifier: String,
-    pub return_type: String,
+    /// Return type of the function. `None` if function returns nothing.
+    pub return_type: Option<ast::Type>,
+    /// Parameters of the function.
     pub parameters: Vec<Variable>,
+    /// Source range of the function definition.
+    pub span: Span,
 }
 
 impl Symbol for Function {
     fn identifier(&self) -> &str {
         &self.identifier
     }
-    
-    fn symbol_type(&self) -> &str {
-        &self.return_type
+
+    fn symbol_type(&self) -> Option<&ast::Type> {
+        self.return_type.as_ref()
     }
 }
 
+/// A variable (or function parameter) definition.
+#[derive(Debug, Clone)]
 pub struct Variable {
+    /// Name of the variable.
     pub identifier: String,
-    pub var_type: String,
+    /// Type of the variable.
+    pub var_type: ast::Type,
 }
 
 impl Symbol for Variable {
     fn identifier(&self) -> &str {
         &self.identifier
     }
-    
-    fn symbol_type(&self) -> &str {
-        &self.var_type
+
+    fn symbol_type(&self) -> Option<&ast::Type> {
+        Some(&self.var_type)
     }
 }
 
-#[derive(Debug)]
+/// Errors which may occur while using a [`SymbolTable`].
+#[derive(Debug, PartialEq, Eq)]
 pub enum SymbolTableError {
+    /// There is no symbol with this name in the table or its upper scopes.
     SymbolNotFound(String),
+    /// There is already a symbol with this name in the same scope.
     SymbolAlreadyExists(String),
 }
 
-pub struct SymbolTable {
-    upper_scope: Option<Box<SymbolTable>>,
-    symbols: HashMap<String, Box<dyn Symbol>>,
+/// The `SymbolTable<T>` struct stores the symbols defined in a scope of
+/// Kolang code, and provides access to symbols of the upper scopes.
+///
+/// # Examples
+///
+/// ```
+/// use diagnostics::Span;
+/// use parser::ast::Type;
+/// use semantic::symbol_table::{SymbolTable, Variable};
+///
+/// let mut global = SymbolTable::new(None);
+/// global.add(Variable { identifier: "a".into(), var_type: Type::Int { span: Span::default() } }).unwrap();
+///
+/// let mut block = SymbolTable::new(Some(global));
+/// block.add(Variable { identifier: "b".into(), var_type: Type::Bool { span: Span::default() } }).unwrap();
+/// assert!(block.exists("a"));
+///
+/// let global = block.into_upper_scope().unwrap();
+/// assert!(!global.exists("b"));
+/// ```
+pub struct SymbolTable<T: Symbol> {
+    upper_scope: Option<Box<SymbolTable<T>>>,
+    symbols: HashMap<String, T>,
 }
 
-impl SymbolTable {
-    pub fn new(upper_scope: Option<SymbolTable>) -> Self {
+impl<T: Symbol> SymbolTable<T> {
+    /// Creates a new empty scope inside `upper_scope`.
+    pub fn new(upper_scope: Option<SymbolTable<T>>) -> Self {
         Self {
             upper_scope: upper_scope.map(Box::new),
             symbols: HashMap::new(),
         }
     }
 
+    /// Leaves this scope, dropping its symbols, and returns the upper scope.
+    pub fn into_upper_scope(self) -> Option<SymbolTable<T>> {
+        self.upper_scope.map(|s| *s)
+    }
+
+    /// Checks whether a symbol is defined in this scope or upper scopes.
     pub fn exists(&self, identifier: &str) -> bool {
         if self.symbols.contains_key(identifier) {
             true
@@ -65,23 +113,33 @@ impl SymbolTable {
         }
     }
 
-    pub fn add(&mut self, symbol: Box<dyn Symbol>) -> Result<(), SymbolTableError> {
+    /// Defines a symbol in this scope.
+    ///
+    /// # Errors
+    /// Returns [`SymbolTableError::SymbolAlreadyExists`] if there is a symbol
+    /// with the same name in this scope. Symbols of upper scopes may be shadowed.
+    pub fn add(&mut self, symbol: T) -> Result<(), SymbolTableError> {
         let identifier = symbol.identifier().to_string();
-        if self.symbols.contains_key(&identifier) {
-            Err(SymbolTableError::SymbolAlreadyExists(identifier))
-        } else {
-            self.symbols.insert(identifier, symbol);
-            Ok(())
+        match self.symbols.entry(identifier) {
+            Entry::Occupied(e) => Err(SymbolTableError::SymbolAlreadyExists(e.key().clone())),
+            Entry::Vacant(e) => {
+                e.insert(symbol);
+                Ok(())
+            }
         }
     }
 
-    pub fn get(&self, identifier: &str) -> Result<&dyn Symbol, SymbolTableError> {
+    /// Finds a symbol in this scope or the nearest upper scope which defines it.
+    ///
+    /// # Errors
+    /// Returns [`SymbolTableError::SymbolNotFound`] if the symbol is not defined.
+    pub fn get(&self, identifier: &str) -> Result<&T, SymbolTableError> {
         if let Some(symbol) = self.symbols.get(identifier) {
-            Ok(symbol.as_ref())
+            Ok(symbol)
         } else if let Some(ref parent) = self.upper_scope {
             parent.get(identifier)
         } else {
             Err(SymbolTableError::SymbolNotFound(identifier.to_string()))
         }
-    }    
+    }
 }
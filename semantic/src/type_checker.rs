@@ -0,0 +1,547 @@
+use std::mem;
+
+use diagnostics::{Diagnostic, Span};
+use parser::ast;
+
+use crate::symbol_table::{Function, SymbolTable, SymbolTableError, Variable};
+
+/// Names of builtin functions. Builtins are variadic and return nothing.
+const BUILTINS: [&str; 1] = ["println"];
+
+/// The `TypeChecker` struct infers the types of Kolang expressions and
+/// checks whether the types are used correctly. It does not stop at the first
+/// error; all errors are collected as [`Diagnostic`]s.
+///
+/// # Examples
+///
+/// ```
+/// use lexer::Lexer;
+/// use parser::Parser;
+/// use semantic::type_checker::TypeChecker;
+///
+/// let source = "fn main() { let a: int = 1 + \"one\"; }".as_bytes();
+/// let (ast, _) = Parser::new(Lexer::new(source)).parse().unwrap();
+///
+/// let mut tc = TypeChecker::new();
+/// tc.check_program(&ast);
+/// let diagnostics = tc.into_diagnostics();
+///
+/// assert_eq!(diagnostics[0].message, "Cannot apply `+` to `int` and `str`");
+/// ```
+pub struct TypeChecker {
+    /// Defined functions.
+    functions: SymbolTable<Function>,
+    /// Variables of the current scope (and its upper scopes).
+    variables: SymbolTable<Variable>,
+    /// Return type of the function being checked.
+    return_type: Option<ast::Type>,
+    /// Errors found so far.
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Default for TypeChecker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TypeChecker {
+    /// Creates a new `TypeChecker` with no defined functions and variables.
+    pub fn new() -> Self {
+        Self {
+            functions: SymbolTable::new(None),
+            variables: SymbolTable::new(None),
+            return_type: None,
+            diagnostics: Vec::new(),
+        }
+    }
+
+    /// Returns the errors found so far.
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
+    /// Consumes the `TypeChecker` and returns the errors found.
+    pub fn into_diagnostics(self) -> Vec<Diagnostic> {
+        self.diagnostics
+    }
+
+    /// Checks a program. All functions are defined before checking
+    /// function bodies, so functions may be called before their definition.
+    pub fn check_program(&mut self, prog: &[ast::Stmt]) {
+        for stmt in prog {
+            self.define_function(stmt);
+        }
+
+        for stmt in prog {
+            self.check_stmt(stmt);
+        }
+    }
+
+    /// Defines a function (`FnDef` statement) so it can be called.
+    pub fn define_function(&mut self, stmt: &ast::Stmt) {
+        let ast::Stmt::FnDef {
+            id,
+            params,
+            return_type,
+            span,
+            ..
+        } = stmt
+        else {
+            return;
+        };
+
+        if BUILTINS.contains(&id.as_str()) {
+            self.error(format!("Function `{}` is a builtin function", id), *span);
+            return;
+        }
+        if id == "main" && !params.is_empty() {
+            // `main` is called without arguments.
+            self.error("Function `main` must not have parameters".into(), *span);
+        }
+
+        let f = Function {
+            identifier: id.clone(),
+            return_type: return_type.clone(),
+            parameters: params
+                .iter()
+                .map(|(id, t)| Variable {
+                    identifier: id.clone(),
+                    var_type: t.clone(),
+                })
+                .collect(),
+            span: *span,
+        };
+
+        if let Err(SymbolTableError::SymbolAlreadyExists(id)) = self.functions.add(f) {
+            let previous = self.functions.get(&id).map(|f| f.span).unwrap_or(*span);
+            let d = Diagnostic::error(format!("Function `{}` is already defined", id), *span)
+                .with_label(previous, "previous definition here".into());
+            self.diagnostics.push(d);
+        }
+    }
+
+    /// Checks a statement.
+    pub fn check_stmt(&mut self, stmt: &ast::Stmt) {
+        match stmt {
+            ast::Stmt::Let {
+                id,
+                var_type,
+                expr,
+                span,
+            } => {
+                if let Some(e) = expr {
+                    if let Some(t) = self.value_type(e) {
+                        self.expect_type(var_type, &t, e.span());
+                    }
+                }
+
+                self.define_variable(id, var_type.clone(), *span);
+            }
+            ast::Stmt::Expr { expr, .. } => {
+                self.expr_type(expr);
+            }
+            ast::Stmt::If {
+                cond,
+                then_stmt,
+                else_stmt,
+                ..
+            } => {
+                self.check_cond(cond);
+                self.check_stmt(then_stmt);
+                if let Some(els) = else_stmt {
+                    self.check_stmt(els);
+                }
+            }
+            ast::Stmt::While { cond, body, .. } => {
+                self.check_cond(cond);
+                self.check_stmt(body);
+            }
+            ast::Stmt::For {
+                id,
+                start,
+                end,
+                body,
+                span,
+            } => {
+                let int = ast::Type::Int { span: *span };
+                for e in [start, end] {
+                    if let Some(t) = self.value_type(e) {
+                        self.expect_type(&int, &t, e.span());
+                    }
+                }
+
+                self.enter_scope();
+                self.define_variable(id, int, *span);
+                self.check_stmt(body);
+                self.exit_scope();
+            }
+            ast::Stmt::Return { expr, span } => {
+                let t = self.value_type(expr);
+                match (self.return_type.clone(), t) {
+                    (Some(expected), Some(found)) => {
+                        if !compatible(&expected, &found) {
+                            let d = type_mismatch(&expected, &found, expr.span()).with_label(
+                                expected.span(),
+                                "expected because of this return type".into(),
+                            );
+                            self.diagnostics.push(d);
+                        }
+                    }
+                    (None, Some(_)) => {
+                        self.error("Function should not return a value".into(), *span);
+                    }
+                    (_, None) => {}
+                }
+            }
+            ast::Stmt::Block { stmts, .. } => {
+                self.enter_scope();
+                for s in stmts {
+                    self.check_stmt(s);
+                }
+                self.exit_scope();
+            }
+            ast::Stmt::FnDef {
+                id,
+                params,
+                return_type,
+                body,
+                span,
+            } => {
+                self.return_type = return_type.clone();
+                self.enter_scope();
+
+                for (param, t) in params {
+                    self.define_variable(param, t.clone(), *span);
+                }
+                self.check_stmt(body);
+
+                self.exit_scope();
+                self.return_type = None;
+
+                if return_type.is_some() && !always_returns(body) {
+                    let d = Diagnostic::warning(
+                        format!("Function `{}` may finish without returning a value", id),
+                        *span,
+                    )
+                    .with_help("Add a `return` statement at the end of the function".into());
+                    self.diagnostics.push(d);
+                }
+            }
+            ast::Stmt::Empty { .. } => {}
+        }
+    }
+
+    /// Infers the type of an expression and checks its subexpressions.
+    /// Returns `None` if the expression has no value (call of a function
+    /// which returns nothing), and [`ast::Type::Error`] if the type is unknown
+    /// because of an error.
+    pub fn expr_type(&mut self, expr: &ast::Expr) -> Option<ast::Type> {
+        let span = expr.span();
+
+        let t = match expr {
+            ast::Expr::LiteralInt { .. } => ast::Type::Int { span },
+            ast::Expr::LiteralStr { .. } => ast::Type::Str { span },
+            ast::Expr::LiteralChar { .. } => ast::Type::Char { span },
+            ast::Expr::LiteralFloat { .. } => ast::Type::Float { span },
+            ast::Expr::LiteralBool { .. } => ast::Type::Bool { span },
+            ast::Expr::LiteralArray { elements, .. } => {
+                let mut element_type = ast::Type::Error { span };
+                for e in elements {
+                    if let Some(t) = self.value_type(e) {
+                        if is_error(&element_type) {
+                            element_type = t;
+                        } else {
+                            self.expect_type(&element_type, &t, e.span());
+                        }
+                    }
+                }
+
+                ast::Type::Array {
+                    element_type: Box::new(element_type),
+                    span,
+                }
+            }
+            ast::Expr::BinaryOp { l, op, r, .. } => {
+                let lt = self.value_type(l)?;
+                let rt = self.value_type(r)?;
+                self.binary_type(op, &lt, &rt, span)
+            }
+            ast::Expr::UnaryOp { op, expr, .. } => {
+                let t = self.value_type(expr)?;
+                self.unary_type(op, &t, span)
+            }
+            ast::Expr::Identifier { id, .. } => self.variable_type(id, span),
+            ast::Expr::Call { id, args, .. } => return self.call_type(id, args, span),
+            ast::Expr::ArrayExpr { id, index, .. } => {
+                if let Some(t) = self.value_type(index) {
+                    self.expect_type(&ast::Type::Int { span }, &t, index.span());
+                }
+
+                match self.variable_type(id, span) {
+                    ast::Type::Array { element_type, .. } => *element_type,
+                    ast::Type::Error { .. } => ast::Type::Error { span },
+                    t => {
+                        self.error(format!("Cannot index into `{}`", t), span);
+                        ast::Type::Error { span }
+                    }
+                }
+            }
+            ast::Expr::Assign { id, expr, .. } => {
+                let var_type = self.variable_type(id, span);
+                if let Some(t) = self.value_type(expr) {
+                    self.expect_type(&var_type, &t, expr.span());
+                }
+                var_type
+            }
+            ast::Expr::Error { .. } => ast::Type::Error { span },
+        };
+
+        Some(t)
+    }
+
+    /// Infers the type of an expression which should have a value.
+    /// Reports an error and returns `None` if it has no value.
+    fn value_type(&mut self, expr: &ast::Expr) -> Option<ast::Type> {
+        let t = self.expr_type(expr);
+
+        if t.is_none() {
+            self.error("Expression does not have a value".into(), expr.span());
+        }
+
+        t
+    }
+
+    /// Infers the result type of a binary operation. `span` is the source
+    /// range of the whole operation.
+    fn binary_type(
+        &mut self,
+        op: &ast::BinOp,
+        l: &ast::Type,
+        r: &ast::Type,
+        span: Span,
+    ) -> ast::Type {
+        use ast::BinOp as B;
+        use ast::Type as T;
+
+        if is_error(l) || is_error(r) {
+            return T::Error { span };
+        }
+
+        match (op, l, r) {
+            (
+                B::Add { .. } | B::Sub { .. } | B::Mul { .. } | B::Div { .. } | B::Mod { .. },
+                _,
+                _,
+            ) if l.same_as(r) && matches!(l, T::Int { .. } | T::Float { .. }) => l.clone(),
+            (B::LogAnd { .. } | B::LogOr { .. }, T::Bool { .. }, T::Bool { .. }) => {
+                T::Bool { span }
+            }
+            (B::BitAnd { .. } | B::BitOr { .. }, _, _)
+                if l.same_as(r) && matches!(l, T::Int { .. } | T::Bool { .. }) =>
+            {
+                l.clone()
+            }
+            (B::Eq { .. } | B::NEq { .. }, _, _) if l.same_as(r) => T::Bool { span },
+            (B::LT { .. } | B::GT { .. } | B::LEq { .. } | B::GEq { .. }, _, _)
+                if l.same_as(r)
+                    && matches!(l, T::Int { .. } | T::Float { .. } | T::Char { .. }) =>
+            {
+                T::Bool { span }
+            }
+            _ => {
+                self.error(
+                    format!("Cannot apply `{}` to `{}` and `{}`", op, l, r),
+                    op.span(),
+                );
+                T::Error { span }
+            }
+        }
+    }
+
+    /// Infers the result type of a unary operation. `span` is the source
+    /// range of the whole operation.
+    fn unary_type(&mut self, op: &ast::UnOp, t: &ast::Type, span: Span) -> ast::Type {
+        use ast::Type as T;
+
+        match (op, t) {
+            (_, T::Error { .. }) => T::Error { span },
+            (ast::UnOp::Neg { .. }, T::Int { .. } | T::Float { .. })
+            | (ast::UnOp::LogNot { .. }, T::Bool { .. })
+            | (ast::UnOp::BitNot { .. }, T::Int { .. }) => t.clone(),
+            _ => {
+                self.error(format!("Cannot apply `{}` to `{}`", op, t), op.span());
+                T::Error { span }
+            }
+        }
+    }
+
+    /// Checks a function call and returns its type.
+    fn call_type(&mut self, id: &str, args: &[ast::Expr], span: Span) -> Option<ast::Type> {
+        let arg_types: Vec<Option<ast::Type>> =
+            args.iter().map(|arg| self.value_type(arg)).collect();
+
+        if BUILTINS.contains(&id) {
+            return None;
+        }
+
+        let f = match self.functions.get(id) {
+            Ok(f) => f.clone(),
+            Err(_) => {
+                self.error(format!("Function `{}` is not defined", id), span);
+                return Some(ast::Type::Error { span });
+            }
+        };
+
+        if f.parameters.len() != args.len() {
+            let (expected, found) = (f.parameters.len(), args.len());
+            let message = format!(
+                "Function `{}` takes {} argument{} but {} {} supplied",
+                id,
+                expected,
+                if expected == 1 { "" } else { "s" },
+                found,
+                if found == 1 { "was" } else { "were" }
+            );
+            let d = Diagnostic::error(message, span)
+                .with_label(f.span, "function declared here".into());
+            self.diagnostics.push(d);
+        }
+
+        for ((param, arg), t) in f.parameters.iter().zip(args).zip(arg_types) {
+            if let Some(t) = t {
+                self.expect_type(&param.var_type, &t, arg.span());
+            }
+        }
+
+        f.return_type
+    }
+
+    /// Finds the type of a variable. Reports an error and returns
+    /// [`ast::Type::Error`] if the variable is not defined.
+    fn variable_type(&mut self, id: &str, span: Span) -> ast::Type {
+        match self.variables.get(id) {
+            Ok(v) => v.var_type.clone(),
+            Err(_) => {
+                self.error(format!("Variable `{}` is not defined", id), span);
+                ast::Type::Error { span }
+            }
+        }
+    }
+
+    /// Checks the type of a condition (which should be `bool`).
+    fn check_cond(&mut self, cond: &ast::Expr) {
+        if let Some(t) = self.value_type(cond) {
+            if !is_error(&t) && !matches!(t, ast::Type::Bool { .. }) {
+                self.error(
+                    format!("Expected `bool` condition, found `{}`", t),
+                    cond.span(),
+                );
+            }
+        }
+    }
+
+    /// Reports an error if `found` type is not compatible with `expected` type.
+    /// `span` is the source range of the value with `found` type.
+    fn expect_type(&mut self, expected: &ast::Type, found: &ast::Type, span: Span) {
+        if !compatible(expected, found) {
+            self.diagnostics.push(type_mismatch(expected, found, span));
+        }
+    }
+
+    /// Defines a variable in the current scope.
+    fn define_variable(&mut self, id: &str, var_type: ast::Type, span: Span) {
+        let v = Variable {
+            identifier: id.to_string(),
+            var_type,
+        };
+
+        if let Err(SymbolTableError::SymbolAlreadyExists(id)) = self.variables.add(v) {
+            self.error(
+                format!("Variable `{}` is already defined in this scope", id),
+                span,
+            );
+        }
+    }
+
+    /// Enters a new (inner) variable scope.
+    fn enter_scope(&mut self) {
+        let upper = mem::replace(&mut self.variables, SymbolTable::new(None));
+        self.variables = SymbolTable::new(Some(upper));
+    }
+
+    /// Exits the current variable scope.
+    fn exit_scope(&mut self) {
+        let inner = mem::replace(&mut self.variables, SymbolTable::new(None));
+        self.variables = inner
+            .into_upper_scope()
+            .expect("Cannot exit the global scope");
+    }
+
+    /// Reports an error in the provided source range.
+    fn error(&mut self, message: String, span: Span) {
+        self.diagnostics.push(Diagnostic::error(message, span));
+    }
+}
+
+/// Creates the error of a value with `found` type, where `expected` type was required.
+fn type_mismatch(expected: &ast::Type, found: &ast::Type, span: Span) -> Diagnostic {
+    Diagnostic::error(format!("Expected `{}`, found `{}`", expected, found), span)
+}
+
+/// Checks whether the type is unknown because of an error.
+fn is_error(t: &ast::Type) -> bool {
+    matches!(t, ast::Type::Error { .. })
+}
+
+/// Checks whether a value of `found` type can be used where `expected`
+/// type is expected. Unknown types are compatible with every type
+/// to prevent reporting an error twice.
+fn compatible(expected: &ast::Type, found: &ast::Type) -> bool {
+    match (expected, found) {
+        (ast::Type::Error { .. }, _) | (_, ast::Type::Error { .. }) => true,
+        (
+            ast::Type::Array {
+                element_type: e, ..
+            },
+            ast::Type::Array {
+                element_type: f, ..
+            },
+        ) => compatible(e, f),
+        (e, f) => e.same_as(f),
+    }
+}
+
+/// Checks whether executing the statement always ends with a `return`.
+fn always_returns(stmt: &ast::Stmt) -> bool {
+    match stmt {
+        ast::Stmt::Return { .. } => true,
+        ast::Stmt::Block { stmts, .. } => stmts.iter().any(always_returns),
+        ast::Stmt::If {
+            then_stmt,
+            else_stmt: Some(els),
+            ..
+        } => always_returns(then_stmt) && always_returns(els),
+        _ => false,
+    }
+}
+
+/// Checks the types of a program and returns all errors found.
+///
+/// # Examples
+///
+/// ```
+/// use lexer::Lexer;
+/// use parser::Parser;
+///
+/// let source = "fn main() { if 1 { } }".as_bytes();
+/// let (ast, _) = Parser::new(Lexer::new(source)).parse().unwrap();
+///
+/// let diagnostics = semantic::type_checker::check(&ast);
+/// assert_eq!(diagnostics[0].message, "Expected `bool` condition, found `int`");
+/// ```
+pub fn check(prog: &[ast::Stmt]) -> Vec<Diagnostic> {
+    let mut tc = TypeChecker::new();
+    tc.check_program(prog);
+    tc.into_diagnostics()
+}
@@ -0,0 +1,376 @@
+use diagnostics::{Diagnostic, Severity, Span};
+use lexer::Lexer;
+use parser::{
+    ast::{Expr, Stmt},
+    Parser,
+};
+use semantic::resolver::{Resolver, ScopeKind, ScopeTree, ShadowingPolicy, SymbolKind};
+
+fn parse(code: &str) -> Vec<Stmt> {
+    let l = Lexer::new(code.as_bytes());
+    let (ast, diagnostics) = Parser::new(l).parse().unwrap();
+    assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+
+    ast
+}
+
+fn check(code: &str) -> Vec<Diagnostic> {
+    semantic::type_checker::check(&parse(code))
+}
+
+fn resolve(code: &str, policy: ShadowingPolicy) -> (ScopeTree, Vec<Diagnostic>) {
+    Resolver::new().with_shadowing(policy).resolve(&parse(code))
+}
+
+fn positions(spans: &[Span]) -> Vec<(usize, usize)> {
+    spans.iter().map(|s| (s.line, s.column)).collect()
+}
+
+fn messages(diagnostics: &[Diagnostic]) -> Vec<(usize, usize, &str)> {
+    diagnostics
+        .iter()
+        .map(|d| (d.span.line, d.span.column, d.message.as_str()))
+        .collect()
+}
+
+#[test]
+fn readme_example() {
+    let diagnostics = check(
+        include_str!("../../README.md")
+            .split("``` Rust")
+            .nth(1)
+            .unwrap()
+            .split("```")
+            .next()
+            .unwrap(),
+    );
+
+    assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+}
+
+#[test]
+fn valid_code() {
+    let diagnostics = check(
+        "fn main() {
+            println(sum([1, 2, 3], 3) > 5 and not false, ' ', -1.5, ~1);
+            let words: str[] = [\"a\", \"b\"];
+            let empty: int[] = [];
+            if words[0] == \"a\" { let words: int = 1; words = words + 1; }
+            for i = 0 to 10 while i < 5 i = i + 1;
+        }
+
+        fn sum(arr: int[], n: int): int {
+            let s: int = 0;
+            for i = 0 to n - 1 s = s + arr[i];
+            return s;
+        }
+
+        fn sign(a: float): int {
+            if a < 1.5 return -1; else if a > 1.5 return 1; else return 0;
+        }",
+    );
+
+    assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+}
+
+#[test]
+fn operator_errors() {
+    let diagnostics = check(
+        "fn main() {
+            let a: int = 1 + \"one\";
+            let b: float = 1 + 2.0;
+            let c: bool = 1 and true;
+            let d: bool = \"a\" < \"b\";
+            let e: bool = not 1;
+            let f: int = -true;
+        }",
+    );
+
+    assert_eq!(
+        messages(&diagnostics),
+        vec![
+            (2, 28, "Cannot apply `+` to `int` and `str`"),
+            (3, 30, "Cannot apply `+` to `int` and `float`"),
+            (4, 29, "Cannot apply `and` to `int` and `bool`"),
+            (5, 31, "Cannot apply `<` to `str` and `str`"),
+            (6, 27, "Cannot apply `not` to `int`"),
+            (7, 26, "Cannot apply `-` to `bool`"),
+        ]
+    );
+}
+
+#[test]
+fn statement_errors() {
+    let diagnostics = check(
+        "fn main() {
+            let a: int = 'a';
+            if 1 { }
+            while \"true\" { }
+            for i = 1.0 to 'z' { }
+            a = true;
+            let a: float;
+            a[0];
+        }",
+    );
+
+    assert_eq!(
+        messages(&diagnostics),
+        vec![
+            (2, 26, "Expected `int`, found `char`"),
+            (3, 16, "Expected `bool` condition, found `int`"),
+            (4, 19, "Expected `bool` condition, found `str`"),
+            (5, 21, "Expected `int`, found `float`"),
+            (5, 28, "Expected `int`, found `char`"),
+            (6, 17, "Expected `int`, found `bool`"),
+            (7, 13, "Variable `a` is already defined in this scope"),
+            (8, 13, "Cannot index into `int`"),
+        ]
+    );
+}
+
+#[test]
+fn call_errors() {
+    let diagnostics = check(
+        "fn f(a: int, b: str[]): bool { return a > 0; }
+        fn g() { }
+        fn main() {
+            f(1);
+            f(\"1\", [1]);
+            let a: int = g();
+            println(g());
+            h();
+        }
+        fn f() { }",
+    );
+
+    assert_eq!(
+        messages(&diagnostics),
+        vec![
+            (10, 9, "Function `f` is already defined"),
+            (4, 13, "Function `f` takes 2 arguments but 1 was supplied"),
+            (5, 15, "Expected `int`, found `str`"),
+            (5, 20, "Expected `str[]`, found `int[]`"),
+            (6, 26, "Expression does not have a value"),
+            (7, 21, "Expression does not have a value"),
+            (8, 13, "Function `h` is not defined"),
+        ]
+    );
+
+    let label = &diagnostics[0].labels[0];
+    assert_eq!((label.span.line, label.span.column), (1, 1));
+    assert_eq!(label.message, "previous definition here");
+    let label = &diagnostics[1].labels[0];
+    assert_eq!((label.span.line, label.span.column), (1, 1));
+    assert_eq!(label.message, "function declared here");
+
+    assert_eq!(
+        messages(&check("fn f() { }\nfn main(a: int) { }")),
+        vec![(2, 1, "Function `main` must not have parameters")]
+    );
+    assert_eq!(
+        messages(&check("fn f(a: int) { }\nfn main() { f(1, 2); }")),
+        vec![(2, 13, "Function `f` takes 1 argument but 2 were supplied")]
+    );
+}
+
+#[test]
+fn return_errors() {
+    let diagnostics = check(
+        "fn f(): int { return 'a'; }
+        fn g() { return 1; }
+        fn h(a: bool): int {
+            if a return 1;
+        }",
+    );
+
+    assert_eq!(
+        messages(&diagnostics),
+        vec![
+            (1, 22, "Expected `int`, found `char`"),
+            (2, 18, "Function should not return a value"),
+            (3, 9, "Function `h` may finish without returning a value"),
+        ]
+    );
+    assert_eq!(diagnostics[2].severity, Severity::Warning);
+
+    let label = &diagnostics[0].labels[0];
+    assert_eq!((label.span.line, label.span.column), (1, 9));
+    assert_eq!(label.message, "expected because of this return type");
+    assert_eq!(diagnostics[2].help.len(), 1);
+}
+
+#[test]
+fn no_cascading_errors() {
+    let diagnostics = check(
+        "fn main() {
+            let a: int = (x + 1) * 2 - y[0];
+        }",
+    );
+
+    assert_eq!(
+        messages(&diagnostics),
+        vec![
+            (2, 27, "Variable `x` is not defined"),
+            (2, 40, "Variable `y` is not defined"),
+        ]
+    );
+}
+
+#[test]
+fn scope_tree() {
+    let code = "fn f(n: int): int {
+            let s: int = 0;
+            for i = 1 to n {
+                s = s + i;
+            }
+            return s;
+        }
+
+        fn main() { println(f(3)); }";
+    let ast = parse(code);
+    let (tree, diagnostics) = Resolver::new()
+        .with_shadowing(ShadowingPolicy::Deny)
+        .resolve(&ast);
+    assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+
+    let global = tree.scope(tree.global());
+    assert_eq!(global.kind, ScopeKind::Global);
+    assert_eq!(global.children.len(), 2);
+
+    let function = tree.scope(global.children[0]);
+    assert_eq!(function.kind, ScopeKind::Function);
+    let block = tree.scope(function.children[0]);
+    assert_eq!(block.kind, ScopeKind::Block);
+    let for_scope = tree.scope(block.children[0]);
+    assert_eq!(for_scope.kind, ScopeKind::For);
+
+    let n = function.symbols["n"];
+    let s = block.symbols["s"];
+    let i = for_scope.symbols["i"];
+    assert_eq!(tree.symbol(n).kind, SymbolKind::Parameter);
+    assert_eq!(tree.symbol(s).kind, SymbolKind::Variable);
+    assert_eq!(tree.symbol(i).kind, SymbolKind::LoopVariable);
+    assert_eq!(tree.lookup_variable(for_scope.children[0], "n"), Some(n));
+    assert_eq!(tree.lookup_variable(tree.global(), "s"), None);
+
+    let Stmt::FnDef { body, .. } = &ast[0] else {
+        panic!("Expected function");
+    };
+    let Stmt::Block { stmts, .. } = body.as_ref() else {
+        panic!("Expected block");
+    };
+    let Stmt::For { end, .. } = &stmts[1] else {
+        panic!("Expected for loop");
+    };
+    let Stmt::Return { expr, .. } = &stmts[2] else {
+        panic!("Expected return statement");
+    };
+    assert!(matches!(end, Expr::Identifier { .. }));
+    assert_eq!(tree.resolution(end), Some(n));
+    assert_eq!(tree.resolution(expr), Some(s));
+    assert_eq!(positions(&tree.references(i)), vec![(4, 25)]);
+    assert_eq!(
+        positions(&tree.references(s)),
+        vec![(4, 17), (4, 21), (6, 20)]
+    );
+
+    let f = tree.lookup_function("f").unwrap();
+    assert_eq!(tree.symbol(f).kind, SymbolKind::Function);
+    let refs = tree.references(f);
+    assert_eq!(positions(&refs), vec![(9, 29)]);
+    assert_eq!(&code[refs[0].start..refs[0].end], "f");
+
+    // Queries by position.
+    let offset = code.find("s + i").unwrap();
+    assert_eq!(
+        tree.reference_at(offset + 4).map(|(_, symbol)| symbol),
+        Some(i)
+    );
+    assert_eq!(tree.reference_at(offset + 2), None);
+    assert_eq!(tree.scope_at(offset), for_scope.children[0]);
+    assert_eq!(
+        tree.scope_at(code.find("fn main").unwrap() - 1),
+        tree.global()
+    );
+    let names: Vec<&str> = tree
+        .visible_symbols(tree.scope_at(offset), offset)
+        .into_iter()
+        .map(|v| tree.symbol(v).identifier.as_str())
+        .collect();
+    assert_eq!(names, vec!["i", "s", "n", "f", "main"]);
+}
+
+#[test]
+fn resolve_errors() {
+    let code = "fn f(a: int, a: int) { }
+        fn main() {
+            let x: int = y;
+            let x: int = 1;
+            g();
+            z = 1;
+            w[0];
+            { let x: int = 2; }
+            for x = 1 to 2 { }
+        }
+        fn f() { }";
+
+    let (_, diagnostics) = resolve(code, ShadowingPolicy::Warn);
+    assert_eq!(
+        messages(&diagnostics),
+        vec![
+            (11, 9, "Function `f` is already defined"),
+            (1, 1, "Variable `a` is already defined in this scope"),
+            (3, 26, "Variable `y` is not defined"),
+            (4, 13, "Variable `x` is already defined in this scope"),
+            (5, 13, "Function `g` is not defined"),
+            (6, 13, "Variable `z` is not defined"),
+            (7, 13, "Variable `w` is not defined"),
+            (8, 15, "Variable `x` shadows a variable declared at 3:13"),
+            (9, 13, "Variable `x` shadows a variable declared at 3:13"),
+        ]
+    );
+    assert_eq!(diagnostics[7].severity, Severity::Warning);
+
+    let (_, diagnostics) = resolve(code, ShadowingPolicy::Deny);
+    assert_eq!(diagnostics[7].severity, Severity::Error);
+
+    let (_, diagnostics) = resolve(code, ShadowingPolicy::Allow);
+    assert_eq!(diagnostics.len(), 7);
+}
+
+#[test]
+fn frame_layout() {
+    let code = "fn f(a: int, b: int): int {
+            let s: int = a;
+            {
+                let x: int = 1;
+                let y: int = 2;
+            }
+            for i = a to b {
+                let z: int = i;
+            }
+            let t: int = b;
+            return s + t;
+        }
+
+        fn main() { }";
+    let (tree, diagnostics) = resolve(code, ShadowingPolicy::Deny);
+    assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+
+    let layouts = semantic::layout::layout(&tree);
+    assert_eq!(layouts.len(), 2);
+    assert_eq!(layouts[1].size, 0);
+
+    let f = &layouts[0];
+    let slot = |name: &str| {
+        let (id, _) = tree.symbols().find(|(_, s)| s.identifier == name).unwrap();
+        f.slot(id).unwrap()
+    };
+    assert_eq!((slot("a"), slot("b")), (0, 1));
+    assert_eq!((slot("s"), slot("t")), (2, 3));
+    // Variables of sibling scopes share slots.
+    assert_eq!((slot("x"), slot("y")), (4, 5));
+    assert_eq!((slot("i"), slot("z")), (4, 5));
+    assert_eq!(f.size, 6);
+    assert_eq!(f.slots.len(), 8);
+}
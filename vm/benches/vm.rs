@@ -0,0 +1,84 @@
+//! Compares the tree-walking interpreter and the bytecode virtual machine
+//! on numeric loops. Run with `cargo bench -p vm`.
+
+use std::{
+    io,
+    time::{Duration, Instant},
+};
+
+use interpreter::Interpreter;
+use lexer::Lexer;
+use parser::Parser;
+
+const SOURCE: &str = "
+fn sum_to_n(n: int): int {
+    let sum: int = 0;
+    for i = 1 to n {
+        sum = sum + i;
+    }
+    return sum;
+}
+
+fn collatz_steps(limit: int): int {
+    let steps: int = 0;
+    for n = 1 to limit {
+        let x: int = n;
+        while x != 1 {
+            if x % 2 == 0 {
+                x = x / 2;
+            } else {
+                x = 3 * x + 1;
+            }
+            steps = steps + 1;
+        }
+    }
+    return steps;
+}
+
+fn main(): int {
+    return sum_to_n(3000000) + collatz_steps(30000);
+}
+";
+
+const ROUNDS: usize = 3;
+
+fn main() {
+    let (ast, diagnostics) = Parser::new(Lexer::new(SOURCE.as_bytes())).parse().unwrap();
+    assert!(diagnostics.is_empty());
+    let program = vm::compile(&ast).unwrap();
+
+    let tree = std::thread::Builder::new()
+        .stack_size(interpreter::STACK_SIZE)
+        .spawn(move || {
+            let mut i = Interpreter::new(io::sink());
+            i.define(ast);
+            measure(|| i.run_main().unwrap().to_string())
+        })
+        .unwrap()
+        .join()
+        .unwrap();
+    let bytecode = measure(|| vm::run(&program, io::sink()).unwrap().to_string());
+
+    assert_eq!(tree.0, bytecode.0, "Engines produced different results");
+
+    println!("result: {}", bytecode.0);
+    for (name, (_, time)) in [("interpreter", &tree), ("vm", &bytecode)] {
+        println!("{:<12} {:>10.2} ms", name, time.as_secs_f64() * 1000.0);
+    }
+    println!(
+        "vm is {:.1}x faster",
+        tree.1.as_secs_f64() / bytecode.1.as_secs_f64()
+    );
+}
+
+/// Runs `f` several times and returns its result and the fastest run time.
+fn measure<T>(mut f: impl FnMut() -> T) -> (T, Duration) {
+    let mut best = Duration::MAX;
+    let mut result = None;
+    for _ in 0..ROUNDS {
+        let start = Instant::now();
+        result = Some(std::hint::black_box(f()));
+        best = best.min(start.elapsed());
+    }
+    (result.unwrap(), best)
+}
@@ -0,0 +1,205 @@
+use std::fmt;
+
+use interpreter::value::Value;
+
+/// A single instruction of the stack machine.
+///
+/// Operands are indexes into the constants pool, local slots of the running
+/// function, instructions of the running function (jump targets) or
+/// functions of the program.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Instr {
+    /// Push a value of the constants pool.
+    Const(u32),
+    /// Push the result of a function which returns nothing.
+    Void,
+    /// Discard the top value.
+    Pop,
+    /// Duplicate the top value.
+    Dup,
+    /// Push the value of a local slot.
+    Load(u32),
+    /// Pop a value and store it in a local slot.
+    Store(u32),
+    /// Pop the provided number of values and push an array of them.
+    Array(u32),
+    /// Pop an array and an index below it, and push the indexed element.
+    Index,
+    /// Addition: `+`
+    Add,
+    /// Subtraction: `-`
+    Sub,
+    /// Multiplication: `*`
+    Mul,
+    /// Division: `/`
+    Div,
+    /// Modulo: `%`
+    Mod,
+    /// Bitwise and: `&`
+    BitAnd,
+    /// Bitwise or: `|`
+    BitOr,
+    /// Equals: `==`
+    Eq,
+    /// Not equal: `!=`
+    NEq,
+    /// Less than: `<`
+    LT,
+    /// Greater than: `>`
+    GT,
+    /// Less than or equal: `<=`
+    LEq,
+    /// Greater than or equal: `>=`
+    GEq,
+    /// Negation: `-`
+    Neg,
+    /// Logical not: `not`
+    LogNot,
+    /// Bitwise not: `~`
+    BitNot,
+    /// Continue from an instruction.
+    Jump(u32),
+    /// Pop a `bool` and continue from an instruction if it is `false`.
+    JumpIfFalse(u32),
+    /// If the top `bool` is `false`, continue from an instruction and keep it.
+    /// Otherwise pop it. Used for short-circuit `and`.
+    JumpIfFalseOrPop(u32),
+    /// If the top `bool` is `true`, continue from an instruction and keep it.
+    /// Otherwise pop it. Used for short-circuit `or`.
+    JumpIfTrueOrPop(u32),
+    /// Start an iteration of a `for` loop. `slot` holds the loop variable and
+    /// `slot + 1` holds the end of the range. Continues from `exit` if the
+    /// loop variable is greater than the end.
+    ForTest {
+        /// Slot of the loop variable.
+        slot: u32,
+        /// First instruction after the loop.
+        exit: u32,
+    },
+    /// Finish an iteration of a `for` loop: increment the loop variable and
+    /// continue from `target`. Ends the loop instead if the increment overflows.
+    ForStep {
+        /// Slot of the loop variable.
+        slot: u32,
+        /// The [`Instr::ForTest`] instruction of the loop.
+        target: u32,
+    },
+    /// Call a function with arguments on top of the stack, and push its result.
+    Call {
+        /// Index of the function in the program.
+        function: u32,
+        /// Number of arguments.
+        argc: u32,
+    },
+    /// Pop the provided number of values, print them and push nothing.
+    Println(u32),
+    /// Pop the result of the running function and return it to the caller.
+    Return,
+    /// Report that a function which has a return type did not return a value.
+    MissingReturn,
+}
+
+impl Instr {
+    /// Returns the operator of a binary or unary operation, as written in
+    /// Kolang code.
+    pub fn operator(&self) -> Option<&'static str> {
+        Some(match self {
+            Instr::Add => "+",
+            Instr::Sub | Instr::Neg => "-",
+            Instr::Mul => "*",
+            Instr::Div => "/",
+            Instr::Mod => "%",
+            Instr::BitAnd => "&",
+            Instr::BitOr => "|",
+            Instr::Eq => "==",
+            Instr::NEq => "!=",
+            Instr::LT => "<",
+            Instr::GT => ">",
+            Instr::LEq => "<=",
+            Instr::GEq => ">=",
+            Instr::LogNot => "not",
+            Instr::BitNot => "~",
+            _ => return None,
+        })
+    }
+}
+
+impl fmt::Display for Instr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Instr::Const(i) => write!(f, "const {}", i),
+            Instr::Void => f.write_str("void"),
+            Instr::Pop => f.write_str("pop"),
+            Instr::Dup => f.write_str("dup"),
+            Instr::Load(slot) => write!(f, "load {}", slot),
+            Instr::Store(slot) => write!(f, "store {}", slot),
+            Instr::Array(n) => write!(f, "array {}", n),
+            Instr::Index => f.write_str("index"),
+            Instr::Add => f.write_str("add"),
+            Instr::Sub => f.write_str("sub"),
+            Instr::Mul => f.write_str("mul"),
+            Instr::Div => f.write_str("div"),
+            Instr::Mod => f.write_str("mod"),
+            Instr::BitAnd => f.write_str("bit_and"),
+            Instr::BitOr => f.write_str("bit_or"),
+            Instr::Eq => f.write_str("eq"),
+            Instr::NEq => f.write_str("neq"),
+            Instr::LT => f.write_str("lt"),
+            Instr::GT => f.write_str("gt"),
+            Instr::LEq => f.write_str("leq"),
+            Instr::GEq => f.write_str("geq"),
+            Instr::Neg => f.write_str("neg"),
+            Instr::LogNot => f.write_str("not"),
+            Instr::BitNot => f.write_str("bit_not"),
+            Instr::Jump(target) => write!(f, "jump {}", target),
+            Instr::JumpIfFalse(target) => write!(f, "jump_if_false {}", target),
+            Instr::JumpIfFalseOrPop(target) => write!(f, "jump_if_false_or_pop {}", target),
+            Instr::JumpIfTrueOrPop(target) => write!(f, "jump_if_true_or_pop {}", target),
+            Instr::ForTest { slot, exit } => write!(f, "for_test {} {}", slot, exit),
+            Instr::ForStep { slot, target } => write!(f, "for_step {} {}", slot, target),
+            Instr::Call { function, argc } => write!(f, "call {} {}", function, argc),
+            Instr::Println(n) => write!(f, "println {}", n),
+            Instr::Return => f.write_str("return"),
+            Instr::MissingReturn => f.write_str("missing_return"),
+        }
+    }
+}
+
+/// A compiled Kolang function.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Function {
+    /// Name of the function.
+    pub name: String,
+    /// Number of parameters. Arguments are stored in the first local slots.
+    pub arity: u32,
+    /// Number of local slots (including parameters).
+    pub locals: u32,
+    /// Instructions of the function.
+    pub code: Vec<Instr>,
+    /// Line and column of code for each instruction, used for error messages.
+    pub positions: Vec<(usize, usize)>,
+    /// Line and column of the function definition.
+    pub pos: (usize, usize),
+}
+
+/// A compiled Kolang program.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Program {
+    /// Constant values used by the functions.
+    pub constants: Vec<Value>,
+    /// Functions of the program.
+    pub functions: Vec<Function>,
+}
+
+impl Program {
+    /// Returns the index of a function by name. If several functions have the
+    /// same name, the last one is used, like the interpreter does.
+    pub fn function_index(&self, name: &str) -> Option<usize> {
+        self.functions.iter().rposition(|f| f.name == name)
+    }
+
+    /// Returns a function by name.
+    pub fn function(&self, name: &str) -> Option<&Function> {
+        self.function_index(name).map(|i| &self.functions[i])
+    }
+}
@@ -0,0 +1,449 @@
+use std::{collections::HashMap, rc::Rc};
+
+use diagnostics::Span;
+use interpreter::value::Value;
+use parser::ast;
+
+use crate::{
+    bytecode::{Function, Instr, Program},
+    error::CompileError,
+};
+
+/// Name of local slots which are not accessible from code
+/// (like the end of a `for` range).
+const HIDDEN: &str = "";
+
+/// The `Compiler` struct compiles function definitions of a Kolang program
+/// to bytecode. Functions are compiled one at a time, and share the
+/// constants pool of the program.
+struct Compiler {
+    /// The compiled program.
+    program: Program,
+    /// Index and number of parameters of the defined functions, by name.
+    signatures: HashMap<String, (u32, usize)>,
+    /// Name of the function being compiled, if it returns a value.
+    /// `return` statements of its calls are compiled to jumps to its
+    /// start, so tail recursion does not grow the stack.
+    tail_callee: Option<String>,
+    /// Instructions of the function being compiled.
+    code: Vec<Instr>,
+    /// Positions of the instructions in `code`.
+    positions: Vec<(usize, usize)>,
+    /// Names of the local slots which are in scope. The slot of each
+    /// variable is its index, so the last variable with a name is visible.
+    locals: Vec<String>,
+    /// Length of `locals` when each open scope started.
+    scopes: Vec<usize>,
+    /// Maximum number of slots used by the function being compiled.
+    max_locals: usize,
+}
+
+impl Compiler {
+    fn new() -> Self {
+        Self {
+            program: Program::default(),
+            signatures: HashMap::new(),
+            tail_callee: None,
+            code: Vec::new(),
+            positions: Vec::new(),
+            locals: Vec::new(),
+            scopes: Vec::new(),
+            max_locals: 0,
+        }
+    }
+
+    /// Compiles a function definition and adds it to the program.
+    fn function(&mut self, stmt: &ast::Stmt) -> Result<(), CompileError> {
+        let ast::Stmt::FnDef {
+            id,
+            params,
+            return_type,
+            body,
+            span,
+        } = stmt
+        else {
+            unreachable!("Only functions are compiled");
+        };
+
+        self.code.clear();
+        self.positions.clear();
+        self.locals = params.iter().map(|(p, _)| p.clone()).collect();
+        self.scopes.clear();
+        self.max_locals = self.locals.len();
+        self.tail_callee = return_type.as_ref().map(|_| id.clone());
+
+        self.stmt(body)?;
+        let end = (span.end_line, span.end_column);
+        match return_type {
+            Some(_) => self.emit(Instr::MissingReturn, (span.line, span.column)),
+            None => {
+                self.emit(Instr::Void, end);
+                self.emit(Instr::Return, end);
+            }
+        }
+
+        self.program.functions.push(Function {
+            name: id.clone(),
+            arity: params.len() as u32,
+            locals: self.max_locals as u32,
+            code: std::mem::take(&mut self.code),
+            positions: std::mem::take(&mut self.positions),
+            pos: (span.line, span.column),
+        });
+        Ok(())
+    }
+
+    /// Compiles a statement. Statements leave the stack as they found it.
+    fn stmt(&mut self, stmt: &ast::Stmt) -> Result<(), CompileError> {
+        match stmt {
+            ast::Stmt::Let {
+                id,
+                var_type,
+                expr,
+                span,
+            } => {
+                let pos = (span.line, span.column);
+                match expr {
+                    Some(e) => self.expr(e)?,
+                    None => {
+                        let value =
+                            Value::default_of(var_type).ok_or(CompileError::InvalidCode {
+                                line: pos.0,
+                                column: pos.1,
+                            })?;
+                        self.constant(value, pos);
+                    }
+                }
+                let slot = self.declare(id);
+                self.emit(Instr::Store(slot), pos);
+            }
+            ast::Stmt::Expr { expr, span } => {
+                self.expr(expr)?;
+                self.emit(Instr::Pop, (span.line, span.column));
+            }
+            ast::Stmt::If {
+                cond,
+                then_stmt,
+                else_stmt,
+                ..
+            } => {
+                self.expr(cond)?;
+                let to_else = self.emit_jump(Instr::JumpIfFalse, cond.span());
+                self.stmt(then_stmt)?;
+                match else_stmt {
+                    Some(els) => {
+                        let to_end = self.emit_jump(Instr::Jump, els.span());
+                        self.patch(to_else);
+                        self.stmt(els)?;
+                        self.patch(to_end);
+                    }
+                    None => self.patch(to_else),
+                }
+            }
+            ast::Stmt::While { cond, body, span } => {
+                let top = self.code.len() as u32;
+                self.expr(cond)?;
+                let to_end = self.emit_jump(Instr::JumpIfFalse, cond.span());
+                self.stmt(body)?;
+                self.emit(Instr::Jump(top), (span.line, span.column));
+                self.patch(to_end);
+            }
+            ast::Stmt::For {
+                id,
+                start,
+                end,
+                body,
+                span,
+            } => {
+                let pos = (span.line, span.column);
+                self.expr(start)?;
+                self.expr(end)?;
+
+                self.scopes.push(self.locals.len());
+                let slot = self.declare(id);
+                let end_slot = self.declare(HIDDEN);
+                self.emit(Instr::Store(end_slot), pos);
+                self.emit(Instr::Store(slot), pos);
+
+                let test = self.code.len() as u32;
+                self.emit(Instr::ForTest { slot, exit: 0 }, pos);
+                self.stmt(body)?;
+                self.emit(Instr::ForStep { slot, target: test }, pos);
+                let exit = self.code.len() as u32;
+                self.code[test as usize] = Instr::ForTest { slot, exit };
+                self.end_scope();
+            }
+            ast::Stmt::Return {
+                expr: ast::Expr::Call { id, args, span },
+                ..
+            } if self.tail_callee.as_ref() == Some(id)
+                && self.signatures.get(id).map(|&(_, params)| params) == Some(args.len()) =>
+            {
+                // The arguments replace the parameters, and the function
+                // starts again.
+                let pos = (span.line, span.column);
+                for arg in args {
+                    self.expr(arg)?;
+                }
+                for slot in (0..args.len() as u32).rev() {
+                    self.emit(Instr::Store(slot), pos);
+                }
+                self.emit(Instr::Jump(0), pos);
+            }
+            ast::Stmt::Return { expr, span } => {
+                self.expr(expr)?;
+                self.emit(Instr::Return, (span.line, span.column));
+            }
+            ast::Stmt::Block { stmts, .. } => {
+                self.scopes.push(self.locals.len());
+                for s in stmts {
+                    self.stmt(s)?;
+                }
+                self.end_scope();
+            }
+            ast::Stmt::FnDef { span, .. } => {
+                return Err(CompileError::InvalidCode {
+                    line: span.line,
+                    column: span.column,
+                })
+            }
+            ast::Stmt::Empty { .. } => {}
+        }
+
+        Ok(())
+    }
+
+    /// Compiles an expression. Expressions push exactly one value.
+    fn expr(&mut self, expr: &ast::Expr) -> Result<(), CompileError> {
+        let span = expr.span();
+        let pos = (span.line, span.column);
+
+        match expr {
+            ast::Expr::LiteralInt { value, .. } => self.constant(Value::Int(*value), pos),
+            ast::Expr::LiteralStr { value, .. } => {
+                self.constant(Value::Str(value.as_str().into()), pos)
+            }
+            ast::Expr::LiteralChar { value, .. } => self.constant(Value::Char(*value), pos),
+            ast::Expr::LiteralFloat { value, .. } => self.constant(Value::Float(*value), pos),
+            ast::Expr::LiteralBool { value, .. } => self.constant(Value::Bool(*value), pos),
+            ast::Expr::LiteralArray { elements, .. } => {
+                if elements.is_empty() {
+                    self.constant(Value::Array(Rc::new([])), pos);
+                } else {
+                    for e in elements {
+                        self.expr(e)?;
+                    }
+                    self.emit(Instr::Array(elements.len() as u32), pos);
+                }
+            }
+            ast::Expr::BinaryOp { l, op, r, .. } => {
+                let op_pos = (op.span().line, op.span().column);
+                let instr = match op {
+                    ast::BinOp::LogAnd { .. } | ast::BinOp::LogOr { .. } => {
+                        self.expr(l)?;
+                        let jump = if let ast::BinOp::LogAnd { .. } = op {
+                            Instr::JumpIfFalseOrPop
+                        } else {
+                            Instr::JumpIfTrueOrPop
+                        };
+                        let to_end = self.emit_jump(jump, l.span());
+                        self.expr(r)?;
+                        self.patch(to_end);
+                        return Ok(());
+                    }
+                    ast::BinOp::Add { .. } => Instr::Add,
+                    ast::BinOp::Sub { .. } => Instr::Sub,
+                    ast::BinOp::Mul { .. } => Instr::Mul,
+                    ast::BinOp::Div { .. } => Instr::Div,
+                    ast::BinOp::Mod { .. } => Instr::Mod,
+                    ast::BinOp::BitAnd { .. } => Instr::BitAnd,
+                    ast::BinOp::BitOr { .. } => Instr::BitOr,
+                    ast::BinOp::Eq { .. } => Instr::Eq,
+                    ast::BinOp::NEq { .. } => Instr::NEq,
+                    ast::BinOp::LT { .. } => Instr::LT,
+                    ast::BinOp::GT { .. } => Instr::GT,
+                    ast::BinOp::LEq { .. } => Instr::LEq,
+                    ast::BinOp::GEq { .. } => Instr::GEq,
+                };
+                self.expr(l)?;
+                self.expr(r)?;
+                self.emit(instr, op_pos);
+            }
+            ast::Expr::UnaryOp { op, expr, .. } => {
+                self.expr(expr)?;
+                let instr = match op {
+                    ast::UnOp::Neg { .. } => Instr::Neg,
+                    ast::UnOp::LogNot { .. } => Instr::LogNot,
+                    ast::UnOp::BitNot { .. } => Instr::BitNot,
+                };
+                self.emit(instr, (op.span().line, op.span().column));
+            }
+            ast::Expr::Identifier { id, .. } => {
+                let slot = self.resolve(id, pos)?;
+                self.emit(Instr::Load(slot), pos);
+            }
+            ast::Expr::Call { id, args, .. } => {
+                for arg in args {
+                    self.expr(arg)?;
+                }
+                let argc = args.len();
+
+                match self.signatures.get(id) {
+                    Some(&(function, params)) if params == argc => self.emit(
+                        Instr::Call {
+                            function,
+                            argc: argc as u32,
+                        },
+                        pos,
+                    ),
+                    Some(&(_, params)) => {
+                        return Err(CompileError::WrongArgumentCount {
+                            function: id.clone(),
+                            expected: params,
+                            found: argc,
+                            line: pos.0,
+                            column: pos.1,
+                        })
+                    }
+                    None if id == "println" => self.emit(Instr::Println(argc as u32), pos),
+                    None => {
+                        return Err(CompileError::UndefinedFunction {
+                            id: id.clone(),
+                            line: pos.0,
+                            column: pos.1,
+                        })
+                    }
+                }
+            }
+            ast::Expr::ArrayExpr { id, index, .. } => {
+                self.expr(index)?;
+                let slot = self.resolve(id, pos)?;
+                self.emit(Instr::Load(slot), pos);
+                self.emit(Instr::Index, pos);
+            }
+            ast::Expr::Assign { id, expr, .. } => {
+                self.expr(expr)?;
+                let slot = self.resolve(id, pos)?;
+                self.emit(Instr::Dup, pos);
+                self.emit(Instr::Store(slot), pos);
+            }
+            ast::Expr::Error { .. } => {
+                return Err(CompileError::InvalidCode {
+                    line: pos.0,
+                    column: pos.1,
+                })
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Appends an instruction to the function being compiled.
+    fn emit(&mut self, instr: Instr, pos: (usize, usize)) {
+        self.code.push(instr);
+        self.positions.push(pos);
+    }
+
+    /// Appends a jump instruction with unknown target, and returns its
+    /// index to be [patched](Self::patch) later.
+    fn emit_jump(&mut self, jump: fn(u32) -> Instr, span: Span) -> usize {
+        self.emit(jump(0), (span.line, span.column));
+        self.code.len() - 1
+    }
+
+    /// Sets the target of a jump instruction to the next instruction.
+    fn patch(&mut self, jump: usize) {
+        let target = self.code.len() as u32;
+        self.code[jump] = match self.code[jump] {
+            Instr::Jump(_) => Instr::Jump(target),
+            Instr::JumpIfFalse(_) => Instr::JumpIfFalse(target),
+            Instr::JumpIfFalseOrPop(_) => Instr::JumpIfFalseOrPop(target),
+            Instr::JumpIfTrueOrPop(_) => Instr::JumpIfTrueOrPop(target),
+            _ => unreachable!("Only jumps are patched"),
+        };
+    }
+
+    /// Pushes a value of the constants pool, adding it to the pool if needed.
+    fn constant(&mut self, value: Value, pos: (usize, usize)) {
+        let constants = &mut self.program.constants;
+        let index = match constants.iter().position(|c| same_constant(c, &value)) {
+            Some(i) => i,
+            None => {
+                constants.push(value);
+                constants.len() - 1
+            }
+        };
+        self.emit(Instr::Const(index as u32), pos);
+    }
+
+    /// Adds a variable to the current scope and returns its slot.
+    fn declare(&mut self, id: &str) -> u32 {
+        self.locals.push(id.to_string());
+        self.max_locals = self.max_locals.max(self.locals.len());
+        (self.locals.len() - 1) as u32
+    }
+
+    /// Returns the slot of a variable.
+    fn resolve(&self, id: &str, pos: (usize, usize)) -> Result<u32, CompileError> {
+        self.locals
+            .iter()
+            .rposition(|l| l == id)
+            .map(|slot| slot as u32)
+            .ok_or_else(|| CompileError::UndefinedVariable {
+                id: id.to_string(),
+                line: pos.0,
+                column: pos.1,
+            })
+    }
+
+    /// Closes the innermost scope. Its slots are reused by later variables.
+    fn end_scope(&mut self) {
+        let start = self.scopes.pop().expect("There is an open scope");
+        self.locals.truncate(start);
+    }
+}
+
+/// Checks whether two constants are the same. Unlike `==`, floats are
+/// compared by their bits, so `0.0` and `-0.0` are different constants.
+pub(crate) fn same_constant(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Float(a), Value::Float(b)) => a.to_bits() == b.to_bits(),
+        _ => a == b,
+    }
+}
+
+/// Compiles the function definitions of a program to bytecode.
+/// Other top level statements are ignored, like the interpreter does.
+///
+/// # Errors
+/// Returns the first error found in the code. Code which passes the
+/// semantic checks compiles without errors.
+///
+/// # Examples
+///
+/// ```
+/// use lexer::Lexer;
+/// use parser::Parser;
+///
+/// let source = "fn main() { println(1 + 2); }".as_bytes();
+/// let (ast, _) = Parser::new(Lexer::new(source)).parse().unwrap();
+///
+/// let program = vm::compile(&ast).unwrap();
+/// assert_eq!(program.functions.len(), 1);
+/// assert_eq!(program.function("main").unwrap().arity, 0);
+/// ```
+pub fn compile(prog: &[ast::Stmt]) -> Result<Program, CompileError> {
+    let mut c = Compiler::new();
+
+    let functions = prog.iter().filter(|s| matches!(s, ast::Stmt::FnDef { .. }));
+    for (i, stmt) in functions.clone().enumerate() {
+        if let ast::Stmt::FnDef { id, params, .. } = stmt {
+            c.signatures.insert(id.clone(), (i as u32, params.len()));
+        }
+    }
+    for stmt in functions {
+        c.function(stmt)?;
+    }
+
+    Ok(c.program)
+}
@@ -0,0 +1,82 @@
+use std::fmt::Write;
+
+use interpreter::value::Value;
+
+use crate::bytecode::{Function, Instr, Program};
+
+/// Returns a human readable listing of the instructions of a function.
+///
+/// Each line has the index of the instruction, its position in code
+/// (or `|` if it is the same as the position of the previous instruction)
+/// and the instruction itself. Constants and called functions are
+/// shown after `;`.
+///
+/// # Examples
+///
+/// ```
+/// use lexer::Lexer;
+/// use parser::Parser;
+///
+/// let source = "fn main() { println(1 + 2); }".as_bytes();
+/// let (ast, _) = Parser::new(Lexer::new(source)).parse().unwrap();
+/// let program = vm::compile(&ast).unwrap();
+///
+/// let listing = vm::disassemble(&program, program.function("main").unwrap());
+/// assert_eq!(
+///     listing,
+///     "\
+/// fn main (0 params, 0 locals)
+/// 0000    1:21  const 0             ; 1
+/// 0001    1:25  const 1             ; 2
+/// 0002    1:23  add
+/// 0003    1:13  println 1
+/// 0004     |    pop
+/// 0005    1:30  void
+/// 0006     |    return
+/// "
+/// );
+/// ```
+pub fn disassemble(program: &Program, function: &Function) -> String {
+    let mut s = String::new();
+    let _ = writeln!(
+        s,
+        "fn {} ({} params, {} locals)",
+        function.name, function.arity, function.locals
+    );
+
+    let mut last = None;
+    for (i, (instr, &(line, column))) in function.code.iter().zip(&function.positions).enumerate() {
+        let pos = if last == Some((line, column)) {
+            format!("{:>5}   ", "|")
+        } else {
+            format!("{:>4}:{:<3}", line, column)
+        };
+        last = Some((line, column));
+
+        let comment = match instr {
+            Instr::Const(c) => program.constants.get(*c as usize).map(constant),
+            Instr::Call { function, .. } => program
+                .functions
+                .get(*function as usize)
+                .map(|f| f.name.clone()),
+            _ => None,
+        };
+        let instr = instr.to_string();
+        let _ = match comment {
+            Some(comment) => writeln!(s, "{:04} {} {:<20}; {}", i, pos, instr, comment),
+            None => writeln!(s, "{:04} {} {}", i, pos, instr),
+        };
+    }
+
+    s
+}
+
+/// Returns a constant as it is written in Kolang code.
+fn constant(value: &Value) -> String {
+    match value {
+        Value::Str(s) => format!("{:?}", s),
+        Value::Char(c) => format!("{:?}", c),
+        Value::Float(n) if n.fract() == 0.0 && n.is_finite() => format!("{:.1}", n),
+        v => v.to_string(),
+    }
+}
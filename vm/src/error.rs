@@ -0,0 +1,148 @@
+use std::{error, fmt, io};
+
+/// Errors which may occur while compiling Kolang code to bytecode.
+/// Code which passes the semantic checks compiles without errors.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CompileError {
+    /// Variable is used before definition.
+    UndefinedVariable {
+        /// Name of the variable.
+        id: String,
+        /// Line of code where the variable is used.
+        line: usize,
+        /// Column of code where the variable is used.
+        column: usize,
+    },
+    /// Called function is not defined.
+    UndefinedFunction {
+        /// Name of the function.
+        id: String,
+        /// Line of code where the function is called.
+        line: usize,
+        /// Column of code where the function is called.
+        column: usize,
+    },
+    /// Function is called with wrong number of arguments.
+    WrongArgumentCount {
+        /// Name of the function.
+        function: String,
+        /// Number of function parameters.
+        expected: usize,
+        /// Number of provided arguments.
+        found: usize,
+        /// Line of code where the function is called.
+        line: usize,
+        /// Column of code where the function is called.
+        column: usize,
+    },
+    /// Code contains a syntax error, so it cannot be compiled.
+    InvalidCode {
+        /// Line of code where the error is.
+        line: usize,
+        /// Column of code where the error is.
+        column: usize,
+    },
+}
+
+impl CompileError {
+    /// Returns line and column of code where the error happened.
+    pub fn position(&self) -> (usize, usize) {
+        match self {
+            CompileError::UndefinedVariable { line, column, .. }
+            | CompileError::UndefinedFunction { line, column, .. }
+            | CompileError::WrongArgumentCount { line, column, .. }
+            | CompileError::InvalidCode { line, column } => (*line, *column),
+        }
+    }
+}
+
+impl fmt::Display for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CompileError::UndefinedVariable { id, .. } => {
+                write!(f, "Variable `{id}` is not defined")
+            }
+            CompileError::UndefinedFunction { id, .. } => {
+                write!(f, "Function `{id}` is not defined")
+            }
+            CompileError::WrongArgumentCount {
+                function,
+                expected,
+                found,
+                ..
+            } => {
+                let s = if *expected == 1 { "" } else { "s" };
+                let verb = if *found == 1 { "was" } else { "were" };
+                write!(
+                    f,
+                    "Function `{function}` takes {expected} argument{s} but {found} {verb} supplied"
+                )
+            }
+            CompileError::InvalidCode { .. } => f.write_str("Code contains syntax errors"),
+        }
+    }
+}
+
+impl error::Error for CompileError {}
+
+/// Errors which may occur while loading a bytecode file.
+#[derive(Debug)]
+pub enum LoadError {
+    /// The file does not start with the bytecode file signature.
+    NotBytecode,
+    /// The file is written in a format version which is not supported.
+    UnsupportedVersion {
+        /// Version of the file.
+        found: u32,
+    },
+    /// The file is shorter than its header says.
+    Truncated {
+        /// Length of the file according to its header.
+        expected: usize,
+        /// Actual length of the file.
+        found: usize,
+    },
+    /// The file is damaged or contains invalid bytecode.
+    Corrupted {
+        /// Description of the problem.
+        message: String,
+    },
+    /// Something went wrong while reading the file.
+    Io(io::Error),
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadError::NotBytecode => f.write_str("Not a Kolang bytecode file"),
+            LoadError::UnsupportedVersion { found } => write!(
+                f,
+                "Unsupported bytecode version {found} (supported version is {})",
+                crate::kbc::VERSION
+            ),
+            LoadError::Truncated { expected, found } => write!(
+                f,
+                "Bytecode file is truncated: expected {expected} bytes, found {found}"
+            ),
+            LoadError::Corrupted { message } => {
+                write!(f, "Bytecode file is corrupted: {message}")
+            }
+            LoadError::Io(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl error::Error for LoadError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            LoadError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for LoadError {
+    fn from(e: io::Error) -> Self {
+        LoadError::Io(e)
+    }
+}
@@ -0,0 +1,273 @@
+use std::rc::Rc;
+
+use interpreter::value::Value;
+use ir::instr::{self, BinOp, BlockId, Constant, Reg, Terminator, UnOp};
+
+use crate::{
+    bytecode::{Function, Instr, Program},
+    compiler::same_constant,
+};
+
+/// The `IrCompiler` struct compiles functions of the intermediate
+/// representation to bytecode. Each register gets its own local slot, so
+/// each instruction loads its operands and stores its result.
+struct IrCompiler<'a> {
+    /// The compiled functions.
+    ir: &'a ir::Program,
+    /// The compiled program.
+    program: Program,
+    /// Instructions of the function being compiled.
+    code: Vec<Instr>,
+    /// Positions of the instructions in `code`.
+    positions: Vec<(usize, usize)>,
+    /// Jumps to patch once the function is compiled, and their target blocks.
+    jumps: Vec<(usize, BlockId)>,
+}
+
+impl IrCompiler<'_> {
+    /// Compiles a function which is not in SSA form and adds it to the
+    /// program.
+    fn function(&mut self, function: &ir::Function) {
+        self.code.clear();
+        self.positions.clear();
+        self.jumps.clear();
+        let pos = function.pos;
+
+        let mut starts = Vec::with_capacity(function.blocks.len());
+        for id in function.block_ids() {
+            starts.push(self.code.len() as u32);
+            let block = function.block(id);
+            for instr in &block.instrs {
+                self.instr(instr, pos);
+            }
+
+            let next = BlockId(id.0 + 1);
+            match block.terminator.as_ref().expect("Blocks have terminators") {
+                Terminator::Jump(target) => self.jump(*target, next, pos),
+                Terminator::Branch {
+                    cond,
+                    then_block,
+                    else_block,
+                } => {
+                    self.emit(Instr::Load(cond.0), pos);
+                    self.jumps.push((self.code.len(), *else_block));
+                    self.emit(Instr::JumpIfFalse(0), pos);
+                    self.jump(*then_block, next, pos);
+                }
+                Terminator::Return(Some(reg)) => {
+                    self.emit(Instr::Load(reg.0), pos);
+                    self.emit(Instr::Return, pos);
+                }
+                Terminator::Return(None) => {
+                    self.emit(Instr::Void, pos);
+                    self.emit(Instr::Return, pos);
+                }
+                Terminator::MissingReturn { pos } => self.emit(Instr::MissingReturn, *pos),
+            }
+        }
+
+        for &(i, target) in &self.jumps {
+            let start = starts[target.0 as usize];
+            self.code[i] = match self.code[i] {
+                Instr::Jump(_) => Instr::Jump(start),
+                Instr::JumpIfFalse(_) => Instr::JumpIfFalse(start),
+                _ => unreachable!("Only jumps are patched"),
+            };
+        }
+
+        self.program.functions.push(Function {
+            name: function.name.clone(),
+            arity: function.arity,
+            locals: function.regs.len() as u32,
+            code: std::mem::take(&mut self.code),
+            positions: std::mem::take(&mut self.positions),
+            pos: function.pos,
+        });
+    }
+
+    /// Compiles an instruction. `pos` is the position of the function, for
+    /// instructions which cannot fail.
+    fn instr(&mut self, instr: &instr::Instr, pos: (usize, usize)) {
+        let load = |c: &mut Self, regs: &[Reg]| {
+            for reg in regs {
+                c.emit(Instr::Load(reg.0), pos);
+            }
+        };
+
+        match instr {
+            instr::Instr::Const { dest, value } => {
+                self.constant(self::value(value), pos);
+                self.emit(Instr::Store(dest.0), pos);
+            }
+            instr::Instr::Copy { dest, src } => {
+                self.emit(Instr::Load(src.0), pos);
+                self.emit(Instr::Store(dest.0), pos);
+            }
+            instr::Instr::Binary {
+                dest,
+                op,
+                l,
+                r,
+                pos: op_pos,
+            } => {
+                load(self, &[*l, *r]);
+                self.emit(binary(*op), *op_pos);
+                self.emit(Instr::Store(dest.0), pos);
+            }
+            instr::Instr::Unary { dest, op, src } => {
+                load(self, &[*src]);
+                let instr = match op {
+                    UnOp::Neg => Instr::Neg,
+                    UnOp::LogNot => Instr::LogNot,
+                    UnOp::BitNot => Instr::BitNot,
+                };
+                self.emit(instr, pos);
+                self.emit(Instr::Store(dest.0), pos);
+            }
+            instr::Instr::Array { dest, elements } => {
+                load(self, elements);
+                self.emit(Instr::Array(elements.len() as u32), pos);
+                self.emit(Instr::Store(dest.0), pos);
+            }
+            instr::Instr::Index {
+                dest,
+                array,
+                index,
+                pos: index_pos,
+            } => {
+                load(self, &[*index, *array]);
+                self.emit(Instr::Index, *index_pos);
+                self.emit(Instr::Store(dest.0), pos);
+            }
+            instr::Instr::Call {
+                dest,
+                function,
+                args,
+                pos: call_pos,
+            } => {
+                load(self, args);
+                let index = self
+                    .ir
+                    .functions
+                    .iter()
+                    .rposition(|f| f.name == *function)
+                    .expect("Called functions are defined");
+                self.emit(
+                    Instr::Call {
+                        function: index as u32,
+                        argc: args.len() as u32,
+                    },
+                    *call_pos,
+                );
+                match dest {
+                    Some(dest) => self.emit(Instr::Store(dest.0), pos),
+                    None => self.emit(Instr::Pop, pos),
+                }
+            }
+            instr::Instr::Println { args } => {
+                load(self, args);
+                self.emit(Instr::Println(args.len() as u32), pos);
+                self.emit(Instr::Pop, pos);
+            }
+            instr::Instr::Phi { .. } => unreachable!("Phis are replaced before compiling"),
+        }
+    }
+
+    /// Appends a jump to a block, unless the block is the next one.
+    fn jump(&mut self, target: BlockId, next: BlockId, pos: (usize, usize)) {
+        if target != next {
+            self.jumps.push((self.code.len(), target));
+            self.emit(Instr::Jump(0), pos);
+        }
+    }
+
+    /// Appends an instruction to the function being compiled.
+    fn emit(&mut self, instr: Instr, pos: (usize, usize)) {
+        self.code.push(instr);
+        self.positions.push(pos);
+    }
+
+    /// Pushes a value of the constants pool, adding it to the pool if needed.
+    fn constant(&mut self, value: Value, pos: (usize, usize)) {
+        let constants = &mut self.program.constants;
+        let index = match constants.iter().position(|c| same_constant(c, &value)) {
+            Some(i) => i,
+            None => {
+                constants.push(value);
+                constants.len() - 1
+            }
+        };
+        self.emit(Instr::Const(index as u32), pos);
+    }
+}
+
+/// Returns the instruction of a binary operator.
+fn binary(op: BinOp) -> Instr {
+    match op {
+        BinOp::Add => Instr::Add,
+        BinOp::Sub => Instr::Sub,
+        BinOp::Mul => Instr::Mul,
+        BinOp::Div => Instr::Div,
+        BinOp::Mod => Instr::Mod,
+        BinOp::BitAnd => Instr::BitAnd,
+        BinOp::BitOr => Instr::BitOr,
+        BinOp::Eq => Instr::Eq,
+        BinOp::NEq => Instr::NEq,
+        BinOp::LT => Instr::LT,
+        BinOp::GT => Instr::GT,
+        BinOp::LEq => Instr::LEq,
+        BinOp::GEq => Instr::GEq,
+    }
+}
+
+/// Returns the runtime value of a constant.
+fn value(c: &Constant) -> Value {
+    match c {
+        Constant::Int(n) => Value::Int(*n),
+        Constant::Float(x) => Value::Float(*x),
+        Constant::Char(c) => Value::Char(*c),
+        Constant::Str(s) => Value::Str(s.as_str().into()),
+        Constant::Bool(b) => Value::Bool(*b),
+        Constant::EmptyArray(_) => Value::Array(Rc::new([])),
+    }
+}
+
+/// Compiles a program of the intermediate representation (which may be in
+/// SSA form, like [`ir::optimize`] leaves it) to bytecode. Registers become
+/// local slots, so the result runs slower than the output of
+/// [`compile`](crate::compile) unless the code was optimized.
+///
+/// # Examples
+///
+/// ```
+/// use ir::Pass;
+/// use lexer::Lexer;
+/// use parser::Parser;
+///
+/// let source = "fn main() { let x: int = 6; println(x * 7); }".as_bytes();
+/// let (ast, _) = Parser::new(Lexer::new(source)).parse().unwrap();
+/// let mut program = ir::lower(&ast).unwrap();
+/// ir::optimize(&mut program, Pass::level(2));
+///
+/// let program = vm::compile_ir(&program);
+/// let mut out = Vec::new();
+/// vm::run(&program, &mut out).unwrap();
+/// assert_eq!(out, b"42\n");
+/// ```
+pub fn compile_ir(program: &ir::Program) -> Program {
+    let mut c = IrCompiler {
+        ir: program,
+        program: Program::default(),
+        code: Vec::new(),
+        positions: Vec::new(),
+        jumps: Vec::new(),
+    };
+
+    for function in &program.functions {
+        let mut function = function.clone();
+        ir::ssa::destruct(&mut function);
+        c.function(&function);
+    }
+
+    c.program
+}
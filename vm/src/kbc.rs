@@ -0,0 +1,680 @@
+//! A `.kbc` file stores a compiled [`Program`]. All numbers are little-endian.
+//!
+//! ```text
+//! file     = header payload
+//! header   = "KBC\0" version:u32 length:u32 checksum:u32
+//! payload  = count:u32 constant* count:u32 function*
+//! constant = tag:u8 value
+//!            (0 int:i64, 1 float:f64, 2 char:u32, 3 str, 4 bool:u8,
+//!             5 array = count:u32 constant*)
+//! function = name:str arity:u32 locals:u32 line:u32 column:u32
+//!            count:u32 instr* count:u32 line*
+//! instr    = opcode:u8 operand:u32*
+//! line     = instr:u32 line:u32 column:u32
+//! str      = length:u32 utf8-bytes
+//! ```
+//!
+//! `length` is the length of the payload and `checksum` is its CRC-32.
+//! Each entry of the line table gives the position in code of an instruction
+//! and the instructions after it, until the next entry.
+
+use std::io::{Read, Write};
+
+use interpreter::value::Value;
+
+use crate::{
+    bytecode::{Function, Instr, Program},
+    error::LoadError,
+};
+
+/// Signature at the start of every bytecode file.
+pub const MAGIC: [u8; 4] = *b"KBC\0";
+
+/// Version of the bytecode format which is written and can be loaded.
+pub const VERSION: u32 = 2;
+
+/// Length of the file header in bytes.
+const HEADER_LEN: usize = 16;
+
+/// Checks whether the bytes start with the bytecode file signature.
+pub fn is_bytecode(bytes: &[u8]) -> bool {
+    bytes.starts_with(&MAGIC)
+}
+
+/// Encodes a program as the contents of a bytecode file.
+///
+/// # Examples
+///
+/// ```
+/// use lexer::Lexer;
+/// use parser::Parser;
+///
+/// let source = "fn main() { println(1 + 2); }".as_bytes();
+/// let (ast, _) = Parser::new(Lexer::new(source)).parse().unwrap();
+/// let program = vm::compile(&ast).unwrap();
+///
+/// let bytes = vm::kbc::to_bytes(&program);
+/// assert!(vm::kbc::is_bytecode(&bytes));
+/// assert_eq!(vm::kbc::from_bytes(&bytes).unwrap(), program);
+/// ```
+pub fn to_bytes(program: &Program) -> Vec<u8> {
+    let mut payload = Vec::new();
+
+    put_u32(&mut payload, program.constants.len());
+    for c in &program.constants {
+        put_constant(&mut payload, c);
+    }
+
+    put_u32(&mut payload, program.functions.len());
+    for f in &program.functions {
+        put_str(&mut payload, &f.name);
+        put_u32(&mut payload, f.arity as usize);
+        put_u32(&mut payload, f.locals as usize);
+        put_u32(&mut payload, f.pos.0);
+        put_u32(&mut payload, f.pos.1);
+
+        put_u32(&mut payload, f.code.len());
+        for instr in &f.code {
+            put_instr(&mut payload, instr);
+        }
+
+        let mut lines = Vec::new();
+        let mut last = None;
+        for (i, &pos) in f.positions.iter().enumerate() {
+            if last != Some(pos) {
+                lines.push((i, pos));
+                last = Some(pos);
+            }
+        }
+        put_u32(&mut payload, lines.len());
+        for (i, (line, column)) in lines {
+            put_u32(&mut payload, i);
+            put_u32(&mut payload, line);
+            put_u32(&mut payload, column);
+        }
+    }
+
+    let mut bytes = Vec::with_capacity(HEADER_LEN + payload.len());
+    bytes.extend_from_slice(&MAGIC);
+    bytes.extend_from_slice(&VERSION.to_le_bytes());
+    put_u32(&mut bytes, payload.len());
+    bytes.extend_from_slice(&crc32(&payload).to_le_bytes());
+    bytes.extend_from_slice(&payload);
+    bytes
+}
+
+/// Writes a program to a bytecode file.
+///
+/// # Errors
+/// Returns any error which happens while writing.
+pub fn write<W: Write>(program: &Program, mut out: W) -> std::io::Result<()> {
+    out.write_all(&to_bytes(program))?;
+    out.flush()
+}
+
+/// Decodes and [verifies](verify) a program from the contents of a bytecode file.
+///
+/// # Errors
+/// Returns an error if the bytes are not a bytecode file of a supported
+/// version, or if the file is truncated, damaged or contains invalid bytecode.
+pub fn from_bytes(bytes: &[u8]) -> Result<Program, LoadError> {
+    if !is_bytecode(bytes) {
+        return Err(LoadError::NotBytecode);
+    }
+    let mut header = Reader::new(bytes.get(..HEADER_LEN).ok_or(LoadError::Truncated {
+        expected: HEADER_LEN,
+        found: bytes.len(),
+    })?);
+    header.bytes(MAGIC.len())?;
+
+    let version = header.u32()?;
+    if version != VERSION {
+        return Err(LoadError::UnsupportedVersion { found: version });
+    }
+
+    let expected = HEADER_LEN + header.u32()? as usize;
+    let checksum = header.u32()?;
+    if bytes.len() < expected {
+        return Err(LoadError::Truncated {
+            expected,
+            found: bytes.len(),
+        });
+    } else if bytes.len() > expected {
+        return Err(corrupted(format!(
+            "{} unexpected bytes after the end",
+            bytes.len() - expected
+        )));
+    }
+
+    let payload = &bytes[HEADER_LEN..];
+    if crc32(payload) != checksum {
+        return Err(corrupted("checksum mismatch".into()));
+    }
+
+    let mut r = Reader::new(payload);
+    let mut program = Program::default();
+
+    let count = r.count()?;
+    for _ in 0..count {
+        program.constants.push(r.constant(false)?);
+    }
+
+    let count = r.count()?;
+    for _ in 0..count {
+        program.functions.push(r.function()?);
+    }
+
+    if r.offset != payload.len() {
+        return Err(corrupted("unexpected bytes after the last function".into()));
+    }
+
+    verify(&program)?;
+    Ok(program)
+}
+
+/// Reads and [verifies](verify) a program from a bytecode file.
+///
+/// # Errors
+/// Returns any error which happens while reading, or the errors
+/// of [`from_bytes`].
+pub fn read<R: Read>(mut input: R) -> Result<Program, LoadError> {
+    let mut bytes = Vec::new();
+    input.read_to_end(&mut bytes)?;
+    from_bytes(&bytes)
+}
+
+/// Checks that a program can run on the virtual machine without crashing:
+/// all constants, local slots, functions and jump targets which are used
+/// exist, calls pass the right number of arguments, and each instruction
+/// finds enough values on the stack, whichever way it is reached.
+///
+/// Programs made by the compiler always pass these checks.
+///
+/// # Errors
+/// Returns [`LoadError::Corrupted`] which describes the first problem found.
+pub fn verify(program: &Program) -> Result<(), LoadError> {
+    for f in &program.functions {
+        verify_function(program, f)
+            .map_err(|message| corrupted(format!("function `{}`: {}", f.name, message)))?;
+    }
+    Ok(())
+}
+
+/// Checks a function of the program. Returns description of the problem.
+fn verify_function(program: &Program, f: &Function) -> Result<(), String> {
+    if f.arity > f.locals {
+        return Err(format!(
+            "{} parameters do not fit in {} local slots",
+            f.arity, f.locals
+        ));
+    }
+    // Each slot after the parameters is set before use, and the VM
+    // allocates all slots when calling, so do not trust a large count.
+    let stores = f
+        .code
+        .iter()
+        .filter(|i| matches!(i, Instr::Store(_)))
+        .count();
+    if f.locals as usize > f.arity as usize + stores {
+        return Err(format!("{} local slots are too many", f.locals));
+    }
+    if f.positions.len() != f.code.len() {
+        return Err("line table does not match the code".into());
+    }
+
+    // Number of values above the local slots before each instruction.
+    let mut heights: Vec<Option<usize>> = vec![None; f.code.len()];
+    let mut pending = vec![(0, 0)];
+
+    while let Some((ip, height)) = pending.pop() {
+        let Some(instr) = f.code.get(ip) else {
+            return Err("execution runs past the last instruction".into());
+        };
+        match heights[ip] {
+            Some(h) if h == height => continue,
+            Some(h) => {
+                return Err(format!(
+                    "instruction {} is reached with {} and {} values on the stack",
+                    ip, h, height
+                ))
+            }
+            None => heights[ip] = Some(height),
+        }
+
+        let check_slot = |slot: u32| {
+            if slot < f.locals {
+                Ok(())
+            } else {
+                Err(format!("instruction {} uses undefined slot {}", ip, slot))
+            }
+        };
+        let check_target = |target: u32| {
+            if (target as usize) < f.code.len() {
+                Ok(target as usize)
+            } else {
+                Err(format!(
+                    "instruction {} jumps to undefined instruction {}",
+                    ip, target
+                ))
+            }
+        };
+
+        // Values popped and pushed by the instruction.
+        let (pop, push) = match *instr {
+            Instr::Const(i) => {
+                if i as usize >= program.constants.len() {
+                    return Err(format!("instruction {} uses undefined constant {}", ip, i));
+                }
+                (0, 1)
+            }
+            Instr::Void => (0, 1),
+            Instr::Pop => (1, 0),
+            Instr::Dup => (1, 2),
+            Instr::Load(slot) => {
+                check_slot(slot)?;
+                (0, 1)
+            }
+            Instr::Store(slot) => {
+                check_slot(slot)?;
+                (1, 0)
+            }
+            Instr::Array(n) => (n as usize, 1),
+            Instr::Index
+            | Instr::Add
+            | Instr::Sub
+            | Instr::Mul
+            | Instr::Div
+            | Instr::Mod
+            | Instr::BitAnd
+            | Instr::BitOr
+            | Instr::Eq
+            | Instr::NEq
+            | Instr::LT
+            | Instr::GT
+            | Instr::LEq
+            | Instr::GEq => (2, 1),
+            Instr::Neg | Instr::LogNot | Instr::BitNot => (1, 1),
+            Instr::Jump(target) => {
+                pending.push((check_target(target)?, height));
+                continue;
+            }
+            Instr::JumpIfFalse(target) => {
+                need(ip, height, 1)?;
+                pending.push((check_target(target)?, height - 1));
+                (1, 0)
+            }
+            Instr::JumpIfFalseOrPop(target) | Instr::JumpIfTrueOrPop(target) => {
+                need(ip, height, 1)?;
+                pending.push((check_target(target)?, height));
+                (1, 0)
+            }
+            Instr::ForTest { slot, exit: target } | Instr::ForStep { slot, target } => {
+                check_slot(slot)?;
+                check_slot(slot + 1)?;
+                pending.push((check_target(target)?, height));
+                (0, 0)
+            }
+            Instr::Call { function, argc } => match program.functions.get(function as usize) {
+                Some(callee) if callee.arity == argc => (argc as usize, 1),
+                Some(callee) => {
+                    return Err(format!(
+                        "instruction {} calls `{}` with {} arguments instead of {}",
+                        ip, callee.name, argc, callee.arity
+                    ))
+                }
+                None => {
+                    return Err(format!(
+                        "instruction {} calls undefined function {}",
+                        ip, function
+                    ))
+                }
+            },
+            Instr::Println(n) => (n as usize, 1),
+            Instr::Return => {
+                need(ip, height, 1)?;
+                continue;
+            }
+            Instr::MissingReturn => continue,
+        };
+
+        need(ip, height, pop)?;
+        pending.push((ip + 1, height - pop + push));
+    }
+
+    Ok(())
+}
+
+/// Checks that there are enough values on the stack for an instruction.
+fn need(ip: usize, height: usize, n: usize) -> Result<(), String> {
+    if height >= n {
+        Ok(())
+    } else {
+        Err(format!(
+            "instruction {} needs {} values but the stack has {}",
+            ip, n, height
+        ))
+    }
+}
+
+fn corrupted(message: String) -> LoadError {
+    LoadError::Corrupted { message }
+}
+
+fn put_u32(out: &mut Vec<u8>, n: usize) {
+    let n = u32::try_from(n).expect("Program is too large for the bytecode format");
+    out.extend_from_slice(&n.to_le_bytes());
+}
+
+fn put_str(out: &mut Vec<u8>, s: &str) {
+    put_u32(out, s.len());
+    out.extend_from_slice(s.as_bytes());
+}
+
+fn put_constant(out: &mut Vec<u8>, value: &Value) {
+    match value {
+        Value::Int(n) => {
+            out.push(0);
+            out.extend_from_slice(&n.to_le_bytes());
+        }
+        Value::Float(n) => {
+            out.push(1);
+            out.extend_from_slice(&n.to_bits().to_le_bytes());
+        }
+        Value::Char(c) => {
+            out.push(2);
+            put_u32(out, *c as usize);
+        }
+        Value::Str(s) => {
+            out.push(3);
+            put_str(out, s);
+        }
+        Value::Bool(b) => {
+            out.push(4);
+            out.push(*b as u8);
+        }
+        Value::Array(elements) => {
+            out.push(5);
+            put_u32(out, elements.len());
+            for e in elements.iter() {
+                put_constant(out, e);
+            }
+        }
+        Value::Void => unreachable!("The result of functions is not a constant"),
+    }
+}
+
+fn put_instr(out: &mut Vec<u8>, instr: &Instr) {
+    let (opcode, operands): (u8, &[u32]) = match instr {
+        Instr::Const(i) => (0, &[*i]),
+        Instr::Void => (1, &[]),
+        Instr::Pop => (2, &[]),
+        Instr::Dup => (3, &[]),
+        Instr::Load(slot) => (4, &[*slot]),
+        Instr::Store(slot) => (5, &[*slot]),
+        Instr::Array(n) => (6, &[*n]),
+        Instr::Index => (7, &[]),
+        Instr::Add => (8, &[]),
+        Instr::Sub => (9, &[]),
+        Instr::Mul => (10, &[]),
+        Instr::Div => (11, &[]),
+        Instr::Mod => (12, &[]),
+        Instr::BitAnd => (13, &[]),
+        Instr::BitOr => (14, &[]),
+        Instr::Eq => (15, &[]),
+        Instr::NEq => (16, &[]),
+        Instr::LT => (17, &[]),
+        Instr::GT => (18, &[]),
+        Instr::LEq => (19, &[]),
+        Instr::GEq => (20, &[]),
+        Instr::Neg => (21, &[]),
+        Instr::LogNot => (22, &[]),
+        Instr::BitNot => (23, &[]),
+        Instr::Jump(target) => (24, &[*target]),
+        Instr::JumpIfFalse(target) => (25, &[*target]),
+        Instr::JumpIfFalseOrPop(target) => (26, &[*target]),
+        Instr::JumpIfTrueOrPop(target) => (27, &[*target]),
+        Instr::ForTest { slot, exit } => (28, &[*slot, *exit]),
+        Instr::ForStep { slot, target } => (29, &[*slot, *target]),
+        Instr::Call { function, argc } => (30, &[*function, *argc]),
+        Instr::Println(n) => (31, &[*n]),
+        Instr::Return => (32, &[]),
+        Instr::MissingReturn => (33, &[]),
+    };
+
+    out.push(opcode);
+    for operand in operands {
+        out.extend_from_slice(&operand.to_le_bytes());
+    }
+}
+
+/// Reads values from the payload of a bytecode file.
+struct Reader<'a> {
+    bytes: &'a [u8],
+    /// Offset of the next byte to read.
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, offset: 0 }
+    }
+
+    /// Reads `n` bytes. The length in the header is already checked,
+    /// so running out of bytes means the payload is damaged.
+    fn bytes(&mut self, n: usize) -> Result<&'a [u8], LoadError> {
+        let bytes = self
+            .offset
+            .checked_add(n)
+            .and_then(|end| self.bytes.get(self.offset..end))
+            .ok_or_else(|| {
+                corrupted(format!(
+                    "unexpected end of data at byte {}",
+                    HEADER_LEN + self.offset
+                ))
+            })?;
+        self.offset += n;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, LoadError> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, LoadError> {
+        let b = self.bytes(4)?;
+        Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    fn u64(&mut self) -> Result<u64, LoadError> {
+        let mut b = [0; 8];
+        b.copy_from_slice(self.bytes(8)?);
+        Ok(u64::from_le_bytes(b))
+    }
+
+    /// Reads the number of following items. Each item takes at least one
+    /// byte, so counts larger than the rest of data are rejected before
+    /// allocating memory for the items.
+    fn count(&mut self) -> Result<usize, LoadError> {
+        let n = self.u32()? as usize;
+        if n > self.bytes.len() - self.offset {
+            return Err(corrupted(format!(
+                "count {} at byte {} is larger than the file",
+                n,
+                HEADER_LEN + self.offset - 4
+            )));
+        }
+        Ok(n)
+    }
+
+    fn str(&mut self) -> Result<String, LoadError> {
+        let len = self.count()?;
+        let offset = HEADER_LEN + self.offset;
+        let bytes = self.bytes(len)?;
+        String::from_utf8(bytes.to_vec())
+            .map_err(|_| corrupted(format!("invalid UTF-8 string at byte {}", offset)))
+    }
+
+    /// Reads a constant. `nested` is true for elements of arrays.
+    fn constant(&mut self, nested: bool) -> Result<Value, LoadError> {
+        let offset = HEADER_LEN + self.offset;
+        Ok(match self.u8()? {
+            0 => Value::Int(self.u64()? as i64),
+            1 => Value::Float(f64::from_bits(self.u64()?)),
+            2 => {
+                let c = self.u32()?;
+                Value::Char(char::from_u32(c).ok_or_else(|| {
+                    corrupted(format!("invalid character {:#x} at byte {}", c, offset))
+                })?)
+            }
+            3 => Value::Str(self.str()?.into()),
+            // Kolang arrays are single-level.
+            5 if nested => return Err(corrupted(format!("nested array at byte {}", offset))),
+            4 => match self.u8()? {
+                0 => Value::Bool(false),
+                1 => Value::Bool(true),
+                b => {
+                    return Err(corrupted(format!(
+                        "invalid boolean {} at byte {}",
+                        b, offset
+                    )))
+                }
+            },
+            5 => {
+                let len = self.count()?;
+                let mut elements = Vec::with_capacity(len);
+                for _ in 0..len {
+                    elements.push(self.constant(true)?);
+                }
+                Value::Array(elements.into())
+            }
+            tag => {
+                return Err(corrupted(format!(
+                    "unknown constant type {} at byte {}",
+                    tag, offset
+                )))
+            }
+        })
+    }
+
+    fn function(&mut self) -> Result<Function, LoadError> {
+        let name = self.str()?;
+        let arity = self.u32()?;
+        let locals = self.u32()?;
+        let definition = (self.u32()? as usize, self.u32()? as usize);
+
+        let len = self.count()?;
+        let mut code = Vec::with_capacity(len);
+        for _ in 0..len {
+            code.push(self.instr()?);
+        }
+
+        let offset = HEADER_LEN + self.offset;
+        let entries = self.count()?;
+        let mut positions = Vec::with_capacity(code.len());
+        let mut next = None;
+        for i in 0..entries {
+            let start = self.u32()? as usize;
+            let pos = (self.u32()? as usize, self.u32()? as usize);
+            let valid = match next {
+                None => start == 0,
+                Some(next) => start > next,
+            };
+            if !valid || start >= code.len() {
+                return Err(corrupted(format!(
+                    "invalid entry {} in line table at byte {}",
+                    i, offset
+                )));
+            }
+            // The previous entry covers the instructions until this one.
+            if let Some(&last) = positions.last() {
+                positions.resize(start, last);
+            }
+            positions.push(pos);
+            next = Some(start);
+        }
+        if let Some(&last) = positions.last() {
+            positions.resize(code.len(), last);
+        }
+
+        Ok(Function {
+            name,
+            arity,
+            locals,
+            code,
+            positions,
+            pos: definition,
+        })
+    }
+
+    fn instr(&mut self) -> Result<Instr, LoadError> {
+        let offset = HEADER_LEN + self.offset;
+        let opcode = self.u8()?;
+        Ok(match opcode {
+            0 => Instr::Const(self.u32()?),
+            1 => Instr::Void,
+            2 => Instr::Pop,
+            3 => Instr::Dup,
+            4 => Instr::Load(self.u32()?),
+            5 => Instr::Store(self.u32()?),
+            6 => Instr::Array(self.u32()?),
+            7 => Instr::Index,
+            8 => Instr::Add,
+            9 => Instr::Sub,
+            10 => Instr::Mul,
+            11 => Instr::Div,
+            12 => Instr::Mod,
+            13 => Instr::BitAnd,
+            14 => Instr::BitOr,
+            15 => Instr::Eq,
+            16 => Instr::NEq,
+            17 => Instr::LT,
+            18 => Instr::GT,
+            19 => Instr::LEq,
+            20 => Instr::GEq,
+            21 => Instr::Neg,
+            22 => Instr::LogNot,
+            23 => Instr::BitNot,
+            24 => Instr::Jump(self.u32()?),
+            25 => Instr::JumpIfFalse(self.u32()?),
+            26 => Instr::JumpIfFalseOrPop(self.u32()?),
+            27 => Instr::JumpIfTrueOrPop(self.u32()?),
+            28 => Instr::ForTest {
+                slot: self.u32()?,
+                exit: self.u32()?,
+            },
+            29 => Instr::ForStep {
+                slot: self.u32()?,
+                target: self.u32()?,
+            },
+            30 => Instr::Call {
+                function: self.u32()?,
+                argc: self.u32()?,
+            },
+            31 => Instr::Println(self.u32()?),
+            32 => Instr::Return,
+            33 => Instr::MissingReturn,
+            _ => {
+                return Err(corrupted(format!(
+                    "unknown opcode {} at byte {}",
+                    opcode, offset
+                )))
+            }
+        })
+    }
+}
+
+/// Calculates the CRC-32 (IEEE) checksum of data.
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &b in data {
+        crc ^= b as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
@@ -0,0 +1,56 @@
+#![warn(missing_docs)]
+
+//! # Kolang virtual machine
+//! Utilities for compiling Kolang code (or its optimized intermediate
+//! representation) to bytecode and running it on a stack-based virtual
+//! machine.
+
+use std::io::Write;
+
+use interpreter::{error::RuntimeError, value::Value};
+
+/// This module includes the instructions and the compiled form of programs.
+pub mod bytecode;
+/// This module includes the compiler from syntax tree to bytecode.
+pub mod compiler;
+/// This module includes the disassembler, which prints bytecode for humans.
+pub mod disassembler;
+/// This module includes the errors which may occur while compiling Kolang code
+/// and loading bytecode files.
+pub mod error;
+/// This module includes the compiler from the intermediate representation
+/// to bytecode.
+pub mod ir_compiler;
+/// This module includes the writer and the loader of bytecode (`.kbc`) files.
+pub mod kbc;
+/// This module includes the virtual machine which runs bytecode.
+pub mod machine;
+
+pub use bytecode::Program;
+pub use compiler::compile;
+pub use disassembler::disassemble;
+pub use ir_compiler::compile_ir;
+pub use machine::Vm;
+
+/// Runs the `main` function of provided program and writes its output to `out`.
+///
+/// # Errors
+/// Returns any error which happens while running the program.
+///
+/// # Examples
+///
+/// ```
+/// use lexer::Lexer;
+/// use parser::Parser;
+///
+/// let source = "fn main() { println(1 + 2); }".as_bytes();
+/// let (ast, _) = Parser::new(Lexer::new(source)).parse().unwrap();
+/// let program = vm::compile(&ast).unwrap();
+///
+/// let mut out = Vec::new();
+/// vm::run(&program, &mut out).unwrap();
+/// assert_eq!(out, b"3\n");
+/// ```
+pub fn run<W: Write>(program: &Program, out: W) -> Result<Value, RuntimeError> {
+    Vm::new(program, out).run_main()
+}
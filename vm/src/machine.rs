@@ -0,0 +1,442 @@
+use std::{cmp::Ordering, io::Write, mem};
+
+use interpreter::{error::RuntimeError, value::Value, MAX_CALL_DEPTH};
+
+use crate::bytecode::{Function, Instr, Program};
+
+/// A running function call.
+#[derive(Debug, Clone, Copy)]
+struct Frame {
+    /// Index of the function in the program.
+    function: usize,
+    /// Index of the next instruction.
+    ip: usize,
+    /// Index of the first local slot of the function on the stack.
+    base: usize,
+}
+
+/// The `Vm<'a, W>` struct runs a compiled Kolang [`Program`] and writes
+/// the program output to any byte sink which implements [`Write`] trait.
+///
+/// Local variables and intermediate values of all running functions live on
+/// a single value stack, and function calls do not use the native stack.
+/// The machine expects code which passed the semantic checks: it reports
+/// errors which can only be found at runtime (like division by zero), but
+/// does not check the types of variables and function results.
+///
+/// # Examples
+///
+/// ```
+/// use lexer::Lexer;
+/// use parser::Parser;
+/// use vm::Vm;
+///
+/// let source = "fn main() { println(\"Hello \", 6 * 7); }".as_bytes();
+/// let (ast, _) = Parser::new(Lexer::new(source)).parse().unwrap();
+/// let program = vm::compile(&ast).unwrap();
+///
+/// let mut out = Vec::new();
+/// Vm::new(&program, &mut out).run_main().unwrap();
+///
+/// assert_eq!(out, b"Hello 42\n");
+/// ```
+pub struct Vm<'a, W: Write> {
+    /// The running program.
+    program: &'a Program,
+    /// Local slots and intermediate values.
+    stack: Vec<Value>,
+    /// Callers of the running function. The running function is not included.
+    frames: Vec<Frame>,
+    /// Program output.
+    out: W,
+}
+
+impl<'a, W: Write> Vm<'a, W> {
+    /// Creates a new `Vm<'a, W>` which runs provided program
+    /// and writes program output to `out`.
+    pub fn new(program: &'a Program, out: W) -> Self {
+        Self {
+            program,
+            stack: Vec::new(),
+            frames: Vec::new(),
+            out,
+        }
+    }
+
+    /// Calls the `main` function and returns its result.
+    ///
+    /// # Errors
+    /// Returns [`RuntimeError::NoMain`] if `main` is not defined,
+    /// or any error which happens while running the program.
+    pub fn run_main(&mut self) -> Result<Value, RuntimeError> {
+        let main = self
+            .program
+            .function_index("main")
+            .ok_or(RuntimeError::NoMain)?;
+        let f = &self.program.functions[main];
+        if f.arity != 0 {
+            return Err(RuntimeError::WrongArgumentCount {
+                function: f.name.clone(),
+                expected: f.arity as usize,
+                found: 0,
+                line: f.pos.0,
+                column: f.pos.1,
+            });
+        }
+
+        self.stack.clear();
+        self.frames.clear();
+        self.stack.resize(f.locals as usize, Value::Void);
+        let r = self.execute(Frame {
+            function: main,
+            ip: 0,
+            base: 0,
+        });
+        self.out.flush()?;
+        r
+    }
+
+    /// Runs instructions from the provided frame until it returns.
+    fn execute(&mut self, mut frame: Frame) -> Result<Value, RuntimeError> {
+        let program = self.program;
+        let mut function = &program.functions[frame.function];
+
+        loop {
+            let instr = function.code[frame.ip];
+            frame.ip += 1;
+
+            match instr {
+                Instr::Const(i) => self.stack.push(program.constants[i as usize].clone()),
+                Instr::Void => self.stack.push(Value::Void),
+                Instr::Pop => {
+                    self.pop();
+                }
+                Instr::Dup => {
+                    let v = self.peek().clone();
+                    self.stack.push(v);
+                }
+                Instr::Load(slot) => {
+                    let v = self.stack[frame.base + slot as usize].clone();
+                    self.stack.push(v);
+                }
+                Instr::Store(slot) => {
+                    let v = self.pop();
+                    self.stack[frame.base + slot as usize] = v;
+                }
+                Instr::Array(n) => {
+                    let elements = self.stack.split_off(self.stack.len() - n as usize);
+                    let mixed = elements
+                        .windows(2)
+                        .any(|w| mem::discriminant(&w[0]) != mem::discriminant(&w[1]));
+                    if mixed {
+                        let (line, column) = position(function, &frame);
+                        return Err(RuntimeError::TypeMismatch {
+                            message: "Array elements should be of the same type".into(),
+                            line,
+                            column,
+                        });
+                    }
+                    self.stack.push(Value::Array(elements.into()));
+                }
+                Instr::Index => {
+                    let array = self.pop();
+                    let index = self.pop();
+                    let v =
+                        index_array(array, index).map_err(|e| e.at(position(function, &frame)))?;
+                    self.stack.push(v);
+                }
+                Instr::Add
+                | Instr::Sub
+                | Instr::Mul
+                | Instr::Div
+                | Instr::Mod
+                | Instr::BitAnd
+                | Instr::BitOr
+                | Instr::Eq
+                | Instr::NEq
+                | Instr::LT
+                | Instr::GT
+                | Instr::LEq
+                | Instr::GEq => {
+                    let r = self.pop();
+                    let l = self.pop();
+                    let v = binary(instr, l, r).map_err(|e| e.at(position(function, &frame)))?;
+                    self.stack.push(v);
+                }
+                Instr::Neg | Instr::LogNot | Instr::BitNot => {
+                    let v = self.pop();
+                    let v = unary(instr, v).map_err(|e| e.at(position(function, &frame)))?;
+                    self.stack.push(v);
+                }
+                Instr::Jump(target) => frame.ip = target as usize,
+                Instr::JumpIfFalse(target) => {
+                    let cond = self.pop();
+                    if !self.cond(&cond, function, &frame)? {
+                        frame.ip = target as usize;
+                    }
+                }
+                Instr::JumpIfFalseOrPop(target) => {
+                    if self.cond(self.peek(), function, &frame)? {
+                        self.pop();
+                    } else {
+                        frame.ip = target as usize;
+                    }
+                }
+                Instr::JumpIfTrueOrPop(target) => {
+                    if self.cond(self.peek(), function, &frame)? {
+                        frame.ip = target as usize;
+                    } else {
+                        self.pop();
+                    }
+                }
+                Instr::ForTest { slot, exit } => {
+                    let slot = frame.base + slot as usize;
+                    match (&self.stack[slot], &self.stack[slot + 1]) {
+                        (Value::Int(i), Value::Int(end)) => {
+                            if i > end {
+                                frame.ip = exit as usize;
+                            }
+                        }
+                        (a, b) => {
+                            let found = if let Value::Int(_) = a { b } else { a };
+                            let (line, column) = position(function, &frame);
+                            return Err(RuntimeError::TypeMismatch {
+                                message: format!("Expected `int`, found `{}`", found.type_name()),
+                                line,
+                                column,
+                            });
+                        }
+                    }
+                }
+                Instr::ForStep { slot, target } => {
+                    match &mut self.stack[frame.base + slot as usize] {
+                        Value::Int(i) if *i < i64::MAX => {
+                            *i += 1;
+                            frame.ip = target as usize;
+                        }
+                        // The loop ends, like the interpreter does.
+                        _ => {}
+                    }
+                }
+                Instr::Call {
+                    function: callee,
+                    argc,
+                } => {
+                    if self.frames.len() + 1 >= MAX_CALL_DEPTH {
+                        let (line, column) = position(function, &frame);
+                        return Err(RuntimeError::StackOverflow { line, column });
+                    }
+
+                    let base = self.stack.len() - argc as usize;
+                    function = &program.functions[callee as usize];
+                    self.stack
+                        .resize(base + function.locals as usize, Value::Void);
+                    self.frames.push(mem::replace(
+                        &mut frame,
+                        Frame {
+                            function: callee as usize,
+                            ip: 0,
+                            base,
+                        },
+                    ));
+                }
+                Instr::Println(n) => {
+                    let start = self.stack.len() - n as usize;
+                    for v in self.stack.drain(start..) {
+                        write!(self.out, "{}", v)?;
+                    }
+                    writeln!(self.out)?;
+                    self.stack.push(Value::Void);
+                }
+                Instr::Return => {
+                    let v = self.pop();
+                    self.stack.truncate(frame.base);
+
+                    match self.frames.pop() {
+                        Some(caller) => {
+                            frame = caller;
+                            function = &program.functions[frame.function];
+                            self.stack.push(v);
+                        }
+                        None => return Ok(v),
+                    }
+                }
+                Instr::MissingReturn => {
+                    let (line, column) = position(function, &frame);
+                    return Err(RuntimeError::MissingReturn {
+                        function: function.name.clone(),
+                        line,
+                        column,
+                    });
+                }
+            }
+        }
+    }
+
+    /// Removes and returns the top value of the stack.
+    fn pop(&mut self) -> Value {
+        self.stack.pop().expect("Stack is not empty")
+    }
+
+    /// Returns the top value of the stack.
+    fn peek(&self) -> &Value {
+        self.stack.last().expect("Stack is not empty")
+    }
+
+    /// Returns the value of a condition which should be `bool`.
+    fn cond(&self, v: &Value, function: &Function, frame: &Frame) -> Result<bool, RuntimeError> {
+        match v {
+            Value::Bool(b) => Ok(*b),
+            v => {
+                let (line, column) = position(function, frame);
+                Err(RuntimeError::TypeMismatch {
+                    message: format!("Expected `bool`, found `{}`", v.type_name()),
+                    line,
+                    column,
+                })
+            }
+        }
+    }
+}
+
+/// Returns the position of code of the last executed instruction of a frame.
+fn position(function: &Function, frame: &Frame) -> (usize, usize) {
+    function.positions[frame.ip - 1]
+}
+
+/// An error of an operation, which does not know its position in code yet.
+enum OpError {
+    /// Integer division or modulo by zero.
+    DivisionByZero,
+    /// Array index is negative or not less than array length.
+    IndexOutOfBounds {
+        /// The index.
+        index: i64,
+        /// Length of the array.
+        len: usize,
+    },
+    /// Operands are not of the expected types.
+    TypeMismatch(String),
+}
+
+impl OpError {
+    /// Converts the error to a [`RuntimeError`] at provided position.
+    fn at(self, (line, column): (usize, usize)) -> RuntimeError {
+        match self {
+            OpError::DivisionByZero => RuntimeError::DivisionByZero { line, column },
+            OpError::IndexOutOfBounds { index, len } => RuntimeError::IndexOutOfBounds {
+                index,
+                len,
+                line,
+                column,
+            },
+            OpError::TypeMismatch(message) => RuntimeError::TypeMismatch {
+                message,
+                line,
+                column,
+            },
+        }
+    }
+}
+
+/// Returns an element of an array.
+fn index_array(array: Value, index: Value) -> Result<Value, OpError> {
+    let index = match index {
+        Value::Int(i) => i,
+        v => {
+            return Err(OpError::TypeMismatch(format!(
+                "Expected `int`, found `{}`",
+                v.type_name()
+            )))
+        }
+    };
+    let elements = match array {
+        Value::Array(elements) => elements,
+        v => {
+            return Err(OpError::TypeMismatch(format!(
+                "Cannot index into `{}`",
+                v.type_name()
+            )))
+        }
+    };
+
+    match usize::try_from(index).ok().and_then(|i| elements.get(i)) {
+        Some(v) => Ok(v.clone()),
+        None => Err(OpError::IndexOutOfBounds {
+            index,
+            len: elements.len(),
+        }),
+    }
+}
+
+/// Applies a binary operation on values.
+fn binary(instr: Instr, l: Value, r: Value) -> Result<Value, OpError> {
+    use Value::*;
+
+    let v = match (instr, &l, &r) {
+        (Instr::Add, Int(a), Int(b)) => Int(a.wrapping_add(*b)),
+        (Instr::Add, Float(a), Float(b)) => Float(a + b),
+        (Instr::Sub, Int(a), Int(b)) => Int(a.wrapping_sub(*b)),
+        (Instr::Sub, Float(a), Float(b)) => Float(a - b),
+        (Instr::Mul, Int(a), Int(b)) => Int(a.wrapping_mul(*b)),
+        (Instr::Mul, Float(a), Float(b)) => Float(a * b),
+        (Instr::Div | Instr::Mod, Int(_), Int(0)) => return Err(OpError::DivisionByZero),
+        (Instr::Div, Int(a), Int(b)) => Int(a.wrapping_div(*b)),
+        (Instr::Div, Float(a), Float(b)) => Float(a / b),
+        (Instr::Mod, Int(a), Int(b)) => Int(a.wrapping_rem(*b)),
+        (Instr::Mod, Float(a), Float(b)) => Float(a % b),
+        (Instr::BitAnd, Int(a), Int(b)) => Int(a & b),
+        (Instr::BitAnd, Bool(a), Bool(b)) => Bool(a & b),
+        (Instr::BitOr, Int(a), Int(b)) => Int(a | b),
+        (Instr::BitOr, Bool(a), Bool(b)) => Bool(a | b),
+        (Instr::Eq, a, b) if mem::discriminant(a) == mem::discriminant(b) => Bool(a == b),
+        (Instr::NEq, a, b) if mem::discriminant(a) == mem::discriminant(b) => Bool(a != b),
+        (Instr::LT | Instr::GT | Instr::LEq | Instr::GEq, a, b) => {
+            let ord = match (a, b) {
+                (Int(a), Int(b)) => a.partial_cmp(b),
+                (Float(a), Float(b)) => a.partial_cmp(b),
+                (Char(a), Char(b)) => a.partial_cmp(b),
+                _ => return Err(operand_mismatch(instr, &l, &r)),
+            };
+
+            Bool(match instr {
+                Instr::LT => ord == Some(Ordering::Less),
+                Instr::GT => ord == Some(Ordering::Greater),
+                Instr::LEq => matches!(ord, Some(Ordering::Less | Ordering::Equal)),
+                _ => matches!(ord, Some(Ordering::Greater | Ordering::Equal)),
+            })
+        }
+        _ => return Err(operand_mismatch(instr, &l, &r)),
+    };
+
+    Ok(v)
+}
+
+/// Applies a unary operation on a value.
+fn unary(instr: Instr, v: Value) -> Result<Value, OpError> {
+    let v = match (instr, v) {
+        (Instr::Neg, Value::Int(n)) => Value::Int(n.wrapping_neg()),
+        (Instr::Neg, Value::Float(n)) => Value::Float(-n),
+        (Instr::LogNot, Value::Bool(b)) => Value::Bool(!b),
+        (Instr::BitNot, Value::Int(n)) => Value::Int(!n),
+        (instr, v) => {
+            return Err(OpError::TypeMismatch(format!(
+                "Cannot apply `{}` to `{}`",
+                instr.operator().unwrap_or_default(),
+                v.type_name()
+            )))
+        }
+    };
+
+    Ok(v)
+}
+
+/// Creates the error of applying a binary operation on wrong operands.
+fn operand_mismatch(instr: Instr, l: &Value, r: &Value) -> OpError {
+    OpError::TypeMismatch(format!(
+        "Cannot apply `{}` to `{}` and `{}`",
+        instr.operator().unwrap_or_default(),
+        l.type_name(),
+        r.type_name()
+    ))
+}
@@ -0,0 +1,687 @@
+use interpreter::{error::RuntimeError, value::Value};
+use ir::Pass;
+use lexer::Lexer;
+use parser::{ast, Parser};
+use vm::{
+    bytecode::{Function, Instr, Program},
+    error::{CompileError, LoadError},
+    kbc,
+};
+
+fn parse(code: &str) -> Vec<ast::Stmt> {
+    let l = Lexer::new(code.as_bytes());
+    let (ast, diagnostics) = Parser::new(l).parse().unwrap();
+    assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+    ast
+}
+
+fn run(code: &str) -> (Result<Value, RuntimeError>, String) {
+    let program = vm::compile(&parse(code)).unwrap();
+
+    let mut out = Vec::new();
+    let r = vm::run(&program, &mut out);
+
+    (r, String::from_utf8(out).unwrap())
+}
+
+/// Runs the code with both the interpreter and the virtual machine,
+/// checks that they behave the same and returns the output.
+fn same_as_interpreter(code: &str) -> String {
+    let (r, out) = run(code);
+
+    let mut expected = Vec::new();
+    let expected_r = interpreter::run(parse(code), &mut expected);
+
+    assert_eq!(out, String::from_utf8(expected).unwrap());
+    match (r, expected_r) {
+        (Ok(v), Ok(expected)) => assert_eq!(v, expected),
+        (Err(e), Err(expected)) => {
+            assert_eq!(e.to_string(), expected.to_string());
+            assert_eq!(e.position(), expected.position());
+        }
+        (r, expected) => panic!("VM returned {:?}, interpreter returned {:?}", r, expected),
+    }
+
+    out
+}
+
+/// Runs the code compiled from the syntax tree, and from the intermediate
+/// representation optimized with each level and each single pass. Checks
+/// that they behave the same and returns the output.
+fn same_when_optimized(code: &str) -> String {
+    let (r, out) = run(code);
+
+    let mut passes = vec![Pass::level(0), Pass::level(1), Pass::level(2)];
+    passes.extend(Pass::ALL.iter().map(std::slice::from_ref));
+    for passes in passes {
+        let mut program = ir::lower(&parse(code)).unwrap();
+        ir::optimize(&mut program, passes);
+        let program = vm::compile_ir(&program);
+
+        let mut optimized = Vec::new();
+        let optimized_r = vm::run(&program, &mut optimized);
+        assert_eq!(String::from_utf8(optimized).unwrap(), out, "{:?}", passes);
+        match (&r, optimized_r) {
+            (Ok(v), Ok(optimized)) => assert_eq!(*v, optimized),
+            (Err(e), Err(optimized)) => {
+                assert_eq!(e.to_string(), optimized.to_string());
+                assert_eq!(e.position(), optimized.position());
+            }
+            (r, optimized) => panic!("{:?} returned {:?}, not {:?}", passes, optimized, r),
+        }
+    }
+
+    out
+}
+
+#[test]
+fn readme_example() {
+    let out = same_as_interpreter(
+        include_str!("../../README.md")
+            .split("``` Rust")
+            .nth(1)
+            .unwrap()
+            .split("```")
+            .next()
+            .unwrap(),
+    );
+
+    assert_eq!(
+        out,
+        concat!(
+            "Addition of 5 and 10 is: 15\n",
+            "Maximum of 5 and 10 is: 10\n",
+            "Sum of numbers from 1 to 10 is: 55\n",
+        )
+    );
+}
+
+#[test]
+fn operators() {
+    let out = same_as_interpreter("fn main() {
+        println(7 + 2, ' ', 7 - 2, ' ', 7 * 2, ' ', 7 / 2, ' ', 7 % 2, ' ', -7);
+        println(7.5 + 2.0, ' ', 7.5 - 2.0, ' ', 7.5 * 2.0, ' ', 7.5 / 2.0, ' ', 7.5 % 2.0, ' ', -7.5);
+        println(12 & 10, ' ', 12 | 10, ' ', ~0, ' ', true & false, ' ', true | false);
+        println(1 < 2, ' ', 2 > 1, ' ', 2 <= 2, ' ', 1 >= 2, ' ', 'a' < 'b', ' ', 1.5 > 2.5);
+        println(1 == 1, ' ', \"a\" != \"b\", ' ', 'c' == 'c', ' ', [1, 2] == [1, 2]);
+        println(true and false, ' ', true or false, ' ', not true, ' ', false or true and false);
+        println(9223372036854775807 + 1, ' ', -(-9223372036854775807 - 1));
+    }");
+
+    assert_eq!(
+        out,
+        concat!(
+            "9 5 14 3 1 -7\n",
+            "9.5 5.5 15 3.75 1.5 -7.5\n",
+            "8 14 -1 false true\n",
+            "true true true false true false\n",
+            "true true true true\n",
+            "false true false false\n",
+            "-9223372036854775808 -9223372036854775808\n",
+        )
+    );
+}
+
+#[test]
+fn short_circuit() {
+    let out = same_as_interpreter(
+        "fn side(b: bool): bool {
+        println(\"side \", b);
+        return b;
+    }
+
+    fn main() {
+        if false and side(true) { println(\"unreachable\"); }
+        if true or side(true) { println(\"done\"); }
+        println(side(true) and side(false));
+        println(side(false) or side(true));
+    }",
+    );
+
+    assert_eq!(
+        out,
+        "done\nside true\nside false\nfalse\nside false\nside true\ntrue\n"
+    );
+}
+
+#[test]
+fn loops_and_scopes() {
+    let out = same_as_interpreter(
+        "fn main() {
+        let i: int = 100;
+        for i = 1 to 3 {
+            let sq: int = i * i;
+            println(sq);
+        }
+        while i > 98 {
+            i = i - 1;
+        }
+        println(i);
+        for j = 5 to 1 println(\"never\");
+        for j = i to i + 1 {
+            let i: int = j * 2;
+            println(i);
+        }
+        for k = 9223372036854775806 to 9223372036854775807 println(k);
+        for k = 1 to 10 {
+            k = k + 3;
+            println(k);
+        }
+    }",
+    );
+
+    assert_eq!(
+        out,
+        "1\n4\n9\n98\n196\n198\n9223372036854775806\n9223372036854775807\n4\n8\n12\n"
+    );
+}
+
+#[test]
+fn arrays() {
+    let out = same_as_interpreter(
+        "fn sum(arr: int[], n: int): int {
+        let s: int = 0;
+        for i = 0 to n - 1 s = s + arr[i];
+        return s;
+    }
+
+    fn main() {
+        let a: int[] = [1, 2, 3, 4];
+        let words: str[] = [\"salam\", \"donya\"];
+        let empty: float[];
+        println(sum(a, 4), ' ', words[1], ' ', a, ' ', empty);
+        a = [a[3], a[2] * 10];
+        println(a, ' ', [] == empty);
+    }",
+    );
+
+    assert_eq!(out, "10 donya [1, 2, 3, 4] []\n[4, 30] true\n");
+}
+
+#[test]
+fn calls() {
+    let out = same_as_interpreter(
+        "fn fib(n: int): int {
+        if n < 2 return n;
+        return fib(n - 1) + fib(n - 2);
+    }
+
+    fn greet(name: str, times: int) {
+        while times > 0 {
+            println(\"Hello \", name);
+            times = times - 1;
+        }
+    }
+
+    fn main(): int {
+        println(fib(15));
+        greet(\"Kolang\", 2);
+        println(greet(\"again\", 1));
+        return fib(10);
+    }",
+    );
+
+    assert_eq!(out, "610\nHello Kolang\nHello Kolang\nHello again\n()\n");
+    assert_eq!(
+        run("fn main(): int { return 7; }").0.unwrap(),
+        Value::Int(7)
+    );
+}
+
+#[test]
+fn runtime_errors() {
+    same_as_interpreter(
+        "fn main() {
+        println(\"before\");
+        let a: int = 0;
+        println(1 / a);
+    }",
+    );
+    same_as_interpreter("fn main() { println(1 % 0); }");
+    same_as_interpreter("fn main() { let a: int[] = [1, 2, 3]; println(a[3]); }");
+    same_as_interpreter("fn main() { let a: int[] = [1]; println(a[-1]); }");
+    same_as_interpreter("fn main() { println(1 + \"a\"); }");
+    same_as_interpreter("fn main() { println(-true); }");
+    same_as_interpreter("fn main() { if 1 { } }");
+    same_as_interpreter("fn main() { println([1, 'a']); }");
+    same_as_interpreter("fn not_main() {}");
+    same_as_interpreter(
+        "fn f(a: int): int {
+            if a > 0 return a;
+        }
+
+        fn main() {
+            f(1);
+            f(0);
+        }",
+    );
+
+    let (r, out) = run("fn main() {
+        println(\"before\");
+        println(1 / 0);
+    }");
+    assert!(matches!(
+        r,
+        Err(RuntimeError::DivisionByZero {
+            line: 3,
+            column: 19
+        })
+    ));
+    assert_eq!(out, "before\n");
+
+    // Code which is not checked may define `main` with parameters.
+    let code = "fn f() {}\nfn main(x: int) {}";
+    same_as_interpreter(code);
+    same_when_optimized(code);
+    assert_eq!(run(code).0.unwrap_err().position(), Some((2, 1)));
+}
+
+#[test]
+fn stack_overflow() {
+    // The virtual machine does not need a big native stack.
+    let (r, out) = run("fn down(n: int): int {
+        if n == 0 return 0;
+        return down(n - 1) + 1;
+    }
+
+    fn inf(n: int): int { return inf(n + 1) + 1; }
+
+    fn main(): int {
+        println(down(5000));
+        return inf(0);
+    }");
+
+    assert!(matches!(
+        r,
+        Err(RuntimeError::StackOverflow {
+            line: 6,
+            column: 34
+        })
+    ));
+    assert_eq!(out, "5000\n");
+}
+
+#[test]
+fn tail_calls() {
+    let code = "fn sum(n: int, acc: int): int {
+        if n == 0 return acc;
+        return sum(n - 1, acc + n);
+    }
+
+    fn gcd(a: int, b: int): int {
+        if b == 0 return a;
+        return gcd(b, a % b);
+    }
+
+    fn main() {
+        println(sum(100000, 0), ' ', gcd(1071, 462));
+        println(gcd(sum(3, 0), 0) / sum(0, 0));
+    }";
+    assert_eq!(same_as_interpreter(code), "5000050000 21\n");
+    same_when_optimized(code);
+
+    let program = vm::compile(&parse(code)).unwrap();
+    let f = program.function("gcd").unwrap();
+    assert_eq!(
+        f.code[f.code.len() - 5..],
+        [
+            Instr::Mod,
+            Instr::Store(1),
+            Instr::Store(0),
+            Instr::Jump(0),
+            Instr::MissingReturn,
+        ]
+    );
+}
+
+#[test]
+fn compile_errors() {
+    let compile = |code| vm::compile(&parse(code));
+
+    assert_eq!(
+        compile("fn f(a: int) {} fn main() { f(1, 2); }"),
+        Err(CompileError::WrongArgumentCount {
+            function: "f".into(),
+            expected: 1,
+            found: 2,
+            line: 1,
+            column: 29,
+        })
+    );
+    assert!(matches!(
+        compile("fn main() { g(); }"),
+        Err(CompileError::UndefinedFunction { .. })
+    ));
+    assert!(matches!(
+        compile("fn main() { { let x: int; } x = 1; }"),
+        Err(CompileError::UndefinedVariable {
+            line: 1,
+            column: 29,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn bytecode() {
+    let program = vm::compile(&parse(
+        "fn sum_to_n(n: int): int {
+    let sum: int = 0;
+    for i = 1 to n {
+        sum = sum + i;
+    }
+    return sum;
+}",
+    ))
+    .unwrap();
+
+    let f = program.function("sum_to_n").unwrap();
+    assert_eq!((f.arity, f.locals), (1, 4));
+    assert_eq!(f.code.len(), f.positions.len());
+    assert_eq!(
+        f.code,
+        [
+            Instr::Const(0),
+            Instr::Store(1),
+            Instr::Const(1),
+            Instr::Load(0),
+            Instr::Store(3),
+            Instr::Store(2),
+            Instr::ForTest { slot: 2, exit: 14 },
+            Instr::Load(1),
+            Instr::Load(2),
+            Instr::Add,
+            Instr::Dup,
+            Instr::Store(1),
+            Instr::Pop,
+            Instr::ForStep { slot: 2, target: 6 },
+            Instr::Load(1),
+            Instr::Return,
+            Instr::MissingReturn,
+        ]
+    );
+    assert_eq!(program.constants, [Value::Int(0), Value::Int(1)]);
+
+    assert_eq!(
+        vm::disassemble(&program, f),
+        "\
+fn sum_to_n (1 params, 4 locals)
+0000    2:20  const 0             ; 0
+0001    2:5   store 1
+0002    3:13  const 1             ; 1
+0003    3:18  load 0
+0004    3:5   store 3
+0005     |    store 2
+0006     |    for_test 2 14
+0007    4:15  load 1
+0008    4:21  load 2
+0009    4:19  add
+0010    4:9   dup
+0011     |    store 1
+0012     |    pop
+0013    3:5   for_step 2 6
+0014    6:12  load 1
+0015    6:5   return
+0016    1:1   missing_return
+"
+    );
+}
+
+#[test]
+fn kbc_round_trip() {
+    let readme = include_str!("../../README.md")
+        .split("``` Rust")
+        .nth(1)
+        .unwrap()
+        .split("```")
+        .next()
+        .unwrap();
+    let program = vm::compile(&parse(&format!(
+        "{}
+        fn constants(): float {{
+            let s: str = \"tab\\t \\\"quoted\\\" ✓\";
+            let a: char[] = ['k', 'ö', '\\n'];
+            let e: bool[];
+            println(s, a, e, true and false, 0.1 + 1e300, -9223372036854775807 - 1);
+            return -0.0;
+        }}",
+        readme
+    )))
+    .unwrap();
+
+    let mut file = Vec::new();
+    kbc::write(&program, &mut file).unwrap();
+    assert!(kbc::is_bytecode(&file));
+    let loaded = kbc::read(file.as_slice()).unwrap();
+    assert_eq!(loaded, program);
+
+    let mut out = Vec::new();
+    vm::run(&loaded, &mut out).unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        concat!(
+            "Addition of 5 and 10 is: 15\n",
+            "Maximum of 5 and 10 is: 10\n",
+            "Sum of numbers from 1 to 10 is: 55\n",
+        )
+    );
+}
+
+#[test]
+fn kbc_errors() {
+    let program = vm::compile(&parse(
+        "fn main() {
+            for i = 1 to 3 println(i * 2, \"!\");
+        }",
+    ))
+    .unwrap();
+    let file = kbc::to_bytes(&program);
+
+    assert!(matches!(
+        kbc::from_bytes(b"fn main() {}"),
+        Err(LoadError::NotBytecode)
+    ));
+
+    // Every prefix of the file is rejected.
+    for len in 0..file.len() {
+        match kbc::from_bytes(&file[..len]) {
+            Err(LoadError::NotBytecode) => assert!(len < 4),
+            Err(LoadError::Truncated { expected, found }) => {
+                assert_eq!(found, len);
+                assert!(expected == 16 || expected == file.len());
+            }
+            r => panic!("Truncated file of {} bytes gave {:?}", len, r.map(|_| ())),
+        }
+    }
+
+    // Every changed byte is detected.
+    for i in 0..file.len() {
+        let mut damaged = file.clone();
+        damaged[i] ^= 0x20;
+        assert!(
+            kbc::from_bytes(&damaged).is_err(),
+            "Change at byte {} is not detected",
+            i
+        );
+    }
+
+    let mut newer = file.clone();
+    newer[4] = 3;
+    let e = kbc::from_bytes(&newer).unwrap_err();
+    assert!(matches!(e, LoadError::UnsupportedVersion { found: 3 }));
+    assert_eq!(
+        e.to_string(),
+        "Unsupported bytecode version 3 (supported version is 2)"
+    );
+
+    let mut longer = file.clone();
+    longer.push(0);
+    assert_eq!(
+        kbc::from_bytes(&longer).unwrap_err().to_string(),
+        "Bytecode file is corrupted: 1 unexpected bytes after the end"
+    );
+
+    assert_eq!(
+        kbc::from_bytes(&file[..file.len() - 3])
+            .unwrap_err()
+            .to_string(),
+        format!(
+            "Bytecode file is truncated: expected {} bytes, found {}",
+            file.len(),
+            file.len() - 3
+        )
+    );
+}
+
+#[test]
+fn kbc_verify() {
+    let main = |code: Vec<Instr>, locals| Program {
+        constants: vec![Value::Int(1)],
+        functions: vec![Function {
+            name: "main".into(),
+            arity: 0,
+            locals,
+            positions: vec![(1, 1); code.len()],
+            code,
+            pos: (1, 1),
+        }],
+    };
+    let verify = |program: Program| {
+        // Invalid programs are rejected when loading too.
+        let loaded = kbc::from_bytes(&kbc::to_bytes(&program)).map(|_| ());
+        let r = kbc::verify(&program);
+        assert_eq!(
+            loaded.map_err(|e| e.to_string()),
+            r.as_ref().map(|_| ()).map_err(|e| e.to_string())
+        );
+        r.map_err(|e| e.to_string())
+    };
+
+    assert!(verify(main(vec![Instr::Void, Instr::Return], 0)).is_ok());
+    assert_eq!(
+        verify(main(vec![Instr::Add, Instr::Return], 0)),
+        Err("Bytecode file is corrupted: function `main`: \
+             instruction 0 needs 2 values but the stack has 0"
+            .into())
+    );
+    assert_eq!(
+        verify(main(vec![Instr::Const(1), Instr::Return], 0)),
+        Err("Bytecode file is corrupted: function `main`: \
+             instruction 0 uses undefined constant 1"
+            .into())
+    );
+    assert_eq!(
+        verify(main(
+            vec![Instr::Const(0), Instr::Store(0), Instr::Load(1)],
+            1
+        )),
+        Err("Bytecode file is corrupted: function `main`: \
+             instruction 2 uses undefined slot 1"
+            .into())
+    );
+    assert_eq!(
+        verify(main(vec![Instr::Void], 0)),
+        Err("Bytecode file is corrupted: function `main`: \
+             execution runs past the last instruction"
+            .into())
+    );
+    assert_eq!(
+        verify(main(vec![Instr::Jump(5)], 0)),
+        Err("Bytecode file is corrupted: function `main`: \
+             instruction 0 jumps to undefined instruction 5"
+            .into())
+    );
+    assert_eq!(
+        verify(main(vec![Instr::Void, Instr::Jump(0)], 0)),
+        Err("Bytecode file is corrupted: function `main`: \
+             instruction 0 is reached with 0 and 1 values on the stack"
+            .into())
+    );
+    assert_eq!(
+        verify(main(
+            vec![Instr::Call {
+                function: 0,
+                argc: 1
+            }],
+            0
+        )),
+        Err("Bytecode file is corrupted: function `main`: \
+             instruction 0 calls `main` with 1 arguments instead of 0"
+            .into())
+    );
+    assert_eq!(
+        verify(main(vec![Instr::MissingReturn], 100)),
+        Err("Bytecode file is corrupted: function `main`: 100 local slots are too many".into())
+    );
+}
+
+#[test]
+fn optimized() {
+    let out = same_when_optimized(
+        "fn collatz(n: int): int {
+        let steps: int = 0;
+        while n != 1 {
+            if n % 2 == 0 { n = n / 2; } else { n = 3 * n + 1; }
+            steps = steps + 1;
+        }
+        return steps;
+    }
+
+    fn table(n: int, scale: float): float {
+        let total: float = 0.0;
+        for i = 1 to n {
+            for j = i to n {
+                let w: float = scale * 2.5 - 0.5;
+                if i == j or j % 3 == 0 { total = total + w; }
+            }
+        }
+        return total;
+    }
+
+    fn main(): int {
+        let words: str[] = [\"a\", \"b\", \"c\"];
+        let last: int = 9223372036854775806;
+        for i = last to last + 1 { println(i, ' ', -i, ' ', ~i & 255); }
+        println(collatz(27), ' ', table(10, 0.5), ' ', words[1], ' ', [] == words);
+        let a: int = 7;
+        let b: int = a;
+        a = b * 2 - 1;
+        println(a / -2, ' ', a % -3, ' ', 'x' >= 'y', ' ', not (a > b) and true);
+        return a;
+    }",
+    );
+    assert_eq!(
+        out,
+        "9223372036854775806 -9223372036854775806 1
+9223372036854775807 -9223372036854775807 0
+111 18.75 b false
+-6 1 false false
+"
+    );
+
+    same_when_optimized("fn main() { let z: int = 0; for i = 1 to 3 { println(i / z); } }");
+    same_when_optimized("fn main() { let a: int[] = [1, 2]; let i: int = 0; while true { println(a[i]); i = i + 1; } }");
+    same_when_optimized(
+        "fn f(n: int): int { if n > 0 { return n; } } fn main() { println(f(1)); f(0); }",
+    );
+    same_when_optimized("fn inf(n: int): int { return inf(n + 1) + 1; } fn main() { inf(0); }");
+
+    // A folded phi is followed by a phi which is not folded.
+    let out = same_when_optimized(
+        "fn id(x: int): int { return x; }
+        fn main() {
+            let a: int = 0;
+            let b: int = 7;
+            if id(2) == 2 { a = 0; b = id(3); }
+            println(a, b);
+        }",
+    );
+    assert_eq!(out, "03\n");
+
+    // Errors in inlined functions keep their positions.
+    same_when_optimized(
+        "fn div(a: int, b: int): int { return a / b; }
+        fn at(a: int[], i: int): int { return a[i]; }
+        fn main() { println(at([1, 2], 1), div(7, 2)); println(div(at([0], 0), 0)); }",
+    );
+}